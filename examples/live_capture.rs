@@ -0,0 +1,66 @@
+//! Captures the spectrum of the 868 MHz band continuously and prints the peak and the mean power of each sweep.
+//!
+//! When no device is connected, the built-in simulator is used instead, receiving a carrier at 868.3 MHz.
+//!
+//! ```bash
+//! cargo run --example live_capture --features simulator -- 10
+//! ```
+use std::error::Error;
+
+use sa430::channel::{Channel, SerialPortChannel};
+use sa430::create_scanner;
+use sa430::device::Sa430;
+use sa430::frequency::Frequency;
+use sa430::simulator::{Carrier, Scenario, Simulator};
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let count: usize = match std::env::args().nth(1) {
+        Some(count) => count.parse()?,
+        None => 5,
+    };
+
+    let mut device = Sa430::new(open_channel()?);
+    for sweep in 1..=count {
+        let spectrum = device.capture(
+            Frequency::from_mhz(867.5),
+            Frequency::from_mhz(869.5),
+            Frequency::from_khz(25.0),
+            -35,
+        )?;
+        let (frequency, peak) =
+            spectrum.iter().copied().fold(
+                (0.0, f64::NEG_INFINITY),
+                |max, point| if point.1 > max.1 { point } else { max },
+            );
+        println!(
+            "Sweep {}: {} points, RBW {}, peak {:.2} dBm at {:.4} MHz, mean {:.2} dBm",
+            sweep,
+            spectrum.len(),
+            spectrum.rbw().unwrap_or_default(),
+            peak,
+            frequency / 1e6,
+            spectrum.mean_power().unwrap_or_default().dbm()
+        );
+    }
+
+    Ok(())
+}
+
+/// Opens the first device found, or the simulator with a carrier at 868.3 MHz.
+fn open_channel() -> Result<Box<dyn Channel>, Box<dyn Error>> {
+    if let Some(port) = create_scanner().scan().first() {
+        println!("Using device at {}", port.path());
+        return Ok(Box::new(SerialPortChannel::new(port.path())?));
+    }
+
+    println!("No device found, using the simulator");
+    let mut scenario = Scenario::new();
+    scenario.add(Carrier {
+        frequency: Frequency::from_mhz(868.3),
+        power: -50.0,
+        bandwidth: Frequency::from_khz(125.0),
+    });
+    let mut simulator = Simulator::new();
+    simulator.set_scenario(scenario);
+    Ok(Box::new(simulator))
+}
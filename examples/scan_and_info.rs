@@ -0,0 +1,38 @@
+//! Scans for connected SA430 devices and prints the information of the first one found.
+//!
+//! When no device is connected, the built-in simulator is used instead.
+//!
+//! ```bash
+//! cargo run --example scan_and_info --features simulator
+//! ```
+use std::error::Error;
+
+use sa430::channel::{Channel, SerialPortChannel};
+use sa430::create_scanner;
+use sa430::device::Sa430;
+use sa430::simulator::Simulator;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let ports = create_scanner().scan();
+
+    let channel: Box<dyn Channel> = match ports.first() {
+        Some(port) => {
+            println!("Using device at {}", port.path());
+            Box::new(SerialPortChannel::new(port.path())?)
+        }
+        None => {
+            println!("No device found, using the simulator");
+            Box::new(Simulator::new())
+        }
+    };
+
+    let mut device = Sa430::new(channel);
+    println!("IDN: {}", device.idn()?);
+    println!("Serial Number: {}", device.serial_number()?);
+    println!("Core Version: {}", device.core_version()?);
+    println!("Spectrum Version: {}", device.spectrum_version()?);
+    println!("Calibration Version: {}", device.calibration_version()?);
+    println!("Calibration Date: {}", device.calibration_date()?);
+
+    Ok(())
+}
@@ -0,0 +1,73 @@
+//! Captures a number of sweeps of the 868 MHz band and writes them as a waterfall CSV, with a row per sweep and a
+//! column per frequency.
+//!
+//! When no device is connected, the built-in simulator is used instead, receiving a carrier at 868.3 MHz.
+//!
+//! ```bash
+//! cargo run --example waterfall_to_csv --features simulator -- 20 waterfall.csv
+//! ```
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, Write};
+use std::time::Instant;
+
+use sa430::channel::{Channel, SerialPortChannel};
+use sa430::create_scanner;
+use sa430::device::Sa430;
+use sa430::formats::csv::{CsvWriter, DEFAULT_DELIMITER};
+use sa430::frequency::Frequency;
+use sa430::simulator::{Carrier, Scenario, Simulator};
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = std::env::args().skip(1);
+    let count: usize = match args.next() {
+        Some(count) => count.parse()?,
+        None => 10,
+    };
+    let output: Box<dyn Write> = match args.next() {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout()),
+    };
+
+    let mut device = Sa430::new(open_channel()?);
+    let mut writer = CsvWriter::new(output, DEFAULT_DELIMITER)?;
+    let start = Instant::now();
+    for sweep in 0..count {
+        let spectrum = device.capture(
+            Frequency::from_mhz(867.5),
+            Frequency::from_mhz(869.5),
+            Frequency::from_khz(25.0),
+            -35,
+        )?;
+        if sweep == 0 {
+            let frequencies = spectrum
+                .frequencies()
+                .map(|frequency| format!("{:.4}", frequency / 1e6));
+            writer.write_row(std::iter::once("time (s)".to_string()).chain(frequencies))?;
+        }
+        let time = format!("{:.3}", start.elapsed().as_secs_f64());
+        let powers = spectrum.powers().map(|power| format!("{:.2}", power));
+        writer.write_row(std::iter::once(time).chain(powers))?;
+    }
+
+    Ok(())
+}
+
+/// Opens the first device found, or the simulator with a carrier at 868.3 MHz.
+fn open_channel() -> Result<Box<dyn Channel>, Box<dyn Error>> {
+    if let Some(port) = create_scanner().scan().first() {
+        eprintln!("Using device at {}", port.path());
+        return Ok(Box::new(SerialPortChannel::new(port.path())?));
+    }
+
+    eprintln!("No device found, using the simulator");
+    let mut scenario = Scenario::new();
+    scenario.add(Carrier {
+        frequency: Frequency::from_mhz(868.3),
+        power: -50.0,
+        bandwidth: Frequency::from_khz(125.0),
+    });
+    let mut simulator = Simulator::new();
+    simulator.set_scenario(scenario);
+    Ok(Box::new(simulator))
+}
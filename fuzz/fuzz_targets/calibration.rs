@@ -0,0 +1,17 @@
+//! Feeds arbitrary flash contents to `Calibration::try_from`, as a device with a corrupted calibration would, and
+//! splits a sweep with the calibration parsed.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sa430::device::Calibration;
+use sa430::frequency::Frequency;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(calibration) = Calibration::try_from(data) {
+        let _ = calibration.split_sweep(
+            Frequency::from_mhz(300.0),
+            Frequency::from_mhz(1000.0),
+            Frequency::from_khz(100.0),
+        );
+    }
+});
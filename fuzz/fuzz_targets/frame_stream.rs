@@ -0,0 +1,14 @@
+//! Feeds arbitrary byte streams to the receive path, `FrameStream`, as a device sending malformed responses would.
+//!
+//! Every frame read must be accepted again from its own bytes, and the stream must end without panicking.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sa430::frame::{split_stream, Frame, FrameStream};
+
+fuzz_target!(|data: &[u8]| {
+    for frame in FrameStream::new(data).flatten() {
+        assert_eq!(Frame::from_bytes(&frame.to_bytes()).unwrap(), frame);
+    }
+    let _ = split_stream(data);
+});
@@ -0,0 +1,231 @@
+//! # Auth Module
+//!
+//! This module authenticates the clients of a network capture with a shared token, so a device exposed on a shared
+//! network only streams to the clients that know it.
+//!
+//! The client sends the token on a line, as `AUTH <token>`, right after connecting, and the server answers `OK`
+//! before streaming, or `DENIED` and closes the connection. The token is sent as is, so on untrusted networks, encrypt
+//! the connection with the `tls` module, built with the `tls` feature, or a tunnel like an SSH port
+//! forward.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use std::io::BufReader;
+//!
+//! use sa430::auth::{accept_token, Token};
+//!
+//! let token = Token::new("lab-secret").unwrap();
+//! let mut request = BufReader::new("AUTH lab-secret\n".as_bytes());
+//! let mut answer = Vec::new();
+//!
+//! accept_token(&mut request, &mut answer, &token).unwrap();
+//! assert_eq!(answer, b"OK\n");
+//! ```
+
+use std::{
+    error::Error,
+    fmt, fs,
+    io::{BufRead, BufReader, Read, Write},
+    path::Path,
+};
+
+/// Prefix of the line sent by the client with its token.
+const AUTH_PREFIX: &str = "AUTH ";
+
+/// Answer of the server to an accepted token.
+const AUTH_OK: &str = "OK";
+
+/// Answer of the server to a rejected token.
+const AUTH_DENIED: &str = "DENIED";
+
+/// Maximum length of the lines exchanged, in bytes, so a peer can't make the other one buffer an endless line.
+pub const MAX_LINE: usize = 1024;
+
+/// A shared secret authenticating the clients of a network capture.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Token(String);
+
+impl Token {
+    /// Creates a token, ignoring the surrounding whitespace.
+    ///
+    /// Fails if it is empty, or doesn't fit in a line of `MAX_LINE` bytes.
+    pub fn new(value: &str) -> Result<Self, AuthError> {
+        match value.trim() {
+            "" => Err(AuthError::EmptyToken),
+            value if AUTH_PREFIX.len() + value.len() + 1 > MAX_LINE => Err(AuthError::TooLong),
+            value => Ok(Token(value.to_string())),
+        }
+    }
+
+    /// Reads a token from the first line of a file, so it doesn't show up in the process list or shell history.
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(path)?;
+        Ok(Token::new(content.lines().next().unwrap_or_default())?)
+    }
+
+    /// Returns true if `candidate` is the token, comparing in constant time for tokens of the same length.
+    pub fn matches(&self, candidate: &str) -> bool {
+        let (expected, candidate) = (self.0.as_bytes(), candidate.as_bytes());
+        expected.len() == candidate.len()
+            && expected
+                .iter()
+                .zip(candidate)
+                .fold(0, |difference, (a, b)| difference | (a ^ b))
+                == 0
+    }
+}
+
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Token(***)")
+    }
+}
+
+/// Reads the token sent by a client and answers it, failing if it isn't `token`.
+///
+/// At most `MAX_LINE` bytes are read, a longer line is denied.
+pub fn accept_token(input: &mut dyn BufRead, output: &mut dyn Write, token: &Token) -> Result<(), Box<dyn Error>> {
+    let line = read_line(input)?;
+    answer_token(&line, output, token)
+}
+
+/// Reads the token sent by the client of `stream` and answers it, like [`accept_token`], for streams that can't be
+/// split into an input and an output, like a TLS one.
+pub(crate) fn accept_token_over(stream: &mut (impl Read + Write), token: &Token) -> Result<(), Box<dyn Error>> {
+    // The client waits for the answer, so the buffer can't read past the token.
+    let line = read_line(&mut BufReader::new(&mut *stream))?;
+    let answer = answer_token(&line, stream, token);
+    stream.flush()?;
+    answer
+}
+
+/// Answers the token line sent by a client, failing if it isn't `token`.
+fn answer_token(line: &str, output: &mut dyn Write, token: &Token) -> Result<(), Box<dyn Error>> {
+    let accepted = line
+        .trim_end_matches(['\r', '\n'])
+        .strip_prefix(AUTH_PREFIX)
+        .is_some_and(|candidate| token.matches(candidate));
+
+    match accepted {
+        true => {
+            writeln!(output, "{}", AUTH_OK)?;
+            Ok(())
+        }
+        false => {
+            writeln!(output, "{}", AUTH_DENIED)?;
+            Err(AuthError::InvalidToken.into())
+        }
+    }
+}
+
+/// Sends `token` to a server and waits for its answer, failing if it is rejected.
+pub fn send_token(input: &mut dyn BufRead, output: &mut dyn Write, token: &Token) -> Result<(), Box<dyn Error>> {
+    writeln!(output, "{}{}", AUTH_PREFIX, token.0)?;
+    output.flush()?;
+    read_answer(input)
+}
+
+/// Sends `token` to the server of `stream` and waits for its answer, like [`send_token`], for streams that can't be
+/// split into an input and an output, like a TLS one.
+pub(crate) fn send_token_over<S: Read + Write>(stream: &mut BufReader<S>, token: &Token) -> Result<(), Box<dyn Error>> {
+    writeln!(stream.get_mut(), "{}{}", AUTH_PREFIX, token.0)?;
+    stream.get_mut().flush()?;
+    read_answer(stream)
+}
+
+/// Reads the answer of a server to a token, failing if it is rejected.
+fn read_answer(input: &mut dyn BufRead) -> Result<(), Box<dyn Error>> {
+    match read_line(input)?.trim_end() {
+        AUTH_OK => Ok(()),
+        _ => Err(AuthError::Denied.into()),
+    }
+}
+
+/// Reads a line of at most `MAX_LINE` bytes, including the line break.
+fn read_line(input: &mut dyn BufRead) -> Result<String, Box<dyn Error>> {
+    let mut line = String::new();
+    input.take(MAX_LINE as u64).read_line(&mut line)?;
+    Ok(line)
+}
+
+/// Authentication errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthError {
+    /// The token is empty.
+    EmptyToken,
+
+    /// The token doesn't fit in a line of `MAX_LINE` bytes.
+    TooLong,
+
+    /// A client sent a wrong token, or none.
+    InvalidToken,
+
+    /// The server rejected the token.
+    Denied,
+}
+
+impl Error for AuthError {}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::EmptyToken => write!(f, "The token is empty"),
+            AuthError::TooLong => write!(f, "The token is longer than {} bytes", MAX_LINE - AUTH_PREFIX.len() - 1),
+            AuthError::InvalidToken => write!(f, "Invalid token"),
+            AuthError::Denied => write!(f, "The server rejected the token"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_a_wrong_token_when_accept_token_then_deny_it() {
+        let token = Token::new("lab-secret").unwrap();
+
+        for request in ["AUTH lab-secreT\n", "AUTH lab-secret-2\n", "lab-secret\n", ""] {
+            let mut answer = Vec::new();
+            let error = accept_token(&mut request.as_bytes(), &mut answer, &token).unwrap_err();
+
+            assert_eq!(error.downcast_ref(), Some(&AuthError::InvalidToken));
+            assert_eq!(answer, b"DENIED\n");
+        }
+    }
+
+    #[test]
+    fn given_an_endless_line_when_accept_token_then_deny_it_after_max_line_bytes() {
+        let token = Token::new("lab-secret").unwrap();
+        let request = format!("AUTH lab-secret{}\n", " ".repeat(MAX_LINE));
+        let mut input = request.as_bytes();
+        let mut answer = Vec::new();
+
+        let error = accept_token(&mut input, &mut answer, &token).unwrap_err();
+
+        assert_eq!(error.downcast_ref(), Some(&AuthError::InvalidToken));
+        assert_eq!(answer, b"DENIED\n");
+        assert_eq!(input.len(), request.len() - MAX_LINE);
+    }
+
+    #[test]
+    fn given_a_token_longer_than_a_line_when_new_then_return_error() {
+        let longest = "x".repeat(MAX_LINE - AUTH_PREFIX.len() - 1);
+
+        assert!(Token::new(&longest).is_ok());
+        assert_eq!(Token::new(&format!("{}x", longest)), Err(AuthError::TooLong));
+    }
+
+    #[test]
+    fn given_a_denied_answer_when_send_token_then_return_error() {
+        let token = Token::new(" lab-secret\n").unwrap();
+        let mut request = Vec::new();
+
+        let error = send_token(&mut "DENIED\n".as_bytes(), &mut request, &token).unwrap_err();
+
+        assert_eq!(request, b"AUTH lab-secret\n");
+        assert_eq!(error.downcast_ref(), Some(&AuthError::Denied));
+        assert_eq!(Token::new("  "), Err(AuthError::EmptyToken));
+    }
+}
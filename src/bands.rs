@@ -0,0 +1,306 @@
+//! # Bands Module
+//!
+//! This module provides a database of frequency bands, with presets for common Sub-1 GHz allocations, so a capture
+//! can be configured by the band name instead of its start and stop frequencies.
+//!
+//! The built-in presets can be extended, or overridden, with bands loaded from a CSV file with one band per line:
+//!
+//! ```text
+//! # name,fstart_mhz,fstop_mhz,description
+//! site-a,868.0,868.6,Gateway uplink at site A
+//! ```
+//!
+//! [Bands::check_csv](struct.Bands.html#method.check_csv) lists every problem of a file, with its line and column, to
+//! lint it before deploying it to a headless box.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use sa430::bands::Bands;
+//!
+//! let mut bands = Bands::builtin();
+//! bands.read_csv("site-a,868.0,868.6,Gateway uplink at site A".as_bytes()).unwrap();
+//!
+//! let band = bands.find("EU868").unwrap();
+//! assert_eq!((band.fstart(), band.fstop()), (863.0e6, 870.0e6));
+//! assert!(bands.find("site-a").is_some());
+//! ```
+
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    io::{self, BufRead},
+};
+
+/// Built-in band presets (name, start in MHz, stop in MHz, description).
+const BUILTIN_BANDS: [(&str, f64, f64, &str); 8] = [
+    ("ism433", 433.05, 434.79, "433 MHz ISM band (ITU Region 1)"),
+    ("eu868", 863.0, 870.0, "EU 868 MHz short range devices"),
+    ("us915", 902.0, 928.0, "US 915 MHz ISM band"),
+    ("lora-eu868", 867.0, 868.6, "LoRaWAN EU868 channels"),
+    ("lora-us915", 902.2, 915.0, "LoRaWAN US915 uplink channels"),
+    ("sigfox-rc1", 868.034, 868.226, "Sigfox RC1 uplink"),
+    ("sigfox-rc2", 902.1, 902.3, "Sigfox RC2 uplink"),
+    ("wmbus", 868.0, 870.0, "Wireless M-Bus modes S, T and C"),
+];
+
+/// A named frequency band.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Band {
+    name: String,
+    fstart: f64,
+    fstop: f64,
+    description: String,
+}
+
+impl Band {
+    /// Creates a new band from `fstart` to `fstop`, in Hz.
+    pub fn new(name: &str, fstart: f64, fstop: f64, description: &str) -> Self {
+        Band {
+            name: name.to_string(),
+            fstart,
+            fstop,
+            description: description.to_string(),
+        }
+    }
+
+    /// Returns the band name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the start frequency, in Hz.
+    pub fn fstart(&self) -> f64 {
+        self.fstart
+    }
+
+    /// Returns the stop frequency, in Hz.
+    pub fn fstop(&self) -> f64 {
+        self.fstop
+    }
+
+    /// Returns the band description.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+}
+
+/// Errors when loading bands from a CSV file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BandError {
+    /// The line doesn't have the name, start and stop columns (line number).
+    MissingColumns(usize),
+
+    /// A frequency column is not a number (line number, column name).
+    InvalidFrequency(usize, &'static str),
+
+    /// The start frequency is not lower than the stop frequency (line number).
+    InvalidRange(usize),
+
+    /// The band has the same name, ignoring case, as the band of a previous line (line number, previous line number).
+    DuplicateName(usize, usize),
+}
+
+impl Error for BandError {}
+
+impl Display for BandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BandError::MissingColumns(line) => write!(f, "Line {}: expected name, fstart and fstop columns", line),
+            BandError::InvalidFrequency(line, column) => write!(f, "Line {}: {} is not a number", line, column),
+            BandError::InvalidRange(line) => write!(f, "Line {}: fstart_mhz must be lower than fstop_mhz", line),
+            BandError::DuplicateName(line, previous) => {
+                write!(f, "Line {}: the band name is already used on line {}", line, previous)
+            }
+        }
+    }
+}
+
+/// A database of frequency bands.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Bands {
+    bands: Vec<Band>,
+}
+
+impl Bands {
+    /// Creates an empty database.
+    pub fn new() -> Self {
+        Bands::default()
+    }
+
+    /// Creates a database with the built-in presets.
+    pub fn builtin() -> Self {
+        let mut bands = Bands::new();
+        for (name, fstart, fstop, description) in BUILTIN_BANDS {
+            bands.add(Band::new(name, fstart * 1e6, fstop * 1e6, description));
+        }
+        bands
+    }
+
+    /// Adds a band, replacing the band with the same name, if any.
+    pub fn add(&mut self, band: Band) {
+        match self.bands.iter_mut().find(|b| b.name.eq_ignore_ascii_case(&band.name)) {
+            Some(existing) => *existing = band,
+            None => self.bands.push(band),
+        }
+    }
+
+    /// Returns the band named `name`, ignoring case.
+    pub fn find(&self, name: &str) -> Option<&Band> {
+        self.bands.iter().find(|band| band.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Returns an iterator over the bands.
+    pub fn iter(&self) -> std::slice::Iter<'_, Band> {
+        self.bands.iter()
+    }
+
+    /// Adds the bands from CSV data with the name, the start and stop frequencies, in MHz, and an optional
+    /// description. Empty lines and lines starting with `#` are ignored.
+    pub fn read_csv<R: BufRead>(&mut self, reader: R) -> Result<(), Box<dyn Error>> {
+        for (index, line) in reader.lines().enumerate() {
+            if let Some(band) = parse_band(index + 1, &line?)? {
+                self.add(band);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks CSV data in the format of [read_csv](#method.read_csv), returning every problem found instead of
+    /// stopping at the first one, including band names repeated in the data.
+    pub fn check_csv<R: BufRead>(reader: R) -> io::Result<Vec<BandError>> {
+        let mut errors = Vec::new();
+        let mut names: Vec<(String, usize)> = Vec::new();
+        for (index, line) in reader.lines().enumerate() {
+            let number = index + 1;
+            match parse_band(number, &line?) {
+                Ok(Some(band)) => match names.iter().find(|(name, _)| name.eq_ignore_ascii_case(band.name())) {
+                    Some((_, previous)) => errors.push(BandError::DuplicateName(number, *previous)),
+                    None => names.push((band.name, number)),
+                },
+                Ok(None) => {}
+                Err(error) => errors.push(error),
+            }
+        }
+        Ok(errors)
+    }
+}
+
+/// Parses a line of a bands CSV file, returning `None` for empty and comment lines.
+fn parse_band(number: usize, line: &str) -> Result<Option<Band>, BandError> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+
+    let mut columns = line.splitn(4, ',').map(str::trim);
+    let (name, fstart, fstop) = match (columns.next(), columns.next(), columns.next()) {
+        (Some(name), Some(fstart), Some(fstop)) if !name.is_empty() => (name, fstart, fstop),
+        _ => return Err(BandError::MissingColumns(number)),
+    };
+    let description = columns.next().unwrap_or_default();
+
+    let fstart: f64 = fstart
+        .parse()
+        .map_err(|_| BandError::InvalidFrequency(number, "fstart_mhz"))?;
+    let fstop: f64 = fstop
+        .parse()
+        .map_err(|_| BandError::InvalidFrequency(number, "fstop_mhz"))?;
+    match fstart < fstop {
+        true => Ok(Some(Band::new(name, fstart * 1e6, fstop * 1e6, description))),
+        false => Err(BandError::InvalidRange(number)),
+    }
+}
+
+impl<'a> IntoIterator for &'a Bands {
+    type Item = &'a Band;
+    type IntoIter = std::slice::Iter<'a, Band>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_builtin_bands_when_find_then_ignore_case() {
+        let bands = Bands::builtin();
+
+        let band = bands.find("US915").unwrap();
+
+        assert_eq!(band.name(), "us915");
+        assert_eq!(band.fstart(), 902.0e6);
+        assert_eq!(band.fstop(), 928.0e6);
+        assert!(bands.find("unknown").is_none());
+    }
+
+    #[test]
+    fn given_builtin_bands_then_all_ranges_are_valid() {
+        for band in &Bands::builtin() {
+            assert!(band.fstart() < band.fstop(), "{}", band.name());
+        }
+    }
+
+    #[test]
+    fn given_a_band_with_an_existing_name_when_add_then_replace_it() {
+        let mut bands = Bands::builtin();
+        let count = bands.iter().count();
+
+        bands.add(Band::new("EU868", 868.0e6, 868.6e6, "Custom"));
+
+        assert_eq!(bands.iter().count(), count);
+        assert_eq!(bands.find("eu868").unwrap().description(), "Custom");
+    }
+
+    #[test]
+    fn given_csv_data_when_read_csv_then_add_bands() {
+        let mut bands = Bands::new();
+        let data = "# name,fstart_mhz,fstop_mhz,description\n\nsite-a, 868.0, 868.6, Uplink, site A\nsite-b,433,434\n";
+
+        bands.read_csv(data.as_bytes()).unwrap();
+
+        assert_eq!(
+            bands.iter().cloned().collect::<Vec<_>>(),
+            vec![
+                Band::new("site-a", 868.0e6, 868.6e6, "Uplink, site A"),
+                Band::new("site-b", 433.0e6, 434.0e6, "")
+            ]
+        );
+    }
+
+    #[test]
+    fn given_invalid_csv_data_when_read_csv_then_return_error() {
+        let cases = [
+            ("site-a,868.0\n", BandError::MissingColumns(1)),
+            (",868.0,868.6\n", BandError::MissingColumns(1)),
+            ("site-a,868.0,abc\n", BandError::InvalidFrequency(1, "fstop_mhz")),
+            ("site-a,868.6,868.0\n", BandError::InvalidRange(1)),
+        ];
+
+        for (data, expected) in cases {
+            let error = Bands::new().read_csv(data.as_bytes()).unwrap_err();
+            assert_eq!(error.downcast_ref::<BandError>(), Some(&expected));
+        }
+    }
+
+    #[test]
+    fn given_csv_data_with_problems_when_check_csv_then_return_all_of_them() {
+        let data = "# name,fstart_mhz,fstop_mhz\nsite-a,x,868.6\nsite-b,868.0,868.6\nSITE-B,433,434\nsite-c,434\n";
+
+        let errors = Bands::check_csv(data.as_bytes()).unwrap();
+
+        assert_eq!(
+            errors,
+            vec![
+                BandError::InvalidFrequency(2, "fstart_mhz"),
+                BandError::DuplicateName(4, 3),
+                BandError::MissingColumns(5)
+            ]
+        );
+        assert_eq!(errors[0].to_string(), "Line 2: fstart_mhz is not a number");
+    }
+}
@@ -0,0 +1,70 @@
+//! # Cancel Module
+//!
+//! This module provides the `CancellationToken`, a handle shared between the code running a long operation, like a
+//! continuous capture, and the code that wants to stop it, like a signal handler or another thread.
+//!
+//! Operations check the token between steps, so the device is never abandoned in the middle of a command, and park
+//! the device with [Sa430::abort](../device/struct.Sa430.html#method.abort) before returning.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use sa430::cancel::CancellationToken;
+//!
+//! let token = CancellationToken::new();
+//! let handle = token.clone();
+//!
+//! std::thread::spawn(move || handle.cancel()).join().unwrap();
+//! assert!(token.is_cancelled());
+//! ```
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// A handle to request the cancellation of an operation.
+///
+/// Clones share the same state, so cancelling any of them cancels all.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Creates a new token, not cancelled.
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    /// Requests the cancellation.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns true if the cancellation was requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_a_new_token_then_it_is_not_cancelled() {
+        assert!(!CancellationToken::new().is_cancelled());
+    }
+
+    #[test]
+    fn given_a_cloned_token_when_cancel_then_cancel_all_clones() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+
+        clone.cancel();
+
+        assert!(token.is_cancelled());
+        assert!(clone.is_cancelled());
+    }
+}
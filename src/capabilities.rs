@@ -0,0 +1,189 @@
+//! # Capabilities Module
+//!
+//! This module maps the firmware versions of a device to the operations it supports, so higher layers can deny an
+//! unsupported operation with a clear message before sending it, instead of failing with a device error.
+//!
+//! The firmware versions required by each command are the ones of its feature, see the [`compat`](crate::compat) module.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use sa430::capabilities::Capabilities;
+//! use sa430::frame::Command;
+//!
+//! let capabilities = Capabilities::new(0x020A, 0x0203, 256);
+//!
+//! assert!(capabilities.supports(Command::GetIdn));
+//! assert!(capabilities.require(Command::GetSpecNoInit).is_err());
+//! assert!(capabilities.rbws_khz().is_empty());
+//! ```
+
+use crate::compat::{check, CompatError, Feature};
+use crate::frame::Command;
+
+/// Resolution bandwidths of the receiver filter, in kHz.
+pub const RBWS_KHZ: [f64; 16] = [
+    58.0, 67.7, 81.3, 101.6, 116.1, 135.4, 162.5, 203.1, 232.1, 270.8, 325.0, 406.3, 464.3, 541.7, 650.0, 812.5,
+];
+
+/// Commands known to the crate, in code order.
+const COMMANDS: [Command; 34] = [
+    Command::GetIdn,
+    Command::GetSerialNumber,
+    Command::HardwareReset,
+    Command::BlinkLed,
+    Command::GetCoreVersion,
+    Command::GetLastError,
+    Command::Sync,
+    Command::FlashRead,
+    Command::FlashWrite,
+    Command::FlashErase,
+    Command::FlashGetCrc,
+    Command::GetSpectrumVersion,
+    Command::SetFStart,
+    Command::SetFStop,
+    Command::SetFStep,
+    Command::SetFrq,
+    Command::SetRbw,
+    Command::SetDac,
+    Command::SetGain,
+    Command::SetIf,
+    Command::InitParameter,
+    Command::GetSpecNoInit,
+    Command::GetProdVer,
+    Command::SetProdFwInit,
+    Command::GetTemp,
+    Command::SetHardwareId,
+    Command::GetHardwareId,
+    Command::GetBootCnt,
+    Command::SetFout,
+    Command::SetFxtal,
+    Command::GetFxtal,
+    Command::SweepEdc,
+    Command::GetChipTlv,
+    Command::FrameError,
+];
+
+/// Operations supported by a device, from its firmware versions and calibration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    core_version: u16,
+    spectrum_version: u16,
+    max_bins: u32,
+}
+
+impl Capabilities {
+    /// Creates the capabilities of a device with the given firmware versions, as reported by the device, and the
+    /// maximum number of points of a sweep.
+    pub fn new(core_version: u16, spectrum_version: u16, max_bins: u32) -> Self {
+        Capabilities {
+            core_version,
+            spectrum_version,
+            max_bins,
+        }
+    }
+
+    /// Returns the version of the core firmware.
+    pub fn core_version(&self) -> u16 {
+        self.core_version
+    }
+
+    /// Returns the version of the spectrum analyzer firmware.
+    pub fn spectrum_version(&self) -> u16 {
+        self.spectrum_version
+    }
+
+    /// Returns the maximum number of points of a sweep, 0 if the device can't measure.
+    pub fn max_bins(&self) -> u32 {
+        match self.spectrum_supported() {
+            true => self.max_bins,
+            false => 0,
+        }
+    }
+
+    /// Returns the resolution bandwidths the device can measure with, in kHz.
+    pub fn rbws_khz(&self) -> &'static [f64] {
+        match self.spectrum_supported() {
+            true => &RBWS_KHZ,
+            false => &[],
+        }
+    }
+
+    /// Returns true if the core firmware is supported.
+    pub fn core_supported(&self) -> bool {
+        check(Feature::Control, self.core_version, self.spectrum_version).is_ok()
+    }
+
+    /// Returns true if the spectrum analyzer firmware is supported.
+    pub fn spectrum_supported(&self) -> bool {
+        check(Feature::Capture, self.core_version, self.spectrum_version).is_ok()
+    }
+
+    /// Returns true if only the core firmware is usable, so the device can be identified and its flash read, but the
+    /// spectrum analyzer firmware must be loaded before measuring.
+    pub fn is_core_only(&self) -> bool {
+        self.core_supported() && !self.spectrum_supported()
+    }
+
+    /// Returns the supported commands.
+    pub fn commands(&self) -> Vec<Command> {
+        COMMANDS.into_iter().filter(|command| self.supports(*command)).collect()
+    }
+
+    /// Returns true if the device supports `command`.
+    pub fn supports(&self, command: Command) -> bool {
+        self.require(command).is_ok()
+    }
+
+    /// Fails if the firmware of the device is older than required by the feature of `command`.
+    ///
+    /// The commands used to identify the device and its firmware versions are always supported.
+    pub fn require(&self, command: Command) -> Result<(), CompatError> {
+        check(Feature::of(command), self.core_version, self.spectrum_version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::compat::{MIN_CORE_VERSION, MIN_SPECTRUM_VERSION};
+
+    #[test]
+    fn given_supported_firmwares_when_capabilities_then_support_all_commands() {
+        let capabilities = Capabilities::new(MIN_CORE_VERSION, MIN_SPECTRUM_VERSION, 256);
+
+        assert_eq!(capabilities.commands(), COMMANDS.to_vec());
+        assert_eq!(capabilities.max_bins(), 256);
+        assert_eq!(capabilities.rbws_khz().len(), 16);
+    }
+
+    #[test]
+    fn given_an_old_spectrum_firmware_when_require_a_measurement_then_return_error() {
+        let capabilities = Capabilities::new(0x020A, 0x0203, 256);
+
+        assert!(capabilities.supports(Command::FlashRead));
+        assert!(capabilities.is_core_only());
+        assert_eq!(capabilities.max_bins(), 0);
+        assert!(capabilities
+            .require(Command::GetSpecNoInit)
+            .unwrap_err()
+            .to_string()
+            .starts_with("Capture requires spectrum analyzer firmware 2.4 or newer, the device has 2.3"));
+    }
+
+    #[test]
+    fn given_an_erased_core_firmware_when_capabilities_then_only_support_identification() {
+        let capabilities = Capabilities::new(0xFFFF, 0xFFFF, 256);
+
+        assert_eq!(
+            capabilities.commands(),
+            vec![
+                Command::GetIdn,
+                Command::GetSerialNumber,
+                Command::GetCoreVersion,
+                Command::GetSpectrumVersion
+            ]
+        );
+    }
+}
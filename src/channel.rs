@@ -1,8 +1,12 @@
 //! # Channel Module
 //!
 //! This module provides an abstraction for communication channels, specifically focusing on serial port communication.
-//! It defines a `Channel` trait that requires implementations for reading and writing, and a `SerialPortChannel` struct
-//! that implements this trait using the `serialport` crate.
+//! It defines a `Channel` trait, a byte stream that is read and written directly and can be sent to another thread,
+//! and a `SerialPortChannel` struct that implements this trait using the `serialport` crate, enabled by the `serial`
+//! feature.
+//!
+//! A channel can be split into reader and writer halves, with `Channel::split`, to read the incoming frames in a
+//! background thread while commands are sent from another one.
 //!
 //! ## Serial port settings
 //!
@@ -11,58 +15,247 @@
 //! - `SERIAL_PORT_DATA_BITS`: The number of data bits used in the serial port communication, 8 [bits].
 //! - `SERIAL_PORT_PARITY`: The parity setting for the serial port communication, none.
 //! - `SERIAL_PORT_FLOW_CONTROL`: The flow control setting for the serial port communication, none.
-//! - `SERIAL_PORT_TIMEOUT`: The timeout duration for the serial port communication, 5 [seconds].
+//! - `SERIAL_PORT_TIMEOUT`: The initial timeout duration for the serial port communication, 5 [seconds]. Sessions
+//!   change it according to the command being executed.
+//!
+//! ## Recovery
+//!
+//! USB-serial adapters occasionally fail a read or a write with `EIO`, while the device is still connected. A
+//! `SerialPortChannel` then closes and reopens the port, discarding the stale input, as set by its `RecoveryPolicy`,
+//! and fails the operation with `ChannelError::Reopened`: the bytes in flight are lost, so the session resynchronizes
+//! the link and runs the command again, once. Any error but a timeout is taken as a failure of the port, since the
+//! `serialport` crate drops the error number of `EIO`, and a port that can't be reopened, like the one of an
+//! unplugged device, leaves the original error.
+//!
+//! The halves of a split channel share the recovery: the half that fails reopens the port and reports the lost bytes,
+//! and the other half switches to the reopened port on its next operation. The port is opened by an `Opener`, which
+//! `SerialPortChannel::with_opener` replaces, like with a fake port in tests.
 //!
 //! ## Usage Example
 //!
 //! ```ignore
+//! use std::io::{Read, Write};
 //! use sa430::channel::{Channel, SerialPortChannel};
 //!
 //! fn main() -> Result<(), Box<dyn std::error::Error>> {
 //!     let mut channel = SerialPortChannel::new("/dev/ttyACM0")?;
-//!     let reader = channel.reader();
-//!     let writer = channel.writer();
-//!     // Use reader and writer for communication
+//!     channel.write_all(&[0x2A, 0x00, 0x07, 0x85, 0x28])?;
+//!     let mut reply = [0; 5];
+//!     channel.read_exact(&mut reply)?;
 //!     Ok(())
 //! }
 //! ```
-use std::{error, io, time::Duration};
+use std::{
+    io,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use serialport::{DataBits, FlowControl, Parity, SerialPort, StopBits};
+#[cfg(feature = "serial")]
+use std::sync::atomic::{AtomicU64, Ordering};
 
+#[cfg(feature = "serial")]
+use serialport::{ClearBuffer, DataBits, FlowControl, Parity, SerialPort, StopBits};
+
+#[cfg(feature = "serial")]
 const SERIAL_PORT_BAUD_RATE: u32 = 926100;
+#[cfg(feature = "serial")]
 const SERIAL_PORT_STOP_BITS: StopBits = StopBits::One;
+#[cfg(feature = "serial")]
 const SERIAL_PORT_DATA_BITS: DataBits = DataBits::Eight;
+#[cfg(feature = "serial")]
 const SERIAL_PORT_PARITY: Parity = Parity::None;
+#[cfg(feature = "serial")]
 const SERIAL_PORT_FLOW_CONTROL: FlowControl = FlowControl::None;
+#[cfg(feature = "serial")]
 const SERIAL_PORT_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// ### `Channel`
 ///
-/// A trait that represents a communication channel. It requires implementations for reading and writing.
-pub trait Channel: io::Read + io::Write {
-    /// Returns a mutable reference to the reader part of the channel.
-    fn reader(&mut self) -> &mut dyn io::Read;
+/// A trait that represents a communication channel: a byte stream read and written directly, with a read timeout.
+/// Channels are `Send`, so a device can be moved to the thread that uses it.
+pub trait Channel: io::Read + io::Write + Send {
+    /// Sets how long a read waits for data before failing with `TimedOut`.
+    ///
+    /// Channels without a configurable timeout can ignore it, which is the default.
+    fn set_timeout(&mut self, _timeout: Duration) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Splits the channel into reader and writer halves, so a thread can drain the incoming frames while another one
+    /// sends the commands.
+    ///
+    /// By default, the halves share the channel behind a mutex and a read holds it until it returns, so channels that
+    /// can wait for data should override it with independent halves.
+    fn split(self: Box<Self>) -> io::Result<(ChannelReader, ChannelWriter)>
+    where
+        Self: 'static,
+    {
+        let shared = Arc::new(Mutex::new(self));
+        Ok((Box::new(SharedHalf(shared.clone())), Box::new(SharedHalf(shared))))
+    }
+
+    /// Returns the channel as a reader.
+    #[deprecated(note = "a channel is a reader, use it directly")]
+    fn reader(&mut self) -> &mut dyn io::Read
+    where
+        Self: Sized,
+    {
+        self
+    }
+
+    /// Returns the channel as a writer.
+    #[deprecated(note = "a channel is a writer, use it directly")]
+    fn writer(&mut self) -> &mut dyn io::Write
+    where
+        Self: Sized,
+    {
+        self
+    }
+}
+
+/// Reader half of a split channel.
+pub type ChannelReader = Box<dyn io::Read + Send>;
+
+/// Writer half of a split channel.
+pub type ChannelWriter = Box<dyn io::Write + Send>;
+
+/// Half of a channel split by the default `Channel::split`, sharing the channel with the other half.
+struct SharedHalf<C: ?Sized>(Arc<Mutex<Box<C>>>);
+
+impl<C: Channel + ?Sized> SharedHalf<C> {
+    fn lock(&self) -> io::Result<std::sync::MutexGuard<'_, Box<C>>> {
+        self.0.lock().map_err(|_| io::Error::other("Channel lock poisoned"))
+    }
+}
+
+impl<C: Channel + ?Sized> io::Read for SharedHalf<C> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.lock()?.read(buf)
+    }
+}
 
-    /// Returns a mutable reference to the writer part of the channel.
-    fn writer(&mut self) -> &mut dyn io::Write;
+impl<C: Channel + ?Sized> io::Write for SharedHalf<C> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.lock()?.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.lock()?.flush()
+    }
 }
 
 /// ### `SerialPortChannel`
 ///
 /// A struct that implements the `Channel` trait using a serial port.
 /// It encapsulates a serial port and provides methods to open and manage the port.
+///
+/// The port is reopened after an error of the adapter, as set by its `RecoveryPolicy`, see the module documentation.
+#[cfg(feature = "serial")]
 pub struct SerialPortChannel {
-    port: Box<dyn SerialPort>,
+    port: RecoveringPort,
+}
+
+/// Handler of the warnings of a serial port channel.
+#[cfg(feature = "serial")]
+pub type WarningHandler = Box<dyn FnMut(&str) + Send>;
+
+/// An open serial port, as used by a `SerialPortChannel`.
+///
+/// Implemented by the ports of the `serialport` crate. Other implementations, like fakes in tests, are used through
+/// the opener of `SerialPortChannel::with_opener`.
+#[cfg(feature = "serial")]
+pub trait SerialHandle: io::Read + io::Write + Send {
+    /// Returns another handle of the same port, for the other half of a split channel.
+    fn try_clone(&self) -> io::Result<Box<dyn SerialHandle>>;
+
+    /// Sets how long a read waits for data before failing with `TimedOut`.
+    fn set_timeout(&mut self, timeout: Duration) -> io::Result<()>;
+
+    /// Discards the bytes received and not read yet.
+    fn clear_input(&mut self) -> io::Result<()>;
+}
+
+#[cfg(feature = "serial")]
+impl SerialHandle for Box<dyn SerialPort> {
+    fn try_clone(&self) -> io::Result<Box<dyn SerialHandle>> {
+        Ok(Box::new(SerialPort::try_clone(self.as_ref())?))
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        Ok(SerialPort::set_timeout(self.as_mut(), timeout)?)
+    }
+
+    fn clear_input(&mut self) -> io::Result<()> {
+        Ok(self.clear(ClearBuffer::Input)?)
+    }
+}
+
+/// Opens the serial port of a channel, when the channel is created and again to recover from an error.
+#[cfg(feature = "serial")]
+pub type Opener = Box<dyn FnMut() -> io::Result<Box<dyn SerialHandle>> + Send>;
+
+/// How a serial port channel recovers from the errors of USB-serial adapters, by reopening the port.
+#[cfg(feature = "serial")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecoveryPolicy {
+    /// Maximum number of times the port is reopened after an error, 0 disables the recovery.
+    pub attempts: u32,
+
+    /// Time waited before each attempt, for the adapter to settle.
+    pub delay: Duration,
 }
 
+#[cfg(feature = "serial")]
+impl Default for RecoveryPolicy {
+    fn default() -> Self {
+        RecoveryPolicy {
+            attempts: 3,
+            delay: Duration::from_millis(500),
+        }
+    }
+}
+
+#[cfg(feature = "serial")]
 impl SerialPortChannel {
     /// Creates a new `SerialPortChannel` by opening the specified serial port with default settings used by the SA430 hardware.
-    pub fn new(port_name: &str) -> Result<Self, Box<dyn error::Error>> {
+    pub fn new(port_name: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let port = SerialPortChannel::open(port_name).map_err(|error| -> Box<dyn std::error::Error> {
+            match error.kind() {
+                serialport::ErrorKind::Io(io::ErrorKind::PermissionDenied) => {
+                    Box::new(ChannelError::PermissionDenied(port_name.to_string()))
+                }
+                _ => Box::new(error),
+            }
+        })?;
+        let name = port_name.to_string();
+        let opener: Opener = Box::new(move || Ok(Box::new(SerialPortChannel::open(&name)?)));
         Ok(SerialPortChannel {
-            port: SerialPortChannel::open(port_name)?,
+            port: RecoveringPort::new(port_name, Box::new(port), opener),
         })
     }
+
+    /// Creates a channel over the port opened by `opener`, which opens it again to recover from its errors.
+    ///
+    /// The port is named `port_name` in the warnings and errors.
+    pub fn with_opener(port_name: &str, mut opener: Opener) -> io::Result<Self> {
+        let mut port = opener()?;
+        port.set_timeout(SERIAL_PORT_TIMEOUT)?;
+        Ok(SerialPortChannel {
+            port: RecoveringPort::new(port_name, port, opener),
+        })
+    }
+
+    /// Sets how the channel, and its halves once split, recover from the errors of the port.
+    pub fn set_recovery_policy(&mut self, policy: RecoveryPolicy) {
+        self.port.shared.lock().recovery = policy;
+    }
+
+    /// Sets the handler of the warnings of the channel, like a port reopened after an error, or `None` to ignore them.
+    pub fn set_warning_handler(&mut self, handler: Option<WarningHandler>) {
+        self.port.shared.lock().warnings = handler;
+    }
+
     /// Opens the specified serial port with the predefined settings.
     fn open(port_name: &str) -> Result<Box<dyn SerialPort>, serialport::Error> {
         let builder = serialport::new(port_name, SERIAL_PORT_BAUD_RATE)
@@ -76,12 +269,249 @@ impl SerialPortChannel {
     }
 }
 
+/// State of the port of a serial port channel, shared with its halves so the port is reopened once for all of them.
+#[cfg(feature = "serial")]
+struct PortState {
+    name: String,
+    opener: Opener,
+    recovery: RecoveryPolicy,
+    warnings: Option<WarningHandler>,
+    timeout: Duration,
+    /// Handle of the port opened by the last recovery, cloned by the halves still using the previous port.
+    latest: Option<Box<dyn SerialHandle>>,
+}
+
+#[cfg(feature = "serial")]
+impl PortState {
+    fn warn(&mut self, message: &str) {
+        if let Some(handler) = &mut self.warnings {
+            handler(message);
+        }
+    }
+
+    /// Opens the port again, with the timeout of the channel and without stale input.
+    fn reopen(&mut self) -> io::Result<Box<dyn SerialHandle>> {
+        let mut port = (self.opener)()?;
+        port.set_timeout(self.timeout)?;
+        port.clear_input()?;
+        Ok(port)
+    }
+}
+
+/// The state of a port and the number of times it was reopened, checked by the halves without locking the state.
+#[cfg(feature = "serial")]
+struct SharedPort {
+    generation: AtomicU64,
+    state: Mutex<PortState>,
+}
+
+#[cfg(feature = "serial")]
+impl SharedPort {
+    fn lock(&self) -> std::sync::MutexGuard<'_, PortState> {
+        self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// A handle of the port of a channel, or of one of its halves, reopening the port after an error.
+#[cfg(feature = "serial")]
+struct RecoveringPort {
+    /// Handle of the port, `None` after a recovery failed.
+    handle: Option<Box<dyn SerialHandle>>,
+    generation: u64,
+    shared: Arc<SharedPort>,
+}
+
+#[cfg(feature = "serial")]
+impl RecoveringPort {
+    fn new(name: &str, handle: Box<dyn SerialHandle>, opener: Opener) -> Self {
+        let state = PortState {
+            name: name.to_string(),
+            opener,
+            recovery: RecoveryPolicy::default(),
+            warnings: None,
+            timeout: SERIAL_PORT_TIMEOUT,
+            latest: None,
+        };
+        RecoveringPort {
+            handle: Some(handle),
+            generation: 0,
+            shared: Arc::new(SharedPort {
+                generation: AtomicU64::new(0),
+                state: Mutex::new(state),
+            }),
+        }
+    }
+
+    /// Returns another handle of the port, sharing its recovery.
+    fn try_clone(&self) -> io::Result<Self> {
+        let handle = match &self.handle {
+            Some(handle) => Some(handle.try_clone()?),
+            None => None,
+        };
+        Ok(RecoveringPort {
+            handle,
+            generation: self.generation,
+            shared: self.shared.clone(),
+        })
+    }
+
+    /// Runs `operation` on the port, recovering from its error.
+    ///
+    /// A port reopened by another half is used right away, as its reopening already reported the lost bytes.
+    fn run<T>(&mut self, mut operation: impl FnMut(&mut dyn SerialHandle) -> io::Result<T>) -> io::Result<T> {
+        if self.shared.generation.load(Ordering::Acquire) != self.generation {
+            let shared = self.shared.clone();
+            self.follow(&shared.lock())?;
+        }
+        let result = match &mut self.handle {
+            Some(handle) => operation(handle.as_mut()),
+            None => Err(io::Error::new(io::ErrorKind::NotConnected, "The serial port is closed")),
+        };
+        result.map_err(|error| self.recover(error))
+    }
+
+    /// Switches to the port reopened by another half.
+    fn follow(&mut self, state: &PortState) -> io::Result<()> {
+        self.handle = match &state.latest {
+            Some(latest) => Some(latest.try_clone()?),
+            None => None,
+        };
+        self.generation = self.shared.generation.load(Ordering::Acquire);
+        Ok(())
+    }
+
+    /// Handles the `error` of an operation on the port, reopening it if the error is recoverable.
+    ///
+    /// Returns `ChannelError::Reopened` once the port is reopened, or `error` if it can't be.
+    fn recover(&mut self, error: io::Error) -> io::Error {
+        let shared = self.shared.clone();
+        let mut state = shared.lock();
+        if state.recovery.attempts == 0 || !is_recoverable(&error) {
+            return error;
+        }
+        if shared.generation.load(Ordering::Acquire) != self.generation {
+            // Another half reopened the port while this one was using the previous port.
+            return match self.follow(&state) {
+                Ok(()) => reopened_error(&state.name),
+                Err(_) => error,
+            };
+        }
+
+        // The previous port is closed first, as a port may only be open once.
+        self.handle = None;
+        state.latest = None;
+        for attempt in 1..=state.recovery.attempts {
+            std::thread::sleep(state.recovery.delay);
+            match state.reopen().and_then(|port| Ok((port.try_clone()?, port))) {
+                Ok((latest, port)) => {
+                    state.latest = Some(latest);
+                    self.handle = Some(port);
+                    self.generation = shared.generation.fetch_add(1, Ordering::AcqRel) + 1;
+                    let message = format!("Reopened {} after an I/O error: {}", state.name, error);
+                    state.warn(&message);
+                    return reopened_error(&state.name);
+                }
+                Err(reopen_error) => {
+                    let message = format!(
+                        "Unable to reopen {} ({}/{}): {}",
+                        state.name, attempt, state.recovery.attempts, reopen_error
+                    );
+                    state.warn(&message);
+                }
+            }
+        }
+        error
+    }
+}
+
+#[cfg(feature = "serial")]
+impl io::Read for RecoveringPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.run(|port| port.read(buf))
+    }
+}
+
+#[cfg(feature = "serial")]
+impl io::Write for RecoveringPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.run(|port| port.write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.run(|port| port.flush())
+    }
+}
+
+/// Returns the error of an operation interrupted by reopening the port `name`.
+#[cfg(feature = "serial")]
+fn reopened_error(name: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::ConnectionReset, ChannelError::Reopened(name.to_string()))
+}
+
+/// Returns true if `error` is a failure of the port that reopening it may fix.
+///
+/// The `serialport` crate reports the `EIO` of USB-serial adapters without its error number, so every error but a
+/// timeout or an interrupted call is taken as a failure of the port. Reopening the port of an unplugged device fails,
+/// which leaves the original error.
+#[cfg(feature = "serial")]
+fn is_recoverable(error: &io::Error) -> bool {
+    let transient = matches!(
+        error.kind(),
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted
+    );
+    !transient && !is_reopened(error)
+}
+
+/// Returns true if `error` is an operation failed because its serial port was reopened, so the link must be
+/// resynchronized.
+pub fn is_reopened(error: &(dyn std::error::Error + 'static)) -> bool {
+    error
+        .downcast_ref::<io::Error>()
+        .and_then(|error| error.get_ref())
+        .and_then(|error| error.downcast_ref::<ChannelError>())
+        .is_some_and(|error| matches!(error, ChannelError::Reopened(_)))
+}
+
+/// Errors of a serial port channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ChannelError {
+    /// The user isn't allowed to open the port (port name).
+    PermissionDenied(String),
+
+    /// The port was reopened after an I/O error, losing the bytes in flight (port name).
+    Reopened(String),
+}
+
+impl std::error::Error for ChannelError {}
+
+impl std::fmt::Display for ChannelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChannelError::PermissionDenied(port) => write!(
+                f,
+                "Permission denied opening {}. Add your user to the dialout group with `sudo usermod -aG dialout \
+                 $USER` and log in again, or install a udev rule granting access to the SA430 (idVendor {}, \
+                 idProduct {}) with `sa430 setup-udev --install`. Run `sa430 doctor` for more details",
+                port,
+                crate::port::USB_VENDOR_ID,
+                crate::port::USB_PRODUCT_ID
+            ),
+            ChannelError::Reopened(port) => {
+                write!(f, "{} reopened after an I/O error, the data in flight was lost", port)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "serial")]
 impl io::Read for SerialPortChannel {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         self.port.read(buf)
     }
 }
 
+#[cfg(feature = "serial")]
 impl io::Write for SerialPortChannel {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         self.port.write(buf)
@@ -92,16 +522,23 @@ impl io::Write for SerialPortChannel {
     }
 }
 
+#[cfg(feature = "serial")]
 impl Channel for SerialPortChannel {
-    fn reader(&mut self) -> &mut dyn io::Read {
-        self
+    /// Splits the channel into independent halves, each with its own handle of the serial port and sharing its
+    /// recovery.
+    fn split(self: Box<Self>) -> io::Result<(ChannelReader, ChannelWriter)> {
+        let reader = self.port.try_clone()?;
+        Ok((Box::new(reader), Box::new(self.port)))
     }
 
-    fn writer(&mut self) -> &mut dyn io::Write {
-        self
+    fn set_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.port.run(|port| port.set_timeout(timeout))?;
+        self.port.shared.lock().timeout = timeout;
+        Ok(())
     }
 }
 
+#[doc(hidden)]
 pub mod fixtures {
     //! # Fixtures Module
     //!
@@ -157,32 +594,36 @@ pub mod fixtures {
 
     impl io::Read for MockChannel {
         fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-            let len = buf.len();
-            // Copy buf.len() bytes from the read buffer and remove the copied data to simulate reading from the channel
-            for byte in buf {
-                *byte = self.read_buffer.remove(0);
+            // Copy up to buf.len() bytes from the read buffer and remove the copied data to simulate reading from the
+            // channel, an empty read buffer reads as end of file.
+            let len = buf.len().min(self.read_buffer.len());
+            for (byte, value) in buf.iter_mut().zip(self.read_buffer.drain(..len)) {
+                *byte = value;
             }
             Ok(len)
         }
     }
 
-    impl Channel for MockChannel {
-        fn reader(&mut self) -> &mut dyn std::io::Read {
-            self
-        }
-
-        fn writer(&mut self) -> &mut dyn std::io::Write {
-            self
-        }
-    }
+    impl Channel for MockChannel {}
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use std::{io::Write, thread};
+
+    #[cfg(feature = "serial")]
+    use std::io::Read;
+
+    use crate::frame::{Command, Frame, FrameStream};
+
+    /// Counters and logs shared between a test and the closures it installs.
+    #[cfg(feature = "serial")]
+    type Shared<T> = Arc<Mutex<T>>;
+
     #[test]
-    #[cfg(target_os = "linux")]
+    #[cfg(all(target_os = "linux", feature = "serial"))]
     fn given_serial_port_path_when_port_does_not_exist_then_error() {
         let port_name = "/some/non/existent/port";
         let result = SerialPortChannel::new(port_name);
@@ -194,4 +635,231 @@ mod tests {
             panic!("Expected an error");
         }
     }
+
+    #[test]
+    #[cfg(feature = "serial")]
+    fn given_a_permission_denied_error_when_displayed_then_suggest_the_dialout_group() {
+        let error = ChannelError::PermissionDenied("/dev/ttyACM0".to_string());
+
+        let message = error.to_string();
+
+        assert!(message.starts_with("Permission denied opening /dev/ttyACM0. Add your user to the dialout group"));
+        assert!(message.contains("(idVendor 2047, idProduct 0005)"));
+    }
+
+    /// A fake serial port replaying its reads, one per call, and sharing the written bytes with its clones.
+    #[cfg(feature = "serial")]
+    #[derive(Clone, Default)]
+    struct FakePort {
+        reads: Shared<std::collections::VecDeque<io::Result<Vec<u8>>>>,
+        written: Shared<Vec<u8>>,
+    }
+
+    #[cfg(feature = "serial")]
+    impl FakePort {
+        fn with_reads(reads: Vec<io::Result<Vec<u8>>>) -> Self {
+            FakePort {
+                reads: Arc::new(Mutex::new(reads.into())),
+                ..Default::default()
+            }
+        }
+    }
+
+    #[cfg(feature = "serial")]
+    impl io::Read for FakePort {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let mut reads = self.reads.lock().unwrap();
+            match reads.pop_front() {
+                Some(Ok(mut bytes)) => {
+                    let len = buf.len().min(bytes.len());
+                    buf[..len].copy_from_slice(&bytes[..len]);
+                    if len < bytes.len() {
+                        reads.push_front(Ok(bytes.split_off(len)));
+                    }
+                    Ok(len)
+                }
+                Some(Err(error)) => Err(error),
+                None => Err(io::ErrorKind::TimedOut.into()),
+            }
+        }
+    }
+
+    #[cfg(feature = "serial")]
+    impl io::Write for FakePort {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.written.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "serial")]
+    impl SerialHandle for FakePort {
+        fn try_clone(&self) -> io::Result<Box<dyn SerialHandle>> {
+            Ok(Box::new(self.clone()))
+        }
+
+        fn set_timeout(&mut self, _timeout: Duration) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn clear_input(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Returns a channel over `ports`, opened in order, with the warnings it reports and the number of ports opened.
+    #[cfg(feature = "serial")]
+    fn a_reopening_channel(ports: Vec<FakePort>) -> (SerialPortChannel, Shared<Vec<String>>, Shared<usize>) {
+        let opened = Arc::new(Mutex::new(0));
+        let count = opened.clone();
+        let mut ports = ports.into_iter();
+        let opener: Opener = Box::new(move || {
+            *count.lock().unwrap() += 1;
+            let port = ports.next().ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))?;
+            Ok(Box::new(port))
+        });
+        let mut channel = SerialPortChannel::with_opener("/dev/fake", opener).unwrap();
+        channel.set_recovery_policy(RecoveryPolicy {
+            attempts: 2,
+            delay: Duration::ZERO,
+        });
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+        let reported = warnings.clone();
+        channel.set_warning_handler(Some(Box::new(move |warning: &str| {
+            reported.lock().unwrap().push(warning.to_string())
+        })));
+        (channel, warnings, opened)
+    }
+
+    #[test]
+    #[cfg(feature = "serial")]
+    fn given_port_errors_when_is_recoverable_then_accept_all_but_timeouts() {
+        assert!(is_recoverable(&io::Error::other("I/O error")));
+        assert!(is_recoverable(&io::Error::from(io::ErrorKind::BrokenPipe)));
+        assert!(!is_recoverable(&io::Error::from(io::ErrorKind::TimedOut)));
+        assert!(!is_recoverable(&io::Error::from(io::ErrorKind::WouldBlock)));
+        assert!(!is_recoverable(&io::Error::from(io::ErrorKind::Interrupted)));
+        assert!(!is_recoverable(&reopened_error("/dev/ttyACM0")));
+    }
+
+    #[test]
+    #[cfg(feature = "serial")]
+    fn given_a_failing_port_when_read_then_reopen_it_and_report_the_lost_bytes() {
+        let failing = FakePort::with_reads(vec![Err(io::Error::other("I/O error"))]);
+        let reopened = FakePort::with_reads(vec![Ok(b"ok".to_vec())]);
+        let (mut channel, warnings, opened) = a_reopening_channel(vec![failing, reopened.clone()]);
+        let mut buf = [0; 2];
+
+        let error = channel.read(&mut buf).unwrap_err();
+        channel.read_exact(&mut buf).unwrap();
+        channel.write_all(b"next").unwrap();
+
+        assert!(is_reopened(&error));
+        assert_eq!(&buf, b"ok");
+        assert_eq!(*reopened.written.lock().unwrap(), b"next");
+        assert_eq!(*opened.lock().unwrap(), 2);
+        assert_eq!(
+            *warnings.lock().unwrap(),
+            ["Reopened /dev/fake after an I/O error: I/O error"]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serial")]
+    fn given_a_port_that_cannot_be_reopened_when_read_then_return_the_original_error() {
+        let failing = FakePort::with_reads(vec![Err(io::Error::other("I/O error"))]);
+        let (mut channel, warnings, opened) = a_reopening_channel(vec![failing]);
+
+        let error = channel.read(&mut [0; 2]).unwrap_err();
+
+        assert!(!is_reopened(&error));
+        assert_eq!(error.to_string(), "I/O error");
+        assert_eq!(*opened.lock().unwrap(), 3);
+        assert_eq!(warnings.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    #[cfg(feature = "serial")]
+    fn given_a_split_channel_when_the_reader_reopens_the_port_then_write_to_the_reopened_port() {
+        let failing = FakePort::with_reads(vec![Err(io::Error::other("I/O error"))]);
+        let reopened = FakePort::default();
+        let (channel, _, opened) = a_reopening_channel(vec![failing.clone(), reopened.clone()]);
+        let (mut reader, mut writer) = (Box::new(channel) as Box<dyn Channel>).split().unwrap();
+
+        writer.write_all(b"before").unwrap();
+        let error = reader.read(&mut [0; 2]).unwrap_err();
+        writer.write_all(b"after").unwrap();
+
+        assert!(is_reopened(&error));
+        assert_eq!(*opened.lock().unwrap(), 2);
+        assert_eq!(*failing.written.lock().unwrap(), b"before");
+        assert_eq!(*reopened.written.lock().unwrap(), b"after");
+    }
+
+    #[test]
+    #[cfg(feature = "serial")]
+    fn given_a_port_failing_a_command_when_sa430_runs_it_then_reopen_the_port_and_retry_the_command() {
+        use crate::device::Sa430;
+        use crate::frame::fixture::*;
+
+        let failing = FakePort::with_reads(vec![Err(io::Error::other("I/O error"))]);
+        let replies = [
+            an_ack_response(Command::Sync),
+            an_ack_response(Command::GetIdn),
+            a_get_idn_response(),
+        ];
+        let reopened = FakePort::with_reads(replies.iter().map(|frame| Ok(frame.to_bytes())).collect());
+        let (channel, warnings, _) = a_reopening_channel(vec![failing, reopened.clone()]);
+        let mut device = Sa430::new(Box::new(channel));
+
+        let idn = device.idn().unwrap();
+
+        assert!(idn.starts_with("Texas Instruments"));
+        assert_eq!(warnings.lock().unwrap().len(), 1);
+        let expected = [
+            Frame::new(Command::Sync).to_bytes(),
+            Frame::new(Command::GetIdn).to_bytes(),
+        ]
+        .concat();
+        assert_eq!(*reopened.written.lock().unwrap(), expected);
+    }
+
+    #[test]
+    #[cfg(feature = "serial")]
+    fn given_a_reopened_port_error_when_is_reopened_then_detect_it_through_io_and_boxed_errors() {
+        let reopened = io::Error::new(
+            io::ErrorKind::ConnectionReset,
+            ChannelError::Reopened("/dev/ttyACM0".to_string()),
+        );
+        let boxed: Box<dyn std::error::Error> = reopened.into();
+
+        assert!(is_reopened(boxed.as_ref()));
+        assert_eq!(
+            boxed.to_string(),
+            "/dev/ttyACM0 reopened after an I/O error, the data in flight was lost"
+        );
+        assert!(!is_reopened(&io::Error::from(io::ErrorKind::ConnectionReset)));
+        assert!(!is_reopened(&ChannelError::PermissionDenied(
+            "/dev/ttyACM0".to_string()
+        )));
+    }
+
+    #[test]
+    fn given_a_split_channel_when_reading_in_another_thread_then_drain_the_frames_while_writing() {
+        let mut channel = fixtures::MockChannel::new();
+        channel.add_response(&Frame::new(Command::Sync).to_bytes());
+        channel.add_response(&Frame::new(Command::BlinkLed).to_bytes());
+        let (reader, mut writer) = (Box::new(channel) as Box<dyn Channel>).split().unwrap();
+
+        let drain = thread::spawn(move || FrameStream::new(reader).map(Result::unwrap).collect::<Vec<_>>());
+        writer.write_all(&Frame::new(Command::GetIdn).to_bytes()).unwrap();
+
+        assert_eq!(
+            drain.join().unwrap(),
+            vec![Frame::new(Command::Sync), Frame::new(Command::BlinkLed)]
+        );
+    }
 }
@@ -0,0 +1,128 @@
+use std::{error, io, time::SystemTime};
+
+use clap::ValueEnum;
+use sa430::dsp::{Comparison, TriggerCondition};
+
+const BELL: &str = "\x07";
+
+/// Actions run when a trigger trips during a capture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum AlertAction {
+    /// Rings the terminal bell and prints the trip.
+    Beep,
+
+    /// Shows a desktop notification, using `notify-send`.
+    #[cfg(feature = "notify")]
+    Notify,
+}
+
+/// A trip of a trigger.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertEvent {
+    /// Serial number of the device.
+    pub serial: u32,
+
+    /// Condition of the trigger.
+    pub condition: TriggerCondition,
+
+    /// Frequency of the peak inside the band, in Hz.
+    pub peak_frequency: f64,
+
+    /// Power of the peak inside the band, in dBm.
+    pub peak_power: f64,
+
+    /// When the trigger tripped.
+    pub timestamp: SystemTime,
+}
+
+/// Runs the alert action for a trip, ringing the bell and printing to `terminal`.
+pub fn alert(
+    action: AlertAction,
+    event: &AlertEvent,
+    terminal: &mut dyn io::Write,
+) -> Result<(), Box<dyn error::Error>> {
+    alert_message(action, &describe(event), terminal)
+}
+
+/// Runs the alert action with a message, like a problem stopping the capture.
+pub fn alert_message(
+    action: AlertAction,
+    message: &str,
+    terminal: &mut dyn io::Write,
+) -> Result<(), Box<dyn error::Error>> {
+    match action {
+        AlertAction::Beep => {
+            writeln!(terminal, "{}{}", BELL, message)?;
+            terminal.flush()?;
+        }
+        #[cfg(feature = "notify")]
+        AlertAction::Notify => notify(message)?,
+    }
+    Ok(())
+}
+
+/// Describes a trip, with frequencies in MHz.
+fn describe(event: &AlertEvent) -> String {
+    format!(
+        "Trigger tripped: {} {} {} dBm, peak of {:.2} dBm at {:.4} MHz",
+        band(&event.condition),
+        operator(&event.condition),
+        event.condition.level,
+        event.peak_power,
+        event.peak_frequency / 1e6
+    )
+}
+
+/// Formats the band of a trigger condition, in MHz.
+pub fn band(condition: &TriggerCondition) -> String {
+    format!("{:.3}-{:.3} MHz", condition.fstart / 1e6, condition.fstop / 1e6)
+}
+
+/// Returns the operator of a trigger condition.
+pub fn operator(condition: &TriggerCondition) -> char {
+    match condition.comparison {
+        Comparison::Above => '>',
+        Comparison::Below => '<',
+    }
+}
+
+#[cfg(feature = "notify")]
+fn notify(message: &str) -> Result<(), Box<dyn error::Error>> {
+    let status = std::process::Command::new("notify-send")
+        .arg("SA430")
+        .arg(message)
+        .status()?;
+    match status.success() {
+        true => Ok(()),
+        false => Err(format!("notify-send failed: {}", status).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn an_event() -> AlertEvent {
+        AlertEvent {
+            serial: 2312,
+            condition: "868.0-868.6 > -80dBm".parse().unwrap(),
+            peak_frequency: 868.3e6,
+            peak_power: -62.5,
+            timestamp: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+        }
+    }
+
+    #[test]
+    fn given_beep_when_alert_then_ring_the_bell_and_print_the_trip() {
+        let mut terminal = Vec::new();
+
+        alert(AlertAction::Beep, &an_event(), &mut terminal).unwrap();
+
+        assert_eq!(
+            String::from_utf8(terminal).unwrap(),
+            "\x07Trigger tripped: 868.000-868.600 MHz > -80 dBm, peak of -62.50 dBm at 868.3000 MHz\n"
+        );
+    }
+}
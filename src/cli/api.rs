@@ -0,0 +1,560 @@
+use std::{
+    error, io,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    thread,
+};
+
+use sa430::auth::Token;
+use sa430::cancel::CancellationToken;
+use sa430::device::Sa430;
+use sa430::formats::csv::DEFAULT_DELIMITER;
+use sa430::formats::ndjson::{escape, NdjsonSink};
+use sa430::frequency::Frequency;
+use sa430::http::{Request, Response};
+use sa430::metrics::{write_metrics, Metric, MetricKind, CONTENT_TYPE};
+use sa430::port::Port;
+use sa430::sink::{Sink, SinkRegistry, SweepRecord};
+
+use super::capture::{capture, CaptureParams, OutputFormat};
+use super::table::Style;
+
+/// Maximum number of sweeps of a capture started through the API, which are kept in memory.
+pub const MAX_SWEEPS: usize = 1000;
+
+/// Maximum number of finished captures kept in memory, the oldest ones are evicted when another capture starts.
+pub const MAX_FINISHED_CAPTURES: usize = 16;
+
+/// Lists the connected devices.
+pub type Scan = Box<dyn Fn() -> Vec<Port> + Send + Sync>;
+
+/// Opens the device at a port, ready to capture.
+pub type Connect = Arc<dyn Fn(&str) -> Result<Sa430, Box<dyn error::Error>> + Send + Sync>;
+
+/// State of a capture started through the API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum CaptureState {
+    Running,
+    Done,
+    Failed(String),
+}
+
+/// A capture started through the API, with the sweeps measured so far.
+struct CaptureJob {
+    id: usize,
+    serial: String,
+    state: CaptureState,
+    records: Vec<SweepRecord>,
+}
+
+/// The captures started through the API, with the totals of the metrics, which keep counting the evicted captures.
+#[derive(Default)]
+struct Captures {
+    jobs: Vec<CaptureJob>,
+    started: usize,
+    failed: usize,
+    sweeps: usize,
+}
+
+impl Captures {
+    /// Returns the capture with the `id`, unless it was evicted.
+    fn get_mut(&mut self, id: usize) -> Option<&mut CaptureJob> {
+        self.jobs.iter_mut().find(|job| job.id == id)
+    }
+
+    /// Evicts the oldest finished captures, keeping `MAX_FINISHED_CAPTURES` of them.
+    fn evict(&mut self) {
+        let finished = self
+            .jobs
+            .iter()
+            .filter(|job| job.state != CaptureState::Running)
+            .count();
+        let mut excess = finished.saturating_sub(MAX_FINISHED_CAPTURES);
+        self.jobs.retain(|job| {
+            let evicted = excess > 0 && job.state != CaptureState::Running;
+            excess -= evicted as usize;
+            !evicted
+        });
+    }
+}
+
+/// A REST API to list the devices and run captures:
+///
+/// - `GET /devices`: the connected devices.
+/// - `POST /devices/{serial}/capture`: starts a capture, with a JSON body with `fstart_mhz`, `fstop_mhz`, `fstep_mhz`
+///   and, optionally, `count` and `rlevel`. Answers the `id` of the capture.
+/// - `GET /captures/{id}`: the state and sweeps of a capture, kept until `MAX_FINISHED_CAPTURES` newer captures
+///   finish.
+/// - `GET /metrics`: the health of the API, in the OpenMetrics format, like the captures that failed.
+pub struct RestApi {
+    scan: Scan,
+    connect: Connect,
+    token: Option<Token>,
+    captures: Arc<Mutex<Captures>>,
+}
+
+impl RestApi {
+    /// Creates an API finding the devices with `scan` and opening them with `connect`, requiring the `token`, if any,
+    /// as a `Bearer` authorization.
+    pub fn new(scan: Scan, connect: Connect, token: Option<Token>) -> Self {
+        RestApi {
+            scan,
+            connect,
+            token,
+            captures: Arc::new(Mutex::new(Captures::default())),
+        }
+    }
+
+    /// Answers a request.
+    pub fn handle(&self, request: &Request) -> Response {
+        if let Some(token) = &self.token {
+            let authorization = request
+                .header("authorization")
+                .and_then(|value| value.strip_prefix("Bearer "));
+            if !authorization.is_some_and(|candidate| token.matches(candidate)) {
+                return Response::error(401, "Missing or invalid bearer token");
+            }
+        }
+
+        let path = request.path.split('?').next().unwrap_or_default();
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        match (request.method.as_str(), segments.as_slice()) {
+            ("GET", ["devices"]) => self.devices(),
+            ("POST", ["devices", serial, "capture"]) => self.start_capture(serial, &request.body),
+            ("GET", ["captures", id]) => self.capture(id),
+            ("GET", ["metrics"]) => self.metrics(),
+            (_, ["devices"]) | (_, ["devices", _, "capture"]) | (_, ["captures", _]) | (_, ["metrics"]) => {
+                Response::error(405, "Method not allowed")
+            }
+            _ => Response::error(404, "Not found"),
+        }
+    }
+
+    fn devices(&self) -> Response {
+        let devices: Vec<String> = (self.scan)()
+            .iter()
+            .map(|port| {
+                format!(
+                    "{{\"serial\":\"{}\",\"port\":\"{}\",\"firmware\":\"{}\"}}",
+                    escape(port.serial_number()),
+                    escape(port.path()),
+                    escape(port.firmware_version())
+                )
+            })
+            .collect();
+        Response::json(200, format!("[{}]", devices.join(",")))
+    }
+
+    fn start_capture(&self, serial: &str, body: &str) -> Response {
+        let Some(port) = (self.scan)().into_iter().find(|port| port.serial_number() == serial) else {
+            return Response::error(404, &format!("No device with serial number {}", serial));
+        };
+        let params = match capture_params(body) {
+            Ok(params) => params,
+            Err(error) => return Response::error(400, &error.to_string()),
+        };
+
+        let mut captures = lock(&self.captures);
+        let busy = captures
+            .jobs
+            .iter()
+            .any(|job| job.serial == serial && job.state == CaptureState::Running);
+        if busy {
+            return Response::error(409, &format!("Device {} is already capturing", serial));
+        }
+        captures.evict();
+        captures.started += 1;
+        let id = captures.started;
+        captures.jobs.push(CaptureJob {
+            id,
+            serial: serial.to_string(),
+            state: CaptureState::Running,
+            records: Vec::new(),
+        });
+
+        let (jobs, connect) = (self.captures.clone(), self.connect.clone());
+        thread::spawn(move || {
+            let state = match panic::catch_unwind(AssertUnwindSafe(|| run_capture(&connect, &port, params, &jobs, id)))
+            {
+                Ok(Ok(())) => CaptureState::Done,
+                Ok(Err(error)) => CaptureState::Failed(error.to_string()),
+                Err(_) => CaptureState::Failed("The capture stopped unexpectedly".to_string()),
+            };
+            let mut captures = lock(&jobs);
+            if matches!(state, CaptureState::Failed(_)) {
+                captures.failed += 1;
+            }
+            if let Some(job) = captures.get_mut(id) {
+                job.state = state;
+            }
+        });
+        Response::json(202, format!("{{\"id\":{}}}", id))
+    }
+
+    fn capture(&self, id: &str) -> Response {
+        let mut captures = lock(&self.captures);
+        let Some(job) = id.parse::<usize>().ok().and_then(|id| captures.get_mut(id)) else {
+            return Response::error(404, &format!("No capture with id {}", id));
+        };
+
+        let mut sweeps = Vec::new();
+        let mut sink = NdjsonSink::new(&mut sweeps);
+        for record in &job.records {
+            if let Err(error) = sink.write(record) {
+                return Response::error(500, &error.to_string());
+            }
+        }
+        let sweeps = String::from_utf8_lossy(&sweeps).lines().collect::<Vec<_>>().join(",");
+        let state = match &job.state {
+            CaptureState::Running => "\"state\":\"running\"".to_string(),
+            CaptureState::Done => "\"state\":\"done\"".to_string(),
+            CaptureState::Failed(error) => format!("\"state\":\"failed\",\"error\":\"{}\"", escape(error)),
+        };
+        Response::json(
+            200,
+            format!(
+                "{{\"id\":{},\"serial\":\"{}\",{},\"sweeps\":[{}]}}",
+                job.id,
+                escape(&job.serial),
+                state,
+                sweeps
+            ),
+        )
+    }
+
+    fn metrics(&self) -> Response {
+        let devices = (self.scan)().len();
+        let captures = lock(&self.captures);
+        let running = captures
+            .jobs
+            .iter()
+            .filter(|job| job.state == CaptureState::Running)
+            .count();
+        let metrics = [
+            Metric::new("sa430_devices", "Connected devices.", MetricKind::Gauge, devices as f64),
+            Metric::new(
+                "sa430_captures",
+                "Captures started.",
+                MetricKind::Counter,
+                captures.started as f64,
+            ),
+            Metric::new(
+                "sa430_captures_running",
+                "Captures running.",
+                MetricKind::Gauge,
+                running as f64,
+            ),
+            Metric::new(
+                "sa430_capture_errors",
+                "Captures stopped by an error, like a device or protocol failure.",
+                MetricKind::Counter,
+                captures.failed as f64,
+            ),
+            Metric::new(
+                "sa430_sweeps",
+                "Sweeps completed.",
+                MetricKind::Counter,
+                captures.sweeps as f64,
+            ),
+        ];
+
+        let mut body = Vec::new();
+        match write_metrics(&metrics, &mut body) {
+            Ok(()) => Response::with_content_type(200, CONTENT_TYPE, String::from_utf8_lossy(&body)),
+            Err(error) => Response::error(500, &error.to_string()),
+        }
+    }
+}
+
+/// Opens the device and captures the sweeps, adding them to the job.
+fn run_capture(
+    connect: &Connect,
+    port: &Port,
+    mut params: CaptureParams,
+    jobs: &Arc<Mutex<Captures>>,
+    id: usize,
+) -> Result<(), Box<dyn error::Error>> {
+    let mut device = connect(port.name())?;
+    let jobs = jobs.clone();
+    params.sinks.add(Box::new(
+        move |record: &SweepRecord| -> Result<(), Box<dyn error::Error>> {
+            let mut captures = lock(&jobs);
+            captures.sweeps += 1;
+            if let Some(job) = captures.get_mut(id) {
+                job.records.push(record.clone());
+            }
+            Ok(())
+        },
+    ));
+    capture(&mut device, &mut params, &mut io::sink())
+}
+
+/// Parses the capture parameters of a request body.
+fn capture_params(body: &str) -> Result<CaptureParams, Box<dyn error::Error>> {
+    let required = |key: &str| number(body, key)?.ok_or(format!("Missing number {} in the request body", key));
+    let count = number(body, "count")?.unwrap_or(1.0);
+    if count.fract() != 0.0 || !(1.0..=MAX_SWEEPS as f64).contains(&count) {
+        return Err(format!("The count must be a whole number from 1 to {}", MAX_SWEEPS).into());
+    }
+    let ref_level = match number(body, "rlevel")? {
+        Some(level) if level.fract() == 0.0 && (i8::MIN as f64..=i8::MAX as f64).contains(&level) => Some(level as i8),
+        Some(_) => return Err(format!("The rlevel must be a whole number from {} to {}", i8::MIN, i8::MAX).into()),
+        None => None,
+    };
+
+    Ok(CaptureParams {
+        fstart: Frequency::from_mhz(required("fstart_mhz")?),
+        fstop: Frequency::from_mhz(required("fstop_mhz")?),
+        fstep: Frequency::from_mhz(required("fstep_mhz")?),
+        ref_level,
+        corrections: None,
+        channel_plan: None,
+        count: count as usize,
+        duration: None,
+        trigger: None,
+        location: None,
+        tags: Vec::new(),
+        note: None,
+        cancel: CancellationToken::new(),
+        style: Style::plain(),
+        format: OutputFormat::Csv,
+        delimiter: DEFAULT_DELIMITER,
+        timestamps: None,
+        alignment: None,
+        alert: None,
+        webhook: None,
+        report: None,
+        retention: None,
+        sinks: SinkRegistry::new(),
+    })
+}
+
+/// Returns the number value of the top level `key` of a JSON object, if any, failing if it isn't a finite number.
+fn number(body: &str, key: &str) -> Result<Option<f64>, String> {
+    let Some(value) = top_level_value(body, key) else {
+        return Ok(None);
+    };
+    // A JSON number starts with a sign or a digit, unlike the `inf` and `NaN` that Rust also parses.
+    value
+        .starts_with(|c: char| c == '-' || c.is_ascii_digit())
+        .then(|| value.parse::<f64>().ok())
+        .flatten()
+        .filter(|value| value.is_finite())
+        .map(Some)
+        .ok_or(format!("The {} must be a finite number", key))
+}
+
+/// Returns the raw value of the top level `key` of a JSON object, skipping over strings and nested values, so a key
+/// only matches at the top level. Returns `None` if the body isn't an object or doesn't have the key.
+fn top_level_value<'a>(body: &'a str, key: &str) -> Option<&'a str> {
+    if !body.trim_start().starts_with('{') {
+        return None;
+    }
+    let (mut depth, mut in_string, mut escaped) = (0, false, false);
+    let (mut string_start, mut expecting_key) = (0, false);
+    let (mut current_key, mut value_start) = (None, None);
+    for (index, byte) in body.bytes().enumerate() {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => {
+                    in_string = false;
+                    if depth == 1 && expecting_key {
+                        current_key = Some(&body[string_start..index]);
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => (in_string, string_start) = (true, index + 1),
+            b'{' | b'[' => {
+                depth += 1;
+                expecting_key = depth == 1;
+            }
+            b'}' | b']' if depth == 1 => return value_start.map(|start| body[start..index].trim()),
+            b'}' | b']' => depth -= 1,
+            b',' if depth == 1 => match value_start {
+                Some(start) => return Some(body[start..index].trim()),
+                None => (expecting_key, current_key) = (true, None),
+            },
+            b':' if depth == 1 => {
+                expecting_key = false;
+                if current_key == Some(key) {
+                    value_start = Some(index + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn lock(captures: &Mutex<Captures>) -> MutexGuard<'_, Captures> {
+    captures.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    fn an_api(token: Option<Token>) -> RestApi {
+        RestApi::new(
+            Box::new(|| vec![Port::new("/dev/ttyACM0", "1234", "0104")]),
+            Arc::new(|port: &str| Err(format!("Unable to open {}", port).into())),
+            token,
+        )
+    }
+
+    fn a_request(method: &str, path: &str, body: &str) -> Request {
+        Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            body: body.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn given_a_capture_request_when_the_device_fails_then_report_the_error() {
+        let api = an_api(None);
+
+        let devices = api.handle(&a_request("GET", "/devices", ""));
+        let started = api.handle(&a_request(
+            "POST",
+            "/devices/1234/capture",
+            "{\"fstart_mhz\": 868, \"fstop_mhz\": 870, \"fstep_mhz\": 0.1}",
+        ));
+        let mut capture = api.handle(&a_request("GET", "/captures/1", ""));
+        while capture.body.contains("running") {
+            thread::sleep(Duration::from_millis(1));
+            capture = api.handle(&a_request("GET", "/captures/1", ""));
+        }
+
+        assert_eq!(
+            devices.body,
+            "[{\"serial\":\"1234\",\"port\":\"/dev/ttyACM0\",\"firmware\":\"0104\"}]"
+        );
+        assert_eq!((started.status, started.body.as_str()), (202, "{\"id\":1}"));
+        assert_eq!(
+            capture.body,
+            "{\"id\":1,\"serial\":\"1234\",\"state\":\"failed\",\"error\":\"Unable to open /dev/ttyACM0\",\"sweeps\":[]}"
+        );
+        let metrics = api.handle(&a_request("GET", "/metrics", "")).body;
+        assert!(metrics.contains("\nsa430_devices 1\n"));
+        assert!(metrics.contains("\nsa430_captures_running 0\n"));
+        assert!(metrics.contains("\nsa430_capture_errors_total 1\n"));
+    }
+
+    /// Starts a capture and waits for it to finish.
+    fn run_a_capture(api: &RestApi) -> Response {
+        let started = api.handle(&a_request(
+            "POST",
+            "/devices/1234/capture",
+            "{\"fstart_mhz\": 868, \"fstop_mhz\": 870, \"fstep_mhz\": 0.1}",
+        ));
+        let path = format!("/captures/{}", &started.body[6..started.body.len() - 1]);
+        while api.handle(&a_request("GET", &path, "")).body.contains("running") {
+            thread::sleep(Duration::from_millis(1));
+        }
+        started
+    }
+
+    #[test]
+    fn given_more_finished_captures_than_the_max_when_start_capture_then_evict_the_oldest() {
+        let api = an_api(None);
+
+        let started: Vec<Response> = (0..MAX_FINISHED_CAPTURES + 2).map(|_| run_a_capture(&api)).collect();
+
+        assert_eq!(
+            started[MAX_FINISHED_CAPTURES + 1].body,
+            format!("{{\"id\":{}}}", MAX_FINISHED_CAPTURES + 2)
+        );
+        assert_eq!(api.handle(&a_request("GET", "/captures/1", "")).status, 404);
+        assert_eq!(api.handle(&a_request("GET", "/captures/2", "")).status, 200);
+        assert_eq!(lock(&api.captures).jobs.len(), MAX_FINISHED_CAPTURES + 1);
+        let metrics = api.handle(&a_request("GET", "/metrics", "")).body;
+        assert!(metrics.contains(&format!("\nsa430_captures_total {}\n", MAX_FINISHED_CAPTURES + 2)));
+        assert!(metrics.contains(&format!("\nsa430_capture_errors_total {}\n", MAX_FINISHED_CAPTURES + 2)));
+    }
+
+    #[test]
+    fn given_an_id_with_a_sign_when_get_capture_then_answer_the_number() {
+        let api = an_api(None);
+        run_a_capture(&api);
+
+        let capture = api.handle(&a_request("GET", "/captures/+1", ""));
+        let missing = api.handle(&a_request("GET", "/captures/\"x\n", ""));
+
+        assert!(capture.body.starts_with("{\"id\":1,"));
+        assert_eq!(missing.body, "{\"error\":\"No capture with id \\\"x\\u000a\"}");
+    }
+
+    #[test]
+    fn given_invalid_requests_when_handle_then_answer_the_error_status() {
+        let api = an_api(Some(Token::new("lab-secret").unwrap()));
+        let authorized = |method: &str, path: &str, body: &str| {
+            let mut request = a_request(method, path, body);
+            request
+                .headers
+                .push(("authorization".to_string(), "Bearer lab-secret".to_string()));
+            api.handle(&request).status
+        };
+
+        assert_eq!(authorized("POST", "/devices/9999/capture", "{}"), 404);
+        assert_eq!(authorized("POST", "/devices/1234/capture", "{\"fstart_mhz\":868}"), 400);
+        assert_eq!(authorized("DELETE", "/devices", ""), 405);
+        assert_eq!(authorized("GET", "/captures/1", ""), 404);
+        assert_eq!(api.handle(&a_request("GET", "/devices", "")).status, 401);
+    }
+
+    #[test]
+    fn given_a_key_inside_a_string_or_a_nested_value_when_number_then_only_match_the_top_level() {
+        let body = r#"{"note":"\"count\":5", "tags":{"count":7}, "list":[{"count":9}], "count": 3}"#;
+
+        assert_eq!(number(body, "count"), Ok(Some(3.0)));
+        assert_eq!(number(r#"{"note":"\"count\":5"}"#, "count"), Ok(None));
+        assert_eq!(number(r#"{"note":"count","count":2}"#, "count"), Ok(Some(2.0)));
+        assert_eq!(number(r#"[{"count":2}]"#, "count"), Ok(None));
+    }
+
+    #[test]
+    fn given_invalid_numbers_when_start_capture_then_answer_bad_request() {
+        let api = an_api(None);
+        let status = |extra: &str| {
+            let body = format!(
+                "{{\"fstart_mhz\": 868, \"fstop_mhz\": 870, \"fstep_mhz\": 0.1{}}}",
+                extra
+            );
+            api.handle(&a_request("POST", "/devices/1234/capture", &body)).status
+        };
+
+        for extra in [
+            ", \"count\": NaN",
+            ", \"count\": inf",
+            ", \"rlevel\": -inf",
+            ", \"count\": 1e999",
+        ] {
+            assert_eq!(status(extra), 400, "{}", extra);
+        }
+        for extra in [
+            ", \"rlevel\": 1000",
+            ", \"rlevel\": -129",
+            ", \"rlevel\": 2.5",
+            ", \"rlevel\": \"high\"",
+        ] {
+            assert_eq!(status(extra), 400, "{}", extra);
+        }
+    }
+
+    #[test]
+    fn given_a_reference_level_when_capture_params_then_keep_it() {
+        let body = r#"{"fstart_mhz": 868, "fstop_mhz": 870, "fstep_mhz": 0.1, "rlevel": -35}"#;
+
+        assert_eq!(capture_params(body).unwrap().ref_level, Some(-35));
+    }
+}
@@ -0,0 +1,101 @@
+use std::{error, fs::File, io, io::BufReader, path::Path};
+
+use sa430::bands::Bands;
+use sa430::frequency::Frequency;
+
+use super::config::default_bands_file;
+use super::table::{Align, Style, Table};
+
+/// Loads the built-in bands, extended with the bands in the CSV file at `path`, or the bands file of the configuration
+/// directory, if any.
+pub fn load_bands(path: Option<&Path>) -> Result<Bands, Box<dyn error::Error>> {
+    let mut bands = Bands::builtin();
+    if let Some(path) = path.map(Path::to_path_buf).or_else(default_bands_file) {
+        bands
+            .read_csv(BufReader::new(File::open(&path)?))
+            .map_err(|error| format!("{}: {}", path.display(), error))?;
+    }
+    Ok(bands)
+}
+
+/// Prints the bands to the output, with frequencies in MHz.
+pub fn list_bands(bands: &Bands, style: &Style, output: &mut dyn io::Write) -> io::Result<()> {
+    let mut table = Table::new()
+        .column("name", Align::Left, 14)
+        .column("fstart (MHz)", Align::Right, 0)
+        .column("fstop (MHz)", Align::Right, 0)
+        .column("description", Align::Left, 0);
+    for band in bands {
+        table.row(vec![
+            band.name().to_string(),
+            format!("{:.3}", band.fstart() / 1e6),
+            format!("{:.3}", band.fstop() / 1e6),
+            band.description().to_string(),
+        ]);
+    }
+    table.write(style, output)
+}
+
+/// Returns the start and stop frequencies of the band named `band`, or the given ones, in MHz, when no band is set.
+///
+/// Explicit frequencies take precedence over the band limits.
+pub fn resolve_range(
+    bands: &Bands,
+    band: Option<&str>,
+    fstart: Option<f64>,
+    fstop: Option<f64>,
+) -> Result<(Frequency, Frequency), Box<dyn error::Error>> {
+    let limits = match band {
+        Some(name) => {
+            let band = bands.find(name).ok_or(format!("Unknown band: {}", name))?;
+            Some((Frequency::from_hz(band.fstart()), Frequency::from_hz(band.fstop())))
+        }
+        None => None,
+    };
+
+    let fstart = fstart.map(Frequency::from_mhz).or(limits.map(|l| l.0));
+    let fstop = fstop.map(Frequency::from_mhz).or(limits.map(|l| l.1));
+    match (fstart, fstop) {
+        (Some(fstart), Some(fstop)) => Ok((fstart, fstop)),
+        _ => Err("Either --band or both --fstart and --fstop must be provided".into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_builtin_bands_when_list_bands_then_print_a_table() {
+        let mut output = Vec::new();
+
+        list_bands(&Bands::builtin(), &Style::plain(), &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("name           | fstart (MHz) | fstop (MHz) | description\n"));
+        assert!(output.contains("eu868          |      863.000 |     870.000 | EU 868 MHz short range devices\n"));
+    }
+
+    #[test]
+    fn given_a_band_when_resolve_range_then_return_band_limits() {
+        let range = resolve_range(&Bands::builtin(), Some("us915"), None, None).unwrap();
+        assert_eq!(range, (Frequency::from_mhz(902.0), Frequency::from_mhz(928.0)));
+    }
+
+    #[test]
+    fn given_a_band_and_a_frequency_when_resolve_range_then_prefer_the_frequency() {
+        let range = resolve_range(&Bands::builtin(), Some("us915"), Some(910.0), None).unwrap();
+        assert_eq!(range, (Frequency::from_mhz(910.0), Frequency::from_mhz(928.0)));
+    }
+
+    #[test]
+    fn given_an_unknown_band_when_resolve_range_then_return_error() {
+        let error = resolve_range(&Bands::builtin(), Some("xx"), None, None).unwrap_err();
+        assert_eq!(error.to_string(), "Unknown band: xx");
+    }
+
+    #[test]
+    fn given_no_band_and_a_missing_frequency_when_resolve_range_then_return_error() {
+        assert!(resolve_range(&Bands::builtin(), None, Some(900.0), None).is_err());
+    }
+}
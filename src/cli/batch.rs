@@ -0,0 +1,142 @@
+use std::{error, io, thread};
+
+use sa430::port::Port;
+use sa430::scanner::Scanner;
+
+use super::table::{Align, Style, Table};
+
+/// Outcome of an operation on one device: a short summary on success, the error message on failure.
+pub struct DeviceResult {
+    pub port: Port,
+    pub result: Result<String, String>,
+}
+
+/// Runs `operation` on every connected device, in parallel if asked, and prints a result table, one row per device.
+///
+/// Fails if no device is connected or the operation failed on any of them, after running it on all.
+pub fn batch<F>(
+    scanner: &dyn Scanner,
+    parallel: bool,
+    style: &Style,
+    output: &mut dyn io::Write,
+    operation: F,
+) -> Result<(), Box<dyn error::Error>>
+where
+    F: Fn(&Port) -> Result<String, Box<dyn error::Error>> + Sync,
+{
+    let ports = scanner.scan();
+    if ports.is_empty() {
+        return Err("No devices connected".into());
+    }
+
+    let results = run_all(ports, parallel, operation);
+    print_results(&results, style, output)?;
+
+    let failed = results.iter().filter(|device| device.result.is_err()).count();
+    match failed {
+        0 => Ok(()),
+        failed => Err(format!("The operation failed on {} of {} devices", failed, results.len()).into()),
+    }
+}
+
+/// Runs `operation` on each port, in a thread per port if `parallel`, returning the results in the order of the ports.
+fn run_all<F>(ports: Vec<Port>, parallel: bool, operation: F) -> Vec<DeviceResult>
+where
+    F: Fn(&Port) -> Result<String, Box<dyn error::Error>> + Sync,
+{
+    let run = |port: &Port| operation(port).map_err(|error| error.to_string());
+    let results: Vec<Result<String, String>> = match parallel {
+        true => thread::scope(|scope| {
+            let handles: Vec<_> = ports.iter().map(|port| scope.spawn(move || run(port))).collect();
+            handles
+                .into_iter()
+                .map(|handle| {
+                    handle
+                        .join()
+                        .unwrap_or_else(|_| Err("The operation panicked".to_string()))
+                })
+                .collect()
+        }),
+        false => ports.iter().map(run).collect(),
+    };
+
+    ports
+        .into_iter()
+        .zip(results)
+        .map(|(port, result)| DeviceResult { port, result })
+        .collect()
+}
+
+fn print_results(results: &[DeviceResult], style: &Style, output: &mut dyn io::Write) -> io::Result<()> {
+    let mut table = Table::new()
+        .column("port", Align::Left, 14)
+        .column("serial number", Align::Left, 16)
+        .column("status", Align::Left, 6)
+        .column("result", Align::Left, 0);
+    for device in results {
+        let (status, result) = match &device.result {
+            Ok(summary) => ("ok", summary),
+            Err(error) => ("failed", error),
+        };
+        table.row(vec![
+            device.port.path().to_string(),
+            device.port.serial_number().to_string(),
+            status.to_string(),
+            result.to_string(),
+        ]);
+    }
+    table.write(style, output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use sa430::scanner::fixtures::MockScanner;
+
+    fn a_scanner() -> MockScanner {
+        MockScanner::with_ports(&[
+            Port::new("/dev/ttyUSB1", "08FF41E50F8B3A34", "0104"),
+            Port::new("/dev/ttyUSB2", "08FF41E50F8B3A35", "0104"),
+        ])
+    }
+
+    fn blink_first(port: &Port) -> Result<String, Box<dyn error::Error>> {
+        match port.name() {
+            "/dev/ttyUSB1" => Ok("blinked".to_string()),
+            _ => Err("Timeout".into()),
+        }
+    }
+
+    #[test]
+    fn given_a_failing_device_when_batch_then_print_every_result_and_fail() {
+        for parallel in [false, true] {
+            let mut output = Vec::new();
+
+            let result = batch(&a_scanner(), parallel, &Style::plain(), &mut output, blink_first);
+
+            assert_eq!(
+                result.unwrap_err().to_string(),
+                "The operation failed on 1 of 2 devices"
+            );
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                "port           | serial number    | status | result\n\
+                 ---------------|------------------|--------|--------\n\
+                 /dev/ttyUSB1   | 08FF41E50F8B3A34 | ok     | blinked\n\
+                 /dev/ttyUSB2   | 08FF41E50F8B3A35 | failed | Timeout\n\
+                 ---------------|------------------|--------|--------\n"
+            );
+        }
+    }
+
+    #[test]
+    fn given_no_devices_when_batch_then_return_an_error() {
+        let mut output = Vec::new();
+
+        let result = batch(&MockScanner::new(), false, &Style::plain(), &mut output, blink_first);
+
+        assert!(result.is_err());
+        assert!(output.is_empty());
+    }
+}
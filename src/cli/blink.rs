@@ -1,10 +1,24 @@
 use sa430::device::Sa430;
 
-use std::{error, io};
-
-pub fn blink(device: &mut Sa430, output: &mut dyn io::Write) -> Result<(), Box<dyn error::Error>> {
-    writeln!(output, "Blinking LED...")?;
-    device.blink()?;
+use std::{error, io, num::NonZeroU32, time::Duration};
+
+/// Blinks the LED `count` times, waiting `interval` between blinks.
+pub fn blink(
+    device: &mut Sa430,
+    count: NonZeroU32,
+    interval: Duration,
+    output: &mut dyn io::Write,
+) -> Result<(), Box<dyn error::Error>> {
+    match count.get() {
+        1 => writeln!(output, "Blinking LED...")?,
+        count => writeln!(
+            output,
+            "Blinking LED {} times every {} ms...",
+            count,
+            interval.as_millis()
+        )?,
+    }
+    device.blink_repeat(count, interval)?;
     writeln!(output, "Done!")?;
     Ok(())
 }
@@ -26,8 +40,22 @@ mod tests {
 
         let mut device = Sa430::new(Box::new(channel));
 
-        blink(&mut device, &mut output).unwrap();
+        blink(&mut device, NonZeroU32::MIN, Duration::ZERO, &mut output).unwrap();
 
         assert_eq!(output, b"Blinking LED...\nDone!\n");
     }
+
+    #[test]
+    fn given_a_count_when_blink_then_blink_count_times() {
+        let mut output = Vec::new();
+        let mut channel = MockChannel::new();
+        channel.add_response(&an_ack_response(Command::BlinkLed).to_bytes());
+        channel.add_response(&an_ack_response(Command::BlinkLed).to_bytes());
+
+        let mut device = Sa430::new(Box::new(channel));
+
+        blink(&mut device, NonZeroU32::new(2).unwrap(), Duration::ZERO, &mut output).unwrap();
+
+        assert_eq!(output, b"Blinking LED 2 times every 0 ms...\nDone!\n");
+    }
 }
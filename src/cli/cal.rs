@@ -0,0 +1,407 @@
+use std::{error, fs::File, io, io::BufReader, path::Path};
+
+use clap::ValueEnum;
+use sa430::device::Sa430;
+use sa430::formats::gains::{read_gains_csv, write_gains_csv, write_gains_json};
+use sa430::frequency::Frequency;
+use sa430::noise::estimate_noise_floors;
+use sa430::power::Dbm;
+use sa430::reference::{Correction, CorrectionProfile, ReferencePoint};
+use sa430::spectrum::Spectrum;
+
+use super::table::{Align, Style, Table};
+
+/// File formats of the exported gain tables.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum GainsFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+/// Prints the calibration gain tables of the device to the output.
+pub fn export_gains(
+    device: &mut Sa430,
+    format: GainsFormat,
+    delimiter: char,
+    output: &mut dyn io::Write,
+) -> Result<(), Box<dyn error::Error>> {
+    let calibration = device.calibration()?;
+    match format {
+        GainsFormat::Csv => write_gains_csv(calibration, delimiter, output),
+        GainsFormat::Json => write_gains_json(calibration, output),
+    }
+}
+
+/// Replaces, in memory, the device gain tables with the ones in the CSV file at `path`.
+pub fn load_gains(device: &mut Sa430, path: &Path) -> Result<(), Box<dyn error::Error>> {
+    let file = BufReader::new(File::open(path)?);
+    let calibration = read_gains_csv(file, device.calibration()?)?;
+    device.set_calibration(calibration);
+    Ok(())
+}
+
+/// Prints the estimated noise floor from `fstart` to `fstop` at each reference level, with a resolution bandwidth of
+/// `rbw`.
+pub fn noise_floor(
+    device: &mut Sa430,
+    fstart: Frequency,
+    fstop: Frequency,
+    rbw: Frequency,
+    style: &Style,
+    output: &mut dyn io::Write,
+) -> Result<(), Box<dyn error::Error>> {
+    let floors = estimate_noise_floors(device.calibration()?, fstart, fstop, rbw)?;
+
+    let mut table = Table::new()
+        .column("ref level (dBm)", Align::Right, 0)
+        .column("min floor (dBm)", Align::Right, 0)
+        .column("max floor (dBm)", Align::Right, 0);
+    for floor in floors {
+        table.row(vec![
+            floor.ref_level.to_string(),
+            format!("{:.1}", floor.min_dbm),
+            format!("{:.1}", floor.max_dbm),
+        ]);
+    }
+    table.write(style, output)?;
+    Ok(())
+}
+
+/// Sweeps from a start to a stop frequency with a step, at a reference level.
+pub type Measure<'a> =
+    dyn FnMut(Frequency, Frequency, Frequency, Option<i8>) -> Result<Spectrum, Box<dyn error::Error>> + 'a;
+
+/// Parameters of a calibration run against an external reference signal.
+pub struct CalRunParams {
+    /// Frequencies and powers the reference signal is set to, measured in order.
+    pub points: Vec<ReferencePoint>,
+    /// Span measured around the frequency of each point.
+    pub span: Frequency,
+    pub fstep: Frequency,
+    pub ref_level: Option<i8>,
+    /// Number of sweeps averaged at each point.
+    pub sweeps: usize,
+    /// Wait for Enter before measuring each point, so the reference can be set.
+    pub prompt: bool,
+}
+
+/// Measures the reference signal at each point of `params`, with `measure` sweeping from a start to a stop frequency
+/// with a step and a reference level, and returns the correction profile of the measurements.
+///
+/// The power of a point is the peak of the span around its frequency, averaged over the sweeps. The prompts and the
+/// offset of each point are written to `notes`.
+pub fn run_calibration(
+    measure: &mut Measure,
+    params: &CalRunParams,
+    input: &mut dyn io::BufRead,
+    notes: &mut dyn io::Write,
+) -> Result<CorrectionProfile, Box<dyn error::Error>> {
+    let mut corrections = Vec::with_capacity(params.points.len());
+    for point in &params.points {
+        if params.prompt {
+            let message = format!("Set the reference to {:.4} at {:.1}", point.frequency, point.power);
+            wait_for_enter(&message, input, notes)?;
+        }
+
+        let (fstart, fstop) = (point.frequency - params.span / 2.0, point.frequency + params.span / 2.0);
+        let mut peaks = Vec::with_capacity(params.sweeps);
+        for _ in 0..params.sweeps {
+            let spectrum = measure(fstart, fstop, params.fstep, params.ref_level)?;
+            let peak = spectrum.powers().max_by(f64::total_cmp);
+            peaks.push(Dbm::new(peak.ok_or("The sweep returned no points")?));
+        }
+        let measured = Dbm::mean(peaks).ok_or("No sweeps to measure the reference")?;
+
+        let correction = Correction::new(point.frequency, point.power, measured);
+        writeln!(
+            notes,
+            "{:.4} at {:.1}: measured {:.2}, offset {:+.2} dB",
+            point.frequency,
+            point.power,
+            measured,
+            correction.offset()
+        )?;
+        corrections.push(correction);
+    }
+    Ok(CorrectionProfile::new(corrections))
+}
+
+/// Parameters of a linearity test with a single-tone source.
+pub struct LinearityParams {
+    /// Frequencies the tone is set to, measured in order.
+    pub tones: Vec<Frequency>,
+    /// Span measured around each tone.
+    pub span: Frequency,
+    pub fstep: Frequency,
+    pub ref_level: Option<i8>,
+    /// Largest deviation of the peak from the tone that passes.
+    pub tolerance: Frequency,
+    /// Wait for Enter before measuring each tone, so the source can be set.
+    pub prompt: bool,
+}
+
+/// Measures a single-tone source at each tone of `params`, with `measure`, and prints the deviation of the reported
+/// peak from the tone, one row per tone.
+///
+/// Fails if the peak of any tone deviates more than the tolerance, after measuring all.
+pub fn linearity(
+    measure: &mut Measure,
+    params: &LinearityParams,
+    input: &mut dyn io::BufRead,
+    notes: &mut dyn io::Write,
+    style: &Style,
+    output: &mut dyn io::Write,
+) -> Result<(), Box<dyn error::Error>> {
+    let mut table = Table::new()
+        .column("tone (MHz)", Align::Right, 0)
+        .column("peak (MHz)", Align::Right, 0)
+        .column("deviation (kHz)", Align::Right, 0)
+        .column("deviation (ppm)", Align::Right, 0)
+        .column("power (dBm)", Align::Right, 0)
+        .column("status", Align::Left, 6);
+    let mut failed = 0;
+    for tone in &params.tones {
+        if params.prompt {
+            wait_for_enter(&format!("Set the tone to {:.4}", tone), input, notes)?;
+        }
+
+        let spectrum = measure(
+            *tone - params.span / 2.0,
+            *tone + params.span / 2.0,
+            params.fstep,
+            params.ref_level,
+        )?;
+        let (peak, power) = spectrum
+            .iter()
+            .copied()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .ok_or("The sweep returned no points")?;
+        let deviation = Frequency::from_hz(peak) - *tone;
+        let passed = deviation.hz().abs() <= params.tolerance.hz();
+        if !passed {
+            failed += 1;
+        }
+        table.row(vec![
+            format!("{:.4}", tone.mhz()),
+            format!("{:.4}", peak / 1e6),
+            format!("{:+.1}", deviation.khz()),
+            format!("{:+.1}", deviation / *tone * 1e6),
+            format!("{:.1}", power),
+            match passed {
+                true => "ok",
+                false => "failed",
+            }
+            .to_string(),
+        ]);
+    }
+    table.write(style, output)?;
+
+    match failed {
+        0 => Ok(()),
+        failed => Err(format!(
+            "The peak of {} of {} tones is outside of the tolerance",
+            failed,
+            params.tones.len()
+        )
+        .into()),
+    }
+}
+
+/// Writes `message` to `notes` and waits for a line of `input`.
+fn wait_for_enter(message: &str, input: &mut dyn io::BufRead, notes: &mut dyn io::Write) -> io::Result<()> {
+    writeln!(notes, "{}, then press Enter", message)?;
+    input.read_line(&mut String::new())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use sa430::{
+        channel::fixtures::MockChannel,
+        frame::{self, Command},
+    };
+
+    fn a_calibrated_device() -> Sa430 {
+        let mut channel = MockChannel::new();
+        let data = [
+            frame::fixture::PROG_HEADER_DATA,
+            frame::fixture::CALIBRATION_DATA_1,
+            frame::fixture::CALIBRATION_DATA_2,
+            frame::fixture::CALIBRATION_DATA_3,
+            frame::fixture::CALIBRATION_DATA_4,
+            frame::fixture::CALIBRATION_DATA_5,
+            frame::fixture::CALIBRATION_DATA_6,
+            frame::fixture::CALIBRATION_DATA_7,
+        ];
+        for chunk in data {
+            channel.add_response(&frame::fixture::an_ack_response(Command::FlashRead).to_bytes());
+            channel.add_response(&frame::fixture::a_read_flash_response(chunk).to_bytes());
+        }
+        Sa430::new(Box::new(channel))
+    }
+
+    #[test]
+    fn given_a_device_when_export_gains_as_csv_then_print_the_gain_tables() {
+        let mut output = Vec::new();
+        let mut device = a_calibrated_device();
+
+        export_gains(&mut device, GainsFormat::Csv, ',', &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.lines().count(), 25);
+        assert!(output.starts_with("range,f_start_hz,f_stop_hz,table,ref_level_index,"));
+    }
+
+    #[test]
+    fn given_a_device_when_export_gains_as_json_then_print_the_gain_tables() {
+        let mut output = Vec::new();
+        let mut device = a_calibrated_device();
+
+        export_gains(&mut device, GainsFormat::Json, ',', &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.matches("\"ref_level_index\"").count(), 24);
+    }
+
+    #[test]
+    fn given_a_device_when_noise_floor_then_print_a_floor_per_ref_level() {
+        let mut output = Vec::new();
+        let mut device = a_calibrated_device();
+
+        noise_floor(
+            &mut device,
+            Frequency::from_mhz(868.0),
+            Frequency::from_mhz(868.6),
+            Frequency::from_khz(100.0),
+            &Style::plain(),
+            &mut output,
+        )
+        .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 11);
+        assert!(lines[2].starts_with("            -70 |"));
+    }
+
+    #[test]
+    fn given_a_gains_file_when_load_gains_then_override_the_device_calibration() {
+        let mut device = a_calibrated_device();
+        let path = std::env::temp_dir().join(format!("sa430-gains-{}.csv", std::process::id()));
+        std::fs::write(&path, "2,0,0,7,5,1,2,3,4,5,6,7,8\n").unwrap();
+
+        load_gains(&mut device, &path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let gain = &device.calibration().unwrap().frq_gains_tables[2][7];
+        assert_eq!(gain.ref_level_index(), 5);
+        assert_eq!(gain.gains(), &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0]);
+    }
+
+    fn some_params(prompt: bool) -> CalRunParams {
+        CalRunParams {
+            points: vec!["868MHz@-30".parse().unwrap(), "915MHz@-50".parse().unwrap()],
+            span: Frequency::from_khz(200.0),
+            fstep: Frequency::from_khz(50.0),
+            ref_level: Some(-35),
+            sweeps: 2,
+            prompt,
+        }
+    }
+
+    /// Measures a tone 2 dB below the reference of 868 MHz and 4 dB below the one of 915 MHz, at the center.
+    fn measure_a_tone(
+        fstart: Frequency,
+        fstop: Frequency,
+        _: Frequency,
+        _: Option<i8>,
+    ) -> Result<Spectrum, Box<dyn error::Error>> {
+        let center = (fstart + fstop) / 2.0;
+        let peak = match center.mhz().round() as u32 {
+            868 => -32.0,
+            _ => -54.0,
+        };
+        Ok(Spectrum::new(vec![
+            (fstart.hz(), -100.0),
+            (center.hz(), peak),
+            (fstop.hz(), -100.0),
+        ]))
+    }
+
+    #[test]
+    fn given_reference_points_when_run_calibration_then_return_the_offset_of_each_point() {
+        let mut notes = Vec::new();
+        let mut input = "\n\n".as_bytes();
+
+        let profile = run_calibration(&mut measure_a_tone, &some_params(true), &mut input, &mut notes).unwrap();
+
+        let offsets: Vec<f64> = profile.corrections().iter().map(|c| c.offset()).collect();
+        assert_eq!(offsets, [2.0, 4.0]);
+        assert_eq!(
+            String::from_utf8(notes).unwrap(),
+            "Set the reference to 868.0000 MHz at -30.0 dBm, then press Enter\n\
+             868.0000 MHz at -30.0 dBm: measured -32.00 dBm, offset +2.00 dB\n\
+             Set the reference to 915.0000 MHz at -50.0 dBm, then press Enter\n\
+             915.0000 MHz at -50.0 dBm: measured -54.00 dBm, offset +4.00 dB\n"
+        );
+    }
+
+    #[test]
+    fn given_no_prompt_when_run_calibration_then_measure_without_reading_the_input() {
+        let mut sweeps = 0;
+        let mut measure = |fstart, fstop, fstep, ref_level| {
+            sweeps += 1;
+            measure_a_tone(fstart, fstop, fstep, ref_level)
+        };
+
+        run_calibration(&mut measure, &some_params(false), &mut io::empty(), &mut io::sink()).unwrap();
+
+        assert_eq!(sweeps, 4);
+    }
+
+    #[test]
+    fn given_a_drifting_peak_when_linearity_then_report_each_deviation_and_fail() {
+        let params = LinearityParams {
+            tones: vec![Frequency::from_mhz(433.92), Frequency::from_mhz(868.0)],
+            span: Frequency::from_khz(200.0),
+            fstep: Frequency::from_khz(10.0),
+            ref_level: None,
+            tolerance: Frequency::from_khz(15.0),
+            prompt: false,
+        };
+        let mut measure = |fstart: Frequency, fstop: Frequency, _: Frequency, _: Option<i8>| {
+            let center = (fstart + fstop) / 2.0;
+            let drift = center * 20e-6;
+            Ok(Spectrum::new(vec![
+                (fstart.hz(), -100.0),
+                ((center + drift).hz(), -40.0),
+                (fstop.hz(), -100.0),
+            ]))
+        };
+        let mut output = Vec::new();
+
+        let result = linearity(
+            &mut measure,
+            &params,
+            &mut io::empty(),
+            &mut io::sink(),
+            &Style::plain(),
+            &mut output,
+        );
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "The peak of 1 of 2 tones is outside of the tolerance"
+        );
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines[2],
+            "  433.9200 |   433.9287 |            +8.7 |           +20.0 |       -40.0 | ok"
+        );
+        assert!(lines[3].contains("+17.4") && lines[3].ends_with("| failed"));
+    }
+}
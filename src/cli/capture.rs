@@ -1,24 +1,479 @@
-use std::{error, io};
+use std::{
+    error,
+    fs::File,
+    io,
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime},
+};
 
+use clap::ValueEnum;
+
+use sa430::cancel::CancellationToken;
 use sa430::device::Sa430;
+use sa430::dsp::{channelize, ChannelPlan, Trigger};
+use sa430::formats::{binary::BinarySink, csv::CsvSink, ndjson::NdjsonSink};
+use sa430::frequency::Frequency;
+use sa430::location::Location;
+use sa430::log::Level;
+use sa430::reference::CorrectionProfile;
+use sa430::sink::{Sink, SinkRegistry, SweepRecord};
+use sa430::spectrum::{BinAlignment, Interpolation, Spectrum};
+use sa430::storage::is_storage_full;
+use sa430::timestamp::TimeZone;
+
+use super::alert::{alert, alert_message, AlertAction, AlertEvent};
+use super::log;
+use super::report::{SessionReport, DEFAULT_OCCUPANCY_THRESHOLD};
+use super::session::{prune_sessions, RetentionPolicy};
+use super::table::{Align, Style, Table};
+#[cfg(feature = "net")]
+use super::webhook::Webhook;
+
+pub const DEFAULT_REF_LEVEL: i8 = -35;
+
+/// Format of the sweeps written by a capture.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// CSV, with a header per sweep and frequencies in MHz.
+    #[default]
+    Csv,
+
+    /// A JSON object per line, with frequencies in Hz.
+    Ndjson,
+
+    /// Binary records, see `sa430::formats::binary`.
+    Binary,
+}
+
+impl OutputFormat {
+    /// Creates the sink writing sweeps in this format to `output`, using `delimiter` for CSV, with the ISO 8601
+    /// timestamps of the sweeps in the time zone `timestamps`, if set, for CSV and NDJSON.
+    pub fn sink<'a, W: io::Write + Send + 'a>(
+        self,
+        output: W,
+        delimiter: char,
+        timestamps: Option<TimeZone>,
+    ) -> Result<Box<dyn Sink + Send + 'a>, Box<dyn error::Error>> {
+        Ok(match (self, timestamps) {
+            (OutputFormat::Csv, Some(zone)) => Box::new(CsvSink::new(output, delimiter)?.with_timestamps(zone)),
+            (OutputFormat::Csv, None) => Box::new(CsvSink::new(output, delimiter)?),
+            (OutputFormat::Ndjson, Some(zone)) => Box::new(NdjsonSink::new(output).with_timestamps(zone)),
+            (OutputFormat::Ndjson, None) => Box::new(NdjsonSink::new(output)),
+            (OutputFormat::Binary, _) => Box::new(BinarySink::new(output)),
+        })
+    }
+}
 
-const DEFAULT_REF_LEVEL: i8 = -35;
+/// Sessions removed to free space when the storage fills.
+pub struct Retention {
+    /// Directory holding the sealed sessions.
+    pub root: PathBuf,
+    pub policy: RetentionPolicy,
+}
 
 pub struct CaptureParams {
-    pub fstart: f64,
-    pub fstop: f64,
-    pub fstep: f64,
+    pub fstart: Frequency,
+    pub fstop: Frequency,
+    pub fstep: Frequency,
     pub ref_level: Option<i8>,
+    /// Offsets added to the measured powers, from a calibration against an external reference.
+    pub corrections: Option<CorrectionProfile>,
+    pub channel_plan: Option<ChannelPlan>,
+    /// Number of sweeps to capture, 0 captures until interrupted.
+    pub count: usize,
+    /// Time after which the capture stops, whatever the number of sweeps captured.
+    pub duration: Option<Duration>,
+    pub trigger: Option<Trigger>,
+    pub location: Option<Location>,
+    /// Labels of the capture, like `site=roof`, written with the sweeps and in the report.
+    pub tags: Vec<String>,
+    /// Note on the capture, written with the sweeps and in the report.
+    pub note: Option<String>,
+    pub cancel: CancellationToken,
+    pub style: Style,
+    /// Format of the sweeps written to the output.
+    pub format: OutputFormat,
+    /// Column delimiter of the spectrum points.
+    pub delimiter: char,
+    /// Time zone of the ISO 8601 timestamp written with each sweep, none to leave it out.
+    pub timestamps: Option<TimeZone>,
+    /// Bring the spectrum points to a power of two count, printing the resulting grid before them.
+    pub alignment: Option<BinAlignment>,
+    /// Action run each time the trigger trips.
+    pub alert: Option<AlertAction>,
+    /// Webhook posted each time the trigger trips.
+    #[cfg(feature = "net")]
+    pub webhook: Option<Webhook>,
+    /// File where the summary of the session is written when the capture ends.
+    pub report: Option<PathBuf>,
+    /// Sessions pruned when the storage fills, none to keep them all.
+    pub retention: Option<Retention>,
+    /// Outputs receiving each sweep, besides `output`, which can be changed while the capture runs. They are closed
+    /// when the capture ends.
+    pub sinks: SinkRegistry,
 }
 
-pub fn capture(_: &mut Sa430, params: &CaptureParams, output: &mut dyn io::Write) -> Result<(), Box<dyn error::Error>> {
-    write!(
-        output,
-        "Capturing data from {:.2} MHz to {:.2} MHz with step of {:.2} MHz and a reference level of {} dBm...",
+/// Captures the sweeps, writing them to `output` in the format of `params`, or the channel table if there's a channel
+/// plan, and to the sinks of `params`.
+///
+/// The capture notes are written to `output` for CSV, and to stderr for the binary formats, so they don't break the
+/// stream.
+pub fn capture(
+    device: &mut Sa430,
+    params: &mut CaptureParams,
+    output: &mut (dyn io::Write + Send),
+) -> Result<(), Box<dyn error::Error>> {
+    let mut stderr = io::stderr();
+    let notes: &mut dyn io::Write = match params.format {
+        OutputFormat::Csv => &mut *output,
+        OutputFormat::Ndjson | OutputFormat::Binary => &mut stderr,
+    };
+    writeln!(
+        notes,
+        "Capturing data from {:.2} to {:.2} with step of {:.2} and a reference level of {} dBm...",
         params.fstart,
         params.fstop,
         params.fstep,
         params.ref_level.unwrap_or(DEFAULT_REF_LEVEL)
     )?;
-    todo!("Implement capture command")
+    if let Some(location) = &params.location {
+        writeln!(notes, "# location: {}", location)?;
+    }
+    for tag in &params.tags {
+        writeln!(notes, "# tag: {}", tag)?;
+    }
+    if let Some(note) = &params.note {
+        writeln!(notes, "# note: {}", note)?;
+    }
+
+    let threshold = params
+        .channel_plan
+        .as_ref()
+        .map_or(DEFAULT_OCCUPANCY_THRESHOLD, |plan| plan.threshold());
+    let mut report = SessionReport::new(threshold);
+    report.set_labels(&params.tags, params.note.as_deref());
+
+    let start = Instant::now();
+    let mut captured = 0;
+    let mut sequence = 0;
+    while params.count == 0 || captured < params.count {
+        if params.duration.is_some_and(|duration| start.elapsed() >= duration) {
+            log::log(
+                Level::Info,
+                &format!("Capture stopped after its duration, with {} sweeps", captured),
+            );
+            break;
+        }
+        if params.cancel.is_cancelled() {
+            match params.format {
+                OutputFormat::Csv => writeln!(output, "Capture cancelled")?,
+                OutputFormat::Ndjson | OutputFormat::Binary => eprintln!("Capture cancelled"),
+            }
+            log::log(Level::Info, &format!("Capture cancelled after {} sweeps", captured));
+            finish(&report, params)?;
+            return device.abort();
+        }
+
+        let spectrum = sweep(device, params.fstart, params.fstop, params.fstep, params.ref_level)?;
+        let spectrum = match &params.corrections {
+            Some(profile) => profile.apply(&spectrum),
+            None => spectrum,
+        };
+        captured += 1;
+        report.add(&spectrum);
+
+        let sweeps = match &mut params.trigger {
+            Some(trigger) => {
+                let was_active = trigger.is_active();
+                let peak = trigger.condition().peak(&spectrum);
+                let sweeps = trigger.push(spectrum);
+                if let (false, true, Some((peak_frequency, peak_power))) = (was_active, trigger.is_active(), peak) {
+                    let event = AlertEvent {
+                        serial: device.serial_number()?,
+                        condition: *trigger.condition(),
+                        peak_frequency,
+                        peak_power,
+                        timestamp: SystemTime::now(),
+                    };
+                    log::log(
+                        Level::Info,
+                        &format!(
+                            "Trigger tripped: {:.4} MHz at {:.2} dBm",
+                            peak_frequency / 1e6,
+                            peak_power
+                        ),
+                    );
+                    notify_trip(&event, params)?;
+                    report.add_violation();
+                }
+                sweeps
+            }
+            None => vec![spectrum],
+        };
+        for spectrum in sweeps {
+            let record = sweep_record(sequence, spectrum, params.alignment);
+            sequence += 1;
+            let written = match &params.channel_plan {
+                Some(plan) => print_channels(&record.spectrum, plan, &params.style, output),
+                None => params
+                    .format
+                    .sink(&mut *output, params.delimiter, params.timestamps)
+                    .and_then(|mut sink| sink.write(&record)),
+            };
+            let mut storage_full = match written {
+                Ok(()) => false,
+                Err(error) if is_storage_full(&*error) => true,
+                Err(error) => return Err(error),
+            };
+            for (_, error) in params.sinks.publish(&record) {
+                log::report(Level::Warn, &format!("Output removed: {}", error));
+                storage_full |= is_storage_full(&*error);
+            }
+            if storage_full {
+                return stop_storage_full(&report, params, captured);
+            }
+        }
+    }
+
+    finish(&report, params)
+}
+
+/// Closes the sinks and writes the report, when the capture ends.
+fn finish(report: &SessionReport, params: &CaptureParams) -> Result<(), Box<dyn error::Error>> {
+    for (_, error) in params.sinks.close() {
+        log::report(Level::Warn, &format!("Failed to close output: {}", error));
+    }
+    write_report(report, params)
+}
+
+/// Stops the capture when the storage is full: alerts, closes the sinks and writes the report as far as possible, and
+/// prunes the oldest sessions if there's a retention policy, so the next capture has room.
+fn stop_storage_full(
+    report: &SessionReport,
+    params: &CaptureParams,
+    captured: usize,
+) -> Result<(), Box<dyn error::Error>> {
+    let message = format!("Storage full, capture stopped after {} sweeps", captured);
+    log::report(Level::Error, &message);
+    if let Some(Err(error)) = params
+        .alert
+        .map(|action| alert_message(action, &message, &mut io::stderr()))
+    {
+        log::report(Level::Warn, &format!("Failed to alert: {}", error));
+    }
+    if let Err(error) = finish(report, params) {
+        log::report(Level::Warn, &format!("Failed to finish the capture: {}", error));
+    }
+    if let Some(retention) = &params.retention {
+        match prune_sessions(&retention.root, &retention.policy, false) {
+            Ok(removed) => {
+                for session in removed {
+                    log::report(Level::Info, &format!("Session removed: {}", session.path.display()));
+                }
+            }
+            Err(error) => log::report(Level::Warn, &format!("Failed to prune the sessions: {}", error)),
+        }
+    }
+    Err("The storage is full".into())
+}
+
+/// Creates the record of a sweep, aligned to a power of two number of points if requested.
+fn sweep_record(sequence: u64, spectrum: Spectrum, alignment: Option<BinAlignment>) -> SweepRecord {
+    match alignment {
+        Some(alignment) => {
+            let (spectrum, window) = spectrum.align_to_power_of_two(alignment, Interpolation::Linear);
+            let mut record = SweepRecord::new(sequence, spectrum);
+            record.window = Some(window);
+            record
+        }
+        None => SweepRecord::new(sequence, spectrum),
+    }
+}
+
+/// Writes the summary of the session to the report file, if any.
+fn write_report(report: &SessionReport, params: &CaptureParams) -> Result<(), Box<dyn error::Error>> {
+    match &params.report {
+        Some(path) => report.write(&Style::plain(), &mut File::create(path)?),
+        None => Ok(()),
+    }
+}
+
+/// Runs the alert actions of a trip, a failed webhook is reported without stopping the capture.
+fn notify_trip(event: &AlertEvent, params: &CaptureParams) -> Result<(), Box<dyn error::Error>> {
+    if let Some(action) = params.alert {
+        alert(action, event, &mut io::stderr())?;
+    }
+    #[cfg(feature = "net")]
+    if let Some(Err(error)) = params.webhook.as_ref().map(|webhook| webhook.post(event)) {
+        log::report(Level::Warn, &error.to_string());
+    }
+    Ok(())
+}
+
+/// Sweeps from `fstart` to `fstop` with a step of `fstep`, at the reference level `ref_level` or the default one.
+pub fn sweep(
+    device: &mut Sa430,
+    fstart: Frequency,
+    fstop: Frequency,
+    fstep: Frequency,
+    ref_level: Option<i8>,
+) -> Result<Spectrum, Box<dyn error::Error>> {
+    device.capture(fstart, fstop, fstep, ref_level.unwrap_or(DEFAULT_REF_LEVEL))
+}
+
+/// Prints the power and occupancy of each channel of the plan.
+fn print_channels(
+    spectrum: &Spectrum,
+    plan: &ChannelPlan,
+    style: &Style,
+    output: &mut dyn io::Write,
+) -> Result<(), Box<dyn error::Error>> {
+    let mut table = Table::new()
+        .column("channel", Align::Left, 0)
+        .column("center (MHz)", Align::Right, 0)
+        .column("mean (dBm)", Align::Right, 10)
+        .column("peak (dBm)", Align::Right, 10)
+        .column("occupancy", Align::Right, 0);
+    for channel in channelize(spectrum, plan) {
+        table.row(vec![
+            channel.name.to_string(),
+            format!("{:.4}", channel.center / 1e6),
+            channel.mean_dbm.map_or("-".to_string(), |p| format!("{:.2}", p)),
+            channel.peak_dbm.map_or("-".to_string(), |p| format!("{:.2}", p)),
+            format!("{:.1}%", channel.occupancy * 100.0),
+        ]);
+    }
+    table.write(style, output)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use sa430::{
+        channel::fixtures::MockChannel,
+        frame::{fixture::an_ack_response, Command},
+    };
+
+    #[test]
+    fn given_a_cancelled_capture_when_capture_then_abort_and_return() {
+        let mut output = Vec::new();
+        let mut channel = MockChannel::new();
+        channel.add_response(&an_ack_response(Command::Sync).to_bytes());
+        let mut device = Sa430::new(Box::new(channel));
+        let mut params = CaptureParams {
+            fstart: Frequency::from_mhz(868.0),
+            fstop: Frequency::from_mhz(870.0),
+            fstep: Frequency::from_mhz(0.1),
+            ref_level: None,
+            corrections: None,
+            channel_plan: None,
+            count: 0,
+            duration: None,
+            trigger: None,
+            location: None,
+            tags: Vec::new(),
+            note: None,
+            cancel: CancellationToken::new(),
+            style: Style::plain(),
+            format: OutputFormat::Csv,
+            delimiter: ',',
+            timestamps: None,
+            alignment: None,
+            alert: None,
+            #[cfg(feature = "net")]
+            webhook: None,
+            report: None,
+            retention: None,
+            sinks: SinkRegistry::new(),
+        };
+        params.cancel.cancel();
+
+        capture(&mut device, &mut params, &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.ends_with("Capture cancelled\n"));
+    }
+
+    #[test]
+    fn given_an_elapsed_duration_when_capture_then_stop_without_sweeping() {
+        let mut output = Vec::new();
+        let mut device = Sa430::new(Box::new(MockChannel::new()));
+        let mut params = CaptureParams {
+            fstart: Frequency::from_mhz(868.0),
+            fstop: Frequency::from_mhz(870.0),
+            fstep: Frequency::from_mhz(0.1),
+            ref_level: None,
+            corrections: None,
+            channel_plan: None,
+            count: 0,
+            duration: Some(Duration::ZERO),
+            trigger: None,
+            location: None,
+            tags: Vec::new(),
+            note: None,
+            cancel: CancellationToken::new(),
+            style: Style::plain(),
+            format: OutputFormat::Csv,
+            delimiter: ',',
+            timestamps: None,
+            alignment: None,
+            alert: None,
+            #[cfg(feature = "net")]
+            webhook: None,
+            report: None,
+            retention: None,
+            sinks: SinkRegistry::new(),
+        };
+
+        capture(&mut device, &mut params, &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.lines().count(), 1);
+    }
+
+    #[test]
+    fn given_an_alignment_when_sweep_record_then_keep_the_grid() {
+        let spectrum = Spectrum::from_sweep(868.0e6, 0.1e6, &[-90.0, -72.5, -80.0]);
+
+        let record = sweep_record(2, spectrum, Some(BinAlignment::Pad));
+
+        assert_eq!(record.sequence, 2);
+        assert_eq!(record.spectrum.len(), 4);
+        assert_eq!(record.window.map(|window| window.measured), Some(3));
+    }
+
+    #[test]
+    fn given_each_format_when_sink_then_write_the_sweep() {
+        let record = SweepRecord::new(0, Spectrum::from_sweep(868.0e6, 0.1e6, &[-90.0]));
+
+        for (format, expected) in [
+            (
+                OutputFormat::Csv,
+                &b"frequency (MHz),power (dBm)\n868.0000,-90.00\n"[..],
+            ),
+            (OutputFormat::Ndjson, &b"{\"sequence\":0,"[..]),
+            (OutputFormat::Binary, &b"SA43"[..]),
+        ] {
+            let mut output = Vec::new();
+            format.sink(&mut output, ',', None).unwrap().write(&record).unwrap();
+            assert!(output.starts_with(expected), "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn given_a_channel_plan_when_print_channels_then_print_a_channel_per_line() {
+        let mut output = Vec::new();
+        let spectrum = Spectrum::from_sweep(868.0e6, 50.0e3, &[-100.0, -100.0, -60.0, -100.0]);
+        let plan = ChannelPlan::uniform("test", 868.1e6, 1.0e6, 2, 125.0e3);
+
+        print_channels(&spectrum, &plan, &Style::plain(), &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[2], "0       |     868.1000 |     -64.77 |     -60.00 |     33.3%");
+        assert_eq!(lines[3], "1       |     869.1000 |          - |          - |      0.0%");
+    }
 }
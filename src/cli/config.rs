@@ -0,0 +1,103 @@
+use std::{
+    env, error,
+    fs::File,
+    io,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+
+use sa430::bands::Bands;
+use sa430::formats::gains::check_gains_csv;
+
+/// Variable overriding the serial port of the commands using a device.
+pub const PORT_VAR: &str = "SA430_PORT";
+
+/// Prefix of the ports that are a usbfs file descriptor of the device inherited from the caller, like `usbfd:7`.
+pub const USB_FD_PREFIX: &str = "usbfd:";
+
+/// Variable overriding the band preset of the commands with a `--band` option.
+pub const PRESET_VAR: &str = "SA430_PRESET";
+
+/// Variable overriding the format of the captured sweeps.
+pub const FORMAT_VAR: &str = "SA430_FORMAT";
+
+/// Variable setting the log file, like `--log-file`.
+pub const LOG_FILE_VAR: &str = "SA430_LOG_FILE";
+
+/// Variable selecting how `watch` monitors the devices.
+pub const MONITOR_VAR: &str = "SA430_MONITOR";
+
+/// Variable overriding the configuration directory.
+pub const CONFIG_DIR_VAR: &str = "SA430_CONFIG_DIR";
+
+/// Bands file loaded from the configuration directory when no `--bands-file` is given.
+pub const BANDS_FILE_NAME: &str = "bands.csv";
+
+/// Returns the configuration directory: `$SA430_CONFIG_DIR`, `$XDG_CONFIG_HOME/sa430` or `$HOME/.config/sa430`.
+pub fn config_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os(CONFIG_DIR_VAR).filter(|dir| !dir.is_empty()) {
+        return Some(PathBuf::from(dir));
+    }
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("sa430"))
+}
+
+/// Returns the bands file of the configuration directory, if it exists.
+pub fn default_bands_file() -> Option<PathBuf> {
+    config_dir()
+        .map(|dir| dir.join(BANDS_FILE_NAME))
+        .filter(|path| path.is_file())
+}
+
+/// Kinds of configuration files checked by `config check`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ConfigKind {
+    /// Band presets, as read by `--bands-file`.
+    Bands,
+
+    /// Gain tables, as read by `--gains-file`.
+    Gains,
+}
+
+/// Checks the configuration file at `path`, printing every problem found with its line and column. Fails if there is
+/// any problem.
+pub fn check_config(kind: ConfigKind, path: &Path, output: &mut dyn io::Write) -> Result<(), Box<dyn error::Error>> {
+    let reader = BufReader::new(File::open(path)?);
+    let problems: Vec<String> = match kind {
+        ConfigKind::Bands => Bands::check_csv(reader)?.iter().map(ToString::to_string).collect(),
+        ConfigKind::Gains => check_gains_csv(reader)?.iter().map(ToString::to_string).collect(),
+    };
+
+    for problem in &problems {
+        writeln!(output, "{}: {}", path.display(), problem)?;
+    }
+    match problems.len() {
+        0 => Ok(writeln!(output, "{}: ok", path.display())?),
+        count => Err(format!("{} problems found in {}", count, path.display()).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_a_bands_file_with_problems_when_check_config_then_print_them_and_fail() {
+        let path = std::env::temp_dir().join(format!("sa430-config-{}.csv", std::process::id()));
+        std::fs::write(&path, "site-a,868.0,868.6\nsite-b,868.6,868.0\nsite-a,433,434\n").unwrap();
+        let mut output = Vec::new();
+
+        let result = check_config(ConfigKind::Bands, &path, &mut output);
+        std::fs::remove_file(&path).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines[0].ends_with(".csv: Line 2: fstart_mhz must be lower than fstop_mhz"));
+        assert!(lines[1].ends_with(".csv: Line 3: the band name is already used on line 1"));
+        assert!(result.unwrap_err().to_string().starts_with("2 problems found in "));
+    }
+}
@@ -0,0 +1,165 @@
+use sa430::device::{ProtocolError, Sa430};
+use sa430::formats::zip::ZipWriter;
+use sa430::frame::FrameError;
+use sa430::timestamp::{format_iso8601, TimeZone};
+
+use std::{
+    error, fmt, fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::doctor::{find_driver, find_udev_rule, SystemPaths};
+
+/// Files with the details of the operating system, included when present.
+const OS_FILES: [&str; 2] = ["/etc/os-release", "/proc/version"];
+
+/// Returns whether `error` is an unexpected failure of the link with the device, worth a crash report: a protocol
+/// error, a corrupt frame or an I/O error, like a timeout.
+pub fn is_protocol_failure(error: &(dyn error::Error + 'static)) -> bool {
+    error.is::<ProtocolError>() || error.is::<FrameError>() || error.is::<io::Error>()
+}
+
+/// Writes a crash report of the failure of a command with `device` to a new zip file in `dir`, returning its path.
+pub fn write_crash_report(
+    device: &mut Sa430,
+    port: &str,
+    failure: &dyn error::Error,
+    paths: &SystemPaths,
+    dir: &Path,
+) -> Result<PathBuf, Box<dyn error::Error>> {
+    let now = SystemTime::now();
+    let seconds = now.duration_since(UNIX_EPOCH)?.as_secs();
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("sa430-crash-{}.zip", seconds));
+    crash_bundle(device, port, failure, paths, now, fs::File::create(&path)?)?;
+    Ok(path)
+}
+
+/// Writes the crash bundle of the failure of a command with `device` as a zip archive, with the error, the last frames
+/// exchanged, the device information, the link stats and the details of the system.
+///
+/// The device is queried after the failure, so the frames are taken and its last error is read first, and the queries
+/// that fail are reported in place of their values.
+pub fn crash_bundle<W: io::Write>(
+    device: &mut Sa430,
+    port: &str,
+    failure: &dyn error::Error,
+    paths: &SystemPaths,
+    now: SystemTime,
+    output: W,
+) -> Result<W, Box<dyn error::Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let error = format!(
+        "sa430 {}\nCommand: sa430 {}\nError: {}\n",
+        env!("CARGO_PKG_VERSION"),
+        args.join(" "),
+        failure
+    );
+
+    let transcript: String = device
+        .recent_frames()
+        .iter()
+        .map(|record| format!("{} {}\n", format_iso8601(record.time, TimeZone::Utc), record))
+        .collect();
+
+    let mut info = String::new();
+    info.push_str(&format!("Port: {}\n", port));
+    info.push_str(&format!("Last error: {}\n", describe(device.last_error())));
+    if device.is_busy() {
+        info.push_str(&format!("Recover: {}\n", describe(device.recover().map(|_| "OK"))));
+    }
+    info.push_str(&format!("IDN: {}\n", describe(device.idn())));
+    info.push_str(&format!("Serial number: {}\n", describe(device.serial_number())));
+    info.push_str(&format!("Core version: {}\n", describe(device.core_version())));
+    info.push_str(&format!("Spectrum version: {}\n", describe(device.spectrum_version())));
+
+    let stats = device.link_stats();
+    let link = format!(
+        "Frames sent: {}\nFrames received: {}\nReceive errors: {}\nCommands: {}\nRecoveries: {}\n",
+        stats.frames_sent, stats.frames_received, stats.receive_errors, stats.commands, stats.recoveries
+    );
+
+    let mut zip = ZipWriter::new(output);
+    zip.add("error.txt", error.as_bytes(), now)?;
+    zip.add("transcript.txt", transcript.as_bytes(), now)?;
+    zip.add("device.txt", info.as_bytes(), now)?;
+    zip.add("link.txt", link.as_bytes(), now)?;
+    zip.add("system.txt", system_details(port, paths).as_bytes(), now)?;
+    Ok(zip.finish()?)
+}
+
+/// Returns the OS, the udev rule of the SA430 and the driver bound to `port`.
+fn system_details(port: &str, paths: &SystemPaths) -> String {
+    let mut details = format!("OS: {} {}\n", std::env::consts::OS, std::env::consts::ARCH);
+    let udev_rule = find_udev_rule(&paths.udev_rules).map(|rule| rule.display().to_string());
+    details.push_str(&format!("udev rule: {}\n", udev_rule.as_deref().unwrap_or("not found")));
+    let driver = find_driver(&paths.sys_class_tty, port);
+    details.push_str(&format!("Driver: {}\n", driver.as_deref().unwrap_or("unknown")));
+    for file in OS_FILES {
+        if let Ok(contents) = fs::read_to_string(file) {
+            details.push_str(&format!("\n# {}\n{}", file, contents));
+        }
+    }
+    details
+}
+
+fn describe<T: fmt::Display>(result: Result<T, Box<dyn error::Error>>) -> String {
+    match result {
+        Ok(value) => value.to_string(),
+        Err(error) => format!("failed: {}", error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use sa430::{
+        channel::fixtures::MockChannel,
+        frame::{
+            fixture::{a_get_idn_response, a_get_serial_number_response, an_ack_response, an_error_response},
+            Command, ErrorCode,
+        },
+    };
+
+    fn contains(archive: &[u8], text: &str) -> bool {
+        archive.windows(text.len()).any(|window| window == text.as_bytes())
+    }
+
+    #[test]
+    fn given_a_failure_when_crash_bundle_then_write_the_error_device_and_system_files() {
+        let mut channel = MockChannel::new();
+        channel.add_response(&an_error_response(ErrorCode::UnknownCmd).to_bytes());
+        channel.add_response(&an_ack_response(Command::GetLastError).to_bytes());
+        channel.add_response(&an_error_response(ErrorCode::UnknownCmd).to_bytes());
+        channel.add_response(&an_ack_response(Command::GetIdn).to_bytes());
+        channel.add_response(&a_get_idn_response().to_bytes());
+        channel.add_response(&an_ack_response(Command::GetSerialNumber).to_bytes());
+        channel.add_response(&a_get_serial_number_response().to_bytes());
+        let mut device = Sa430::new(Box::new(channel));
+        device.blink().unwrap_err();
+        let failure = io::Error::new(io::ErrorKind::TimedOut, "Operation timed out");
+        let paths = SystemPaths {
+            udev_rules: vec![],
+            sys_class_tty: PathBuf::from("/nonexistent"),
+        };
+
+        let archive = crash_bundle(&mut device, "/dev/ttyACM0", &failure, &paths, UNIX_EPOCH, Vec::new()).unwrap();
+
+        assert!(is_protocol_failure(&failure));
+        assert!(contains(&archive, "error.txt"));
+        assert!(contains(&archive, "Error: Operation timed out\n"));
+        assert!(contains(
+            &archive,
+            &format!("Z < {}\nPK", an_error_response(ErrorCode::UnknownCmd).hexdump())
+        ));
+        assert!(contains(
+            &archive,
+            "Port: /dev/ttyACM0\nLast error: Unknown command\nIDN: Texas Instruments"
+        ));
+        assert!(contains(&archive, "Serial number: 2312\nCore version: failed: "));
+        assert!(contains(&archive, "Frames sent: "));
+        assert!(contains(&archive, "udev rule: not found\nDriver: unknown\n"));
+    }
+}
@@ -0,0 +1,125 @@
+use std::{error, fs, io, path::Path};
+
+use sa430::frame::{hexdump, split_stream, Command, Frame, StreamItem};
+
+/// Prints each frame of the bytes in `input`, a hex string or a file with a hex string or raw bytes, annotated field
+/// by field, with the bytes outside of frames.
+pub fn decode(input: &str, output: &mut dyn io::Write) -> Result<(), Box<dyn error::Error>> {
+    let bytes = match Path::new(input).is_file() {
+        true => read_file(Path::new(input))?,
+        false => parse_hex(input)?,
+    };
+
+    for (offset, item) in offsets(&split_stream(&bytes)) {
+        match item {
+            StreamItem::Frame(frame) => {
+                writeln!(output, "{:06X}  frame    {}", offset, hexdump(frame))?;
+                if let Ok(frame) = Frame::from_bytes(frame) {
+                    write_details(&frame, output)?;
+                }
+            }
+            StreamItem::Corrupt(frame) => writeln!(output, "{:06X}  corrupt  {}", offset, hexdump(frame))?,
+            StreamItem::Truncated(bytes) => writeln!(output, "{:06X}  partial  {}", offset, hex(bytes))?,
+            StreamItem::Garbage(bytes) => writeln!(output, "{:06X}  garbage  {}", offset, hex(bytes))?,
+        }
+    }
+    Ok(())
+}
+
+/// Returns the items with their offset in the stream.
+fn offsets<'a>(items: &'a [StreamItem<'a>]) -> impl Iterator<Item = (usize, &'a StreamItem<'a>)> {
+    items.iter().scan(0, |offset, item| {
+        let start = *offset;
+        *offset += match item {
+            StreamItem::Frame(bytes)
+            | StreamItem::Corrupt(bytes)
+            | StreamItem::Truncated(bytes)
+            | StreamItem::Garbage(bytes) => bytes.len(),
+        };
+        Some((start, item))
+    })
+}
+
+/// Prints the command description, the error code of error frames and the data as text, when printable.
+fn write_details(frame: &Frame, output: &mut dyn io::Write) -> Result<(), Box<dyn error::Error>> {
+    const INDENT: &str = "                 ";
+
+    writeln!(output, "{}{}", INDENT, frame.cmd())?;
+    if let Some(code) = frame.to_error_code() {
+        writeln!(output, "{}error {:X}: {}", INDENT, code, code)?;
+    } else if frame.cmd() != Command::FlashRead && is_text(frame.data()) {
+        writeln!(output, "{}text \"{}\"", INDENT, String::from_utf8_lossy(frame.data()))?;
+    }
+    Ok(())
+}
+
+fn is_text(data: &[u8]) -> bool {
+    data.len() > 1 && data.iter().all(|byte| byte.is_ascii_graphic() || *byte == b' ')
+}
+
+/// Reads a file with a hex string or, if it isn't one, raw bytes.
+fn read_file(path: &Path) -> Result<Vec<u8>, Box<dyn error::Error>> {
+    let data = fs::read(path)?;
+    match std::str::from_utf8(&data).ok().map(parse_hex) {
+        Some(Ok(bytes)) => Ok(bytes),
+        _ => Ok(data),
+    }
+}
+
+/// Parses a hex string, ignoring whitespace, `0x` prefixes and `:`, `,` or `-` separators.
+fn parse_hex(value: &str) -> Result<Vec<u8>, Box<dyn error::Error>> {
+    let digits: String = value
+        .split(|c: char| c.is_whitespace() || c == ':' || c == ',' || c == '-')
+        .map(|token| token.strip_prefix("0x").or(token.strip_prefix("0X")).unwrap_or(token))
+        .collect();
+
+    if digits.is_empty() || digits.len() % 2 != 0 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid hex string or missing file: {}", value).into());
+    }
+
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|error| error.into()))
+        .collect()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use sa430::frame::{fixture, ErrorCode};
+
+    #[test]
+    fn given_hex_strings_when_parse_hex_then_ignore_separators() {
+        assert_eq!(parse_hex("2a 00 01").unwrap(), vec![0x2A, 0x00, 0x01]);
+        assert_eq!(parse_hex("0x2A,0x00:ff-10").unwrap(), vec![0x2A, 0x00, 0xFF, 0x10]);
+        assert!(parse_hex("2A0").is_err());
+        assert!(parse_hex("zz").is_err());
+    }
+
+    #[test]
+    fn given_a_dump_when_decode_then_print_a_breakdown_per_frame() {
+        let mut output = Vec::new();
+        let error = fixture::an_error_response(ErrorCode::UnknownCmd).to_bytes();
+        let idn = fixture::a_get_idn_response().to_bytes();
+        let dump = [&[0x00][..], &error, &idn].concat();
+
+        decode(&hex(&dump), &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "000000  garbage  00");
+        assert!(lines[1].starts_with("000001  frame    magic 2A | len 2 | cmd GetLastError (06) | data 03 24 |"));
+        assert_eq!(lines[3], "                 error 0324: Unknown command");
+        assert!(lines[4].starts_with(&format!("{:06X}  frame    magic 2A |", 1 + error.len())));
+        assert!(lines[6].starts_with("                 text \""));
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    error, fs, io,
+    path::{Path, PathBuf},
+};
+
+use sa430::channel::{ChannelError, SerialPortChannel};
+use sa430::port::{Port, USB_PRODUCT_ID, USB_VENDOR_ID};
+
+use super::table::{Align, Style, Table};
+
+/// Driver expected to be bound to the SA430 USB serial interface.
+const EXPECTED_DRIVER: &str = "cdc_acm";
+
+/// System locations inspected by the doctor command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SystemPaths {
+    /// Directories with udev rules, in the order udev reads them.
+    pub udev_rules: Vec<PathBuf>,
+
+    /// Directory with a link per tty device, pointing to its sysfs node.
+    pub sys_class_tty: PathBuf,
+}
+
+impl Default for SystemPaths {
+    fn default() -> Self {
+        SystemPaths {
+            udev_rules: vec![
+                PathBuf::from("/etc/udev/rules.d"),
+                PathBuf::from("/run/udev/rules.d"),
+                PathBuf::from("/usr/lib/udev/rules.d"),
+                PathBuf::from("/lib/udev/rules.d"),
+            ],
+            sys_class_tty: PathBuf::from("/sys/class/tty"),
+        }
+    }
+}
+
+/// Checks the setup needed to use the devices in `ports`: a udev rule for the SA430, the driver bound to each port and
+/// the permission to open it, printing a hint for each problem found.
+pub fn doctor(
+    ports: &[Port],
+    paths: &SystemPaths,
+    style: &Style,
+    output: &mut dyn io::Write,
+) -> Result<(), Box<dyn error::Error>> {
+    let mut hints = Vec::new();
+
+    match find_udev_rule(&paths.udev_rules) {
+        Some(rule) => writeln!(output, "udev rule: {}", rule.display())?,
+        None => {
+            writeln!(output, "udev rule: not found")?;
+            hints.push(format!(
+                "No udev rule matches the SA430 (idVendor {}, idProduct {}). The dialout group is enough to use it, \
+                 otherwise install one with `sa430 setup-udev --install`.",
+                USB_VENDOR_ID, USB_PRODUCT_ID
+            ));
+        }
+    }
+
+    if ports.is_empty() {
+        writeln!(output, "No SA430 devices found.")?;
+        hints.push("Check the USB cable and that the device shows up in `lsusb`.".to_string());
+    }
+
+    let mut table = Table::new()
+        .column("port", Align::Left, 14)
+        .column("driver", Align::Left, 8)
+        .column("access", Align::Left, 6);
+    for port in ports {
+        let driver = find_driver(&paths.sys_class_tty, port.name());
+        if driver.as_deref() != Some(EXPECTED_DRIVER) {
+            hints.push(format!(
+                "{} is not bound to the {} driver. Load it with `sudo modprobe {}` and reconnect the device.",
+                port.name(),
+                EXPECTED_DRIVER,
+                EXPECTED_DRIVER
+            ));
+        }
+
+        let access = match SerialPortChannel::new(port.name()) {
+            Ok(_) => "ok".to_string(),
+            Err(error) => {
+                let denied = matches!(error.downcast_ref(), Some(ChannelError::PermissionDenied(_)));
+                hints.push(error.to_string());
+                if denied { "denied" } else { "error" }.to_string()
+            }
+        };
+
+        table.row(vec![
+            port.name().to_string(),
+            driver.unwrap_or_else(|| "none".to_string()),
+            access,
+        ]);
+    }
+    if !ports.is_empty() {
+        table.write(style, output)?;
+    }
+
+    for hint in hints {
+        writeln!(output, "Hint: {}", hint)?;
+    }
+    Ok(())
+}
+
+/// Returns the first rule file matching the SA430 vendor and product IDs.
+pub fn find_udev_rule(dirs: &[PathBuf]) -> Option<PathBuf> {
+    let mut files: Vec<PathBuf> = dirs
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.filter_map(|entry| entry.ok().map(|entry| entry.path())))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "rules"))
+        .collect();
+    files.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+
+    files.into_iter().find(|path| {
+        fs::read_to_string(path).is_ok_and(|rules| {
+            rules.lines().any(|line| {
+                let line = line.to_ascii_lowercase();
+                !line.trim_start().starts_with('#') && line.contains(USB_VENDOR_ID) && line.contains(USB_PRODUCT_ID)
+            })
+        })
+    })
+}
+
+/// Returns the name of the driver bound to the USB interface of a tty port.
+pub fn find_driver(sys_class_tty: &Path, port_name: &str) -> Option<String> {
+    let name = Path::new(port_name).file_name()?;
+    let driver = fs::read_link(sys_class_tty.join(name).join("device").join("driver")).ok()?;
+    driver.file_name().map(|name| name.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn a_system(name: &str) -> (PathBuf, SystemPaths) {
+        let root = std::env::temp_dir().join(format!("sa430-doctor-{}-{}", name, std::process::id()));
+        let paths = SystemPaths {
+            udev_rules: vec![root.join("rules.d")],
+            sys_class_tty: root.join("tty"),
+        };
+        fs::create_dir_all(&paths.udev_rules[0]).unwrap();
+        fs::create_dir_all(&paths.sys_class_tty).unwrap();
+        (root, paths)
+    }
+
+    #[test]
+    fn given_a_rule_for_the_sa430_when_find_udev_rule_then_return_its_file() {
+        let (root, paths) = a_system("rule");
+        let dir = &paths.udev_rules[0];
+        fs::write(
+            dir.join("10-other.rules"),
+            "# ATTRS{idVendor}==\"2047\", ATTRS{idProduct}==\"0005\"\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("99-sa430.rules"),
+            "SUBSYSTEM==\"tty\", ATTRS{idVendor}==\"2047\", ATTRS{idProduct}==\"0005\", MODE=\"0666\"\n",
+        )
+        .unwrap();
+
+        let rule = find_udev_rule(&paths.udev_rules);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(rule, Some(dir.join("99-sa430.rules")));
+    }
+
+    #[test]
+    fn given_a_port_without_driver_when_doctor_then_print_hints() {
+        let (root, paths) = a_system("driver");
+        let mut output = Vec::new();
+
+        doctor(
+            &[Port::new("/some/non/existent/ttyACM9", "1234", "0104")],
+            &paths,
+            &Style::plain(),
+            &mut output,
+        )
+        .unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "udev rule: not found");
+        assert!(lines[3].starts_with("/some/non/existent/ttyACM9 | none"));
+        assert!(lines[3].ends_with("| error"));
+        assert!(output.contains("Hint: /some/non/existent/ttyACM9 is not bound to the cdc_acm driver."));
+    }
+}
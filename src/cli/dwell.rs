@@ -0,0 +1,159 @@
+use std::{
+    error, io,
+    time::{Duration, Instant},
+};
+
+use sa430::device::Sa430;
+use sa430::dsp::{detect_bursts, DwellSample, DwellStats, TimeStats};
+use sa430::frequency::Frequency;
+
+use super::capture::{sweep, DEFAULT_REF_LEVEL};
+use super::table::{Align, Style, Table};
+
+/// Step of the single point sweeps, any positive value works as the sweep starts and stops at the frequency.
+const DWELL_STEP: Frequency = Frequency::from_khz(1.0);
+
+pub struct DwellParams {
+    /// Frequency to measure.
+    pub frequency: Frequency,
+    /// Number of samples to measure.
+    pub samples: usize,
+    /// Power above which a sample is part of a burst, in dBm.
+    pub threshold: f64,
+    pub ref_level: Option<i8>,
+    /// Print the start and duration of each burst.
+    pub list_bursts: bool,
+    pub style: Style,
+}
+
+/// Measures a single frequency back to back, approximating a zero span measurement, and prints the statistics of the
+/// power over time.
+pub fn dwell(
+    device: &mut Sa430,
+    params: &DwellParams,
+    output: &mut dyn io::Write,
+) -> Result<(), Box<dyn error::Error>> {
+    writeln!(
+        output,
+        "Measuring {:.4} {} times with a reference level of {} dBm...",
+        params.frequency,
+        params.samples,
+        params.ref_level.unwrap_or(DEFAULT_REF_LEVEL)
+    )?;
+
+    let start = Instant::now();
+    let mut samples = Vec::with_capacity(params.samples);
+    for _ in 0..params.samples {
+        let spectrum = sweep(device, params.frequency, params.frequency, DWELL_STEP, params.ref_level)?;
+        let power = spectrum.powers().next().ok_or("The sweep returned no points")?;
+        samples.push(DwellSample {
+            elapsed: start.elapsed(),
+            power,
+        });
+    }
+
+    let stats = match DwellStats::new(&samples, params.threshold) {
+        Some(stats) => stats,
+        None => return Ok(writeln!(output, "No samples measured.")?),
+    };
+    print_stats(&stats, params.threshold, output)?;
+    if params.list_bursts && stats.bursts.count > 0 {
+        print_bursts(&samples, params.threshold, &params.style, output)?;
+    }
+    Ok(())
+}
+
+/// Prints the statistics of a dwell measurement.
+fn print_stats(stats: &DwellStats, threshold: f64, output: &mut dyn io::Write) -> Result<(), Box<dyn error::Error>> {
+    let seconds = stats.duration.as_secs_f64();
+    write!(output, "Samples: {} in {:.3} s", stats.samples, seconds)?;
+    if seconds > 0.0 {
+        write!(output, " ({:.1} samples/s)", (stats.samples - 1) as f64 / seconds)?;
+    }
+    writeln!(output)?;
+    writeln!(
+        output,
+        "Power: min {:.2} dBm, max {:.2} dBm, mean {:.2} dBm",
+        stats.min, stats.max, stats.mean
+    )?;
+    writeln!(
+        output,
+        "Bursts above {} dBm: {}, duty cycle {:.1}%",
+        threshold,
+        stats.bursts.count,
+        stats.bursts.duty_cycle * 100.0
+    )?;
+    if let Some(duration) = &stats.bursts.duration {
+        writeln!(output, "Burst duration: {}", format_times(duration))?;
+    }
+    if let Some(period) = &stats.bursts.period {
+        writeln!(output, "Burst period: {}", format_times(period))?;
+    }
+    Ok(())
+}
+
+/// Prints the start and duration of each burst.
+fn print_bursts(
+    samples: &[DwellSample],
+    threshold: f64,
+    style: &Style,
+    output: &mut dyn io::Write,
+) -> Result<(), Box<dyn error::Error>> {
+    let mut table = Table::new()
+        .column("start (ms)", Align::Right, 0)
+        .column("duration (ms)", Align::Right, 0);
+    for burst in detect_bursts(samples, threshold) {
+        table.row(vec![format_millis(burst.start), format_millis(burst.duration)]);
+    }
+    table.write(style, output)?;
+    Ok(())
+}
+
+fn format_times(times: &TimeStats) -> String {
+    format!(
+        "min {} ms, mean {} ms, max {} ms",
+        format_millis(times.min),
+        format_millis(times.mean),
+        format_millis(times.max)
+    )
+}
+
+fn format_millis(time: Duration) -> String {
+    format!("{:.1}", time.as_secs_f64() * 1e3)
+}
+
+#[cfg(test)]
+mod tests {
+    use sa430::dsp::BurstTiming;
+
+    use super::*;
+
+    #[test]
+    fn given_dwell_stats_when_print_stats_then_print_the_power_and_bursts() {
+        let stats = DwellStats {
+            samples: 11,
+            duration: Duration::from_millis(500),
+            min: -100.0,
+            max: -50.0,
+            mean: -57.894,
+            bursts: BurstTiming {
+                count: 2,
+                duration: TimeStats::new([Duration::from_millis(100), Duration::from_millis(140)]),
+                period: TimeStats::new([Duration::from_millis(250)]),
+                duty_cycle: 0.48,
+            },
+        };
+        let mut output = Vec::new();
+
+        print_stats(&stats, -90.0, &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Samples: 11 in 0.500 s (20.0 samples/s)\n\
+             Power: min -100.00 dBm, max -50.00 dBm, mean -57.89 dBm\n\
+             Bursts above -90 dBm: 2, duty cycle 48.0%\n\
+             Burst duration: min 100.0 ms, mean 120.0 ms, max 140.0 ms\n\
+             Burst period: min 250.0 ms, mean 250.0 ms, max 250.0 ms\n"
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use sa430::device::Sa430;
+use sa430::formats::ndjson::escape;
+
+use std::{error, io};
+
+/// Prints the health of the device as a single JSON object, for monitoring scripts.
+pub fn health(device: &mut Sa430, output: &mut dyn io::Write) -> Result<(), Box<dyn error::Error>> {
+    let health = device.health()?;
+    let temperature: Vec<String> = health.temperature_raw.iter().map(|byte| byte.to_string()).collect();
+    writeln!(
+        output,
+        "{{\"serial_number\":{},\"boot_count\":{},\"temperature_raw\":[{}],\"last_error\":{{\"code\":\"{:X}\",\
+         \"message\":\"{}\"}},\"link\":{{\"frames_sent\":{},\"frames_received\":{},\"receive_errors\":{},\
+         \"commands\":{},\"recoveries\":{}}},\"uptime_ms\":{}}}",
+        health.serial_number,
+        health.boot_count,
+        temperature.join(","),
+        health.last_error,
+        escape(&health.last_error.to_string()),
+        health.link.frames_sent,
+        health.link.frames_received,
+        health.link.receive_errors,
+        health.link.commands,
+        health.link.recoveries,
+        health.uptime.as_millis()
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use sa430::{
+        channel::fixtures::MockChannel,
+        frame::{
+            fixture::{a_get_serial_number_response, an_ack_response, an_error_response},
+            Command, ErrorCode, Frame,
+        },
+    };
+
+    #[test]
+    fn given_a_device_when_health_then_print_a_json_object() {
+        let mut output = Vec::new();
+        let mut channel = MockChannel::new();
+        channel.add_response(&an_ack_response(Command::GetLastError).to_bytes());
+        channel.add_response(&an_error_response(ErrorCode::UnknownCmd).to_bytes());
+        channel.add_response(&an_ack_response(Command::GetSerialNumber).to_bytes());
+        channel.add_response(&a_get_serial_number_response().to_bytes());
+        channel.add_response(&an_ack_response(Command::GetBootCnt).to_bytes());
+        channel.add_response(&Frame::with_data(Command::GetBootCnt, &[0, 0, 0, 42]).to_bytes());
+        channel.add_response(&an_ack_response(Command::GetTemp).to_bytes());
+        channel.add_response(&Frame::with_data(Command::GetTemp, &[0, 0, 10, 92, 1, 155]).to_bytes());
+
+        let mut device = Sa430::new(Box::new(channel));
+
+        health(&mut device, &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with(
+            "{\"serial_number\":2312,\"boot_count\":42,\"temperature_raw\":[0,0,10,92,1,155],\
+             \"last_error\":{\"code\":\"0324\",\"message\":\"Unknown command\"},\
+             \"link\":{\"frames_sent\":4,\"frames_received\":8,\"receive_errors\":0,\"commands\":4,\"recoveries\":0},\
+             \"uptime_ms\":"
+        ));
+        assert!(output.ends_with("}\n"));
+    }
+}
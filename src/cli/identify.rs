@@ -0,0 +1,76 @@
+use std::{error, io, thread, time::Duration};
+
+use sa430::cancel::CancellationToken;
+use sa430::device::Sa430;
+use sa430::port::Port;
+use sa430::scanner::Scanner;
+
+/// Returns the port of the connected device with `serial_number`.
+pub fn find_port(scanner: &dyn Scanner, serial_number: &str) -> Result<Port, Box<dyn error::Error>> {
+    scanner
+        .scan()
+        .into_iter()
+        .find(|port| port.serial_number() == serial_number)
+        .ok_or_else(|| format!("No device connected with serial number {}", serial_number).into())
+}
+
+/// Prints the port and the USB topology path of the device, to find where it is physically plugged.
+pub fn print_location(port: &Port, output: &mut dyn io::Write) -> io::Result<()> {
+    writeln!(output, "Serial number: {}", port.serial_number())?;
+    writeln!(output, "Port: {}", port.path())?;
+    writeln!(output, "USB path: {}", port.usb_path().unwrap_or("unknown"))
+}
+
+/// Blinks the LED every `interval` until `stop` is cancelled.
+pub fn identify(
+    device: &mut Sa430,
+    interval: Duration,
+    stop: &CancellationToken,
+    output: &mut dyn io::Write,
+) -> Result<(), Box<dyn error::Error>> {
+    writeln!(output, "Blinking LED, press Enter to stop...")?;
+    while !stop.is_cancelled() {
+        device.blink()?;
+        thread::sleep(interval);
+    }
+    writeln!(output, "Done!")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use sa430::channel::fixtures::MockChannel;
+    use sa430::scanner::fixtures::MockScanner;
+
+    #[test]
+    fn given_connected_devices_when_find_port_then_return_the_one_with_the_serial_number() {
+        let scanner = MockScanner::with_ports(&[
+            Port::new("/dev/ttyUSB1", "08FF41E50F8B3A34", "0104"),
+            Port::new("/dev/ttyUSB2", "08FF41E50F8B3A35", "0104").with_usb_path("1-2.3"),
+        ]);
+
+        let port = find_port(&scanner, "08FF41E50F8B3A35").unwrap();
+        let mut output = Vec::new();
+        print_location(&port, &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Serial number: 08FF41E50F8B3A35\nPort: /dev/ttyUSB2\nUSB path: 1-2.3\n"
+        );
+        assert!(find_port(&scanner, "08FF41E50F8B3A36").is_err());
+    }
+
+    #[test]
+    fn given_a_stopped_identify_when_identify_then_return_without_blinking() {
+        let mut device = Sa430::new(Box::new(MockChannel::new()));
+        let stop = CancellationToken::new();
+        stop.cancel();
+        let mut output = Vec::new();
+
+        identify(&mut device, Duration::ZERO, &stop, &mut output).unwrap();
+
+        assert_eq!(output, b"Blinking LED, press Enter to stop...\nDone!\n");
+    }
+}
@@ -1,16 +1,47 @@
 use sa430::device::Sa430;
 
-/// Prints the device information to the output.
-pub fn info(device: &mut Sa430, output: &mut dyn std::io::Write) -> Result<(), Box<dyn std::error::Error>> {
-    writeln!(output, "IDN: {}", device.idn()?)?;
-    writeln!(output, "Serial Number: {}", device.serial_number()?)?;
-    writeln!(output, "Core Version: {}", device.core_version()?)?;
-    writeln!(output, "Spectrum Version: {}", device.spectrum_version()?)?;
-    writeln!(output, "Calibration Version: {}", device.calibration_version()?)?;
-    writeln!(output, "Calibration Date: {}", device.calibration_date()?)?;
+use super::table::{Align, Layout, Style, Table};
+
+/// Prints the device information to the output, one `name: value` line per property.
+pub fn info(
+    device: &mut Sa430,
+    style: &Style,
+    output: &mut dyn std::io::Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut table = Table::new()
+        .column("IDN", Align::Left, 0)
+        .column("Serial Number", Align::Left, 0)
+        .column("Core Version", Align::Left, 0)
+        .column("Spectrum Version", Align::Left, 0)
+        .column("Calibration Version", Align::Left, 0)
+        .column("Calibration Date", Align::Left, 0);
+    table.row(vec![
+        device.idn()?,
+        device.serial_number()?.to_string(),
+        device.core_version()?,
+        device.spectrum_version()?,
+        device.calibration_version()?,
+        device.calibration_date()?,
+    ]);
+
+    let style = Style {
+        layout: Layout::Narrow,
+        ..*style
+    };
+    table.write(&style, output)?;
     Ok(())
 }
 
+/// Returns the firmware versions and calibration date of the device in one line, for a table of many devices.
+pub fn info_summary(device: &mut Sa430) -> Result<String, Box<dyn std::error::Error>> {
+    Ok(format!(
+        "core {}, spectrum {}, calibration {}",
+        device.core_version()?,
+        device.spectrum_version()?,
+        device.calibration_date()?
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -52,7 +83,7 @@ mod tests {
 
         let mut device = Sa430::new(Box::new(channel));
 
-        info(&mut device, &mut output).unwrap();
+        info(&mut device, &Style::plain(), &mut output).unwrap();
 
         let output = String::from_utf8(output).unwrap();
         assert_eq!(
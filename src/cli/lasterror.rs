@@ -0,0 +1,42 @@
+use sa430::device::Sa430;
+
+use std::{error, io};
+
+/// Prints the last error stored by the device firmware, with its code and a suggestion to recover from it.
+pub fn last_error(device: &mut Sa430, output: &mut dyn io::Write) -> Result<(), Box<dyn error::Error>> {
+    let code = device.last_error()?;
+    writeln!(output, "Last error: {:X} {}", code, code)?;
+    writeln!(output, "Hint: {}", code.remediation())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use sa430::{
+        channel::fixtures::MockChannel,
+        frame::{
+            fixture::{an_ack_response, an_error_response},
+            Command, ErrorCode,
+        },
+    };
+
+    #[test]
+    fn given_a_stored_error_when_last_error_then_print_code_and_hint() {
+        let mut output = Vec::new();
+        let mut channel = MockChannel::new();
+        channel.add_response(&an_ack_response(Command::GetLastError).to_bytes());
+        channel.add_response(&an_error_response(ErrorCode::UnknownCmd).to_bytes());
+
+        let mut device = Sa430::new(Box::new(channel));
+
+        last_error(&mut device, &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Last error: 0324 Unknown command\n\
+             Hint: The firmware doesn't support the command. Check the firmware versions.\n"
+        );
+    }
+}
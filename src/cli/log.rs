@@ -0,0 +1,24 @@
+use std::sync::OnceLock;
+
+use sa430::log::{Level, Logger};
+
+/// Log file of the command, if set with `--log-file`.
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/// Sets the log file of the command, once.
+pub fn init(logger: Logger) {
+    let _ = LOGGER.set(logger);
+}
+
+/// Writes a message to the log file, if any. A failure to write is printed to stderr, but doesn't stop the command.
+pub fn log(level: Level, message: &str) {
+    if let Some(Err(error)) = LOGGER.get().map(|logger| logger.log(level, message)) {
+        eprintln!("Failed to write the log file: {}", error);
+    }
+}
+
+/// Prints a message to stderr and writes it to the log file, if any.
+pub fn report(level: Level, message: &str) {
+    eprintln!("{}", message);
+    log(level, message);
+}
@@ -1,6 +1,31 @@
+pub mod alert;
+#[cfg(feature = "http")]
+pub mod api;
+pub mod bands;
+pub mod batch;
 pub mod blink;
+pub mod cal;
 pub mod capture;
+pub mod config;
+pub mod crash;
+pub mod decode;
+pub mod doctor;
+pub mod dwell;
+pub mod health;
+pub mod identify;
 pub mod info;
+pub mod lasterror;
+pub mod log;
 pub mod reboot;
+pub mod report;
 pub mod scan;
+pub mod session;
+#[cfg(feature = "simulator")]
+pub mod soak;
+pub mod survey;
+pub mod table;
+pub mod tuning;
+pub mod udev;
 pub mod watch;
+#[cfg(feature = "net")]
+pub mod webhook;
@@ -1,6 +1,20 @@
-use std::{error, io};
+use std::{
+    error, io, thread,
+    time::{Duration, Instant},
+};
 
 use sa430::device::Sa430;
+use sa430::port::Port;
+use sa430::scanner::Scanner;
+
+/// Default time to wait for the device to come back after the reboot.
+pub const DEFAULT_WAIT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Time the device is given to disconnect after the reboot, so the old port is not mistaken for the new one.
+const DISCONNECT_GRACE: Duration = Duration::from_secs(2);
+
+/// Time between the scans while waiting for the device.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
 
 pub fn reboot(device: &mut Sa430, output: &mut dyn io::Write) -> Result<(), Box<dyn error::Error>> {
     writeln!(output, "Rebooting device...")?;
@@ -9,15 +23,100 @@ pub fn reboot(device: &mut Sa430, output: &mut dyn io::Write) -> Result<(), Box<
     Ok(())
 }
 
+/// Returns the serial number of the device connected to `port`, by its name or stable path.
+pub fn serial_number_of(scanner: &dyn Scanner, port: &str) -> Result<String, Box<dyn error::Error>> {
+    scanner
+        .scan()
+        .into_iter()
+        .find(|found| found.name() == port || found.by_id() == Some(port))
+        .map(|found| found.serial_number().to_string())
+        .ok_or_else(|| {
+            format!(
+                "Unable to find the device connected to {}, use --no-wait to skip waiting",
+                port
+            )
+            .into()
+        })
+}
+
+/// Waits until the device with `serial_number` disconnects and comes back, and `handshake` succeeds with its new port,
+/// printing and returning the downtime.
+///
+/// The device is looked for again if it doesn't disconnect within a grace time, as it may re-enumerate faster than the
+/// scans.
+pub fn wait_for_device<F>(
+    scanner: &dyn Scanner,
+    serial_number: &str,
+    timeout: Duration,
+    mut handshake: F,
+    output: &mut dyn io::Write,
+) -> Result<Duration, Box<dyn error::Error>>
+where
+    F: FnMut(&Port) -> Result<(), Box<dyn error::Error>>,
+{
+    writeln!(output, "Waiting for the device to come back...")?;
+    let start = Instant::now();
+    let find = || {
+        scanner
+            .scan()
+            .into_iter()
+            .find(|port| port.serial_number() == serial_number)
+    };
+
+    while find().is_some() && start.elapsed() < DISCONNECT_GRACE {
+        thread::sleep(POLL_INTERVAL);
+    }
+    loop {
+        if let Some(port) = find() {
+            if handshake(&port).is_ok() {
+                break;
+            }
+        }
+        if start.elapsed() >= timeout {
+            return Err(format!(
+                "The device {} did not come back within {} s",
+                serial_number,
+                timeout.as_secs()
+            )
+            .into());
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    let downtime = start.elapsed();
+    writeln!(output, "Device back after {:.1} s", downtime.as_secs_f64())?;
+    Ok(downtime)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use std::cell::RefCell;
+
     use sa430::{
         channel::fixtures::MockChannel,
         frame::{fixture::an_ack_response, Command},
+        scanner::fixtures::MockScanner,
     };
 
+    /// Returns the scripted ports of each scan, in order, repeating the last.
+    struct ScriptedScanner(RefCell<Vec<Vec<Port>>>);
+
+    impl Scanner for ScriptedScanner {
+        fn scan(&self) -> Vec<Port> {
+            let mut scans = self.0.borrow_mut();
+            match scans.len() {
+                1 => scans[0].clone(),
+                _ => scans.remove(0),
+            }
+        }
+    }
+
+    fn a_port(name: &str) -> Port {
+        Port::new(name, "08FF41E50F8B3A34", "0104")
+    }
+
     #[test]
     fn given_a_channel_when_reboot_then_return_ok() {
         let mut output = Vec::new();
@@ -30,4 +129,52 @@ mod tests {
 
         assert_eq!(output, b"Rebooting device...\nDone!\n");
     }
+
+    #[test]
+    fn given_a_port_when_serial_number_of_then_match_its_name_or_stable_path() {
+        let scanner = MockScanner::with_ports(&[a_port("/dev/ttyACM0").with_by_id("/dev/serial/by-id/usb-SA430-if00")]);
+
+        assert_eq!(serial_number_of(&scanner, "/dev/ttyACM0").unwrap(), "08FF41E50F8B3A34");
+        assert_eq!(
+            serial_number_of(&scanner, "/dev/serial/by-id/usb-SA430-if00").unwrap(),
+            "08FF41E50F8B3A34"
+        );
+        assert!(serial_number_of(&scanner, "/dev/ttyACM1").is_err());
+    }
+
+    #[test]
+    fn given_a_rebooted_device_when_wait_for_device_then_wait_for_its_new_port() {
+        let scanner = ScriptedScanner(RefCell::new(vec![
+            vec![a_port("/dev/ttyACM0")],
+            vec![],
+            vec![a_port("/dev/ttyACM1")],
+        ]));
+        let mut handshakes = Vec::new();
+        let mut output = Vec::new();
+
+        wait_for_device(
+            &scanner,
+            "08FF41E50F8B3A34",
+            DEFAULT_WAIT_TIMEOUT,
+            |port| {
+                handshakes.push(port.name().to_string());
+                Ok(())
+            },
+            &mut output,
+        )
+        .unwrap();
+
+        assert_eq!(handshakes, vec!["/dev/ttyACM1"]);
+        assert!(String::from_utf8(output).unwrap().contains("Device back after"));
+    }
+
+    #[test]
+    fn given_a_device_not_coming_back_when_wait_for_device_then_return_an_error() {
+        let scanner = MockScanner::new();
+        let mut output = Vec::new();
+
+        let result = wait_for_device(&scanner, "08FF41E50F8B3A34", Duration::ZERO, |_| Ok(()), &mut output);
+
+        assert!(result.is_err());
+    }
 }
@@ -0,0 +1,287 @@
+use std::{error, io};
+
+use sa430::formats::csv::{parse_spectrum, DELIMITERS};
+use sa430::formats::html::{write_html_report, Trace};
+use sa430::spectrum::Spectrum;
+use sa430::storage::Storage;
+
+use super::table::{Align, Style, Table};
+
+/// Default power above which a bin is considered occupied, in dBm.
+pub const DEFAULT_OCCUPANCY_THRESHOLD: f64 = -90.0;
+
+/// Number of peaks listed in the report.
+const TOP_PEAKS: usize = 5;
+
+/// Summary of a capture session: the strongest peaks, the occupancy of the band and the trigger trips.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionReport {
+    threshold: f64,
+    sweeps: usize,
+    violations: usize,
+    /// Highest power of each bin, as `(frequency_hz, power_dbm)` points.
+    max_hold: Vec<(f64, f64)>,
+    /// Number of sweeps with each bin above the threshold.
+    occupied: Vec<usize>,
+    tags: Vec<String>,
+    note: Option<String>,
+}
+
+impl SessionReport {
+    /// Creates an empty report, with bins occupied above `threshold`, in dBm.
+    pub fn new(threshold: f64) -> Self {
+        SessionReport {
+            threshold,
+            sweeps: 0,
+            violations: 0,
+            max_hold: Vec::new(),
+            occupied: Vec::new(),
+            tags: Vec::new(),
+            note: None,
+        }
+    }
+
+    /// Sets the labels of the capture, printed before the summary.
+    pub fn set_labels(&mut self, tags: &[String], note: Option<&str>) {
+        self.tags = tags.to_vec();
+        self.note = note.map(str::to_string);
+    }
+
+    /// Adds a sweep, sweeps with a grid other than the first one are ignored.
+    pub fn add(&mut self, spectrum: &Spectrum) {
+        if self.sweeps == 0 {
+            self.max_hold = spectrum.points().to_vec();
+            self.occupied = vec![0; spectrum.len()];
+        } else if !spectrum
+            .frequencies()
+            .eq(self.max_hold.iter().map(|(frequency, _)| *frequency))
+        {
+            return;
+        }
+
+        for (index, (_, power)) in spectrum.iter().enumerate() {
+            self.max_hold[index].1 = self.max_hold[index].1.max(*power);
+            self.occupied[index] += (*power > self.threshold) as usize;
+        }
+        self.sweeps += 1;
+    }
+
+    /// Counts a trip of the trigger.
+    pub fn add_violation(&mut self) {
+        self.violations += 1;
+    }
+
+    /// Returns the fraction of the sweeps with the bin at `index` above the threshold, from 0 to 1.
+    fn occupancy(&self, index: usize) -> f64 {
+        match self.sweeps {
+            0 => 0.0,
+            sweeps => self.occupied[index] as f64 / sweeps as f64,
+        }
+    }
+
+    /// Returns the indexes of the strongest local maxima of the max hold, strongest first.
+    fn peaks(&self) -> Vec<usize> {
+        let power = |index: usize| self.max_hold[index].1;
+        let mut peaks: Vec<usize> = (0..self.max_hold.len())
+            .filter(|index| *index == 0 || power(*index) > power(index - 1))
+            .filter(|index| *index + 1 == self.max_hold.len() || power(*index) >= power(index + 1))
+            .collect();
+        peaks.sort_by(|a, b| power(*b).total_cmp(&power(*a)));
+        peaks.truncate(TOP_PEAKS);
+        peaks
+    }
+
+    /// Writes the report, with frequencies in MHz.
+    pub fn write(&self, style: &Style, output: &mut dyn io::Write) -> Result<(), Box<dyn error::Error>> {
+        let band_occupancy = match self.occupied.len() {
+            0 => 0.0,
+            bins => (0..bins).map(|index| self.occupancy(index)).sum::<f64>() / bins as f64,
+        };
+
+        writeln!(output, "Capture report")?;
+        if !self.tags.is_empty() {
+            writeln!(output, "Tags: {}", self.tags.join(", "))?;
+        }
+        if let Some(note) = &self.note {
+            writeln!(output, "Note: {}", note)?;
+        }
+        writeln!(output, "Sweeps: {}", self.sweeps)?;
+        writeln!(output, "Trigger violations: {}", self.violations)?;
+        writeln!(
+            output,
+            "Occupancy above {} dBm: {:.1}%",
+            self.threshold,
+            band_occupancy * 100.0
+        )?;
+        writeln!(output)?;
+
+        let mut table = Table::new()
+            .column("peak (MHz)", Align::Right, 0)
+            .column("max (dBm)", Align::Right, 0)
+            .column("occupancy", Align::Right, 0);
+        for index in self.peaks() {
+            let (frequency, power) = self.max_hold[index];
+            table.row(vec![
+                format!("{:.4}", frequency / 1e6),
+                format!("{:.2}", power),
+                format!("{:.1}%", self.occupancy(index) * 100.0),
+            ]);
+        }
+        table.write(style, output)?;
+        Ok(())
+    }
+}
+
+/// Writes an HTML report titled `title` with the CSV traces of `storage`, one per `.csv` recording, ordered by name.
+///
+/// The lines before the first CSV row, like the banner of `capture`, are skipped and the `# key: value` comments are
+/// shown as the trace metadata.
+pub fn html_report(
+    storage: &dyn Storage,
+    title: &str,
+    output: &mut dyn io::Write,
+) -> Result<(), Box<dyn error::Error>> {
+    let names: Vec<String> = storage
+        .list()?
+        .into_iter()
+        .filter(|name| {
+            name.rsplit_once('.')
+                .is_some_and(|(_, extension)| extension.eq_ignore_ascii_case("csv"))
+        })
+        .collect();
+
+    if names.is_empty() {
+        return Err(format!("No CSV traces in {}", storage.location()).into());
+    }
+
+    let mut traces = Vec::with_capacity(names.len());
+    for name in names {
+        let mut data = String::new();
+        storage.open(&name)?.read_to_string(&mut data)?;
+        let stem = name.rsplit_once('.').map_or(name.as_str(), |(stem, _)| stem);
+        traces.push(read_trace(stem, &data).map_err(|error| format!("{}: {}", name, error))?);
+    }
+
+    write_html_report(title, &traces, output)
+}
+
+/// Reads a trace from CSV data, skipping the lines before the first CSV row.
+fn read_trace(name: &str, data: &str) -> Result<Trace, Box<dyn error::Error>> {
+    let rows: Vec<&str> = data
+        .lines()
+        .skip_while(|line| !line.starts_with('#') && !line.contains(DELIMITERS))
+        .collect();
+
+    let mut trace = Trace::new(name, parse_spectrum(rows.join("\n").as_bytes())?);
+    trace.metadata = rows
+        .iter()
+        .filter_map(|line| line.strip_prefix('#')?.split_once(':'))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect();
+    Ok(trace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    use sa430::storage::LocalStorage;
+
+    fn a_report() -> SessionReport {
+        let mut report = SessionReport::new(-90.0);
+        report.add(&Spectrum::from_sweep(
+            868.0e6,
+            0.1e6,
+            &[-100.0, -60.0, -70.0, -100.0, -85.0],
+        ));
+        report.add(&Spectrum::from_sweep(
+            868.0e6,
+            0.1e6,
+            &[-100.0, -95.0, -95.0, -100.0, -50.0],
+        ));
+        report.add_violation();
+        report
+    }
+
+    #[test]
+    fn given_a_capture_output_when_read_trace_then_skip_the_banner_and_keep_the_metadata() {
+        let data = "Capturing data from 868.00 MHz...\n# location: -27.59,-48.55\n\
+                    frequency (MHz),power (dBm)\n868.0000,-90.00\n868.1000,-72.50\n";
+
+        let trace = read_trace("eu868", data).unwrap();
+
+        assert_eq!(trace.spectrum.points(), &[(868.0e6, -90.0), (868.1e6, -72.5)]);
+        assert_eq!(
+            trace.metadata,
+            vec![("location".to_string(), "-27.59,-48.55".to_string())]
+        );
+    }
+
+    #[test]
+    fn given_a_session_dir_when_html_report_then_include_each_trace() {
+        let dir = std::env::temp_dir().join(format!("sa430-session-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("b.csv"), "868.0,-90\n").unwrap();
+        fs::write(dir.join("a.csv"), "868.0,-80\n").unwrap();
+        fs::write(dir.join("notes.txt"), "ignored").unwrap();
+        let mut output = Vec::new();
+
+        html_report(&LocalStorage::new(&dir), "session", &mut output).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let html = String::from_utf8(output).unwrap();
+        let (a, b) = (html.find("<td>a</td>").unwrap(), html.find("<td>b</td>").unwrap());
+        assert!(a < b);
+        assert!(!html.contains("notes"));
+    }
+
+    #[test]
+    fn given_sweeps_when_add_then_keep_the_max_hold_and_occupancy() {
+        let report = a_report();
+
+        assert_eq!(report.sweeps, 2);
+        assert_eq!(report.peaks(), vec![4, 1]);
+        assert_eq!(report.occupancy(1), 0.5);
+        assert_eq!(report.occupancy(4), 1.0);
+    }
+
+    #[test]
+    fn given_a_sweep_with_another_grid_when_add_then_ignore_it() {
+        let mut report = a_report();
+
+        report.add(&Spectrum::from_sweep(900.0e6, 0.1e6, &[-10.0]));
+
+        assert_eq!(report.sweeps, 2);
+    }
+
+    #[test]
+    fn given_a_report_when_write_then_print_the_summary_and_top_peaks() {
+        let mut output = Vec::new();
+
+        a_report().write(&Style::plain(), &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines[1..4],
+            ["Sweeps: 2", "Trigger violations: 1", "Occupancy above -90 dBm: 40.0%"]
+        );
+        assert_eq!(lines[7], "  868.4000 |    -50.00 |    100.0%");
+        assert_eq!(lines[8], "  868.1000 |    -60.00 |     50.0%");
+    }
+
+    #[test]
+    fn given_labels_when_write_then_print_them_before_the_summary() {
+        let mut report = a_report();
+        report.set_labels(&["site=roof".to_string()], Some("new antenna"));
+        let mut output = Vec::new();
+
+        report.write(&Style::plain(), &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[1..4], ["Tags: site=roof", "Note: new antenna", "Sweeps: 2"]);
+    }
+}
@@ -1,6 +1,8 @@
 use sa430::port::Port;
 use sa430::scanner::Scanner;
 
+use super::table::{Align, Style, Table};
+
 /// Handles the scan command logic.
 ///
 /// Will scan for devices using the provided scanner and print a list of ports where the devices were found using the
@@ -8,41 +10,33 @@ use sa430::scanner::Scanner;
 ///
 /// # Arguments
 /// * `scanner` - The scanner to use to find the devices.
+/// * `style` - The style of the table.
 /// * `writer` - The writer to output the devices found.
 ///
-pub fn scan(scanner: Box<dyn Scanner>, writer: &mut dyn std::io::Write) -> std::io::Result<()> {
-    let ports = scanner.scan();
-    writeln!(writer, "port           | serial number    | version")?;
-    writeln!(writer, "---------------|------------------|--------")?;
-    for port in ports {
-        print_port_information(&port, writer)?;
+pub fn scan(scanner: Box<dyn Scanner>, style: &Style, writer: &mut dyn std::io::Write) -> std::io::Result<()> {
+    let mut table = Table::new()
+        .column("port", Align::Left, 14)
+        .column("serial number", Align::Left, 16)
+        .column("version", Align::Left, 4);
+    for port in scanner.scan() {
+        table.row(port_information(&port));
     }
-    writeln!(writer, "---------------|------------------|--------")
+    table.write(style, writer)
 }
 
-fn print_port_information(port: &Port, writer: &mut dyn std::io::Write) -> std::io::Result<()> {
-    writeln!(
-        writer,
-        "{:14} | {:16} | {:4}",
-        port.name(),
-        port.serial_number(),
-        port.firmware_version()
-    )
+fn port_information(port: &Port) -> Vec<String> {
+    vec![
+        port.path().to_string(),
+        port.serial_number().to_string(),
+        port.firmware_version().to_string(),
+    ]
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    struct FakeScanner {
-        ports: Vec<Port>,
-    }
-
-    impl Scanner for FakeScanner {
-        fn scan(&self) -> Vec<Port> {
-            self.ports.clone()
-        }
-    }
+    use sa430::scanner::fixtures::MockScanner;
 
     #[test]
     fn given_a_device_is_connected_when_scan_then_print_port_information() {
@@ -52,9 +46,9 @@ mod tests {
             Port::new("/dev/ttyUSB2", "08FF41E50F8B3A35", "0104"),
             Port::new("/dev/ttyUSB3", "08FF41E50F8B3A36", "0102"),
         ];
-        let scanner = FakeScanner { ports };
+        let scanner = MockScanner::with_ports(&ports);
 
-        scan(Box::new(scanner), writer).unwrap();
+        scan(Box::new(scanner), &Style::plain(), writer).unwrap();
 
         let output = String::from_utf8(writer.to_vec()).unwrap();
         assert_eq!(
@@ -68,13 +62,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn given_a_device_with_a_stable_path_when_scan_then_print_the_stable_path() {
+        let writer = &mut Vec::new();
+        let ports = vec![Port::new("/dev/ttyACM0", "08FF41E50F8B3A34", "0104").with_by_id("/dev/serial/by-id/sa430")];
+        let scanner = MockScanner::with_ports(&ports);
+
+        scan(Box::new(scanner), &Style::plain(), writer).unwrap();
+
+        let output = String::from_utf8(writer.to_vec()).unwrap();
+        assert_eq!(
+            output.lines().nth(2).unwrap(),
+            "/dev/serial/by-id/sa430 | 08FF41E50F8B3A34 | 0104"
+        );
+    }
+
     #[test]
     fn given_no_device_is_connected_when_scan_then_print_no_port_information() {
         let writer = &mut Vec::new();
-        let ports = vec![];
-        let scanner = FakeScanner { ports };
+        let scanner = MockScanner::new();
 
-        scan(Box::new(scanner), writer).unwrap();
+        scan(Box::new(scanner), &Style::plain(), writer).unwrap();
 
         let output = String::from_utf8(writer.to_vec()).unwrap();
         assert_eq!(
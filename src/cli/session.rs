@@ -0,0 +1,442 @@
+use std::{
+    error, fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use sa430::formats::repair;
+use sa430::session::{seal, verify, SessionInfo, Status, MANIFEST_NAME};
+use sa430::storage::{LocalStorage, Storage};
+use sa430::timestamp::{civil_from_days, days_from_civil, SECONDS_PER_DAY};
+
+use super::table::{Align, Style, Table};
+
+/// Number of bytes in a megabyte, as used by the retention limits.
+pub const BYTES_PER_MEGABYTE: u64 = 1024 * 1024;
+
+/// Writes the metadata and integrity manifest of the recordings of a session.
+pub fn seal_session(
+    storage: &dyn Storage,
+    info: &SessionInfo,
+    output: &mut dyn io::Write,
+) -> Result<(), Box<dyn error::Error>> {
+    let count = seal(storage, info)?;
+    writeln!(
+        output,
+        "Sealed {} recordings of {} in {}",
+        count,
+        storage.location(),
+        MANIFEST_NAME
+    )?;
+    Ok(())
+}
+
+/// Prints the status of each recording of a session, failing if any doesn't match the manifest.
+pub fn verify_session(
+    storage: &dyn Storage,
+    style: &Style,
+    output: &mut dyn io::Write,
+) -> Result<(), Box<dyn error::Error>> {
+    let checks = verify(storage)?;
+    let mut table = Table::new()
+        .column("recording", Align::Left, 0)
+        .column("status", Align::Left, 8);
+    for check in &checks {
+        table.row(vec![check.name.clone(), check.status.to_string()]);
+    }
+    table.write(style, output)?;
+
+    match checks.iter().filter(|check| check.status != Status::Ok).count() {
+        0 => Ok(()),
+        failed => Err(format!("{} of {} recordings failed the check", failed, checks.len()).into()),
+    }
+}
+
+/// Removes the truncated final records of the recordings of a session, left by a power loss while recording, and prints
+/// the recordings repaired.
+pub fn repair_session(dir: &Path, style: &Style, output: &mut dyn io::Write) -> Result<(), Box<dyn error::Error>> {
+    let mut table = Table::new()
+        .column("recording", Align::Left, 0)
+        .column("removed (bytes)", Align::Right, 0);
+    let mut repaired = 0;
+    for name in LocalStorage::new(dir).list()? {
+        if let Some(removed) = repair(&dir.join(&name))? {
+            if removed > 0 {
+                repaired += 1;
+            }
+            table.row(vec![name, removed.to_string()]);
+        }
+    }
+    table.write(style, output)?;
+    writeln!(output, "Repaired {} recordings", repaired)?;
+    Ok(())
+}
+
+/// Criteria of the sessions listed, all of which must match.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionFilter {
+    pub device: Option<String>,
+    pub band: Option<String>,
+    pub tags: Vec<String>,
+
+    /// Only sessions that ended at or after this time.
+    pub since: Option<SystemTime>,
+
+    /// Only sessions that started before this time.
+    pub until: Option<SystemTime>,
+}
+
+impl SessionFilter {
+    /// Returns true if the session matches all the criteria. Bands and tags are compared ignoring case.
+    pub fn matches(&self, info: &SessionInfo) -> bool {
+        let same = |expected: &Option<String>, actual: &Option<String>| match (expected, actual) {
+            (Some(expected), Some(actual)) => expected.eq_ignore_ascii_case(actual),
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+
+        same(&self.device, &info.device)
+            && same(&self.band, &info.band)
+            && self
+                .tags
+                .iter()
+                .all(|tag| info.tags.iter().any(|actual| actual.eq_ignore_ascii_case(tag)))
+            && self.since.is_none_or(|since| info.end.is_some_and(|end| end >= since))
+            && self
+                .until
+                .is_none_or(|until| info.start.is_some_and(|start| start < until))
+    }
+}
+
+/// Lists the sealed sessions in the subdirectories of `root` that match the filter, ordered by start time.
+pub fn list_sessions(
+    root: &Path,
+    filter: &SessionFilter,
+    style: &Style,
+    output: &mut dyn io::Write,
+) -> Result<(), Box<dyn error::Error>> {
+    let sessions: Vec<(String, SessionInfo)> = read_sessions(root)?
+        .into_iter()
+        .filter(|(_, info)| filter.matches(info))
+        .map(|(path, info)| (file_name(&path), info))
+        .collect();
+
+    let mut table = Table::new()
+        .column("session", Align::Left, 0)
+        .column("device", Align::Left, 0)
+        .column("band", Align::Left, 0)
+        .column("start (UTC)", Align::Left, 19)
+        .column("duration", Align::Right, 0)
+        .column("tags", Align::Left, 0);
+    for (name, info) in &sessions {
+        table.row(vec![
+            name.clone(),
+            info.device.clone().unwrap_or_else(|| "-".to_string()),
+            info.band.clone().unwrap_or_else(|| "-".to_string()),
+            info.start.map_or("-".to_string(), format_time),
+            info.duration().map_or("-".to_string(), format_duration),
+            info.tags.join(", "),
+        ]);
+    }
+    table.write(style, output)?;
+    writeln!(output, "{} sessions", sessions.len())?;
+    Ok(())
+}
+
+/// Limits of the sealed sessions kept in a directory: the oldest sessions past any of them are removed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Total size of the sessions, in bytes.
+    pub max_size: Option<u64>,
+
+    /// Age of the sessions, from their end, or their start if they have no end.
+    pub max_age: Option<Duration>,
+
+    /// Number of sessions.
+    pub max_sessions: Option<usize>,
+}
+
+impl RetentionPolicy {
+    /// Returns the indexes of the sessions past the limits at `now`, given each session and its size in bytes, ordered
+    /// from the oldest. Sessions without times never expire by age.
+    fn expired(&self, sessions: &[(SessionInfo, u64)], now: SystemTime) -> Vec<usize> {
+        let mut expired = Vec::new();
+        let mut total_size = 0;
+        for (index, (info, size)) in sessions.iter().enumerate().rev() {
+            total_size += size;
+            let kept = sessions.len() - index;
+            let too_many = self.max_sessions.is_some_and(|max_sessions| kept > max_sessions);
+            let too_big = self.max_size.is_some_and(|max_size| total_size > max_size);
+            let too_old = self.max_age.is_some_and(|max_age| {
+                info.end
+                    .or(info.start)
+                    .is_some_and(|time| now.duration_since(time).is_ok_and(|age| age > max_age))
+            });
+            if too_many || too_big || too_old {
+                expired.push(index);
+            }
+        }
+        expired.reverse();
+        expired
+    }
+}
+
+/// A session removed, or to be removed, by a retention policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrunedSession {
+    pub path: PathBuf,
+    /// Size of the session directory, in bytes.
+    pub size: u64,
+}
+
+/// Removes the sealed sessions in the subdirectories of `root` past the limits of the policy, oldest first, to free
+/// space. Returns the removed sessions, or the ones that would be removed if `dry_run`.
+pub fn prune_sessions(
+    root: &Path,
+    policy: &RetentionPolicy,
+    dry_run: bool,
+) -> Result<Vec<PrunedSession>, Box<dyn error::Error>> {
+    let (paths, infos): (Vec<PathBuf>, Vec<SessionInfo>) = read_sessions(root)?.into_iter().unzip();
+    let mut sessions = Vec::with_capacity(infos.len());
+    for (path, info) in paths.iter().zip(infos) {
+        sessions.push((info, dir_size(path)?));
+    }
+
+    let mut pruned = Vec::new();
+    for index in policy.expired(&sessions, SystemTime::now()) {
+        if !dry_run {
+            fs::remove_dir_all(&paths[index])?;
+        }
+        pruned.push(PrunedSession {
+            path: paths[index].clone(),
+            size: sessions[index].1,
+        });
+    }
+    Ok(pruned)
+}
+
+/// Prunes the sessions in the subdirectories of `root` and prints them, with the space freed.
+pub fn prune(
+    root: &Path,
+    policy: &RetentionPolicy,
+    dry_run: bool,
+    style: &Style,
+    output: &mut dyn io::Write,
+) -> Result<(), Box<dyn error::Error>> {
+    let pruned = prune_sessions(root, policy, dry_run)?;
+
+    let mut table = Table::new()
+        .column("session", Align::Left, 0)
+        .column("size (MB)", Align::Right, 0);
+    for session in &pruned {
+        table.row(vec![file_name(&session.path), format_megabytes(session.size)]);
+    }
+    table.write(style, output)?;
+    let verb = match dry_run {
+        true => "Would remove",
+        false => "Removed",
+    };
+    let size: u64 = pruned.iter().map(|session| session.size).sum();
+    writeln!(
+        output,
+        "{} {} sessions, {} MB",
+        verb,
+        pruned.len(),
+        format_megabytes(size)
+    )?;
+    Ok(())
+}
+
+/// Returns the total size of the files in a directory and its subdirectories, in bytes.
+fn dir_size(path: &Path) -> io::Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += match metadata.is_dir() {
+            true => dir_size(&entry.path())?,
+            false => metadata.len(),
+        };
+    }
+    Ok(size)
+}
+
+fn format_megabytes(bytes: u64) -> String {
+    format!("{:.1}", bytes as f64 / BYTES_PER_MEGABYTE as f64)
+}
+
+/// Returns the sealed sessions in the subdirectories of `root`, with their directory, ordered by start time.
+fn read_sessions(root: &Path) -> Result<Vec<(PathBuf, SessionInfo)>, Box<dyn error::Error>> {
+    let mut sessions = Vec::new();
+    for entry in fs::read_dir(root)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        // Directories without metadata are not sealed sessions.
+        if let Ok(info) = SessionInfo::read(&LocalStorage::new(entry.path())) {
+            sessions.push((entry.path(), info));
+        }
+    }
+    sessions.sort_by(|(a_path, a), (b_path, b)| (a.start, a_path).cmp(&(b.start, b_path)));
+    Ok(sessions)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().unwrap_or_default().to_string_lossy().to_string()
+}
+
+/// Parses a UTC date, as `YYYY-MM-DD`, returning its start.
+pub fn parse_date(value: &str) -> Result<SystemTime, String> {
+    let invalid = || format!("Invalid date {}, expected YYYY-MM-DD", value);
+    let parts: Vec<&str> = value.split('-').collect();
+    let [year, month, day] = parts.as_slice() else {
+        return Err(invalid());
+    };
+    let (year, month, day): (i64, u32, u32) = (
+        year.parse().map_err(|_| invalid())?,
+        month.parse().map_err(|_| invalid())?,
+        day.parse().map_err(|_| invalid())?,
+    );
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || year < 1970 {
+        return Err(invalid());
+    }
+
+    let days = days_from_civil(year, month, day);
+    Ok(UNIX_EPOCH + Duration::from_secs(days as u64 * SECONDS_PER_DAY))
+}
+
+/// Formats a time as `YYYY-MM-DD HH:MM:SS`, in UTC.
+fn format_time(time: SystemTime) -> String {
+    let seconds = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let (year, month, day) = civil_from_days((seconds / SECONDS_PER_DAY) as i64);
+    let seconds = seconds % SECONDS_PER_DAY;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Formats a duration with its two most significant units, like `1h 05m`.
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        0..60 => format!("{}s", seconds),
+        60..3600 => format!("{}m {:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {:02}m", seconds / 3600, seconds / 60 % 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use sa430::session::METADATA_NAME;
+
+    #[test]
+    fn given_dates_when_parse_and_format_then_round_trip_in_utc() {
+        let time = parse_date("2024-02-29").unwrap();
+
+        assert_eq!(format_time(time), "2024-02-29 00:00:00");
+        assert_eq!(
+            format_time(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            "2023-11-14 22:13:20"
+        );
+        assert!(parse_date("2024-13-01").is_err());
+        assert_eq!(format_duration(Duration::from_secs(3900)), "1h 05m");
+    }
+
+    #[test]
+    fn given_a_filter_when_matches_then_require_every_criteria() {
+        let info = SessionInfo {
+            band: Some("EU868".to_string()),
+            start: Some(parse_date("2026-10-15").unwrap()),
+            end: Some(parse_date("2026-10-16").unwrap()),
+            tags: vec!["north".to_string(), "drive-test".to_string()],
+            ..Default::default()
+        };
+        let filter = |band: &str, tags: &[&str], since: &str| SessionFilter {
+            band: Some(band.to_string()),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            since: Some(parse_date(since).unwrap()),
+            ..Default::default()
+        };
+
+        assert!(filter("eu868", &["North"], "2026-10-16").matches(&info));
+        assert!(!filter("eu868", &["north", "south"], "2026-10-16").matches(&info));
+        assert!(!filter("us915", &[], "2026-10-16").matches(&info));
+        assert!(!filter("eu868", &[], "2026-10-17").matches(&info));
+        assert!(!SessionFilter {
+            device: Some("1234".to_string()),
+            ..Default::default()
+        }
+        .matches(&info));
+    }
+
+    fn a_session(start: u64) -> SessionInfo {
+        SessionInfo {
+            start: Some(UNIX_EPOCH + Duration::from_secs(start)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn given_limits_when_expired_then_return_the_oldest_sessions_past_any_of_them() {
+        let sessions = [
+            (a_session(100), 30),
+            (a_session(200), 20),
+            (a_session(300), 10),
+            (a_session(400), 10),
+        ];
+        let now = UNIX_EPOCH + Duration::from_secs(1000);
+        let count = RetentionPolicy {
+            max_sessions: Some(3),
+            ..Default::default()
+        };
+        let size = RetentionPolicy {
+            max_size: Some(30),
+            ..Default::default()
+        };
+        let age = RetentionPolicy {
+            max_age: Some(Duration::from_secs(750)),
+            ..Default::default()
+        };
+
+        assert_eq!(count.expired(&sessions, now), vec![0]);
+        assert_eq!(size.expired(&sessions, now), vec![0, 1]);
+        assert_eq!(age.expired(&sessions, now), vec![0, 1]);
+        assert!(RetentionPolicy::default().expired(&sessions, now).is_empty());
+    }
+
+    #[test]
+    fn given_sealed_sessions_when_prune_sessions_then_remove_the_oldest() {
+        let root = std::env::temp_dir().join(format!("sa430-prune-{}", std::process::id()));
+        for (name, start) in [("b", 200), ("a", 100), ("c", 300)] {
+            fs::create_dir_all(root.join(name)).unwrap();
+            fs::write(root.join(name).join(METADATA_NAME), format!("start: {}\n", start)).unwrap();
+        }
+        fs::create_dir_all(root.join("unsealed")).unwrap();
+        let policy = RetentionPolicy {
+            max_sessions: Some(1),
+            ..Default::default()
+        };
+
+        let planned = prune_sessions(&root, &policy, true).unwrap();
+        let before = fs::read_dir(&root).unwrap().count();
+        let removed = prune_sessions(&root, &policy, false).unwrap();
+        let after = fs::read_dir(&root).unwrap().count();
+
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(planned, removed);
+        assert_eq!(
+            removed.iter().map(|session| session.path.clone()).collect::<Vec<_>>(),
+            vec![root.join("a"), root.join("b")]
+        );
+        assert_eq!((before, after), (4, 2));
+    }
+}
@@ -0,0 +1,188 @@
+use std::{
+    error, io,
+    time::{Duration, Instant},
+};
+
+use sa430::device::Sa430;
+use sa430::frequency::Frequency;
+use sa430::simulator::Simulator;
+use sa430::sweep::SweepConfig;
+
+/// Number of cycles between reconnections, when the device is dropped and the calibration read again.
+const CYCLES_PER_CONNECTION: usize = 100;
+
+/// Maximum number of attempts to recover the link after a failed command.
+const MAX_RECOVER_ATTEMPTS: usize = 8;
+
+/// Maximum duration of a cycle before it is considered stalled.
+const MAX_CYCLE_DURATION: Duration = Duration::from_secs(1);
+
+/// Number of commands of the cycles, the last one is a sweep.
+const COMMANDS: usize = 7;
+
+/// Start frequency of the sweep cycles.
+const SWEEP_FSTART: Frequency = Frequency::from_mhz(867.0);
+
+/// Stop frequency of the sweep cycles.
+const SWEEP_FSTOP: Frequency = Frequency::from_mhz(869.0);
+
+/// Step frequency of the sweep cycles.
+const SWEEP_FSTEP: Frequency = Frequency::from_khz(50.0);
+
+/// Reference level of the sweep cycles, in dBm.
+const SWEEP_REF_LEVEL: i8 = -35;
+
+pub struct SoakParams {
+    pub cycles: usize,
+    /// Probability of a fault in each reply frame, from 0 to 1.
+    pub fault_rate: f64,
+    pub seed: u64,
+}
+
+/// Results of a soak run.
+#[derive(Debug, Default, PartialEq)]
+pub struct SoakReport {
+    pub cycles: usize,
+    pub failures: usize,
+    pub recoveries: usize,
+    pub sweeps: usize,
+    pub sweep_failures: usize,
+    pub max_cycle_duration: Duration,
+}
+
+/// Runs command and sweep cycles against a simulator injecting faults, failing on a stall or a desync.
+///
+/// Each failed command must be followed by a successful recovery, each successful command must return the simulator
+/// values, each successful sweep must return a point per bin of its configuration and no command may leave the
+/// session busy.
+///
+/// The errors and the summary name the seed, to reproduce the run with `--seed`.
+pub fn soak(params: &SoakParams, output: &mut dyn io::Write) -> Result<SoakReport, Box<dyn error::Error>> {
+    let report = run_cycles(params).map_err(|error| format!("{} (seed {})", error, params.seed))?;
+    writeln!(
+        output,
+        "{} cycles, {} failures, {} recoveries, {} sweeps, {} sweep failures, longest cycle {:?}, seed {}",
+        report.cycles,
+        report.failures,
+        report.recoveries,
+        report.sweeps,
+        report.sweep_failures,
+        report.max_cycle_duration,
+        params.seed
+    )?;
+    Ok(report)
+}
+
+fn run_cycles(params: &SoakParams) -> Result<SoakReport, Box<dyn error::Error>> {
+    let mut report = SoakReport::default();
+    let mut device = connect(params, 0);
+
+    for cycle in 0..params.cycles {
+        if cycle > 0 && cycle % CYCLES_PER_CONNECTION == 0 {
+            device = connect(params, cycle);
+        }
+
+        let start = Instant::now();
+        let is_sweep = cycle % COMMANDS == COMMANDS - 1;
+        if is_sweep {
+            report.sweeps += 1;
+        }
+        match run_command(&mut device, cycle) {
+            Ok(true) => {}
+            Ok(false) => return Err(format!("Cycle {}: unexpected result, the link is out of sync", cycle).into()),
+            Err(_) => {
+                report.failures += 1;
+                if is_sweep {
+                    report.sweep_failures += 1;
+                }
+                recover(&mut device, cycle)?;
+                report.recoveries += 1;
+            }
+        }
+
+        if device.is_busy() {
+            return Err(format!("Cycle {}: session left busy", cycle).into());
+        }
+
+        let duration = start.elapsed();
+        if duration > MAX_CYCLE_DURATION {
+            return Err(format!("Cycle {}: stalled for {:?}", cycle, duration).into());
+        }
+        report.max_cycle_duration = report.max_cycle_duration.max(duration);
+        report.cycles += 1;
+    }
+    Ok(report)
+}
+
+/// Creates a device over a new simulator, with a seed derived from the cycle so each connection sees other faults.
+fn connect(params: &SoakParams, cycle: usize) -> Sa430 {
+    let mut simulator = Simulator::new();
+    simulator.set_fault_rate(params.fault_rate, params.seed.wrapping_add(cycle as u64));
+    Sa430::new(Box::new(simulator))
+}
+
+/// Runs the command of the cycle and returns whether its result matches the simulator values, or for a sweep, its
+/// configuration.
+fn run_command(device: &mut Sa430, cycle: usize) -> Result<bool, Box<dyn error::Error>> {
+    let matches = match cycle % COMMANDS {
+        0 => device.idn()?.starts_with("Texas Instruments"),
+        1 => device.serial_number()? == 0x0908,
+        2 => device.core_version()? == "2.10",
+        3 => device.spectrum_version()? == "2.5",
+        4 => device.calibration_version()? == "1.16",
+        5 => device.blink().map(|_| true)?,
+        _ => {
+            let spectrum = device.capture(SWEEP_FSTART, SWEEP_FSTOP, SWEEP_FSTEP, SWEEP_REF_LEVEL)?;
+            let bins: usize = spectrum.configs().iter().map(SweepConfig::bin_count).sum();
+            !spectrum.is_empty() && spectrum.len() == bins && spectrum.powers().all(f64::is_finite)
+        }
+    };
+    Ok(matches)
+}
+
+/// Recovers the link after a failed command.
+fn recover(device: &mut Sa430, cycle: usize) -> Result<(), Box<dyn error::Error>> {
+    for _ in 0..MAX_RECOVER_ATTEMPTS {
+        if device.recover().is_ok() {
+            return Ok(());
+        }
+    }
+    Err(format!("Cycle {}: unable to recover the link", cycle).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_no_faults_when_soak_then_run_all_cycles_without_failures() {
+        let mut output = Vec::new();
+        let params = SoakParams {
+            cycles: 200,
+            fault_rate: 0.0,
+            seed: 1,
+        };
+
+        let report = soak(&params, &mut output).unwrap();
+
+        assert_eq!((report.cycles, report.failures), (200, 0));
+        assert_eq!((report.sweeps, report.sweep_failures), (28, 0));
+        assert!(String::from_utf8(output).unwrap().starts_with("200 cycles, 0 failures"));
+    }
+
+    #[test]
+    fn given_faults_when_soak_then_recover_from_every_failure() {
+        let params = SoakParams {
+            cycles: 300,
+            fault_rate: 0.05,
+            seed: 42,
+        };
+
+        let report = soak(&params, &mut io::sink()).unwrap();
+
+        assert_eq!(report.cycles, 300);
+        assert!(report.failures > 0);
+        assert!(report.sweep_failures > 0);
+        assert_eq!(report.recoveries, report.failures);
+    }
+}
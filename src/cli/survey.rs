@@ -0,0 +1,145 @@
+use std::{error, fs::File, io, path::PathBuf};
+
+use sa430::device::Sa430;
+use sa430::dsp::{active_regions, merge_sweeps, Region, SurveyConfig};
+use sa430::formats::csv::CsvSink;
+use sa430::frequency::Frequency;
+use sa430::sink::{Sink, SweepRecord};
+use sa430::spectrum::Spectrum;
+
+use super::capture::sweep;
+use super::table::{Align, Style, Table};
+
+pub struct SurveyParams {
+    /// Range of the coarse sweep, the whole range of the device if not set.
+    pub fstart: Option<Frequency>,
+    pub fstop: Option<Frequency>,
+    /// Step of the coarse sweep.
+    pub coarse_step: Frequency,
+    /// Step of the fine sweeps of the active regions.
+    pub fine_step: Frequency,
+    pub ref_level: Option<i8>,
+    pub config: SurveyConfig,
+    /// CSV file where the coarse sweep, with the fine sweeps in place of its active regions, is written.
+    pub output: Option<PathBuf>,
+    pub style: Style,
+}
+
+/// Sweeps the range of `params` with the coarse step, then sweeps again each region with activity with the fine step,
+/// printing the regions found with their peaks.
+pub fn survey(
+    device: &mut Sa430,
+    params: &SurveyParams,
+    output: &mut dyn io::Write,
+) -> Result<(), Box<dyn error::Error>> {
+    let (fstart, fstop) = match (params.fstart, params.fstop) {
+        (Some(fstart), Some(fstop)) => (fstart, fstop),
+        (fstart, fstop) => {
+            let (lo, hi) = device_range(device)?;
+            (fstart.unwrap_or(lo), fstop.unwrap_or(hi))
+        }
+    };
+
+    writeln!(
+        output,
+        "Coarse sweep from {:.2} to {:.2} with step of {:.3}...",
+        fstart, fstop, params.coarse_step
+    )?;
+    let coarse = sweep(device, fstart, fstop, params.coarse_step, params.ref_level)?;
+
+    let regions = active_regions(&coarse, &params.config);
+    writeln!(
+        output,
+        "{} active regions above {} dBm, fine sweeps with step of {:.3}...",
+        regions.len(),
+        params.config.threshold,
+        params.fine_step
+    )?;
+    let mut fine = Vec::with_capacity(regions.len());
+    for region in &regions {
+        fine.push(sweep(
+            device,
+            Frequency::from_hz(region.fstart),
+            Frequency::from_hz(region.fstop),
+            params.fine_step,
+            params.ref_level,
+        )?);
+    }
+
+    print_regions(&regions, &fine, &params.style, output)?;
+    if let Some(path) = &params.output {
+        let mut sink = CsvSink::new(File::create(path)?, ',')?;
+        sink.write(&SweepRecord::new(0, merge_sweeps(&coarse, &fine)))?;
+        sink.close()?;
+    }
+    Ok(())
+}
+
+/// Returns the range covered by the frequency ranges of the device calibration.
+fn device_range(device: &mut Sa430) -> Result<(Frequency, Frequency), Box<dyn error::Error>> {
+    let ranges = &device.calibration()?.frq_ranges;
+    let lo = ranges
+        .iter()
+        .filter(|range| range.f_start() < range.f_stop())
+        .map(|range| range.f_start())
+        .min();
+    let hi = ranges
+        .iter()
+        .filter(|range| range.f_start() < range.f_stop())
+        .map(|range| range.f_stop())
+        .max();
+    match (lo, hi) {
+        (Some(lo), Some(hi)) => Ok((Frequency::from_hz(lo as f64), Frequency::from_hz(hi as f64))),
+        _ => Err("The device calibration has no frequency ranges".into()),
+    }
+}
+
+/// Prints each region with the peak of its fine sweep.
+fn print_regions(
+    regions: &[Region],
+    fine: &[Spectrum],
+    style: &Style,
+    output: &mut dyn io::Write,
+) -> Result<(), Box<dyn error::Error>> {
+    let mut table = Table::new()
+        .column("fstart (MHz)", Align::Right, 0)
+        .column("fstop (MHz)", Align::Right, 0)
+        .column("peak (MHz)", Align::Right, 0)
+        .column("peak (dBm)", Align::Right, 10);
+    for (region, spectrum) in regions.iter().zip(fine) {
+        let peak = spectrum.iter().max_by(|a, b| a.1.total_cmp(&b.1));
+        table.row(vec![
+            format!("{:.4}", region.fstart / 1e6),
+            format!("{:.4}", region.fstop / 1e6),
+            peak.map_or("-".to_string(), |(frequency, _)| format!("{:.4}", frequency / 1e6)),
+            peak.map_or("-".to_string(), |(_, power)| format!("{:.2}", power)),
+        ]);
+    }
+    table.write(style, output)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_regions_and_their_fine_sweeps_when_print_regions_then_print_the_peak_of_each() {
+        let regions = [Region {
+            fstart: 868.0e6,
+            fstop: 868.2e6,
+        }];
+        let fine = [Spectrum::from_sweep(
+            868.0e6,
+            0.05e6,
+            &[-90.0, -62.5, -80.0, -95.0, -99.0],
+        )];
+        let mut output = Vec::new();
+
+        print_regions(&regions, &fine, &Style::plain(), &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[2], "    868.0000 |    868.2000 |   868.0500 |     -62.50");
+    }
+}
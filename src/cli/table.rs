@@ -0,0 +1,259 @@
+use std::{
+    env,
+    io::{self, IsTerminal},
+};
+
+use clap::ValueEnum;
+
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// Alignment of the values of a column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+/// How the tables are laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Layout {
+    /// Wide when it fits the terminal, narrow otherwise.
+    Auto,
+
+    /// One line per row, with the columns separated by `|`.
+    Wide,
+
+    /// One `header: value` line per column, with the rows separated by an empty line.
+    Narrow,
+}
+
+/// Output styling shared by the commands printing tables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Style {
+    /// Highlight the table headers using ANSI escape codes.
+    pub color: bool,
+
+    /// Table layout.
+    pub layout: Layout,
+
+    /// Terminal width, in characters, if known.
+    pub width: Option<usize>,
+}
+
+impl Style {
+    /// Plain style, without color and with the wide layout, used when the output is not a terminal.
+    pub fn plain() -> Self {
+        Style {
+            color: false,
+            layout: Layout::Wide,
+            width: None,
+        }
+    }
+
+    /// Creates the style for the standard output.
+    ///
+    /// Color is disabled by `no_color`, by a non-empty `NO_COLOR` environment variable, or when the output is not a
+    /// terminal. The terminal width is read from the `COLUMNS` environment variable.
+    pub fn from_env(no_color: bool, layout: Layout) -> Self {
+        if !io::stdout().is_terminal() {
+            return Style {
+                layout,
+                ..Style::plain()
+            };
+        }
+
+        let no_color = no_color || env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        Style {
+            color: !no_color,
+            layout,
+            width: env::var("COLUMNS").ok().and_then(|columns| columns.parse().ok()),
+        }
+    }
+
+    fn header(&self, text: &str) -> String {
+        match self.color {
+            true => format!("{}{}{}", BOLD, text, RESET),
+            false => text.to_string(),
+        }
+    }
+}
+
+struct Column {
+    header: String,
+    align: Align,
+    width: usize,
+}
+
+/// A table printed according to a [`Style`].
+#[derive(Default)]
+pub struct Table {
+    columns: Vec<Column>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// Creates a table without columns.
+    pub fn new() -> Self {
+        Table::default()
+    }
+
+    /// Adds a column at least `width` characters wide in the wide layout.
+    pub fn column(mut self, header: &str, align: Align, width: usize) -> Self {
+        self.columns.push(Column {
+            header: header.to_string(),
+            align,
+            width: width.max(header.chars().count()),
+        });
+        self
+    }
+
+    /// Adds a row, with a value per column.
+    pub fn row(&mut self, values: Vec<String>) {
+        for (column, value) in self.columns.iter_mut().zip(&values) {
+            column.width = column.width.max(value.chars().count());
+        }
+        self.rows.push(values);
+    }
+
+    /// Writes the table to the output.
+    pub fn write(&self, style: &Style, output: &mut dyn io::Write) -> io::Result<()> {
+        let wide = match style.layout {
+            Layout::Wide => true,
+            Layout::Narrow => false,
+            Layout::Auto => style.width.is_none_or(|width| self.wide_width() <= width),
+        };
+
+        match wide {
+            true => self.write_wide(style, output),
+            false => self.write_narrow(style, output),
+        }
+    }
+
+    fn wide_width(&self) -> usize {
+        self.columns
+            .iter()
+            .map(|column| column.width + 3)
+            .sum::<usize>()
+            .saturating_sub(3)
+    }
+
+    fn write_wide(&self, style: &Style, output: &mut dyn io::Write) -> io::Result<()> {
+        let headers: Vec<String> = self.columns.iter().map(|column| column.header.clone()).collect();
+        let line = style.header(&self.format_line(&headers));
+        let separator = self.separator();
+
+        writeln!(output, "{}", line)?;
+        writeln!(output, "{}", separator)?;
+        for row in &self.rows {
+            writeln!(output, "{}", self.format_line(row))?;
+        }
+        writeln!(output, "{}", separator)
+    }
+
+    fn write_narrow(&self, style: &Style, output: &mut dyn io::Write) -> io::Result<()> {
+        for (index, row) in self.rows.iter().enumerate() {
+            if index > 0 {
+                writeln!(output)?;
+            }
+            for (column, value) in self.columns.iter().zip(row) {
+                writeln!(output, "{}: {}", style.header(&column.header), value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Formats the values of a line, without padding the last column when left aligned.
+    fn format_line(&self, values: &[String]) -> String {
+        let last = self.columns.len().saturating_sub(1);
+        let cells: Vec<String> = self
+            .columns
+            .iter()
+            .zip(values)
+            .enumerate()
+            .map(|(index, (column, value))| match column.align {
+                Align::Left if index == last => value.clone(),
+                Align::Left => format!("{:<width$}", value, width = column.width),
+                Align::Right => format!("{:>width$}", value, width = column.width),
+            })
+            .collect();
+        cells.join(" | ")
+    }
+
+    fn separator(&self) -> String {
+        let last = self.columns.len().saturating_sub(1);
+        let cells: Vec<String> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(index, column)| {
+                let padding = (index > 0) as usize + (index < last) as usize;
+                "-".repeat(column.width + padding)
+            })
+            .collect();
+        cells.join("|")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn a_table() -> Table {
+        let mut table = Table::new()
+            .column("name", Align::Left, 6)
+            .column("power", Align::Right, 0)
+            .column("note", Align::Left, 0);
+        table.row(vec!["a".to_string(), "-70.0".to_string(), "carrier".to_string()]);
+        table.row(vec!["b".to_string(), "-9.5".to_string(), "".to_string()]);
+        table
+    }
+
+    #[test]
+    fn given_a_table_when_write_wide_then_align_columns() {
+        let mut output = Vec::new();
+
+        a_table().write(&Style::plain(), &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "name   | power | note\n\
+             -------|-------|--------\n\
+             a      | -70.0 | carrier\n\
+             b      |  -9.5 | \n\
+             -------|-------|--------\n"
+        );
+    }
+
+    #[test]
+    fn given_a_narrow_terminal_when_write_auto_then_print_a_value_per_line() {
+        let mut output = Vec::new();
+        let style = Style {
+            layout: Layout::Auto,
+            width: Some(20),
+            ..Style::plain()
+        };
+
+        a_table().write(&style, &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "name: a\npower: -70.0\nnote: carrier\n\nname: b\npower: -9.5\nnote: \n"
+        );
+    }
+
+    #[test]
+    fn given_color_when_write_then_highlight_headers_only() {
+        let mut output = Vec::new();
+        let style = Style {
+            color: true,
+            ..Style::plain()
+        };
+
+        a_table().write(&style, &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("\x1b[1mname   | power | note\x1b[0m\n-------|"));
+        assert_eq!(output.matches("\x1b[").count(), 2);
+    }
+}
@@ -0,0 +1,150 @@
+use std::{
+    collections::BTreeMap,
+    env, error, fs,
+    num::{NonZeroU8, NonZeroUsize},
+    path::{Path, PathBuf},
+};
+
+use sa430::device::{FlashReadPolicy, Sa430};
+
+/// Chunk sizes tried when tuning the flash reads.
+const CANDIDATE_CHUNK_SIZES: [u8; 3] = [64, 128, 255];
+
+/// Windows tried when tuning the flash reads.
+const CANDIDATE_WINDOWS: [usize; 3] = [1, 2, 4];
+
+/// Flash read policies tuned for each device, by serial number, persisted between runs as CSV lines with the serial
+/// number, the chunk size and the window.
+#[derive(Debug, Default, PartialEq)]
+pub struct FlashTuningCache {
+    policies: BTreeMap<u32, FlashReadPolicy>,
+}
+
+impl FlashTuningCache {
+    /// Returns the default cache file, in `$XDG_CACHE_HOME/sa430` or `$HOME/.cache/sa430`.
+    pub fn default_path() -> Option<PathBuf> {
+        let base = env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+        Some(base.join("sa430").join("flash-read.csv"))
+    }
+
+    /// Loads the cache from `path`, a missing file is an empty cache.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn error::Error>> {
+        let mut cache = FlashTuningCache::default();
+        let data = match fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(cache),
+            Err(error) => return Err(error.into()),
+        };
+
+        for line in data.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let columns: Vec<&str> = line.split(',').map(str::trim).collect();
+            let (serial, policy) = match columns.as_slice() {
+                [serial, chunk_size, window] => (
+                    serial.parse()?,
+                    FlashReadPolicy {
+                        chunk_size: chunk_size.parse()?,
+                        window: window.parse()?,
+                    },
+                ),
+                _ => return Err(format!("Invalid flash tuning cache line: {}", line).into()),
+            };
+            cache.policies.insert(serial, policy);
+        }
+        Ok(cache)
+    }
+
+    /// Saves the cache to `path`, creating its directory.
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn error::Error>> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let lines: Vec<String> = self
+            .policies
+            .iter()
+            .map(|(serial, policy)| format!("{},{},{}\n", serial, policy.chunk_size, policy.window))
+            .collect();
+        fs::write(path, lines.concat())?;
+        Ok(())
+    }
+
+    /// Returns the policy tuned for the device with `serial`.
+    pub fn get(&self, serial: u32) -> Option<&FlashReadPolicy> {
+        self.policies.get(&serial)
+    }
+
+    /// Sets the policy tuned for the device with `serial`.
+    pub fn insert(&mut self, serial: u32, policy: FlashReadPolicy) {
+        self.policies.insert(serial, policy);
+    }
+}
+
+/// Returns the flash read policies tried when tuning.
+pub fn candidates() -> Vec<FlashReadPolicy> {
+    let mut candidates = Vec::new();
+    for chunk_size in CANDIDATE_CHUNK_SIZES.iter().filter_map(|size| NonZeroU8::new(*size)) {
+        for window in CANDIDATE_WINDOWS.iter().filter_map(|window| NonZeroUsize::new(*window)) {
+            candidates.push(FlashReadPolicy { chunk_size, window });
+        }
+    }
+    candidates
+}
+
+/// Applies the flash read policy cached for the device at `path`, benchmarking and caching the best one if the device
+/// was never tuned.
+pub fn apply_tuned_policy(device: &mut Sa430, path: &Path) -> Result<FlashReadPolicy, Box<dyn error::Error>> {
+    let serial = device.serial_number()?;
+    let mut cache = FlashTuningCache::load(path)?;
+
+    if let Some(policy) = cache.get(serial) {
+        device.set_flash_read_policy(*policy);
+        return Ok(*policy);
+    }
+
+    let policy = device.tune_flash_reads(&candidates())?;
+    cache.insert(serial, policy);
+    cache.save(path)?;
+    Ok(policy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn a_policy(chunk_size: u8, window: usize) -> FlashReadPolicy {
+        FlashReadPolicy {
+            chunk_size: NonZeroU8::new(chunk_size).unwrap(),
+            window: NonZeroUsize::new(window).unwrap(),
+        }
+    }
+
+    #[test]
+    fn given_a_cache_when_save_and_load_then_return_the_same_policies() {
+        let path = env::temp_dir()
+            .join(format!("sa430-tuning-{}", std::process::id()))
+            .join("flash-read.csv");
+        let mut cache = FlashTuningCache::default();
+        cache.insert(2312, a_policy(128, 4));
+        cache.insert(7, a_policy(255, 1));
+
+        cache.save(&path).unwrap();
+        let loaded = FlashTuningCache::load(&path).unwrap();
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        assert_eq!(loaded, cache);
+        assert_eq!(loaded.get(2312), Some(&a_policy(128, 4)));
+    }
+
+    #[test]
+    fn given_a_missing_file_when_load_then_return_an_empty_cache() {
+        let cache = FlashTuningCache::load(Path::new("/nonexistent/flash-read.csv")).unwrap();
+        assert_eq!(cache, FlashTuningCache::default());
+    }
+
+    #[test]
+    fn given_the_candidates_then_include_the_default_policy() {
+        assert_eq!(candidates().len(), 9);
+        assert!(candidates().contains(&FlashReadPolicy::default()));
+    }
+}
@@ -0,0 +1,92 @@
+use std::{
+    error, io,
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use sa430::port::{USB_PRODUCT_ID, USB_VENDOR_ID};
+
+/// Path of the installed rule, read after the rules setting the serial number of the port.
+pub const UDEV_RULE_PATH: &str = "/etc/udev/rules.d/99-sa430.rules";
+
+/// Returns the udev rule granting the logged in user and the `dialout` group access to the SA430 ports, with a
+/// `/dev/sa430-<serial>` link that doesn't change between connections.
+pub fn udev_rule() -> String {
+    format!(
+        "# SA430 Sub-1 GHz RF Spectrum Analyzer, generated by `sa430 setup-udev`.\n\
+         SUBSYSTEM==\"tty\", ATTRS{{idVendor}}==\"{}\", ATTRS{{idProduct}}==\"{}\", MODE=\"0660\", \
+         GROUP=\"dialout\", TAG+=\"uaccess\", SYMLINK+=\"sa430-$env{{ID_SERIAL_SHORT}}\"\n",
+        USB_VENDOR_ID, USB_PRODUCT_ID
+    )
+}
+
+/// Prints the udev rule and how to install it or, with `install`, writes it with `sudo` and reloads the rules.
+pub fn setup_udev(install: bool, output: &mut dyn io::Write) -> Result<(), Box<dyn error::Error>> {
+    let rule = udev_rule();
+    if !install {
+        writeln!(
+            output,
+            "# Save as {}, or run `sa430 setup-udev --install`.",
+            UDEV_RULE_PATH
+        )?;
+        write!(output, "{}", rule)?;
+        return Ok(());
+    }
+
+    writeln!(output, "Writing {}...", UDEV_RULE_PATH)?;
+    let mut tee = Command::new("sudo")
+        .args(["tee", UDEV_RULE_PATH])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()?;
+    tee.stdin
+        .take()
+        .ok_or("Unable to write to sudo")?
+        .write_all(rule.as_bytes())?;
+    check_status("sudo tee", tee.wait()?)?;
+
+    writeln!(output, "Reloading the udev rules...")?;
+    run_sudo(&["udevadm", "control", "--reload-rules"])?;
+    run_sudo(&["udevadm", "trigger", "--subsystem-match=tty", "--action=add"])?;
+    writeln!(
+        output,
+        "Done! Reconnect the SA430 if its port doesn't show up as /dev/sa430-<serial>."
+    )?;
+    Ok(())
+}
+
+fn run_sudo(args: &[&str]) -> Result<(), Box<dyn error::Error>> {
+    let status = Command::new("sudo").args(args).status()?;
+    check_status(&format!("sudo {}", args.join(" ")), status)
+}
+
+fn check_status(command: &str, status: std::process::ExitStatus) -> Result<(), Box<dyn error::Error>> {
+    match status.success() {
+        true => Ok(()),
+        false => Err(format!("{} failed: {}", command, status).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_no_install_when_setup_udev_then_print_the_rule() {
+        let mut output = Vec::new();
+
+        setup_udev(false, &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines[0],
+            "# Save as /etc/udev/rules.d/99-sa430.rules, or run `sa430 setup-udev --install`."
+        );
+        assert_eq!(
+            lines[2],
+            "SUBSYSTEM==\"tty\", ATTRS{idVendor}==\"2047\", ATTRS{idProduct}==\"0005\", MODE=\"0660\", \
+             GROUP=\"dialout\", TAG+=\"uaccess\", SYMLINK+=\"sa430-$env{ID_SERIAL_SHORT}\""
+        );
+    }
+}
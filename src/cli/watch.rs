@@ -1,5 +1,17 @@
+use clap::ValueEnum;
+
 use sa430::{monitor::*, port::Port};
 
+/// How the devices are monitored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MonitorKind {
+    /// udev events, received over netlink.
+    Udev,
+
+    /// Device nodes created and deleted in `/dev` and `/dev/serial/by-id`, for containers without udev or netlink.
+    Inotify,
+}
+
 pub struct PrinterEventHandler<'a> {
     output: &'a mut dyn std::io::Write,
 }
@@ -13,8 +25,11 @@ impl<'a> PrinterEventHandler<'a> {
 impl<'a> EventHandler for PrinterEventHandler<'a> {
     fn handle(&mut self, event: &Event) {
         match event {
-            Event::DeviceAdded(port) => print("Connected", port, self.output),
-            Event::DeviceRemoved(port) => print("Disconnected", port, self.output),
+            Event::DeviceAdded(port, _) => print("Connected", port, self.output),
+            Event::DeviceRemoved(port, _) => print("Disconnected", port, self.output),
+            Event::MonitorRestarted(_) => writeln!(self.output, "Monitor restarted, events may have been missed")
+                .expect("Failed to write to output"),
+            _ => {}
         }
     }
 }
@@ -37,7 +52,7 @@ fn print(event_type: &str, port: &Port, output: &mut dyn std::io::Write) {
         output,
         "{}: {:14} | {:16} | {:4}",
         event_type,
-        port.name(),
+        port.path(),
         port.serial_number(),
         port.firmware_version()
     )
@@ -48,45 +63,7 @@ fn print(event_type: &str, port: &Port, output: &mut dyn std::io::Write) {
 mod tests {
     use super::*;
 
-    struct MockMonitor<'a> {
-        handlers: Vec<&'a mut dyn EventHandler>,
-        started: u8,
-    }
-
-    impl<'a> MockMonitor<'a> {
-        fn new() -> Self {
-            MockMonitor {
-                handlers: Vec::new(),
-                started: 0,
-            }
-        }
-
-        fn handlers(&mut self) -> &[&'a mut dyn EventHandler] {
-            &self.handlers
-        }
-
-        fn started(&self) -> u8 {
-            self.started
-        }
-    }
-
-    impl<'a> Monitor<'a> for MockMonitor<'a> {
-        fn start(&mut self) -> std::io::Result<()> {
-            self.started += 1;
-            for handler in self.handlers.iter_mut() {
-                handler.handle(&Event::DeviceAdded(a_port()));
-            }
-
-            for handler in self.handlers.iter_mut() {
-                handler.handle(&Event::DeviceRemoved(a_port()));
-            }
-            Ok(())
-        }
-
-        fn subscribe(&mut self, handler: &'a mut dyn EventHandler) {
-            self.handlers.push(handler);
-        }
-    }
+    use sa430::monitor::fixtures::MockMonitor;
 
     fn a_port() -> Port {
         Port::new("/dev/ttyUSB1", "08FF41E50F8B3A34", "0104")
@@ -97,15 +74,20 @@ mod tests {
         let mut output = Vec::new();
         let mut handler = PrinterEventHandler::new(&mut output);
         let mut monitor = MockMonitor::new();
+        monitor.add_event(Event::DeviceAdded(a_port(), Metadata::now(Some(1))));
+        monitor.add_event(Event::DeviceRemoved(a_port(), Metadata::now(Some(2))));
+        monitor.add_event(Event::MonitorRestarted(Metadata::now(None)));
 
         watch(&mut monitor, &mut handler).expect("Failed to monitor");
 
         assert_eq!(monitor.started(), 1);
-        assert_eq!(monitor.handlers().len(), 1);
+        assert_eq!(monitor.handlers(), 1);
+        drop(monitor);
         assert_eq!(
             String::from_utf8(output).unwrap(),
             "Connected: /dev/ttyUSB1   | 08FF41E50F8B3A34 | 0104\n\
-            Disconnected: /dev/ttyUSB1   | 08FF41E50F8B3A34 | 0104\n"
+            Disconnected: /dev/ttyUSB1   | 08FF41E50F8B3A34 | 0104\n\
+            Monitor restarted, events may have been missed\n"
         );
     }
 }
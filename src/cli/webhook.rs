@@ -0,0 +1,186 @@
+use std::{
+    error,
+    io::{BufRead, BufReader, Write},
+    net::TcpStream,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use sa430::formats::ndjson::escape;
+
+use super::alert::{band, operator, AlertEvent};
+
+/// Payload posted to the webhooks without a template.
+pub const DEFAULT_WEBHOOK_TEMPLATE: &str =
+    r#"{"serial":{serial},"band":"{band}","peak_mhz":{peak_mhz},"peak_dbm":{peak_dbm},"timestamp":{timestamp}}"#;
+
+/// Timeout to connect to the webhook server and to wait for its response.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// HTTP endpoint receiving a JSON payload on each trip.
+///
+/// The payload is rendered from a template, replacing the placeholders `{serial}`, `{band}`, `{condition}`,
+/// `{peak_mhz}`, `{peak_dbm}` and `{timestamp}`, in seconds since the Unix epoch. Text values are JSON escaped, but not
+/// quoted, so they must be placed inside a string. Only plain `http://` URLs are supported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Webhook {
+    host: String,
+    port: u16,
+    path: String,
+    template: String,
+}
+
+impl Webhook {
+    /// Creates a webhook posting to `url` the payload rendered from `template`.
+    pub fn new(url: &str, template: &str) -> Result<Self, Box<dyn error::Error>> {
+        let address = match url.strip_prefix("http://") {
+            Some(address) => address,
+            None if url.starts_with("https://") => return Err("HTTPS webhooks are not supported, use http://".into()),
+            None => return Err(format!("Invalid webhook URL: {}", url).into()),
+        };
+
+        let (authority, path) = match address.find('/') {
+            Some(index) => address.split_at(index),
+            None => (address, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse().map_err(|_| format!("Invalid webhook port: {}", port))?,
+            ),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(format!("Invalid webhook URL: {}", url).into());
+        }
+
+        Ok(Webhook {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+            template: template.to_string(),
+        })
+    }
+
+    /// Renders the payload of a trip.
+    pub fn render(&self, event: &AlertEvent) -> String {
+        let timestamp = event.timestamp.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let condition = format!(
+            "{} {} {} dBm",
+            band(&event.condition),
+            operator(&event.condition),
+            event.condition.level
+        );
+
+        self.template
+            .replace("{serial}", &event.serial.to_string())
+            .replace("{band}", &escape(&band(&event.condition)))
+            .replace("{condition}", &escape(&condition))
+            .replace("{peak_mhz}", &format!("{:.4}", event.peak_frequency / 1e6))
+            .replace("{peak_dbm}", &format!("{:.2}", event.peak_power))
+            .replace("{timestamp}", &timestamp.to_string())
+    }
+
+    /// Posts the payload of a trip, failing if the server doesn't respond with a 2xx status.
+    pub fn post(&self, event: &AlertEvent) -> Result<(), Box<dyn error::Error>> {
+        let body = self.render(event);
+        let mut stream = TcpStream::connect((self.host.as_str(), self.port))?;
+        stream.set_read_timeout(Some(WEBHOOK_TIMEOUT))?;
+        stream.set_write_timeout(Some(WEBHOOK_TIMEOUT))?;
+
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+             Connection: close\r\n\r\n{}",
+            self.path,
+            self.host,
+            body.len(),
+            body
+        )?;
+        stream.flush()?;
+
+        let mut status = String::new();
+        BufReader::new(stream).read_line(&mut status)?;
+        match status.split_whitespace().nth(1) {
+            Some(code) if code.starts_with('2') => Ok(()),
+            _ => Err(format!("Webhook failed: {}", status.trim()).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{io::Read, net::TcpListener, thread};
+
+    fn an_event() -> AlertEvent {
+        AlertEvent {
+            serial: 2312,
+            condition: "868.0-868.6 > -80dBm".parse().unwrap(),
+            peak_frequency: 868.3e6,
+            peak_power: -62.5,
+            timestamp: UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+        }
+    }
+
+    #[test]
+    fn given_the_default_template_when_render_then_return_the_event_as_json() {
+        let webhook = Webhook::new("http://localhost/hook", DEFAULT_WEBHOOK_TEMPLATE).unwrap();
+
+        assert_eq!(
+            webhook.render(&an_event()),
+            r#"{"serial":2312,"band":"868.000-868.600 MHz","peak_mhz":868.3000,"peak_dbm":-62.50,"timestamp":1700000000}"#
+        );
+    }
+
+    #[test]
+    fn given_a_template_when_render_then_escape_the_text_values() {
+        let webhook = Webhook::new("http://localhost", r#"{"text":"SA430 {serial}: {condition}"}"#).unwrap();
+
+        assert_eq!(
+            webhook.render(&an_event()),
+            r#"{"text":"SA430 2312: 868.000-868.600 MHz > -80 dBm"}"#
+        );
+        assert_eq!(escape("a \"b\"\n"), "a \\\"b\\\"\\u000a");
+    }
+
+    #[test]
+    fn given_urls_when_new_webhook_then_parse_the_host_port_and_path() {
+        let webhook = Webhook::new("http://hooks.local:8080/services/alert", "").unwrap();
+        assert_eq!(
+            (webhook.host.as_str(), webhook.port, webhook.path.as_str()),
+            ("hooks.local", 8080, "/services/alert")
+        );
+
+        assert_eq!(Webhook::new("http://hooks.local", "").unwrap().port, 80);
+        assert!(Webhook::new("https://hooks.local", "").is_err());
+        assert!(Webhook::new("hooks.local", "").is_err());
+    }
+
+    #[test]
+    fn given_a_server_when_post_then_send_the_payload() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 1024];
+            while !String::from_utf8_lossy(&request).ends_with('}') {
+                let count = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..count]);
+            }
+            stream.write_all(b"HTTP/1.1 204 No Content\r\n\r\n").unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        Webhook::new(&url, r#"{"serial":{serial}}"#)
+            .unwrap()
+            .post(&an_event())
+            .unwrap();
+
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /hook HTTP/1.1\r\n"));
+        assert!(request.contains("Content-Length: 15\r\n"));
+        assert!(request.ends_with("\r\n\r\n{\"serial\":2312}"));
+    }
+}
@@ -0,0 +1,161 @@
+//! # Client Module
+//!
+//! This module reads the sweeps of a capture running on another host, so applications can consume local and remote
+//! devices through the same [`SweepSource`](crate::sink::SweepSource) trait.
+//!
+//! The remote capture streams its sweeps as the records of the [`binary`](crate::formats::binary) format over TCP, like
+//! a capture started with `sa430 capture <PORT> --listen <ADDR>`, which can also require a token, see
+//! `Client::connect_with_token`. Records are read as they arrive; use a [`Throttled`](crate::sink::Throttled) sink on
+//! the remote side for slow links. With the `tls` feature, `Client::connect_tls` reads a capture served over TLS, see
+//! the `tls` module.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use sa430::client::Client;
+//! use sa430::formats::binary::BinarySink;
+//! use sa430::sink::{Sink, SweepRecord, SweepSource};
+//! use sa430::spectrum::Spectrum;
+//!
+//! let mut stream = Vec::new();
+//! BinarySink::new(&mut stream)
+//!     .write(&SweepRecord::new(0, Spectrum::from_sweep(868.0e6, 0.1e6, &[-90.0])))
+//!     .unwrap();
+//!
+//! // Client::connect("sa430-host:5430") reads the same records from a TCP stream.
+//! let mut client = Client::from_reader(stream.as_slice());
+//! assert_eq!(client.next_sweep().unwrap().unwrap().sequence, 0);
+//! assert!(client.next_sweep().unwrap().is_none());
+//! ```
+
+use std::{
+    error::Error,
+    io::{BufReader, Read},
+    net::TcpStream,
+};
+
+use crate::auth::{send_token_over, Token};
+use crate::formats::binary::read_record;
+use crate::sink::{SweepRecord, SweepSource};
+#[cfg(feature = "tls")]
+use crate::tls::{ClientStream, TlsConnector};
+
+/// Default TCP port of a remote capture.
+pub const DEFAULT_PORT: u16 = 5430;
+
+/// Reads the sweeps streamed by a remote capture.
+pub struct Client<R: Read> {
+    input: BufReader<R>,
+}
+
+impl Client<TcpStream> {
+    /// Connects to a remote capture at `address`, as `host:port`, or `host` for the [`DEFAULT_PORT`].
+    pub fn connect(address: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(Client::from_reader(connect_tcp(address)?))
+    }
+
+    /// Connects to a remote capture at `address` requiring a token, see the [`auth`](crate::auth) module.
+    pub fn connect_with_token(address: &str, token: &Token) -> Result<Self, Box<dyn Error>> {
+        let mut client = Client::connect(address)?;
+        send_token_over(&mut client.input, token)?;
+        Ok(client)
+    }
+}
+
+#[cfg(feature = "tls")]
+impl Client<ClientStream> {
+    /// Connects to a remote capture served over TLS at `address`, like [`Client::connect`], verifying the certificate
+    /// of its host with `connector`.
+    pub fn connect_tls(address: &str, connector: &TlsConnector) -> Result<Self, Box<dyn Error>> {
+        let stream = connector.connect(host(address), connect_tcp(address)?)?;
+        Ok(Client::from_reader(stream))
+    }
+
+    /// Connects to a remote capture served over TLS at `address` requiring a token, see [`Client::connect_tls`].
+    pub fn connect_tls_with_token(
+        address: &str,
+        connector: &TlsConnector,
+        token: &Token,
+    ) -> Result<Self, Box<dyn Error>> {
+        let mut client = Client::connect_tls(address, connector)?;
+        send_token_over(&mut client.input, token)?;
+        Ok(client)
+    }
+}
+
+/// Opens a TCP connection to `address`, as `host:port`, or `host` for the [`DEFAULT_PORT`].
+fn connect_tcp(address: &str) -> Result<TcpStream, Box<dyn Error>> {
+    let stream = match address.contains(':') {
+        true => TcpStream::connect(address)?,
+        false => TcpStream::connect((address, DEFAULT_PORT))?,
+    };
+    stream.set_nodelay(true)?;
+    Ok(stream)
+}
+
+/// Returns the host of `address`, without its port and the brackets of an IPv6 address.
+#[cfg(feature = "tls")]
+fn host(address: &str) -> &str {
+    match address.rsplit_once(':') {
+        Some((host, _)) => host.trim_start_matches('[').trim_end_matches(']'),
+        None => address,
+    }
+}
+
+impl<R: Read> Client<R> {
+    /// Creates a client reading the records from `input`, like a saved binary capture.
+    pub fn from_reader(input: R) -> Self {
+        Client {
+            input: BufReader::new(input),
+        }
+    }
+}
+
+impl<R: Read> SweepSource for Client<R> {
+    fn next_sweep(&mut self) -> Result<Option<SweepRecord>, Box<dyn Error>> {
+        read_record(&mut self.input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{io::Write, net::TcpListener, thread};
+
+    use crate::formats::binary::BinarySink;
+    use crate::sink::Sink;
+    use crate::spectrum::Spectrum;
+
+    #[test]
+    fn given_a_remote_capture_when_next_sweep_then_return_its_records_in_order() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut sink = BinarySink::new(stream);
+            for sequence in 0..3 {
+                let spectrum = Spectrum::from_sweep(868.0e6, 0.1e6, &[-90.0, -72.5]);
+                sink.write(&SweepRecord::new(sequence, spectrum)).unwrap();
+            }
+            sink.close().unwrap();
+        });
+
+        let mut client = Client::connect(&address).unwrap();
+        let mut sequences = Vec::new();
+        while let Some(record) = client.next_sweep().unwrap() {
+            sequences.push(record.sequence);
+        }
+        server.join().unwrap();
+
+        assert_eq!(sequences, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn given_a_stream_that_is_not_a_capture_when_next_sweep_then_return_error() {
+        let mut input = Vec::new();
+        input.write_all(b"HTTP/1.1 400 Bad Request\r\n").unwrap();
+
+        assert!(Client::from_reader(input.as_slice()).next_sweep().is_err());
+    }
+}
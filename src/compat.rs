@@ -0,0 +1,230 @@
+//! # Compat Module
+//!
+//! This module encodes the minimum firmware versions required by each feature of the library, so a device with an old
+//! or erased firmware is rejected when connecting, with a hint on how to fix it, instead of failing in the middle of an
+//! operation.
+//!
+//! The SA430 runs two firmwares: the core, which handles the general, production and flash commands, and the spectrum
+//! analyzer, which handles the measurement commands. The minimum versions are the ones checked by the TI software when
+//! connecting, see `docs/protocol.md`.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use sa430::compat::{check, CompatError, Feature};
+//!
+//! assert!(check(Feature::Capture, 0x020A, 0x0205).is_ok());
+//! assert!(check(Feature::Calibration, 0x020A, 0x0203).is_ok());
+//! assert!(matches!(
+//!     check(Feature::Capture, 0x020A, 0x0203),
+//!     Err(CompatError::IncompatibleFirmware(Feature::Capture, _, 0x0204, 0x0203))
+//! ));
+//! ```
+
+use std::{error::Error, fmt};
+
+use crate::frame::Command;
+
+/// Minimum supported version of the core firmware.
+pub const MIN_CORE_VERSION: u16 = 0x0209;
+
+/// Minimum supported version of the spectrum analyzer firmware.
+pub const MIN_SPECTRUM_VERSION: u16 = 0x0204;
+
+/// Version reported by an erased firmware.
+const ERASED_VERSION: u16 = 0xFFFF;
+
+/// Firmwares of the device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Firmware {
+    /// Core firmware.
+    Core,
+
+    /// Spectrum analyzer firmware.
+    Spectrum,
+}
+
+impl fmt::Display for Firmware {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Firmware::Core => write!(f, "core"),
+            Firmware::Spectrum => write!(f, "spectrum analyzer"),
+        }
+    }
+}
+
+/// Features of the library, each requiring a set of firmware versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Feature {
+    /// Reading the device identification and firmware versions, always supported.
+    Identification,
+
+    /// Controlling the device, like blinking the LED or rebooting it.
+    Control,
+
+    /// Reading the calibration data from the flash memory.
+    Calibration,
+
+    /// Measuring the spectrum.
+    Capture,
+
+    /// Production commands, like reading the temperature or setting the hardware ID.
+    Production,
+}
+
+impl Feature {
+    /// Returns the feature that uses `command`.
+    pub fn of(command: Command) -> Feature {
+        match command {
+            Command::GetIdn | Command::GetSerialNumber | Command::GetCoreVersion | Command::GetSpectrumVersion => {
+                Feature::Identification
+            }
+            Command::FlashRead | Command::FlashWrite | Command::FlashErase | Command::FlashGetCrc => {
+                Feature::Calibration
+            }
+            Command::SetFStart
+            | Command::SetFStop
+            | Command::SetFStep
+            | Command::SetFrq
+            | Command::SetRbw
+            | Command::SetDac
+            | Command::SetGain
+            | Command::SetIf
+            | Command::InitParameter
+            | Command::GetSpecNoInit
+            | Command::SetFout
+            | Command::SweepEdc => Feature::Capture,
+            Command::GetProdVer
+            | Command::SetProdFwInit
+            | Command::GetTemp
+            | Command::SetHardwareId
+            | Command::GetHardwareId
+            | Command::GetBootCnt
+            | Command::SetFxtal
+            | Command::GetFxtal
+            | Command::GetChipTlv => Feature::Production,
+            _ => Feature::Control,
+        }
+    }
+
+    /// Returns the minimum version of each firmware required by the feature.
+    pub fn requirements(&self) -> &'static [(Firmware, u16)] {
+        match self {
+            Feature::Identification => &[],
+            Feature::Control | Feature::Calibration | Feature::Production => &[(Firmware::Core, MIN_CORE_VERSION)],
+            Feature::Capture => &[
+                (Firmware::Core, MIN_CORE_VERSION),
+                (Firmware::Spectrum, MIN_SPECTRUM_VERSION),
+            ],
+        }
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Feature::Identification => write!(f, "Identification"),
+            Feature::Control => write!(f, "Device control"),
+            Feature::Calibration => write!(f, "Calibration"),
+            Feature::Capture => write!(f, "Capture"),
+            Feature::Production => write!(f, "Production"),
+        }
+    }
+}
+
+/// Fails if the firmware versions, as reported by the device, are older than required by `feature`.
+pub fn check(feature: Feature, core_version: u16, spectrum_version: u16) -> Result<(), CompatError> {
+    for (firmware, required) in feature.requirements() {
+        let current = match firmware {
+            Firmware::Core => core_version,
+            Firmware::Spectrum => spectrum_version,
+        };
+        if current < *required || current == ERASED_VERSION {
+            return Err(CompatError::IncompatibleFirmware(
+                feature, *firmware, *required, current,
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Formats a firmware version as `major.minor`.
+pub fn format_version(version: u16) -> String {
+    format!("{}.{}", version >> 8, version & 0xFF)
+}
+
+/// Firmware compatibility errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CompatError {
+    /// The firmware of the device is older than required by the feature (feature, firmware, required, current).
+    IncompatibleFirmware(Feature, Firmware, u16, u16),
+}
+
+impl Error for CompatError {}
+
+impl fmt::Display for CompatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompatError::IncompatibleFirmware(feature, firmware, required, current) => {
+                write!(
+                    f,
+                    "{} requires {} firmware {} or newer, ",
+                    feature,
+                    firmware,
+                    format_version(*required)
+                )?;
+                match *current {
+                    ERASED_VERSION => write!(f, "the device has no {} firmware loaded. ", firmware)?,
+                    current => write!(f, "the device has {}. ", format_version(current))?,
+                }
+                write!(
+                    f,
+                    "Flash the latest firmware with the TI MSP-SA430-SUB1GHZ software before using this device"
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_supported_versions_when_check_then_accept_every_feature() {
+        for feature in [
+            Feature::Identification,
+            Feature::Control,
+            Feature::Calibration,
+            Feature::Capture,
+            Feature::Production,
+        ] {
+            assert_eq!(check(feature, MIN_CORE_VERSION, MIN_SPECTRUM_VERSION), Ok(()));
+        }
+    }
+
+    #[test]
+    fn given_an_old_spectrum_firmware_when_check_capture_then_return_incompatible_firmware() {
+        let error = check(Feature::Capture, 0x020A, 0x0203).unwrap_err();
+
+        assert_eq!(
+            error,
+            CompatError::IncompatibleFirmware(Feature::Capture, Firmware::Spectrum, 0x0204, 0x0203)
+        );
+        assert!(error
+            .to_string()
+            .starts_with("Capture requires spectrum analyzer firmware 2.4 or newer, the device has 2.3. Flash"));
+    }
+
+    #[test]
+    fn given_an_erased_core_firmware_when_check_then_only_accept_identification() {
+        assert_eq!(check(Feature::Identification, 0xFFFF, 0xFFFF), Ok(()));
+        assert!(check(Feature::Control, 0xFFFF, 0xFFFF)
+            .unwrap_err()
+            .to_string()
+            .contains("the device has no core firmware loaded"));
+    }
+}
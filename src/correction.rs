@@ -0,0 +1,286 @@
+//! # Correction Module
+//!
+//! This module converts the raw samples of a sweep into calibrated powers, with the gain tables of the `Calibration`
+//! stored in the device flash. It is the factory correction of every measurement, unlike the profiles of the
+//! [reference](../reference/index.html) module, which fine tune the result against an external reference.
+//!
+//! The device returns the RSSI of each bin in half dB, as a two's complement byte, before the gain of the Rx path.
+//! The gain, `β`, is a polynomial of the frequency, with the coefficients of the gain table of the frequency range of
+//! the sweep and of its reference level, so the power of the bin `n` is:
+//!
+//! ```text
+//! P(n) = sample(n) / 2 - β(f(n))  [dBm]
+//! ```
+//!
+//! Computing `β` is expensive, so a `SweepCorrection` computes it once per bin of a `SweepConfig`, and converts the
+//! samples of every measurement of the sweep with it.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use sa430::correction::SweepCorrection;
+//! use sa430::device::Calibration;
+//! use sa430::frequency::Frequency;
+//! use sa430::sweep::SweepConfig;
+//!
+//! fn to_dbm(calibration: &Calibration, samples: &[u8]) -> Result<Vec<f64>, Box<dyn std::error::Error>> {
+//!     let config = SweepConfig::with_auto_rbw(
+//!         Frequency::from_mhz(868.0),
+//!         Frequency::from_mhz(870.0),
+//!         Frequency::from_khz(100.0),
+//!         0,
+//!         calibration.xtal(),
+//!     );
+//!     let correction = SweepCorrection::new(calibration, &config)?;
+//!     Ok(correction.apply(samples)?.powers().collect())
+//! }
+//! ```
+
+use std::{
+    error::Error,
+    fmt::{self, Display},
+};
+
+use super::device::Calibration;
+use super::frequency::Frequency;
+use super::spectrum::Spectrum;
+use super::sweep::SweepConfig;
+
+/// Errors when correcting the samples of a sweep.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum CorrectionError {
+    /// The sweep from the start to the stop frequency isn't inside a single frequency range of the calibration.
+    OutsideRanges(Frequency, Frequency),
+
+    /// The calibration has no reference level with the index.
+    UnknownRefLevel(u8),
+
+    /// The number of samples isn't the number of bins of the sweep (expected, received).
+    SampleCount(usize, usize),
+}
+
+impl Error for CorrectionError {}
+
+impl Display for CorrectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CorrectionError::OutsideRanges(fstart, fstop) => {
+                write!(f, "Sweep from {} to {} is not inside a frequency range", fstart, fstop)
+            }
+            CorrectionError::UnknownRefLevel(index) => write!(f, "Unknown reference level index: {}", index),
+            CorrectionError::SampleCount(expected, received) => write!(
+                f,
+                "Invalid number of samples, expected: {}, got: {}",
+                expected, received
+            ),
+        }
+    }
+}
+
+/// Correction of the samples of a sweep, with the gain of the Rx path at each bin.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepCorrection {
+    config: SweepConfig,
+    range: usize,
+    ref_level: i8,
+    bins: Vec<(Frequency, f64)>,
+}
+
+impl SweepCorrection {
+    /// Creates the correction of the sweep `config`, with the gain table of its frequency range and reference level.
+    ///
+    /// Fails if the sweep isn't inside a single frequency range or the reference level isn't in the calibration.
+    pub fn new(calibration: &Calibration, config: &SweepConfig) -> Result<Self, CorrectionError> {
+        let range = calibration
+            .range_of(config.fstart, config.fstop)
+            .ok_or(CorrectionError::OutsideRanges(config.fstart, config.fstop))?;
+        SweepCorrection::with_range(calibration, config, range)
+    }
+
+    /// Creates the correction of the sweep `config` with the gain tables of the frequency range `range`, which isn't
+    /// checked against the frequencies of the sweep.
+    pub(crate) fn with_range(
+        calibration: &Calibration,
+        config: &SweepConfig,
+        range: usize,
+    ) -> Result<Self, CorrectionError> {
+        let index = config.ref_level_index as usize;
+        let (ref_level, gains) = calibration
+            .ref_levels
+            .get(index)
+            .zip(calibration.frq_gains_tables[range].get(index))
+            .ok_or(CorrectionError::UnknownRefLevel(config.ref_level_index))?;
+
+        let bins = (0..config.bin_count())
+            .map(|bin| {
+                let frequency = config.frequency_at(bin);
+                (frequency, gains.gain_at(frequency))
+            })
+            .collect();
+        Ok(SweepCorrection {
+            config: *config,
+            range,
+            ref_level: ref_level.dbm(),
+            bins,
+        })
+    }
+
+    /// Returns the configuration of the sweep.
+    pub fn config(&self) -> &SweepConfig {
+        &self.config
+    }
+
+    /// Returns the index of the frequency range of the sweep in the calibration.
+    pub fn range(&self) -> usize {
+        self.range
+    }
+
+    /// Returns the reference level of the sweep, in dBm.
+    pub fn ref_level(&self) -> i8 {
+        self.ref_level
+    }
+
+    /// Returns the number of bins of the sweep.
+    pub fn len(&self) -> usize {
+        self.bins.len()
+    }
+
+    /// Returns true if the sweep has no bins.
+    pub fn is_empty(&self) -> bool {
+        self.bins.is_empty()
+    }
+
+    /// Returns the frequency and the gain of the Rx path, in dB, of each bin.
+    pub fn bins(&self) -> &[(Frequency, f64)] {
+        &self.bins
+    }
+
+    /// Converts the samples of a measurement of the sweep, one per bin, into the calibrated spectrum, holding the
+    /// configuration of the sweep.
+    ///
+    /// Fails if there isn't a sample per bin.
+    pub fn apply(&self, samples: &[u8]) -> Result<Spectrum, CorrectionError> {
+        if samples.len() != self.bins.len() {
+            return Err(CorrectionError::SampleCount(self.bins.len(), samples.len()));
+        }
+
+        let points = self
+            .bins
+            .iter()
+            .zip(samples)
+            .map(|((frequency, gain), sample)| (frequency.hz(), to_dbm(*sample, *gain)))
+            .collect();
+        Ok(Spectrum::with_config(points, self.config))
+    }
+
+    /// Returns the sample the device sends for a power of `dbm` at the bin `bin`, the inverse of `apply`, saturating
+    /// at the limits of the RSSI. The index isn't checked against `len`.
+    pub fn sample(&self, bin: usize, dbm: f64) -> u8 {
+        to_sample(dbm, self.bins[bin].1)
+    }
+}
+
+/// Returns the power of a sample measured with a gain of `gain` dB, in dBm.
+fn to_dbm(sample: u8, gain: f64) -> f64 {
+    sample as i8 as f64 / 2.0 - gain
+}
+
+/// Returns the sample of a power of `dbm` measured with a gain of `gain` dB, saturated at the limits of the RSSI.
+fn to_sample(dbm: f64, gain: f64) -> u8 {
+    let rssi = (2.0 * (dbm + gain)).round();
+    rssi.clamp(i8::MIN as f64, i8::MAX as f64) as i8 as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::device::{FrequencyGain, FrequencyRange, RefLevel};
+    use crate::sweep::NOMINAL_XTAL_HZ;
+
+    fn mhz(value: f64) -> Frequency {
+        Frequency::from_mhz(value)
+    }
+
+    /// Calibration with the range 779 to 928 MHz only, as the third range, and a flat gain of 70 dB at the reference
+    /// level -50 dBm, with the index 3.
+    fn a_calibration() -> Calibration {
+        let range: Vec<u8> = [779_000_000u32, 928_000_000, 0]
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect();
+        let mut calibration = Calibration::default();
+        calibration.frq_ranges[2] = FrequencyRange::from(&range.try_into().unwrap());
+        calibration.ref_levels[3] = RefLevel::from(&[-50i8 as u8, 74]);
+        calibration.frq_gains_tables[2][3] = FrequencyGain::new(3, [70.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+        calibration
+    }
+
+    fn a_config() -> SweepConfig {
+        SweepConfig {
+            fstart: mhz(868.0),
+            fstop: mhz(868.2),
+            fstep: Frequency::from_khz(100.0),
+            rbw: Frequency::from_khz(58.0),
+            ref_level_index: 3,
+            xtal: Frequency::from_hz(NOMINAL_XTAL_HZ),
+        }
+    }
+
+    #[test]
+    fn given_samples_when_apply_then_subtract_the_gain_of_the_range_and_ref_level() {
+        let correction = SweepCorrection::new(&a_calibration(), &a_config()).unwrap();
+
+        let spectrum = correction.apply(&[0x10, 0x00, 0xF0]).unwrap();
+
+        assert_eq!(correction.range(), 2);
+        assert_eq!(correction.ref_level(), -50);
+        assert_eq!(spectrum.configs(), [a_config()]);
+        assert_eq!(
+            spectrum.points(),
+            [
+                (a_config().frequency_at(0).hz(), -62.0),
+                (a_config().frequency_at(1).hz(), -70.0),
+                (a_config().frequency_at(2).hz(), -78.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn given_a_power_when_sample_then_round_trip_through_apply_and_saturate() {
+        let correction = SweepCorrection::new(&a_calibration(), &a_config()).unwrap();
+
+        assert_eq!(correction.sample(0, -62.0), 0x10);
+        assert_eq!(correction.sample(1, -78.2), 0xF0);
+        assert_eq!(correction.sample(2, 0.0), 0x7F);
+        assert_eq!(correction.sample(2, -200.0), 0x80);
+    }
+
+    #[test]
+    fn given_an_invalid_sweep_when_new_or_apply_then_fail() {
+        let calibration = a_calibration();
+        let across = SweepConfig {
+            fstart: mhz(460.0),
+            fstop: mhz(800.0),
+            ..a_config()
+        };
+        let unknown = SweepConfig {
+            ref_level_index: 8,
+            ..a_config()
+        };
+
+        assert_eq!(
+            SweepCorrection::new(&calibration, &across),
+            Err(CorrectionError::OutsideRanges(mhz(460.0), mhz(800.0)))
+        );
+        assert_eq!(
+            SweepCorrection::new(&calibration, &unknown),
+            Err(CorrectionError::UnknownRefLevel(8))
+        );
+        assert_eq!(
+            SweepCorrection::new(&calibration, &a_config()).unwrap().apply(&[0, 0]),
+            Err(CorrectionError::SampleCount(3, 2))
+        );
+    }
+}
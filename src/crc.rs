@@ -19,6 +19,22 @@ pub fn crc16(raw: &[u8]) -> u16 {
     crc
 }
 
+/// Calculate the CRC-32 (IEEE 802.3) value of a slice of bytes, as used by zip files.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+
+    for value in data {
+        crc ^= *value as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0xEDB8_8320,
+                _ => crc >> 1,
+            };
+        }
+    }
+    !crc
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -29,4 +45,10 @@ mod tests {
         let crc = crc16(&data);
         assert_eq!(crc, 0x9509);
     }
+
+    #[test]
+    fn given_a_slice_when_crc32_then_compute_crc32_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(&[]), 0);
+    }
 }
@@ -1,10 +1,30 @@
-use std::error::Error;
+use std::{
+    collections::VecDeque,
+    error::Error,
+    io,
+    num::NonZeroU32,
+    thread,
+    time::{Duration, Instant},
+};
 
+use super::capabilities::Capabilities;
 use super::channel::*;
+use super::compat::{check, Feature};
+use super::correction::SweepCorrection;
+use super::engine::{builtin_engines, SpectrumEngine};
 use super::frame::*;
+use super::frequency::Frequency;
+use super::profile::ProfileReport;
 use super::protocol::*;
+use super::spectrum::Spectrum;
+use super::sweep::{SweepConfig, NOMINAL_XTAL_HZ};
 use crate::parser::ByteArrayParser;
 
+pub use super::protocol::{
+    CommandClass, CommandTiming, FlashReadPolicy, FlushPolicy, FrameDirection, FrameHandler, FrameRecord, LinkStats,
+    ProtocolError, Timeouts, WritePolicy, DEFAULT_TRANSCRIPT_SIZE,
+};
+
 /// Start address of the calibration data in the flash memory.
 const FLASH_PROG_HEADER_ADDR: u16 = 0xD400;
 
@@ -15,10 +35,10 @@ const FLASH_PROG_HEADER_SIZE: u16 = 0x000A;
 const FLASH_PROG_HEADER_TYPE: u16 = 0x003E;
 
 /// Start address of the calibration data in the flash memory.
-const FLASH_CALIBRATION_ADDR: u16 = 0xD40A;
+pub(crate) const FLASH_CALIBRATION_ADDR: u16 = 0xD40A;
 
 /// Size of the calibration data in the flash memory.
-const FLASH_CALIBRATION_SIZE: u16 = 0x0687;
+pub(crate) const FLASH_CALIBRATION_SIZE: u16 = 0x0687;
 
 /// Represents a frequency range with start and stop frequencies and number of samples.
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
@@ -31,6 +51,23 @@ pub struct FrequencyRange {
     samples: u32,
 }
 
+impl FrequencyRange {
+    /// Returns the start frequency in Hz.
+    pub fn f_start(&self) -> u32 {
+        self.f_start
+    }
+
+    /// Returns the stop frequency in Hz.
+    pub fn f_stop(&self) -> u32 {
+        self.f_stop
+    }
+
+    /// Returns the number of samples.
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+}
+
 impl From<&[u8; 12]> for FrequencyRange {
     fn from(value: &[u8; 12]) -> Self {
         Self {
@@ -50,6 +87,18 @@ pub struct RefLevel {
     gain: u8,
 }
 
+impl RefLevel {
+    /// Returns the reference level, in dBm.
+    pub fn dbm(&self) -> i8 {
+        self.value as i8
+    }
+
+    /// Returns the receiver gain setting of the reference level.
+    pub fn gain(&self) -> u8 {
+        self.gain
+    }
+}
+
 impl From<&[u8; 2]> for RefLevel {
     fn from(value: &[u8; 2]) -> Self {
         Self {
@@ -68,6 +117,32 @@ pub struct FrequencyGain {
     gains: [f64; 8],
 }
 
+impl FrequencyGain {
+    /// Creates a new frequency gain for the reference level at `ref_level_index`.
+    pub fn new(ref_level_index: u8, gains: [f64; 8]) -> Self {
+        Self { ref_level_index, gains }
+    }
+
+    /// Returns the reference level index.
+    pub fn ref_level_index(&self) -> u8 {
+        self.ref_level_index
+    }
+
+    /// Returns the gain values.
+    pub fn gains(&self) -> &[f64; 8] {
+        &self.gains
+    }
+
+    /// Returns the gain at `frequency`, the gain values being the coefficients of a polynomial of the frequency in Hz,
+    /// from the constant term up.
+    pub fn gain_at(&self, frequency: Frequency) -> f64 {
+        self.gains
+            .iter()
+            .rev()
+            .fold(0.0, |gain, coefficient| gain * frequency.hz() + coefficient)
+    }
+}
+
 impl From<&[u8; 65]> for FrequencyGain {
     fn from(value: &[u8; 65]) -> Self {
         let ref_level_index = value[0];
@@ -126,6 +201,102 @@ pub struct Calibration {
     pub frq_gains_tables: [[FrequencyGain; 8]; 3],
 }
 
+/// Health of a device, for monitoring a fleet of devices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Health {
+    /// Serial number of the device.
+    pub serial_number: u32,
+
+    /// Number of times the device booted.
+    pub boot_count: u32,
+
+    /// Temperature reading, as sent by the firmware.
+    pub temperature_raw: Vec<u8>,
+
+    /// Last error stored by the firmware before the health was read.
+    pub last_error: ErrorCode,
+
+    /// Counters of the frames exchanged with the device.
+    pub link: LinkStats,
+
+    /// Time since the device proxy was created.
+    pub uptime: Duration,
+}
+
+/// Part of a sweep inside a single hardware frequency range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SweepSegment {
+    /// Index of the frequency range in `Calibration::frq_ranges`, and of its gain tables.
+    pub range: usize,
+
+    /// Start frequency.
+    pub fstart: Frequency,
+
+    /// Stop frequency.
+    pub fstop: Frequency,
+}
+
+impl Calibration {
+    /// Returns the index of the frequency range that contains the sweep from `fstart` to `fstop`, if any.
+    pub fn range_of(&self, fstart: Frequency, fstop: Frequency) -> Option<usize> {
+        self.frq_ranges.iter().position(|range| {
+            range.f_start < range.f_stop
+                && Frequency::from_hz(range.f_start as f64) <= fstart
+                && fstop <= Frequency::from_hz(range.f_stop as f64)
+        })
+    }
+
+    /// Returns the crystal frequency, or the nominal one if the calibration doesn't have it.
+    pub fn xtal(&self) -> Frequency {
+        match self.xtal_freq_hz {
+            0 => Frequency::from_hz(NOMINAL_XTAL_HZ),
+            hz => Frequency::from_hz(hz as f64),
+        }
+    }
+
+    /// Splits a sweep from `fstart` to `fstop` with a step of `fstep` into one segment per hardware frequency range, so
+    /// each segment is measured with the gain tables of its range.
+    ///
+    /// The segments keep the frequencies on the grid of the requested sweep, and the parts of the sweep between
+    /// ranges, which the device can't measure, are left out. Fails if no part of the sweep is inside a range.
+    pub fn split_sweep(
+        &self,
+        fstart: Frequency,
+        fstop: Frequency,
+        fstep: Frequency,
+    ) -> Result<Vec<SweepSegment>, Box<dyn Error>> {
+        if fstep <= Frequency::ZERO || fstart > fstop {
+            return Err(format!("Invalid sweep: {} to {} with step {}", fstart, fstop, fstep).into());
+        }
+
+        let mut segments: Vec<SweepSegment> = self
+            .frq_ranges
+            .iter()
+            .enumerate()
+            .filter(|(_, range)| range.f_start < range.f_stop)
+            .filter_map(|(index, range)| {
+                let first = ((Frequency::from_hz(range.f_start as f64) - fstart) / fstep)
+                    .ceil()
+                    .max(0.0);
+                let last = ((Frequency::from_hz(range.f_stop as f64) - fstart) / fstep).floor();
+                let last = last.min(((fstop - fstart) / fstep).floor());
+                (first <= last).then_some(SweepSegment {
+                    range: index,
+                    fstart: fstart + first * fstep,
+                    fstop: fstart + last * fstep,
+                })
+            })
+            .collect();
+
+        if segments.is_empty() {
+            return Err(format!("Sweep from {} to {} is outside of the device ranges", fstart, fstop).into());
+        }
+
+        segments.sort_by(|a, b| a.fstart.hz().total_cmp(&b.fstart.hz()));
+        Ok(segments)
+    }
+}
+
 impl TryFrom<&[u8]> for Calibration {
     type Error = Box<dyn std::error::Error>;
 
@@ -196,47 +367,375 @@ impl From<&[u8]> for ProgHeader {
 /// # Note
 ///  - All methods are blocking.
 pub struct Sa430 {
-    channel: Box<dyn Channel>,
+    session: ProtocolSession,
     calibration: Option<Calibration>,
+    engines: Vec<Box<dyn SpectrumEngine>>,
+    engine: Option<usize>,
+    connected: Instant,
+    closed: bool,
 }
 
 impl Sa430 {
     /// Creates a new SA430 device with the specified channel.
     pub fn new(channel: Box<dyn Channel>) -> Self {
         Sa430 {
-            channel,
+            session: ProtocolSession::new(channel),
             calibration: None,
+            engines: builtin_engines(),
+            engine: None,
+            connected: Instant::now(),
+            closed: false,
+        }
+    }
+
+    /// Parks the device and releases the channel, reporting any error.
+    ///
+    /// Dropping the device parks it too, but ignoring errors.
+    pub fn close(mut self) -> Result<(), Box<dyn Error>> {
+        self.closed = true;
+        self.session.abort()
+    }
+
+    /// Returns the response timeouts of each command class.
+    pub fn timeouts(&self) -> &Timeouts {
+        self.session.timeouts()
+    }
+
+    /// Sets the response timeouts of each command class.
+    pub fn set_timeouts(&mut self, timeouts: Timeouts) {
+        self.session.set_timeouts(timeouts);
+    }
+
+    /// Returns the policy used to write frames to the channel.
+    pub fn write_policy(&self) -> &WritePolicy {
+        self.session.write_policy()
+    }
+
+    /// Sets the policy used to write frames to the channel.
+    pub fn set_write_policy(&mut self, policy: WritePolicy) {
+        self.session.set_write_policy(policy);
+    }
+
+    /// Returns the policy used to read the flash memory, like the calibration data.
+    pub fn flash_read_policy(&self) -> &FlashReadPolicy {
+        self.session.flash_read_policy()
+    }
+
+    /// Sets the policy used to read the flash memory.
+    pub fn set_flash_read_policy(&mut self, policy: FlashReadPolicy) {
+        self.session.set_flash_read_policy(policy);
+    }
+
+    /// Reads the calibration data with each of the `candidates` policies and keeps the fastest one.
+    ///
+    /// Fails, keeping the current policy, if a read fails or returns different data than the others, since the
+    /// device can't keep up with that policy.
+    pub fn tune_flash_reads(&mut self, candidates: &[FlashReadPolicy]) -> Result<FlashReadPolicy, Box<dyn Error>> {
+        let current = *self.session.flash_read_policy();
+        let mut best: Option<(Duration, FlashReadPolicy)> = None;
+        let mut reference: Option<Vec<u8>> = None;
+
+        for candidate in candidates {
+            self.session.set_flash_read_policy(*candidate);
+            let start = Instant::now();
+            let data = self.session.read_flash(FLASH_CALIBRATION_ADDR, FLASH_CALIBRATION_SIZE);
+            let elapsed = start.elapsed();
+            self.session.set_flash_read_policy(current);
+
+            let data = data?;
+            if reference.get_or_insert_with(|| data.clone()) != &data {
+                return Err(format!("Inconsistent flash data read with {:?}", candidate).into());
+            }
+            if best.is_none_or(|(duration, _)| elapsed < duration) {
+                best = Some((elapsed, *candidate));
+            }
         }
+
+        let (_, policy) = best.ok_or("No flash read policy to tune")?;
+        self.session.set_flash_read_policy(policy);
+        Ok(policy)
+    }
+
+    /// Dumps every frame sent and received to `output`, as annotated hex, or stops dumping with `None`.
+    pub fn set_frame_dump(&mut self, output: Option<Box<dyn io::Write + Send>>) {
+        self.session.set_frame_dump(output);
+    }
+
+    /// Returns the last frames sent to and received from the device, oldest first, kept even when they aren't dumped.
+    pub fn recent_frames(&self) -> &VecDeque<FrameRecord> {
+        self.session.recent_frames()
+    }
+
+    /// Sets how many of the last frames exchanged are kept, [`DEFAULT_TRANSCRIPT_SIZE`] by default, 0 disables it.
+    pub fn set_transcript_size(&mut self, size: usize) {
+        self.session.set_transcript_size(size);
+    }
+
+    /// Returns how the frames received from the device are parsed.
+    pub fn parse_mode(&self) -> ParseMode {
+        self.session.parse_mode()
+    }
+
+    /// Sets how the frames received from the device are parsed, [`ParseMode::Lenient`] skips the corrupt frames of a
+    /// noisy link instead of failing the command.
+    pub fn set_parse_mode(&mut self, mode: ParseMode) {
+        self.session.set_parse_mode(mode);
+    }
+
+    /// Subscribes a handler to the frames the device sends without being asked, like the frames of some firmware
+    /// states, instead of failing the command being executed.
+    pub fn subscribe(&mut self, handler: Box<dyn FrameHandler>) {
+        self.session.subscribe(handler);
+    }
+
+    /// Enables or disables recording a latency histogram per command, see [`Sa430::profile_report`].
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.session.set_profiling(enabled);
+    }
+
+    /// Returns the latency histograms of the commands executed since profiling was enabled, if enabled.
+    pub fn profile_report(&self) -> Option<&ProfileReport> {
+        self.session.profile_report()
+    }
+
+    /// Returns the host timestamps around the last command that completed.
+    ///
+    /// Recording the timing of each sweep allows the sweeps from multiple devices to be aligned in post-processing.
+    pub fn last_command_timing(&self) -> Option<&CommandTiming> {
+        self.session.last_timing()
+    }
+
+    /// Returns true if a command didn't complete and the link must be recovered before sending new commands.
+    ///
+    /// While busy, all methods fail with `ProtocolError::DeviceBusy` instead of mixing their frames with the stale
+    /// replies of the previous command.
+    pub fn is_busy(&self) -> bool {
+        self.session.is_busy()
+    }
+
+    /// Brings the link back to a known state after a command didn't complete, discarding its stale replies.
+    pub fn recover(&mut self) -> Result<(), Box<dyn Error>> {
+        self.session.recover()
+    }
+
+    /// Stops the current operation and parks the device, so it can be safely released.
+    ///
+    /// Sends a `Sync` command, discards any stale reply and flushes the channel.
+    pub fn abort(&mut self) -> Result<(), Box<dyn Error>> {
+        self.session.abort()
     }
 
     /// Gets the device identification string.
     pub fn idn(&mut self) -> Result<String, Box<dyn Error>> {
-        get_string(self.channel.as_mut(), Command::GetIdn)
+        self.session.get_string(Command::GetIdn)
     }
 
     /// Gets the device model.
     pub fn serial_number(&mut self) -> Result<u32, Box<dyn Error>> {
-        get_u32(self.channel.as_mut(), Command::GetSerialNumber)
+        self.session.get_u32(Command::GetSerialNumber)
     }
 
     /// Gets the device model.
     pub fn core_version(&mut self) -> Result<String, Box<dyn Error>> {
-        get_u16(self.channel.as_mut(), Command::GetCoreVersion).map(|v| (format!("{}.{}", v >> 8, v & 0xFF)))
+        self.session
+            .get_u16(Command::GetCoreVersion)
+            .map(|v| format!("{}.{}", v >> 8, v & 0xFF))
     }
 
     /// Gets the device model.
     pub fn spectrum_version(&mut self) -> Result<String, Box<dyn Error>> {
-        get_u16(self.channel.as_mut(), Command::GetSpectrumVersion).map(|v| (format!("{}.{}", v >> 8, v & 0xFF)))
+        self.session
+            .get_u16(Command::GetSpectrumVersion)
+            .map(|v| format!("{}.{}", v >> 8, v & 0xFF))
+    }
+
+    /// Probes the firmware versions and calibration of the device, to find the operations it supports.
+    ///
+    /// The maximum number of points of a sweep is read from the calibration, which is fetched only when the spectrum
+    /// analyzer firmware is supported.
+    pub fn capabilities(&mut self) -> Result<Capabilities, Box<dyn Error>> {
+        let core_version = self.session.get_u16(Command::GetCoreVersion)?;
+        let spectrum_version = self.session.get_u16(Command::GetSpectrumVersion)?;
+        let mut capabilities = Capabilities::new(core_version, spectrum_version, 0);
+
+        if capabilities.spectrum_supported() {
+            let calibration = self.calibration()?;
+            let max_bins = calibration
+                .frq_ranges
+                .iter()
+                .map(|range| range.samples())
+                .max()
+                .unwrap_or(0);
+            capabilities = Capabilities::new(core_version, spectrum_version, max_bins);
+        }
+        Ok(capabilities)
+    }
+
+    /// Fails with [`CompatError::IncompatibleFirmware`](crate::compat::CompatError) if the device firmware is older
+    /// than required by `feature`.
+    ///
+    /// The firmware versions are only read when the feature requires any.
+    pub fn check_compat(&mut self, feature: Feature) -> Result<(), Box<dyn Error>> {
+        if feature.requirements().is_empty() {
+            return Ok(());
+        }
+        let core_version = self.session.get_u16(Command::GetCoreVersion)?;
+        let spectrum_version = self.session.get_u16(Command::GetSpectrumVersion)?;
+        Ok(check(feature, core_version, spectrum_version)?)
+    }
+
+    /// Gets the last error stored by the firmware, to find why a previous command failed.
+    ///
+    /// Any command sent before may replace the stored error, so this should be the first command after the failure.
+    pub fn last_error(&mut self) -> Result<ErrorCode, Box<dyn Error>> {
+        self.session
+            .exec_with_result(Command::GetLastError)
+            .map(ErrorCode::from)
+    }
+
+    /// Gets the number of times the device booted.
+    pub fn boot_count(&mut self) -> Result<u32, Box<dyn Error>> {
+        self.session.get_u32(Command::GetBootCnt)
+    }
+
+    /// Gets the temperature reading of the device, as the raw bytes sent by the firmware, since their format is
+    /// undocumented.
+    pub fn temperature_raw(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.session.exec_with_result(Command::GetTemp)
+    }
+
+    /// Returns the counters of the frames exchanged with the device since the proxy was created.
+    pub fn link_stats(&self) -> &LinkStats {
+        self.session.link_stats()
+    }
+
+    /// Gets the health of the device, combining its boot count, temperature and last error with the link stats.
+    ///
+    /// The last error is read first, before the other commands replace it. The device has no clock, so the uptime is
+    /// the time since the proxy was created.
+    pub fn health(&mut self) -> Result<Health, Box<dyn Error>> {
+        let last_error = self.last_error()?;
+        Ok(Health {
+            serial_number: self.serial_number()?,
+            boot_count: self.boot_count()?,
+            temperature_raw: self.temperature_raw()?,
+            last_error,
+            link: *self.link_stats(),
+            uptime: self.connected.elapsed(),
+        })
     }
 
     /// Blink the device LED.
     pub fn blink(&mut self) -> Result<(), Box<dyn Error>> {
-        exec(self.channel.as_mut(), Command::BlinkLed)
+        self.session.exec(Command::BlinkLed)
+    }
+
+    /// Blink the device LED `count` times, waiting `interval` between blinks, to identify a unit for longer than a
+    /// single blink.
+    pub fn blink_repeat(&mut self, count: NonZeroU32, interval: Duration) -> Result<(), Box<dyn Error>> {
+        for index in 0..count.get() {
+            if index > 0 {
+                thread::sleep(interval);
+            }
+            self.blink()?;
+        }
+        Ok(())
+    }
+
+    /// Registers the engine of another spectrum analyzer firmware, tried before the engines already registered, which
+    /// start with the built-in ones.
+    pub fn register_engine(&mut self, engine: Box<dyn SpectrumEngine>) {
+        self.engines.insert(0, engine);
+        self.engine = None;
+    }
+
+    /// Selects the engine of the spectrum analyzer firmware, the first registered engine that supports the version
+    /// reported by the device.
+    ///
+    /// Result is cached for subsequent calls, until another engine is registered.
+    ///
+    /// Fails with [`CompatError::IncompatibleFirmware`](crate::compat::CompatError) if the device has only the core
+    /// firmware, see [`Capabilities::is_core_only`], as the spectrum analyzer firmware must be loaded with the TI
+    /// software.
+    pub fn select_engine(&mut self) -> Result<&dyn SpectrumEngine, Box<dyn Error>> {
+        let index = match self.engine {
+            Some(index) => index,
+            None => {
+                let version = self.session.get_u16(Command::GetSpectrumVersion)?;
+                let Some(index) = self.engines.iter().position(|engine| engine.supports(version)) else {
+                    let core_version = self.session.get_u16(Command::GetCoreVersion)?;
+                    if Capabilities::new(core_version, version, 0).is_core_only() {
+                        check(Feature::Capture, core_version, version)?;
+                    }
+                    return Err(format!(
+                        "No engine supports the spectrum analyzer firmware {}.{}",
+                        version >> 8,
+                        version & 0xFF
+                    )
+                    .into());
+                };
+                self.engine = Some(index);
+                index
+            }
+        };
+        Ok(self.engines[index].as_ref())
+    }
+
+    /// Sets up the device for the spectrum measurement of `config`, with the requests of the selected engine.
+    pub fn init_sweep(&mut self, config: &SweepConfig) -> Result<(), Box<dyn Error>> {
+        let requests = self.select_engine()?.init_requests(config)?;
+        for request in &requests {
+            self.session.exec_frame(request)?;
+        }
+        Ok(())
+    }
+
+    /// Measures the spectrum of `config` with the selected engine, converting the samples to dBm with the
+    /// `SweepCorrection` of the calibration.
+    ///
+    /// Fails if the sweep isn't inside a single frequency range, or the device doesn't return one sample per bin.
+    pub fn measure(&mut self, config: &SweepConfig) -> Result<Spectrum, Box<dyn Error>> {
+        let correction = SweepCorrection::new(self.calibration()?, config)?;
+        self.init_sweep(config)?;
+        let request = self.select_engine()?.measure_request(config);
+        let samples = self.session.exec_stream(&request)?;
+        Ok(correction.apply(&samples)?)
+    }
+
+    /// Captures the spectrum from `fstart` to `fstop` with a step of `fstep`, at the reference level `ref_level` in
+    /// dBm, measuring one segment per frequency range of the calibration, with the resolution bandwidth of
+    /// [`SweepConfig::with_auto_rbw`].
+    ///
+    /// The spectrum holds the `SweepConfig` of each segment, see `Spectrum::configs`.
+    ///
+    /// Fails if the reference level is not in the calibration or no part of the sweep is inside a range.
+    pub fn capture(
+        &mut self,
+        fstart: Frequency,
+        fstop: Frequency,
+        fstep: Frequency,
+        ref_level: i8,
+    ) -> Result<Spectrum, Box<dyn Error>> {
+        let calibration = self.calibration()?;
+        let ref_level_index = calibration
+            .ref_levels
+            .iter()
+            .position(|level| level.dbm() == ref_level)
+            .ok_or_else(|| format!("Unknown reference level: {} dBm", ref_level))?;
+        let xtal = calibration.xtal();
+        let segments = calibration.split_sweep(fstart, fstop, fstep)?;
+
+        let mut spectra = Vec::with_capacity(segments.len());
+        for segment in segments {
+            let config = SweepConfig::with_auto_rbw(segment.fstart, segment.fstop, fstep, ref_level_index as u8, xtal);
+            spectra.push(self.measure(&config)?);
+        }
+        Ok(Spectrum::concat(spectra))
     }
 
     /// Reboot the device.
     pub fn reboot(&mut self) -> Result<(), Box<dyn Error>> {
-        exec(self.channel.as_mut(), Command::HardwareReset)
+        self.session.exec(Command::HardwareReset)
     }
 
     /// Gets the device calibration data.
@@ -247,7 +746,14 @@ impl Sa430 {
             self.calibration = Some(self.fetch_calibration()?);
         }
 
-        return Ok(self.calibration.as_ref().unwrap());
+        Ok(self.calibration.as_ref().unwrap())
+    }
+
+    /// Replaces the calibration data used by the device proxy.
+    ///
+    /// The override is kept in memory only, the calibration stored in the device flash is not changed.
+    pub fn set_calibration(&mut self, calibration: Calibration) {
+        self.calibration = Some(calibration);
     }
 
     /// Prettifies the calibration data version.
@@ -268,7 +774,9 @@ impl Sa430 {
     }
 
     fn check_prog_header(&mut self) -> Result<(), Box<dyn Error>> {
-        let prog_header_vec = read_flash(self.channel.as_mut(), FLASH_PROG_HEADER_ADDR, FLASH_PROG_HEADER_SIZE)?;
+        let prog_header_vec = self
+            .session
+            .read_flash(FLASH_PROG_HEADER_ADDR, FLASH_PROG_HEADER_SIZE)?;
         let prog_header: ProgHeader = prog_header_vec.as_slice().into();
         if prog_header.mem_type != FLASH_PROG_HEADER_TYPE {
             let message = format!(
@@ -281,7 +789,372 @@ impl Sa430 {
     }
 
     fn read_calibration(&mut self) -> Result<Calibration, Box<dyn Error>> {
-        let calibration_vec = read_flash(self.channel.as_mut(), FLASH_CALIBRATION_ADDR, FLASH_CALIBRATION_SIZE)?;
+        let calibration_vec = self
+            .session
+            .read_flash(FLASH_CALIBRATION_ADDR, FLASH_CALIBRATION_SIZE)?;
         calibration_vec.as_slice().try_into()
     }
 }
+
+/// Parks the device so the next session doesn't receive stale half-frames, e.g. after Ctrl+C.
+impl Drop for Sa430 {
+    fn drop(&mut self) {
+        if !self.closed {
+            let _ = self.session.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{
+        io,
+        sync::{Arc, Mutex},
+        thread,
+    };
+
+    fn mhz(value: f64) -> Frequency {
+        Frequency::from_mhz(value)
+    }
+
+    fn a_range(f_start: u32, f_stop: u32) -> FrequencyRange {
+        let bytes: Vec<u8> = [f_start.to_be_bytes(), f_stop.to_be_bytes(), 0u32.to_be_bytes()].concat();
+        FrequencyRange::from(&bytes.try_into().unwrap())
+    }
+
+    fn a_calibration() -> Calibration {
+        Calibration {
+            frq_ranges: [
+                a_range(300_000_000, 348_000_000),
+                a_range(389_000_000, 464_000_000),
+                a_range(779_000_000, 928_000_000),
+            ],
+            ..Default::default()
+        }
+    }
+
+    /// Channel that keeps the written bytes after being dropped, and reads as end of file.
+    struct SharedChannel(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Read for SharedChannel {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Ok(0)
+        }
+    }
+
+    impl io::Write for SharedChannel {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Channel for SharedChannel {}
+
+    /// Mock channel that keeps the written bytes after being dropped.
+    struct RecordingChannel {
+        channel: crate::channel::fixtures::MockChannel,
+        written: Arc<Mutex<Vec<u8>>>,
+    }
+
+    impl io::Read for RecordingChannel {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.channel.read(buf)
+        }
+    }
+
+    impl io::Write for RecordingChannel {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.written.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Channel for RecordingChannel {}
+
+    #[test]
+    fn given_a_device_when_dropped_then_send_sync() {
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let device = Sa430::new(Box::new(SharedChannel(written.clone())));
+
+        drop(device);
+
+        assert_eq!(*written.lock().unwrap(), Frame::new(Command::Sync).to_bytes());
+    }
+
+    #[test]
+    fn given_a_device_when_moved_to_another_thread_then_use_it_there() {
+        let mut channel = crate::channel::fixtures::MockChannel::new();
+        channel.add_response(&fixture::an_ack_response(Command::BlinkLed).to_bytes());
+        let mut device = Sa430::new(Box::new(channel));
+
+        let result = thread::spawn(move || device.blink().is_ok()).join().unwrap();
+
+        assert!(result);
+    }
+
+    #[test]
+    fn given_a_count_when_blink_repeat_then_send_a_blink_per_count() {
+        let mut channel = crate::channel::fixtures::MockChannel::new();
+        for _ in 0..3 {
+            channel.add_response(&fixture::an_ack_response(Command::BlinkLed).to_bytes());
+        }
+        let mut device = Sa430::new(Box::new(channel));
+
+        device
+            .blink_repeat(NonZeroU32::new(3).unwrap(), Duration::ZERO)
+            .unwrap();
+
+        assert!(device.blink().is_err());
+    }
+
+    /// Adds the ACKs of the requests setting up a sweep with the built-in engine.
+    fn add_init_sweep_acks(channel: &mut crate::channel::fixtures::MockChannel) {
+        for command in INIT_SWEEP_COMMANDS {
+            channel.add_response(&fixture::an_ack_response(command).to_bytes());
+        }
+    }
+
+    const INIT_SWEEP_COMMANDS: [Command; 7] = [
+        Command::SetFStart,
+        Command::SetFStop,
+        Command::SetFStep,
+        Command::SetRbw,
+        Command::SetIf,
+        Command::SetGain,
+        Command::InitParameter,
+    ];
+
+    #[test]
+    fn given_an_invalid_sweep_config_when_init_sweep_then_fail_before_sending_it() {
+        let mut channel = crate::channel::fixtures::MockChannel::new();
+        channel.add_response(&fixture::a_get_spectrum_version_response().to_bytes());
+        add_init_sweep_acks(&mut channel);
+        let mut device = Sa430::new(Box::new(channel));
+        let config = SweepConfig {
+            fstart: mhz(868.0),
+            fstop: mhz(870.0),
+            fstep: Frequency::ZERO,
+            rbw: Frequency::from_khz(58.0),
+            ref_level_index: 0,
+            xtal: Frequency::from_hz(NOMINAL_XTAL_HZ),
+        };
+
+        assert!(device.init_sweep(&config).is_err());
+        assert!(device
+            .init_sweep(&SweepConfig {
+                fstep: Frequency::from_khz(100.0),
+                ..config
+            })
+            .is_ok());
+    }
+
+    #[test]
+    fn given_samples_when_measure_then_convert_them_to_dbm_with_the_gain_of_the_range() {
+        let mut channel = crate::channel::fixtures::MockChannel::new();
+        channel.add_response(&fixture::a_get_spectrum_version_response().to_bytes());
+        add_init_sweep_acks(&mut channel);
+        channel.add_response(&fixture::an_ack_response(Command::GetSpecNoInit).to_bytes());
+        channel.add_response(&Frame::with_data(Command::GetSpecNoInit, &[0x10]).to_bytes());
+        channel.add_response(&Frame::with_data(Command::GetSpecNoInit, &[0x00, 0xF0]).to_bytes());
+        channel.add_response(&fixture::an_error_response(ErrorCode::NoError).to_bytes());
+        let mut calibration = a_calibration();
+        calibration.frq_gains_tables[2][3] = FrequencyGain::new(3, [70.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let mut device = Sa430::new(Box::new(RecordingChannel {
+            channel,
+            written: written.clone(),
+        }));
+        device.set_calibration(calibration);
+        let config = SweepConfig {
+            fstart: mhz(868.0),
+            fstop: mhz(868.2),
+            fstep: Frequency::from_khz(100.0),
+            rbw: Frequency::from_khz(58.0),
+            ref_level_index: 3,
+            xtal: Frequency::from_hz(NOMINAL_XTAL_HZ),
+        };
+
+        let spectrum = device.measure(&config).unwrap();
+
+        assert_eq!(
+            spectrum.points(),
+            [
+                (config.frequency_at(0).hz(), -62.0),
+                (config.frequency_at(1).hz(), -70.0),
+                (config.frequency_at(2).hz(), -78.0)
+            ]
+        );
+        let written = written.lock().unwrap().clone();
+        let requests: Vec<Frame> = FrameStream::new(written.as_slice()).map(Result::unwrap).collect();
+        let mut expected = vec![Frame::new(Command::GetSpectrumVersion)];
+        expected.extend(config.registers().unwrap().to_frames());
+        expected.push(Frame::new(Command::InitParameter));
+        expected.push(Frame::new(Command::GetSpecNoInit));
+        assert_eq!(requests, expected);
+    }
+
+    #[test]
+    fn given_a_sweep_across_ranges_when_measure_then_fail_before_sending_it() {
+        let channel = crate::channel::fixtures::MockChannel::new();
+        let mut device = Sa430::new(Box::new(channel));
+        device.set_calibration(a_calibration());
+        let config = SweepConfig {
+            fstart: mhz(460.0),
+            fstop: mhz(800.0),
+            fstep: Frequency::from_khz(100.0),
+            rbw: Frequency::from_khz(58.0),
+            ref_level_index: 0,
+            xtal: Frequency::from_hz(NOMINAL_XTAL_HZ),
+        };
+
+        assert_eq!(
+            device.measure(&config).unwrap_err().to_string(),
+            "Sweep from 460 MHz to 800 MHz is not inside a frequency range"
+        );
+    }
+
+    /// Engine of a modded firmware setting up the sweep with the frequency commands only.
+    struct FrequencyCommandsEngine;
+
+    impl SpectrumEngine for FrequencyCommandsEngine {
+        fn name(&self) -> &str {
+            "frequency-commands"
+        }
+
+        fn supports(&self, spectrum_version: u16) -> bool {
+            spectrum_version == 0x0205
+        }
+
+        fn init_requests(&self, config: &SweepConfig) -> Result<Vec<Frame>, Box<dyn Error>> {
+            let registers = config.registers()?;
+            Ok(registers.to_frames()[..3].to_vec())
+        }
+
+        fn measure_request(&self, _: &SweepConfig) -> Frame {
+            Frame::new(Command::GetSpecNoInit)
+        }
+    }
+
+    #[test]
+    fn given_a_registered_engine_when_select_engine_then_prefer_it_over_the_builtin_ones() {
+        let mut channel = crate::channel::fixtures::MockChannel::new();
+        channel.add_response(&fixture::a_get_spectrum_version_response().to_bytes());
+        for command in [Command::SetFStart, Command::SetFStop, Command::SetFStep] {
+            channel.add_response(&fixture::an_ack_response(command).to_bytes());
+        }
+        let mut device = Sa430::new(Box::new(channel));
+        device.register_engine(Box::new(FrequencyCommandsEngine));
+        let config = SweepConfig {
+            fstart: mhz(868.0),
+            fstop: mhz(870.0),
+            fstep: Frequency::from_khz(100.0),
+            rbw: Frequency::from_khz(58.0),
+            ref_level_index: 0,
+            xtal: Frequency::from_hz(NOMINAL_XTAL_HZ),
+        };
+
+        assert_eq!(device.select_engine().unwrap().name(), "frequency-commands");
+        assert!(device.init_sweep(&config).is_ok());
+    }
+
+    #[test]
+    fn given_an_unknown_firmware_when_select_engine_then_return_an_error() {
+        let mut channel = crate::channel::fixtures::MockChannel::new();
+        channel.add_response(&Frame::with_data(Command::GetSpectrumVersion, &0x0300u16.to_be_bytes()).to_bytes());
+        channel.add_response(&fixture::a_get_core_version_response().to_bytes());
+        let mut device = Sa430::new(Box::new(channel));
+
+        assert_eq!(
+            device.select_engine().err().unwrap().to_string(),
+            "No engine supports the spectrum analyzer firmware 3.0"
+        );
+    }
+
+    #[test]
+    fn given_a_core_only_device_when_select_engine_then_report_the_missing_spectrum_firmware() {
+        let mut channel = crate::channel::fixtures::MockChannel::new();
+        channel.add_response(&Frame::with_data(Command::GetSpectrumVersion, &0xFFFFu16.to_be_bytes()).to_bytes());
+        channel.add_response(&fixture::a_get_core_version_response().to_bytes());
+        let mut device = Sa430::new(Box::new(channel));
+
+        let error = device.select_engine().err().unwrap();
+
+        assert!(error.downcast_ref::<crate::compat::CompatError>().is_some());
+        assert!(error
+            .to_string()
+            .contains("the device has no spectrum analyzer firmware loaded"));
+    }
+
+    #[test]
+    fn given_a_device_when_close_then_report_abort_errors() {
+        let channel = crate::channel::fixtures::MockChannel::new();
+        let device = Sa430::new(Box::new(channel));
+
+        assert!(device.close().is_err());
+    }
+
+    #[test]
+    fn given_a_sweep_inside_a_range_when_split_sweep_then_return_a_single_segment() {
+        let segments = a_calibration().split_sweep(mhz(868.0), mhz(870.0), mhz(0.5)).unwrap();
+
+        assert_eq!(
+            segments,
+            vec![SweepSegment {
+                range: 2,
+                fstart: mhz(868.0),
+                fstop: mhz(870.0)
+            }]
+        );
+    }
+
+    #[test]
+    fn given_a_sweep_across_ranges_when_split_sweep_then_return_a_segment_per_range_on_the_grid() {
+        let segments = a_calibration().split_sweep(mhz(340.5), mhz(400.5), mhz(1.0)).unwrap();
+
+        assert_eq!(
+            segments,
+            vec![
+                SweepSegment {
+                    range: 0,
+                    fstart: mhz(340.5),
+                    fstop: mhz(347.5)
+                },
+                SweepSegment {
+                    range: 1,
+                    fstart: mhz(389.5),
+                    fstop: mhz(400.5)
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn given_a_sweep_outside_the_ranges_when_split_sweep_then_return_error() {
+        assert!(a_calibration().split_sweep(mhz(350.0), mhz(380.0), mhz(1.0)).is_err());
+        assert!(a_calibration()
+            .split_sweep(mhz(868.0), mhz(870.0), Frequency::ZERO)
+            .is_err());
+        assert!(Calibration::default()
+            .split_sweep(mhz(868.0), mhz(870.0), mhz(1.0))
+            .is_err());
+    }
+
+    #[test]
+    fn given_truncated_calibration_data_when_try_from_then_return_error() {
+        let data = [0xA5; FLASH_CALIBRATION_SIZE as usize];
+
+        for length in 0..data.len() {
+            assert!(Calibration::try_from(&data[..length]).is_err(), "{} bytes", length);
+        }
+        assert!(Calibration::try_from(&data[..]).is_ok());
+    }
+}
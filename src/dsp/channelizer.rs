@@ -0,0 +1,201 @@
+use crate::power::Dbm;
+use crate::spectrum::Spectrum;
+
+/// Default power above which a bin is considered occupied, in dBm.
+const DEFAULT_OCCUPANCY_THRESHOLD: f64 = -90.0;
+
+/// A channel of a channel plan.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelSpec {
+    /// Channel name.
+    pub name: String,
+
+    /// Center frequency, in Hz.
+    pub center: f64,
+
+    /// Channel bandwidth, in Hz.
+    pub bandwidth: f64,
+}
+
+impl ChannelSpec {
+    /// Creates a new channel centered at `center` with `bandwidth`, both in Hz.
+    pub fn new(name: &str, center: f64, bandwidth: f64) -> Self {
+        ChannelSpec {
+            name: name.to_string(),
+            center,
+            bandwidth,
+        }
+    }
+
+    /// Returns true if `frequency`, in Hz, is inside the channel.
+    pub fn contains(&self, frequency: f64) -> bool {
+        (frequency - self.center).abs() <= self.bandwidth / 2.0
+    }
+}
+
+/// A named list of channels.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelPlan {
+    name: String,
+    channels: Vec<ChannelSpec>,
+    threshold: f64,
+}
+
+impl ChannelPlan {
+    /// Creates a new channel plan.
+    pub fn new(name: &str, channels: Vec<ChannelSpec>) -> Self {
+        ChannelPlan {
+            name: name.to_string(),
+            channels,
+            threshold: DEFAULT_OCCUPANCY_THRESHOLD,
+        }
+    }
+
+    /// Creates a plan with `count` channels of `bandwidth`, the first centered at `first_center` and the others
+    /// `spacing` apart, all in Hz. Channels are named by their index.
+    pub fn uniform(name: &str, first_center: f64, spacing: f64, count: usize, bandwidth: f64) -> Self {
+        let channels = (0..count)
+            .map(|i| ChannelSpec::new(&i.to_string(), first_center + i as f64 * spacing, bandwidth))
+            .collect();
+        ChannelPlan::new(name, channels)
+    }
+
+    /// Returns the built-in plan named `name`, one of `lora-eu868` or `lora-us915`.
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "lora-eu868" => {
+                let centers = [868.1, 868.3, 868.5, 867.1, 867.3, 867.5, 867.7, 867.9];
+                let channels = centers
+                    .iter()
+                    .enumerate()
+                    .map(|(i, center)| ChannelSpec::new(&i.to_string(), center * 1e6, 125.0e3))
+                    .collect();
+                Some(ChannelPlan::new("lora-eu868", channels))
+            }
+            "lora-us915" => Some(ChannelPlan::uniform("lora-us915", 902.3e6, 200.0e3, 64, 125.0e3)),
+            _ => None,
+        }
+    }
+
+    /// Sets the power above which a bin is considered occupied, in dBm.
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Returns the plan name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the channels.
+    pub fn channels(&self) -> &[ChannelSpec] {
+        &self.channels
+    }
+
+    /// Returns the power above which a bin is considered occupied, in dBm.
+    pub fn threshold(&self) -> f64 {
+        self.threshold
+    }
+}
+
+/// Power measured in a channel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelPower {
+    /// Channel name.
+    pub name: String,
+
+    /// Center frequency, in Hz.
+    pub center: f64,
+
+    /// Number of spectrum bins inside the channel.
+    pub bins: usize,
+
+    /// Mean power of the bins, averaged in linear scale, in dBm, or `None` if the channel has no bins.
+    pub mean_dbm: Option<f64>,
+
+    /// Power of the strongest bin, in dBm, or `None` if the channel has no bins.
+    pub peak_dbm: Option<f64>,
+
+    /// Fraction of the bins above the plan threshold, from 0 to 1.
+    pub occupancy: f64,
+}
+
+/// Maps the bins of `spectrum` onto the channels of `plan`, returning the power of each channel in plan order.
+pub fn channelize(spectrum: &Spectrum, plan: &ChannelPlan) -> Vec<ChannelPower> {
+    plan.channels
+        .iter()
+        .map(|channel| {
+            let powers: Vec<f64> = spectrum
+                .iter()
+                .filter(|(frequency, _)| channel.contains(*frequency))
+                .map(|(_, power)| *power)
+                .collect();
+
+            let bins = powers.len();
+            let (mean_dbm, peak_dbm, occupancy) = if bins == 0 {
+                (None, None, 0.0)
+            } else {
+                let mean = Dbm::mean(powers.iter().copied().map(Dbm::new)).map(|mean| mean.dbm());
+                let peak = powers.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                let occupied = powers.iter().filter(|power| **power > plan.threshold).count();
+                (mean, Some(peak), occupied as f64 / bins as f64)
+            };
+
+            ChannelPower {
+                name: channel.name.clone(),
+                center: channel.center,
+                bins,
+                mean_dbm,
+                peak_dbm,
+                occupancy,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_builtin_plans_when_builtin_then_return_the_channels() {
+        let eu868 = ChannelPlan::builtin("LoRa-EU868").unwrap();
+        let us915 = ChannelPlan::builtin("lora-us915").unwrap();
+
+        assert_eq!(eu868.channels().len(), 8);
+        assert_eq!(eu868.channels()[0], ChannelSpec::new("0", 868.1e6, 125.0e3));
+        assert_eq!(us915.channels().len(), 64);
+        assert!(ChannelPlan::builtin("unknown").is_none());
+    }
+
+    #[test]
+    fn given_a_spectrum_when_channelize_then_report_power_per_channel() {
+        let spectrum = Spectrum::from_sweep(100.0, 10.0, &[-100.0, -60.0, -80.0, -100.0, -100.0, -100.0]);
+        let plan = ChannelPlan::uniform("test", 110.0, 30.0, 2, 20.0);
+
+        let channels = channelize(&spectrum, &plan);
+
+        assert_eq!(channels.len(), 2);
+        assert_eq!(channels[0].bins, 3);
+        assert_eq!(channels[0].peak_dbm, Some(-60.0));
+        assert_eq!(channels[0].occupancy, 2.0 / 3.0);
+        assert!((channels[0].mean_dbm.unwrap() - -64.73).abs() < 0.01);
+        assert_eq!(channels[1].bins, 3);
+        assert_eq!(channels[1].mean_dbm, Some(-100.0));
+        assert_eq!(channels[1].occupancy, 0.0);
+    }
+
+    #[test]
+    fn given_a_channel_outside_the_spectrum_when_channelize_then_report_no_bins() {
+        let spectrum = Spectrum::from_sweep(100.0, 10.0, &[-100.0, -60.0]);
+        let plan = ChannelPlan::new("test", vec![ChannelSpec::new("far", 500.0, 20.0)]).with_threshold(-50.0);
+
+        let channels = channelize(&spectrum, &plan);
+
+        assert_eq!(channels[0].bins, 0);
+        assert_eq!(channels[0].mean_dbm, None);
+        assert_eq!(channels[0].peak_dbm, None);
+        assert_eq!(channels[0].occupancy, 0.0);
+    }
+}
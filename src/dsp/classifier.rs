@@ -0,0 +1,284 @@
+use std::fmt::{self, Display};
+
+use crate::spectrum::SpectrumSet;
+
+/// Default power above which a bin is considered active, in dBm.
+const DEFAULT_THRESHOLD: f64 = -90.0;
+
+/// Default maximum bandwidth of a narrowband signal, in Hz.
+const DEFAULT_NARROWBAND_MAX: f64 = 50.0e3;
+
+/// Default minimum bandwidth of a wideband signal, in Hz.
+const DEFAULT_WIDEBAND_MIN: f64 = 500.0e3;
+
+/// Default minimum fraction of sweeps a signal is active to be considered continuous.
+const DEFAULT_CONTINUOUS_DUTY: f64 = 0.9;
+
+/// Default maximum gap between active bins of the same signal, in Hz.
+const DEFAULT_MAX_GAP: f64 = 500.0e3;
+
+/// Label given to a detected signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SignalClass {
+    /// A narrow carrier that is always on.
+    NarrowbandCw,
+
+    /// A signal that changes frequency between sweeps, like frequency hopping spread spectrum.
+    FhssLike,
+
+    /// A wide signal that is on only part of the time.
+    WidebandBurst,
+
+    /// A signal that doesn't match the other classes.
+    Unknown,
+}
+
+impl Display for SignalClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            SignalClass::NarrowbandCw => "narrowband CW",
+            SignalClass::FhssLike => "FHSS-like",
+            SignalClass::WidebandBurst => "wideband burst",
+            SignalClass::Unknown => "unknown",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// Parameters of the classifier heuristics.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClassifierConfig {
+    /// Power above which a bin is considered active, in dBm.
+    pub threshold: f64,
+
+    /// Maximum bandwidth of a narrowband signal, in Hz.
+    pub narrowband_max: f64,
+
+    /// Minimum bandwidth of a wideband signal, in Hz.
+    pub wideband_min: f64,
+
+    /// Minimum fraction of sweeps a signal is active to be considered continuous, from 0 to 1.
+    pub continuous_duty: f64,
+
+    /// Maximum gap between active bins of the same signal, in Hz.
+    pub max_gap: f64,
+}
+
+impl Default for ClassifierConfig {
+    fn default() -> Self {
+        ClassifierConfig {
+            threshold: DEFAULT_THRESHOLD,
+            narrowband_max: DEFAULT_NARROWBAND_MAX,
+            wideband_min: DEFAULT_WIDEBAND_MIN,
+            continuous_duty: DEFAULT_CONTINUOUS_DUTY,
+            max_gap: DEFAULT_MAX_GAP,
+        }
+    }
+}
+
+/// A signal detected in a waterfall history.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Detection {
+    /// Frequency of the first active bin, in Hz.
+    pub fstart: f64,
+
+    /// Frequency of the last active bin, in Hz.
+    pub fstop: f64,
+
+    /// Mean bandwidth occupied in the sweeps where the signal is active, in Hz.
+    pub bandwidth: f64,
+
+    /// Fraction of sweeps where the signal is active, from 0 to 1.
+    pub duty_cycle: f64,
+
+    /// Period of the on/off pattern, in sweeps, if the pattern repeats.
+    pub period: Option<usize>,
+
+    /// Label given to the signal.
+    pub class: SignalClass,
+}
+
+/// Detects and labels the signals in a waterfall history, the sweeps in `history` in measurement order.
+///
+/// Active bins closer than `config.max_gap` across all sweeps are grouped in the same signal, which is then labeled
+/// by its bandwidth, duty cycle and how its center moves between sweeps.
+pub fn classify(history: &SpectrumSet, config: &ClassifierConfig) -> Vec<Detection> {
+    let frequencies: Vec<f64> = match history.spectra().first() {
+        Some(spectrum) => spectrum.frequencies().collect(),
+        None => return Vec::new(),
+    };
+    let bin_width = match frequencies.len() {
+        0 | 1 => 0.0,
+        n => (frequencies[n - 1] - frequencies[0]) / (n - 1) as f64,
+    };
+
+    let active: Vec<Vec<bool>> = history
+        .spectra()
+        .iter()
+        .map(|spectrum| spectrum.powers().map(|power| power > config.threshold).collect())
+        .collect();
+
+    regions(&frequencies, &active, config.max_gap)
+        .into_iter()
+        .map(|(lo, hi)| detect(&frequencies, &active, lo, hi, bin_width, config))
+        .collect()
+}
+
+/// Returns the bin ranges, inclusive, with activity in any sweep, merging ranges closer than `max_gap`.
+fn regions(frequencies: &[f64], active: &[Vec<bool>], max_gap: f64) -> Vec<(usize, usize)> {
+    let mut regions: Vec<(usize, usize)> = Vec::new();
+
+    for bin in 0..frequencies.len() {
+        if !active.iter().any(|sweep| sweep[bin]) {
+            continue;
+        }
+
+        match regions.last_mut() {
+            Some((_, hi)) if frequencies[bin] - frequencies[*hi] <= max_gap => *hi = bin,
+            _ => regions.push((bin, bin)),
+        }
+    }
+
+    regions
+}
+
+/// Measures and labels the signal in the bins from `lo` to `hi`.
+fn detect(
+    frequencies: &[f64],
+    active: &[Vec<bool>],
+    lo: usize,
+    hi: usize,
+    bin_width: f64,
+    config: &ClassifierConfig,
+) -> Detection {
+    let mut pattern = Vec::with_capacity(active.len());
+    let mut widths = Vec::new();
+    let mut centers = Vec::new();
+
+    for sweep in active {
+        let bins: Vec<usize> = (lo..=hi).filter(|bin| sweep[*bin]).collect();
+        pattern.push(!bins.is_empty());
+        if !bins.is_empty() {
+            widths.push(bins.len() as f64 * bin_width);
+            centers.push(bins.iter().map(|bin| frequencies[*bin]).sum::<f64>() / bins.len() as f64);
+        }
+    }
+
+    let bandwidth = widths.iter().sum::<f64>() / widths.len() as f64;
+    let duty_cycle = widths.len() as f64 / active.len() as f64;
+    let extent = (hi - lo + 1) as f64 * bin_width;
+    let mean_center = centers.iter().sum::<f64>() / centers.len() as f64;
+    let spread = centers
+        .iter()
+        .map(|center| (center - mean_center).abs())
+        .fold(0.0, f64::max);
+
+    let class = if bandwidth < extent / 2.0 && spread > bandwidth {
+        SignalClass::FhssLike
+    } else if bandwidth <= config.narrowband_max && duty_cycle >= config.continuous_duty {
+        SignalClass::NarrowbandCw
+    } else if bandwidth >= config.wideband_min && duty_cycle < config.continuous_duty {
+        SignalClass::WidebandBurst
+    } else {
+        SignalClass::Unknown
+    };
+
+    Detection {
+        fstart: frequencies[lo],
+        fstop: frequencies[hi],
+        bandwidth,
+        duty_cycle,
+        period: period(&pattern),
+        class,
+    }
+}
+
+/// Returns the shortest period of an on/off pattern that repeats at least twice.
+fn period(pattern: &[bool]) -> Option<usize> {
+    if pattern.iter().all(|on| *on == pattern[0]) {
+        return None;
+    }
+
+    (1..=pattern.len() / 2).find(|lag| pattern.iter().zip(&pattern[*lag..]).all(|(a, b)| a == b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spectrum::Spectrum;
+
+    const FLOOR: f64 = -110.0;
+
+    /// Creates a history of sweeps with 100 bins of 25 kHz from 868 MHz, where `on` returns the active bins of each
+    /// sweep.
+    fn a_history(sweeps: usize, on: impl Fn(usize) -> Vec<usize>) -> SpectrumSet {
+        let mut set = SpectrumSet::new();
+        for sweep in 0..sweeps {
+            let mut powers = vec![FLOOR; 100];
+            for bin in on(sweep) {
+                powers[bin] = -50.0;
+            }
+            set.push(Spectrum::from_sweep(868.0e6, 25.0e3, &powers)).unwrap();
+        }
+        set
+    }
+
+    #[test]
+    fn given_an_always_on_carrier_when_classify_then_label_as_narrowband_cw() {
+        let history = a_history(10, |_| vec![40]);
+
+        let detections = classify(&history, &ClassifierConfig::default());
+
+        assert_eq!(detections.len(), 1);
+        assert_eq!(detections[0].class, SignalClass::NarrowbandCw);
+        assert_eq!(detections[0].fstart, 869.0e6);
+        assert_eq!(detections[0].bandwidth, 25.0e3);
+        assert_eq!(detections[0].duty_cycle, 1.0);
+        assert_eq!(detections[0].period, None);
+    }
+
+    #[test]
+    fn given_a_hopping_signal_when_classify_then_label_as_fhss_like() {
+        let history = a_history(12, |sweep| vec![10 + (sweep * 7) % 20]);
+
+        let detections = classify(&history, &ClassifierConfig::default());
+
+        assert_eq!(detections.len(), 1);
+        assert_eq!(detections[0].class, SignalClass::FhssLike);
+    }
+
+    #[test]
+    fn given_a_periodic_wide_burst_when_classify_then_label_as_wideband_burst() {
+        let history = a_history(12, |sweep| if sweep % 4 == 0 { (50..80).collect() } else { vec![] });
+
+        let detections = classify(&history, &ClassifierConfig::default());
+
+        assert_eq!(detections.len(), 1);
+        assert_eq!(detections[0].class, SignalClass::WidebandBurst);
+        assert_eq!(detections[0].bandwidth, 750.0e3);
+        assert_eq!(detections[0].duty_cycle, 0.25);
+        assert_eq!(detections[0].period, Some(4));
+    }
+
+    #[test]
+    fn given_distant_signals_when_classify_then_detect_each_one() {
+        let history = a_history(4, |_| vec![0, 99]);
+
+        let detections = classify(&history, &ClassifierConfig::default());
+
+        assert_eq!(detections.len(), 2);
+    }
+
+    #[test]
+    fn given_a_quiet_band_when_classify_then_detect_nothing() {
+        assert!(classify(&a_history(4, |_| vec![]), &ClassifierConfig::default()).is_empty());
+        assert!(classify(&SpectrumSet::new(), &ClassifierConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn given_signal_classes_when_display_then_return_labels() {
+        assert_eq!(SignalClass::NarrowbandCw.to_string(), "narrowband CW");
+        assert_eq!(SignalClass::FhssLike.to_string(), "FHSS-like");
+        assert_eq!(SignalClass::WidebandBurst.to_string(), "wideband burst");
+    }
+}
@@ -0,0 +1,225 @@
+use std::time::Duration;
+
+use crate::power::Dbm;
+
+/// Power of a single frequency measured at a point in time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DwellSample {
+    /// Time of the measurement, since the first sample.
+    pub elapsed: Duration,
+
+    /// Measured power, in dBm.
+    pub power: f64,
+}
+
+/// A run of consecutive samples above a threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Burst {
+    /// Time of the rising crossing, the first sample above the threshold.
+    pub start: Duration,
+
+    /// Time from the rising crossing to the falling crossing, the first sample below the threshold, or to the last
+    /// sample if the burst is still on.
+    pub duration: Duration,
+}
+
+/// Minimum, mean and maximum of a set of times.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeStats {
+    pub min: Duration,
+    pub mean: Duration,
+    pub max: Duration,
+}
+
+impl TimeStats {
+    /// Computes the statistics of `times`, returning `None` if there are none.
+    pub fn new<I: IntoIterator<Item = Duration>>(times: I) -> Option<Self> {
+        let times: Vec<Duration> = times.into_iter().collect();
+        Some(TimeStats {
+            min: *times.iter().min()?,
+            mean: times.iter().sum::<Duration>() / times.len() as u32,
+            max: *times.iter().max()?,
+        })
+    }
+}
+
+/// Timing of the bursts of a transmitter, to validate its duty cycle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BurstTiming {
+    /// Number of bursts.
+    pub count: usize,
+
+    /// Statistics of the burst durations, if there are bursts.
+    pub duration: Option<TimeStats>,
+
+    /// Statistics of the time between the starts of consecutive bursts, if there are two or more.
+    pub period: Option<TimeStats>,
+
+    /// Fraction of the measured time with a burst on, from 0 to 1.
+    pub duty_cycle: f64,
+}
+
+impl BurstTiming {
+    /// Computes the timing of `bursts`, in start order, measured over `total` time.
+    pub fn new(bursts: &[Burst], total: Duration) -> Self {
+        let on: Duration = bursts.iter().map(|burst| burst.duration).sum();
+        BurstTiming {
+            count: bursts.len(),
+            duration: TimeStats::new(bursts.iter().map(|burst| burst.duration)),
+            period: TimeStats::new(
+                bursts
+                    .windows(2)
+                    .map(|pair| pair[1].start.saturating_sub(pair[0].start)),
+            ),
+            duty_cycle: match total.is_zero() {
+                true => 0.0,
+                false => (on.as_secs_f64() / total.as_secs_f64()).min(1.0),
+            },
+        }
+    }
+}
+
+/// Detects the bursts in `samples`, in measurement order, from the rising and falling crossings of `threshold`, in dBm.
+pub fn detect_bursts(samples: &[DwellSample], threshold: f64) -> Vec<Burst> {
+    let mut bursts = Vec::new();
+    let mut rising: Option<Duration> = None;
+    for sample in samples {
+        match (rising, sample.power > threshold) {
+            (None, true) => rising = Some(sample.elapsed),
+            (Some(start), false) => {
+                bursts.push(Burst {
+                    start,
+                    duration: sample.elapsed.saturating_sub(start),
+                });
+                rising = None;
+            }
+            _ => {}
+        }
+    }
+    if let (Some(start), Some(last)) = (rising, samples.last()) {
+        bursts.push(Burst {
+            start,
+            duration: last.elapsed.saturating_sub(start),
+        });
+    }
+    bursts
+}
+
+/// Time series statistics of the power of a single frequency, approximating a zero span measurement.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DwellStats {
+    /// Number of samples.
+    pub samples: usize,
+
+    /// Time between the first and the last sample.
+    pub duration: Duration,
+
+    /// Minimum power, in dBm.
+    pub min: f64,
+
+    /// Maximum power, in dBm.
+    pub max: f64,
+
+    /// Mean power, averaged in linear scale (mW), in dBm.
+    pub mean: f64,
+
+    /// Timing of the bursts above the threshold.
+    pub bursts: BurstTiming,
+}
+
+impl DwellStats {
+    /// Computes the statistics of `samples`, in measurement order, with the timing of the bursts above `threshold`, in
+    /// dBm.
+    ///
+    /// Returns `None` if there are no samples.
+    pub fn new(samples: &[DwellSample], threshold: f64) -> Option<Self> {
+        let (first, last) = (samples.first()?, samples.last()?);
+        let duration = last.elapsed.saturating_sub(first.elapsed);
+
+        let mean = Dbm::mean(samples.iter().map(|sample| Dbm::new(sample.power)))?;
+        Some(DwellStats {
+            samples: samples.len(),
+            duration,
+            min: samples.iter().map(|sample| sample.power).fold(f64::INFINITY, f64::min),
+            max: samples
+                .iter()
+                .map(|sample| sample.power)
+                .fold(f64::NEG_INFINITY, f64::max),
+            mean: mean.dbm(),
+            bursts: BurstTiming::new(&detect_bursts(samples, threshold), duration),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples(powers: &[f64]) -> Vec<DwellSample> {
+        powers
+            .iter()
+            .enumerate()
+            .map(|(index, power)| DwellSample {
+                elapsed: Duration::from_millis(10 * index as u64),
+                power: *power,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn given_an_on_off_keyed_signal_when_dwell_stats_then_count_the_bursts() {
+        let samples = samples(&[-100.0, -60.0, -60.0, -60.0, -100.0, -100.0, -50.0, -100.0]);
+
+        let stats = DwellStats::new(&samples, -90.0).unwrap();
+
+        assert_eq!(stats.samples, 8);
+        assert_eq!(stats.duration, Duration::from_millis(70));
+        assert_eq!((stats.min, stats.max), (-100.0, -50.0));
+        assert_eq!(stats.bursts.count, 2);
+        assert!((stats.mean - -57.9).abs() < 0.1);
+    }
+
+    #[test]
+    fn given_periodic_bursts_when_burst_timing_then_return_durations_period_and_duty_cycle() {
+        let samples = samples(&[
+            -100.0, -60.0, -60.0, -100.0, -100.0, -60.0, -100.0, -100.0, -100.0, -60.0, -60.0,
+        ]);
+
+        let bursts = detect_bursts(&samples, -90.0);
+        let timing = BurstTiming::new(&bursts, Duration::from_millis(100));
+
+        assert_eq!(
+            bursts,
+            vec![
+                Burst {
+                    start: Duration::from_millis(10),
+                    duration: Duration::from_millis(20)
+                },
+                Burst {
+                    start: Duration::from_millis(50),
+                    duration: Duration::from_millis(10)
+                },
+                Burst {
+                    start: Duration::from_millis(90),
+                    duration: Duration::from_millis(10)
+                },
+            ]
+        );
+        assert_eq!(timing.count, 3);
+        assert_eq!(
+            timing.period,
+            Some(TimeStats {
+                min: Duration::from_millis(40),
+                mean: Duration::from_millis(40),
+                max: Duration::from_millis(40)
+            })
+        );
+        assert_eq!(timing.duration.unwrap().max, Duration::from_millis(20));
+        assert!((timing.duty_cycle - 0.4).abs() < 1e-9);
+    }
+
+    #[test]
+    fn given_no_samples_when_dwell_stats_then_return_none() {
+        assert_eq!(DwellStats::new(&[], -90.0), None);
+    }
+}
@@ -0,0 +1,32 @@
+//! # DSP Module
+//!
+//! This module provides processing on top of the measured spectra, like mapping the bins of a spectrum onto the
+//! channels of a channel plan, detecting and labeling the signals in a waterfall history, selecting the sweeps to
+//! persist with a trigger, summarizing the power of a single frequency over time, or finding the regions of a coarse
+//! sweep worth sweeping again with a finer step.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use sa430::dsp::{channelize, ChannelPlan};
+//! use sa430::spectrum::Spectrum;
+//!
+//! let spectrum = Spectrum::from_sweep(867.0e6, 50.0e3, &[-100.0; 33]);
+//! let plan = ChannelPlan::builtin("lora-eu868").unwrap();
+//!
+//! for channel in channelize(&spectrum, &plan) {
+//!     println!("{}: {:?} dBm, {:.0}% occupied", channel.name, channel.mean_dbm, channel.occupancy * 100.0);
+//! }
+//! ```
+
+mod channelizer;
+mod classifier;
+mod dwell;
+mod survey;
+mod trigger;
+
+pub use channelizer::{channelize, ChannelPlan, ChannelPower, ChannelSpec};
+pub use classifier::{classify, ClassifierConfig, Detection, SignalClass};
+pub use dwell::{detect_bursts, Burst, BurstTiming, DwellSample, DwellStats, TimeStats};
+pub use survey::{active_regions, merge_sweeps, Region, SurveyConfig};
+pub use trigger::{Comparison, Trigger, TriggerCondition, TriggerError};
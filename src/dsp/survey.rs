@@ -0,0 +1,153 @@
+use crate::spectrum::Spectrum;
+
+/// Default power above which a bin of the coarse sweep is considered active, in dBm.
+const DEFAULT_THRESHOLD: f64 = -90.0;
+
+/// Default bandwidth added on each side of an active region before sweeping it again, in Hz.
+const DEFAULT_MARGIN: f64 = 200.0e3;
+
+/// Default maximum gap between active bins of the same region, in Hz.
+const DEFAULT_MAX_GAP: f64 = 500.0e3;
+
+/// Parameters of the detection of the regions to sweep again with a finer step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SurveyConfig {
+    /// Power above which a bin is considered active, in dBm.
+    pub threshold: f64,
+
+    /// Bandwidth added on each side of an active region, in Hz, so the fine sweep covers the edges of the signal.
+    pub margin: f64,
+
+    /// Maximum gap between active bins of the same region, in Hz.
+    pub max_gap: f64,
+}
+
+impl Default for SurveyConfig {
+    fn default() -> Self {
+        SurveyConfig {
+            threshold: DEFAULT_THRESHOLD,
+            margin: DEFAULT_MARGIN,
+            max_gap: DEFAULT_MAX_GAP,
+        }
+    }
+}
+
+/// A frequency range with activity, to sweep again with a finer step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Region {
+    /// Start frequency, in Hz.
+    pub fstart: f64,
+
+    /// Stop frequency, in Hz.
+    pub fstop: f64,
+}
+
+/// Returns the regions of a coarse sweep with bins above `config.threshold`, sorted by frequency.
+///
+/// Active bins closer than `config.max_gap` are grouped in the same region, which is then widened by `config.margin`
+/// on each side, without going past the swept range. Regions overlapping after widening are merged.
+pub fn active_regions(spectrum: &Spectrum, config: &SurveyConfig) -> Vec<Region> {
+    let (Some(lo), Some(hi)) = (spectrum.fstart().map(|f| f.hz()), spectrum.fstop().map(|f| f.hz())) else {
+        return Vec::new();
+    };
+
+    let mut regions: Vec<Region> = Vec::new();
+    for (frequency, _) in spectrum.iter().filter(|(_, power)| *power > config.threshold) {
+        match regions.last_mut() {
+            Some(region) if frequency - region.fstop <= config.max_gap => region.fstop = *frequency,
+            _ => regions.push(Region {
+                fstart: *frequency,
+                fstop: *frequency,
+            }),
+        }
+    }
+
+    let mut widened: Vec<Region> = Vec::with_capacity(regions.len());
+    for region in regions {
+        let region = Region {
+            fstart: (region.fstart - config.margin).max(lo),
+            fstop: (region.fstop + config.margin).min(hi),
+        };
+        match widened.last_mut() {
+            Some(last) if region.fstart <= last.fstop => last.fstop = last.fstop.max(region.fstop),
+            _ => widened.push(region),
+        }
+    }
+    widened
+}
+
+/// Combines a coarse sweep with the fine sweeps of its regions, replacing the coarse points inside each fine sweep.
+pub fn merge_sweeps(coarse: &Spectrum, fine: &[Spectrum]) -> Spectrum {
+    let ranges: Vec<(f64, f64)> = fine
+        .iter()
+        .filter_map(|spectrum| Some((spectrum.fstart()?.hz(), spectrum.fstop()?.hz())))
+        .collect();
+    let mut points: Vec<(f64, f64)> = coarse
+        .iter()
+        .filter(|(frequency, _)| !ranges.iter().any(|(lo, hi)| lo <= frequency && frequency <= hi))
+        .copied()
+        .collect();
+    points.extend(fine.iter().flat_map(|spectrum| spectrum.iter().copied()));
+    Spectrum::new(points)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_a_coarse_sweep_when_active_regions_then_group_widen_and_merge_the_active_bins() {
+        let mut powers = vec![-100.0; 30];
+        powers[2] = -70.0;
+        powers[3] = -80.0;
+        powers[10] = -60.0;
+        powers[11] = -60.0;
+        powers[29] = -50.0;
+        let spectrum = Spectrum::from_sweep(860.0e6, 1.0e6, &powers);
+        let config = SurveyConfig {
+            margin: 1.0e6,
+            max_gap: 1.0e6,
+            ..Default::default()
+        };
+
+        let regions = active_regions(&spectrum, &config);
+
+        assert_eq!(
+            regions,
+            vec![
+                Region {
+                    fstart: 861.0e6,
+                    fstop: 864.0e6
+                },
+                Region {
+                    fstart: 869.0e6,
+                    fstop: 872.0e6
+                },
+                Region {
+                    fstart: 888.0e6,
+                    fstop: 889.0e6
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn given_fine_sweeps_when_merge_sweeps_then_replace_the_coarse_points_in_their_range() {
+        let coarse = Spectrum::from_sweep(860.0e6, 1.0e6, &[-100.0, -80.0, -100.0, -100.0]);
+        let fine = Spectrum::from_sweep(860.5e6, 0.5e6, &[-90.0, -70.0, -90.0]);
+
+        let merged = merge_sweeps(&coarse, &[fine]);
+
+        assert_eq!(
+            merged.points(),
+            &[
+                (860.0e6, -100.0),
+                (860.5e6, -90.0),
+                (861.0e6, -70.0),
+                (861.5e6, -90.0),
+                (862.0e6, -100.0),
+                (863.0e6, -100.0)
+            ]
+        );
+    }
+}
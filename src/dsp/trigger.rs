@@ -0,0 +1,257 @@
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use crate::spectrum::Spectrum;
+
+/// How the power in the band is compared with the trigger level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    /// Triggers when the peak power is above the level.
+    Above,
+
+    /// Triggers when the peak power is below the level.
+    Below,
+}
+
+/// Errors when parsing a trigger condition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerError {
+    /// The condition has no `>` or `<` operator.
+    MissingOperator,
+
+    /// The frequency range is not `<fstart>-<fstop>`, in MHz, with the start before the stop.
+    InvalidRange,
+
+    /// The level is not a number, in dBm.
+    InvalidLevel,
+}
+
+impl Error for TriggerError {}
+
+impl Display for TriggerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TriggerError::MissingOperator => write!(f, "Invalid trigger: expected '>' or '<'"),
+            TriggerError::InvalidRange => write!(f, "Invalid trigger: expected a range like 868.0-868.6 (MHz)"),
+            TriggerError::InvalidLevel => write!(f, "Invalid trigger: expected a level like -80dBm"),
+        }
+    }
+}
+
+/// Condition on the peak power inside a frequency band.
+///
+/// Parsed from strings like `868.0-868.6 > -80dBm`, with frequencies in MHz.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TriggerCondition {
+    /// Start of the band, in Hz.
+    pub fstart: f64,
+
+    /// Stop of the band, in Hz.
+    pub fstop: f64,
+
+    /// How the power is compared with the level.
+    pub comparison: Comparison,
+
+    /// Trigger level, in dBm.
+    pub level: f64,
+}
+
+impl TriggerCondition {
+    /// Returns true if the peak power of `spectrum` inside the band satisfies the condition.
+    ///
+    /// A spectrum without bins inside the band never satisfies the condition.
+    pub fn matches(&self, spectrum: &Spectrum) -> bool {
+        match (self.peak(spectrum), self.comparison) {
+            (Some((_, peak)), Comparison::Above) => peak > self.level,
+            (Some((_, peak)), Comparison::Below) => peak < self.level,
+            (None, _) => false,
+        }
+    }
+
+    /// Returns the `(frequency_hz, power_dbm)` point with the highest power of `spectrum` inside the band.
+    pub fn peak(&self, spectrum: &Spectrum) -> Option<(f64, f64)> {
+        spectrum
+            .iter()
+            .filter(|(frequency, _)| *frequency >= self.fstart && *frequency <= self.fstop)
+            .fold(None, |peak: Option<(f64, f64)>, point| match peak {
+                Some(peak) if peak.1 >= point.1 => Some(peak),
+                _ => Some(*point),
+            })
+    }
+}
+
+impl FromStr for TriggerCondition {
+    type Err = TriggerError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (position, comparison) = match (value.find('>'), value.find('<')) {
+            (Some(position), None) => (position, Comparison::Above),
+            (None, Some(position)) => (position, Comparison::Below),
+            _ => return Err(TriggerError::MissingOperator),
+        };
+
+        let (range, level) = (value[..position].trim(), value[position + 1..].trim());
+
+        let (fstart, fstop) = range
+            .split_once('-')
+            .and_then(|(fstart, fstop)| Some((fstart.trim().parse::<f64>().ok()?, fstop.trim().parse::<f64>().ok()?)))
+            .filter(|(fstart, fstop)| fstart < fstop)
+            .ok_or(TriggerError::InvalidRange)?;
+
+        let level = level.strip_suffix("dBm").or(level.strip_suffix("dbm")).unwrap_or(level);
+        let level = level.trim().parse::<f64>().map_err(|_| TriggerError::InvalidLevel)?;
+
+        Ok(TriggerCondition {
+            fstart: fstart * 1e6,
+            fstop: fstop * 1e6,
+            comparison,
+            level,
+        })
+    }
+}
+
+/// Selects the sweeps to persist in continuous mode.
+///
+/// Sweeps are kept while the condition holds, together with the `pretrigger` sweeps preceding the crossing, taken
+/// from a ring buffer with the latest sweeps that didn't trigger.
+#[derive(Debug, Clone)]
+pub struct Trigger {
+    condition: TriggerCondition,
+    pretrigger: usize,
+    history: VecDeque<Spectrum>,
+    active: bool,
+}
+
+impl Trigger {
+    /// Creates a new trigger keeping up to `pretrigger` sweeps before the crossing.
+    pub fn new(condition: TriggerCondition, pretrigger: usize) -> Self {
+        Trigger {
+            condition,
+            pretrigger,
+            history: VecDeque::with_capacity(pretrigger),
+            active: false,
+        }
+    }
+
+    /// Returns the trigger condition.
+    pub fn condition(&self) -> &TriggerCondition {
+        &self.condition
+    }
+
+    /// Returns true if the last sweep satisfied the condition.
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Feeds a new sweep and returns the sweeps to persist, oldest first.
+    ///
+    /// On a crossing, returns the buffered pretrigger sweeps followed by `spectrum`, while the condition holds,
+    /// returns only `spectrum`, and otherwise buffers `spectrum` and returns nothing.
+    pub fn push(&mut self, spectrum: Spectrum) -> Vec<Spectrum> {
+        self.active = self.condition.matches(&spectrum);
+        if self.active {
+            let mut sweeps: Vec<Spectrum> = self.history.drain(..).collect();
+            sweeps.push(spectrum);
+            return sweeps;
+        }
+
+        if self.pretrigger > 0 {
+            if self.history.len() == self.pretrigger {
+                self.history.pop_front();
+            }
+            self.history.push_back(spectrum);
+        }
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn a_sweep(power: f64) -> Spectrum {
+        Spectrum::from_sweep(867.9e6, 0.1e6, &[-50.0, power, power, -50.0])
+    }
+
+    #[test]
+    fn given_a_condition_string_when_parse_then_return_condition() {
+        let condition: TriggerCondition = "868.0-868.1 > -80dBm".parse().unwrap();
+
+        assert_eq!(
+            condition,
+            TriggerCondition {
+                fstart: 868.0e6,
+                fstop: 868.1e6,
+                comparison: Comparison::Above,
+                level: -80.0
+            }
+        );
+        assert_eq!(
+            "900 - 910<-95.5".parse::<TriggerCondition>().unwrap().comparison,
+            Comparison::Below
+        );
+    }
+
+    #[test]
+    fn given_invalid_condition_strings_when_parse_then_return_error() {
+        let cases = [
+            ("868.0-868.6 -80dBm", TriggerError::MissingOperator),
+            ("868.0 > -80dBm", TriggerError::InvalidRange),
+            ("868.6-868.0 > -80dBm", TriggerError::InvalidRange),
+            ("868.0-868.6 > loud", TriggerError::InvalidLevel),
+        ];
+
+        for (value, expected) in cases {
+            assert_eq!(value.parse::<TriggerCondition>(), Err(expected));
+        }
+    }
+
+    #[test]
+    fn given_a_condition_when_matches_then_compare_the_peak_power_in_the_band() {
+        let condition: TriggerCondition = "868.0-868.1 > -80dBm".parse().unwrap();
+
+        assert!(condition.matches(&a_sweep(-70.0)));
+        assert!(!condition.matches(&a_sweep(-90.0)));
+        assert!(!condition.matches(&Spectrum::from_sweep(900.0e6, 0.1e6, &[-10.0])));
+    }
+
+    #[test]
+    fn given_a_condition_when_peak_then_return_the_strongest_point_in_the_band() {
+        let condition: TriggerCondition = "868.0-868.1 > -80dBm".parse().unwrap();
+        let spectrum = Spectrum::from_sweep(867.9e6, 0.1e6, &[-10.0, -70.0, -65.0, -5.0]);
+
+        assert_eq!(condition.peak(&spectrum), Some((868.1e6, -65.0)));
+        assert_eq!(condition.peak(&Spectrum::default()), None);
+    }
+
+    #[test]
+    fn given_sweeps_when_push_then_return_pretrigger_history_on_crossing() {
+        let mut trigger = Trigger::new("868.0-868.1 > -80dBm".parse().unwrap(), 2);
+
+        assert!(trigger.push(a_sweep(-90.0)).is_empty());
+        assert!(trigger.push(a_sweep(-91.0)).is_empty());
+        assert!(trigger.push(a_sweep(-92.0)).is_empty());
+
+        assert_eq!(
+            trigger.push(a_sweep(-70.0)),
+            vec![a_sweep(-91.0), a_sweep(-92.0), a_sweep(-70.0)]
+        );
+        assert_eq!(trigger.push(a_sweep(-60.0)), vec![a_sweep(-60.0)]);
+        assert!(trigger.push(a_sweep(-93.0)).is_empty());
+        assert_eq!(trigger.push(a_sweep(-75.0)), vec![a_sweep(-93.0), a_sweep(-75.0)]);
+    }
+
+    #[test]
+    fn given_no_pretrigger_when_push_then_return_only_matching_sweeps() {
+        let mut trigger = Trigger::new("868.0-868.1 > -80dBm".parse().unwrap(), 0);
+
+        assert!(trigger.push(a_sweep(-90.0)).is_empty());
+        assert!(!trigger.is_active());
+        assert_eq!(trigger.push(a_sweep(-70.0)), vec![a_sweep(-70.0)]);
+        assert!(trigger.is_active());
+    }
+}
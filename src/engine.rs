@@ -0,0 +1,120 @@
+//! # Engine Module
+//!
+//! This module provides `SpectrumEngine`, the sweep protocol of a spectrum analyzer firmware: the requests that set
+//! up a measurement and the request that measures it. The stock TI firmware is driven by `ParameterCommandsEngine`,
+//! which sets each parameter of the sweep with its own command, other firmwares, like modded ones with a different
+//! command set or payload layout, are supported by registering their engine in the device with
+//! `Sa430::register_engine`.
+//!
+//! The engine of a device is selected when connecting, by `Sa430::select_engine`, as the first registered engine that
+//! supports the version reported by `GetSpectrumVersion`, falling back to the built-in ones.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use sa430::engine::{ParameterCommandsEngine, SpectrumEngine};
+//!
+//! assert_eq!(ParameterCommandsEngine.name(), "parameter-commands");
+//! assert!(ParameterCommandsEngine.supports(0x0205));
+//! assert!(!ParameterCommandsEngine.supports(0x0300));
+//! ```
+
+use std::error::Error;
+
+use super::compat::MIN_SPECTRUM_VERSION;
+use super::frame::{Command, Frame};
+use super::sweep::SweepConfig;
+
+/// Sweep protocol of a spectrum analyzer firmware.
+pub trait SpectrumEngine: Send {
+    /// Returns the name of the engine, for logs and errors.
+    fn name(&self) -> &str;
+
+    /// Returns whether the engine drives the spectrum analyzer firmware of `spectrum_version`.
+    fn supports(&self, spectrum_version: u16) -> bool;
+
+    /// Returns the requests that set up the device for the measurement of `config`, sent in order.
+    fn init_requests(&self, config: &SweepConfig) -> Result<Vec<Frame>, Box<dyn Error>>;
+
+    /// Returns the request that measures the spectrum set up by the init requests.
+    fn measure_request(&self, config: &SweepConfig) -> Frame;
+}
+
+/// Engine of the stock TI firmware 2.x, setting up the sweep with a request per parameter, `SetFStart`, `SetFStop`,
+/// `SetFStep`, `SetRbw`, `SetIf` and `SetGain`, followed by `InitParameter`, and measuring it with `GetSpecNoInit`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParameterCommandsEngine;
+
+impl SpectrumEngine for ParameterCommandsEngine {
+    fn name(&self) -> &str {
+        "parameter-commands"
+    }
+
+    fn supports(&self, spectrum_version: u16) -> bool {
+        (MIN_SPECTRUM_VERSION..0x0300).contains(&spectrum_version)
+    }
+
+    fn init_requests(&self, config: &SweepConfig) -> Result<Vec<Frame>, Box<dyn Error>> {
+        let mut requests = config.registers()?.to_frames();
+        requests.push(Frame::new(Command::InitParameter));
+        Ok(requests)
+    }
+
+    fn measure_request(&self, _: &SweepConfig) -> Frame {
+        Frame::new(Command::GetSpecNoInit)
+    }
+}
+
+/// Returns the engines built in the library, in the order they are tried.
+pub fn builtin_engines() -> Vec<Box<dyn SpectrumEngine>> {
+    vec![Box::new(ParameterCommandsEngine)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::frequency::Frequency;
+    use crate::sweep::NOMINAL_XTAL_HZ;
+
+    #[test]
+    fn given_a_config_when_init_requests_then_set_each_parameter_and_init_parameter() {
+        let config = SweepConfig::with_auto_rbw(
+            Frequency::from_mhz(868.0),
+            Frequency::from_mhz(870.0),
+            Frequency::from_khz(100.0),
+            3,
+            Frequency::from_hz(NOMINAL_XTAL_HZ),
+        );
+
+        let requests = ParameterCommandsEngine.init_requests(&config).unwrap();
+
+        assert_eq!(
+            requests.iter().map(Frame::cmd).collect::<Vec<_>>(),
+            [
+                Command::SetFStart,
+                Command::SetFStop,
+                Command::SetFStep,
+                Command::SetRbw,
+                Command::SetIf,
+                Command::SetGain,
+                Command::InitParameter
+            ]
+        );
+        assert_eq!(requests[5].data(), [74]);
+        assert!(requests[6].data().is_empty());
+        assert_eq!(
+            ParameterCommandsEngine.measure_request(&config),
+            Frame::new(Command::GetSpecNoInit)
+        );
+    }
+
+    #[test]
+    fn given_the_stock_firmware_versions_when_supports_then_accept_only_2_x_from_the_minimum() {
+        assert!(ParameterCommandsEngine.supports(0x0204));
+        assert!(ParameterCommandsEngine.supports(0x0205));
+        assert!(!ParameterCommandsEngine.supports(0x0203));
+        assert!(!ParameterCommandsEngine.supports(0x0300));
+        assert!(!ParameterCommandsEngine.supports(0xFFFF));
+    }
+}
@@ -0,0 +1,220 @@
+//! # Binary Module
+//!
+//! This module writes capture sweeps in a compact binary format, for high rate captures where text formats are too
+//! large or slow to parse.
+//!
+//! Each sweep is written as a record, with all fields in little endian:
+//!
+//! | Field     | Type                 | Description                               |
+//! |-----------|----------------------|-------------------------------------------|
+//! | magic     | `[u8; 4]`            | `SA43`                                    |
+//! | sequence  | `u64`                | Number of the sweep in the capture        |
+//! | timestamp | `f64`                | When the sweep was measured, Unix seconds |
+//! | count     | `u32`                | Number of points                          |
+//! | points    | `count * (f64, f32)` | Frequency in Hz and power in dBm          |
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use sa430::formats::binary::{read_record, BinarySink};
+//! use sa430::sink::{Sink, SweepRecord};
+//! use sa430::spectrum::Spectrum;
+//!
+//! let record = SweepRecord::new(7, Spectrum::from_sweep(868.0e6, 0.1e6, &[-90.0, -72.5]));
+//!
+//! let mut output = Vec::new();
+//! BinarySink::new(&mut output).write(&record).unwrap();
+//!
+//! let decoded = read_record(&mut output.as_slice()).unwrap().unwrap();
+//! assert_eq!(decoded.sequence, 7);
+//! assert_eq!(decoded.spectrum, record.spectrum);
+//! ```
+
+use std::{
+    error::Error,
+    fmt,
+    io::{self, Read, Write},
+    time::{Duration, UNIX_EPOCH},
+};
+
+use crate::sink::{Sink, SweepRecord};
+use crate::spectrum::Spectrum;
+
+/// Marks the start of each record.
+pub const RECORD_MAGIC: [u8; 4] = *b"SA43";
+
+/// Length of the fields before the points of a record, in bytes.
+const HEADER_LENGTH: usize = 24;
+
+/// Length of a point of a record, in bytes.
+const POINT_LENGTH: usize = 12;
+
+/// Errors when reading binary records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryError {
+    /// The record doesn't start with [`RECORD_MAGIC`].
+    InvalidMagic,
+}
+
+impl Error for BinaryError {}
+
+impl fmt::Display for BinaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinaryError::InvalidMagic => write!(f, "Invalid binary record, missing the SA43 magic"),
+        }
+    }
+}
+
+/// A sink writing each sweep as a binary record.
+pub struct BinarySink<W: Write> {
+    output: W,
+}
+
+impl<W: Write> BinarySink<W> {
+    /// Creates a sink writing to `output`.
+    pub fn new(output: W) -> Self {
+        BinarySink { output }
+    }
+}
+
+impl<W: Write> Sink for BinarySink<W> {
+    fn write(&mut self, record: &SweepRecord) -> Result<(), Box<dyn Error>> {
+        let timestamp = record
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        let count = u32::try_from(record.spectrum.len())?;
+
+        let mut bytes = Vec::with_capacity(record_length(record.spectrum.len()) as usize);
+        bytes.extend_from_slice(&RECORD_MAGIC);
+        bytes.extend_from_slice(&record.sequence.to_le_bytes());
+        bytes.extend_from_slice(&timestamp.to_le_bytes());
+        bytes.extend_from_slice(&count.to_le_bytes());
+        for (frequency, power) in &record.spectrum {
+            bytes.extend_from_slice(&frequency.to_le_bytes());
+            bytes.extend_from_slice(&(*power as f32).to_le_bytes());
+        }
+        self.output.write_all(&bytes)?;
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(self.output.flush()?)
+    }
+}
+
+/// Returns the length, in bytes, of the complete records at the start of the input, ignoring a truncated final record,
+/// like the one left by a power loss while it was written.
+pub fn complete_length(input: &mut dyn Read) -> Result<u64, Box<dyn Error>> {
+    let mut length = 0;
+    loop {
+        match read_record(input) {
+            Ok(Some(record)) => length += record_length(record.spectrum.len()),
+            Ok(None) => return Ok(length),
+            Err(error) if is_truncated(&*error) => return Ok(length),
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+/// Returns the length, in bytes, of a record with `count` points.
+fn record_length(count: usize) -> u64 {
+    (HEADER_LENGTH + POINT_LENGTH * count) as u64
+}
+
+fn is_truncated(error: &(dyn Error + 'static)) -> bool {
+    error
+        .downcast_ref::<io::Error>()
+        .is_some_and(|error| error.kind() == io::ErrorKind::UnexpectedEof)
+}
+
+/// Reads the next record, or `None` at the end of the input.
+pub fn read_record(input: &mut dyn Read) -> Result<Option<SweepRecord>, Box<dyn Error>> {
+    let mut magic = [0; 4];
+    match input.read_exact(&mut magic) {
+        Ok(()) => {}
+        Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(error) => return Err(error.into()),
+    }
+    if magic != RECORD_MAGIC {
+        return Err(BinaryError::InvalidMagic.into());
+    }
+
+    let sequence = u64::from_le_bytes(read_array(input)?);
+    let timestamp = f64::from_le_bytes(read_array(input)?);
+    let count = u32::from_le_bytes(read_array(input)?);
+    let mut points = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let frequency = f64::from_le_bytes(read_array(input)?);
+        let power = f32::from_le_bytes(read_array(input)?);
+        points.push((frequency, power as f64));
+    }
+
+    let mut record = SweepRecord::new(sequence, Spectrum::new(points));
+    record.timestamp = UNIX_EPOCH + Duration::try_from_secs_f64(timestamp)?;
+    Ok(Some(record))
+}
+
+fn read_array<const N: usize>(input: &mut dyn Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    input.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_records_when_written_then_read_them_back_in_order() {
+        let mut output = Vec::new();
+        let mut sink = BinarySink::new(&mut output);
+        for sequence in 0..2 {
+            sink.write(&SweepRecord::new(
+                sequence,
+                Spectrum::from_sweep(868.0e6, 0.1e6, &[-90.0]),
+            ))
+            .unwrap();
+        }
+        let mut input = output.as_slice();
+
+        assert_eq!(read_record(&mut input).unwrap().unwrap().sequence, 0);
+        assert_eq!(read_record(&mut input).unwrap().unwrap().sequence, 1);
+        assert!(read_record(&mut input).unwrap().is_none());
+    }
+
+    #[test]
+    fn given_bytes_without_magic_when_read_record_then_return_error() {
+        let result = read_record(&mut &b"SA44"[..]);
+
+        assert_eq!(
+            result.unwrap_err().downcast_ref::<BinaryError>(),
+            Some(&BinaryError::InvalidMagic)
+        );
+    }
+
+    #[test]
+    fn given_a_truncated_final_record_when_complete_length_then_return_the_length_of_the_others() {
+        let mut output = Vec::new();
+        let mut sink = BinarySink::new(&mut output);
+        sink.write(&SweepRecord::new(
+            0,
+            Spectrum::from_sweep(868.0e6, 0.1e6, &[-90.0, -72.5]),
+        ))
+        .unwrap();
+        sink.write(&SweepRecord::new(
+            1,
+            Spectrum::from_sweep(868.0e6, 0.1e6, &[-91.0, -73.5]),
+        ))
+        .unwrap();
+        let complete = output.len() as u64;
+
+        assert_eq!(complete_length(&mut output.as_slice()).unwrap(), complete);
+        for cut in [1, 10, 30] {
+            let truncated = &output[..output.len() - cut];
+            assert_eq!(complete_length(&mut &truncated[..]).unwrap(), complete / 2);
+        }
+    }
+}
@@ -0,0 +1,391 @@
+//! # CSV Module
+//!
+//! This module loads traces exported by other instruments as CSV files into a `Spectrum`, so they can be compared
+//! with SA430 captures.
+//!
+//! Each row has the frequency in the first column and the power, in dBm, in the second one, separated by a comma, a
+//! semicolon or a tab. Extra columns, empty lines and lines starting with `#` are ignored. The first row may be a
+//! header, in which case the frequency unit is taken from the name of the first column (`Hz`, `kHz`, `MHz` or `GHz`),
+//! otherwise the frequency is in Hz.
+//!
+//! CSV files are written with `CsvWriter`, which always uses `.` as the decimal separator, regardless of the system
+//! locale, and a comma as the delimiter, unless a semicolon or a tab is set for spreadsheets that expect them.
+//!
+//! Capture sweeps are written with `CsvSink`, as a header line followed by a row per point, with the frequency in MHz
+//! and, if set, the ISO 8601 timestamp of the sweep in a third column.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use sa430::formats::csv::{parse_spectrum, CsvWriter};
+//!
+//! let data = "Frequency (MHz),Power (dBm)\n433.0,-90.5\n433.1,-72.0\n";
+//! let spectrum = parse_spectrum(data.as_bytes()).unwrap();
+//!
+//! assert_eq!(spectrum.points(), &[(433.0e6, -90.5), (433.1e6, -72.0)]);
+//!
+//! let mut output = Vec::new();
+//! let mut writer = CsvWriter::new(&mut output, ';').unwrap();
+//! for (frequency, power) in &spectrum {
+//!     writer.write_row([frequency, power]).unwrap();
+//! }
+//! assert_eq!(output, b"433000000;-90.5\n433100000;-72\n");
+//! ```
+
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    fs::File,
+    io::{self, BufRead, BufReader, Write},
+    path::Path,
+};
+
+use crate::sink::{Sink, SweepRecord};
+use crate::spectrum::{BinWindow, Spectrum};
+use crate::timestamp::{format_iso8601, TimeZone};
+
+/// Column delimiters accepted in the CSV files.
+pub const DELIMITERS: [char; 3] = [',', ';', '\t'];
+
+/// Default column delimiter of the written CSV files.
+pub const DEFAULT_DELIMITER: char = ',';
+
+/// Errors when parsing a CSV trace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsvError {
+    /// The row has less than two columns (line number).
+    MissingColumns(usize),
+
+    /// The row has a value that is not a number (line number).
+    InvalidValue(usize),
+
+    /// The delimiter is not a comma, a semicolon or a tab.
+    InvalidDelimiter,
+}
+
+impl Error for CsvError {}
+
+impl Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsvError::MissingColumns(line) => write!(f, "Line {}: expected frequency and power columns", line),
+            CsvError::InvalidValue(line) => write!(f, "Line {}: invalid number", line),
+            CsvError::InvalidDelimiter => write!(f, "Invalid delimiter, expected ',', ';' or tab"),
+        }
+    }
+}
+
+/// Reads the spectrum from the CSV file at `path`.
+pub fn read_spectrum<P: AsRef<Path>>(path: P) -> Result<Spectrum, Box<dyn Error>> {
+    let file = File::open(path)?;
+    parse_spectrum(BufReader::new(file))
+}
+
+/// Parses the spectrum from CSV data.
+pub fn parse_spectrum<R: BufRead>(reader: R) -> Result<Spectrum, Box<dyn Error>> {
+    let mut points = Vec::new();
+    let mut scale = 1.0;
+    let mut first_row = true;
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        let number = index + 1;
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut columns = line.split(DELIMITERS).map(str::trim);
+        let (frequency, power) = match (columns.next(), columns.next()) {
+            (Some(frequency), Some(power)) => (frequency, power),
+            _ => return Err(Box::new(CsvError::MissingColumns(number))),
+        };
+
+        match (frequency.parse::<f64>(), power.parse::<f64>()) {
+            (Ok(frequency), Ok(power)) => points.push((frequency * scale, power)),
+            _ if first_row => scale = unit_scale(frequency),
+            _ => return Err(Box::new(CsvError::InvalidValue(number))),
+        }
+        first_row = false;
+    }
+
+    Ok(Spectrum::new(points))
+}
+
+/// Parses a delimiter: `,`, `;` or a tab, also accepted as `tab` or `\t`.
+pub fn parse_delimiter(value: &str) -> Result<char, CsvError> {
+    match value {
+        "tab" | "\\t" => Ok('\t'),
+        _ => {
+            let mut chars = value.chars();
+            match (chars.next(), chars.next()) {
+                (Some(delimiter), None) if DELIMITERS.contains(&delimiter) => Ok(delimiter),
+                _ => Err(CsvError::InvalidDelimiter),
+            }
+        }
+    }
+}
+
+/// Writes CSV rows.
+///
+/// Values are formatted with `Display`, which doesn't depend on the system locale, so numbers always use `.` as the
+/// decimal separator.
+pub struct CsvWriter<W: Write> {
+    output: W,
+    delimiter: char,
+}
+
+impl<W: Write> CsvWriter<W> {
+    /// Creates a writer separating the columns with `delimiter`, one of [`DELIMITERS`].
+    pub fn new(output: W, delimiter: char) -> Result<Self, CsvError> {
+        if !DELIMITERS.contains(&delimiter) {
+            return Err(CsvError::InvalidDelimiter);
+        }
+        Ok(CsvWriter { output, delimiter })
+    }
+
+    /// Writes a row with the given values.
+    pub fn write_row<I>(&mut self, values: I) -> io::Result<()>
+    where
+        I: IntoIterator,
+        I::Item: Display,
+    {
+        for (index, value) in values.into_iter().enumerate() {
+            if index > 0 {
+                write!(self.output, "{}", self.delimiter)?;
+            }
+            write!(self.output, "{}", value)?;
+        }
+        writeln!(self.output)
+    }
+}
+
+/// A sink writing each sweep as CSV, with a header line followed by the points, with frequencies in MHz.
+///
+/// The grid of an aligned sweep is written before the header, as `#` comment lines.
+pub struct CsvSink<W: Write> {
+    output: W,
+    delimiter: char,
+    timestamps: Option<TimeZone>,
+}
+
+impl<W: Write> CsvSink<W> {
+    /// Creates a sink separating the columns with `delimiter`, one of [`DELIMITERS`].
+    pub fn new(output: W, delimiter: char) -> Result<Self, CsvError> {
+        if !DELIMITERS.contains(&delimiter) {
+            return Err(CsvError::InvalidDelimiter);
+        }
+        Ok(CsvSink {
+            output,
+            delimiter,
+            timestamps: None,
+        })
+    }
+
+    /// Adds a `timestamp` column with the time of the sweep on each row, in the time zone `zone`.
+    pub fn with_timestamps(mut self, zone: TimeZone) -> Self {
+        self.timestamps = Some(zone);
+        self
+    }
+}
+
+impl<W: Write> Sink for CsvSink<W> {
+    fn write(&mut self, record: &SweepRecord) -> Result<(), Box<dyn Error>> {
+        if let Some(window) = &record.window {
+            write_window(window, &mut self.output)?;
+        }
+        let mut writer = CsvWriter::new(&mut self.output, self.delimiter)?;
+        let timestamp = self.timestamps.map(|zone| format_iso8601(record.timestamp, zone));
+        match &timestamp {
+            Some(_) => writer.write_row(["frequency (MHz)", "power (dBm)", "timestamp"])?,
+            None => writer.write_row(["frequency (MHz)", "power (dBm)"])?,
+        }
+        for (frequency, power) in &record.spectrum {
+            let mut row = vec![format!("{:.4}", frequency / 1e6), format!("{:.2}", power)];
+            row.extend(timestamp.clone());
+            writer.write_row(row)?;
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(self.output.flush()?)
+    }
+}
+
+/// Writes the grid of an aligned spectrum as comment lines, with frequencies in MHz.
+fn write_window(window: &BinWindow, output: &mut dyn Write) -> io::Result<()> {
+    writeln!(output, "# fstart: {:.4}", window.fstart)?;
+    writeln!(output, "# fstep: {:.6}", window.fstep)?;
+    writeln!(output, "# bins: {}", window.bins)?;
+    writeln!(output, "# measured bins: {}", window.measured)?;
+    writeln!(output, "# alignment: {}", window.alignment)
+}
+
+/// Returns the factor to convert the frequency unit named in a column header to Hz.
+fn unit_scale(header: &str) -> f64 {
+    let header = header.to_lowercase();
+    if header.contains("ghz") {
+        1e9
+    } else if header.contains("mhz") {
+        1e6
+    } else if header.contains("khz") {
+        1e3
+    } else {
+        1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::spectrum::{BinAlignment, Interpolation};
+
+    fn a_record(spectrum: Spectrum) -> SweepRecord {
+        SweepRecord::new(0, spectrum)
+    }
+
+    #[test]
+    fn given_a_spectrum_when_csv_sink_write_then_write_a_point_per_line() {
+        let mut output = Vec::new();
+        let spectrum = Spectrum::from_sweep(868.0e6, 0.1e6, &[-90.0, -72.5]);
+
+        CsvSink::new(&mut output, ',')
+            .unwrap()
+            .write(&a_record(spectrum))
+            .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(
+            output,
+            "frequency (MHz),power (dBm)\n868.0000,-90.00\n868.1000,-72.50\n"
+        );
+    }
+
+    #[test]
+    fn given_timestamps_when_csv_sink_write_then_add_the_sweep_time_to_each_row() {
+        let mut output = Vec::new();
+        let mut record = a_record(Spectrum::from_sweep(868.0e6, 0.1e6, &[-90.0, -72.5]));
+        record.timestamp = std::time::UNIX_EPOCH + std::time::Duration::from_millis(1_709_209_800_250);
+
+        CsvSink::new(&mut output, ',')
+            .unwrap()
+            .with_timestamps(TimeZone::Utc)
+            .write(&record)
+            .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(
+            output,
+            "frequency (MHz),power (dBm),timestamp\n\
+             868.0000,-90.00,2024-02-29T12:30:00.250Z\n\
+             868.1000,-72.50,2024-02-29T12:30:00.250Z\n"
+        );
+    }
+
+    #[test]
+    fn given_a_semicolon_delimiter_when_csv_sink_write_then_keep_the_decimal_point() {
+        let mut output = Vec::new();
+        let spectrum = Spectrum::from_sweep(868.0e6, 0.1e6, &[-90.0]);
+
+        CsvSink::new(&mut output, ';')
+            .unwrap()
+            .write(&a_record(spectrum))
+            .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output, "frequency (MHz);power (dBm)\n868.0000;-90.00\n");
+    }
+
+    #[test]
+    fn given_an_aligned_spectrum_when_csv_sink_write_then_write_the_grid_as_comments() {
+        let mut output = Vec::new();
+        let spectrum = Spectrum::from_sweep(868.0e6, 0.1e6, &[-90.0, -72.5, -80.0]);
+        let (spectrum, window) = spectrum.align_to_power_of_two(BinAlignment::Pad, Interpolation::Linear);
+        let mut record = a_record(spectrum);
+        record.window = Some(window);
+
+        CsvSink::new(&mut output, ',').unwrap().write(&record).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with(
+            "# fstart: 868.0000 MHz\n# fstep: 0.100000 MHz\n# bins: 4\n# measured bins: 3\n# alignment: pad\n"
+        ));
+        assert_eq!(output.lines().count(), 10);
+    }
+
+    #[test]
+    fn given_rows_without_header_when_parse_spectrum_then_read_frequency_in_hz() {
+        let data = "433000000,-90.5\n433100000,-72\n";
+
+        let spectrum = parse_spectrum(data.as_bytes()).unwrap();
+
+        assert_eq!(spectrum.points(), &[(433.0e6, -90.5), (433.1e6, -72.0)]);
+    }
+
+    #[test]
+    fn given_a_header_with_unit_when_parse_spectrum_then_scale_frequency() {
+        let data = "# Exported trace\nFreq [kHz];Level [dBm];Extra\n\n868000;-101.25;x\n868100;-99;x\n";
+
+        let spectrum = parse_spectrum(data.as_bytes()).unwrap();
+
+        assert_eq!(spectrum.points(), &[(868.0e6, -101.25), (868.1e6, -99.0)]);
+    }
+
+    #[test]
+    fn given_tab_separated_rows_when_parse_spectrum_then_split_columns() {
+        let data = "Frequency (GHz)\tPower\n0.915\t-80\n";
+
+        let spectrum = parse_spectrum(data.as_bytes()).unwrap();
+
+        assert_eq!(spectrum.points(), &[(915.0e6, -80.0)]);
+    }
+
+    #[test]
+    fn given_a_row_with_a_single_column_when_parse_spectrum_then_return_missing_columns() {
+        let data = "433000000,-90\n433100000\n";
+
+        let error = parse_spectrum(data.as_bytes()).unwrap_err();
+
+        assert_eq!(error.downcast_ref::<CsvError>(), Some(&CsvError::MissingColumns(2)));
+    }
+
+    #[test]
+    fn given_an_invalid_number_after_the_first_row_when_parse_spectrum_then_return_invalid_value() {
+        let data = "433000000,-90\n433100000,abc\n";
+
+        let error = parse_spectrum(data.as_bytes()).unwrap_err();
+
+        assert_eq!(error.downcast_ref::<CsvError>(), Some(&CsvError::InvalidValue(2)));
+    }
+
+    #[test]
+    fn given_a_semicolon_delimiter_when_write_row_then_keep_the_decimal_point() {
+        let mut output = Vec::new();
+        let mut writer = CsvWriter::new(&mut output, ';').unwrap();
+
+        writer.write_row(["frequency (MHz)", "power (dBm)"]).unwrap();
+        writer.write_row([868.1, -72.5]).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "frequency (MHz);power (dBm)\n868.1;-72.5\n"
+        );
+    }
+
+    #[test]
+    fn given_delimiter_names_when_parse_delimiter_then_return_the_delimiter() {
+        assert_eq!(parse_delimiter(";"), Ok(';'));
+        assert_eq!(parse_delimiter("tab"), Ok('\t'));
+        assert_eq!(parse_delimiter("."), Err(CsvError::InvalidDelimiter));
+        assert_eq!(parse_delimiter(",,"), Err(CsvError::InvalidDelimiter));
+        assert!(CsvWriter::new(Vec::new(), '.').is_err());
+    }
+
+    #[test]
+    fn given_a_missing_file_when_read_spectrum_then_return_error() {
+        assert!(read_spectrum("/nonexistent/trace.csv").is_err());
+    }
+}
@@ -0,0 +1,331 @@
+//! # Gains Module
+//!
+//! This module exports the calibration gain tables of a device as an instrument correction file, in CSV or JSON, and
+//! imports a modified CSV file back into a `Calibration`, so the effect of other corrections can be analyzed without
+//! touching the device flash.
+//!
+//! The CSV file has one row per gain table, with the index of the frequency range, its start and stop frequencies in
+//! Hz, the index of the table, the reference level index and the 8 gain values:
+//!
+//! ```text
+//! range,f_start_hz,f_stop_hz,table,ref_level_index,gain_0,gain_1,gain_2,gain_3,gain_4,gain_5,gain_6,gain_7
+//! 0,300000000,348000000,0,0,-71.2,0.31,...
+//! ```
+//!
+//! The columns may be separated by a comma, a semicolon or a tab. When importing, only the range index, the table index, the reference level index and the gains are used, and the
+//! tables missing from the file keep their values. [check_gains_csv] lists every problem of a file, with its line and
+//! column, before importing it.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use sa430::device::Calibration;
+//! use sa430::formats::gains::{read_gains_csv, write_gains_csv};
+//!
+//! let calibration = Calibration::default();
+//! let mut file = Vec::new();
+//! write_gains_csv(&calibration, ';', &mut file).unwrap();
+//!
+//! let imported = read_gains_csv(file.as_slice(), &calibration).unwrap();
+//! assert_eq!(imported, calibration);
+//! ```
+
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    io::{self, BufRead, Write},
+};
+
+use crate::device::{Calibration, FrequencyGain};
+use crate::formats::csv::{CsvWriter, DELIMITERS};
+
+/// Header of the CSV file.
+const CSV_HEADER: [&str; CSV_COLUMNS] = [
+    "range",
+    "f_start_hz",
+    "f_stop_hz",
+    "table",
+    "ref_level_index",
+    "gain_0",
+    "gain_1",
+    "gain_2",
+    "gain_3",
+    "gain_4",
+    "gain_5",
+    "gain_6",
+    "gain_7",
+];
+
+/// Number of columns in the CSV file.
+const CSV_COLUMNS: usize = 13;
+
+/// Errors when importing a gains file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GainsError {
+    /// The row doesn't have all the columns (line number).
+    MissingColumns(usize),
+
+    /// The row has a value that is not a number (line number, column name).
+    InvalidValue(usize, &'static str),
+
+    /// The row references a range or table that doesn't exist (line number).
+    InvalidIndex(usize),
+
+    /// The row has the same range and table as a previous row (line number, previous line number).
+    DuplicateTable(usize, usize),
+}
+
+impl Error for GainsError {}
+
+impl Display for GainsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GainsError::MissingColumns(line) => write!(f, "Line {}: expected {} columns", line, CSV_COLUMNS),
+            GainsError::InvalidValue(line, column) => write!(f, "Line {}: {} is not a number", line, column),
+            GainsError::InvalidIndex(line) => write!(f, "Line {}: invalid range or table index", line),
+            GainsError::DuplicateTable(line, previous) => {
+                write!(
+                    f,
+                    "Line {}: the range and table are already set on line {}",
+                    line, previous
+                )
+            }
+        }
+    }
+}
+
+/// Writes the gain tables of `calibration` as CSV, with the columns separated by `delimiter`.
+pub fn write_gains_csv(
+    calibration: &Calibration,
+    delimiter: char,
+    output: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    let mut writer = CsvWriter::new(output, delimiter)?;
+    writer.write_row(CSV_HEADER)?;
+
+    for (i, range) in calibration.frq_ranges.iter().enumerate() {
+        for (j, gain) in calibration.frq_gains_tables[i].iter().enumerate() {
+            let mut row = vec![
+                i.to_string(),
+                range.f_start().to_string(),
+                range.f_stop().to_string(),
+                j.to_string(),
+                gain.ref_level_index().to_string(),
+            ];
+            row.extend(gain.gains().iter().map(|value| value.to_string()));
+            writer.write_row(row)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the gain tables of `calibration` as JSON.
+pub fn write_gains_json(calibration: &Calibration, output: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+    writeln!(output, "{{")?;
+    writeln!(output, "  \"ranges\": [")?;
+
+    for (i, range) in calibration.frq_ranges.iter().enumerate() {
+        writeln!(output, "    {{")?;
+        writeln!(output, "      \"f_start_hz\": {},", range.f_start())?;
+        writeln!(output, "      \"f_stop_hz\": {},", range.f_stop())?;
+        writeln!(output, "      \"tables\": [")?;
+
+        let tables = &calibration.frq_gains_tables[i];
+        for (j, gain) in tables.iter().enumerate() {
+            let values: Vec<String> = gain.gains().iter().map(|value| value.to_string()).collect();
+            write!(
+                output,
+                "        {{ \"ref_level_index\": {}, \"gains\": [{}] }}",
+                gain.ref_level_index(),
+                values.join(", ")
+            )?;
+            writeln!(output, "{}", if j + 1 < tables.len() { "," } else { "" })?;
+        }
+
+        writeln!(output, "      ]")?;
+        writeln!(
+            output,
+            "    }}{}",
+            if i + 1 < calibration.frq_ranges.len() { "," } else { "" }
+        )?;
+    }
+
+    writeln!(output, "  ]")?;
+    writeln!(output, "}}")?;
+    Ok(())
+}
+
+/// Reads a CSV gains file and returns a copy of `calibration` with its gain tables replaced.
+pub fn read_gains_csv<R: BufRead>(reader: R, calibration: &Calibration) -> Result<Calibration, Box<dyn Error>> {
+    let mut result = calibration.clone();
+
+    for (index, line) in reader.lines().enumerate() {
+        let number = index + 1;
+        let Some((range, table, gain)) = parse_row(number, &line?)? else {
+            continue;
+        };
+
+        let slot = result
+            .frq_gains_tables
+            .get_mut(range)
+            .and_then(|tables| tables.get_mut(table))
+            .ok_or(GainsError::InvalidIndex(number))?;
+        *slot = gain;
+    }
+
+    Ok(result)
+}
+
+/// Checks a CSV gains file, returning every problem found instead of stopping at the first one, including tables set
+/// by more than one row.
+pub fn check_gains_csv<R: BufRead>(reader: R) -> io::Result<Vec<GainsError>> {
+    let calibration = Calibration::default();
+    let mut errors = Vec::new();
+    let mut rows: Vec<((usize, usize), usize)> = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let number = index + 1;
+        match parse_row(number, &line?) {
+            Ok(Some((range, table, _))) => {
+                let exists = calibration
+                    .frq_gains_tables
+                    .get(range)
+                    .is_some_and(|tables| table < tables.len());
+                let previous = rows
+                    .iter()
+                    .find(|(key, _)| *key == (range, table))
+                    .map(|(_, line)| *line);
+                match (exists, previous) {
+                    (false, _) => errors.push(GainsError::InvalidIndex(number)),
+                    (true, Some(previous)) => errors.push(GainsError::DuplicateTable(number, previous)),
+                    (true, None) => rows.push(((range, table), number)),
+                }
+            }
+            Ok(None) => {}
+            Err(error) => errors.push(error),
+        }
+    }
+    Ok(errors)
+}
+
+/// Parses a row of a CSV gains file into its range index, table index and gain table, returning `None` for empty,
+/// comment and header lines.
+fn parse_row(number: usize, line: &str) -> Result<Option<(usize, usize, FrequencyGain)>, GainsError> {
+    let line = line.trim();
+    let columns: Vec<&str> = line.split(DELIMITERS).map(str::trim).collect();
+    if line.is_empty() || line.starts_with('#') || columns[0] == CSV_HEADER[0] {
+        return Ok(None);
+    }
+
+    if columns.len() < CSV_COLUMNS {
+        return Err(GainsError::MissingColumns(number));
+    }
+
+    let invalid = |column: usize| GainsError::InvalidValue(number, CSV_HEADER[column]);
+    let range: usize = columns[0].parse().map_err(|_| invalid(0))?;
+    let table: usize = columns[3].parse().map_err(|_| invalid(3))?;
+    let ref_level_index: u8 = columns[4].parse().map_err(|_| invalid(4))?;
+
+    let mut gains = [0.0; 8];
+    for (column, gain) in gains.iter_mut().enumerate() {
+        *gain = columns[5 + column].parse().map_err(|_| invalid(5 + column))?;
+    }
+
+    Ok(Some((range, table, FrequencyGain::new(ref_level_index, gains))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn a_calibration() -> Calibration {
+        let mut calibration = Calibration::default();
+        calibration.frq_gains_tables[1][2] = FrequencyGain::new(3, [-71.5, 0.25, 0.0, 0.0, 0.0, 0.0, 0.0, 1e-9]);
+        calibration
+    }
+
+    #[test]
+    fn given_a_calibration_when_write_gains_csv_then_write_a_row_per_table() {
+        let mut output = Vec::new();
+
+        write_gains_csv(&a_calibration(), ',', &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 1 + 3 * 8);
+        assert_eq!(lines[0], CSV_HEADER.join(","));
+        assert_eq!(lines[11], "1,0,0,2,3,-71.5,0.25,0,0,0,0,0,0.000000001");
+    }
+
+    #[test]
+    fn given_an_exported_file_when_read_gains_csv_then_return_the_same_calibration() {
+        let calibration = a_calibration();
+        let mut output = Vec::new();
+        write_gains_csv(&calibration, ';', &mut output).unwrap();
+
+        let imported = read_gains_csv(output.as_slice(), &Calibration::default()).unwrap();
+
+        assert_eq!(imported, calibration);
+    }
+
+    #[test]
+    fn given_a_partial_file_when_read_gains_csv_then_keep_other_tables() {
+        let data = "0,0,0,7,1,1,2,3,4,5,6,7,8\n";
+
+        let imported = read_gains_csv(data.as_bytes(), &a_calibration()).unwrap();
+
+        assert_eq!(
+            imported.frq_gains_tables[0][7],
+            FrequencyGain::new(1, [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0])
+        );
+        assert_eq!(imported.frq_gains_tables[1][2], a_calibration().frq_gains_tables[1][2]);
+    }
+
+    #[test]
+    fn given_an_invalid_row_when_read_gains_csv_then_return_error() {
+        let cases = [
+            ("0,0,0,7,1,1,2\n", GainsError::MissingColumns(1)),
+            ("0,0,0,7,1,1,2,3,4,5,6,7,x\n", GainsError::InvalidValue(1, "gain_7")),
+            ("3,0,0,0,1,1,2,3,4,5,6,7,8\n", GainsError::InvalidIndex(1)),
+            ("0,0,0,8,1,1,2,3,4,5,6,7,8\n", GainsError::InvalidIndex(1)),
+        ];
+
+        for (data, expected) in cases {
+            let error = read_gains_csv(data.as_bytes(), &Calibration::default()).unwrap_err();
+            assert_eq!(error.downcast_ref::<GainsError>(), Some(&expected));
+        }
+    }
+
+    #[test]
+    fn given_rows_with_problems_when_check_gains_csv_then_return_all_of_them() {
+        let data = "range,f_start_hz,f_stop_hz,table,ref_level_index,gain_0\n\
+                    0,0,0,x,1,1,2,3,4,5,6,7,8\n\
+                    0,0,0,1,1,1,2,3,4,5,6,7,8\n\
+                    0,0,0,1,1,1,2,3,4,5,6,7,8\n\
+                    2,0,0,9,1,1,2,3,4,5,6,7,8\n";
+
+        let errors = check_gains_csv(data.as_bytes()).unwrap();
+
+        assert_eq!(
+            errors,
+            vec![
+                GainsError::InvalidValue(2, "table"),
+                GainsError::DuplicateTable(4, 3),
+                GainsError::InvalidIndex(5)
+            ]
+        );
+    }
+
+    #[test]
+    fn given_a_calibration_when_write_gains_json_then_write_ranges_and_tables() {
+        let mut output = Vec::new();
+
+        write_gains_json(&a_calibration(), &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("{\n  \"ranges\": [\n    {\n      \"f_start_hz\": 0,"));
+        assert!(output.contains("{ \"ref_level_index\": 3, \"gains\": [-71.5, 0.25, 0, 0, 0, 0, 0, 0.000000001] },\n"));
+        assert_eq!(output.matches("\"tables\"").count(), 3);
+        assert!(output.ends_with("    }\n  ]\n}\n"));
+    }
+}
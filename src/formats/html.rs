@@ -0,0 +1,337 @@
+//! # HTML Module
+//!
+//! This module writes spectra as a standalone HTML report, with an interactive plot drawn by inlined JavaScript, a
+//! summary table and the metadata of each trace, so results can be shared with anyone that has a browser.
+//!
+//! The report has no external resources: the traces are embedded as JSON and drawn on a canvas, hovering the plot
+//! shows the power of each trace at the pointer frequency and the legend toggles the traces.
+//!
+//! Capture sweeps are plotted with `HtmlSink`, which keeps each sweep as a trace and writes the report when closed.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use sa430::formats::html::{write_html_report, Trace};
+//! use sa430::spectrum::Spectrum;
+//!
+//! let mut trace = Trace::new("eu868", Spectrum::from_sweep(868.0e6, 0.1e6, &[-90.0, -70.0, -85.0]));
+//! trace.metadata.push(("location".to_string(), "-27.59,-48.55".to_string()));
+//!
+//! let mut output = Vec::new();
+//! write_html_report("Site survey", &[trace], &mut output).unwrap();
+//!
+//! let html = String::from_utf8(output).unwrap();
+//! assert!(html.starts_with("<!DOCTYPE html>"));
+//! assert!(html.contains("<td>eu868</td>"));
+//! ```
+
+use std::{error::Error, io::Write};
+
+use crate::frequency::Frequency;
+use crate::sink::{Sink, SweepRecord};
+use crate::spectrum::Spectrum;
+
+/// A spectrum of the report.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Trace {
+    /// Name shown in the legend and tables.
+    pub name: String,
+
+    /// `(key, value)` pairs shown in the metadata table.
+    pub metadata: Vec<(String, String)>,
+
+    /// Measured spectrum.
+    pub spectrum: Spectrum,
+}
+
+impl Trace {
+    /// Creates a trace without metadata.
+    pub fn new(name: &str, spectrum: Spectrum) -> Self {
+        Trace {
+            name: name.to_string(),
+            metadata: Vec::new(),
+            spectrum,
+        }
+    }
+}
+
+/// A sink plotting the sweeps of a capture in a standalone HTML report, written when the sink is closed.
+pub struct HtmlSink<W: Write> {
+    output: W,
+    title: String,
+    traces: Vec<Trace>,
+}
+
+impl<W: Write> HtmlSink<W> {
+    /// Creates a sink writing a report with `title` to `output`.
+    pub fn new(output: W, title: &str) -> Self {
+        HtmlSink {
+            output,
+            title: title.to_string(),
+            traces: Vec::new(),
+        }
+    }
+}
+
+impl<W: Write> Sink for HtmlSink<W> {
+    fn write(&mut self, record: &SweepRecord) -> Result<(), Box<dyn Error>> {
+        let name = format!("sweep {}", record.sequence);
+        self.traces.push(Trace::new(&name, record.spectrum.clone()));
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), Box<dyn Error>> {
+        write_html_report(&self.title, &self.traces, &mut self.output)?;
+        Ok(self.output.flush()?)
+    }
+}
+
+/// Style of the report.
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; color: #222; }
+canvas { border: 1px solid #ccc; width: 100%; height: 420px; }
+table { border-collapse: collapse; margin: 1em 0; }
+th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: right; }
+th:first-child, td:first-child { text-align: left; }
+#legend label { margin-right: 1em; }
+#cursor { font-family: monospace; min-height: 1.5em; }";
+
+/// Plot script, drawing the `TRACES` array of `{name, points}` objects, with frequencies in Hz and powers in dBm.
+const SCRIPT: &str = r#"const COLORS = ['#1f77b4', '#d62728', '#2ca02c', '#ff7f0e', '#9467bd', '#8c564b', '#e377c2', '#17becf'];
+const canvas = document.getElementById('plot');
+const visible = TRACES.map(() => true);
+const all = TRACES.flatMap((trace) => trace.points);
+const fmin = Math.min(...all.map((p) => p[0])), fmax = Math.max(...all.map((p) => p[0]));
+const pmin = Math.floor(Math.min(...all.map((p) => p[1])) / 10) * 10;
+const pmax = Math.ceil(Math.max(...all.map((p) => p[1])) / 10) * 10;
+const margin = { left: 60, right: 20, top: 10, bottom: 40 };
+
+function x(f, width) { return margin.left + (f - fmin) / ((fmax - fmin) || 1) * (width - margin.left - margin.right); }
+function y(p, height) { return margin.top + (pmax - p) / ((pmax - pmin) || 1) * (height - margin.top - margin.bottom); }
+
+function draw() {
+  const width = canvas.width = canvas.clientWidth, height = canvas.height = canvas.clientHeight;
+  const ctx = canvas.getContext('2d');
+  ctx.font = '12px sans-serif';
+  ctx.strokeStyle = '#eee';
+  ctx.fillStyle = '#444';
+  for (let p = pmin; p <= pmax; p += 10) {
+    ctx.beginPath(); ctx.moveTo(margin.left, y(p, height)); ctx.lineTo(width - margin.right, y(p, height)); ctx.stroke();
+    ctx.fillText(p + ' dBm', 4, y(p, height) + 4);
+  }
+  for (let i = 0; i <= 5; i++) {
+    const f = fmin + (fmax - fmin) * i / 5;
+    ctx.fillText((f / 1e6).toFixed(3) + ' MHz', x(f, width) - 30, height - margin.bottom + 20);
+  }
+  TRACES.forEach((trace, index) => {
+    if (!visible[index]) return;
+    ctx.strokeStyle = COLORS[index % COLORS.length];
+    ctx.beginPath();
+    trace.points.forEach((p, i) => i ? ctx.lineTo(x(p[0], width), y(p[1], height)) : ctx.moveTo(x(p[0], width), y(p[1], height)));
+    ctx.stroke();
+  });
+}
+
+function nearest(points, f) {
+  return points.reduce((best, p) => Math.abs(p[0] - f) < Math.abs(best[0] - f) ? p : best, points[0]);
+}
+
+canvas.addEventListener('mousemove', (event) => {
+  const width = canvas.clientWidth;
+  const f = fmin + (event.offsetX - margin.left) / (width - margin.left - margin.right) * (fmax - fmin);
+  const values = TRACES.filter((trace, index) => visible[index] && trace.points.length)
+    .map((trace) => { const p = nearest(trace.points, f); return trace.name + ': ' + p[1].toFixed(2) + ' dBm @ ' + (p[0] / 1e6).toFixed(4) + ' MHz'; });
+  document.getElementById('cursor').textContent = values.join(' | ');
+});
+
+const legend = document.getElementById('legend');
+TRACES.forEach((trace, index) => {
+  const label = document.createElement('label');
+  const box = document.createElement('input');
+  box.type = 'checkbox';
+  box.checked = true;
+  box.addEventListener('change', () => { visible[index] = box.checked; draw(); });
+  label.appendChild(box);
+  label.style.color = COLORS[index % COLORS.length];
+  label.appendChild(document.createTextNode(trace.name));
+  legend.appendChild(label);
+});
+window.addEventListener('resize', draw);
+draw();"#;
+
+/// Writes a standalone HTML report with the traces.
+pub fn write_html_report(title: &str, traces: &[Trace], output: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+    writeln!(output, "<!DOCTYPE html>")?;
+    writeln!(output, "<html>\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(output, "<title>{}</title>", escape_html(title))?;
+    writeln!(output, "<style>\n{}\n</style>\n</head>\n<body>", STYLE)?;
+    writeln!(output, "<h1>{}</h1>", escape_html(title))?;
+    writeln!(
+        output,
+        "<div id=\"legend\"></div>\n<canvas id=\"plot\"></canvas>\n<div id=\"cursor\"></div>"
+    )?;
+
+    write_summary(traces, output)?;
+    write_metadata(traces, output)?;
+
+    writeln!(output, "<script>")?;
+    write_traces(traces, output)?;
+    writeln!(output, "{}\n</script>\n</body>\n</html>", SCRIPT)?;
+    Ok(())
+}
+
+/// Writes a table with the range, peak and mean power of each trace.
+fn write_summary(traces: &[Trace], output: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+    writeln!(output, "<h2>Summary</h2>\n<table>")?;
+    writeln!(
+        output,
+        "<tr><th>trace</th><th>points</th><th>start (MHz)</th><th>stop (MHz)</th><th>peak (MHz)</th>\
+         <th>peak (dBm)</th><th>mean (dBm)</th></tr>"
+    )?;
+    for trace in traces {
+        let spectrum = &trace.spectrum;
+        let peak = spectrum.iter().copied().reduce(|a, b| if b.1 > a.1 { b } else { a });
+        let mean = spectrum.mean_power().map(|mean| mean.dbm());
+        let mhz = |frequency: Option<Frequency>| frequency.map_or("-".to_string(), |f| format!("{:.4}", f.mhz()));
+        let dbm = |power: Option<f64>| power.map_or("-".to_string(), |p| format!("{:.2}", p));
+
+        writeln!(
+            output,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape_html(&trace.name),
+            spectrum.len(),
+            mhz(spectrum.fstart()),
+            mhz(spectrum.fstop()),
+            mhz(peak.map(|(frequency, _)| Frequency::from_hz(frequency))),
+            dbm(peak.map(|(_, power)| power)),
+            dbm(mean)
+        )?;
+    }
+    writeln!(output, "</table>")?;
+    Ok(())
+}
+
+/// Writes a table with the metadata of the traces that have any.
+fn write_metadata(traces: &[Trace], output: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+    if traces.iter().all(|trace| trace.metadata.is_empty()) {
+        return Ok(());
+    }
+
+    writeln!(output, "<h2>Metadata</h2>\n<table>")?;
+    writeln!(output, "<tr><th>trace</th><th>key</th><th>value</th></tr>")?;
+    for trace in traces {
+        for (key, value) in &trace.metadata {
+            writeln!(
+                output,
+                "<tr><td>{}</td><td>{}</td><td>{}</td></tr>",
+                escape_html(&trace.name),
+                escape_html(key),
+                escape_html(value)
+            )?;
+        }
+    }
+    writeln!(output, "</table>")?;
+    Ok(())
+}
+
+/// Writes the traces as the `TRACES` JavaScript array.
+fn write_traces(traces: &[Trace], output: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+    writeln!(output, "const TRACES = [")?;
+    for trace in traces {
+        let points: Vec<String> = trace
+            .spectrum
+            .iter()
+            .map(|(frequency, power)| format!("[{},{}]", frequency, power))
+            .collect();
+        writeln!(
+            output,
+            "  {{ \"name\": \"{}\", \"points\": [{}] }},",
+            escape_script(&trace.name),
+            points.join(",")
+        )?;
+    }
+    writeln!(output, "];")?;
+    Ok(())
+}
+
+/// Escapes the characters with a meaning in HTML text and attributes.
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Escapes a string value inside a script, so it can't close the string nor the script element.
+fn escape_script(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '<' => escaped.push_str("\\u003c"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn a_report(traces: &[Trace]) -> String {
+        let mut output = Vec::new();
+        write_html_report("Report", traces, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn given_traces_when_write_html_report_then_embed_the_points_and_summary() {
+        let html = a_report(&[Trace::new(
+            "a",
+            Spectrum::from_sweep(868.0e6, 0.5e6, &[-90.0, -60.0, -80.0]),
+        )]);
+
+        assert!(html.contains(r#"{ "name": "a", "points": [[868000000,-90],[868500000,-60],[869000000,-80]] },"#));
+        assert!(html.contains(
+            "<tr><td>a</td><td>3</td><td>868.0000</td><td>869.0000</td><td>868.5000</td><td>-60.00</td><td>-64.72</td></tr>"
+        ));
+        assert!(!html.contains("<h2>Metadata</h2>"));
+        assert!(html.trim_end().ends_with("</html>"));
+    }
+
+    #[test]
+    fn given_sweeps_when_html_sink_closed_then_write_a_trace_per_sweep() {
+        let mut output = Vec::new();
+        let mut sink = HtmlSink::new(&mut output, "Capture");
+        for sequence in 0..2 {
+            sink.write(&SweepRecord::new(
+                sequence,
+                Spectrum::from_sweep(868.0e6, 0.5e6, &[-90.0]),
+            ))
+            .unwrap();
+        }
+
+        sink.close().unwrap();
+
+        let html = String::from_utf8(output).unwrap();
+        assert!(html.contains("<title>Capture</title>"));
+        assert!(html.contains("<td>sweep 0</td>"));
+        assert!(html.contains("<td>sweep 1</td>"));
+    }
+
+    #[test]
+    fn given_names_with_markup_when_write_html_report_then_escape_them() {
+        let mut trace = Trace::new("</script><b>", Spectrum::default());
+        trace.metadata.push(("note".to_string(), "a & b".to_string()));
+
+        let html = a_report(&[trace]);
+
+        assert!(html.contains(r#""name": "\u003c/script>\u003cb>""#));
+        assert!(!html.contains("</script><b>"));
+        assert!(html.contains("<td>&lt;/script&gt;&lt;b&gt;</td><td>0</td><td>-</td>"));
+        assert!(html.contains("<td>note</td><td>a &amp; b</td>"));
+    }
+}
@@ -0,0 +1,76 @@
+//! # Formats Module
+//!
+//! This module groups the readers and writers of file formats used to exchange data with other tools.
+//!
+//! The formats capture sweeps can be written in implement [`Sink`](crate::sink::Sink): `csv::CsvSink`,
+//! `ndjson::NdjsonSink`, `binary::BinarySink` and `html::HtmlSink`, for plots.
+//!
+//! A recording cut by a power loss ends with a truncated record. `repair` removes it, keeping the complete records
+//! before it, so the recording can be read and appended to again.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use sa430::formats::repair;
+//!
+//! let path = std::env::temp_dir().join(format!("sa430-repair-doc-{}.csv", std::process::id()));
+//! std::fs::write(&path, "868.0,-90.0\n868.1,-7").unwrap();
+//!
+//! assert_eq!(repair(&path).unwrap(), Some(8));
+//! assert_eq!(std::fs::read_to_string(&path).unwrap(), "868.0,-90.0\n");
+//! # std::fs::remove_file(&path).unwrap();
+//! ```
+
+pub mod binary;
+pub mod csv;
+pub mod gains;
+pub mod html;
+pub mod ndjson;
+pub mod zip;
+
+use std::{
+    error::Error,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+/// Removes the truncated final record of a recording, detected from its extension: binary records for `.bin` files,
+/// lines for `.csv`, `.ndjson` and `.jsonl` files. Returns the number of bytes removed, or `None` if the format isn't
+/// known.
+pub fn repair(path: &Path) -> Result<Option<u64>, Box<dyn Error>> {
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+    let mut input = BufReader::new(File::open(path)?);
+    let complete = match extension.as_deref() {
+        Some("bin") => binary::complete_length(&mut input)?,
+        Some("csv" | "ndjson" | "jsonl") => complete_lines_length(&mut input)?,
+        _ => return Ok(None),
+    };
+
+    let file = OpenOptions::new().write(true).open(path)?;
+    let length = file.metadata()?.len();
+    if complete < length {
+        file.set_len(complete)?;
+        file.sync_all()?;
+    }
+    Ok(Some(length - complete))
+}
+
+/// Returns the length, in bytes, of the input up to the end of its last line ending.
+fn complete_lines_length(input: &mut dyn BufRead) -> Result<u64, Box<dyn Error>> {
+    let mut length = 0;
+    let mut complete = 0;
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        match input.read_until(b'\n', &mut line)? {
+            0 => return Ok(complete),
+            read => length += read as u64,
+        }
+        if line.ends_with(b"\n") {
+            complete = length;
+        }
+    }
+}
@@ -0,0 +1,156 @@
+//! # NDJSON Module
+//!
+//! This module writes capture sweeps as newline delimited JSON, one object per sweep, for tools that process the
+//! sweeps as a stream, like `jq` or log collectors.
+//!
+//! Each object has the sweep `sequence`, the `timestamp` in Unix seconds, the `points` as `[frequency_hz, power_dbm]`
+//! pairs and, for sweeps aligned to a power of two number of points, the `window` of the grid. Sinks created
+//! `with_timestamps` add the `time` of the sweep as an ISO 8601 string.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use std::time::{Duration, UNIX_EPOCH};
+//!
+//! use sa430::formats::ndjson::NdjsonSink;
+//! use sa430::sink::{Sink, SweepRecord};
+//! use sa430::spectrum::Spectrum;
+//!
+//! let mut record = SweepRecord::new(3, Spectrum::from_sweep(868.0e6, 0.1e6, &[-90.0, -72.5]));
+//! record.timestamp = UNIX_EPOCH + Duration::from_millis(1_700_000_000_250);
+//!
+//! let mut output = Vec::new();
+//! NdjsonSink::new(&mut output).write(&record).unwrap();
+//!
+//! assert_eq!(
+//!     String::from_utf8(output).unwrap(),
+//!     "{\"sequence\":3,\"timestamp\":1700000000.250,\"points\":[[868000000,-90],[868100000,-72.5]]}\n"
+//! );
+//! ```
+
+use std::{error::Error, io::Write, time::UNIX_EPOCH};
+
+use crate::sink::{Sink, SweepRecord};
+use crate::timestamp::{format_iso8601, TimeZone};
+
+/// A sink writing each sweep as a JSON object on its own line.
+pub struct NdjsonSink<W: Write> {
+    output: W,
+    timestamps: Option<TimeZone>,
+}
+
+impl<W: Write> NdjsonSink<W> {
+    /// Creates a sink writing to `output`.
+    pub fn new(output: W) -> Self {
+        NdjsonSink {
+            output,
+            timestamps: None,
+        }
+    }
+
+    /// Adds the `time` of each sweep as an ISO 8601 string, in the time zone `zone`.
+    pub fn with_timestamps(mut self, zone: TimeZone) -> Self {
+        self.timestamps = Some(zone);
+        self
+    }
+}
+
+impl<W: Write> Sink for NdjsonSink<W> {
+    fn write(&mut self, record: &SweepRecord) -> Result<(), Box<dyn Error>> {
+        let timestamp = record
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        let points: Vec<String> = record
+            .spectrum
+            .iter()
+            .map(|(frequency, power)| format!("[{},{}]", frequency, power))
+            .collect();
+
+        write!(
+            self.output,
+            "{{\"sequence\":{},\"timestamp\":{:.3},",
+            record.sequence, timestamp
+        )?;
+        if let Some(zone) = self.timestamps {
+            write!(self.output, "\"time\":\"{}\",", format_iso8601(record.timestamp, zone))?;
+        }
+        write!(self.output, "\"points\":[{}]", points.join(","))?;
+        if let Some(window) = &record.window {
+            write!(
+                self.output,
+                ",\"window\":{{\"fstart\":{},\"fstep\":{},\"bins\":{},\"measured\":{},\"alignment\":\"{}\"}}",
+                window.fstart.hz(),
+                window.fstep.hz(),
+                window.bins,
+                window.measured,
+                window.alignment
+            )?;
+        }
+        writeln!(self.output, "}}")?;
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(self.output.flush()?)
+    }
+}
+
+/// Escapes the quotes, backslashes and control characters of a JSON string value.
+pub fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use crate::spectrum::{BinAlignment, Interpolation, Spectrum};
+
+    #[test]
+    fn given_timestamps_when_write_then_include_the_iso_8601_time() {
+        let mut record = SweepRecord::new(3, Spectrum::from_sweep(868.0e6, 0.1e6, &[-90.0]));
+        record.timestamp = UNIX_EPOCH + Duration::from_millis(1_709_209_800_250);
+        let mut output = Vec::new();
+
+        NdjsonSink::new(&mut output)
+            .with_timestamps(TimeZone::Utc)
+            .write(&record)
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"sequence\":3,\"timestamp\":1709209800.250,\"time\":\"2024-02-29T12:30:00.250Z\",\
+             \"points\":[[868000000,-90]]}\n"
+        );
+    }
+
+    #[test]
+    fn given_an_aligned_sweep_when_write_then_include_the_window() {
+        let spectrum = Spectrum::from_sweep(868.0e6, 0.1e6, &[-90.0, -72.5, -80.0]);
+        let (spectrum, window) = spectrum.align_to_power_of_two(BinAlignment::Pad, Interpolation::Linear);
+        let mut record = SweepRecord::new(0, spectrum);
+        record.window = Some(window);
+        let mut output = Vec::new();
+
+        NdjsonSink::new(&mut output).write(&record).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.ends_with(
+            ",\"window\":{\"fstart\":868000000,\"fstep\":100000,\"bins\":4,\"measured\":3,\"alignment\":\"pad\"}}\n"
+        ));
+        assert_eq!(output.lines().count(), 1);
+    }
+}
@@ -0,0 +1,219 @@
+//! # Zip Module
+//!
+//! This module writes zip archives with the files stored uncompressed, enough to bundle diagnostic files into a single
+//! attachment for an issue report, readable by any unzip tool.
+//!
+//! The archive is written as the files are added, and `finish` writes the central directory at its end. Archives are
+//! limited to 65535 files of less than 4 GiB, without the zip64 extensions.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use std::time::SystemTime;
+//!
+//! use sa430::formats::zip::ZipWriter;
+//!
+//! let mut zip = ZipWriter::new(Vec::new());
+//! zip.add("device.txt", b"Serial number: 2312\n", SystemTime::now()).unwrap();
+//!
+//! let archive = zip.finish().unwrap();
+//! assert_eq!(&archive[..4], b"PK\x03\x04");
+//! ```
+
+use std::{
+    io::{self, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::crc::crc32;
+use crate::timestamp::{civil_from_days, SECONDS_PER_DAY};
+
+/// Signature of a local file header.
+const LOCAL_FILE_SIGNATURE: u32 = 0x0403_4B50;
+
+/// Signature of a central directory file header.
+const CENTRAL_FILE_SIGNATURE: u32 = 0x0201_4B50;
+
+/// Signature of the end of central directory record.
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4B50;
+
+/// Version of the zip specification needed to extract stored files, 1.0.
+const VERSION_NEEDED: u16 = 10;
+
+/// General purpose flag marking the file names as UTF-8.
+const UTF8_NAMES: u16 = 1 << 11;
+
+/// First year representable in an MS-DOS date.
+const DOS_EPOCH_YEAR: i64 = 1980;
+
+/// File added to an archive, kept for the central directory.
+#[derive(Debug, Clone)]
+struct Entry {
+    name: String,
+    crc: u32,
+    size: u32,
+    time: u16,
+    date: u16,
+    offset: u32,
+}
+
+/// Writer of a zip archive with stored files.
+pub struct ZipWriter<W: Write> {
+    output: W,
+    entries: Vec<Entry>,
+    offset: u64,
+}
+
+impl<W: Write> ZipWriter<W> {
+    /// Creates a writer of an empty archive to `output`.
+    pub fn new(output: W) -> Self {
+        ZipWriter {
+            output,
+            entries: Vec::new(),
+            offset: 0,
+        }
+    }
+
+    /// Adds a file with `contents` to the archive, named `name` and modified at `modified`.
+    ///
+    /// Fails if the archive is full or the file or the archive grows past 4 GiB.
+    pub fn add(&mut self, name: &str, contents: &[u8], modified: SystemTime) -> io::Result<()> {
+        if self.entries.len() == u16::MAX as usize {
+            return Err(too_large("Too many files in the zip archive"));
+        }
+        let size = u32::try_from(contents.len()).map_err(|_| too_large("File too large for a zip archive"))?;
+        let offset = u32::try_from(self.offset).map_err(|_| too_large("Zip archive too large"))?;
+        let (time, date) = dos_date_time(modified);
+        let entry = Entry {
+            name: name.to_string(),
+            crc: crc32(contents),
+            size,
+            time,
+            date,
+            offset,
+        };
+
+        let mut header = Vec::with_capacity(30 + name.len());
+        header.extend_from_slice(&LOCAL_FILE_SIGNATURE.to_le_bytes());
+        header.extend_from_slice(&VERSION_NEEDED.to_le_bytes());
+        push_file_fields(&mut header, &entry);
+        header.extend_from_slice(&0u16.to_le_bytes()); // extra field length
+        header.extend_from_slice(name.as_bytes());
+
+        self.write(&header)?;
+        self.write(contents)?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Writes the central directory, completing the archive, and returns the output.
+    pub fn finish(mut self) -> io::Result<W> {
+        let start = u32::try_from(self.offset).map_err(|_| too_large("Zip archive too large"))?;
+        let mut directory = Vec::new();
+        for entry in &self.entries {
+            directory.extend_from_slice(&CENTRAL_FILE_SIGNATURE.to_le_bytes());
+            directory.extend_from_slice(&VERSION_NEEDED.to_le_bytes()); // version made by
+            directory.extend_from_slice(&VERSION_NEEDED.to_le_bytes());
+            push_file_fields(&mut directory, entry);
+            directory.extend_from_slice(&0u16.to_le_bytes()); // extra field length
+            directory.extend_from_slice(&0u16.to_le_bytes()); // comment length
+            directory.extend_from_slice(&0u16.to_le_bytes()); // disk number
+            directory.extend_from_slice(&0u16.to_le_bytes()); // internal attributes
+            directory.extend_from_slice(&0u32.to_le_bytes()); // external attributes
+            directory.extend_from_slice(&entry.offset.to_le_bytes());
+            directory.extend_from_slice(entry.name.as_bytes());
+        }
+        let count = self.entries.len() as u16;
+        let size = u32::try_from(directory.len()).map_err(|_| too_large("Zip archive too large"))?;
+
+        directory.extend_from_slice(&END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+        directory.extend_from_slice(&0u16.to_le_bytes()); // disk number
+        directory.extend_from_slice(&0u16.to_le_bytes()); // disk with the central directory
+        directory.extend_from_slice(&count.to_le_bytes());
+        directory.extend_from_slice(&count.to_le_bytes());
+        directory.extend_from_slice(&size.to_le_bytes());
+        directory.extend_from_slice(&start.to_le_bytes());
+        directory.extend_from_slice(&0u16.to_le_bytes()); // comment length
+
+        self.write(&directory)?;
+        self.output.flush()?;
+        Ok(self.output)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.output.write_all(bytes)?;
+        self.offset += bytes.len() as u64;
+        Ok(())
+    }
+}
+
+/// Appends the fields shared by the local and central headers, from the flags to the name length.
+fn push_file_fields(header: &mut Vec<u8>, entry: &Entry) {
+    header.extend_from_slice(&UTF8_NAMES.to_le_bytes());
+    header.extend_from_slice(&0u16.to_le_bytes()); // stored, no compression
+    header.extend_from_slice(&entry.time.to_le_bytes());
+    header.extend_from_slice(&entry.date.to_le_bytes());
+    header.extend_from_slice(&entry.crc.to_le_bytes());
+    header.extend_from_slice(&entry.size.to_le_bytes()); // compressed size
+    header.extend_from_slice(&entry.size.to_le_bytes());
+    header.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+}
+
+/// Returns the MS-DOS time and date of `time`, in UTC, clamped to 1980-01-01.
+fn dos_date_time(time: SystemTime) -> (u16, u16) {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+    let (year, month, day) = civil_from_days((seconds / SECONDS_PER_DAY) as i64);
+    if year < DOS_EPOCH_YEAR {
+        return (0, (1 << 5) | 1);
+    }
+
+    let seconds_of_day = seconds % SECONDS_PER_DAY;
+    let time = ((seconds_of_day / 3600) << 11) | ((seconds_of_day / 60 % 60) << 5) | (seconds_of_day % 60 / 2);
+    let date = (((year - DOS_EPOCH_YEAR).min(127) as u64) << 9) | ((month as u64) << 5) | day as u64;
+    (time as u16, date as u16)
+}
+
+fn too_large(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    #[test]
+    fn given_files_when_finish_then_write_the_headers_and_the_central_directory() {
+        // 2024-03-15 13:45:30 UTC
+        let modified = UNIX_EPOCH + Duration::from_secs(1_710_510_330);
+        let mut zip = ZipWriter::new(Vec::new());
+
+        zip.add("a.txt", b"123456789", modified).unwrap();
+        zip.add("b.txt", b"", modified).unwrap();
+        let archive = zip.finish().unwrap();
+
+        assert_eq!(&archive[..4], &LOCAL_FILE_SIGNATURE.to_le_bytes());
+        assert_eq!(&archive[10..14], &[0xAF, 0x6D, 0x6F, 0x58]); // 13:45:30, 2024-03-15
+        assert_eq!(&archive[14..18], &0xCBF4_3926u32.to_le_bytes());
+        assert_eq!(&archive[30..44], b"a.txt123456789");
+        assert_eq!(&archive[44..48], &LOCAL_FILE_SIGNATURE.to_le_bytes());
+
+        let end = archive.len() - 22;
+        assert_eq!(
+            &archive[end..end + 4],
+            &END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes()
+        );
+        assert_eq!(&archive[end + 8..end + 12], &[2, 0, 2, 0]);
+        assert_eq!(&archive[end + 12..end + 16], &(2 * 51u32).to_le_bytes());
+        assert_eq!(&archive[end + 16..end + 20], &79u32.to_le_bytes());
+    }
+
+    #[test]
+    fn given_a_time_before_1980_when_dos_date_time_then_clamp_it() {
+        assert_eq!(dos_date_time(UNIX_EPOCH), (0, 0x21));
+    }
+}
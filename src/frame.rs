@@ -4,6 +4,9 @@
 //! It defines constants, enums, and functions to handle the creation, parsing, and validation of frames
 //! used in communication with SA430 devices.
 //!
+//! Byte streams holding many frames can be read with `FrameStream`, from any reader, or split with `split_stream`,
+//! from a buffer, keeping the garbage and corrupt frames between them.
+//!
 //! ## Usage Example
 //! ```rust
 //! use sa430::frame::{Command, Frame};
@@ -17,6 +20,7 @@
 use std::{
     error::Error,
     fmt::{self, Display},
+    io::{self, Read},
 };
 
 use super::crc::crc16;
@@ -44,6 +48,7 @@ pub const FRAME_CRC_SIZE: usize = 2;
 
 /// SA430 command codes.
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[non_exhaustive]
 pub enum Command {
     /// Unknown
     #[default]
@@ -240,6 +245,7 @@ impl From<u8> for Command {
 
 /// SA430 Error codes.
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[non_exhaustive]
 pub enum ErrorCode {
     NoError = 0x0000,
     CmdBufferOverflow = 0x0320,
@@ -302,6 +308,48 @@ impl fmt::Display for ErrorCode {
     }
 }
 
+impl ErrorCode {
+    /// Returns a suggestion to recover from the error.
+    pub fn remediation(&self) -> &'static str {
+        match self {
+            ErrorCode::NoError => "No error is stored on the device.",
+            ErrorCode::WrongCrcLowByte | ErrorCode::WrongCrcHighByte => {
+                "A frame was corrupted on the link. Check the USB cable and retry."
+            }
+            ErrorCode::WrongCmdLength
+            | ErrorCode::RestoreFromPacketError
+            | ErrorCode::NoFrameStart
+            | ErrorCode::WrongPacketLength
+            | ErrorCode::PacketIncomplete
+            | ErrorCode::PacketError
+            | ErrorCode::StupidPacketHandler => {
+                "The device received a malformed frame. Make sure no other program is using the port and retry."
+            }
+            ErrorCode::CmdBufferOverflow
+            | ErrorCode::CmdAborted
+            | ErrorCode::LostCmd
+            | ErrorCode::EeqBufferOverflow
+            | ErrorCode::BufferOverflow
+            | ErrorCode::BufferUnderrun => {
+                "The device couldn't keep up with the commands. Wait for each reply before sending the next command, \
+                 or reduce the flash read window."
+            }
+            ErrorCode::UnknownCmd => "The firmware doesn't support the command. Check the firmware versions.",
+            ErrorCode::TooMuchDataRequestedByUserFunction | ErrorCode::BufferPosOutOfRange => {
+                "A request exceeded the device buffers. Read the flash in smaller chunks."
+            }
+            ErrorCode::RestoreProgramCounter => "The firmware recovered from an internal fault. Reboot the device.",
+            ErrorCode::FlashNotErased | ErrorCode::FlashMismatch => {
+                "The flash memory couldn't be written. Reboot the device and check its calibration data."
+            }
+            ErrorCode::RssiValidFlagNotSet | ErrorCode::PllNotSettled => {
+                "The receiver didn't settle during the measurement. Retry the sweep and reboot the device if it persists."
+            }
+            ErrorCode::Unknown => "The error is not documented. Reboot the device if it persists.",
+        }
+    }
+}
+
 impl fmt::UpperHex for ErrorCode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{:04X}", *self as u16)
@@ -310,6 +358,10 @@ impl fmt::UpperHex for ErrorCode {
 
 impl From<Vec<u8>> for ErrorCode {
     fn from(value: Vec<u8>) -> Self {
+        if value.len() < 2 {
+            return ErrorCode::Unknown;
+        }
+
         let code = u16::from_be_bytes([value[0], value[1]]);
         match code {
             0x0000 => ErrorCode::NoError,
@@ -343,6 +395,7 @@ impl From<Vec<u8>> for ErrorCode {
 
 /// Error types for the SA430 protocol.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[non_exhaustive]
 pub enum FrameError {
     /// Invalid magic value (current value).
     InvalidMagicValue(u8),
@@ -352,6 +405,9 @@ pub enum FrameError {
 
     /// Invalid CRC (expected, current).
     InvalidCrc(u16, u16),
+
+    /// No valid frame in the bytes.
+    NoFrame,
 }
 
 impl Error for FrameError {}
@@ -380,10 +436,22 @@ impl Display for FrameError {
                     expected, current
                 )
             }
+            FrameError::NoFrame => write!(f, "No valid frame found"),
         }
     }
 }
 
+/// How strictly bytes are parsed into frames.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    /// The bytes must hold exactly one valid frame.
+    #[default]
+    Strict,
+
+    /// The bytes may hold any number of frames, the garbage around them and the corrupt frames are skipped.
+    Lenient,
+}
+
 /// SA430 Frame.
 #[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Frame {
@@ -426,6 +494,27 @@ impl Frame {
         Frame::parse(bytes)
     }
 
+    /// Creates the frames held by a byte array, parsed according to `mode`.
+    ///
+    /// In lenient mode, the frames are found with [`split_stream`] and the bytes must hold at least one valid frame.
+    pub fn from_bytes_with_mode(bytes: &[u8], mode: ParseMode) -> Result<Vec<Self>, FrameError> {
+        if mode == ParseMode::Strict {
+            return Ok(vec![Frame::from_bytes(bytes)?]);
+        }
+
+        let frames: Vec<Self> = split_stream(bytes)
+            .into_iter()
+            .filter_map(|item| match item {
+                StreamItem::Frame(frame) => Frame::parse(frame).ok(),
+                _ => None,
+            })
+            .collect();
+        match frames.is_empty() {
+            true => Err(FrameError::NoFrame),
+            false => Ok(frames),
+        }
+    }
+
     /// Creates a byte array from the frame.
     pub fn to_bytes(&self) -> Vec<u8> {
         let mut bytes = vec![FRAME_MAGIC_VALUE, self.data.len() as u8, self.cmd as u8];
@@ -435,6 +524,11 @@ impl Frame {
         bytes
     }
 
+    /// Returns the frame bytes as an annotated hex dump, see [`hexdump`].
+    pub fn hexdump(&self) -> String {
+        hexdump(&self.to_bytes())
+    }
+
     /// Returns the error code if the frame is an error.
     pub fn to_error_code(&self) -> Option<ErrorCode> {
         match self.cmd() {
@@ -474,6 +568,174 @@ impl Frame {
     }
 }
 
+/// Formats the bytes of a frame as a line with each field annotated, for debugging.
+///
+/// The line has the magic value, the data length, the command name and code, the data and the CRC, followed by `ok`
+/// or by the expected CRC when it doesn't match, e.g. `magic 2A | len 2 | cmd SetGain (1A) | data 00 01 | crc
+/// 59A3 ok`. Bytes too short to hold a frame are dumped without annotations.
+pub fn hexdump(bytes: &[u8]) -> String {
+    let hex = |bytes: &[u8]| bytes.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" ");
+
+    if bytes.len() < FRAME_HEADER_SIZE + FRAME_CRC_SIZE {
+        return format!("truncated {}", hex(bytes));
+    }
+
+    let crc_index = bytes.len() - FRAME_CRC_SIZE;
+    let frame_crc = u16::from_be_bytes([bytes[crc_index], bytes[crc_index + 1]]);
+    let computed_crc = crc16(&bytes[..crc_index]);
+    let status = match frame_crc == computed_crc {
+        true => "ok".to_string(),
+        false => format!("fail, expected {:04X}", computed_crc),
+    };
+
+    format!(
+        "magic {:02X} | len {} | cmd {:?} ({:02X}) | data {} | crc {:04X} {}",
+        bytes[FRAME_MAGIC_INDEX],
+        bytes[FRAME_DATA_LENGTH_INDEX],
+        Command::from(bytes[FRAME_COMMAND_INDEX]),
+        bytes[FRAME_COMMAND_INDEX],
+        hex(&bytes[FRAME_DATA_INDEX..crc_index]),
+        frame_crc,
+        status
+    )
+}
+
+/// Part of a captured byte stream, as split by [`split_stream`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamItem<'a> {
+    /// Bytes of a frame with a valid CRC.
+    Frame(&'a [u8]),
+
+    /// Bytes of a frame with an invalid CRC, followed by another frame or by the end of the stream.
+    Corrupt(&'a [u8]),
+
+    /// Frame cut by the end of the stream.
+    Truncated(&'a [u8]),
+
+    /// Bytes outside of any frame.
+    Garbage(&'a [u8]),
+}
+
+/// Splits a captured byte stream, like a logic analyzer dump of the serial link, into frames.
+///
+/// A frame starts at each magic value and its length is taken from the header. When its CRC doesn't match and it is
+/// not followed by another frame, the magic value is taken as garbage and the search resumes at the next byte, so
+/// noise with a magic value doesn't hide the frames after it.
+pub fn split_stream(bytes: &[u8]) -> Vec<StreamItem<'_>> {
+    let mut items = Vec::new();
+    let mut garbage_start = None;
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let item = match bytes[index] {
+            FRAME_MAGIC_VALUE => frame_at(bytes, index),
+            _ => None,
+        };
+
+        match item {
+            Some(item) => {
+                if let Some(start) = garbage_start.take() {
+                    items.push(StreamItem::Garbage(&bytes[start..index]));
+                }
+                index += match item {
+                    StreamItem::Frame(frame) | StreamItem::Corrupt(frame) | StreamItem::Truncated(frame) => frame.len(),
+                    StreamItem::Garbage(garbage) => garbage.len(),
+                };
+                items.push(item);
+            }
+            None => {
+                garbage_start.get_or_insert(index);
+                index += 1;
+            }
+        }
+    }
+
+    if let Some(start) = garbage_start {
+        items.push(StreamItem::Garbage(&bytes[start..]));
+    }
+    items
+}
+
+/// Returns the frame starting at `index`, or `None` if the magic value there is garbage.
+fn frame_at(bytes: &[u8], index: usize) -> Option<StreamItem<'_>> {
+    let rest = &bytes[index..];
+    if rest.len() <= FRAME_DATA_LENGTH_INDEX {
+        return Some(StreamItem::Truncated(rest));
+    }
+
+    let size = FRAME_HEADER_SIZE + rest[FRAME_DATA_LENGTH_INDEX] as usize + FRAME_CRC_SIZE;
+    if rest.len() < size {
+        return Some(StreamItem::Truncated(rest));
+    }
+
+    let frame = &rest[..size];
+    let crc_index = size - FRAME_CRC_SIZE;
+    if u16::from_be_bytes([frame[crc_index], frame[crc_index + 1]]) == crc16(&frame[..crc_index]) {
+        return Some(StreamItem::Frame(frame));
+    }
+
+    match rest.get(size) {
+        None | Some(&FRAME_MAGIC_VALUE) => Some(StreamItem::Corrupt(frame)),
+        Some(_) => None,
+    }
+}
+
+/// Iterator over the frames read from a byte stream, like a serial port or a capture file.
+///
+/// The bytes before each magic value are skipped and the frame length is taken from its header, so each frame is read
+/// without reading past it. The iterator ends when the stream ends before a magic value, a stream that ends inside a
+/// frame yields an error.
+pub struct FrameStream<R: Read> {
+    reader: R,
+}
+
+impl<R: Read> FrameStream<R> {
+    /// Creates a stream reading the frames from `reader`.
+    pub fn new(reader: R) -> Self {
+        FrameStream { reader }
+    }
+
+    /// Reads the bytes of the next frame, without validating them, or `None` at the end of the stream.
+    pub fn next_bytes(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut magic = [0; 1];
+        loop {
+            match self.reader.read_exact(&mut magic) {
+                Ok(()) if magic[0] == FRAME_MAGIC_VALUE => break,
+                Ok(()) => continue,
+                Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+                Err(error) => return Err(error),
+            }
+        }
+
+        let mut bytes = vec![FRAME_MAGIC_VALUE, 0, 0];
+        self.reader.read_exact(&mut bytes[FRAME_DATA_LENGTH_INDEX..])?;
+        bytes.resize(
+            FRAME_HEADER_SIZE + bytes[FRAME_DATA_LENGTH_INDEX] as usize + FRAME_CRC_SIZE,
+            0,
+        );
+        self.reader.read_exact(&mut bytes[FRAME_HEADER_SIZE..])?;
+        Ok(Some(bytes))
+    }
+
+    /// Returns the reader of the stream.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read> Iterator for FrameStream<R> {
+    type Item = Result<Frame, Box<dyn Error>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_bytes() {
+            Ok(Some(bytes)) => Some(Frame::from_bytes(&bytes).map_err(|error| error.into())),
+            Ok(None) => None,
+            Err(error) => Some(Err(error.into())),
+        }
+    }
+}
+
+#[doc(hidden)]
 pub mod fixture {
     //! # Fixture Module
     //!
@@ -629,6 +891,11 @@ pub mod fixture {
         Frame::new(command)
     }
 
+    /// Fake error (NAK) response with the given error `code`
+    pub fn an_error_response(code: ErrorCode) -> Frame {
+        Frame::with_data(Command::GetLastError, &(code as u16).to_be_bytes())
+    }
+
     /// Fake get idn response
     pub fn a_get_idn_response() -> Frame {
         Frame::with_data(
@@ -664,6 +931,95 @@ pub mod fixture {
 mod tests {
     use super::*;
 
+    #[test]
+    fn given_a_frame_when_hexdump_then_annotate_each_field() {
+        let frame = Frame::with_data(Command::SetGain, &[0x00, 0x01]);
+        let crc = crc16(&[FRAME_MAGIC_VALUE, 0x02, 0x1B, 0x00, 0x01]);
+
+        assert_eq!(
+            frame.hexdump(),
+            format!("magic 2A | len 2 | cmd SetGain (1B) | data 00 01 | crc {:04X} ok", crc)
+        );
+    }
+
+    #[test]
+    fn given_a_stream_when_split_stream_then_return_frames_and_garbage() {
+        let idn = Frame::new(Command::GetIdn).to_bytes();
+        let sync = Frame::new(Command::Sync).to_bytes();
+        let mut corrupt = Frame::with_data(Command::SetGain, &[0x01]).to_bytes();
+        corrupt[3] = 0x02;
+        let stream = [&[0xFF, 0x2A, 0x00][..], &idn, &corrupt, &sync, &[0x2A, 0x03, 0x01]].concat();
+
+        let items = split_stream(&stream);
+
+        assert_eq!(
+            items,
+            vec![
+                StreamItem::Garbage(&[0xFF, 0x2A, 0x00]),
+                StreamItem::Frame(&idn),
+                StreamItem::Corrupt(&corrupt),
+                StreamItem::Frame(&sync),
+                StreamItem::Truncated(&[0x2A, 0x03, 0x01]),
+            ]
+        );
+    }
+
+    #[test]
+    fn given_frames_with_garbage_when_from_bytes_with_mode_then_recover_the_frames_only_when_lenient() {
+        let idn = Frame::new(Command::GetIdn);
+        let sync = Frame::new(Command::Sync);
+        let bytes = [&[0x00][..], &idn.to_bytes(), &sync.to_bytes(), &[0x2A, 0x05]].concat();
+
+        assert!(Frame::from_bytes_with_mode(&bytes, ParseMode::Strict).is_err());
+        assert_eq!(
+            Frame::from_bytes_with_mode(&bytes, ParseMode::Lenient),
+            Ok(vec![idn.clone(), sync])
+        );
+        assert_eq!(
+            Frame::from_bytes_with_mode(&idn.to_bytes(), ParseMode::Strict),
+            Ok(vec![idn])
+        );
+        assert_eq!(
+            Frame::from_bytes_with_mode(&[0x00, 0x2A], ParseMode::Lenient),
+            Err(FrameError::NoFrame)
+        );
+    }
+
+    #[test]
+    fn given_a_stream_with_garbage_when_frame_stream_then_iterate_over_the_frames() {
+        let idn = Frame::new(Command::GetIdn);
+        let gain = Frame::with_data(Command::SetGain, &[0x00, 0x01]);
+        let bytes = [&[0x00, 0xFF][..], &idn.to_bytes(), &[0x10], &gain.to_bytes()].concat();
+
+        let frames: Vec<Frame> = FrameStream::new(bytes.as_slice()).map(Result::unwrap).collect();
+
+        assert_eq!(frames, vec![idn, gain]);
+    }
+
+    #[test]
+    fn given_a_stream_ending_inside_a_frame_when_frame_stream_then_return_error() {
+        let bytes = Frame::new(Command::GetIdn).to_bytes();
+        let mut stream = FrameStream::new(&bytes[..4]);
+
+        assert!(stream.next().unwrap().is_err());
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn given_invalid_bytes_when_hexdump_then_report_crc_failure_and_truncation() {
+        let dump = hexdump(&[0x2A, 0x00, 0x07, 0x00, 0x01]);
+        let crc = crc16(&[0x2A, 0x00, 0x07]);
+
+        assert_eq!(
+            dump,
+            format!(
+                "magic 2A | len 0 | cmd Sync (07) | data  | crc 0001 fail, expected {:04X}",
+                crc
+            )
+        );
+        assert_eq!(hexdump(&[0x2A, 0x01]), "truncated 2A 01");
+    }
+
     #[test]
     fn given_a_command_when_new_then_return_frame() {
         let frame = Frame::new(Command::GetIdn);
@@ -729,10 +1085,50 @@ mod tests {
         assert_eq!(bytes, vec![0x2A, 0x04, 0x0A, 0xD4, 0x00, 0x00, 0x0A, 0xCD, 0xAD]);
     }
 
+    #[test]
+    fn given_an_error_without_data_when_to_error_code_then_return_unknown() {
+        let frame = Frame::new(Command::GetLastError);
+        assert_eq!(frame.to_error_code(), Some(ErrorCode::Unknown));
+    }
+
     #[test]
     fn given_an_error_when_to_error_code_then_return_error_code() {
         let frame = Frame::with_data(Command::GetLastError, &[0x03, 0x20]);
         let error_code = frame.to_error_code().unwrap();
         assert_eq!(error_code, ErrorCode::CmdBufferOverflow);
     }
+
+    #[test]
+    fn given_an_error_code_when_remediation_then_return_a_suggestion() {
+        assert_eq!(
+            ErrorCode::WrongCrcHighByte.remediation(),
+            "A frame was corrupted on the link. Check the USB cable and retry."
+        );
+        assert_eq!(ErrorCode::NoError.remediation(), "No error is stored on the device.");
+    }
+
+    #[test]
+    fn given_random_streams_when_reading_frames_then_never_panic_and_round_trip() {
+        // Same properties as the frame_stream fuzz target, over a fixed set of inputs.
+        let mut state: u64 = 0x5A43;
+        for length in 0..512 {
+            let bytes: Vec<u8> = (0..length)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    // Make magic values frequent, so frames are found.
+                    match state % 8 {
+                        0 => FRAME_MAGIC_VALUE,
+                        _ => (state >> 8) as u8,
+                    }
+                })
+                .collect();
+
+            for frame in FrameStream::new(bytes.as_slice()).flatten() {
+                assert_eq!(Frame::from_bytes(&frame.to_bytes()).unwrap(), frame);
+            }
+            split_stream(&bytes);
+        }
+    }
 }
@@ -0,0 +1,235 @@
+//! # Frequency Module
+//!
+//! This module provides `Frequency`, a frequency stored in Hz, used by the sweep parameters of the device, spectrum
+//! and simulator APIs, so a value in MHz can't be passed where Hz are expected. It is created from Hz, kHz or MHz,
+//! read back in any of them, and supports the arithmetic of sweep grids, like adding a number of steps to a start.
+//!
+//! Frequencies are displayed in MHz, honoring the precision of the format, and parsed from a number with an optional
+//! `Hz`, `kHz`, `MHz` or `GHz` unit, Hz when it's left out.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use sa430::frequency::Frequency;
+//!
+//! let fstart = Frequency::from_mhz(868.0);
+//! let fstep = Frequency::from_khz(100.0);
+//!
+//! assert_eq!((fstart + 10.0 * fstep).mhz(), 869.0);
+//! assert_eq!(format!("{:.2}", fstart), "868.00 MHz");
+//! assert_eq!("868.1 MHz".parse::<Frequency>(), Ok(Frequency::from_mhz(868.1)));
+//! ```
+
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    ops::{Add, AddAssign, Div, Mul, Sub, SubAssign},
+    str::FromStr,
+};
+
+/// A frequency, in Hz.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct Frequency(f64);
+
+impl Frequency {
+    /// A frequency of 0 Hz.
+    pub const ZERO: Frequency = Frequency(0.0);
+
+    /// Creates a frequency of `hz` Hz.
+    pub const fn from_hz(hz: f64) -> Self {
+        Frequency(hz)
+    }
+
+    /// Creates a frequency of `khz` kHz.
+    pub const fn from_khz(khz: f64) -> Self {
+        Frequency(khz * 1e3)
+    }
+
+    /// Creates a frequency of `mhz` MHz.
+    pub const fn from_mhz(mhz: f64) -> Self {
+        Frequency(mhz * 1e6)
+    }
+
+    /// Returns the frequency in Hz.
+    pub const fn hz(&self) -> f64 {
+        self.0
+    }
+
+    /// Returns the frequency in kHz.
+    pub fn khz(&self) -> f64 {
+        self.0 / 1e3
+    }
+
+    /// Returns the frequency in MHz.
+    pub fn mhz(&self) -> f64 {
+        self.0 / 1e6
+    }
+
+    /// Returns the lowest of two frequencies.
+    pub fn min(self, other: Frequency) -> Frequency {
+        Frequency(self.0.min(other.0))
+    }
+
+    /// Returns the highest of two frequencies.
+    pub fn max(self, other: Frequency) -> Frequency {
+        Frequency(self.0.max(other.0))
+    }
+}
+
+impl Add for Frequency {
+    type Output = Frequency;
+
+    fn add(self, other: Frequency) -> Frequency {
+        Frequency(self.0 + other.0)
+    }
+}
+
+impl AddAssign for Frequency {
+    fn add_assign(&mut self, other: Frequency) {
+        self.0 += other.0;
+    }
+}
+
+impl Sub for Frequency {
+    type Output = Frequency;
+
+    fn sub(self, other: Frequency) -> Frequency {
+        Frequency(self.0 - other.0)
+    }
+}
+
+impl SubAssign for Frequency {
+    fn sub_assign(&mut self, other: Frequency) {
+        self.0 -= other.0;
+    }
+}
+
+impl Mul<f64> for Frequency {
+    type Output = Frequency;
+
+    fn mul(self, factor: f64) -> Frequency {
+        Frequency(self.0 * factor)
+    }
+}
+
+impl Mul<Frequency> for f64 {
+    type Output = Frequency;
+
+    fn mul(self, frequency: Frequency) -> Frequency {
+        Frequency(self * frequency.0)
+    }
+}
+
+impl Div<f64> for Frequency {
+    type Output = Frequency;
+
+    fn div(self, divisor: f64) -> Frequency {
+        Frequency(self.0 / divisor)
+    }
+}
+
+/// The ratio of two frequencies, like the number of steps in a span.
+impl Div for Frequency {
+    type Output = f64;
+
+    fn div(self, other: Frequency) -> f64 {
+        self.0 / other.0
+    }
+}
+
+impl Display for Frequency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match f.precision() {
+            Some(precision) => write!(f, "{:.*} MHz", precision, self.mhz()),
+            None => write!(f, "{} MHz", self.mhz()),
+        }
+    }
+}
+
+/// Error parsing a frequency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidFrequency(String);
+
+impl Error for InvalidFrequency {}
+
+impl Display for InvalidFrequency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid frequency: {}, expected a number with an optional Hz, kHz, MHz or GHz unit",
+            self.0
+        )
+    }
+}
+
+impl FromStr for Frequency {
+    type Err = InvalidFrequency;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidFrequency(value.to_string());
+        let trimmed = value.trim();
+        let split = trimmed
+            .find(|c: char| c.is_ascii_alphabetic() && c != 'e' && c != 'E')
+            .unwrap_or(trimmed.len());
+        let (number, unit) = trimmed.split_at(split);
+        let scale = match unit.to_lowercase().as_str() {
+            "" | "hz" => 1.0,
+            "khz" => 1e3,
+            "mhz" => 1e6,
+            "ghz" => 1e9,
+            _ => return Err(invalid()),
+        };
+        match number.trim().parse::<f64>() {
+            Ok(number) if number.is_finite() => Ok(Frequency(number * scale)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_units_when_create_a_frequency_then_store_it_in_hz() {
+        assert_eq!(Frequency::from_khz(125.0).hz(), 125.0e3);
+        assert_eq!(Frequency::from_mhz(868.1).hz(), 868.1e6);
+        assert_eq!(Frequency::from_hz(433.92e6).mhz(), 433.92);
+        assert_eq!(Frequency::from_hz(58.0e3).khz(), 58.0);
+    }
+
+    #[test]
+    fn given_frequencies_when_compute_a_grid_then_keep_the_units() {
+        let fstart = Frequency::from_mhz(868.0);
+        let fstop = Frequency::from_mhz(870.0);
+        let fstep = Frequency::from_khz(500.0);
+
+        assert_eq!((fstop - fstart) / fstep, 4.0);
+        assert_eq!(fstart + 2.0 * fstep, Frequency::from_mhz(869.0));
+        assert_eq!(fstep * 2.0 / 4.0, Frequency::from_khz(250.0));
+        assert_eq!(fstart.max(fstop), fstop);
+    }
+
+    #[test]
+    fn given_strings_with_units_when_parse_then_return_the_frequency() {
+        assert_eq!("868100000".parse(), Ok(Frequency::from_mhz(868.1)));
+        assert_eq!("868.1MHz".parse(), Ok(Frequency::from_mhz(868.1)));
+        assert_eq!(" 125 kHz ".parse(), Ok(Frequency::from_khz(125.0)));
+        assert_eq!("0.9 GHz".parse(), Ok(Frequency::from_mhz(900.0)));
+        assert_eq!("1e3 hz".parse(), Ok(Frequency::from_khz(1.0)));
+    }
+
+    #[test]
+    fn given_invalid_strings_when_parse_then_return_an_error() {
+        assert!("".parse::<Frequency>().is_err());
+        assert!("868 MHZ MHz".parse::<Frequency>().is_err());
+        assert!("fast".parse::<Frequency>().is_err());
+        assert!("inf".parse::<Frequency>().is_err());
+    }
+
+    #[test]
+    fn given_a_precision_when_display_then_write_mhz() {
+        assert_eq!(Frequency::from_mhz(868.1).to_string(), "868.1 MHz");
+        assert_eq!(format!("{:.3}", Frequency::from_khz(433_920.0)), "433.920 MHz");
+    }
+}
@@ -0,0 +1,429 @@
+//! # HTTP Module
+//!
+//! This module provides a minimal HTTP/1.1 server, enough to expose a JSON API and metrics to integrations that prefer plain HTTP
+//! over a streaming protocol, without pulling a web framework into the crate.
+//!
+//! Each connection carries a single request, answered and closed. Request lines and headers are limited to
+//! [`MAX_LINE`] bytes, request bodies to [`MAX_BODY_SIZE`] bytes, and chunked transfers are not supported. At most
+//! [`MAX_CONNECTIONS`] requests are answered at once, the clients connecting past them get a 503. With the `tls`
+//! feature, `serve_tls` answers over HTTPS instead, see the `tls` module.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use std::io::BufReader;
+//!
+//! use sa430::http::{read_request, write_response, Response};
+//!
+//! let mut input = BufReader::new("GET /devices HTTP/1.1\r\nHost: localhost\r\n\r\n".as_bytes());
+//! let request = read_request(&mut input).unwrap();
+//! assert_eq!((request.method.as_str(), request.path.as_str()), ("GET", "/devices"));
+//!
+//! let mut output = Vec::new();
+//! write_response(&mut output, &Response::json(200, "[]")).unwrap();
+//! assert!(output.starts_with(b"HTTP/1.1 200 OK\r\n"));
+//! ```
+
+use std::{
+    error::Error,
+    fmt,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::formats::ndjson::escape;
+#[cfg(feature = "tls")]
+use crate::tls::TlsAcceptor;
+
+/// Maximum size of a request body, in bytes.
+pub const MAX_BODY_SIZE: usize = 64 * 1024;
+
+/// Maximum length of the request line and of each header, in bytes, including the line break.
+pub const MAX_LINE: usize = 8 * 1024;
+
+/// Maximum number of requests answered at once.
+pub const MAX_CONNECTIONS: usize = 32;
+
+/// Maximum number of headers of a request.
+const MAX_HEADERS: usize = 64;
+
+/// Time a client has to send its request, and to receive the response.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Time to wait after failing to accept a connection, like when the process runs out of file descriptors.
+const ACCEPT_ERROR_DELAY: Duration = Duration::from_millis(100);
+
+/// A request, with its headers names in lower case.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Request {
+    /// Returns the value of a header, by its lower case name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Content type of the JSON responses.
+const JSON_CONTENT_TYPE: &str = "application/json";
+
+/// A response, with a JSON body by default.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    /// Creates a response with a JSON body.
+    pub fn json(status: u16, body: impl Into<String>) -> Self {
+        Response::with_content_type(status, JSON_CONTENT_TYPE, body)
+    }
+
+    /// Creates a response with a body of another content type, like metrics.
+    pub fn with_content_type(status: u16, content_type: &'static str, body: impl Into<String>) -> Self {
+        Response {
+            status,
+            content_type,
+            body: body.into(),
+        }
+    }
+
+    /// Creates an error response, with the message in the `error` field of the body.
+    pub fn error(status: u16, message: &str) -> Self {
+        Response::json(status, format!("{{\"error\":\"{}\"}}", escape(message)))
+    }
+}
+
+/// Handles the requests of a server.
+pub type Handler = Arc<dyn Fn(&Request) -> Response + Send + Sync>;
+
+/// Handler of the warnings of a server, like a connection it failed to accept.
+pub type WarningHandler = Box<dyn FnMut(&str) + Send>;
+
+/// Answers the requests of the clients of `listener`, each in its own thread, up to [`MAX_CONNECTIONS`] at once.
+///
+/// Runs until the process exits: a connection that fails to be accepted is reported to `warnings`, if any, and the
+/// server goes on with the next one.
+pub fn serve(listener: TcpListener, handler: Handler, warnings: Option<WarningHandler>) {
+    serve_with(listener, None, handler, warnings)
+}
+
+/// Answers the requests of the clients of `listener` like [`serve`], over TLS with the certificate of `acceptor`.
+#[cfg(feature = "tls")]
+pub fn serve_tls(listener: TcpListener, acceptor: TlsAcceptor, handler: Handler, warnings: Option<WarningHandler>) {
+    let secure: Secure = Arc::new(move |stream| Ok(Box::new(acceptor.accept(stream)?)));
+    serve_with(listener, Some(secure), handler, warnings)
+}
+
+/// A connection read and written through a single value, like a TCP or a TLS stream.
+trait Stream: Read + Write + Send {}
+
+impl<S: Read + Write + Send> Stream for S {}
+
+/// Secures the connection of a client, like with a TLS handshake.
+type Secure = Arc<dyn Fn(TcpStream) -> Result<Box<dyn Stream>, Box<dyn Error>> + Send + Sync>;
+
+/// Answers the requests of the clients of `listener`, securing their connections with `secure`, if any.
+fn serve_with(listener: TcpListener, secure: Option<Secure>, handler: Handler, mut warnings: Option<WarningHandler>) {
+    let active = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(error) => {
+                if let Some(warnings) = &mut warnings {
+                    warnings(&format!("Unable to accept a connection: {}", error));
+                }
+                thread::sleep(ACCEPT_ERROR_DELAY);
+                continue;
+            }
+        };
+        let connection = Connection::open(&active);
+        if connection.is_none() {
+            // A client that can't be answered now only gets a short error, without holding a thread. A TLS client
+            // couldn't read it without a handshake, so its connection is only closed.
+            if secure.is_none() {
+                let _ = stream.set_write_timeout(Some(REQUEST_TIMEOUT));
+                let _ = write_response(&mut &stream, &Response::error(503, "Too many connections"));
+            }
+            continue;
+        }
+
+        let (secure, handler) = (secure.clone(), handler.clone());
+        thread::spawn(move || {
+            let _connection = connection;
+            // A client that disconnects or sends garbage only loses its own request.
+            let _ = answer(stream, secure.as_ref(), &*handler);
+        });
+    }
+}
+
+/// A connection being answered, counted in the active connections of the server while it lives.
+struct Connection(Arc<AtomicUsize>);
+
+impl Connection {
+    /// Counts a new connection, or returns `None` if there are already [`MAX_CONNECTIONS`].
+    fn open(active: &Arc<AtomicUsize>) -> Option<Self> {
+        active
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |count| {
+                (count < MAX_CONNECTIONS).then_some(count + 1)
+            })
+            .ok()
+            .map(|_| Connection(active.clone()))
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Reads the request of a client and writes the response of the handler, or a 400 for invalid requests.
+fn answer(
+    stream: TcpStream,
+    secure: Option<&Secure>,
+    handler: &(dyn Fn(&Request) -> Response + Send + Sync),
+) -> Result<(), Box<dyn Error>> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut stream = match secure {
+        Some(secure) => secure(stream)?,
+        None => Box::new(stream),
+    };
+    let response = match read_request(&mut BufReader::new(&mut stream)) {
+        Ok(request) => handler(&request),
+        Err(error) => Response::error(400, &error.to_string()),
+    };
+    write_response(&mut stream, &response)
+}
+
+/// Reads a request: the request line, the headers and a body of `Content-Length` bytes, if any.
+///
+/// Fails if a line is longer than [`MAX_LINE`], or there are more than `MAX_HEADERS` headers.
+pub fn read_request(input: &mut dyn BufRead) -> Result<Request, Box<dyn Error>> {
+    let mut line = String::new();
+    read_line(input, &mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path), Some(version)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(HttpError::InvalidRequest.into());
+    };
+    if !version.starts_with("HTTP/1.") {
+        return Err(HttpError::InvalidRequest.into());
+    }
+    let mut request = Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        ..Default::default()
+    };
+
+    loop {
+        line.clear();
+        read_line(input, &mut line)?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let (name, value) = header.split_once(':').ok_or(HttpError::InvalidRequest)?;
+        if request.headers.len() == MAX_HEADERS {
+            return Err(HttpError::InvalidRequest.into());
+        }
+        request
+            .headers
+            .push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+    }
+
+    let length = match request.header("content-length") {
+        Some(length) => length.parse().map_err(|_| HttpError::InvalidRequest)?,
+        None => 0,
+    };
+    if length > MAX_BODY_SIZE {
+        return Err(HttpError::BodyTooLarge.into());
+    }
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    request.body = String::from_utf8(body)?;
+    Ok(request)
+}
+
+/// Reads a line of at most [`MAX_LINE`] bytes into `line`, failing if it is longer.
+fn read_line(input: &mut dyn BufRead, line: &mut String) -> Result<(), Box<dyn Error>> {
+    let length = input.take(MAX_LINE as u64).read_line(line)?;
+    if length == MAX_LINE && !line.ends_with('\n') {
+        return Err(HttpError::LineTooLong.into());
+    }
+    Ok(())
+}
+
+/// Writes a response and asks the client to close the connection.
+pub fn write_response(output: &mut dyn Write, response: &Response) -> Result<(), Box<dyn Error>> {
+    write!(
+        output,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason(response.status),
+        response.content_type,
+        response.body.len(),
+        response.body
+    )?;
+    Ok(output.flush()?)
+}
+
+/// Returns the reason phrase of the status codes used by the crate.
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+/// HTTP errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpError {
+    /// The request line or a header is malformed.
+    InvalidRequest,
+
+    /// The body is larger than [`MAX_BODY_SIZE`].
+    BodyTooLarge,
+
+    /// The request line or a header is longer than [`MAX_LINE`].
+    LineTooLong,
+}
+
+impl Error for HttpError {}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HttpError::InvalidRequest => write!(f, "Invalid HTTP request"),
+            HttpError::BodyTooLarge => write!(f, "The request body is larger than {} bytes", MAX_BODY_SIZE),
+            HttpError::LineTooLong => write!(f, "A request line is longer than {} bytes", MAX_LINE),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Read;
+
+    #[test]
+    fn given_a_request_with_a_body_when_read_request_then_return_its_headers_and_body() {
+        let mut input = BufReader::new(
+            "POST /devices/1234/capture HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: 11\r\n\r\n\
+             {\"count\":2}"
+                .as_bytes(),
+        );
+
+        let request = read_request(&mut input).unwrap();
+
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/devices/1234/capture");
+        assert_eq!(request.header("content-type"), Some("application/json"));
+        assert_eq!(request.body, "{\"count\":2}");
+    }
+
+    #[test]
+    fn given_an_endless_line_or_too_many_headers_when_read_request_then_return_error() {
+        let endless = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_LINE));
+        let headers = format!("GET / HTTP/1.1\r\n{}\r\n", "X-Header: 1\r\n".repeat(MAX_HEADERS + 1));
+
+        let endless = read_request(&mut endless.as_bytes()).unwrap_err();
+        let headers = read_request(&mut headers.as_bytes()).unwrap_err();
+
+        assert_eq!(endless.downcast_ref(), Some(&HttpError::LineTooLong));
+        assert_eq!(headers.downcast_ref(), Some(&HttpError::InvalidRequest));
+    }
+
+    #[test]
+    fn given_a_message_with_control_characters_when_error_then_escape_them() {
+        let response = Response::error(404, "No capture with id \"1\n\u{1}\"");
+
+        assert_eq!(
+            response.body,
+            "{\"error\":\"No capture with id \\\"1\\u000a\\u0001\\\"\"}"
+        );
+    }
+
+    #[test]
+    fn given_max_connections_when_open_then_refuse_the_next_until_one_closes() {
+        let active = Arc::new(AtomicUsize::new(0));
+
+        let mut connections: Vec<Connection> = (0..MAX_CONNECTIONS).filter_map(|_| Connection::open(&active)).collect();
+
+        assert_eq!(connections.len(), MAX_CONNECTIONS);
+        assert!(Connection::open(&active).is_none());
+        connections.pop();
+        assert!(Connection::open(&active).is_some());
+        drop(connections);
+        assert_eq!(active.load(Ordering::Acquire), 0);
+    }
+
+    #[test]
+    fn given_a_server_when_a_client_sends_garbage_then_answer_bad_request() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            serve(listener, Arc::new(|_: &Request| Response::json(200, "{}")), None);
+        });
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(b"HELLO\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+        assert!(response.ends_with("{\"error\":\"Invalid HTTP request\"}"));
+    }
+
+    #[cfg(feature = "tls")]
+    #[test]
+    fn given_a_tls_server_when_a_client_sends_a_request_then_answer_it_encrypted() {
+        use crate::tls::{fixtures, TlsConnector};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let acceptor = TlsAcceptor::from_pem(fixtures::SERVER_CERTIFICATE, fixtures::SERVER_KEY).unwrap();
+        thread::spawn(move || {
+            let handler = Arc::new(|request: &Request| Response::json(200, format!("\"{}\"", request.path)));
+            serve_tls(listener, acceptor, handler, None);
+        });
+
+        let connector = TlsConnector::from_pem(fixtures::CA).unwrap();
+        let mut stream = connector
+            .connect("127.0.0.1", TcpStream::connect(address).unwrap())
+            .unwrap();
+        stream
+            .write_all(b"GET /devices HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("\"/devices\""));
+    }
+}
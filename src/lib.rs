@@ -3,22 +3,78 @@
 //! This crate provides functionality to interact with SA430 SA430 Sub-1 GHz RF Spectrum Analyzer
 //! from [Texas Instruments](https://www.ti.com/). It includes modules for finding connected devices, handling
 //! commands, capturing data, and more.
+//!
+//! ## Features
+//!
+//! The frame, protocol and device modules have no dependencies, so a minimal build with a custom `Channel` only needs
+//! `default-features = false`. The default features add:
+//!
+//! - `serial`: `SerialPortChannel`, using the `serialport` crate.
+//! - `scanner`: finding the connected devices, using `udev` on Linux.
+//! - `monitor`: watching devices being connected and removed, using `udev` or inotify on Linux.
+//! - `dsp`: spectrum processing, like channel plans, triggers and classification.
+//! - `usbfs`: `UsbfsChannel`, talking to the device through a usbfs file descriptor, like the ones Android grants to
+//!   apps, on Linux and Android.
+//! - `net`: network integrations, like the webhook alerts of the CLI and the capture `server` and `client`.
+//! - `cli`: the `sa430` command line utility, with all of the above but `net`.
 
+#[cfg(feature = "net")]
+pub mod auth;
+pub mod bands;
+pub mod cancel;
+pub mod capabilities;
 pub mod channel;
+#[cfg(feature = "net")]
+pub mod client;
+pub mod compat;
+pub mod correction;
 pub mod device;
+#[cfg(feature = "dsp")]
+pub mod dsp;
+pub mod engine;
+pub mod formats;
 pub mod frame;
+pub mod frequency;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod location;
+pub mod log;
+pub mod metrics;
+#[cfg(feature = "monitor")]
 pub mod monitor;
+pub mod noise;
 pub mod port;
+pub mod power;
+pub mod profile;
+pub mod reference;
+#[cfg(feature = "scanner")]
 pub mod scanner;
+#[cfg(feature = "net")]
+pub mod server;
+pub mod session;
+pub mod sink;
+pub mod spectrum;
+pub mod storage;
+pub mod sweep;
+pub mod timestamp;
+#[cfg(feature = "tls")]
+pub mod tls;
+#[cfg(all(feature = "usbfs", any(target_os = "linux", target_os = "android")))]
+pub mod usbfs;
+
+#[cfg(feature = "simulator")]
+pub mod simulator;
 
 pub(crate) mod crc;
 pub(crate) mod parser;
 pub(crate) mod protocol;
+pub(crate) mod sha256;
 
-#[cfg(target_os = "linux")]
+#[cfg(all(target_os = "linux", any(feature = "scanner", feature = "monitor")))]
 pub(crate) mod linux;
 
 /// Creates a scanner for the current OS.
+#[cfg(feature = "scanner")]
 pub fn create_scanner() -> Box<dyn scanner::Scanner> {
     #[cfg(target_os = "linux")]
     return Box::new(linux::scanner::LinuxScanner::new());
@@ -28,42 +84,54 @@ pub fn create_scanner() -> Box<dyn scanner::Scanner> {
 }
 
 /// Creates a monitor for Linux.
-#[cfg(target_os = "linux")]
+#[cfg(all(target_os = "linux", feature = "monitor"))]
 pub fn create_monitor<'a>() -> Box<linux::monitor::LinuxMonitor<'a>> {
     Box::new(linux::monitor::LinuxMonitor::new())
 }
 
+/// Creates a monitor for Linux watching `/dev` with inotify, for containers without udev or netlink.
+#[cfg(all(target_os = "linux", feature = "monitor"))]
+pub fn create_inotify_monitor<'a>() -> Box<dyn monitor::Monitor<'a> + 'a> {
+    Box::new(linux::inotify::InotifyMonitor::new())
+}
+
 /// Creates a monitor for other OS.
-#[cfg(not(target_os = "linux"))]
+#[cfg(all(not(target_os = "linux"), feature = "monitor"))]
 pub fn create_monitor<'a>() -> Box<dyn monitor::Monitor<'a>> {
     panic!("No monitor for current OS");
 }
 
-#[cfg(test)]
+#[cfg(all(test, any(feature = "scanner", feature = "monitor")))]
 mod tests {
     use super::*;
 
     #[test]
-    #[cfg(target_os = "linux")]
+    #[cfg(all(target_os = "linux", feature = "scanner"))]
     fn given_target_is_linux_when_create_scanner_then_create_a_linux_scanner() {
         create_scanner();
     }
 
     #[test]
-    #[cfg(not(target_os = "linux"))]
+    #[cfg(all(not(target_os = "linux"), feature = "monitor"))]
     #[should_panic]
     fn given_target_is_unknown_when_create_scanner_then_panic() {
         create_monitor();
     }
 
     #[test]
-    #[cfg(target_os = "linux")]
+    #[cfg(all(target_os = "linux", feature = "monitor"))]
     fn given_target_is_linux_when_create_monitor_then_create_a_linux_monitor() {
         create_monitor();
     }
 
     #[test]
-    #[cfg(not(target_os = "linux"))]
+    #[cfg(all(target_os = "linux", feature = "monitor"))]
+    fn given_target_is_linux_when_create_inotify_monitor_then_create_an_inotify_monitor() {
+        create_inotify_monitor();
+    }
+
+    #[test]
+    #[cfg(all(not(target_os = "linux"), feature = "monitor"))]
     #[should_panic]
     fn given_target_is_unknown_when_create_monitor_then_panic() {
         create_monitor();
@@ -0,0 +1,492 @@
+use std::{
+    collections::HashMap,
+    ffi::{c_char, c_int, CString},
+    fs::{self, File},
+    io::{self, Read},
+    os::{
+        fd::{FromRawFd, OwnedFd},
+        unix::ffi::OsStrExt,
+    },
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use crate::monitor::*;
+use crate::port::*;
+
+/// Time to wait before each attempt to watch the directories again after an error.
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+
+/// Size of the fixed part of an inotify event, before its name.
+const EVENT_HEADER_SIZE: usize = 16;
+
+/// Size of the buffer events are read into, enough for a few events with the longest names.
+const EVENT_BUFFER_SIZE: usize = 4096;
+
+/// Close the inotify file descriptor on exec, `O_CLOEXEC`.
+const IN_CLOEXEC: c_int = 0o2000000;
+
+/// Flags of the events: a file was created, deleted or renamed in a watched directory.
+const IN_MOVED_FROM: u32 = 0x0000_0040;
+const IN_MOVED_TO: u32 = 0x0000_0080;
+const IN_CREATE: u32 = 0x0000_0100;
+const IN_DELETE: u32 = 0x0000_0200;
+
+/// Flag of the event telling the event queue overflowed, events were lost.
+const IN_Q_OVERFLOW: u32 = 0x0000_4000;
+
+/// Prefixes of the names of the tty nodes of USB serial devices.
+const TTY_PREFIXES: [&str; 2] = ["ttyACM", "ttyUSB"];
+
+/// Part of the name of the `/dev/serial/by-id` links of the SA430.
+const BY_ID_NAME: &str = "SA430";
+
+extern "C" {
+    fn inotify_init1(flags: c_int) -> c_int;
+    fn inotify_add_watch(fd: c_int, path: *const c_char, mask: u32) -> c_int;
+}
+
+/// An inotify event, with the watch it came from and the name of the file in the watched directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct InotifyEvent {
+    watch: i32,
+    mask: u32,
+    name: String,
+}
+
+/// Monitor watching the device nodes in `/dev` and the links in `/dev/serial/by-id` with inotify, for containers
+/// without udev or netlink.
+///
+/// The SA430 nodes are told apart by their name, `ttyACM*` or `ttyUSB*`, and the USB IDs of their device in sysfs, or
+/// by the `SA430` in their by-id link when sysfs isn't available. Removals are only reported for the devices seen
+/// before, since sysfs is gone by then.
+pub struct InotifyMonitor<'a> {
+    handlers: Vec<&'a mut dyn EventHandler>,
+    dev_dir: PathBuf,
+    by_id_dir: PathBuf,
+    sys_class_tty: PathBuf,
+    ports: HashMap<String, Port>,
+    by_id_watch: Option<i32>,
+}
+
+impl InotifyMonitor<'_> {
+    pub fn new<'a>() -> InotifyMonitor<'a> {
+        InotifyMonitor::with_paths(
+            PathBuf::from("/dev"),
+            PathBuf::from(BY_ID_DIR),
+            PathBuf::from("/sys/class/tty"),
+        )
+    }
+
+    /// Creates a monitor of the nodes in `dev_dir` and the links in `by_id_dir`, reading the devices from
+    /// `sys_class_tty`.
+    pub fn with_paths<'a>(dev_dir: PathBuf, by_id_dir: PathBuf, sys_class_tty: PathBuf) -> InotifyMonitor<'a> {
+        InotifyMonitor {
+            handlers: Vec::new(),
+            dev_dir,
+            by_id_dir,
+            sys_class_tty,
+            ports: HashMap::new(),
+            by_id_watch: None,
+        }
+    }
+
+    /// Watches the directories and finds the devices already connected, without notifying them.
+    fn listen(&mut self) -> io::Result<File> {
+        // SAFETY: inotify_init1 has no preconditions, and a non-negative result is a new file descriptor we own.
+        let fd = unsafe { inotify_init1(IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: fd was just created by inotify_init1 and isn't owned by anything else.
+        let file = File::from(unsafe { OwnedFd::from_raw_fd(fd) });
+
+        let mask = IN_CREATE | IN_DELETE | IN_MOVED_FROM | IN_MOVED_TO;
+        add_watch(fd, &self.dev_dir, mask)?;
+        self.by_id_watch = add_watch(fd, &self.by_id_dir, mask).ok();
+
+        self.ports.clear();
+        for name in list_dir(&self.dev_dir) {
+            if let Some(port) = self.tty_port(&name) {
+                self.ports.insert(name, port);
+            }
+        }
+        Ok(file)
+    }
+
+    /// Reads and processes the next events, returning the error that stopped the inotify file, if any.
+    fn poll(&mut self, file: &mut File) -> io::Result<()> {
+        let mut buffer = [0u8; EVENT_BUFFER_SIZE];
+        let read = match file.read(&mut buffer) {
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => return Ok(()),
+            result => result?,
+        };
+
+        for event in parse_events(&buffer[..read]) {
+            if event.mask & IN_Q_OVERFLOW != 0 {
+                return Err(io::Error::other("inotify event queue overflow"));
+            }
+            self.process(&event);
+        }
+        Ok(())
+    }
+
+    fn process(&mut self, event: &InotifyEvent) {
+        let created = event.mask & (IN_CREATE | IN_MOVED_TO) != 0;
+        let by_id = Some(event.watch) == self.by_id_watch;
+
+        match (by_id, created) {
+            (false, true) => {
+                if let Some(port) = self.tty_port(&event.name) {
+                    self.add(&event.name, port);
+                }
+            }
+            (false, false) => {
+                if let Some(port) = self.ports.remove(&event.name) {
+                    self.notify(&Event::DeviceRemoved(port, Metadata::now(None)));
+                }
+            }
+            (true, true) => self.link_created(&event.name),
+            (true, false) => {}
+        }
+    }
+
+    /// Completes the port of the link target with the link, or adds it if its node wasn't seen, like when `/dev` is
+    /// bind-mounted without its nodes being watchable.
+    fn link_created(&mut self, link: &str) {
+        if !link.contains(BY_ID_NAME) {
+            return;
+        }
+        let Some(name) = link_target(&self.by_id_dir.join(link)) else {
+            return;
+        };
+
+        let by_id = self.by_id_dir.join(link).to_string_lossy().to_string();
+        match self.ports.remove(&name) {
+            Some(port) => {
+                self.ports.insert(name, port.with_by_id(&by_id));
+            }
+            None => {
+                let port = self
+                    .tty_port(&name)
+                    .unwrap_or_else(|| port_from_link(&self.dev_dir.join(&name), link));
+                self.add(&name, port.with_by_id(&by_id));
+            }
+        }
+    }
+
+    fn add(&mut self, name: &str, port: Port) {
+        if self.ports.contains_key(name) {
+            return;
+        }
+        self.ports.insert(name.to_string(), port.clone());
+        self.notify(&Event::DeviceAdded(port, Metadata::now(None)));
+    }
+
+    /// Returns the port of the tty node `name`, if it is a SA430, with its by-id link, if any.
+    fn tty_port(&self, name: &str) -> Option<Port> {
+        if !TTY_PREFIXES.iter().any(|prefix| name.starts_with(prefix)) {
+            return None;
+        }
+
+        let port = port_from_sysfs(&self.sys_class_tty, &self.dev_dir.join(name))?;
+        let by_id = list_dir(&self.by_id_dir)
+            .into_iter()
+            .find(|link| link.contains(BY_ID_NAME) && link_target(&self.by_id_dir.join(link)).as_deref() == Some(name));
+        match by_id {
+            Some(link) => Some(port.with_by_id(&self.by_id_dir.join(link).to_string_lossy())),
+            None => Some(port),
+        }
+    }
+
+    /// Watches the directories again, retrying until it succeeds, and notifies the handlers.
+    fn restart(&mut self) -> File {
+        loop {
+            thread::sleep(RESTART_DELAY);
+            if let Ok(file) = self.listen() {
+                self.notify(&Event::MonitorRestarted(Metadata::now(None)));
+                return file;
+            }
+        }
+    }
+
+    fn notify(&mut self, event: &Event) {
+        for handler in self.handlers.iter_mut() {
+            handler.handle(event);
+        }
+    }
+}
+
+impl<'a> Monitor<'a> for InotifyMonitor<'a> {
+    fn subscribe(&mut self, handler: &'a mut dyn EventHandler) {
+        self.handlers.push(handler);
+    }
+
+    fn start(&mut self) -> io::Result<()> {
+        let mut file = self.listen()?;
+
+        loop {
+            if self.poll(&mut file).is_err() {
+                drop(file);
+                file = self.restart();
+            }
+        }
+    }
+}
+
+fn add_watch(fd: c_int, dir: &Path, mask: u32) -> io::Result<i32> {
+    let path = CString::new(dir.as_os_str().as_bytes()).map_err(|error| io::Error::other(error.to_string()))?;
+    // SAFETY: path is a valid NUL terminated string for the duration of the call.
+    let watch = unsafe { inotify_add_watch(fd, path.as_ptr(), mask) };
+    match watch < 0 {
+        true => Err(io::Error::last_os_error()),
+        false => Ok(watch),
+    }
+}
+
+/// Parses the events read from an inotify file, skipping a truncated event at the end.
+fn parse_events(buffer: &[u8]) -> Vec<InotifyEvent> {
+    let mut events = Vec::new();
+    let mut offset = 0;
+    while offset + EVENT_HEADER_SIZE <= buffer.len() {
+        let field = |index: usize| {
+            let start = offset + index * 4;
+            [buffer[start], buffer[start + 1], buffer[start + 2], buffer[start + 3]]
+        };
+        let length = u32::from_ne_bytes(field(3)) as usize;
+        let end = offset + EVENT_HEADER_SIZE + length;
+        if end > buffer.len() {
+            break;
+        }
+
+        let name = &buffer[offset + EVENT_HEADER_SIZE..end];
+        let name = name.split(|byte| *byte == 0).next().unwrap_or_default();
+        events.push(InotifyEvent {
+            watch: i32::from_ne_bytes(field(0)),
+            mask: u32::from_ne_bytes(field(1)),
+            name: String::from_utf8_lossy(name).to_string(),
+        });
+        offset = end;
+    }
+    events
+}
+
+/// Returns the port of the tty node at `path` from the USB device of its interface in sysfs, if it is a SA430.
+fn port_from_sysfs(sys_class_tty: &Path, path: &Path) -> Option<Port> {
+    let interface = fs::canonicalize(sys_class_tty.join(path.file_name()?).join("device")).ok()?;
+    let usb_device = interface.parent()?;
+    let attribute = |name: &str| fs::read_to_string(usb_device.join(name)).map(|value| value.trim().to_string());
+
+    if attribute("idVendor").ok()? != USB_VENDOR_ID || attribute("idProduct").ok()? != USB_PRODUCT_ID {
+        return None;
+    }
+    let port = Port::new(
+        &path.to_string_lossy(),
+        &attribute("serial").unwrap_or_default(),
+        &attribute("bcdDevice").unwrap_or_default(),
+    );
+    match usb_device.file_name() {
+        Some(usb_path) => Some(port.with_usb_path(&usb_path.to_string_lossy())),
+        None => Some(port),
+    }
+}
+
+/// Returns the port of the tty node at `path` from the name of its by-id link, like
+/// `usb-Texas_Instruments_SA430_08FF41E50F8B3A34-if00`, which has the serial number but not the firmware version.
+fn port_from_link(path: &Path, link: &str) -> Port {
+    let serial_number = link
+        .rsplit_once('_')
+        .map(|(_, rest)| rest.split('-').next().unwrap_or_default())
+        .unwrap_or_default();
+    Port::new(&path.to_string_lossy(), serial_number, "")
+}
+
+/// Returns the name of the node the link at `path` points to.
+fn link_target(path: &Path) -> Option<String> {
+    let target = fs::read_link(path).ok()?;
+    Some(target.file_name()?.to_string_lossy().to_string())
+}
+
+fn list_dir(dir: &Path) -> Vec<String> {
+    match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::os::unix::fs::symlink;
+
+    #[derive(Default)]
+    struct Recorder(Vec<Event>);
+
+    impl EventHandler for Recorder {
+        fn handle(&mut self, event: &Event) {
+            self.0.push(event.clone());
+        }
+    }
+
+    /// Creates a fake `/dev`, by-id and sysfs tree with a SA430 on `ttyACM0`, returning its root.
+    fn a_system(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("sa430-inotify-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let usb_device = root.join("devices").join("1-2");
+        fs::create_dir_all(usb_device.join("1-2:1.0")).unwrap();
+        fs::create_dir_all(root.join("tty").join("ttyACM0")).unwrap();
+        fs::create_dir_all(root.join("by-id")).unwrap();
+        fs::create_dir_all(root.join("dev")).unwrap();
+        for (attribute, value) in [
+            ("idVendor", "2047\n"),
+            ("idProduct", "0005\n"),
+            ("serial", "08FF41E50F8B3A34\n"),
+            ("bcdDevice", "0104\n"),
+        ] {
+            fs::write(usb_device.join(attribute), value).unwrap();
+        }
+        symlink(
+            usb_device.join("1-2:1.0"),
+            root.join("tty").join("ttyACM0").join("device"),
+        )
+        .unwrap();
+        root
+    }
+
+    fn event(watch: i32, mask: u32, name: &str) -> InotifyEvent {
+        InotifyEvent {
+            watch,
+            mask,
+            name: name.to_string(),
+        }
+    }
+
+    fn ports(recorder: &Recorder) -> Vec<(bool, Port)> {
+        recorder
+            .0
+            .iter()
+            .map(|event| match event {
+                Event::DeviceAdded(port, _) => (true, port.clone()),
+                Event::DeviceRemoved(port, _) => (false, port.clone()),
+                _ => panic!("Unexpected event: {:?}", event),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn given_sa430_nodes_when_created_and_deleted_then_notify_them() {
+        let root = a_system("nodes");
+        let mut recorder = Recorder::default();
+        let mut monitor = InotifyMonitor::with_paths(root.join("dev"), root.join("by-id"), root.join("tty"));
+        monitor.subscribe(&mut recorder);
+
+        monitor.process(&event(1, IN_CREATE, "ttyACM0"));
+        monitor.process(&event(1, IN_CREATE, "ttyACM1"));
+        monitor.process(&event(1, IN_CREATE, "null"));
+        monitor.process(&event(1, IN_DELETE, "ttyACM0"));
+        monitor.process(&event(1, IN_DELETE, "ttyACM1"));
+        drop(monitor);
+
+        let port =
+            Port::new(&root.join("dev/ttyACM0").to_string_lossy(), "08FF41E50F8B3A34", "0104").with_usb_path("1-2");
+        assert_eq!(ports(&recorder), [(true, port.clone()), (false, port)]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn given_a_watched_dir_when_a_node_is_created_then_read_its_event() {
+        let root = a_system("listen");
+        let mut recorder = Recorder::default();
+        let mut monitor = InotifyMonitor::with_paths(root.join("dev"), root.join("by-id"), root.join("tty"));
+        monitor.subscribe(&mut recorder);
+
+        let mut file = monitor.listen().unwrap();
+        fs::write(root.join("dev/ttyACM0"), "").unwrap();
+        monitor.poll(&mut file).unwrap();
+        drop(monitor);
+
+        assert_eq!(ports(&recorder)[0].1.serial_number(), "08FF41E50F8B3A34");
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn given_a_by_id_link_when_created_then_complete_the_port() {
+        let root = a_system("by-id");
+        let link = root.join("by-id/usb-Texas_Instruments_SA430_08FF41E50F8B3A34-if00");
+        let mut recorder = Recorder::default();
+        let mut monitor = InotifyMonitor::with_paths(root.join("dev"), root.join("by-id"), root.join("tty"));
+        monitor.by_id_watch = Some(2);
+        monitor.subscribe(&mut recorder);
+
+        monitor.process(&event(1, IN_CREATE, "ttyACM0"));
+        symlink("../dev/ttyACM0", &link).unwrap();
+        monitor.process(&event(
+            2,
+            IN_CREATE,
+            "usb-Texas_Instruments_SA430_08FF41E50F8B3A34-if00",
+        ));
+        monitor.process(&event(1, IN_DELETE, "ttyACM0"));
+        drop(monitor);
+
+        let events = ports(&recorder);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].1.by_id(), None);
+        assert_eq!(events[1].1.by_id(), Some(link.to_string_lossy().as_ref()));
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn given_a_by_id_link_without_sysfs_when_created_then_add_the_port_from_its_name() {
+        let root = a_system("no-sysfs");
+        fs::remove_dir_all(root.join("tty")).unwrap();
+        symlink(
+            "../dev/ttyACM3",
+            root.join("by-id/usb-Texas_Instruments_SA430_08FF41E50F8B3A34-if00"),
+        )
+        .unwrap();
+        let mut recorder = Recorder::default();
+        let mut monitor = InotifyMonitor::with_paths(root.join("dev"), root.join("by-id"), root.join("tty"));
+        monitor.by_id_watch = Some(2);
+        monitor.subscribe(&mut recorder);
+
+        monitor.process(&event(
+            2,
+            IN_CREATE,
+            "usb-Texas_Instruments_SA430_08FF41E50F8B3A34-if00",
+        ));
+        monitor.process(&event(2, IN_CREATE, "usb-Other_Device_1234-if00"));
+        drop(monitor);
+
+        let events = ports(&recorder);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].1.name(), root.join("dev/ttyACM3").to_string_lossy());
+        assert_eq!(events[0].1.serial_number(), "08FF41E50F8B3A34");
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn given_raw_events_when_parse_events_then_split_them_and_trim_the_names() {
+        let mut buffer = Vec::new();
+        for (watch, mask, name) in [
+            (1i32, IN_CREATE, &b"ttyACM0\0\0\0\0\0\0\0\0\0"[..]),
+            (2, IN_DELETE, b"x\0\0\0"),
+        ] {
+            buffer.extend_from_slice(&watch.to_ne_bytes());
+            buffer.extend_from_slice(&mask.to_ne_bytes());
+            buffer.extend_from_slice(&0u32.to_ne_bytes());
+            buffer.extend_from_slice(&(name.len() as u32).to_ne_bytes());
+            buffer.extend_from_slice(name);
+        }
+
+        assert_eq!(
+            parse_events(&buffer),
+            [event(1, IN_CREATE, "ttyACM0"), event(2, IN_DELETE, "x")]
+        );
+        assert_eq!(parse_events(&buffer[..40]), [event(1, IN_CREATE, "ttyACM0")]);
+    }
+}
@@ -1,4 +1,8 @@
+#[cfg(feature = "monitor")]
+pub(crate) mod inotify;
+#[cfg(feature = "monitor")]
 pub(crate) mod monitor;
+#[cfg(feature = "scanner")]
 pub(crate) mod scanner;
 
 mod udev_utils;
@@ -1,6 +1,11 @@
+use std::{io, thread, time::Duration};
+
 use super::udev_utils::*;
 use crate::monitor::*;
 
+/// Time to wait before each attempt to re-create the socket after an error.
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+
 pub struct LinuxMonitor<'a> {
     handlers: Vec<&'a mut dyn EventHandler>,
 }
@@ -10,24 +15,45 @@ impl LinuxMonitor<'_> {
         LinuxMonitor { handlers: Vec::new() }
     }
 
-    fn poll(&mut self, socket: &udev::MonitorSocket) {
+    /// Processes the pending events of the socket, returning the error that stopped the socket, if any.
+    fn poll(&mut self, socket: &udev::MonitorSocket) -> io::Result<()> {
         for event in socket.iter() {
             if is_sa430(&event.device()) {
                 self.process(event)
             }
         }
+
+        // The socket iterator ends when there are no more events or when the socket fails, like when the netlink buffer
+        // overflows, and only errno tells them apart.
+        let error = io::Error::last_os_error();
+        match is_socket_error(&error) {
+            true => Err(error),
+            false => Ok(()),
+        }
     }
 
     fn process(&mut self, event: udev::Event) {
         let port = port_from_event(&event);
+        let metadata = Metadata::now(Some(event.sequence_number()));
 
         match event.event_type() {
-            udev::EventType::Add => self.notify(&Event::DeviceAdded(port)),
-            udev::EventType::Remove => self.notify(&Event::DeviceRemoved(port)),
+            udev::EventType::Add => self.notify(&Event::DeviceAdded(port, metadata)),
+            udev::EventType::Remove => self.notify(&Event::DeviceRemoved(port, metadata)),
             _ => {}
         }
     }
 
+    /// Re-creates the socket, retrying until it succeeds, and notifies the handlers.
+    fn restart(&mut self) -> udev::MonitorSocket {
+        loop {
+            thread::sleep(RESTART_DELAY);
+            if let Ok(socket) = listen() {
+                self.notify(&Event::MonitorRestarted(Metadata::now(None)));
+                return socket;
+            }
+        }
+    }
+
     fn notify(&mut self, event: &Event) {
         for handler in self.handlers.iter_mut() {
             handler.handle(event);
@@ -40,11 +66,45 @@ impl<'a> Monitor<'a> for LinuxMonitor<'a> {
         self.handlers.push(handler);
     }
 
-    fn start(&mut self) -> std::io::Result<()> {
-        let socket = udev::MonitorBuilder::new()?.match_subsystem("tty")?.listen()?;
+    fn start(&mut self) -> io::Result<()> {
+        let mut socket = listen()?;
 
         loop {
-            self.poll(&socket);
+            if self.poll(&socket).is_err() {
+                drop(socket);
+                socket = self.restart();
+            }
         }
     }
 }
+
+fn listen() -> io::Result<udev::MonitorSocket> {
+    udev::MonitorBuilder::new()?.match_subsystem("tty")?.listen()
+}
+
+/// Returns whether `error`, read from errno after the socket iterator ended, means the socket failed, as opposed to
+/// having no more events.
+fn is_socket_error(error: &io::Error) -> bool {
+    match error.raw_os_error() {
+        None | Some(0) => false,
+        Some(_) => !matches!(error.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_no_more_events_when_is_socket_error_then_return_false() {
+        assert!(!is_socket_error(&io::Error::from(io::ErrorKind::WouldBlock)));
+        assert!(!is_socket_error(&io::Error::from_raw_os_error(0)));
+        assert!(!is_socket_error(&io::Error::from_raw_os_error(11)));
+    }
+
+    #[test]
+    fn given_a_buffer_overflow_when_is_socket_error_then_return_true() {
+        // ENOBUFS
+        assert!(is_socket_error(&io::Error::from_raw_os_error(105)));
+    }
+}
@@ -25,13 +25,12 @@ impl LinuxScanner {
 
 impl Scanner for LinuxScanner {
     fn scan(&self) -> Vec<Port> {
-        return self
-            .enumerator()
+        self.enumerator()
             .scan_devices()
             .expect("Failed to scan devices")
             .filter(is_sa430)
             .map(port_from_device)
-            .collect();
+            .collect()
     }
 }
 
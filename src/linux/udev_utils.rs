@@ -19,6 +19,7 @@ pub fn get_property<'a>(device: &'a udev::Device, key: &'a str) -> Option<&'a st
     device.property_value(key).and_then(|value| value.to_str())
 }
 
+#[cfg(feature = "monitor")]
 pub fn port_from_event(event: &udev::Event) -> Port {
     let device = event.device();
     port_from_device(device)
@@ -33,5 +34,25 @@ pub fn port_from_device(device: udev::Device) -> Port {
     let msg = format!("Failed to get device firmware version for {}", port);
     let firmware_version = get_property(&device, FIRMWARE_VERSION_PROPERTY_KEY).expect(&msg);
 
-    Port::new(port, serial_number, firmware_version)
+    let mut port = Port::new(port, serial_number, firmware_version);
+    if let Some(path) = find_by_id(&device) {
+        port = port.with_by_id(path);
+    }
+    match find_usb_path(&device) {
+        Some(path) => port.with_usb_path(&path),
+        None => port,
+    }
+}
+
+/// Returns the `/dev/serial/by-id` link of the device, if any.
+fn find_by_id(device: &udev::Device) -> Option<&str> {
+    get_property(device, DEVLINKS_PROPERTY_KEY)?
+        .split_whitespace()
+        .find(|link| link.starts_with(BY_ID_DIR))
+}
+
+/// Returns the USB topology path of the device, the name of its USB device parent, like `1-2.3`.
+fn find_usb_path(device: &udev::Device) -> Option<String> {
+    let usb_device = device.parent_with_subsystem_devtype("usb", "usb_device").ok()??;
+    usb_device.sysname().to_str().map(String::from)
 }
@@ -0,0 +1,174 @@
+//! # Location Module
+//!
+//! This module provides the `Location` type, used to tag recordings with the place where they were captured, for
+//! drive-test style coverage surveys.
+//!
+//! A location can be parsed from a `lat,lon` string or, with the `gpsd` feature, read from a running
+//! [gpsd](https://gpsd.io/) daemon.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use sa430::location::Location;
+//!
+//! let location: Location = "-27.5954,-48.5480".parse().unwrap();
+//! assert_eq!(location.to_string(), "-27.595400,-48.548000");
+//! ```
+
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+/// Errors when parsing a location.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocationError {
+    /// The location is not formatted as `lat,lon`.
+    InvalidFormat,
+
+    /// The latitude is not between -90 and 90 degrees, or the longitude between -180 and 180 degrees.
+    OutOfRange,
+}
+
+impl Error for LocationError {}
+
+impl Display for LocationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LocationError::InvalidFormat => write!(f, "Invalid location, expected: lat,lon"),
+            LocationError::OutOfRange => write!(f, "Invalid location, coordinates out of range"),
+        }
+    }
+}
+
+/// A geographic location, in decimal degrees (WGS84).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Location {
+    /// Latitude, positive to the north.
+    pub latitude: f64,
+
+    /// Longitude, positive to the east.
+    pub longitude: f64,
+}
+
+impl Location {
+    /// Creates a new location, checking the coordinates range.
+    pub fn new(latitude: f64, longitude: f64) -> Result<Self, LocationError> {
+        if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+            return Err(LocationError::OutOfRange);
+        }
+        Ok(Location { latitude, longitude })
+    }
+}
+
+impl FromStr for Location {
+    type Err = LocationError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (latitude, longitude) = value.split_once(',').ok_or(LocationError::InvalidFormat)?;
+        let latitude = latitude.trim().parse().map_err(|_| LocationError::InvalidFormat)?;
+        let longitude = longitude.trim().parse().map_err(|_| LocationError::InvalidFormat)?;
+        Location::new(latitude, longitude)
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.6},{:.6}", self.latitude, self.longitude)
+    }
+}
+
+#[cfg(feature = "gpsd")]
+pub mod gpsd {
+    //! # gpsd Module
+    //!
+    //! Reads the current location from a [gpsd](https://gpsd.io/) daemon, using its JSON protocol.
+
+    use std::{
+        error::Error,
+        io::{BufRead, BufReader, Write},
+        net::TcpStream,
+        time::Duration,
+    };
+
+    use super::Location;
+
+    /// Default gpsd address.
+    pub const DEFAULT_ADDRESS: &str = "127.0.0.1:2947";
+
+    /// Maximum number of reports read while waiting for a fix.
+    const MAX_REPORTS: usize = 64;
+
+    /// Timeout to wait for each report from gpsd.
+    const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Connects to gpsd at `address` and returns the location of the first report with a 2D or 3D fix.
+    pub fn read_location(address: &str) -> Result<Location, Box<dyn Error>> {
+        let mut stream = TcpStream::connect(address)?;
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        stream.write_all(b"?WATCH={\"enable\":true,\"json\":true}\n")?;
+
+        let reader = BufReader::new(stream);
+        for line in reader.lines().take(MAX_REPORTS) {
+            if let Some(location) = parse_tpv(&line?) {
+                return Ok(location);
+            }
+        }
+
+        Err("No GPS fix reported by gpsd".into())
+    }
+
+    /// Parses a gpsd report, returning the location if it is a TPV report with a 2D or 3D fix.
+    pub fn parse_tpv(report: &str) -> Option<Location> {
+        if !report.contains("\"class\":\"TPV\"") || number(report, "mode")? < 2.0 {
+            return None;
+        }
+        Location::new(number(report, "lat")?, number(report, "lon")?).ok()
+    }
+
+    /// Returns the number value of the top level `key` of a JSON report.
+    fn number(report: &str, key: &str) -> Option<f64> {
+        let pattern = format!("\"{}\":", key);
+        let start = report.find(&pattern)? + pattern.len();
+        let value = &report[start..];
+        let end = value.find([',', '}']).unwrap_or(value.len());
+        value[..end].trim().parse().ok()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn given_a_tpv_report_with_fix_when_parse_tpv_then_return_location() {
+            let report = r#"{"class":"TPV","device":"/dev/ttyACM0","mode":3,"lat":-27.5954,"lon":-48.548,"alt":12.5}"#;
+            assert_eq!(parse_tpv(report), Some(Location::new(-27.5954, -48.548).unwrap()));
+        }
+
+        #[test]
+        fn given_reports_without_fix_when_parse_tpv_then_return_none() {
+            assert_eq!(parse_tpv(r#"{"class":"TPV","mode":1}"#), None);
+            assert_eq!(parse_tpv(r#"{"class":"SKY","mode":3,"lat":1.0,"lon":2.0}"#), None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_a_location_string_when_parse_then_return_location() {
+        let location: Location = " 48.8584, 2.2945 ".parse().unwrap();
+        assert_eq!(location, Location::new(48.8584, 2.2945).unwrap());
+    }
+
+    #[test]
+    fn given_invalid_location_strings_when_parse_then_return_error() {
+        assert_eq!("48.8584".parse::<Location>(), Err(LocationError::InvalidFormat));
+        assert_eq!("north,2.29".parse::<Location>(), Err(LocationError::InvalidFormat));
+        assert_eq!("91.0,2.29".parse::<Location>(), Err(LocationError::OutOfRange));
+        assert_eq!("48.85,-180.5".parse::<Location>(), Err(LocationError::OutOfRange));
+    }
+}
@@ -0,0 +1,271 @@
+//! # Log Module
+//!
+//! This module provides a `Logger` writing diagnostics as JSON lines to a file, for field deployments and long
+//! captures where the terminal output is lost. Each line has the `timestamp` in Unix seconds, the `level` and the
+//! `message`.
+//!
+//! The file is rotated when it grows past a size or gets older than a period, by renaming it with a `.1` suffix, the
+//! previous `.1` to `.2` and so on, keeping a number of old files.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use sa430::log::{Level, Logger, Rotation};
+//!
+//! let path = std::env::temp_dir().join("sa430-log-example.log");
+//! let rotation = Rotation {
+//!     max_size: Some(1024 * 1024),
+//!     ..Default::default()
+//! };
+//!
+//! let logger = Logger::open(&path, rotation).unwrap();
+//! logger.log(Level::Warn, "Output removed: broken pipe").unwrap();
+//!
+//! let text = std::fs::read_to_string(&path).unwrap();
+//! assert!(text.ends_with(",\"level\":\"warn\",\"message\":\"Output removed: broken pipe\"}\n"));
+//! # std::fs::remove_file(&path).unwrap();
+//! ```
+
+use std::{
+    fmt::{self, Display},
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::formats::ndjson::escape;
+
+/// Default maximum size of the log file, in bytes.
+const DEFAULT_MAX_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Default number of rotated files kept.
+const DEFAULT_KEEP: usize = 5;
+
+/// Severity of a log message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Progress of the normal operation, like a command starting.
+    Info,
+
+    /// A problem the operation recovered from, like a dropped output.
+    Warn,
+
+    /// A failed operation.
+    Error,
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Level::Info => write!(f, "info"),
+            Level::Warn => write!(f, "warn"),
+            Level::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// When the log file is rotated and how many old files are kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rotation {
+    /// Size past which the file is rotated, in bytes, `None` to never rotate by size.
+    pub max_size: Option<u64>,
+
+    /// Age past which the file is rotated, from when it was opened, `None` to never rotate by age.
+    pub max_age: Option<Duration>,
+
+    /// Number of rotated files kept, the older ones are removed. With 0, the file is truncated on rotation.
+    pub keep: usize,
+}
+
+impl Default for Rotation {
+    fn default() -> Self {
+        Rotation {
+            max_size: Some(DEFAULT_MAX_SIZE),
+            max_age: None,
+            keep: DEFAULT_KEEP,
+        }
+    }
+}
+
+struct LogFile {
+    file: File,
+    size: u64,
+    opened: SystemTime,
+}
+
+/// Writes log messages as JSON lines to a rotated file.
+///
+/// The logger can be shared between threads, each line is written at once.
+pub struct Logger {
+    path: PathBuf,
+    rotation: Rotation,
+    file: Mutex<LogFile>,
+}
+
+impl Logger {
+    /// Opens the log file at `path`, appending to it if it exists.
+    pub fn open(path: &Path, rotation: Rotation) -> io::Result<Self> {
+        Ok(Logger {
+            path: path.to_path_buf(),
+            rotation,
+            file: Mutex::new(open_file(path)?),
+        })
+    }
+
+    /// Returns the path of the log file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes a message, rotating the file first if it is due.
+    pub fn log(&self, level: Level, message: &str) -> io::Result<()> {
+        self.log_at(SystemTime::now(), level, message)
+    }
+
+    fn log_at(&self, now: SystemTime, level: Level, message: &str) -> io::Result<()> {
+        let timestamp = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+        let line = format!(
+            "{{\"timestamp\":{:.3},\"level\":\"{}\",\"message\":\"{}\"}}\n",
+            timestamp,
+            level,
+            escape(message)
+        );
+
+        let mut file = self.file.lock().unwrap_or_else(PoisonError::into_inner);
+        let too_big = self
+            .rotation
+            .max_size
+            .is_some_and(|max_size| file.size > 0 && file.size + line.len() as u64 > max_size);
+        let too_old = self
+            .rotation
+            .max_age
+            .is_some_and(|max_age| now.duration_since(file.opened).is_ok_and(|age| age >= max_age));
+        if too_big || too_old {
+            *file = self.rotate(now)?;
+        }
+
+        file.file.write_all(line.as_bytes())?;
+        file.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Shifts the rotated files, moves the current one to `.1` and opens a new one.
+    fn rotate(&self, now: SystemTime) -> io::Result<LogFile> {
+        if self.rotation.keep == 0 {
+            File::create(&self.path)?;
+        } else {
+            for index in (1..self.rotation.keep).rev() {
+                let from = rotated_path(&self.path, index);
+                if from.exists() {
+                    fs::rename(&from, rotated_path(&self.path, index + 1))?;
+                }
+            }
+            fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        }
+
+        let mut file = open_file(&self.path)?;
+        file.opened = now;
+        Ok(file)
+    }
+}
+
+/// Returns the path of the `index`th rotated file, like `sa430.log.1`.
+pub fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+fn open_file(path: &Path) -> io::Result<LogFile> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let size = file.metadata()?.len();
+    Ok(LogFile {
+        file,
+        size,
+        opened: SystemTime::now(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn a_log_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sa430-log-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("sa430.log")
+    }
+
+    #[test]
+    fn given_a_message_when_log_then_write_a_json_line() {
+        let path = a_log_path("line");
+        let logger = Logger::open(&path, Rotation::default()).unwrap();
+
+        logger
+            .log_at(
+                UNIX_EPOCH + Duration::from_millis(1_700_000_000_250),
+                Level::Error,
+                "Bad \"port\"",
+            )
+            .unwrap();
+
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert_eq!(
+            text,
+            "{\"timestamp\":1700000000.250,\"level\":\"error\",\"message\":\"Bad \\\"port\\\"\"}\n"
+        );
+    }
+
+    #[test]
+    fn given_a_max_size_when_log_past_it_then_rotate_and_keep_the_last_files() {
+        let path = a_log_path("size");
+        let rotation = Rotation {
+            max_size: Some(100),
+            max_age: None,
+            keep: 2,
+        };
+        let logger = Logger::open(&path, rotation).unwrap();
+
+        for index in 0..4 {
+            logger
+                .log(Level::Info, &format!("Message {} {}", index, "x".repeat(40)))
+                .unwrap();
+        }
+
+        let current = fs::read_to_string(&path).unwrap();
+        let first = fs::read_to_string(rotated_path(&path, 1)).unwrap();
+        let second = fs::read_to_string(rotated_path(&path, 2)).unwrap();
+        let third = rotated_path(&path, 3).exists();
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert!(current.contains("Message 3"));
+        assert!(first.contains("Message 2"));
+        assert!(second.contains("Message 1"));
+        assert!(!third);
+    }
+
+    #[test]
+    fn given_a_max_age_when_log_after_it_then_rotate() {
+        let path = a_log_path("age");
+        let rotation = Rotation {
+            max_size: None,
+            max_age: Some(Duration::from_secs(3600)),
+            keep: 1,
+        };
+        let logger = Logger::open(&path, rotation).unwrap();
+        let now = SystemTime::now();
+
+        logger.log_at(now, Level::Info, "Before").unwrap();
+        logger
+            .log_at(now + Duration::from_secs(3600), Level::Info, "After")
+            .unwrap();
+
+        let current = fs::read_to_string(&path).unwrap();
+        let rotated = fs::read_to_string(rotated_path(&path, 1)).unwrap();
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert!(current.contains("After") && !current.contains("Before"));
+        assert!(rotated.contains("Before"));
+    }
+}
@@ -1,21 +1,90 @@
 mod cli;
 
-use clap::{Parser, Subcommand};
-use cli::capture::capture;
-use cli::capture::CaptureParams;
+use clap::{Args, Parser, Subcommand};
+use cli::capture::{capture, sweep};
+use cli::capture::{CaptureParams, OutputFormat, Retention};
 use cli::watch::PrinterEventHandler;
-use sa430::create_monitor;
+use sa430::{create_inotify_monitor, create_monitor};
 use std::error::Error;
+use std::io::BufReader;
+use std::num::{NonZeroU32, NonZeroU8, NonZeroUsize};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use std::os::fd::{FromRawFd, OwnedFd, RawFd};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+#[cfg(feature = "http")]
+use std::{net::TcpListener, sync::Arc};
 
+use cli::alert::AlertAction;
+#[cfg(feature = "http")]
+use cli::api::RestApi;
+use cli::bands::{list_bands, load_bands, resolve_range};
+use cli::batch::batch;
 use cli::blink::blink;
-use cli::info::info;
-use cli::reboot::reboot;
+use cli::cal::{
+    export_gains, linearity, load_gains, noise_floor, run_calibration, CalRunParams, GainsFormat, LinearityParams,
+};
+use cli::config::{
+    check_config, ConfigKind, FORMAT_VAR, LOG_FILE_VAR, MONITOR_VAR, PORT_VAR, PRESET_VAR, USB_FD_PREFIX,
+};
+use cli::crash::{is_protocol_failure, write_crash_report};
+use cli::decode::decode;
+use cli::doctor::{doctor, SystemPaths};
+use cli::dwell::{dwell, DwellParams};
+use cli::health::health;
+use cli::identify::{find_port, identify, print_location};
+use cli::info::{info, info_summary};
+use cli::lasterror::last_error;
+use cli::reboot::{reboot, serial_number_of, wait_for_device, DEFAULT_WAIT_TIMEOUT};
+use cli::report::html_report;
 use cli::scan::scan;
-use cli::watch::watch;
+use cli::session::{
+    list_sessions, parse_date, prune, repair_session, seal_session, verify_session, RetentionPolicy, SessionFilter,
+    BYTES_PER_MEGABYTE,
+};
+use cli::survey::{survey, SurveyParams};
+use cli::table::{Layout, Style};
+use cli::tuning::{apply_tuned_policy, FlashTuningCache};
+use cli::udev::setup_udev;
+use cli::watch::{watch, MonitorKind};
+#[cfg(feature = "net")]
+use cli::webhook::{Webhook, DEFAULT_WEBHOOK_TEMPLATE};
 
-use sa430::channel::SerialPortChannel;
+#[cfg(feature = "net")]
+use sa430::auth::Token;
+use sa430::cancel::CancellationToken;
+use sa430::channel::{Channel, RecoveryPolicy, SerialPortChannel};
+use sa430::compat::Feature;
 use sa430::create_scanner;
-use sa430::device::Sa430;
+use sa430::device::{FlashReadPolicy, Sa430, DEFAULT_TRANSCRIPT_SIZE};
+use sa430::dsp::{ChannelPlan, SurveyConfig, Trigger, TriggerCondition};
+use sa430::formats::csv::{parse_delimiter, DEFAULT_DELIMITER};
+use sa430::formats::html::HtmlSink;
+use sa430::formats::repair;
+use sa430::frame::ParseMode;
+use sa430::frequency::Frequency;
+#[cfg(all(feature = "http", feature = "tls"))]
+use sa430::http::serve_tls;
+#[cfg(feature = "http")]
+use sa430::http::{serve, Handler, Request, WarningHandler};
+use sa430::location::Location;
+use sa430::log::{Level, Logger, Rotation};
+use sa430::monitor::{Debouncer, DEFAULT_DEBOUNCE};
+use sa430::port::Port;
+use sa430::reference::{CorrectionProfile, ReferencePoint};
+#[cfg(feature = "tls")]
+use sa430::server::listen_tls;
+#[cfg(feature = "net")]
+use sa430::server::{listen, Arbitration};
+use sa430::session::SessionInfo;
+use sa430::sink::{parse_period, Aggregated, Aggregation, SinkRegistry, Throttled};
+use sa430::spectrum::BinAlignment;
+use sa430::storage::{LocalStorage, SyncedFile, DEFAULT_SYNC_INTERVAL};
+use sa430::timestamp::TimeZone;
+#[cfg(feature = "tls")]
+use sa430::tls::TlsAcceptor;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use sa430::usbfs::UsbfsChannel;
 
 #[derive(Parser)]
 #[command(version)]
@@ -23,6 +92,64 @@ use sa430::device::Sa430;
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+    #[arg(long, global = true)]
+    #[arg(help = "Disable colored output, also disabled by the NO_COLOR environment variable")]
+    no_color: bool,
+    #[arg(long, global = true, value_enum, default_value_t = Layout::Auto)]
+    #[arg(help = "Table layout, auto uses the narrow layout when the wide one doesn't fit the terminal")]
+    layout: Layout,
+    #[arg(long = "dump-frames", global = true)]
+    #[arg(help = "Print every frame sent (>) and received (<) to stderr, annotated")]
+    dump_frames: bool,
+    #[arg(long, global = true)]
+    #[arg(help = "Print the latency of each command to stderr at exit")]
+    profile: bool,
+    #[arg(long = "lenient-frames", global = true)]
+    #[arg(help = "Skip corrupt frames received from the device instead of failing the command")]
+    lenient_frames: bool,
+    #[arg(long = "flash-chunk", global = true)]
+    #[arg(help = "Maximum bytes per flash read request, from 1 to 255")]
+    flash_chunk: Option<NonZeroU8>,
+    #[arg(long = "flash-window", global = true)]
+    #[arg(help = "Number of flash read requests in flight")]
+    flash_window: Option<NonZeroUsize>,
+    #[arg(long = "tune-flash", global = true, conflicts_with_all = ["flash_chunk", "flash_window"])]
+    #[arg(help = "Benchmark the flash read settings once per device and reuse the fastest, cached by serial number")]
+    tune_flash: bool,
+    #[arg(long = "log-file", global = true, env = LOG_FILE_VAR)]
+    #[arg(help = "Write the diagnostics of the command as JSON lines to this file")]
+    log_file: Option<PathBuf>,
+    #[arg(long = "log-max-size", global = true, default_value_t = 10 * 1024 * 1024)]
+    #[arg(help = "Rotate the log file when it grows past this number of bytes, 0 to never rotate by size")]
+    log_max_size: u64,
+    #[arg(long = "log-max-age", global = true, value_parser = parse_period)]
+    #[arg(help = "Rotate the log file when it gets older than this period, like 1h or 1d")]
+    log_max_age: Option<Duration>,
+    #[arg(long = "log-keep", global = true, default_value_t = 5)]
+    #[arg(help = "Number of rotated log files kept")]
+    log_keep: usize,
+    #[arg(long = "transcript-size", global = true, default_value_t = DEFAULT_TRANSCRIPT_SIZE)]
+    #[arg(help = "Number of the last frames exchanged printed when the link with the device fails, 0 to disable")]
+    transcript_size: usize,
+    #[arg(long = "crash-report", global = true)]
+    #[arg(help = "Write a zip bundle with diagnostics to this directory when the link with the device fails")]
+    crash_report: Option<PathBuf>,
+    #[arg(long = "reopen-attempts", global = true, default_value_t = RecoveryPolicy::default().attempts)]
+    #[arg(help = "Times the serial port is reopened after an I/O error of the adapter, 0 to fail instead")]
+    reopen_attempts: u32,
+}
+
+/// Options applied to the devices opened by the commands.
+#[derive(Clone)]
+struct DeviceOptions {
+    dump_frames: bool,
+    profile: bool,
+    lenient_frames: bool,
+    flash_read: Option<FlashReadPolicy>,
+    tune_flash: bool,
+    transcript_size: usize,
+    crash_report: Option<PathBuf>,
+    recovery: RecoveryPolicy,
 }
 
 #[derive(Subcommand)]
@@ -33,109 +160,1294 @@ enum Commands {
 
     #[command(about = "Monitor for connected SA430 devices")]
     #[command(short_flag = 'w')]
-    Watch {},
+    Watch {
+        #[arg(long, default_value_t = DEFAULT_DEBOUNCE.as_millis() as u64)]
+        #[arg(help = "Time within which repeated events of a device are printed once, in ms, 0 to print all")]
+        debounce: u64,
+        #[arg(long, value_enum, env = MONITOR_VAR, default_value_t = MonitorKind::Udev)]
+        #[arg(help = "How to monitor the devices, inotify for containers without udev")]
+        monitor: MonitorKind,
+    },
+
+    #[command(about = "Check the permissions, udev rules and drivers needed to use the SA430 devices")]
+    Doctor {},
+
+    #[command(about = "Print or install the udev rule granting access to the SA430 devices")]
+    SetupUdev {
+        #[arg(long)]
+        #[arg(help = "Write the rule with sudo and reload the udev rules")]
+        install: bool,
+    },
 
     #[command(about = "Read device information")]
     #[command(short_flag = 'i')]
     Info {
-        #[arg(help = "The port to read device information from")]
-        port: String,
+        #[command(flatten)]
+        target: Target,
     },
 
     #[command(about = "Blink the LED on the device")]
     #[command(short_flag = 'b')]
     Blink {
-        #[arg(help = "Serial port to use")]
-        port: String,
+        #[command(flatten)]
+        target: Target,
+        #[arg(long, default_value_t = NonZeroU32::MIN)]
+        #[arg(help = "Number of times to blink, to identify a unit among many")]
+        count: NonZeroU32,
+        #[arg(long, default_value_t = 500)]
+        #[arg(help = "Time between blinks, in ms")]
+        interval: u64,
+    },
+
+    #[command(about = "Blink a device, found by serial number, until Enter is pressed and print where it is plugged")]
+    Identify {
+        #[arg(help = "Serial number of the device")]
+        serial_number: String,
+        #[arg(long, default_value_t = 500)]
+        #[arg(help = "Time between blinks, in ms")]
+        interval: u64,
     },
 
     #[command(about = "Performs a hardware reset on the device")]
     #[command(short_flag = 'r')]
     Reboot {
-        #[arg(help = "Serial port to use")]
+        #[command(flatten)]
+        target: Target,
+        #[arg(long = "no-wait")]
+        #[arg(help = "Return right after the reboot, without waiting for the device to come back")]
+        no_wait: bool,
+        #[arg(long = "wait-timeout", default_value_t = DEFAULT_WAIT_TIMEOUT.as_secs())]
+        #[arg(help = "Time to wait for the device to come back, in seconds")]
+        wait_timeout: u64,
+    },
+
+    #[command(about = "Read the last error stored by the device firmware")]
+    #[command(name = "lasterror")]
+    LastError {
+        #[arg(env = PORT_VAR, help = "Serial port to use")]
+        port: String,
+    },
+
+    #[command(about = "Print the health of the device as JSON, for monitoring")]
+    Health {
+        #[arg(env = PORT_VAR, help = "Serial port to use")]
         port: String,
     },
 
     #[command(about = "Capture a spectrum")]
     #[command(short_flag = 'c')]
-    Capture {
-        #[arg(help = "Serial port to use")]
+    Capture(Box<CaptureArgs>),
+
+    #[cfg(feature = "http")]
+    #[command(about = "Serve a REST API to list the devices and run captures")]
+    Api(ApiArgs),
+
+    #[command(about = "Build a report from the CSV traces of a capture session")]
+    Report {
+        #[arg(help = "Directory with the CSV traces, one per file")]
+        session_dir: PathBuf,
+        #[arg(long)]
+        #[arg(help = "Write a standalone HTML report, with interactive plots, to this file")]
+        html: PathBuf,
+    },
+
+    #[command(about = "Validate configuration files, like band presets, before deploying them")]
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+
+    #[command(about = "Seal and verify the recordings of a capture session")]
+    Session {
+        #[command(subcommand)]
+        command: SessionCommands,
+    },
+
+    #[command(about = "Measure a single frequency over time, like a zero span, and print its statistics")]
+    Dwell {
+        #[arg(env = PORT_VAR, help = "Serial port to use")]
         port: String,
         #[arg(long)]
-        #[arg(help = "The frequency to start capturing at, in MHz")]
-        fstart: f64,
+        #[arg(help = "The frequency to measure, in MHz")]
+        frequency: f64,
+        #[arg(long, default_value_t = 100)]
+        #[arg(help = "Number of samples to measure")]
+        samples: usize,
+        #[arg(long, default_value_t = -90.0, allow_negative_numbers = true)]
+        #[arg(help = "Power above which a sample is part of a burst, in dBm")]
+        threshold: f64,
+        #[arg(long = "rlevel")]
+        #[arg(help = "Maximum signal power before saturation, in dBm. Default is -35 dBm")]
+        ref_level: Option<i8>,
+        #[arg(long)]
+        #[arg(help = "Print the start and duration of each burst")]
+        bursts: bool,
+    },
+
+    #[command(about = "Find the active regions of a coarse sweep and sweep them again with a finer step")]
+    Survey {
+        #[arg(env = PORT_VAR, help = "Serial port to use")]
+        port: String,
         #[arg(long)]
-        #[arg(long, help = "The frequency to stop capturing at, in MHz")]
-        fstop: f64,
+        #[arg(help = "The frequency to start the coarse sweep at, in MHz. Default is the start of the device range")]
+        fstart: Option<f64>,
         #[arg(long)]
-        #[arg(long, help = "The frequency to step by, in MHz")]
-        fstep: f64,
+        #[arg(help = "The frequency to stop the coarse sweep at, in MHz. Default is the stop of the device range")]
+        fstop: Option<f64>,
+        #[arg(long = "coarse-step", default_value_t = 1.0)]
+        #[arg(help = "The frequency step of the coarse sweep, in MHz")]
+        coarse_step: f64,
+        #[arg(long = "fine-step", default_value_t = 0.025)]
+        #[arg(help = "The frequency step of the fine sweeps of the active regions, in MHz")]
+        fine_step: f64,
         #[arg(long = "rlevel")]
         #[arg(help = "Maximum signal power before saturation, in dBm. Default is -35 dBm")]
-        #[arg(long_help = "Must be one of -35,-40, -45, -50, -55, -60, -65 or -70 dBm")]
         ref_level: Option<i8>,
+        #[arg(long, default_value_t = -90.0, allow_negative_numbers = true)]
+        #[arg(help = "Power above which a bin of the coarse sweep is active, in dBm")]
+        threshold: f64,
+        #[arg(long, default_value_t = 0.2)]
+        #[arg(help = "Bandwidth added on each side of an active region, in MHz")]
+        margin: f64,
+        #[arg(long)]
+        #[arg(help = "Write the combined coarse and fine sweeps to this CSV file")]
+        output: Option<PathBuf>,
+    },
+
+    #[command(about = "Decode SA430 frames from captured bytes, like a logic analyzer dump")]
+    Decode {
+        #[arg(help = "Hex string, or file with a hex string or raw bytes")]
+        input: String,
+    },
+
+    #[command(about = "Frequency band presets")]
+    Bands {
+        #[command(subcommand)]
+        command: BandsCommands,
+    },
+
+    #[command(about = "Calibration data utilities")]
+    Cal {
+        #[command(subcommand)]
+        command: CalCommands,
+    },
+
+    #[cfg(feature = "simulator")]
+    #[command(about = "Run command cycles against the simulator with fault injection", hide = true)]
+    Soak {
+        #[arg(long, default_value_t = 10000)]
+        #[arg(help = "Number of command cycles")]
+        cycles: usize,
+        #[arg(long = "fault-rate", default_value_t = 0.01)]
+        #[arg(help = "Probability of a fault in each reply, from 0 to 1")]
+        fault_rate: f64,
+        #[arg(long, default_value_t = 1)]
+        #[arg(help = "Seed of the fault generator, printed with the results to reproduce a run")]
+        seed: u64,
+    },
+}
+
+/// Device, or devices with `--all`, a command runs on.
+#[derive(Args)]
+struct Target {
+    #[arg(env = PORT_VAR, required_unless_present = "all", help = "Serial port to use")]
+    port: Option<String>,
+    #[arg(
+        long,
+        help = "Run on every connected device and print a result table, failing if any device fails"
+    )]
+    all: bool,
+    #[arg(long, requires = "all", help = "Run on the devices in parallel")]
+    parallel: bool,
+}
+
+impl Target {
+    /// Returns the port of the single device to run on, `None` to run on all.
+    fn port(&self) -> Option<&str> {
+        match self.all {
+            true => None,
+            false => self.port.as_deref(),
+        }
+    }
+}
+
+#[cfg(feature = "http")]
+#[derive(Args)]
+struct ApiArgs {
+    #[arg(long, default_value = "127.0.0.1:8430")]
+    #[arg(help = "Address to listen at")]
+    listen: String,
+    #[arg(long, conflicts_with = "token_file")]
+    #[arg(help = "Token the clients must send as a Bearer authorization")]
+    token: Option<String>,
+    #[arg(long = "token-file")]
+    #[arg(help = "File with the token of --token, so it doesn't show up in the process list")]
+    token_file: Option<PathBuf>,
+    #[cfg(feature = "tls")]
+    #[arg(long = "tls-cert", requires = "tls_key")]
+    #[arg(help = "PEM certificate chain to serve the API over HTTPS with")]
+    tls_cert: Option<PathBuf>,
+    #[cfg(feature = "tls")]
+    #[arg(long = "tls-key", requires = "tls_cert")]
+    #[arg(help = "PEM private key of --tls-cert")]
+    tls_key: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct CaptureArgs {
+    #[arg(env = PORT_VAR, help = "Serial port to use")]
+    port: String,
+    #[arg(long)]
+    #[arg(help = "The frequency to start capturing at, in MHz")]
+    fstart: Option<f64>,
+    #[arg(long)]
+    #[arg(long, help = "The frequency to stop capturing at, in MHz")]
+    fstop: Option<f64>,
+    #[arg(long)]
+    #[arg(long, help = "The frequency to step by, in MHz")]
+    fstep: f64,
+    #[arg(long = "rlevel")]
+    #[arg(help = "Maximum signal power before saturation, in dBm. Default is -35 dBm")]
+    #[arg(long_help = "Must be one of -35,-40, -45, -50, -55, -60, -65 or -70 dBm")]
+    ref_level: Option<i8>,
+    #[arg(long = "gains-file")]
+    #[arg(help = "CSV file with gain tables to use instead of the device calibration")]
+    gains_file: Option<PathBuf>,
+    #[arg(long = "corrections")]
+    #[arg(help = "CSV correction profile written by `sa430 cal run`, added to the measured powers")]
+    corrections: Option<PathBuf>,
+    #[arg(long, env = PRESET_VAR)]
+    #[arg(help = "Band preset setting the start and stop frequencies, see `sa430 bands list`")]
+    band: Option<String>,
+    #[arg(long = "bands-file")]
+    #[arg(help = "CSV file with extra bands (name,fstart_mhz,fstop_mhz,description)")]
+    bands_file: Option<PathBuf>,
+    #[arg(long = "channel-plan")]
+    #[arg(help = "Report the power per channel of a channel plan: lora-eu868 or lora-us915")]
+    channel_plan: Option<String>,
+    #[arg(long, visible_alias = "sweeps")]
+    #[arg(help = "Number of sweeps to capture, 0 captures until interrupted [default: 1, or 0 with --duration]")]
+    count: Option<usize>,
+    #[arg(long, value_parser = parse_period)]
+    #[arg(help = "Stop the capture after this time, like 90s, 10m or 2h, even if fewer sweeps were captured")]
+    duration: Option<Duration>,
+    #[arg(long)]
+    #[arg(help = "Only output sweeps when the peak power in a band crosses a level, e.g. \"868.0-868.6 > -80dBm\"")]
+    trigger: Option<TriggerCondition>,
+    #[arg(long, value_enum)]
+    #[arg(help = "Alert when the trigger trips or the storage fills: beep, or notify (desktop, notify feature)")]
+    alert: Option<AlertAction>,
+    #[cfg(feature = "net")]
+    #[arg(long, requires = "trigger")]
+    #[arg(help = "Post a JSON payload to this http:// URL each time the trigger trips")]
+    webhook: Option<String>,
+    #[cfg(feature = "net")]
+    #[arg(long = "webhook-template", requires = "webhook")]
+    #[arg(help = "File with the JSON payload template of the webhook")]
+    #[arg(
+        long_help = "File with the JSON payload template of the webhook, with the placeholders {serial}, {band}, \
+                       {condition}, {peak_mhz}, {peak_dbm} and {timestamp}, in seconds since the Unix epoch"
+    )]
+    webhook_template: Option<PathBuf>,
+    #[arg(long)]
+    #[arg(help = "Write a summary of the session, with the top peaks, occupancy and trigger trips, to this file")]
+    report: Option<PathBuf>,
+    #[arg(long = "prune-sessions", requires = "keep_sessions")]
+    #[arg(help = "When the storage fills, remove the oldest sealed sessions in this directory")]
+    prune_sessions: Option<PathBuf>,
+    #[arg(long = "keep-sessions", requires = "prune_sessions")]
+    #[arg(help = "Number of most recent sessions --prune-sessions keeps")]
+    keep_sessions: Option<usize>,
+    #[arg(long, env = FORMAT_VAR, value_enum, default_value_t = OutputFormat::Csv)]
+    #[arg(help = "Format of the sweeps: csv, ndjson or binary, unused with --channel-plan")]
+    format: OutputFormat,
+    #[arg(long)]
+    #[arg(help = "Also write each sweep to this file, in the output format")]
+    tee: Option<PathBuf>,
+    #[arg(long = "tee-append", requires = "tee")]
+    #[arg(help = "Append to the --tee file, removing the truncated final record left by a power loss, if any")]
+    tee_append: bool,
+    #[arg(long = "tee-sync", requires = "tee", value_parser = parse_period)]
+    #[arg(help = "Flush the --tee file to the disk at most this often, like 10s or 1m [default: 10s]")]
+    tee_sync: Option<Duration>,
+    #[arg(long = "tee-rate", requires = "tee")]
+    #[arg(help = "Write at most this number of sweeps per second to the --tee file")]
+    tee_rate: Option<f64>,
+    #[arg(long = "tee-aggregate", requires = "tee")]
+    #[arg(help = "Write the max hold of each this number of sweeps to the --tee file")]
+    tee_aggregate: Option<NonZeroUsize>,
+    #[arg(long = "tee-window", requires = "tee", conflicts_with = "tee_aggregate", value_parser = parse_period)]
+    #[arg(help = "Write one sweep per period, like 1m or 1h, combining its sweeps, to the --tee file")]
+    tee_window: Option<Duration>,
+    #[arg(long = "tee-aggregation", requires = "tee", default_value_t = Aggregation::Max)]
+    #[arg(help = "How --tee-aggregate and --tee-window combine the sweeps: max or mean")]
+    tee_aggregation: Aggregation,
+    #[arg(long)]
+    #[arg(help = "Plot the sweeps in a standalone HTML file, written when the capture ends")]
+    plot: Option<PathBuf>,
+    #[cfg(feature = "net")]
+    #[arg(long)]
+    #[arg(help = "Stream the sweeps, as binary records, to the clients connecting to this address, e.g. 0.0.0.0:5430")]
+    listen: Option<String>,
+    #[cfg(feature = "net")]
+    #[arg(long, requires = "listen", conflicts_with = "token_file")]
+    #[arg(help = "Token the clients of --listen must send before receiving the sweeps")]
+    token: Option<String>,
+    #[cfg(feature = "net")]
+    #[arg(long = "token-file", requires = "listen")]
+    #[arg(help = "File with the token of --token, so it doesn't show up in the process list")]
+    token_file: Option<PathBuf>,
+    #[cfg(feature = "net")]
+    #[arg(long, requires = "listen")]
+    #[arg(help = "Stream to one client of --listen at a time, queueing the others until it disconnects")]
+    exclusive: bool,
+    #[cfg(feature = "tls")]
+    #[arg(long = "tls-cert", requires_all = ["listen", "tls_key"])]
+    #[arg(help = "PEM certificate chain to encrypt the connections of --listen with TLS")]
+    tls_cert: Option<PathBuf>,
+    #[cfg(feature = "tls")]
+    #[arg(long = "tls-key", requires = "tls_cert")]
+    #[arg(help = "PEM private key of --tls-cert")]
+    tls_key: Option<PathBuf>,
+    #[arg(long, default_value_t = 0, requires = "trigger")]
+    #[arg(help = "Number of sweeps before the trigger to also output")]
+    pretrigger: usize,
+    #[arg(long)]
+    #[arg(help = "Location of the capture, as latitude,longitude in decimal degrees")]
+    location: Option<Location>,
+    #[arg(long = "tag")]
+    #[arg(help = "Label of the capture, like site=roof, written with the sweeps and in the report, can be repeated")]
+    tags: Vec<String>,
+    #[arg(long)]
+    #[arg(help = "Note on the capture, like \"new antenna\", written with the sweeps and in the report")]
+    note: Option<String>,
+    #[arg(long, default_value_t = DEFAULT_DELIMITER, value_parser = parse_delimiter)]
+    #[arg(help = "Column delimiter of the spectrum points: ',', ';' or tab")]
+    delimiter: char,
+    #[arg(long, num_args = 0..=1, default_missing_value = "utc")]
+    #[arg(
+        help = "Add the ISO 8601 timestamp of each sweep to the CSV rows and NDJSON objects: utc (default) or local"
+    )]
+    timestamps: Option<TimeZone>,
+    #[arg(long = "pow2", conflicts_with = "channel_plan")]
+    #[arg(help = "Bring the spectrum to a power of two number of points, for FFT processing: pad or resample")]
+    #[arg(
+        long_help = "pad extends the grid past the stop frequency with the lowest measured power, resample keeps \
+                       the range with a smaller step. The resulting grid is printed as # comments before the points"
+    )]
+    alignment: Option<BinAlignment>,
+    #[cfg(feature = "gpsd")]
+    #[arg(long, num_args = 0..=1, default_missing_value = sa430::location::gpsd::DEFAULT_ADDRESS)]
+    #[arg(help = "Read the location of the capture from gpsd, at 127.0.0.1:2947 by default")]
+    gpsd: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum BandsCommands {
+    #[command(about = "List the band presets")]
+    List {
+        #[arg(long = "bands-file")]
+        #[arg(help = "CSV file with extra bands (name,fstart_mhz,fstop_mhz,description)")]
+        bands_file: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommands {
+    #[command(about = "Check a configuration file, printing every problem with its line and column")]
+    Check {
+        #[arg(value_enum, help = "Kind of configuration file")]
+        kind: ConfigKind,
+        #[arg(help = "Configuration file to check")]
+        file: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum SessionCommands {
+    #[command(about = "Write the metadata and SHA-256 manifest of the recordings in a session directory")]
+    Seal {
+        #[arg(help = "Directory with the recordings")]
+        dir: PathBuf,
+        #[arg(long)]
+        #[arg(help = "Serial number of the device that recorded the session")]
+        device: Option<String>,
+        #[arg(long)]
+        #[arg(help = "Band of the session, like a band preset name")]
+        band: Option<String>,
+        #[arg(long = "tag")]
+        #[arg(help = "Label of the session, like a route or site, can be repeated")]
+        tags: Vec<String>,
+        #[arg(long)]
+        #[arg(help = "Note on the session, like a change of the setup")]
+        note: Option<String>,
+    },
+    #[command(about = "List the sealed sessions in the subdirectories of a directory")]
+    List {
+        #[arg(help = "Directory with a subdirectory per session")]
+        dir: PathBuf,
+        #[arg(long)]
+        #[arg(help = "Only sessions recorded by the device with this serial number")]
+        device: Option<String>,
+        #[arg(long)]
+        #[arg(help = "Only sessions of this band")]
+        band: Option<String>,
+        #[arg(long = "tag")]
+        #[arg(help = "Only sessions with this tag, can be repeated to require all of them")]
+        tags: Vec<String>,
+        #[arg(long, value_parser = parse_date)]
+        #[arg(help = "Only sessions that ended on or after this UTC date, as YYYY-MM-DD")]
+        since: Option<SystemTime>,
+        #[arg(long, value_parser = parse_date)]
+        #[arg(help = "Only sessions that started on or before this UTC date, as YYYY-MM-DD")]
+        until: Option<SystemTime>,
+    },
+    #[command(about = "Check the recordings in a session directory against its manifest")]
+    Verify {
+        #[arg(help = "Directory with the recordings")]
+        dir: PathBuf,
+    },
+    #[command(about = "Remove the truncated final records left in the recordings of a session by a power loss")]
+    Repair {
+        #[arg(help = "Directory with the recordings")]
+        dir: PathBuf,
+    },
+    #[command(about = "Remove the oldest sealed sessions in the subdirectories of a directory past retention limits")]
+    Prune {
+        #[arg(help = "Directory with a subdirectory per session")]
+        dir: PathBuf,
+        #[arg(long = "max-size")]
+        #[arg(help = "Keep the most recent sessions totalling at most this size, in MB")]
+        max_size: Option<u64>,
+        #[arg(long = "max-age", value_parser = parse_period)]
+        #[arg(help = "Remove the sessions that ended longer ago than this period, like 7d")]
+        max_age: Option<Duration>,
+        #[arg(long = "max-sessions")]
+        #[arg(help = "Keep at most this number of the most recent sessions")]
+        max_sessions: Option<usize>,
+        #[arg(long = "dry-run")]
+        #[arg(help = "List the sessions that would be removed, without removing them")]
+        dry_run: bool,
     },
 }
 
+#[derive(Subcommand)]
+enum CalCommands {
+    #[command(about = "Export the calibration gain tables")]
+    ExportGains {
+        #[arg(env = PORT_VAR, help = "Serial port to use")]
+        port: String,
+        #[arg(long, value_enum, default_value_t = GainsFormat::Csv)]
+        #[arg(help = "Output file format")]
+        format: GainsFormat,
+        #[arg(long, default_value_t = DEFAULT_DELIMITER, value_parser = parse_delimiter)]
+        #[arg(help = "Column delimiter of the CSV format: ',', ';' or tab")]
+        delimiter: char,
+    },
+    #[command(about = "Estimate the noise floor of a band at each reference level")]
+    NoiseFloor(NoiseFloorArgs),
+    #[command(about = "Measure an external reference signal and write a host-side correction profile")]
+    Run(CalRunArgs),
+    #[command(about = "Check that the peak of a single-tone source stays within tolerance across the band")]
+    Linearity(LinearityArgs),
+}
+
+#[derive(Args)]
+struct LinearityArgs {
+    #[arg(env = PORT_VAR, help = "Serial port to use")]
+    port: String,
+    #[arg(long = "tone", required = true)]
+    #[arg(help = "Frequency the source is set to, like 868.1MHz, repeat for each frequency to check")]
+    tones: Vec<Frequency>,
+    #[arg(long, default_value_t = 0.2)]
+    #[arg(help = "Span measured around each tone, in MHz")]
+    span: f64,
+    #[arg(long, default_value_t = 0.005)]
+    #[arg(help = "The frequency to step by, in MHz")]
+    fstep: f64,
+    #[arg(long = "rlevel")]
+    #[arg(help = "Maximum signal power before saturation, in dBm. Default is -35 dBm")]
+    ref_level: Option<i8>,
+    #[arg(long, default_value_t = 10.0)]
+    #[arg(help = "Largest deviation of the peak from the tone that passes, in kHz")]
+    tolerance: f64,
+    #[arg(long = "no-prompt")]
+    #[arg(help = "Measure the tones without waiting for Enter, when the source is set by a script")]
+    no_prompt: bool,
+}
+
+#[derive(Args)]
+struct CalRunArgs {
+    #[arg(env = PORT_VAR, help = "Serial port to use")]
+    port: String,
+    #[arg(long = "point", required = true)]
+    #[arg(help = "Frequency and power of the reference, like 868.1MHz@-30, repeat for each point to measure")]
+    points: Vec<ReferencePoint>,
+    #[arg(long, default_value_t = 0.2)]
+    #[arg(help = "Span measured around each reference frequency, in MHz")]
+    span: f64,
+    #[arg(long, default_value_t = 0.01)]
+    #[arg(help = "The frequency to step by, in MHz")]
+    fstep: f64,
+    #[arg(long = "rlevel")]
+    #[arg(help = "Maximum signal power before saturation, in dBm. Default is -35 dBm")]
+    ref_level: Option<i8>,
+    #[arg(long, default_value_t = 4)]
+    #[arg(help = "Number of sweeps averaged at each point")]
+    sweeps: usize,
+    #[arg(long = "no-prompt")]
+    #[arg(help = "Measure the points without waiting for Enter, when the reference is set by a script")]
+    no_prompt: bool,
+    #[arg(short, long)]
+    #[arg(help = "File where the correction profile is written, default is the standard output")]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct NoiseFloorArgs {
+    #[arg(env = PORT_VAR, help = "Serial port to use")]
+    port: String,
+    #[arg(long)]
+    #[arg(help = "Start frequency of the band, in MHz")]
+    fstart: Option<f64>,
+    #[arg(long)]
+    #[arg(help = "Stop frequency of the band, in MHz")]
+    fstop: Option<f64>,
+    #[arg(long, env = PRESET_VAR)]
+    #[arg(help = "Band preset setting the start and stop frequencies, see `sa430 bands list`")]
+    band: Option<String>,
+    #[arg(long = "bands-file")]
+    #[arg(help = "CSV file with extra bands (name,fstart_mhz,fstop_mhz,description)")]
+    bands_file: Option<PathBuf>,
+    #[arg(long, default_value_t = 100.0)]
+    #[arg(help = "Resolution bandwidth, in kHz")]
+    rbw: f64,
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
     let cli = Cli::parse();
+    let style = Style::from_env(cli.no_color, cli.layout);
+    if let Some(path) = &cli.log_file {
+        let rotation = Rotation {
+            max_size: (cli.log_max_size > 0).then_some(cli.log_max_size),
+            max_age: cli.log_max_age,
+            keep: cli.log_keep,
+        };
+        cli::log::init(Logger::open(path, rotation)?);
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        cli::log::log(Level::Info, &format!("Running sa430 {}", args.join(" ")));
+    }
+    let options = DeviceOptions {
+        dump_frames: cli.dump_frames,
+        profile: cli.profile,
+        lenient_frames: cli.lenient_frames,
+        flash_read: match (cli.flash_chunk, cli.flash_window) {
+            (None, None) => None,
+            (chunk_size, window) => Some(FlashReadPolicy {
+                chunk_size: chunk_size.unwrap_or(FlashReadPolicy::default().chunk_size),
+                window: window.unwrap_or(FlashReadPolicy::default().window),
+            }),
+        },
+        tune_flash: cli.tune_flash,
+        transcript_size: cli.transcript_size,
+        crash_report: cli.crash_report,
+        recovery: RecoveryPolicy {
+            attempts: cli.reopen_attempts,
+            ..RecoveryPolicy::default()
+        },
+    };
 
-    match cli.command {
-        Some(Commands::Scan {}) => exec_scan(),
-        Some(Commands::Watch {}) => exec_watch(),
-        Some(Commands::Info { port }) => exec_info(&port),
-        Some(Commands::Blink { port }) => exec_blink(&port),
-        Some(Commands::Reboot { port }) => exec_reboot(&port),
-        Some(Commands::Capture {
+    let result = match cli.command {
+        Some(Commands::Scan {}) => exec_scan(&style),
+        Some(Commands::Watch { debounce, monitor }) => exec_watch(Duration::from_millis(debounce), monitor),
+        Some(Commands::Doctor {}) => exec_doctor(&style),
+        Some(Commands::SetupUdev { install }) => setup_udev(install, &mut std::io::stdout()),
+        Some(Commands::Info { target }) => exec_info(&target, &options, &style),
+        Some(Commands::Identify {
+            serial_number,
+            interval,
+        }) => exec_identify(&serial_number, Duration::from_millis(interval), &options),
+        Some(Commands::Blink {
+            target,
+            count,
+            interval,
+        }) => exec_blink(&target, count, Duration::from_millis(interval), &options, &style),
+        Some(Commands::Reboot {
+            target,
+            no_wait,
+            wait_timeout,
+        }) => {
+            let timeout = match no_wait {
+                true => None,
+                false => Some(Duration::from_secs(wait_timeout)),
+            };
+            exec_reboot(&target, timeout, &options, &style)
+        }
+        Some(Commands::LastError { port }) => exec_last_error(&port, &options),
+        Some(Commands::Health { port }) => exec_health(&port, &options),
+        Some(Commands::Capture(args)) => exec_capture(*args, style, &options),
+        #[cfg(feature = "http")]
+        Some(Commands::Api(args)) => exec_api(args, &options),
+        Some(Commands::Report { session_dir, html }) => exec_report(&session_dir, &html),
+        Some(Commands::Session {
+            command:
+                SessionCommands::Seal {
+                    dir,
+                    device,
+                    band,
+                    tags,
+                    note,
+                },
+        }) => {
+            let info = SessionInfo {
+                device,
+                band,
+                tags,
+                note,
+                ..Default::default()
+            };
+            seal_session(&LocalStorage::new(dir), &info, &mut std::io::stdout())
+        }
+        Some(Commands::Session {
+            command:
+                SessionCommands::List {
+                    dir,
+                    device,
+                    band,
+                    tags,
+                    since,
+                    until,
+                },
+        }) => {
+            let filter = SessionFilter {
+                device,
+                band,
+                tags,
+                since,
+                // Include the whole day.
+                until: until.map(|until| until + Duration::from_secs(24 * 60 * 60)),
+            };
+            list_sessions(&dir, &filter, &style, &mut std::io::stdout())
+        }
+        Some(Commands::Session {
+            command: SessionCommands::Verify { dir },
+        }) => verify_session(&LocalStorage::new(dir), &style, &mut std::io::stdout()),
+        Some(Commands::Session {
+            command: SessionCommands::Repair { dir },
+        }) => repair_session(&dir, &style, &mut std::io::stdout()),
+        Some(Commands::Session {
+            command:
+                SessionCommands::Prune {
+                    dir,
+                    max_size,
+                    max_age,
+                    max_sessions,
+                    dry_run,
+                },
+        }) => {
+            let policy = RetentionPolicy {
+                max_size: max_size.map(|max_size| max_size * BYTES_PER_MEGABYTE),
+                max_age,
+                max_sessions,
+            };
+            prune(&dir, &policy, dry_run, &style, &mut std::io::stdout())
+        }
+        Some(Commands::Dwell {
+            port,
+            frequency,
+            samples,
+            threshold,
+            ref_level,
+            bursts,
+        }) => {
+            let params = DwellParams {
+                frequency: Frequency::from_mhz(frequency),
+                samples,
+                threshold,
+                ref_level,
+                list_bursts: bursts,
+                style,
+            };
+            with_device(&port, &options, Feature::Capture, |device| {
+                dwell(device, &params, &mut std::io::stdout())
+            })
+        }
+        Some(Commands::Survey {
             port,
             fstart,
             fstop,
-            fstep,
+            coarse_step,
+            fine_step,
             ref_level,
-        }) => exec_capture(&port, fstart, fstop, fstep, ref_level),
+            threshold,
+            margin,
+            output,
+        }) => {
+            let params = SurveyParams {
+                fstart: fstart.map(Frequency::from_mhz),
+                fstop: fstop.map(Frequency::from_mhz),
+                coarse_step: Frequency::from_mhz(coarse_step),
+                fine_step: Frequency::from_mhz(fine_step),
+                ref_level,
+                config: SurveyConfig {
+                    threshold,
+                    margin: margin * 1e6,
+                    ..Default::default()
+                },
+                output,
+                style,
+            };
+            with_device(&port, &options, Feature::Capture, |device| {
+                survey(device, &params, &mut std::io::stdout())
+            })
+        }
+        Some(Commands::Config {
+            command: ConfigCommands::Check { kind, file },
+        }) => check_config(kind, &file, &mut std::io::stdout()),
+        Some(Commands::Decode { input }) => exec_decode(&input),
+        Some(Commands::Bands {
+            command: BandsCommands::List { bands_file },
+        }) => exec_list_bands(bands_file, &style),
+        Some(Commands::Cal {
+            command:
+                CalCommands::ExportGains {
+                    port,
+                    format,
+                    delimiter,
+                },
+        }) => exec_export_gains(&port, &options, format, delimiter),
+        Some(Commands::Cal {
+            command: CalCommands::NoiseFloor(args),
+        }) => exec_noise_floor(args, &style, &options),
+        Some(Commands::Cal {
+            command: CalCommands::Run(args),
+        }) => exec_cal_run(args, &options),
+        Some(Commands::Cal {
+            command: CalCommands::Linearity(args),
+        }) => exec_linearity(args, &style, &options),
+        #[cfg(feature = "simulator")]
+        Some(Commands::Soak {
+            cycles,
+            fault_rate,
+            seed,
+        }) => exec_soak(cli::soak::SoakParams {
+            cycles,
+            fault_rate,
+            seed,
+        }),
         None => panic!("No command provided, use --help for usage"),
+    };
+    match &result {
+        Ok(()) => cli::log::log(Level::Info, "Command finished"),
+        Err(error) => cli::log::log(Level::Error, &format!("Command failed: {}", error)),
     }
+    result
 }
 
-fn exec_scan() -> Result<(), Box<dyn Error>> {
-    scan(create_scanner(), &mut std::io::stdout())?;
+fn exec_scan(style: &Style) -> Result<(), Box<dyn Error>> {
+    scan(create_scanner(), style, &mut std::io::stdout())?;
     Ok(())
 }
 
-fn exec_watch() -> Result<(), Box<dyn Error>> {
+fn exec_watch(debounce: Duration, kind: MonitorKind) -> Result<(), Box<dyn Error>> {
     let mut output = std::io::stdout();
-    let mut handler = PrinterEventHandler::new(&mut output);
-    let mut monitor = create_monitor();
+    let mut printer = PrinterEventHandler::new(&mut output);
+    let mut handler = Debouncer::new(&mut printer, debounce);
+    let mut monitor = match kind {
+        MonitorKind::Udev => create_monitor(),
+        MonitorKind::Inotify => create_inotify_monitor(),
+    };
     watch(&mut *monitor, &mut handler)?;
     Ok(())
 }
 
-fn exec_info(port: &str) -> Result<(), Box<dyn Error>> {
-    let channel = SerialPortChannel::new(port)?;
-    let mut device = Sa430::new(Box::new(channel));
-    info(&mut device, &mut std::io::stdout())
+/// Opens the device at `port` and runs `command` with it, printing the profile report to stderr afterwards, even if
+/// the command fails. If the link with the device failed, prints the last frames exchanged and writes a crash report,
+/// if enabled.
+fn with_device<F>(port: &str, options: &DeviceOptions, feature: Feature, command: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnOnce(&mut Sa430) -> Result<(), Box<dyn Error>>,
+{
+    let mut device = open_device(port, options, feature)?;
+    let result = command(&mut device);
+    if let Some(report) = device.profile_report() {
+        eprint!("{}", report);
+    }
+    let Err(error) = &result else {
+        return result;
+    };
+    if !is_protocol_failure(error.as_ref()) {
+        return result;
+    }
+
+    if !options.dump_frames && !device.recent_frames().is_empty() {
+        eprintln!("Last frames exchanged:");
+        for record in device.recent_frames() {
+            eprintln!("{}", record);
+        }
+    }
+    if let Some(dir) = &options.crash_report {
+        match write_crash_report(&mut device, port, error.as_ref(), &SystemPaths::default(), dir) {
+            Ok(path) => eprintln!("Crash report written to {}", path.display()),
+            Err(report_error) => eprintln!("Unable to write the crash report: {}", report_error),
+        }
+    }
+    result
+}
+
+/// Opens the channel of `port`, a serial port or a usbfs file descriptor inherited from the caller, like the one passed
+/// by `termux-usb` on Android. Serial ports are reopened after an I/O error as set by `recovery`, with a warning.
+fn open_channel(port: &str, recovery: RecoveryPolicy) -> Result<Box<dyn Channel>, Box<dyn Error>> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    if let Some(fd) = port.strip_prefix(USB_FD_PREFIX) {
+        let fd = fd
+            .parse::<RawFd>()
+            .ok()
+            .filter(|fd| *fd >= 0)
+            .ok_or_else(|| format!("Invalid file descriptor: {}", fd))?;
+        // SAFETY: the caller passes the file descriptor for this process to use, and nothing else in it owns it.
+        return Ok(Box::new(UsbfsChannel::new(unsafe { OwnedFd::from_raw_fd(fd) })?));
+    }
+    let mut channel = SerialPortChannel::new(port)?;
+    channel.set_recovery_policy(recovery);
+    channel.set_warning_handler(Some(Box::new(|message: &str| cli::log::report(Level::Warn, message))));
+    Ok(Box::new(channel))
+}
+
+/// Opens the device at `port` with the options, checking that its firmware supports `feature`.
+fn open_device(port: &str, options: &DeviceOptions, feature: Feature) -> Result<Sa430, Box<dyn Error>> {
+    let mut device = Sa430::new(open_channel(port, options.recovery)?);
+    if options.dump_frames {
+        device.set_frame_dump(Some(Box::new(std::io::stderr())));
+    }
+    device.set_profiling(options.profile);
+    device.set_transcript_size(options.transcript_size);
+    if options.lenient_frames {
+        device.set_parse_mode(ParseMode::Lenient);
+    }
+    if let Some(policy) = options.flash_read {
+        device.set_flash_read_policy(policy);
+    }
+    if options.tune_flash {
+        let path = FlashTuningCache::default_path().ok_or("Unable to find the cache directory")?;
+        apply_tuned_policy(&mut device, &path)?;
+    }
+    device.check_compat(feature)?;
+    if feature == Feature::Capture {
+        device.select_engine()?;
+    }
+    Ok(device)
+}
+
+/// Returns the token given on the command line or read from a file, if any.
+#[cfg(feature = "net")]
+fn load_token(token: Option<String>, token_file: Option<PathBuf>) -> Result<Option<Token>, Box<dyn Error>> {
+    match (token, token_file) {
+        (Some(token), _) => Ok(Some(Token::new(&token)?)),
+        (None, Some(path)) => Ok(Some(Token::from_file(&path)?)),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Returns the TLS certificate and key given on the command line, if any.
+#[cfg(feature = "tls")]
+fn load_tls(certificates: Option<PathBuf>, key: Option<PathBuf>) -> Result<Option<TlsAcceptor>, Box<dyn Error>> {
+    match (certificates, key) {
+        (Some(certificates), Some(key)) => Ok(Some(TlsAcceptor::from_pem_files(&certificates, &key)?)),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(feature = "http")]
+fn exec_api(args: ApiArgs, options: &DeviceOptions) -> Result<(), Box<dyn Error>> {
+    let token = load_token(args.token, args.token_file)?;
+    #[cfg(feature = "tls")]
+    let acceptor = load_tls(args.tls_cert, args.tls_key)?;
+    let options = options.clone();
+    let api = Arc::new(RestApi::new(
+        Box::new(|| create_scanner().scan()),
+        Arc::new(move |port: &str| open_device(port, &options, Feature::Capture)),
+        token,
+    ));
+    let handler: Handler = Arc::new(move |request: &Request| api.handle(request));
+    let warnings: Option<WarningHandler> = Some(Box::new(|warning: &str| cli::log::report(Level::Warn, warning)));
+
+    let listener = TcpListener::bind(&args.listen)?;
+    #[cfg(feature = "tls")]
+    if let Some(acceptor) = acceptor {
+        cli::log::report(
+            Level::Info,
+            &format!("Serving the REST API at https://{}", listener.local_addr()?),
+        );
+        serve_tls(listener, acceptor, handler, warnings);
+        return Ok(());
+    }
+    cli::log::report(
+        Level::Info,
+        &format!("Serving the REST API at http://{}", listener.local_addr()?),
+    );
+    serve(listener, handler, warnings);
+    Ok(())
+}
+
+fn exec_doctor(style: &Style) -> Result<(), Box<dyn Error>> {
+    let ports = create_scanner().scan();
+    doctor(&ports, &SystemPaths::default(), style, &mut std::io::stdout())
+}
+
+fn exec_info(target: &Target, options: &DeviceOptions, style: &Style) -> Result<(), Box<dyn Error>> {
+    match target.port() {
+        Some(port) => with_device(port, options, Feature::Identification, |device| {
+            info(device, style, &mut std::io::stdout())
+        }),
+        None => batch(
+            &*create_scanner(),
+            target.parallel,
+            style,
+            &mut std::io::stdout(),
+            |port| info_summary(&mut open_device(port.path(), options, Feature::Identification)?),
+        ),
+    }
+}
+
+fn exec_blink(
+    target: &Target,
+    count: NonZeroU32,
+    interval: Duration,
+    options: &DeviceOptions,
+    style: &Style,
+) -> Result<(), Box<dyn Error>> {
+    match target.port() {
+        Some(port) => with_device(port, options, Feature::Control, |device| {
+            blink(device, count, interval, &mut std::io::stdout())
+        }),
+        None => batch(
+            &*create_scanner(),
+            target.parallel,
+            style,
+            &mut std::io::stdout(),
+            |port| {
+                let mut device = open_device(port.path(), options, Feature::Control)?;
+                device.blink_repeat(count, interval)?;
+                Ok("blinked".to_string())
+            },
+        ),
+    }
+}
+
+fn exec_identify(serial_number: &str, interval: Duration, options: &DeviceOptions) -> Result<(), Box<dyn Error>> {
+    let port = find_port(&*create_scanner(), serial_number)?;
+    print_location(&port, &mut std::io::stdout())?;
+
+    let stop = CancellationToken::new();
+    let enter = stop.clone();
+    std::thread::spawn(move || {
+        let _ = std::io::stdin().read_line(&mut String::new());
+        enter.cancel();
+    });
+    with_device(port.path(), options, Feature::Control, |device| {
+        identify(device, interval, &stop, &mut std::io::stdout())
+    })
+}
+
+/// Reboots the target devices and, with a `timeout`, waits for them to come back and answer.
+fn exec_reboot(
+    target: &Target,
+    timeout: Option<Duration>,
+    options: &DeviceOptions,
+    style: &Style,
+) -> Result<(), Box<dyn Error>> {
+    let handshake = |port: &Port| open_device(port.path(), options, Feature::Control).map(drop);
+    let Some(port) = target.port() else {
+        return batch(
+            &*create_scanner(),
+            target.parallel,
+            style,
+            &mut std::io::stdout(),
+            |port| {
+                open_device(port.path(), options, Feature::Control)?.reboot()?;
+                match timeout {
+                    Some(timeout) => {
+                        let downtime = wait_for_device(
+                            &*create_scanner(),
+                            port.serial_number(),
+                            timeout,
+                            handshake,
+                            &mut std::io::sink(),
+                        )?;
+                        Ok(format!("back after {:.1} s", downtime.as_secs_f64()))
+                    }
+                    None => Ok("rebooted".to_string()),
+                }
+            },
+        );
+    };
+
+    let scanner = create_scanner();
+    let wait = match timeout {
+        Some(timeout) => Some((serial_number_of(&*scanner, port)?, timeout)),
+        None => None,
+    };
+
+    with_device(port, options, Feature::Control, |device| {
+        reboot(device, &mut std::io::stdout())
+    })?;
+
+    match wait {
+        Some((serial_number, timeout)) => {
+            wait_for_device(&*scanner, &serial_number, timeout, handshake, &mut std::io::stdout()).map(drop)
+        }
+        None => Ok(()),
+    }
 }
 
-fn exec_blink(port: &str) -> Result<(), Box<dyn Error>> {
-    let channel = SerialPortChannel::new(port)?;
-    let mut device = Sa430::new(Box::new(channel));
-    blink(&mut device, &mut std::io::stdout())
+fn exec_last_error(port: &str, options: &DeviceOptions) -> Result<(), Box<dyn Error>> {
+    // No other command may be sent before, since it could replace the stored error.
+    with_device(port, options, Feature::Identification, |device| {
+        last_error(device, &mut std::io::stdout())
+    })
 }
 
-fn exec_reboot(port: &str) -> Result<(), Box<dyn Error>> {
-    let channel = SerialPortChannel::new(port)?;
-    let mut device = Sa430::new(Box::new(channel));
-    reboot(&mut device, &mut std::io::stdout())
+fn exec_health(port: &str, options: &DeviceOptions) -> Result<(), Box<dyn Error>> {
+    with_device(port, options, Feature::Production, |device| {
+        health(device, &mut std::io::stdout())
+    })
 }
 
-fn exec_capture(port: &str, fstart: f64, fstop: f64, fstep: f64, ref_level: Option<i8>) -> Result<(), Box<dyn Error>> {
-    let channel = SerialPortChannel::new(port)?;
-    let mut device = Sa430::new(Box::new(channel));
-    let params = CaptureParams {
+fn exec_capture(args: CaptureArgs, style: Style, options: &DeviceOptions) -> Result<(), Box<dyn Error>> {
+    let bands = load_bands(args.bands_file.as_deref())?;
+    let (fstart, fstop) = resolve_range(&bands, args.band.as_deref(), args.fstart, args.fstop)?;
+    let channel_plan = match args.channel_plan {
+        Some(name) => Some(ChannelPlan::builtin(&name).ok_or(format!("Unknown channel plan: {}", name))?),
+        None => None,
+    };
+    let mut params = CaptureParams {
         fstart,
         fstop,
-        fstep,
-        ref_level,
+        fstep: Frequency::from_mhz(args.fstep),
+        ref_level: args.ref_level,
+        corrections: match &args.corrections {
+            Some(path) => Some(CorrectionProfile::read_csv(BufReader::new(std::fs::File::open(path)?))?),
+            None => None,
+        },
+        channel_plan,
+        count: args.count.unwrap_or(match args.duration {
+            Some(_) => 0,
+            None => 1,
+        }),
+        duration: args.duration,
+        trigger: args.trigger.map(|condition| Trigger::new(condition, args.pretrigger)),
+        location: args.location,
+        tags: args.tags,
+        note: args.note,
+        cancel: CancellationToken::new(),
+        style,
+        format: args.format,
+        delimiter: args.delimiter,
+        timestamps: args.timestamps,
+        alignment: args.alignment,
+        alert: args.alert,
+        report: args.report,
+        retention: args
+            .prune_sessions
+            .zip(args.keep_sessions)
+            .map(|(root, keep)| Retention {
+                root,
+                policy: RetentionPolicy {
+                    max_sessions: Some(keep),
+                    ..Default::default()
+                },
+            }),
+        sinks: SinkRegistry::new(),
+        #[cfg(feature = "net")]
+        webhook: match args.webhook {
+            Some(url) => {
+                let template = match args.webhook_template {
+                    Some(path) => std::fs::read_to_string(path)?,
+                    None => DEFAULT_WEBHOOK_TEMPLATE.to_string(),
+                };
+                Some(Webhook::new(&url, &template)?)
+            }
+            None => None,
+        },
+    };
+    if let Some(path) = args.tee {
+        let file = match args.tee_append {
+            true => open_appended(&path)?,
+            false => std::fs::File::create(path)?,
+        };
+        let file = SyncedFile::new(file, args.tee_sync.unwrap_or(DEFAULT_SYNC_INTERVAL));
+        let mut sink = args.format.sink(file, args.delimiter, args.timestamps)?;
+        if let Some(count) = args.tee_aggregate {
+            sink = Box::new(Aggregated::new(sink, count, args.tee_aggregation));
+        }
+        if let Some(period) = args.tee_window {
+            sink = Box::new(Aggregated::per_period(sink, period, args.tee_aggregation));
+        }
+        if let Some(rate) = args.tee_rate {
+            if !(rate.is_finite() && rate > 0.0) {
+                return Err(format!(
+                    "Invalid --tee-rate {}, expected a positive number of sweeps per second",
+                    rate
+                )
+                .into());
+            }
+            sink = Box::new(Throttled::new(sink, rate));
+        }
+        params.sinks.add(sink);
+    }
+    if let Some(path) = args.plot {
+        let file = std::fs::File::create(path)?;
+        params.sinks.add(Box::new(HtmlSink::new(file, "SA430 capture")));
+    }
+    #[cfg(feature = "net")]
+    if let Some(address) = args.listen {
+        let token = load_token(args.token, args.token_file)?;
+        let arbitration = match args.exclusive {
+            true => Arbitration::Exclusive,
+            false => Arbitration::Shared,
+        };
+        #[cfg(feature = "tls")]
+        let address = match load_tls(args.tls_cert, args.tls_key)? {
+            Some(acceptor) => listen_tls(&address, acceptor, token, arbitration, params.sinks.clone())?,
+            None => listen(&address, token, arbitration, params.sinks.clone())?,
+        };
+        #[cfg(not(feature = "tls"))]
+        let address = listen(&address, token, arbitration, params.sinks.clone())?;
+        cli::log::report(
+            Level::Info,
+            &format!("Streaming the sweeps to the clients of {}", address),
+        );
+    }
+    #[cfg(feature = "gpsd")]
+    if let Some(address) = args.gpsd {
+        params.location = Some(sa430::location::gpsd::read_location(&address)?);
+    }
+
+    with_device(&args.port, options, Feature::Capture, |device| {
+        if let Some(path) = args.gains_file {
+            load_gains(device, &path)?;
+        }
+        capture(device, &mut params, &mut std::io::stdout())
+    })
+}
+
+/// Opens a recording to append to it, after removing its truncated final record, if any.
+fn open_appended(path: &Path) -> Result<std::fs::File, Box<dyn Error>> {
+    if path.exists() {
+        if let Some(removed @ 1..) = repair(path)? {
+            cli::log::report(
+                Level::Warn,
+                &format!(
+                    "Removed a truncated record of {} bytes from {}",
+                    removed,
+                    path.display()
+                ),
+            );
+        }
+    }
+    Ok(std::fs::OpenOptions::new().create(true).append(true).open(path)?)
+}
+
+fn exec_export_gains(
+    port: &str,
+    options: &DeviceOptions,
+    format: GainsFormat,
+    delimiter: char,
+) -> Result<(), Box<dyn Error>> {
+    with_device(port, options, Feature::Calibration, |device| {
+        export_gains(device, format, delimiter, &mut std::io::stdout())
+    })
+}
+
+fn exec_noise_floor(args: NoiseFloorArgs, style: &Style, options: &DeviceOptions) -> Result<(), Box<dyn Error>> {
+    let bands = load_bands(args.bands_file.as_deref())?;
+    let (fstart, fstop) = resolve_range(&bands, args.band.as_deref(), args.fstart, args.fstop)?;
+
+    with_device(&args.port, options, Feature::Calibration, |device| {
+        noise_floor(
+            device,
+            fstart,
+            fstop,
+            Frequency::from_khz(args.rbw),
+            style,
+            &mut std::io::stdout(),
+        )
+    })
+}
+
+fn exec_cal_run(args: CalRunArgs, options: &DeviceOptions) -> Result<(), Box<dyn Error>> {
+    let params = CalRunParams {
+        points: args.points,
+        span: Frequency::from_mhz(args.span),
+        fstep: Frequency::from_mhz(args.fstep),
+        ref_level: args.ref_level,
+        sweeps: args.sweeps,
+        prompt: !args.no_prompt,
     };
-    capture(&mut device, &params, &mut std::io::stdout())
+
+    with_device(&args.port, options, Feature::Capture, |device| {
+        let mut measure = |fstart, fstop, fstep, ref_level| sweep(device, fstart, fstop, fstep, ref_level);
+        let profile = run_calibration(
+            &mut measure,
+            &params,
+            &mut std::io::stdin().lock(),
+            &mut std::io::stderr(),
+        )?;
+        match &args.output {
+            Some(path) => profile.write_csv(&mut std::fs::File::create(path)?)?,
+            None => profile.write_csv(&mut std::io::stdout())?,
+        }
+        Ok(())
+    })
+}
+
+fn exec_linearity(args: LinearityArgs, style: &Style, options: &DeviceOptions) -> Result<(), Box<dyn Error>> {
+    let params = LinearityParams {
+        tones: args.tones,
+        span: Frequency::from_mhz(args.span),
+        fstep: Frequency::from_mhz(args.fstep),
+        ref_level: args.ref_level,
+        tolerance: Frequency::from_khz(args.tolerance),
+        prompt: !args.no_prompt,
+    };
+
+    with_device(&args.port, options, Feature::Capture, |device| {
+        let mut measure = |fstart, fstop, fstep, ref_level| sweep(device, fstart, fstop, fstep, ref_level);
+        linearity(
+            &mut measure,
+            &params,
+            &mut std::io::stdin().lock(),
+            &mut std::io::stderr(),
+            style,
+            &mut std::io::stdout(),
+        )
+    })
+}
+
+fn exec_report(session_dir: &Path, html: &Path) -> Result<(), Box<dyn Error>> {
+    let title = session_dir
+        .file_name()
+        .map_or("SA430 report".into(), |name| name.to_string_lossy());
+    html_report(
+        &LocalStorage::new(session_dir),
+        &title,
+        &mut std::fs::File::create(html)?,
+    )
+}
+
+fn exec_decode(input: &str) -> Result<(), Box<dyn Error>> {
+    decode(input, &mut std::io::stdout())
+}
+
+fn exec_list_bands(bands_file: Option<PathBuf>, style: &Style) -> Result<(), Box<dyn Error>> {
+    let bands = load_bands(bands_file.as_deref())?;
+    list_bands(&bands, style, &mut std::io::stdout())?;
+    Ok(())
+}
+
+#[cfg(feature = "simulator")]
+fn exec_soak(params: cli::soak::SoakParams) -> Result<(), Box<dyn Error>> {
+    cli::soak::soak(&params, &mut std::io::stdout())?;
+    Ok(())
 }
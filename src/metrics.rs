@@ -0,0 +1,93 @@
+//! # Metrics Module
+//!
+//! This module writes metrics in the [OpenMetrics](https://openmetrics.io/) text format, scraped by Prometheus and
+//! compatible monitoring systems, so the health of long running services, like the REST API of the CLI, can be
+//! alerted on.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use sa430::metrics::{write_metrics, Metric, MetricKind};
+//!
+//! let metrics = [Metric::new("sa430_sweeps", "Sweeps completed.", MetricKind::Counter, 42.0)];
+//!
+//! let mut output = Vec::new();
+//! write_metrics(&metrics, &mut output).unwrap();
+//!
+//! assert_eq!(
+//!     String::from_utf8(output).unwrap(),
+//!     "# TYPE sa430_sweeps counter\n# HELP sa430_sweeps Sweeps completed.\nsa430_sweeps_total 42\n# EOF\n"
+//! );
+//! ```
+
+use std::io::{self, Write};
+
+/// Content type of the OpenMetrics text format.
+pub const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Kinds of metrics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind {
+    /// A value that only increases, like the number of sweeps completed.
+    Counter,
+
+    /// A value that goes up and down, like the number of connected devices.
+    Gauge,
+}
+
+/// A metric with a single value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metric {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub kind: MetricKind,
+    pub value: f64,
+}
+
+impl Metric {
+    /// Creates a metric, named without the `_total` suffix of the counters, which is added when written.
+    pub fn new(name: &'static str, help: &'static str, kind: MetricKind, value: f64) -> Self {
+        Metric {
+            name,
+            help,
+            kind,
+            value,
+        }
+    }
+}
+
+/// Writes the metrics, followed by the `# EOF` marker.
+pub fn write_metrics(metrics: &[Metric], output: &mut dyn Write) -> io::Result<()> {
+    for metric in metrics {
+        let (kind, suffix) = match metric.kind {
+            MetricKind::Counter => ("counter", "_total"),
+            MetricKind::Gauge => ("gauge", ""),
+        };
+        writeln!(output, "# TYPE {} {}", metric.name, kind)?;
+        writeln!(output, "# HELP {} {}", metric.name, metric.help)?;
+        writeln!(output, "{}{} {}", metric.name, suffix, metric.value)?;
+    }
+    writeln!(output, "# EOF")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_a_gauge_when_write_metrics_then_write_it_without_suffix() {
+        let metrics = [Metric::new(
+            "sa430_devices",
+            "Connected devices.",
+            MetricKind::Gauge,
+            2.0,
+        )];
+        let mut output = Vec::new();
+
+        write_metrics(&metrics, &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.lines().nth(2), Some("sa430_devices 2"));
+        assert!(output.ends_with("# EOF\n"));
+    }
+}
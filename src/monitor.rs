@@ -4,8 +4,16 @@
 //! that allows an `EventHandler` to subscribe to these events and starting the monitoring process.
 //!
 //! The `Event` enum includes:
-//! - `DeviceAdded(Port)`: Indicates that a new device has been connected to the `Port`.
-//! - `DeviceRemoved(Port)`: Indicates that a device has been disconnected from `Port`.
+//! - `DeviceAdded(Port, Metadata)`: Indicates that a new device has been connected to the `Port`.
+//! - `DeviceRemoved(Port, Metadata)`: Indicates that a device has been disconnected from `Port`.
+//! - `MonitorRestarted(Metadata)`: Indicates that the monitor recovered from an error, devices may have been connected or
+//!   disconnected meanwhile without an event.
+//!
+//! Each event carries its `Metadata`, with the time it was received and the sequence number given by the OS, to order
+//! and deduplicate events.
+//!
+//! USB enumeration sometimes reports several events for a single connection or disconnection. Wrapping a handler in a
+//! `Debouncer` coalesces them, so the handler is called once per physical action.
 //!
 //! The `EventHandler` trait defines the behavior for handling these events, which includes:
 //! - `handle(&mut self, event: &Event)`: This method is called when an event occurs.
@@ -15,7 +23,8 @@
 //! the currently available (Linux).
 //!
 //! # Note
-//! When start is called, the monitor will run indefinitely until the process/thread is killed.
+//! When start is called, the monitor will run indefinitely until the process/thread is killed. Errors after it started
+//! listening are recovered from, notifying `MonitorRestarted`.
 //!
 //! # Examples
 //!
@@ -30,8 +39,9 @@
 //! impl sa430::monitor::EventHandler for SomeEventHandler {
 //!   fn handle(&mut self, event: &Event) {
 //!     match event {
-//!       Event::DeviceAdded(port) => println!("Device added: {:?}", port),
-//!       Event::DeviceRemoved(port) => println!("Device removed: {:?}", port),
+//!       Event::DeviceAdded(port, _) => println!("Device added: {:?}", port),
+//!       Event::DeviceRemoved(port, _) => println!("Device removed: {:?}", port),
+//!       Event::MonitorRestarted(_) => println!("Monitor restarted"),
 //!     }
 //!   }
 //! }
@@ -41,17 +51,59 @@
 //! monitor.start()
 //! ```
 
+use std::{
+    collections::HashMap,
+    mem::{self, Discriminant},
+    time::{Duration, SystemTime},
+};
+
 use super::port::Port;
 
+/// When and in which order an event happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Metadata {
+    /// Time the monitor received the event.
+    pub timestamp: SystemTime,
+
+    /// Sequence number given by the OS, increasing with each event, like the udev `SEQNUM` on Linux. `None` for the
+    /// events of the monitor itself.
+    pub sequence: Option<u64>,
+}
+
+impl Metadata {
+    /// Creates the metadata of an event received now.
+    pub fn now(sequence: Option<u64>) -> Self {
+        Metadata {
+            timestamp: SystemTime::now(),
+            sequence,
+        }
+    }
+}
+
 /// Represents an event that can occur during device monitoring.
 ///
 /// The `Event` enum includes:
-/// - `DeviceAdded(Device)`: Indicates that a new device has been connected.
-/// - `DeviceRemoved(Device)`: Indicates that a device has been disconnected.
+/// - `DeviceAdded(Port, Metadata)`: Indicates that a new device has been connected.
+/// - `DeviceRemoved(Port, Metadata)`: Indicates that a device has been disconnected.
+/// - `MonitorRestarted(Metadata)`: Indicates that the monitor recovered from an error, like the udev socket buffer overflowing,
+///   by creating its socket again. Events in between are lost, so handlers tracking devices should scan them again.
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum Event {
-    DeviceAdded(Port),
-    DeviceRemoved(Port),
+    DeviceAdded(Port, Metadata),
+    DeviceRemoved(Port, Metadata),
+    MonitorRestarted(Metadata),
+}
+
+impl Event {
+    /// Returns when and in which order the event happened.
+    pub fn metadata(&self) -> &Metadata {
+        match self {
+            Event::DeviceAdded(_, metadata) | Event::DeviceRemoved(_, metadata) | Event::MonitorRestarted(metadata) => {
+                metadata
+            }
+        }
+    }
 }
 
 /// Defines the behavior for handling events.
@@ -62,6 +114,66 @@ pub trait EventHandler {
     fn handle(&mut self, event: &Event);
 }
 
+/// Default time within which repeated events of a device are coalesced by a `Debouncer`.
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// An `EventHandler` forwarding events to another handler once per real connection or disconnection.
+///
+/// Events are keyed by the serial number of the device, or its port path when the serial number is unknown, so devices
+/// without one aren't taken for the same device. An event is dropped when the last event forwarded for the same
+/// device was of the same kind and received less than `window` before. `MonitorRestarted` is always forwarded and
+/// forgets the previous events, as the devices may have changed meanwhile.
+pub struct Debouncer<'a> {
+    handler: &'a mut dyn EventHandler,
+    window: Duration,
+    last: HashMap<String, (Discriminant<Event>, SystemTime)>,
+}
+
+impl<'a> Debouncer<'a> {
+    /// Creates a debouncer forwarding to `handler`, coalescing the events within `window`, zero to forward all events.
+    pub fn new(handler: &'a mut dyn EventHandler, window: Duration) -> Self {
+        Debouncer {
+            handler,
+            window,
+            last: HashMap::new(),
+        }
+    }
+
+    fn is_duplicate(&self, key: &str, event: &Event) -> bool {
+        match self.last.get(key) {
+            Some((kind, timestamp)) if *kind == mem::discriminant(event) => event
+                .metadata()
+                .timestamp
+                .duration_since(*timestamp)
+                .is_ok_and(|elapsed| elapsed < self.window),
+            _ => false,
+        }
+    }
+}
+
+impl EventHandler for Debouncer<'_> {
+    fn handle(&mut self, event: &Event) {
+        let port = match event {
+            Event::DeviceAdded(port, _) | Event::DeviceRemoved(port, _) => port,
+            Event::MonitorRestarted(_) => {
+                self.last.clear();
+                return self.handler.handle(event);
+            }
+        };
+
+        let key = match port.serial_number() {
+            "" => port.path(),
+            serial_number => serial_number,
+        };
+        if self.is_duplicate(key, event) {
+            return;
+        }
+        self.last
+            .insert(key.to_string(), (mem::discriminant(event), event.metadata().timestamp));
+        self.handler.handle(event);
+    }
+}
+
 /// A monitor is responsible for monitoring devices connected to the computer.
 pub trait Monitor<'a> {
     /// Subscribes to usb events.
@@ -70,3 +182,163 @@ pub trait Monitor<'a> {
     /// Starts the monitor.
     fn start(&mut self) -> std::io::Result<()>;
 }
+
+#[doc(hidden)]
+pub mod fixtures {
+    //! # Fixtures Module
+    //!
+    //! This module provides a mock implementation of the `Monitor` trait for testing purposes.
+    //! It includes the `MockMonitor` struct which replays a scripted list of events to the subscribed handlers.
+    //!
+    //! ## Usage Example
+    //!
+    //! ```rust
+    //! use sa430::monitor::fixtures::MockMonitor;
+    //! use sa430::monitor::{Event, EventHandler, Metadata, Monitor};
+    //! use sa430::port::Port;
+    //!
+    //! struct Counter(usize);
+    //!
+    //! impl EventHandler for Counter {
+    //!     fn handle(&mut self, _: &Event) {
+    //!         self.0 += 1;
+    //!     }
+    //! }
+    //!
+    //! let mut counter = Counter(0);
+    //! let mut monitor = MockMonitor::new();
+    //! monitor.add_event(Event::DeviceAdded(
+    //!     Port::new("/dev/ttyACM0", "08FF41E50F8B3A34", "0104"),
+    //!     Metadata::now(Some(1)),
+    //! ));
+    //! monitor.subscribe(&mut counter);
+    //! monitor.start().unwrap();
+    //! drop(monitor);
+    //!
+    //! assert_eq!(counter.0, 1);
+    //! ```
+    use super::*;
+
+    /// ### `MockMonitor`
+    ///
+    /// A monitor that, when started, notifies every subscribed handler of each scripted event, in order, and returns.
+    #[derive(Default)]
+    pub struct MockMonitor<'a> {
+        /// Events delivered to the handlers when the monitor is started.
+        pub events: Vec<Event>,
+        handlers: Vec<&'a mut dyn EventHandler>,
+        started: usize,
+    }
+
+    impl MockMonitor<'_> {
+        /// Creates a new `MockMonitor` with no scripted events and no handlers.
+        pub fn new() -> Self {
+            MockMonitor {
+                events: Vec::new(),
+                handlers: Vec::new(),
+                started: 0,
+            }
+        }
+
+        /// Adds an event to the script, simulating a device being connected or disconnected.
+        pub fn add_event(&mut self, event: Event) {
+            self.events.push(event);
+        }
+
+        /// Returns the number of subscribed handlers.
+        pub fn handlers(&self) -> usize {
+            self.handlers.len()
+        }
+
+        /// Returns how many times the monitor was started.
+        pub fn started(&self) -> usize {
+            self.started
+        }
+    }
+
+    impl<'a> Monitor<'a> for MockMonitor<'a> {
+        fn subscribe(&mut self, handler: &'a mut dyn EventHandler) {
+            self.handlers.push(handler);
+        }
+
+        fn start(&mut self) -> std::io::Result<()> {
+            self.started += 1;
+            for event in self.events.iter() {
+                for handler in self.handlers.iter_mut() {
+                    handler.handle(event);
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Recorder(Vec<Event>);
+
+    impl EventHandler for Recorder {
+        fn handle(&mut self, event: &Event) {
+            self.0.push(event.clone());
+        }
+    }
+
+    fn metadata(millis: u64) -> Metadata {
+        Metadata {
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_millis(millis),
+            sequence: Some(millis),
+        }
+    }
+
+    fn a_port(serial_number: &str) -> Port {
+        Port::new("/dev/ttyACM0", serial_number, "0104")
+    }
+
+    #[test]
+    fn given_repeated_events_of_a_device_when_debounce_then_forward_the_first() {
+        let mut recorder = Recorder::default();
+        let mut debouncer = Debouncer::new(&mut recorder, Duration::from_millis(500));
+
+        debouncer.handle(&Event::DeviceAdded(a_port("A"), metadata(0)));
+        debouncer.handle(&Event::DeviceAdded(a_port("A"), metadata(100)));
+        debouncer.handle(&Event::DeviceAdded(a_port("B"), metadata(200)));
+        debouncer.handle(&Event::DeviceRemoved(a_port("A"), metadata(300)));
+        debouncer.handle(&Event::DeviceRemoved(a_port("A"), metadata(400)));
+        debouncer.handle(&Event::DeviceRemoved(a_port("A"), metadata(1000)));
+
+        let sequences: Vec<Option<u64>> = recorder.0.iter().map(|event| event.metadata().sequence).collect();
+        assert_eq!(sequences, vec![Some(0), Some(200), Some(300), Some(1000)]);
+    }
+
+    #[test]
+    fn given_devices_without_serial_number_when_debounce_then_key_them_by_path() {
+        let mut recorder = Recorder::default();
+        let mut debouncer = Debouncer::new(&mut recorder, Duration::from_millis(500));
+        let (first, second) = (
+            Port::new("/dev/ttyACM0", "", "0104"),
+            Port::new("/dev/ttyACM1", "", "0104"),
+        );
+
+        debouncer.handle(&Event::DeviceRemoved(first, metadata(0)));
+        debouncer.handle(&Event::DeviceRemoved(second.clone(), metadata(100)));
+        debouncer.handle(&Event::DeviceRemoved(second, metadata(200)));
+
+        let sequences: Vec<Option<u64>> = recorder.0.iter().map(|event| event.metadata().sequence).collect();
+        assert_eq!(sequences, vec![Some(0), Some(100)]);
+    }
+
+    #[test]
+    fn given_a_restart_when_debounce_then_forget_the_previous_events() {
+        let mut recorder = Recorder::default();
+        let mut debouncer = Debouncer::new(&mut recorder, Duration::from_millis(500));
+
+        debouncer.handle(&Event::DeviceAdded(a_port("A"), metadata(0)));
+        debouncer.handle(&Event::MonitorRestarted(metadata(100)));
+        debouncer.handle(&Event::DeviceAdded(a_port("A"), metadata(200)));
+
+        assert_eq!(recorder.0.len(), 3);
+    }
+}
@@ -0,0 +1,279 @@
+//! # Noise Module
+//!
+//! This module estimates the noise floor of a measurement configuration, a frequency band, a reference level and a
+//! resolution bandwidth, from the device calibration, so the settings that give the required sensitivity can be chosen
+//! before measuring.
+//!
+//! The floor at each frequency is the highest of:
+//!  - the thermal noise in the resolution bandwidth, raised by the receiver noise figure and by the gain lost, in the
+//!    calibration gain tables, compared to the most sensitive reference level;
+//!  - the bottom of the detector range, `DETECTOR_RANGE_DB` below the reference level.
+//!
+//! It is a planning aid: the actual floor of a device should be confirmed with a capture of an idle band. The simulator
+//! uses the same model for the noise of its sweeps.
+//!
+//! ## Usage Example
+//!
+//! ```rust,no_run
+//! use sa430::device::Sa430;
+//! use sa430::frequency::Frequency;
+//! use sa430::noise::estimate_noise_floors;
+//!
+//! fn print_noise_floors(device: &mut Sa430) {
+//!     let calibration = device.calibration().unwrap();
+//!
+//!     let (fstart, fstop) = (Frequency::from_mhz(868.0), Frequency::from_mhz(868.6));
+//!     for floor in estimate_noise_floors(calibration, fstart, fstop, Frequency::from_khz(100.0)).unwrap() {
+//!         println!("{} dBm: {:.1} dBm to {:.1} dBm", floor.ref_level, floor.min_dbm, floor.max_dbm);
+//!     }
+//! }
+//! ```
+
+use std::error::Error;
+
+use crate::device::Calibration;
+use crate::frequency::Frequency;
+
+/// Thermal noise density at room temperature, in dBm/Hz.
+const THERMAL_NOISE_DBM_HZ: f64 = -174.0;
+
+/// Noise figure of the receiver at the most sensitive reference level, in dB.
+const NOISE_FIGURE_DB: f64 = 8.0;
+
+/// Span of the detector below the reference level, in dB.
+const DETECTOR_RANGE_DB: f64 = 75.0;
+
+/// Number of frequencies evaluated across the band.
+const BAND_POINTS: usize = 64;
+
+/// Estimated noise floor of a band at a reference level.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoiseFloor {
+    /// Reference level, in dBm.
+    pub ref_level: i8,
+
+    /// Lowest floor across the band, in dBm.
+    pub min_dbm: f64,
+
+    /// Highest floor across the band, in dBm.
+    pub max_dbm: f64,
+}
+
+/// Estimates the noise floor from `fstart` to `fstop`, at the reference level `ref_level`, in dBm, with a resolution
+/// bandwidth of `rbw`.
+///
+/// The parts of the band outside of the device ranges are left out. Fails if the reference level is not in the
+/// calibration or if the band is outside of the device ranges.
+pub fn estimate_noise_floor(
+    calibration: &Calibration,
+    fstart: Frequency,
+    fstop: Frequency,
+    ref_level: i8,
+    rbw: Frequency,
+) -> Result<NoiseFloor, Box<dyn Error>> {
+    if rbw <= Frequency::ZERO {
+        return Err(format!("Invalid resolution bandwidth: {}", rbw).into());
+    }
+
+    let model = FloorModel::new(calibration, ref_level, rbw)?;
+
+    let fstep = match fstop > fstart {
+        true => (fstop - fstart) / (BAND_POINTS - 1) as f64,
+        false => Frequency::from_hz(1.0),
+    };
+
+    let mut floor = NoiseFloor {
+        ref_level,
+        min_dbm: f64::INFINITY,
+        max_dbm: f64::NEG_INFINITY,
+    };
+    for segment in calibration.split_sweep(fstart, fstop, fstep)? {
+        let count = ((segment.fstop - segment.fstart) / fstep).round() as usize + 1;
+        for frequency in (0..count).map(|i| segment.fstart + i as f64 * fstep) {
+            let dbm = model.floor(calibration, segment.range, frequency);
+            floor.min_dbm = floor.min_dbm.min(dbm);
+            floor.max_dbm = floor.max_dbm.max(dbm);
+        }
+    }
+    Ok(floor)
+}
+
+/// Returns the noise floor at `frequency`, inside the frequency range `range` of the calibration, at the reference
+/// level `ref_level`, in dBm, with a resolution bandwidth of `rbw`.
+///
+/// Fails if the reference level is not in the calibration or the resolution bandwidth is not positive.
+pub fn noise_floor_at(
+    calibration: &Calibration,
+    range: usize,
+    frequency: Frequency,
+    ref_level: i8,
+    rbw: Frequency,
+) -> Result<f64, Box<dyn Error>> {
+    if rbw <= Frequency::ZERO {
+        return Err(format!("Invalid resolution bandwidth: {}", rbw).into());
+    }
+    Ok(FloorModel::new(calibration, ref_level, rbw)?.floor(calibration, range, frequency))
+}
+
+/// Noise floor terms of a reference level and resolution bandwidth, the same at every frequency.
+struct FloorModel {
+    table: usize,
+    sensitive: usize,
+    thermal: f64,
+    detector: f64,
+}
+
+impl FloorModel {
+    fn new(calibration: &Calibration, ref_level: i8, rbw: Frequency) -> Result<Self, Box<dyn Error>> {
+        let table = table_index(calibration, ref_level).ok_or(format!("Unknown reference level: {} dBm", ref_level))?;
+        Ok(FloorModel {
+            table,
+            sensitive: most_sensitive_table(calibration),
+            thermal: THERMAL_NOISE_DBM_HZ + 10.0 * rbw.hz().log10() + NOISE_FIGURE_DB,
+            detector: ref_level as f64 - DETECTOR_RANGE_DB,
+        })
+    }
+
+    /// Returns the floor at `frequency` in the frequency range `range`, in dBm.
+    fn floor(&self, calibration: &Calibration, range: usize, frequency: Frequency) -> f64 {
+        let gains = &calibration.frq_gains_tables[range];
+        let lost = (gains[self.sensitive].gain_at(frequency) - gains[self.table].gain_at(frequency)).max(0.0);
+        (self.thermal + lost).max(self.detector)
+    }
+}
+
+/// Estimates the noise floor of the band at each reference level of the calibration, from the most sensitive.
+pub fn estimate_noise_floors(
+    calibration: &Calibration,
+    fstart: Frequency,
+    fstop: Frequency,
+    rbw: Frequency,
+) -> Result<Vec<NoiseFloor>, Box<dyn Error>> {
+    let mut ref_levels: Vec<i8> = calibration.ref_levels.iter().map(|level| level.dbm()).collect();
+    ref_levels.sort();
+    ref_levels.dedup();
+
+    ref_levels
+        .into_iter()
+        .map(|ref_level| estimate_noise_floor(calibration, fstart, fstop, ref_level, rbw))
+        .collect()
+}
+
+/// Returns the index of the gain tables of the reference level.
+fn table_index(calibration: &Calibration, ref_level: i8) -> Option<usize> {
+    calibration.ref_levels.iter().position(|level| level.dbm() == ref_level)
+}
+
+/// Returns the index of the gain tables of the lowest reference level.
+fn most_sensitive_table(calibration: &Calibration) -> usize {
+    calibration
+        .ref_levels
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, level)| level.dbm())
+        .map_or(0, |(index, _)| index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::device::FrequencyGain;
+
+    /// Calibration with a single range from 800 to 900 MHz, and reference levels from -35 to -70 dBm, each one 5 dB
+    /// below the previous, with constant gains 0.5 dB apart.
+    fn a_calibration() -> Calibration {
+        let mut data = vec![0u8; 2 + 16 + 2 + 1];
+        for (f_start, f_stop) in [(800_000_000u32, 900_000_000u32), (0, 0), (0, 0)] {
+            data.extend(f_start.to_be_bytes());
+            data.extend(f_stop.to_be_bytes());
+            data.extend(10u32.to_be_bytes());
+        }
+        for index in 0..8 {
+            data.extend([(-35 - 5 * index) as u8, 0]);
+        }
+        data.extend([0u8; 4 + 16 + 4 + 2 + 6 + 6]);
+        data.extend(vec![0u8; 3 * 8 * 65]);
+
+        let mut calibration = Calibration::try_from(data.as_slice()).unwrap();
+        for (index, gain) in calibration.frq_gains_tables[0].iter_mut().enumerate() {
+            *gain = FrequencyGain::new(
+                index as u8,
+                [80.0 + 0.5 * index as f64, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+            );
+        }
+        calibration
+    }
+
+    #[test]
+    fn given_the_most_sensitive_ref_level_when_estimate_then_return_the_thermal_floor() {
+        let floor = estimate_noise_floor(
+            &a_calibration(),
+            Frequency::from_mhz(868.0),
+            Frequency::from_mhz(868.6),
+            -70,
+            Frequency::from_khz(100.0),
+        )
+        .unwrap();
+
+        assert!((floor.min_dbm - (-174.0 + 50.0 + 8.0)).abs() < 1e-9);
+        assert_eq!(floor.min_dbm, floor.max_dbm);
+    }
+
+    #[test]
+    fn given_a_less_sensitive_ref_level_when_estimate_then_add_the_gain_lost() {
+        let floor = estimate_noise_floor(
+            &a_calibration(),
+            Frequency::from_mhz(868.0),
+            Frequency::from_mhz(868.6),
+            -50,
+            Frequency::from_khz(100.0),
+        )
+        .unwrap();
+
+        assert!((floor.max_dbm - (-116.0 + 2.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn given_a_high_ref_level_when_estimate_then_return_the_detector_floor() {
+        let floor = estimate_noise_floor(
+            &a_calibration(),
+            Frequency::from_mhz(868.0),
+            Frequency::from_mhz(868.6),
+            -35,
+            Frequency::from_khz(100.0),
+        )
+        .unwrap();
+
+        assert_eq!(floor.max_dbm, -35.0 - 75.0);
+    }
+
+    #[test]
+    fn given_an_unknown_ref_level_when_estimate_then_return_an_error() {
+        let error = estimate_noise_floor(
+            &a_calibration(),
+            Frequency::from_mhz(868.0),
+            Frequency::from_mhz(868.6),
+            -37,
+            Frequency::from_khz(100.0),
+        )
+        .unwrap_err();
+
+        assert_eq!(error.to_string(), "Unknown reference level: -37 dBm");
+    }
+
+    #[test]
+    fn given_a_calibration_when_estimate_noise_floors_then_return_a_floor_per_ref_level() {
+        let floors = estimate_noise_floors(
+            &a_calibration(),
+            Frequency::from_mhz(868.0),
+            Frequency::from_mhz(868.6),
+            Frequency::from_khz(100.0),
+        )
+        .unwrap();
+
+        assert_eq!(floors.len(), 8);
+        assert_eq!(floors[0].ref_level, -70);
+        assert!(floors.windows(2).all(|pair| pair[0].max_dbm <= pair[1].max_dbm));
+    }
+}
@@ -30,7 +30,7 @@ impl ByteArrayParser<'_> {
     /// # Arguments
     ///
     /// * `buffer` - A slice of bytes to be parsed.
-    pub fn new(buffer: &[u8]) -> ByteArrayParser {
+    pub fn new(buffer: &[u8]) -> ByteArrayParser<'_> {
         ByteArrayParser { offset: 0, buffer }
     }
 
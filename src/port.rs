@@ -7,6 +7,12 @@
 //! the port, serial number, and firmware version. The `Port` struct provides methods for creating a new port
 //! instance with specified attributes.
 //!
+//! Ports may also have a stable path, the `/dev/serial/by-id` link of the device, that doesn't change when the
+//! `ttyUSB`/`ttyACM` numbering changes between boots. Use `Port::path` to open the port by its stable path when known.
+//!
+//! The USB topology path of the device, like `1-2.3` for port 3 of the hub on port 2 of bus 1, tells where it is
+//! physically plugged, to find a unit among many.
+//!
 //! # Examples
 //!
 //! ```rust
@@ -16,6 +22,9 @@
 //! println!("Port connected on port: {}", port.name());
 //! println!("Port serial number: {}", port.serial_number());
 //! println!("Port firmware version: {}", port.firmware_version());
+//!
+//! let port = port.with_by_id("/dev/serial/by-id/usb-Texas_Instruments_SA430_08FF41E50F8B3A34-if00");
+//! assert_eq!(port.path(), "/dev/serial/by-id/usb-Texas_Instruments_SA430_08FF41E50F8B3A34-if00");
 //! ```
 
 /// SA430 USB Vendor ID.
@@ -36,12 +45,20 @@ pub const SERIAL_NUMBER_PROPERTY_KEY: &str = "ID_SERIAL_SHORT";
 /// Property name for the firmware version of the port.
 pub const FIRMWARE_VERSION_PROPERTY_KEY: &str = "ID_REVISION";
 
+/// Property name for the links to the port, separated by spaces.
+pub const DEVLINKS_PROPERTY_KEY: &str = "DEVLINKS";
+
+/// Directory with the stable links to the serial ports, named after the USB device.
+pub const BY_ID_DIR: &str = "/dev/serial/by-id/";
+
 /// A port represents a SA430 connected to the computer.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Port {
     name: String,
     serial_number: String,
     firmware_version: String,
+    by_id: Option<String>,
+    usb_path: Option<String>,
 }
 
 impl Port {
@@ -51,9 +68,23 @@ impl Port {
             name: String::from(port),
             serial_number: String::from(serial_number),
             firmware_version: String::from(firmware_version),
+            by_id: None,
+            usb_path: None,
         }
     }
 
+    /// Sets the stable path of the port, ex "/dev/serial/by-id/usb-Texas_Instruments_SA430_08FF41E50F8B3A34-if00".
+    pub fn with_by_id(mut self, path: &str) -> Self {
+        self.by_id = Some(String::from(path));
+        self
+    }
+
+    /// Sets the USB topology path of the device, ex "1-2.3".
+    pub fn with_usb_path(mut self, path: &str) -> Self {
+        self.usb_path = Some(String::from(path));
+        self
+    }
+
     /// Returns the COM port name, ex "/dev/ttyUSB1".
     pub fn name(&self) -> &str {
         &self.name
@@ -68,4 +99,19 @@ impl Port {
     pub fn firmware_version(&self) -> &str {
         &self.firmware_version
     }
+
+    /// Returns the stable path of the port, if known.
+    pub fn by_id(&self) -> Option<&str> {
+        self.by_id.as_deref()
+    }
+
+    /// Returns the USB topology path of the device, the bus and the hub ports it is plugged to, if known.
+    pub fn usb_path(&self) -> Option<&str> {
+        self.usb_path.as_deref()
+    }
+
+    /// Returns the path to open the port: the stable path when known, or the COM port name otherwise.
+    pub fn path(&self) -> &str {
+        self.by_id().unwrap_or(&self.name)
+    }
 }
@@ -0,0 +1,162 @@
+//! # Power Module
+//!
+//! This module provides `Dbm`, a power in dBm, with the arithmetic of powers done in the right scale: adding two
+//! powers or averaging many adds them in linear scale (mW), while adding a plain `f64` applies a gain or a loss in dB.
+//! Averaging dBm values directly underestimates the power of bursty signals, as a few strong sweeps are drowned by the
+//! weak ones.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use sa430::power::Dbm;
+//!
+//! let total = Dbm::new(-60.0) + Dbm::new(-60.0);
+//! assert!((total.dbm() + 56.99).abs() < 0.01);
+//!
+//! let mean = Dbm::mean([Dbm::new(-90.0), Dbm::new(-60.0)]).unwrap();
+//! assert!((mean.dbm() + 63.0).abs() < 0.01);
+//!
+//! assert_eq!((Dbm::new(-60.0) - 10.0).dbm(), -70.0);
+//! assert_eq!(Dbm::new(-60.0) - Dbm::new(-70.0), 10.0);
+//! ```
+
+use std::{
+    fmt::{self, Display},
+    iter::Sum,
+    ops::{Add, Sub},
+};
+
+/// A power, in dBm.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct Dbm(f64);
+
+impl Dbm {
+    /// Creates a power of `dbm` dBm.
+    pub const fn new(dbm: f64) -> Self {
+        Dbm(dbm)
+    }
+
+    /// Creates a power of `mw` mW, `-inf` dBm for 0 mW.
+    pub fn from_mw(mw: f64) -> Self {
+        Dbm(10.0 * mw.log10())
+    }
+
+    /// Returns the power in dBm.
+    pub const fn dbm(&self) -> f64 {
+        self.0
+    }
+
+    /// Returns the power in mW.
+    pub fn mw(&self) -> f64 {
+        10f64.powf(self.0 / 10.0)
+    }
+
+    /// Returns the mean of `powers`, averaged in linear scale, or `None` if there are none.
+    pub fn mean<I: IntoIterator<Item = Dbm>>(powers: I) -> Option<Dbm> {
+        let (count, total) = powers
+            .into_iter()
+            .fold((0usize, 0.0), |(count, total), power| (count + 1, total + power.mw()));
+        match count {
+            0 => None,
+            count => Some(Dbm::from_mw(total / count as f64)),
+        }
+    }
+
+    /// Returns the lowest of two powers.
+    pub fn min(self, other: Dbm) -> Dbm {
+        Dbm(self.0.min(other.0))
+    }
+
+    /// Returns the highest of two powers.
+    pub fn max(self, other: Dbm) -> Dbm {
+        Dbm(self.0.max(other.0))
+    }
+}
+
+/// The total power of two signals, added in linear scale.
+impl Add for Dbm {
+    type Output = Dbm;
+
+    fn add(self, other: Dbm) -> Dbm {
+        Dbm::from_mw(self.mw() + other.mw())
+    }
+}
+
+/// The power after a gain, in dB.
+impl Add<f64> for Dbm {
+    type Output = Dbm;
+
+    fn add(self, gain: f64) -> Dbm {
+        Dbm(self.0 + gain)
+    }
+}
+
+/// The power after a loss, in dB.
+impl Sub<f64> for Dbm {
+    type Output = Dbm;
+
+    fn sub(self, loss: f64) -> Dbm {
+        Dbm(self.0 - loss)
+    }
+}
+
+/// The ratio of two powers, in dB.
+impl Sub for Dbm {
+    type Output = f64;
+
+    fn sub(self, other: Dbm) -> f64 {
+        self.0 - other.0
+    }
+}
+
+/// The total power of many signals, added in linear scale.
+impl Sum for Dbm {
+    fn sum<I: Iterator<Item = Dbm>>(powers: I) -> Dbm {
+        Dbm::from_mw(powers.map(|power| power.mw()).sum())
+    }
+}
+
+impl Display for Dbm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match f.precision() {
+            Some(precision) => write!(f, "{:.*} dBm", precision, self.0),
+            None => write!(f, "{} dBm", self.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_powers_when_add_then_sum_them_in_linear_scale() {
+        assert!(((Dbm::new(0.0) + Dbm::new(0.0)).dbm() - 3.0103).abs() < 1e-4);
+        assert!(((Dbm::new(-50.0) + Dbm::new(f64::NEG_INFINITY)).dbm() + 50.0).abs() < 1e-9);
+        assert!(([Dbm::new(-30.0); 10].into_iter().sum::<Dbm>().dbm() + 20.0).abs() < 1e-9);
+        assert_eq!(Vec::<Dbm>::new().into_iter().sum::<Dbm>().dbm(), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn given_powers_when_mean_then_average_them_in_linear_scale() {
+        let mean = Dbm::mean([Dbm::new(-100.0), Dbm::new(-100.0), Dbm::new(-100.0), Dbm::new(-50.0)]).unwrap();
+
+        assert!((mean.dbm() + 56.02).abs() < 0.01);
+        assert_eq!(Dbm::mean([]), None);
+    }
+
+    #[test]
+    fn given_gains_when_add_or_sub_a_number_then_offset_the_power_in_db() {
+        assert_eq!(Dbm::new(-60.0) + 3.5, Dbm::new(-56.5));
+        assert_eq!(Dbm::new(-60.0) - 10.0, Dbm::new(-70.0));
+        assert_eq!(Dbm::new(-60.0) - Dbm::new(-75.0), 15.0);
+    }
+
+    #[test]
+    fn given_milliwatts_when_convert_then_return_dbm() {
+        assert_eq!(Dbm::from_mw(1.0), Dbm::new(0.0));
+        assert!((Dbm::from_mw(0.001).dbm() + 30.0).abs() < 1e-9);
+        assert_eq!(Dbm::new(20.0).mw(), 100.0);
+        assert_eq!(format!("{:.1}", Dbm::new(-62.26)), "-62.3 dBm");
+    }
+}
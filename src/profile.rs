@@ -0,0 +1,219 @@
+//! # Profile Module
+//!
+//! This module provides the `ProfileReport`, with a latency histogram per command, recorded by a device with
+//! profiling enabled, to find the slow operations when optimizing the sweep throughput.
+//!
+//! The latencies are the round trip of each command that completed, from sending the request to receiving the last
+//! reply, and are grouped in buckets with power of two bounds, from 1 µs.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use std::time::Duration;
+//!
+//! use sa430::frame::Command;
+//! use sa430::profile::ProfileReport;
+//!
+//! let mut report = ProfileReport::new();
+//! report.record(Command::GetIdn, Duration::from_micros(900));
+//! report.record(Command::GetIdn, Duration::from_micros(1100));
+//!
+//! let histogram = report.get(Command::GetIdn).unwrap();
+//! assert_eq!(histogram.count(), 2);
+//! assert_eq!(histogram.mean(), Duration::from_millis(1));
+//! println!("{}", report);
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    time::Duration,
+};
+
+use crate::frame::Command;
+
+/// Number of histogram buckets, the last one holds latencies of 2^30 µs (about 18 minutes) or more.
+const BUCKETS: usize = 32;
+
+/// Latency histogram of a command.
+///
+/// Bucket 0 counts the latencies under 1 µs and bucket `i` the latencies from 2^(i-1) µs up to 2^i µs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    buckets: [usize; BUCKETS],
+    count: usize,
+    total: Duration,
+    min: Duration,
+    max: Duration,
+}
+
+impl LatencyHistogram {
+    /// Adds a latency to the histogram.
+    pub fn record(&mut self, latency: Duration) {
+        let micros = latency.as_micros();
+        let bucket = (u128::BITS - micros.leading_zeros()) as usize;
+        self.buckets[bucket.min(BUCKETS - 1)] += 1;
+
+        self.min = if self.count == 0 {
+            latency
+        } else {
+            self.min.min(latency)
+        };
+        self.max = self.max.max(latency);
+        self.total += latency;
+        self.count += 1;
+    }
+
+    /// Returns the number of latencies recorded.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Returns the number of latencies in each bucket.
+    pub fn buckets(&self) -> &[usize] {
+        &self.buckets
+    }
+
+    /// Returns the sum of the latencies.
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+
+    /// Returns the mean latency, zero if nothing was recorded.
+    pub fn mean(&self) -> Duration {
+        match self.count {
+            0 => Duration::ZERO,
+            count => self.total / count as u32,
+        }
+    }
+
+    /// Returns the shortest latency.
+    pub fn min(&self) -> Duration {
+        self.min
+    }
+
+    /// Returns the longest latency.
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// Returns an upper bound of the `p` percentile, from 0 to 100: the upper bound of the bucket holding it, limited
+    /// to the longest latency.
+    pub fn percentile(&self, p: f64) -> Duration {
+        let rank = ((p / 100.0) * self.count as f64).ceil().max(1.0) as usize;
+        let mut seen = 0;
+        for (bucket, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Duration::from_micros(1 << bucket).min(self.max);
+            }
+        }
+        self.max
+    }
+}
+
+/// Latency histograms of the commands executed by a device.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProfileReport {
+    commands: BTreeMap<Command, LatencyHistogram>,
+}
+
+impl ProfileReport {
+    /// Creates an empty report.
+    pub fn new() -> Self {
+        ProfileReport::default()
+    }
+
+    /// Adds the latency of a command.
+    pub fn record(&mut self, command: Command, latency: Duration) {
+        self.commands.entry(command).or_default().record(latency);
+    }
+
+    /// Returns the histogram of a command, if it was executed.
+    pub fn get(&self, command: Command) -> Option<&LatencyHistogram> {
+        self.commands.get(&command)
+    }
+
+    /// Returns an iterator over the commands executed and their histograms, ordered by command code.
+    pub fn iter(&self) -> impl Iterator<Item = (Command, &LatencyHistogram)> {
+        self.commands.iter().map(|(command, histogram)| (*command, histogram))
+    }
+
+    /// Returns true if no command was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+}
+
+impl Display for ProfileReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1e3;
+
+        writeln!(
+            f,
+            "{:20} | {:>6} | {:>10} | {:>10} | {:>10} | {:>10} | {:>10} | {:>10}",
+            "command", "count", "total (ms)", "mean (ms)", "min (ms)", "p50 (ms)", "p99 (ms)", "max (ms)"
+        )?;
+        for (command, histogram) in self.iter() {
+            writeln!(
+                f,
+                "{:20} | {:6} | {:10.3} | {:10.3} | {:10.3} | {:10.3} | {:10.3} | {:10.3}",
+                format!("{:?}", command),
+                histogram.count(),
+                ms(histogram.total()),
+                ms(histogram.mean()),
+                ms(histogram.min()),
+                ms(histogram.percentile(50.0)),
+                ms(histogram.percentile(99.0)),
+                ms(histogram.max())
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_latencies_when_record_then_count_them_in_power_of_two_buckets() {
+        let mut histogram = LatencyHistogram::default();
+
+        for micros in [0, 1, 2, 3, 1000] {
+            histogram.record(Duration::from_micros(micros));
+        }
+
+        assert_eq!(&histogram.buckets()[..4], &[1, 1, 2, 0]);
+        assert_eq!(histogram.buckets()[10], 1);
+        assert_eq!(histogram.count(), 5);
+        assert_eq!(histogram.min(), Duration::ZERO);
+        assert_eq!(histogram.max(), Duration::from_micros(1000));
+    }
+
+    #[test]
+    fn given_latencies_when_percentile_then_return_the_bucket_upper_bound() {
+        let mut histogram = LatencyHistogram::default();
+        for _ in 0..99 {
+            histogram.record(Duration::from_micros(100));
+        }
+        histogram.record(Duration::from_millis(20));
+
+        assert_eq!(histogram.percentile(50.0), Duration::from_micros(128));
+        assert_eq!(histogram.percentile(99.0), Duration::from_micros(128));
+        assert_eq!(histogram.percentile(100.0), Duration::from_millis(20));
+    }
+
+    #[test]
+    fn given_a_report_when_display_then_print_a_line_per_command() {
+        let mut report = ProfileReport::new();
+        report.record(Command::FlashRead, Duration::from_millis(4));
+        report.record(Command::GetIdn, Duration::from_millis(1));
+
+        let lines: Vec<String> = report.to_string().lines().map(String::from).collect();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with("GetIdn               |      1 |      1.000 |"));
+        assert!(lines[2].starts_with("FlashRead            |      1 |      4.000 |"));
+    }
+}
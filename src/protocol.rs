@@ -7,184 +7,1063 @@
 //! It is designed to abstract the complexities of device communication, providing a simple interface for common
 //! operations that return different types of data. It handles the low-level details of frame construction,
 //! transmission, and validation, allowing users to focus on higher-level logic.
+//!
+//! Commands are executed through a `ProtocolSession`, which owns the channel and applies the session settings, like
+//! the response timeout of each `CommandClass` and the `WritePolicy` used to send frames. For debugging, the session
+//! can also dump every frame sent and received as annotated hex, see `Frame::hexdump`.
+//!
+//! Frames that don't answer the request being executed, emitted by some firmware states, fail the command unless a
+//! `FrameHandler` is subscribed to the session, in which case they are routed to the handlers and the session keeps
+//! waiting for the reply.
+//!
+//! A command interrupted by the channel being reopened, see `channel::is_reopened`, is run again once, after the link
+//! is resynchronized, since the bytes in flight were lost.
 use std::{
+    collections::VecDeque,
     error::Error,
-    io::{Read, Write},
+    fmt::{self, Display},
+    io::{self, Read, Write},
+    num::{NonZeroU8, NonZeroUsize},
+    time::{Duration, Instant, SystemTime},
 };
 
 use super::channel::*;
 use super::frame::*;
 use super::parser::*;
+use super::profile::ProfileReport;
 
-/// Sends a command to the device and returns the response as a string.
-pub fn get_string(channel: &mut dyn Channel, command: Command) -> Result<String, Box<dyn Error>> {
-    let result = exec_with_result(channel, command)?;
-    let value = String::from_utf8(result)?;
-    Ok(value)
-}
+/// Maximum number of stale frames discarded while recovering the link.
+const MAX_STALE_FRAMES: usize = 16;
+
+/// Default timeout for commands that are answered right away.
+const DEFAULT_FAST_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Default timeout for commands that change the device settings.
+const DEFAULT_NORMAL_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Default timeout for commands that measure the spectrum.
+const DEFAULT_SWEEP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default timeout for commands that access the flash memory.
+const DEFAULT_FLASH_TIMEOUT: Duration = Duration::from_secs(5);
 
-/// Sends a command to the device and returns the response as a `u32`.
-pub fn get_u32(channel: &mut dyn Channel, command: Command) -> Result<u32, Box<dyn Error>> {
-    let result = exec_with_result(channel, command)?;
-    let mut parser = ByteArrayParser::new(&result);
-    parser.take_u32()
+/// Default number of frames kept in the transcript of a session.
+pub const DEFAULT_TRANSCRIPT_SIZE: usize = 64;
+
+/// Groups commands by how long the device takes to answer them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CommandClass {
+    /// Queries answered right away, like `GetIdn`.
+    Fast,
+
+    /// Commands that change the device settings, like `SetGain`.
+    Normal,
+
+    /// Spectrum measurements.
+    Sweep,
+
+    /// Flash memory access.
+    Flash,
 }
 
-/// Sends a command to the device and returns the response as a `u16`.
-pub fn get_u16(channel: &mut dyn Channel, command: Command) -> Result<u16, Box<dyn Error>> {
-    let result = exec_with_result(channel, command)?;
-    let mut parser = ByteArrayParser::new(&result);
-    parser.take_u16()
+impl From<Command> for CommandClass {
+    fn from(command: Command) -> Self {
+        match command {
+            Command::GetIdn
+            | Command::GetSerialNumber
+            | Command::BlinkLed
+            | Command::GetCoreVersion
+            | Command::GetLastError
+            | Command::Sync
+            | Command::GetSpectrumVersion
+            | Command::GetProdVer
+            | Command::GetTemp
+            | Command::GetHardwareId
+            | Command::GetBootCnt
+            | Command::GetFxtal
+            | Command::GetChipTlv => CommandClass::Fast,
+            Command::InitParameter | Command::GetSpecNoInit | Command::SweepEdc => CommandClass::Sweep,
+            Command::FlashRead | Command::FlashWrite | Command::FlashErase | Command::FlashGetCrc => {
+                CommandClass::Flash
+            }
+            _ => CommandClass::Normal,
+        }
+    }
 }
 
-/// Reads a block of data from the device's flash memory starting at the specified address and of the specified size.
-pub fn read_flash(channel: &mut dyn Channel, addr: u16, size: u16) -> Result<Vec<u8>, Box<dyn Error>> {
-    let mut pointer = addr;
-    let mut remains = size;
-    let mut buffer = Vec::new();
+/// Response timeouts for each command class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    /// Timeout for `CommandClass::Fast` commands.
+    pub fast: Duration,
 
-    while remains > 0 {
-        let chunk_size = if remains > 255 { 255 } else { remains };
-        let data: Vec<u8> = [pointer.to_be_bytes(), chunk_size.to_be_bytes()].concat();
-        let request = Frame::with_data(Command::FlashRead, &data);
-        send_frame(&request, channel.writer())?;
+    /// Timeout for `CommandClass::Normal` commands.
+    pub normal: Duration,
 
-        let ack = receive_frame(channel.reader())?;
-        validate(&request, &ack)?;
+    /// Timeout for `CommandClass::Sweep` commands.
+    pub sweep: Duration,
 
-        let response = receive_frame(channel.reader())?;
-        validate(&request, &response)?;
+    /// Timeout for `CommandClass::Flash` commands.
+    pub flash: Duration,
+}
 
-        buffer.extend_from_slice(response.data());
-        remains -= chunk_size;
-        pointer += chunk_size;
+impl Default for Timeouts {
+    fn default() -> Self {
+        Timeouts {
+            fast: DEFAULT_FAST_TIMEOUT,
+            normal: DEFAULT_NORMAL_TIMEOUT,
+            sweep: DEFAULT_SWEEP_TIMEOUT,
+            flash: DEFAULT_FLASH_TIMEOUT,
+        }
     }
+}
 
-    Ok(buffer)
+impl Timeouts {
+    /// Returns the timeout for the class of `command`.
+    pub fn for_command(&self, command: Command) -> Duration {
+        match CommandClass::from(command) {
+            CommandClass::Fast => self.fast,
+            CommandClass::Normal => self.normal,
+            CommandClass::Sweep => self.sweep,
+            CommandClass::Flash => self.flash,
+        }
+    }
 }
 
-/// Executes a command that has no result.
-pub fn exec(channel: &mut dyn Channel, command: Command) -> Result<(), Box<dyn Error>> {
-    let request = Frame::new(command);
-    send_frame(&request, channel.writer())?;
+/// When the frames written to the channel are flushed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FlushPolicy {
+    /// Never flush, leaving it to the channel.
+    Never,
 
-    let ack = receive_frame(channel.reader())?;
-    validate(&request, &ack)?;
+    /// Flush after each complete frame.
+    #[default]
+    EveryFrame,
 
-    Ok(())
+    /// Flush after each chunk of a frame.
+    EveryChunk,
 }
 
-/// Executes a command and returns the response as a byte vector.
-pub fn exec_with_result(channel: &mut dyn Channel, command: Command) -> Result<Vec<u8>, Box<dyn Error>> {
-    let request = Frame::new(command);
-    send_frame(&request, channel.writer())?;
+/// How frames are written to the channel.
+///
+/// Each frame is assembled in a single buffer and, by default, written with one call and flushed, so it is not
+/// fragmented by the channel. High-latency transports may prefer smaller writes, set with `chunk_size`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WritePolicy {
+    /// Maximum number of bytes per write, `None` writes the whole frame at once.
+    pub chunk_size: Option<NonZeroUsize>,
 
-    let ack = receive_frame(channel.reader())?;
-    validate(&request, &ack)?;
+    /// When to flush the channel.
+    pub flush: FlushPolicy,
+}
 
-    let response = receive_frame(channel.reader())?;
-    validate(&request, &response)?;
+/// How flash memory is read.
+///
+/// Reads are split in chunks of up to `chunk_size` bytes, one `FlashRead` request each. By default, each request is
+/// sent after the previous one is answered; a `window` above 1 keeps that many requests in flight, hiding the link
+/// latency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FlashReadPolicy {
+    /// Maximum number of bytes per request.
+    pub chunk_size: NonZeroU8,
 
-    Ok(response.data().to_vec())
+    /// Maximum number of requests sent before their replies are received.
+    pub window: NonZeroUsize,
 }
 
-/// Validates the response frame against the request frame.
-fn validate(request: &Frame, response: &Frame) -> Result<(), Box<dyn Error>> {
-    if response.is_error() {
-        let message = format!(
-            "Error executing command {}: {} ({:04X})",
-            request.cmd(),
-            response.to_error_code().unwrap(),
-            response.to_error_code().unwrap()
-        );
-        return Err(Box::from(message));
+impl Default for FlashReadPolicy {
+    fn default() -> Self {
+        FlashReadPolicy {
+            chunk_size: NonZeroU8::MAX,
+            window: NonZeroUsize::MIN,
+        }
     }
+}
 
-    if request.cmd() != response.cmd() {
-        let message = format!(
-            "Invalid response, expected: {:?}, received: {:?}",
-            request.cmd(),
-            response.cmd()
-        );
-        return Err(Box::from(message));
+/// Host timestamps taken around a command, used to align the measurements of multiple devices.
+///
+/// The device has no clock, so the moment a command is executed is estimated as the midpoint between sending the
+/// request and receiving the reply, within half of the round trip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommandTiming {
+    /// The command executed.
+    pub command: Command,
+
+    /// Host time when the request was sent.
+    pub sent: SystemTime,
+
+    /// Time between sending the request and receiving the last reply.
+    pub round_trip: Duration,
+}
+
+impl CommandTiming {
+    /// Returns the estimated host time when the command was executed.
+    pub fn midpoint(&self) -> SystemTime {
+        self.sent + self.round_trip / 2
     }
 
-    Ok(())
+    /// Returns the maximum error of the midpoint estimate.
+    pub fn uncertainty(&self) -> Duration {
+        self.round_trip / 2
+    }
 }
 
-enum ReceiverState {
-    Start,
-    Length,
-    Command,
-    Data,
-    CrcHigh,
-    CrcLow,
+/// Counters of the frames exchanged by a session, to monitor the health of the link.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LinkStats {
+    /// Frames sent to the device.
+    pub frames_sent: u64,
+
+    /// Valid frames received from the device.
+    pub frames_received: u64,
+
+    /// Frames that couldn't be received, like on timeouts and corrupt frames.
+    pub receive_errors: u64,
+
+    /// Commands that completed, whatever their reply.
+    pub commands: u64,
+
+    /// Times the link was brought back to a known state.
+    pub recoveries: u64,
 }
 
-fn send_frame(frame: &Frame, port: &mut dyn Write) -> Result<(), Box<dyn Error>> {
-    port.write_all(&frame.to_bytes())?;
-    Ok(())
+/// Direction of a frame exchanged with the device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameDirection {
+    /// Sent to the device, dumped with `>`.
+    Sent,
+
+    /// Received from the device, dumped with `<`.
+    Received,
+}
+
+/// A frame exchanged with the device, kept in the transcript of the session.
+///
+/// The bytes are kept as they were sent or received, before being validated, so corrupt frames are kept too.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameRecord {
+    /// Whether the frame was sent or received.
+    pub direction: FrameDirection,
+
+    /// Host time when the frame was sent or received.
+    pub time: SystemTime,
+
+    /// Bytes of the frame.
+    pub bytes: Vec<u8>,
+}
+
+/// Formats the record like the frame dump, prefixed with `>` for the frames sent and `<` for the frames received.
+impl Display for FrameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let direction = match self.direction {
+            FrameDirection::Sent => '>',
+            FrameDirection::Received => '<',
+        };
+        write!(f, "{} {}", direction, hexdump(&self.bytes))
+    }
+}
+
+/// Receives the frames that don't answer the request being executed, see [`ProtocolSession::subscribe`].
+pub trait FrameHandler: Send {
+    fn handle(&mut self, frame: &Frame);
 }
 
-fn receive_frame(port: &mut dyn Read) -> Result<Frame, Box<dyn Error>> {
-    let mut state = ReceiverState::Start;
-    let mut buffer: [u8; 260] = [0; 260];
-    let mut index: usize = 0;
-
-    loop {
-        let byte = read(port)?;
-        buffer[index] = byte;
-        index += 1;
-
-        match state {
-            ReceiverState::Start => {
-                if byte == FRAME_MAGIC_VALUE {
-                    state = ReceiverState::Length;
-                } else {
-                    index = 0;
+impl<F: FnMut(&Frame) + Send> FrameHandler for F {
+    fn handle(&mut self, frame: &Frame) {
+        self(frame)
+    }
+}
+
+/// A protocol session with a device.
+///
+/// The session owns the channel and executes the commands, applying the session settings: the timeout of each
+/// command class and the write policy.
+pub struct ProtocolSession<C: Channel + ?Sized = dyn Channel> {
+    channel: Box<C>,
+    timeouts: Timeouts,
+    timeout: Option<Duration>,
+    write_policy: WritePolicy,
+    flash_read_policy: FlashReadPolicy,
+    last_timing: Option<CommandTiming>,
+    stats: LinkStats,
+    busy: bool,
+    frame_dump: Option<Box<dyn Write + Send>>,
+    transcript: VecDeque<FrameRecord>,
+    transcript_size: usize,
+    profile: Option<ProfileReport>,
+    parse_mode: ParseMode,
+    handlers: Vec<Box<dyn FrameHandler>>,
+    /// Bytes received after the magic value of a corrupt frame, parsed again in lenient mode.
+    pending: Vec<u8>,
+}
+
+impl<C: Channel + ?Sized> ProtocolSession<C> {
+    /// Creates a new session over `channel` with default settings.
+    pub fn new(channel: Box<C>) -> Self {
+        ProtocolSession {
+            channel,
+            timeouts: Timeouts::default(),
+            timeout: None,
+            write_policy: WritePolicy::default(),
+            flash_read_policy: FlashReadPolicy::default(),
+            last_timing: None,
+            stats: LinkStats::default(),
+            busy: false,
+            frame_dump: None,
+            transcript: VecDeque::with_capacity(DEFAULT_TRANSCRIPT_SIZE),
+            transcript_size: DEFAULT_TRANSCRIPT_SIZE,
+            profile: None,
+            parse_mode: ParseMode::default(),
+            handlers: Vec::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Returns the channel used by the session.
+    #[cfg(test)]
+    pub fn channel(&self) -> &C {
+        &self.channel
+    }
+
+    /// Returns the channel used by the session, for changing it.
+    #[cfg(all(test, feature = "simulator"))]
+    pub fn channel_mut(&mut self) -> &mut C {
+        &mut self.channel
+    }
+
+    /// Returns true if a command didn't complete and the link may hold its stale replies.
+    pub fn is_busy(&self) -> bool {
+        self.busy
+    }
+
+    /// Stops the device, bringing the link to a known state, and flushes the channel.
+    pub fn abort(&mut self) -> Result<(), Box<dyn Error>> {
+        self.recover()?;
+        self.channel.flush()?;
+        Ok(())
+    }
+
+    /// Brings the link back to a known state after a command didn't complete.
+    ///
+    /// Sends a `Sync` command and discards the stale frames received before its reply.
+    pub fn recover(&mut self) -> Result<(), Box<dyn Error>> {
+        self.busy = true;
+        let request = Frame::new(Command::Sync);
+        self.send(&request)?;
+
+        for _ in 0..MAX_STALE_FRAMES {
+            if self.receive()?.cmd() == Command::Sync {
+                self.busy = false;
+                self.stats.recoveries += 1;
+                return Ok(());
+            }
+        }
+
+        Err("Unable to recover the link, too many stale frames".into())
+    }
+
+    /// Returns the timeouts used by the session.
+    pub fn timeouts(&self) -> &Timeouts {
+        &self.timeouts
+    }
+
+    /// Sets the timeouts used by the session.
+    pub fn set_timeouts(&mut self, timeouts: Timeouts) {
+        self.timeouts = timeouts;
+    }
+
+    /// Returns the policy used to write frames.
+    pub fn write_policy(&self) -> &WritePolicy {
+        &self.write_policy
+    }
+
+    /// Sets the policy used to write frames.
+    pub fn set_write_policy(&mut self, policy: WritePolicy) {
+        self.write_policy = policy;
+    }
+
+    /// Sets where every frame sent and received is dumped, as annotated hex lines prefixed with `>` for the frames
+    /// sent and `<` for the frames received, or `None` to stop dumping.
+    pub fn set_frame_dump(&mut self, output: Option<Box<dyn Write + Send>>) {
+        self.frame_dump = output;
+    }
+
+    /// Returns the last frames sent and received, oldest first, up to the transcript size.
+    pub fn recent_frames(&self) -> &VecDeque<FrameRecord> {
+        &self.transcript
+    }
+
+    /// Sets how many of the last frames are kept in the transcript, dropping the oldest ones, 0 disables it.
+    pub fn set_transcript_size(&mut self, size: usize) {
+        self.transcript_size = size;
+        while self.transcript.len() > size {
+            self.transcript.pop_front();
+        }
+    }
+
+    /// Returns the policy used to read the flash memory.
+    pub fn flash_read_policy(&self) -> &FlashReadPolicy {
+        &self.flash_read_policy
+    }
+
+    /// Sets the policy used to read the flash memory.
+    pub fn set_flash_read_policy(&mut self, policy: FlashReadPolicy) {
+        self.flash_read_policy = policy;
+    }
+
+    /// Returns how the received frames are parsed.
+    pub fn parse_mode(&self) -> ParseMode {
+        self.parse_mode
+    }
+
+    /// Sets how the received frames are parsed.
+    ///
+    /// In lenient mode, a corrupt frame is skipped and the next frame is searched from the byte after its magic value,
+    /// so garbage with a magic value doesn't hide the frames after it.
+    pub fn set_parse_mode(&mut self, mode: ParseMode) {
+        self.parse_mode = mode;
+    }
+
+    /// Subscribes a handler to the unsolicited frames, the frames received while executing a command that are neither
+    /// its reply nor an error.
+    ///
+    /// Without handlers, an unsolicited frame fails the command with [`ProtocolError::UnexpectedResponse`].
+    pub fn subscribe(&mut self, handler: Box<dyn FrameHandler>) {
+        self.handlers.push(handler);
+    }
+
+    /// Enables or disables recording the latency of each command, disabling discards the recorded latencies.
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.profile = enabled.then(ProfileReport::new);
+    }
+
+    /// Returns the latencies recorded since profiling was enabled, if enabled.
+    pub fn profile_report(&self) -> Option<&ProfileReport> {
+        self.profile.as_ref()
+    }
+
+    /// Returns the host timestamps around the last command that completed.
+    pub fn last_timing(&self) -> Option<&CommandTiming> {
+        self.last_timing.as_ref()
+    }
+
+    /// Returns the counters of the frames exchanged since the session was created.
+    pub fn link_stats(&self) -> &LinkStats {
+        &self.stats
+    }
+
+    /// Sends a command to the device and returns the response as a string.
+    pub fn get_string(&mut self, command: Command) -> Result<String, Box<dyn Error>> {
+        let result = self.exec_with_result(command)?;
+        let value = String::from_utf8(result)?;
+        Ok(value)
+    }
+
+    /// Sends a command to the device and returns the response as a `u32`.
+    pub fn get_u32(&mut self, command: Command) -> Result<u32, Box<dyn Error>> {
+        let result = self.exec_with_result(command)?;
+        let mut parser = ByteArrayParser::new(&result);
+        parser.take_u32()
+    }
+
+    /// Sends a command to the device and returns the response as a `u16`.
+    pub fn get_u16(&mut self, command: Command) -> Result<u16, Box<dyn Error>> {
+        let result = self.exec_with_result(command)?;
+        let mut parser = ByteArrayParser::new(&result);
+        parser.take_u16()
+    }
+
+    /// Reads a block of data from the device's flash memory starting at the specified address and of the specified
+    /// size, according to the flash read policy.
+    pub fn read_flash(&mut self, addr: u16, size: u16) -> Result<Vec<u8>, Box<dyn Error>> {
+        let max_chunk = self.flash_read_policy.chunk_size.get() as u16;
+        let mut pointer = addr;
+        let mut remains = size;
+        let mut requests = Vec::new();
+
+        while remains > 0 {
+            let chunk_size = remains.min(max_chunk);
+            let data: Vec<u8> = [pointer.to_be_bytes(), chunk_size.to_be_bytes()].concat();
+            requests.push(Frame::with_data(Command::FlashRead, &data));
+            remains -= chunk_size;
+            pointer += chunk_size;
+        }
+
+        let mut buffer = Vec::new();
+        if self.flash_read_policy.window.get() == 1 {
+            for request in &requests {
+                buffer.extend_from_slice(&self.transact(request)?);
+            }
+        } else {
+            for data in self.transact_pipelined(&requests)? {
+                buffer.extend_from_slice(&data);
+            }
+        }
+
+        Ok(buffer)
+    }
+
+    /// Executes a command that has no result.
+    pub fn exec(&mut self, command: Command) -> Result<(), Box<dyn Error>> {
+        self.exec_frame(&Frame::new(command))
+    }
+
+    /// Executes a request with data that has no result, like a command setting a parameter.
+    pub fn exec_frame(&mut self, request: &Frame) -> Result<(), Box<dyn Error>> {
+        self.retry_reopened(|session| session.exec_frame_once(request))
+    }
+
+    fn exec_frame_once(&mut self, request: &Frame) -> Result<(), Box<dyn Error>> {
+        let command = request.cmd();
+        self.begin()?;
+        let (sent, start) = (SystemTime::now(), Instant::now());
+        self.send(request)?;
+
+        let reply = self.receive_reply(request)?;
+        self.record_timing(command, sent, start);
+        self.busy = false;
+
+        match reply {
+            Reply::Ack | Reply::Response(_) => Ok(()),
+            Reply::Nak(code) => Err(Box::new(ProtocolError::Nak(command, code))),
+        }
+    }
+
+    /// Executes a command and returns the response as a byte vector.
+    pub fn exec_with_result(&mut self, command: Command) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.transact(&Frame::new(command))
+    }
+
+    /// Executes a request whose response is streamed in several data frames, like a spectrum measurement, and returns
+    /// their data in order.
+    ///
+    /// The stream ends with an error frame, reporting `NoError` when the command completed, or the error that stopped
+    /// it otherwise.
+    pub fn exec_stream(&mut self, request: &Frame) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.retry_reopened(|session| session.exec_stream_once(request))
+    }
+
+    fn exec_stream_once(&mut self, request: &Frame) -> Result<Vec<u8>, Box<dyn Error>> {
+        let command = request.cmd();
+        self.begin()?;
+        let (sent, start) = (SystemTime::now(), Instant::now());
+        self.send(request)?;
+
+        let mut data = Vec::new();
+        loop {
+            let frame = self.receive_solicited(request)?;
+            match frame.to_error_code() {
+                Some(ErrorCode::NoError) => break,
+                Some(code) => {
+                    self.busy = false;
+                    return Err(Box::new(ProtocolError::Nak(command, code)));
                 }
+                None if frame.cmd() == command => data.extend_from_slice(frame.data()),
+                None => return Err(Box::new(ProtocolError::UnexpectedResponse(command, frame.cmd()))),
             }
-            ReceiverState::Length => {
-                state = ReceiverState::Command;
+        }
+        self.record_timing(command, sent, start);
+        self.busy = false;
+
+        Ok(data)
+    }
+
+    /// Sends a request and waits for its response data.
+    ///
+    /// The firmware usually acknowledges the request before sending the response, but the ACK may be omitted, and
+    /// the request can be rejected (NAK) either instead of the ACK or instead of the response.
+    fn transact(&mut self, request: &Frame) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.retry_reopened(|session| session.transact_once(request))
+    }
+
+    fn transact_once(&mut self, request: &Frame) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.begin()?;
+        let (sent, start) = (SystemTime::now(), Instant::now());
+        self.send(request)?;
+
+        let reply = self.receive_response(request)?;
+        self.record_timing(request.cmd(), sent, start);
+        self.busy = false;
+
+        response_data(request, reply)
+    }
+
+    /// Sends the requests keeping up to `window` of them in flight, and returns their response data in order.
+    ///
+    /// If a request is rejected while others are in flight, the session is left busy, since their replies are still
+    /// on the link.
+    fn transact_pipelined(&mut self, requests: &[Frame]) -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
+        self.retry_reopened(|session| session.transact_pipelined_once(requests))
+    }
+
+    fn transact_pipelined_once(&mut self, requests: &[Frame]) -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
+        self.begin()?;
+        let window = self.flash_read_policy.window.get();
+        let mut in_flight = VecDeque::with_capacity(window);
+        let mut responses = Vec::with_capacity(requests.len());
+
+        for (index, request) in requests.iter().enumerate() {
+            while in_flight.len() < window && index + in_flight.len() < requests.len() {
+                let next = &requests[index + in_flight.len()];
+                in_flight.push_back((SystemTime::now(), Instant::now()));
+                self.send(next)?;
+            }
+
+            let reply = self.receive_response(request)?;
+            if let Some((sent, start)) = in_flight.pop_front() {
+                self.record_timing(request.cmd(), sent, start);
             }
-            ReceiverState::Command => {
-                if buffer[FRAME_DATA_LENGTH_INDEX] > 0 {
-                    state = ReceiverState::Data
-                } else {
-                    state = ReceiverState::CrcHigh;
+
+            match response_data(request, reply) {
+                Ok(data) => responses.push(data),
+                Err(error) => {
+                    self.busy = !in_flight.is_empty();
+                    return Err(error);
                 }
             }
-            ReceiverState::Data => {
-                if index == FRAME_HEADER_SIZE + buffer[FRAME_DATA_LENGTH_INDEX] as usize {
-                    state = ReceiverState::CrcHigh;
+        }
+
+        self.busy = false;
+        Ok(responses)
+    }
+
+    /// Runs a command, and runs it again once after resynchronizing the link if the channel was reopened while it ran,
+    /// losing the bytes in flight, see [`is_reopened`].
+    fn retry_reopened<T>(
+        &mut self,
+        mut command: impl FnMut(&mut Self) -> Result<T, Box<dyn Error>>,
+    ) -> Result<T, Box<dyn Error>> {
+        match command(self) {
+            Err(error) if is_reopened(error.as_ref()) => {
+                self.pending.clear();
+                self.recover()?;
+                command(self)
+            }
+            result => result,
+        }
+    }
+
+    /// Sends a request using the timeout of its command and the write policy.
+    fn send(&mut self, request: &Frame) -> Result<(), Box<dyn Error>> {
+        self.prepare(request.cmd())?;
+        self.dump(FrameDirection::Sent, &request.to_bytes());
+        send_frame(request, &mut self.channel, &self.write_policy)?;
+        self.stats.frames_sent += 1;
+        Ok(())
+    }
+
+    /// Receives the next frame, dumping it before it is validated.
+    ///
+    /// In lenient mode, corrupt frames are skipped, up to [`MAX_STALE_FRAMES`].
+    fn receive(&mut self) -> Result<Frame, Box<dyn Error>> {
+        for _ in 0..MAX_STALE_FRAMES {
+            let bytes = self.receive_bytes().inspect_err(|_| self.stats.receive_errors += 1)?;
+            self.dump(FrameDirection::Received, &bytes);
+            match Frame::from_bytes(&bytes) {
+                Ok(frame) => {
+                    self.stats.frames_received += 1;
+                    return Ok(frame);
+                }
+                Err(_) if self.parse_mode == ParseMode::Lenient => {
+                    self.stats.receive_errors += 1;
+                    self.pending.splice(..0, bytes[1..].iter().copied());
+                }
+                Err(error) => {
+                    self.stats.receive_errors += 1;
+                    return Err(error.into());
                 }
             }
-            ReceiverState::CrcHigh => {
-                state = ReceiverState::CrcLow;
+        }
+
+        Err("Unable to receive a frame, too many corrupt frames".into())
+    }
+
+    /// Reads the bytes of the next frame, starting with the pending bytes.
+    fn receive_bytes(&mut self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut pending = self.pending.as_slice();
+        let bytes = receive_bytes(&mut Read::chain(&mut pending, &mut self.channel));
+        let consumed = self.pending.len() - pending.len();
+        self.pending.drain(..consumed);
+        bytes
+    }
+
+    /// Receives the response to `request`, skipping its ACK.
+    fn receive_response(&mut self, request: &Frame) -> Result<Reply, Box<dyn Error>> {
+        let reply = self.receive_reply(request)?;
+        match reply {
+            Reply::Ack => self.receive_reply(request),
+            _ => Ok(reply),
+        }
+    }
+
+    /// Receives the next frame and classifies it as a reply to `request`, routing the unsolicited frames before it to
+    /// the handlers, if any.
+    fn receive_reply(&mut self, request: &Frame) -> Result<Reply, Box<dyn Error>> {
+        let frame = self.receive_solicited(request)?;
+        Ok(classify(request, frame)?)
+    }
+
+    /// Receives the next frame for `request`, routing the unsolicited frames before it to the handlers, if any.
+    fn receive_solicited(&mut self, request: &Frame) -> Result<Frame, Box<dyn Error>> {
+        loop {
+            let frame = self.receive()?;
+            if self.handlers.is_empty() || !is_unsolicited(request, &frame) {
+                return Ok(frame);
             }
-            ReceiverState::CrcLow => {
-                break;
+            for handler in &mut self.handlers {
+                handler.handle(&frame);
             }
         }
     }
 
-    let frame = Frame::from_bytes(&buffer[..index])?;
-    Ok(frame)
+    /// Keeps the bytes of a frame in the transcript and dumps them, if enabled. Errors are ignored, so debugging never
+    /// breaks a command.
+    fn dump(&mut self, direction: FrameDirection, bytes: &[u8]) {
+        if self.frame_dump.is_none() && self.transcript_size == 0 {
+            return;
+        }
+
+        let record = FrameRecord {
+            direction,
+            time: SystemTime::now(),
+            bytes: bytes.to_vec(),
+        };
+        if let Some(output) = &mut self.frame_dump {
+            let _ = writeln!(output, "{}", record);
+        }
+        if self.transcript_size > 0 {
+            if self.transcript.len() == self.transcript_size {
+                self.transcript.pop_front();
+            }
+            self.transcript.push_back(record);
+        }
+    }
+
+    /// Marks the session busy until the command completes.
+    ///
+    /// Fails if a previous command didn't complete, since its replies would be taken as replies to the new command.
+    fn begin(&mut self) -> Result<(), ProtocolError> {
+        if self.busy {
+            return Err(ProtocolError::DeviceBusy);
+        }
+        self.busy = true;
+        Ok(())
+    }
+
+    /// Records the timestamps of a command sent at `sent`, measured from `start`.
+    fn record_timing(&mut self, command: Command, sent: SystemTime, start: Instant) {
+        let round_trip = start.elapsed();
+        self.stats.commands += 1;
+        if let Some(profile) = &mut self.profile {
+            profile.record(command, round_trip);
+        }
+        self.last_timing = Some(CommandTiming {
+            command,
+            sent,
+            round_trip,
+        });
+    }
+
+    /// Applies the timeout of `command`, only touching the channel when the timeout changes.
+    fn prepare(&mut self, command: Command) -> Result<(), Box<dyn Error>> {
+        let timeout = self.timeouts.for_command(command);
+        if self.timeout != Some(timeout) {
+            self.channel.set_timeout(timeout)?;
+            self.timeout = Some(timeout);
+        }
+        Ok(())
+    }
 }
 
-fn read(port: &mut dyn Read) -> Result<u8, Box<dyn Error>> {
-    let mut byte = [0; 1];
-    port.read_exact(byte.as_mut())?;
-    Ok(byte[0])
+/// Errors reported by the device while executing a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ProtocolError {
+    /// The device rejected the command (command, error code).
+    Nak(Command, ErrorCode),
+
+    /// The device replied to another command (expected, received).
+    UnexpectedResponse(Command, Command),
+
+    /// A previous command didn't complete, the link must be recovered before sending new commands.
+    DeviceBusy,
+}
+
+impl Error for ProtocolError {}
+
+impl Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtocolError::Nak(command, code) => {
+                write!(f, "Error executing command {}: {} ({:04X})", command, code, code)
+            }
+            ProtocolError::UnexpectedResponse(expected, received) => {
+                write!(
+                    f,
+                    "Invalid response, expected: {:?}, received: {:?}",
+                    expected, received
+                )
+            }
+            ProtocolError::DeviceBusy => write!(f, "Device is busy, a previous command did not complete"),
+        }
+    }
+}
+
+/// A frame received from the device, classified against the request being executed.
+#[derive(Debug, PartialEq)]
+enum Reply {
+    /// The request was accepted.
+    Ack,
+
+    /// The request was rejected with an error code.
+    Nak(ErrorCode),
+
+    /// Response data for the request.
+    Response(Frame),
+}
+
+/// Returns the data of the response to `request`, or the error if it was rejected.
+fn response_data(request: &Frame, reply: Reply) -> Result<Vec<u8>, Box<dyn Error>> {
+    match reply {
+        Reply::Ack => Ok(Vec::new()),
+        Reply::Response(frame) => Ok(frame.data().to_vec()),
+        Reply::Nak(code) => Err(Box::new(ProtocolError::Nak(request.cmd(), code))),
+    }
+}
+
+/// Classifies a frame as a reply to `request`.
+///
+/// A frame with the request command is an ACK when it has no data, or the response otherwise. An error frame is a
+/// NAK, except when it reports `NoError`, which the firmware uses as a positive acknowledge.
+fn classify(request: &Frame, frame: Frame) -> Result<Reply, ProtocolError> {
+    if frame.cmd() == request.cmd() {
+        if frame.data().is_empty() {
+            return Ok(Reply::Ack);
+        }
+        return Ok(Reply::Response(frame));
+    }
+
+    match frame.to_error_code() {
+        Some(ErrorCode::NoError) => Ok(Reply::Ack),
+        Some(code) => Ok(Reply::Nak(code)),
+        None => Err(ProtocolError::UnexpectedResponse(request.cmd(), frame.cmd())),
+    }
+}
+
+/// Returns true if `frame` is neither a reply to `request` nor an error, see [`ProtocolSession::subscribe`].
+fn is_unsolicited(request: &Frame, frame: &Frame) -> bool {
+    frame.cmd() != request.cmd() && !frame.is_error()
+}
+
+/// Writes a frame to the port according to the write `policy`.
+fn send_frame(frame: &Frame, port: &mut dyn Write, policy: &WritePolicy) -> Result<(), Box<dyn Error>> {
+    let bytes = frame.to_bytes();
+    let chunk_size = policy.chunk_size.map_or(bytes.len(), NonZeroUsize::get);
+
+    for chunk in bytes.chunks(chunk_size) {
+        port.write_all(chunk)?;
+        if policy.flush == FlushPolicy::EveryChunk {
+            port.flush()?;
+        }
+    }
+
+    if policy.flush == FlushPolicy::EveryFrame {
+        port.flush()?;
+    }
+
+    Ok(())
+}
+
+/// Reads the bytes of the next frame, skipping the bytes before its magic value.
+fn receive_bytes(port: &mut dyn Read) -> Result<Vec<u8>, Box<dyn Error>> {
+    match FrameStream::new(port).next_bytes()? {
+        Some(bytes) => Ok(bytes),
+        None => Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use std::io;
+
     use crate::channel::fixtures::MockChannel;
     use crate::frame::fixture::*;
 
+    fn receive_frame(port: &mut dyn Read) -> Result<Frame, Box<dyn Error>> {
+        Ok(Frame::from_bytes(&receive_bytes(port)?)?)
+    }
+
+    /// Mock channel that records the timeouts set by the session.
+    struct TimeoutRecorder {
+        channel: MockChannel,
+        timeouts: Vec<Duration>,
+    }
+
+    impl io::Read for TimeoutRecorder {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.channel.read(buf)
+        }
+    }
+
+    impl io::Write for TimeoutRecorder {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.channel.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.channel.flush()
+        }
+    }
+
+    impl Channel for TimeoutRecorder {
+        fn set_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+            self.timeouts.push(timeout);
+            Ok(())
+        }
+    }
+
+    fn a_recording_session(channel: MockChannel) -> ProtocolSession<TimeoutRecorder> {
+        ProtocolSession::new(Box::new(TimeoutRecorder {
+            channel,
+            timeouts: Vec::new(),
+        }))
+    }
+
+    /// Mock channel whose first read fails as if its serial port was reopened.
+    struct ReopenedOnce {
+        channel: MockChannel,
+        reopened: bool,
+    }
+
+    impl io::Read for ReopenedOnce {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if !self.reopened {
+                self.reopened = true;
+                let error = ChannelError::Reopened("/dev/ttyACM0".to_string());
+                return Err(io::Error::new(io::ErrorKind::ConnectionReset, error));
+            }
+            self.channel.read(buf)
+        }
+    }
+
+    impl io::Write for ReopenedOnce {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.channel.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.channel.flush()
+        }
+    }
+
+    impl Channel for ReopenedOnce {}
+
+    /// Writer that records each write and flush.
+    #[derive(Default)]
+    struct WriteRecorder {
+        writes: Vec<Vec<u8>>,
+        flushes: usize,
+    }
+
+    impl io::Write for WriteRecorder {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.writes.push(buf.to_vec());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.flushes += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn given_a_reopened_channel_when_a_command_runs_then_resynchronize_and_run_it_again() {
+        let mut channel = MockChannel::new();
+        channel.add_response(&an_ack_response(Command::Sync).to_bytes());
+        channel.add_response(&an_ack_response(Command::GetIdn).to_bytes());
+        channel.add_response(&a_get_idn_response().to_bytes());
+        let mut session = ProtocolSession::new(Box::new(ReopenedOnce {
+            channel,
+            reopened: false,
+        }));
+
+        let idn = session.get_string(Command::GetIdn).unwrap();
+
+        assert!(idn.starts_with("Texas Instruments"));
+        assert!(!session.is_busy());
+        assert_eq!(session.link_stats().recoveries, 1);
+        let expected = [
+            Frame::new(Command::GetIdn).to_bytes(),
+            Frame::new(Command::Sync).to_bytes(),
+            Frame::new(Command::GetIdn).to_bytes(),
+        ]
+        .concat();
+        assert_eq!(session.channel().channel.write_buffer, expected);
+    }
+
+    #[test]
+    fn given_a_command_when_command_class_then_return_its_class() {
+        assert_eq!(CommandClass::from(Command::GetIdn), CommandClass::Fast);
+        assert_eq!(CommandClass::from(Command::SetGain), CommandClass::Normal);
+        assert_eq!(CommandClass::from(Command::GetSpecNoInit), CommandClass::Sweep);
+        assert_eq!(CommandClass::from(Command::FlashRead), CommandClass::Flash);
+    }
+
+    #[test]
+    fn given_timeouts_when_for_command_then_return_the_timeout_of_the_command_class() {
+        let timeouts = Timeouts {
+            fast: Duration::from_millis(1),
+            normal: Duration::from_millis(2),
+            sweep: Duration::from_millis(3),
+            flash: Duration::from_millis(4),
+        };
+
+        assert_eq!(timeouts.for_command(Command::GetIdn), Duration::from_millis(1));
+        assert_eq!(timeouts.for_command(Command::HardwareReset), Duration::from_millis(2));
+        assert_eq!(timeouts.for_command(Command::GetSpecNoInit), Duration::from_millis(3));
+        assert_eq!(timeouts.for_command(Command::FlashRead), Duration::from_millis(4));
+    }
+
+    #[test]
+    fn given_a_session_when_executing_commands_then_set_the_timeout_only_when_the_class_changes() {
+        let mut channel = MockChannel::new();
+        channel.add_response(&an_ack_response(Command::BlinkLed).to_bytes());
+        channel.add_response(&an_ack_response(Command::GetSerialNumber).to_bytes());
+        channel.add_response(&a_get_serial_number_response().to_bytes());
+        channel.add_response(&an_ack_response(Command::FlashRead).to_bytes());
+        channel.add_response(&a_read_flash_response(PROG_HEADER_DATA).to_bytes());
+        let mut session = a_recording_session(channel);
+
+        session.exec(Command::BlinkLed).unwrap();
+        session.get_u32(Command::GetSerialNumber).unwrap();
+        session.read_flash(0xD400, 10).unwrap();
+
+        assert_eq!(
+            session.channel().timeouts,
+            vec![DEFAULT_FAST_TIMEOUT, DEFAULT_FLASH_TIMEOUT]
+        );
+    }
+
+    #[test]
+    fn given_custom_timeouts_when_executing_a_command_then_use_the_custom_timeout() {
+        let mut channel = MockChannel::new();
+        channel.add_response(&an_ack_response(Command::HardwareReset).to_bytes());
+        let mut session = a_recording_session(channel);
+        let custom = Timeouts {
+            normal: Duration::from_millis(250),
+            ..Timeouts::default()
+        };
+
+        session.set_timeouts(custom);
+        session.exec(Command::HardwareReset).unwrap();
+
+        assert_eq!(session.timeouts(), &custom);
+        assert_eq!(session.channel().timeouts, vec![Duration::from_millis(250)]);
+    }
+
     #[test]
     fn given_a_channel_and_a_command_when_get_string_then_return_the_string() {
         let mut channel = MockChannel::new();
@@ -192,9 +1071,10 @@ mod tests {
         channel.add_response(&an_ack_response(Command::GetIdn).to_bytes());
         channel.add_response(&a_get_idn_response().to_bytes());
 
-        let result = get_string(&mut channel, Command::GetIdn).unwrap();
+        let mut session = ProtocolSession::new(Box::new(channel));
+        let result = session.get_string(Command::GetIdn).unwrap();
 
-        assert_eq!(channel.write_buffer, Frame::new(Command::GetIdn).to_bytes());
+        assert_eq!(session.channel().write_buffer, Frame::new(Command::GetIdn).to_bytes());
         assert_eq!(result, "Texas Instruments,MSP-SA430-SUB1GHZ: RF Dev Support Tool,HW2.0");
     }
 
@@ -205,9 +1085,13 @@ mod tests {
         channel.add_response(&an_ack_response(Command::GetSerialNumber).to_bytes());
         channel.add_response(&a_get_serial_number_response().to_bytes());
 
-        let result = get_u32(&mut channel, Command::GetSerialNumber).unwrap();
+        let mut session = ProtocolSession::new(Box::new(channel));
+        let result = session.get_u32(Command::GetSerialNumber).unwrap();
 
-        assert_eq!(channel.write_buffer, Frame::new(Command::GetSerialNumber).to_bytes());
+        assert_eq!(
+            session.channel().write_buffer,
+            Frame::new(Command::GetSerialNumber).to_bytes()
+        );
         assert_eq!(result, 0x0908);
     }
 
@@ -221,10 +1105,11 @@ mod tests {
         channel.add_response(&an_ack_response(Command::FlashRead).to_bytes());
         channel.add_response(&a_read_flash_response(&data).to_bytes());
 
-        let result = read_flash(&mut channel, addr, size).unwrap();
+        let mut session = ProtocolSession::new(Box::new(channel));
+        let result = session.read_flash(addr, size).unwrap();
 
         assert_eq!(
-            channel.write_buffer,
+            session.channel().write_buffer,
             vec![0x2A, 0x04, 0x0A, 0x43, 0x21, 0x00, 0x44, 0x42, 0xE3]
         );
         assert_eq!(result.len(), size as usize);
@@ -246,10 +1131,11 @@ mod tests {
         channel.add_response(&an_ack_response(Command::FlashRead).to_bytes());
         channel.add_response(&a_read_flash_response(&data_155).to_bytes());
 
-        let result = read_flash(&mut channel, addr, size).unwrap();
+        let mut session = ProtocolSession::new(Box::new(channel));
+        let result = session.read_flash(addr, size).unwrap();
 
         assert_eq!(
-            channel.write_buffer,
+            session.channel().write_buffer,
             vec![
                 42, 4, 10, 67, 33, 0, 255, 84, 83, 42, 4, 10, 68, 32, 0, 255, 50, 78, 42, 4, 10, 69, 31, 0, 155, 129,
                 76
@@ -258,14 +1144,462 @@ mod tests {
         assert_eq!(result.len(), size as usize);
     }
 
+    #[test]
+    fn given_a_response_without_ack_when_get_string_then_return_the_string() {
+        let mut channel = MockChannel::new();
+
+        channel.add_response(&a_get_idn_response().to_bytes());
+
+        let result = ProtocolSession::new(Box::new(channel))
+            .get_string(Command::GetIdn)
+            .unwrap();
+
+        assert_eq!(result, "Texas Instruments,MSP-SA430-SUB1GHZ: RF Dev Support Tool,HW2.0");
+    }
+
+    #[test]
+    fn given_a_nak_instead_of_ack_when_exec_with_result_then_return_error() {
+        let mut channel = MockChannel::new();
+
+        channel.add_response(&an_error_response(ErrorCode::UnknownCmd).to_bytes());
+
+        let result = ProtocolSession::new(Box::new(channel)).exec_with_result(Command::GetIdn);
+
+        let error = result.unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<ProtocolError>(),
+            Some(&ProtocolError::Nak(Command::GetIdn, ErrorCode::UnknownCmd))
+        );
+        assert_eq!(
+            error.to_string(),
+            "Error executing command Get IDN: Unknown command (0324)"
+        );
+    }
+
+    #[test]
+    fn given_a_nak_after_ack_when_exec_with_result_then_return_error() {
+        let mut channel = MockChannel::new();
+
+        channel.add_response(&an_ack_response(Command::GetIdn).to_bytes());
+        channel.add_response(&an_error_response(ErrorCode::PacketIncomplete).to_bytes());
+
+        let result = ProtocolSession::new(Box::new(channel)).exec_with_result(Command::GetIdn);
+
+        assert_eq!(
+            result.unwrap_err().downcast_ref::<ProtocolError>(),
+            Some(&ProtocolError::Nak(Command::GetIdn, ErrorCode::PacketIncomplete))
+        );
+    }
+
+    #[test]
+    fn given_data_frames_when_exec_stream_then_return_their_data_until_no_error() {
+        let mut channel = MockChannel::new();
+        channel.add_response(&an_ack_response(Command::GetSpecNoInit).to_bytes());
+        channel.add_response(&Frame::with_data(Command::GetSpecNoInit, &[1, 2, 3]).to_bytes());
+        channel.add_response(&Frame::with_data(Command::GetSpecNoInit, &[4, 5]).to_bytes());
+        channel.add_response(&an_error_response(ErrorCode::NoError).to_bytes());
+        let mut session = ProtocolSession::new(Box::new(channel));
+
+        let data = session.exec_stream(&Frame::new(Command::GetSpecNoInit)).unwrap();
+
+        assert_eq!(data, [1, 2, 3, 4, 5]);
+        assert!(!session.is_busy());
+        assert_eq!(session.link_stats().commands, 1);
+    }
+
+    #[test]
+    fn given_an_error_in_the_stream_when_exec_stream_then_return_it() {
+        let mut channel = MockChannel::new();
+        channel.add_response(&an_ack_response(Command::GetSpecNoInit).to_bytes());
+        channel.add_response(&Frame::with_data(Command::GetSpecNoInit, &[1, 2, 3]).to_bytes());
+        channel.add_response(&an_error_response(ErrorCode::PacketIncomplete).to_bytes());
+        let mut session = ProtocolSession::new(Box::new(channel));
+
+        let result = session.exec_stream(&Frame::new(Command::GetSpecNoInit));
+
+        assert_eq!(
+            result.unwrap_err().downcast_ref::<ProtocolError>(),
+            Some(&ProtocolError::Nak(Command::GetSpecNoInit, ErrorCode::PacketIncomplete))
+        );
+        assert!(!session.is_busy());
+    }
+
+    #[test]
+    fn given_a_nak_when_exec_then_return_error() {
+        let mut channel = MockChannel::new();
+
+        channel.add_response(&an_error_response(ErrorCode::WrongCrcLowByte).to_bytes());
+
+        let result = ProtocolSession::new(Box::new(channel)).exec(Command::BlinkLed);
+
+        assert_eq!(
+            result.unwrap_err().downcast_ref::<ProtocolError>(),
+            Some(&ProtocolError::Nak(Command::BlinkLed, ErrorCode::WrongCrcLowByte))
+        );
+    }
+
+    #[test]
+    fn given_a_no_error_status_when_exec_then_return_ok() {
+        let mut channel = MockChannel::new();
+
+        channel.add_response(&an_error_response(ErrorCode::NoError).to_bytes());
+
+        assert!(ProtocolSession::new(Box::new(channel)).exec(Command::BlinkLed).is_ok());
+    }
+
+    #[test]
+    fn given_a_response_to_another_command_when_exec_then_return_error() {
+        let mut channel = MockChannel::new();
+
+        channel.add_response(&an_ack_response(Command::GetIdn).to_bytes());
+
+        let result = ProtocolSession::new(Box::new(channel)).exec(Command::BlinkLed);
+
+        assert_eq!(
+            result.unwrap_err().downcast_ref::<ProtocolError>(),
+            Some(&ProtocolError::UnexpectedResponse(Command::BlinkLed, Command::GetIdn))
+        );
+    }
+
+    #[test]
+    fn given_last_error_is_requested_when_exec_with_result_then_return_the_error_code() {
+        let mut channel = MockChannel::new();
+
+        channel.add_response(&an_ack_response(Command::GetLastError).to_bytes());
+        channel.add_response(&an_error_response(ErrorCode::LostCmd).to_bytes());
+
+        let result = ProtocolSession::new(Box::new(channel))
+            .exec_with_result(Command::GetLastError)
+            .unwrap();
+
+        assert_eq!(result, vec![0x03, 0x23]);
+    }
+
+    #[test]
+    fn given_a_command_when_executed_then_record_its_timing() {
+        let mut channel = MockChannel::new();
+        channel.add_response(&fixture::an_ack_response(Command::BlinkLed).to_bytes());
+        channel.add_response(&fixture::an_ack_response(Command::GetIdn).to_bytes());
+        channel.add_response(&fixture::a_get_idn_response().to_bytes());
+        let mut session = ProtocolSession::new(Box::new(channel));
+        assert_eq!(session.last_timing(), None);
+
+        session.exec(Command::BlinkLed).unwrap();
+        assert_eq!(session.last_timing().unwrap().command, Command::BlinkLed);
+
+        let before = SystemTime::now();
+        session.get_string(Command::GetIdn).unwrap();
+        let timing = *session.last_timing().unwrap();
+
+        assert_eq!(timing.command, Command::GetIdn);
+        assert!(timing.sent >= before);
+        assert!(timing.midpoint() <= SystemTime::now());
+        assert_eq!(timing.uncertainty(), timing.round_trip / 2);
+    }
+
+    #[test]
+    fn given_commands_when_executed_then_count_the_frames_exchanged() {
+        let mut channel = MockChannel::new();
+        channel.add_response(&fixture::an_ack_response(Command::BlinkLed).to_bytes());
+        channel.add_response(&fixture::an_ack_response(Command::GetIdn).to_bytes());
+        channel.add_response(&fixture::a_get_idn_response().to_bytes());
+        let mut session = ProtocolSession::new(Box::new(channel));
+
+        session.exec(Command::BlinkLed).unwrap();
+        session.get_string(Command::GetIdn).unwrap();
+        assert!(session.exec(Command::BlinkLed).is_err());
+
+        assert_eq!(
+            session.link_stats(),
+            &LinkStats {
+                frames_sent: 3,
+                frames_received: 3,
+                receive_errors: 1,
+                commands: 2,
+                recoveries: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn given_a_response_to_another_command_when_exec_then_reject_the_next_commands() {
+        let mut channel = MockChannel::new();
+        channel.add_response(&fixture::an_ack_response(Command::GetIdn).to_bytes());
+        let mut session = ProtocolSession::new(Box::new(channel));
+
+        assert!(session.exec(Command::BlinkLed).is_err());
+        assert!(session.is_busy());
+
+        let result = session.exec(Command::BlinkLed);
+        assert_eq!(
+            result.unwrap_err().downcast_ref::<ProtocolError>(),
+            Some(&ProtocolError::DeviceBusy)
+        );
+    }
+
+    #[test]
+    fn given_a_handler_when_an_unsolicited_frame_is_received_then_route_it_and_wait_for_the_reply() {
+        let received = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut channel = MockChannel::new();
+        channel.add_response(&fixture::a_get_idn_response().to_bytes());
+        channel.add_response(&fixture::an_ack_response(Command::BlinkLed).to_bytes());
+        let mut session = ProtocolSession::new(Box::new(channel));
+        let sink = received.clone();
+        session.subscribe(Box::new(move |frame: &Frame| sink.lock().unwrap().push(frame.clone())));
+
+        assert!(session.exec(Command::BlinkLed).is_ok());
+
+        assert_eq!(*received.lock().unwrap(), vec![fixture::a_get_idn_response()]);
+        assert!(!session.is_busy());
+    }
+
+    #[test]
+    fn given_stale_frames_when_recover_then_discard_them_until_sync() {
+        let mut channel = MockChannel::new();
+        channel.add_response(&fixture::an_ack_response(Command::GetIdn).to_bytes());
+        channel.add_response(&fixture::a_get_idn_response().to_bytes());
+        channel.add_response(&fixture::an_ack_response(Command::Sync).to_bytes());
+        channel.add_response(&fixture::an_ack_response(Command::BlinkLed).to_bytes());
+        let mut session = ProtocolSession::new(Box::new(channel));
+
+        assert!(session.exec(Command::BlinkLed).is_err());
+        session.recover().unwrap();
+
+        assert!(!session.is_busy());
+        assert!(session.exec(Command::BlinkLed).is_ok());
+        assert_eq!(
+            &session.channel().write_buffer[session.channel().write_buffer.len() - 10..][..5],
+            &Frame::new(Command::Sync).to_bytes()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "simulator")]
+    fn given_an_unresponsive_simulator_when_a_command_times_out_then_be_busy_until_recovered() {
+        use crate::simulator::Simulator;
+
+        let mut session = ProtocolSession::new(Box::new(Simulator::new()));
+        session.channel_mut().set_responsive(false);
+
+        assert!(session.get_string(Command::GetIdn).is_err());
+        let result = session.get_u32(Command::GetSerialNumber);
+        assert_eq!(
+            result.unwrap_err().downcast_ref::<ProtocolError>(),
+            Some(&ProtocolError::DeviceBusy)
+        );
+
+        session.channel_mut().set_responsive(true);
+        session.recover().unwrap();
+        assert_eq!(session.get_u32(Command::GetSerialNumber).unwrap(), 0x0908);
+    }
+
     #[test]
     fn given_a_frame_when_send_frame_then_send_frame_to_port() {
         let frame = Frame::with_data(Command::SetGain, &[0x00, 0x01]);
         let mut port = Vec::new();
-        send_frame(&frame, &mut port).unwrap();
+        send_frame(&frame, &mut port, &WritePolicy::default()).unwrap();
         assert_eq!(port, vec![0x2a, 0x02, 0x1B, 0x00, 0x01, 0x0F, 0xDC]);
     }
 
+    #[test]
+    fn given_the_default_policy_when_send_frame_then_write_the_whole_frame_and_flush() {
+        let frame = Frame::with_data(Command::SetGain, &[0x00, 0x01]);
+        let mut port = WriteRecorder::default();
+
+        send_frame(&frame, &mut port, &WritePolicy::default()).unwrap();
+
+        assert_eq!(port.writes, vec![frame.to_bytes()]);
+        assert_eq!(port.flushes, 1);
+    }
+
+    #[test]
+    fn given_a_chunked_policy_when_send_frame_then_write_the_frame_in_chunks() {
+        let frame = Frame::with_data(Command::SetGain, &[0x00, 0x01]);
+        let mut port = WriteRecorder::default();
+        let policy = WritePolicy {
+            chunk_size: NonZeroUsize::new(3),
+            flush: FlushPolicy::EveryChunk,
+        };
+
+        send_frame(&frame, &mut port, &policy).unwrap();
+
+        assert_eq!(
+            port.writes,
+            vec![vec![0x2a, 0x02, 0x1B], vec![0x00, 0x01, 0x0F], vec![0xDC]]
+        );
+        assert_eq!(port.flushes, 3);
+    }
+
+    #[test]
+    fn given_a_never_flush_policy_when_send_frame_then_do_not_flush() {
+        let frame = Frame::new(Command::BlinkLed);
+        let mut port = WriteRecorder::default();
+        let policy = WritePolicy {
+            flush: FlushPolicy::Never,
+            ..WritePolicy::default()
+        };
+
+        send_frame(&frame, &mut port, &policy).unwrap();
+
+        assert_eq!(port.flushes, 0);
+    }
+
+    #[test]
+    fn given_a_write_policy_when_executing_a_command_then_use_the_policy() {
+        let mut channel = MockChannel::new();
+        channel.add_response(&an_ack_response(Command::BlinkLed).to_bytes());
+        let mut session = ProtocolSession::new(Box::new(channel));
+        let policy = WritePolicy {
+            chunk_size: NonZeroUsize::new(1),
+            flush: FlushPolicy::Never,
+        };
+
+        session.set_write_policy(policy);
+        session.exec(Command::BlinkLed).unwrap();
+
+        assert_eq!(session.write_policy(), &policy);
+        assert_eq!(session.channel().write_buffer, Frame::new(Command::BlinkLed).to_bytes());
+    }
+
+    /// Writer sharing its buffer, to inspect the frame dump after it is handed to the session.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl io::Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn given_a_frame_dump_when_exec_then_dump_frames_sent_and_received() {
+        let dump = SharedBuffer::default();
+        let mut channel = MockChannel::new();
+        channel.add_response(&an_ack_response(Command::BlinkLed).to_bytes());
+        let mut session = ProtocolSession::new(Box::new(channel));
+        session.set_frame_dump(Some(Box::new(dump.clone())));
+
+        session.exec(Command::BlinkLed).unwrap();
+
+        let expected = format!(
+            "> {}\n< {}\n",
+            Frame::new(Command::BlinkLed).hexdump(),
+            an_ack_response(Command::BlinkLed).hexdump()
+        );
+        assert_eq!(String::from_utf8(dump.0.lock().unwrap().clone()).unwrap(), expected);
+    }
+
+    #[test]
+    fn given_a_transcript_size_when_exec_then_keep_the_last_frames_only() {
+        let mut channel = MockChannel::new();
+        channel.add_response(&an_ack_response(Command::BlinkLed).to_bytes());
+        channel.add_response(&an_ack_response(Command::Sync).to_bytes());
+        let mut session = ProtocolSession::new(Box::new(channel));
+        session.set_transcript_size(3);
+
+        session.exec(Command::BlinkLed).unwrap();
+        session.exec(Command::Sync).unwrap();
+
+        let frames: Vec<String> = session
+            .recent_frames()
+            .iter()
+            .map(|record| record.to_string())
+            .collect();
+        assert_eq!(
+            frames,
+            [
+                format!("< {}", an_ack_response(Command::BlinkLed).hexdump()),
+                format!("> {}", Frame::new(Command::Sync).hexdump()),
+                format!("< {}", an_ack_response(Command::Sync).hexdump()),
+            ]
+        );
+
+        session.set_transcript_size(0);
+        assert!(session.recent_frames().is_empty());
+    }
+
+    #[test]
+    fn given_a_flash_read_window_when_read_flash_then_pipeline_the_requests() {
+        let mut channel = MockChannel::new();
+        for data in [[1, 2], [3, 4], [5, 0]] {
+            channel.add_response(&an_ack_response(Command::FlashRead).to_bytes());
+            channel.add_response(&a_read_flash_response(&data).to_bytes());
+        }
+        let mut session = ProtocolSession::new(Box::new(channel));
+        session.set_flash_read_policy(FlashReadPolicy {
+            chunk_size: NonZeroU8::new(2).unwrap(),
+            window: NonZeroUsize::new(2).unwrap(),
+        });
+
+        let data = session.read_flash(0xD400, 6).unwrap();
+
+        assert_eq!(data, vec![1, 2, 3, 4, 5, 0]);
+        assert!(!session.is_busy());
+        let expected: Vec<u8> = [0xD400u16, 0xD402, 0xD404]
+            .iter()
+            .flat_map(|addr| Frame::with_data(Command::FlashRead, &[addr.to_be_bytes(), [0, 2]].concat()).to_bytes())
+            .collect();
+        assert_eq!(session.channel().write_buffer, expected);
+    }
+
+    #[test]
+    fn given_a_rejected_request_in_flight_when_read_flash_then_leave_the_session_busy() {
+        let mut channel = MockChannel::new();
+        channel.add_response(&an_error_response(ErrorCode::BufferPosOutOfRange).to_bytes());
+        let mut session = ProtocolSession::new(Box::new(channel));
+        session.set_flash_read_policy(FlashReadPolicy {
+            chunk_size: NonZeroU8::new(2).unwrap(),
+            window: NonZeroUsize::new(4).unwrap(),
+        });
+
+        assert!(session.read_flash(0xD400, 6).is_err());
+        assert!(session.is_busy());
+    }
+
+    #[test]
+    fn given_profiling_when_exec_then_record_the_latency_of_each_command() {
+        let mut channel = MockChannel::new();
+        channel.add_response(&an_ack_response(Command::BlinkLed).to_bytes());
+        channel.add_response(&an_ack_response(Command::BlinkLed).to_bytes());
+        let mut session = ProtocolSession::new(Box::new(channel));
+        assert!(session.profile_report().is_none());
+
+        session.set_profiling(true);
+        session.exec(Command::BlinkLed).unwrap();
+        session.exec(Command::BlinkLed).unwrap();
+
+        let report = session.profile_report().unwrap();
+        assert_eq!(report.get(Command::BlinkLed).unwrap().count(), 2);
+        assert!(report.get(Command::GetIdn).is_none());
+    }
+
+    #[test]
+    fn given_garbage_with_a_magic_value_when_receiving_in_lenient_mode_then_skip_it() {
+        let mut channel = MockChannel::new();
+        channel.add_response(&[0x2A, 0x02, 0x00]);
+        channel.add_response(&fixture::an_ack_response(Command::BlinkLed).to_bytes());
+        let mut session = ProtocolSession::new(Box::new(channel));
+
+        session.set_parse_mode(ParseMode::Lenient);
+
+        assert!(session.exec(Command::BlinkLed).is_ok());
+        assert!(session.pending.is_empty());
+    }
+
+    #[test]
+    fn given_garbage_with_a_magic_value_when_receiving_in_strict_mode_then_return_error() {
+        let mut channel = MockChannel::new();
+        channel.add_response(&[0x2A, 0x02, 0x00]);
+        channel.add_response(&fixture::an_ack_response(Command::BlinkLed).to_bytes());
+        let mut session = ProtocolSession::new(Box::new(channel));
+
+        assert!(session.exec(Command::BlinkLed).is_err());
+    }
+
     #[test]
     fn given_a_frame_when_receive_frame_then_receive_frame_from_port() {
         let frame = Frame::with_data(Command::SetGain, &[0x00, 0x01]);
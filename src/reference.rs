@@ -0,0 +1,290 @@
+//! # Reference Module
+//!
+//! This module computes a host-side correction profile from the measurements of an external reference signal, like a
+//! signal generator, to improve the absolute accuracy of uncalibrated or aged units without touching the calibration
+//! stored in the device flash.
+//!
+//! Each `Correction` pairs the power of the reference at a frequency with the power measured by the device there. The
+//! `CorrectionProfile` interpolates their offsets linearly between frequencies, keeping the offset of the nearest
+//! correction outside of them, and averages the offsets of the corrections measured at the same frequency with
+//! different levels.
+//!
+//! Profiles are stored as CSV files, with a `frequency_hz,reference_dbm,measured_dbm` row per correction.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use sa430::frequency::Frequency;
+//! use sa430::power::Dbm;
+//! use sa430::reference::{Correction, CorrectionProfile};
+//!
+//! let profile = CorrectionProfile::new(vec![
+//!     Correction::new(Frequency::from_mhz(868.0), Dbm::new(-30.0), Dbm::new(-32.0)),
+//!     Correction::new(Frequency::from_mhz(870.0), Dbm::new(-30.0), Dbm::new(-31.0)),
+//! ]);
+//!
+//! assert_eq!(profile.offset_at(Frequency::from_mhz(869.0)), 1.5);
+//! assert_eq!(profile.offset_at(Frequency::from_mhz(900.0)), 1.0);
+//! ```
+
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    io::{self, BufRead},
+    str::FromStr,
+};
+
+use super::frequency::Frequency;
+use super::power::Dbm;
+use super::spectrum::Spectrum;
+
+/// Header of the CSV correction profiles.
+const PROFILE_HEADER: &str = "frequency_hz,reference_dbm,measured_dbm";
+
+/// Frequency and power of the reference signal of a measurement.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReferencePoint {
+    /// Frequency of the reference signal.
+    pub frequency: Frequency,
+
+    /// Power of the reference signal at the input of the device.
+    pub power: Dbm,
+}
+
+/// Error parsing a reference point.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidReferencePoint(String);
+
+impl Error for InvalidReferencePoint {}
+
+impl Display for InvalidReferencePoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid reference point: {}, expected a frequency and a power in dBm, like 868.1MHz@-30",
+            self.0
+        )
+    }
+}
+
+/// Parses a reference point as `frequency@power`, like `868.1MHz@-30`, with the power in dBm.
+impl FromStr for ReferencePoint {
+    type Err = InvalidReferencePoint;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidReferencePoint(value.to_string());
+        let (frequency, power) = value.split_once('@').ok_or_else(invalid)?;
+        let frequency: Frequency = frequency.parse().map_err(|_| invalid())?;
+        match power.trim().parse::<f64>() {
+            Ok(power) if power.is_finite() && frequency > Frequency::ZERO => Ok(ReferencePoint {
+                frequency,
+                power: Dbm::new(power),
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Power of the reference signal and power measured by the device, at a frequency.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Correction {
+    /// Frequency of the reference signal.
+    pub frequency: Frequency,
+
+    /// Power of the reference signal.
+    pub reference: Dbm,
+
+    /// Power measured by the device.
+    pub measured: Dbm,
+}
+
+impl Correction {
+    /// Creates the correction of a measurement of `measured` dBm of a reference of `reference` dBm at `frequency`.
+    pub fn new(frequency: Frequency, reference: Dbm, measured: Dbm) -> Self {
+        Correction {
+            frequency,
+            reference,
+            measured,
+        }
+    }
+
+    /// Returns the offset to add to the measured powers, in dB.
+    pub fn offset(&self) -> f64 {
+        self.reference - self.measured
+    }
+}
+
+/// Offsets of the measured powers, by frequency, computed from the measurements of a reference signal.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CorrectionProfile {
+    corrections: Vec<Correction>,
+}
+
+impl CorrectionProfile {
+    /// Creates a profile from the corrections, in any order.
+    pub fn new(mut corrections: Vec<Correction>) -> Self {
+        corrections.sort_by(|a, b| a.frequency.hz().total_cmp(&b.frequency.hz()));
+        CorrectionProfile { corrections }
+    }
+
+    /// Returns the corrections, sorted by frequency.
+    pub fn corrections(&self) -> &[Correction] {
+        &self.corrections
+    }
+
+    /// Returns the offset at `frequency`, in dB, 0 for an empty profile.
+    pub fn offset_at(&self, frequency: Frequency) -> f64 {
+        let offsets = self.offsets();
+        let after = offsets.partition_point(|(f, _)| *f <= frequency);
+        match (
+            after.checked_sub(1).map(|index| offsets[index]),
+            offsets.get(after).copied(),
+        ) {
+            (None, None) => 0.0,
+            (Some((_, offset)), None) | (None, Some((_, offset))) => offset,
+            (Some((f0, offset0)), Some((f1, offset1))) => {
+                offset0 + (offset1 - offset0) * ((frequency - f0) / (f1 - f0))
+            }
+        }
+    }
+
+    /// Returns the spectrum with the offset of each frequency added to its power.
+    pub fn apply(&self, spectrum: &Spectrum) -> Spectrum {
+        Spectrum::new(
+            spectrum
+                .iter()
+                .map(|(frequency, power)| (*frequency, power + self.offset_at(Frequency::from_hz(*frequency))))
+                .collect(),
+        )
+    }
+
+    /// Reads a profile from a CSV file, skipping the header and empty lines.
+    pub fn read_csv<R: BufRead>(reader: R) -> Result<Self, Box<dyn Error>> {
+        let mut corrections = Vec::new();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line == PROFILE_HEADER {
+                continue;
+            }
+
+            let columns: Vec<&str> = line.split(',').map(str::trim).collect();
+            let numbers: Vec<f64> = columns.iter().filter_map(|column| column.parse().ok()).collect();
+            match numbers[..] {
+                [frequency, reference, measured] if columns.len() == 3 => corrections.push(Correction::new(
+                    Frequency::from_hz(frequency),
+                    Dbm::new(reference),
+                    Dbm::new(measured),
+                )),
+                _ => return Err(format!("Line {}: expected {}", index + 1, PROFILE_HEADER).into()),
+            }
+        }
+        Ok(CorrectionProfile::new(corrections))
+    }
+
+    /// Writes the profile as a CSV file, with a header.
+    pub fn write_csv(&self, output: &mut dyn io::Write) -> io::Result<()> {
+        writeln!(output, "{}", PROFILE_HEADER)?;
+        for correction in &self.corrections {
+            writeln!(
+                output,
+                "{:.0},{:.2},{:.2}",
+                correction.frequency.hz(),
+                correction.reference.dbm(),
+                correction.measured.dbm()
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Returns the offset of each frequency, averaging the corrections at the same frequency.
+    fn offsets(&self) -> Vec<(Frequency, f64)> {
+        let mut offsets: Vec<(Frequency, f64, usize)> = Vec::new();
+        for correction in &self.corrections {
+            match offsets.last_mut() {
+                Some((frequency, total, count)) if *frequency == correction.frequency => {
+                    *total += correction.offset();
+                    *count += 1;
+                }
+                _ => offsets.push((correction.frequency, correction.offset(), 1)),
+            }
+        }
+        offsets
+            .into_iter()
+            .map(|(frequency, total, count)| (frequency, total / count as f64))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mhz(value: f64) -> Frequency {
+        Frequency::from_mhz(value)
+    }
+
+    fn a_profile() -> CorrectionProfile {
+        CorrectionProfile::new(vec![
+            Correction::new(mhz(915.0), Dbm::new(-50.0), Dbm::new(-54.0)),
+            Correction::new(mhz(868.0), Dbm::new(-30.0), Dbm::new(-32.0)),
+            Correction::new(mhz(868.0), Dbm::new(-50.0), Dbm::new(-53.0)),
+        ])
+    }
+
+    #[test]
+    fn given_corrections_when_offset_at_then_interpolate_between_frequencies() {
+        let profile = a_profile();
+
+        assert_eq!(profile.offset_at(mhz(868.0)), 2.5);
+        assert!((profile.offset_at(mhz(891.5)) - 3.25).abs() < 1e-9);
+        assert_eq!(profile.offset_at(mhz(433.0)), 2.5);
+        assert_eq!(profile.offset_at(mhz(928.0)), 4.0);
+        assert_eq!(CorrectionProfile::default().offset_at(mhz(868.0)), 0.0);
+    }
+
+    #[test]
+    fn given_a_spectrum_when_apply_then_add_the_offsets_to_the_powers() {
+        let spectrum = Spectrum::new(vec![(868.0e6, -70.0), (915.0e6, -80.0)]);
+
+        let corrected = a_profile().apply(&spectrum);
+
+        assert_eq!(corrected.points(), &[(868.0e6, -67.5), (915.0e6, -76.0)]);
+    }
+
+    #[test]
+    fn given_a_profile_when_written_and_read_then_keep_the_corrections() {
+        let mut output = Vec::new();
+
+        a_profile().write_csv(&mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output.clone()).unwrap(),
+            "frequency_hz,reference_dbm,measured_dbm\n\
+             868000000,-30.00,-32.00\n\
+             868000000,-50.00,-53.00\n\
+             915000000,-50.00,-54.00\n"
+        );
+        assert_eq!(CorrectionProfile::read_csv(output.as_slice()).unwrap(), a_profile());
+        assert_eq!(
+            CorrectionProfile::read_csv("868000000,-30\n".as_bytes())
+                .unwrap_err()
+                .to_string(),
+            "Line 1: expected frequency_hz,reference_dbm,measured_dbm"
+        );
+    }
+
+    #[test]
+    fn given_strings_when_parse_reference_points_then_split_frequency_and_power() {
+        assert_eq!(
+            "868.1MHz@-30".parse(),
+            Ok(ReferencePoint {
+                frequency: mhz(868.1),
+                power: Dbm::new(-30.0)
+            })
+        );
+        assert!("868.1MHz".parse::<ReferencePoint>().is_err());
+        assert!("0@-30".parse::<ReferencePoint>().is_err());
+        assert!("868.1MHz@loud".parse::<ReferencePoint>().is_err());
+    }
+}
@@ -32,3 +32,57 @@ pub trait Scanner {
     /// A list of ports were SA430 devices are connected to.
     fn scan(&self) -> Vec<Port>;
 }
+
+#[doc(hidden)]
+pub mod fixtures {
+    //! # Fixtures Module
+    //!
+    //! This module provides a mock implementation of the `Scanner` trait for testing purposes.
+    //! It includes the `MockScanner` struct which returns a predefined list of ports.
+    //!
+    //! ## Usage Example
+    //!
+    //! ```rust
+    //! use sa430::port::Port;
+    //! use sa430::scanner::fixtures::MockScanner;
+    //! use sa430::scanner::Scanner;
+    //!
+    //! let mut scanner = MockScanner::new();
+    //! scanner.add_port(Port::new("/dev/ttyACM0", "08FF41E50F8B3A34", "0104"));
+    //!
+    //! assert_eq!(scanner.scan().len(), 1);
+    //! ```
+    use super::*;
+
+    /// ### `MockScanner`
+    ///
+    /// A scanner that always returns the same list of ports.
+    #[derive(Debug, Default, Clone)]
+    pub struct MockScanner {
+        /// Ports returned by every call to `scan`.
+        pub ports: Vec<Port>,
+    }
+
+    impl MockScanner {
+        /// Creates a new `MockScanner` with no ports.
+        pub fn new() -> Self {
+            MockScanner { ports: Vec::new() }
+        }
+
+        /// Creates a new `MockScanner` that returns the given ports.
+        pub fn with_ports(ports: &[Port]) -> Self {
+            MockScanner { ports: ports.to_vec() }
+        }
+
+        /// Adds a port to the list, simulating a device being connected.
+        pub fn add_port(&mut self, port: Port) {
+            self.ports.push(port);
+        }
+    }
+
+    impl Scanner for MockScanner {
+        fn scan(&self) -> Vec<Port> {
+            self.ports.clone()
+        }
+    }
+}
@@ -0,0 +1,320 @@
+//! # Server Module
+//!
+//! This module streams the sweeps of a capture to network clients, like the [`Client`](crate::client::Client) of
+//! another host. Each client that connects is added to the [`SinkRegistry`] of the capture as a
+//! [`BinarySink`](crate::formats::binary::BinarySink), so clients come and go without restarting the sweeps.
+//!
+//! With a [`Token`], clients must authenticate before receiving any sweep, see the [`auth`](crate::auth) module. Each
+//! client authenticates in a thread of its own, so a client that doesn't send its token doesn't hold back the others.
+//!
+//! The sweeps are written to each client from a thread of its own, through a [`Queued`] sink of
+//! `CLIENT_QUEUE` sweeps, so a slow client only loses sweeps, without stalling the capture or the other clients.
+//!
+//! The device runs a single capture, so the clients share it according to an [`Arbitration`] policy: either all of
+//! them follow the sweeps, or one client at a time owns the stream while the others wait in line for it to disconnect.
+//!
+//! With the `tls` feature, `listen_tls` encrypts the connections, see the `tls` module. The TLS
+//! handshake also runs in the thread of each client, before its token.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use sa430::auth::Token;
+//! use sa430::client::Client;
+//! use sa430::server::{listen, Arbitration};
+//! use sa430::sink::SinkRegistry;
+//!
+//! let sinks = SinkRegistry::new();
+//! let token = Token::new("lab-secret").unwrap();
+//! let address = listen("127.0.0.1:0", Some(token.clone()), Arbitration::Shared, sinks.clone()).unwrap();
+//!
+//! let client = Client::connect_with_token(&address.to_string(), &token);
+//! assert!(client.is_ok());
+//! ```
+
+use std::{
+    collections::VecDeque,
+    error::Error,
+    io::{Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, Mutex, PoisonError},
+    thread,
+    time::Duration,
+};
+
+use crate::auth::{accept_token_over, Token};
+use crate::formats::binary::BinarySink;
+use crate::sink::{Queued, SinkId, SinkRegistry};
+#[cfg(feature = "tls")]
+use crate::tls::TlsAcceptor;
+
+/// Time a client has to send its token, and to receive each sweep before it is dropped.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Number of sweeps queued for each client, the next ones are dropped until the client catches up.
+pub const CLIENT_QUEUE: usize = 8;
+
+/// Interval to check if the owner of an exclusive stream disconnected.
+const OWNER_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How the clients share the capture.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Arbitration {
+    /// Every client follows the sweeps.
+    #[default]
+    Shared,
+
+    /// The first client owns the stream, the next ones are queued and receive the sweeps, in order, once the clients
+    /// before them disconnect.
+    Exclusive,
+}
+
+/// Clients of an exclusive stream.
+#[derive(Default)]
+struct Session {
+    owner: Option<SinkId>,
+    queue: VecDeque<ClientStream>,
+}
+
+/// Connection of an authenticated client, over TCP or TLS.
+type ClientStream = Box<dyn Write + Send>;
+
+/// Listens for clients at `address`, in a background thread, adding them to `sinks` according to `arbitration` once
+/// authenticated with `token`, if any. Returns the bound address, to find the port when listening on port 0.
+pub fn listen(
+    address: &str,
+    token: Option<Token>,
+    arbitration: Arbitration,
+    sinks: SinkRegistry,
+) -> Result<SocketAddr, Box<dyn Error>> {
+    listen_with(address, Ok, token, arbitration, sinks)
+}
+
+/// Listens for clients at `address` like [`listen`], encrypting their connections with the certificate of `acceptor`.
+#[cfg(feature = "tls")]
+pub fn listen_tls(
+    address: &str,
+    acceptor: TlsAcceptor,
+    token: Option<Token>,
+    arbitration: Arbitration,
+    sinks: SinkRegistry,
+) -> Result<SocketAddr, Box<dyn Error>> {
+    listen_with(
+        address,
+        move |stream| acceptor.accept(stream),
+        token,
+        arbitration,
+        sinks,
+    )
+}
+
+/// Listens for clients at `address`, securing their connections with `secure` before authenticating them.
+fn listen_with<S, F>(
+    address: &str,
+    secure: F,
+    token: Option<Token>,
+    arbitration: Arbitration,
+    sinks: SinkRegistry,
+) -> Result<SocketAddr, Box<dyn Error>>
+where
+    S: Read + Write + Send + 'static,
+    F: Fn(TcpStream) -> Result<S, Box<dyn Error>> + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind(address)?;
+    let local_address = listener.local_addr()?;
+    let session = Arc::new(Mutex::new(Session::default()));
+    let secure = Arc::new(secure);
+
+    if arbitration == Arbitration::Exclusive {
+        let (session, sinks) = (session.clone(), sinks.clone());
+        thread::spawn(move || loop {
+            thread::sleep(OWNER_POLL_INTERVAL);
+            promote(&mut session.lock().unwrap_or_else(PoisonError::into_inner), &sinks);
+        });
+    }
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let (secure, token, session, sinks) = (secure.clone(), token.clone(), session.clone(), sinks.clone());
+            thread::spawn(move || {
+                // A client failing to authenticate only closes its own connection.
+                if let Ok(stream) = accept(stream, &*secure, token.as_ref()) {
+                    let mut session = session.lock().unwrap_or_else(PoisonError::into_inner);
+                    admit(stream, arbitration, &mut session, &sinks);
+                }
+            });
+        }
+    });
+    Ok(local_address)
+}
+
+/// Configures the connection of a client, secures it and authenticates it.
+fn accept<S: Read + Write + Send + 'static>(
+    stream: TcpStream,
+    secure: &dyn Fn(TcpStream) -> Result<S, Box<dyn Error>>,
+    token: Option<&Token>,
+) -> Result<ClientStream, Box<dyn Error>> {
+    stream.set_nodelay(true)?;
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut stream = secure(stream)?;
+    if let Some(token) = token {
+        accept_token_over(&mut stream, token)?;
+    }
+    Ok(Box::new(stream))
+}
+
+/// Adds an authenticated client to the sinks, or to the queue if another one owns an exclusive stream.
+fn admit(stream: ClientStream, arbitration: Arbitration, session: &mut Session, sinks: &SinkRegistry) {
+    match arbitration {
+        Arbitration::Shared => {
+            sinks.add(client_sink(stream));
+        }
+        Arbitration::Exclusive => {
+            session.queue.push_back(stream);
+            promote(session, sinks);
+        }
+    }
+}
+
+/// Gives an exclusive stream to the next client in line, once its owner is removed from the sinks.
+fn promote(session: &mut Session, sinks: &SinkRegistry) {
+    if session.owner.is_some_and(|owner| sinks.contains(owner)) {
+        return;
+    }
+    session.owner = session.queue.pop_front().map(|stream| sinks.add(client_sink(stream)));
+}
+
+/// Returns the sink writing the sweeps to a client, from a thread of its own.
+fn client_sink(stream: ClientStream) -> Box<Queued> {
+    Box::new(Queued::new(BinarySink::new(stream), CLIENT_QUEUE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Instant;
+
+    use crate::auth::AuthError;
+    use crate::client::Client;
+    use crate::sink::{SweepRecord, SweepSource};
+    use crate::spectrum::Spectrum;
+
+    fn a_record(sequence: u64) -> SweepRecord {
+        SweepRecord::new(sequence, Spectrum::from_sweep(868.0e6, 0.1e6, &[-90.0]))
+    }
+
+    /// Returns the server side of the connections of `count` clients.
+    fn some_connections(count: usize) -> (Vec<Client<TcpStream>>, Vec<TcpStream>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        (0..count)
+            .map(|_| {
+                let client = Client::connect(&address).unwrap();
+                (client, listener.accept().unwrap().0)
+            })
+            .unzip()
+    }
+
+    #[test]
+    fn given_authenticated_clients_when_publish_then_stream_the_sweeps_to_them() {
+        let sinks = SinkRegistry::new();
+        let token = Token::new("lab-secret").unwrap();
+        let address = listen("127.0.0.1:0", Some(token.clone()), Arbitration::Shared, sinks.clone())
+            .unwrap()
+            .to_string();
+
+        let mut client = Client::connect_with_token(&address, &token).unwrap();
+        let denied = Client::connect_with_token(&address, &Token::new("guess").unwrap());
+        while sinks.is_empty() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        sinks.publish(&a_record(7));
+        sinks.close();
+
+        assert_eq!(client.next_sweep().unwrap().unwrap().sequence, 7);
+        assert!(client.next_sweep().unwrap().is_none());
+        assert_eq!(denied.err().unwrap().downcast_ref(), Some(&AuthError::Denied));
+        assert_eq!(sinks.len(), 0);
+    }
+
+    #[test]
+    fn given_a_silent_client_when_another_client_connects_then_authenticate_it_without_waiting() {
+        let sinks = SinkRegistry::new();
+        let token = Token::new("lab-secret").unwrap();
+        let address = listen("127.0.0.1:0", Some(token.clone()), Arbitration::Shared, sinks.clone())
+            .unwrap()
+            .to_string();
+        let _silent = TcpStream::connect(&address).unwrap();
+        let start = Instant::now();
+
+        let client = Client::connect_with_token(&address, &token);
+
+        assert!(client.is_ok());
+        assert!(start.elapsed() < CLIENT_TIMEOUT);
+    }
+
+    #[cfg(feature = "tls")]
+    #[test]
+    fn given_a_tls_server_when_publish_then_stream_the_sweeps_to_its_authenticated_clients() {
+        use crate::tls::{fixtures, TlsConnector};
+
+        let sinks = SinkRegistry::new();
+        let token = Token::new("lab-secret").unwrap();
+        let acceptor = TlsAcceptor::from_pem(fixtures::SERVER_CERTIFICATE, fixtures::SERVER_KEY).unwrap();
+        let port = listen_tls(
+            "127.0.0.1:0",
+            acceptor,
+            Some(token.clone()),
+            Arbitration::Shared,
+            sinks.clone(),
+        )
+        .unwrap()
+        .port();
+        let (address, connector) = (
+            format!("localhost:{}", port),
+            TlsConnector::from_pem(fixtures::CA).unwrap(),
+        );
+
+        let mut client = Client::connect_tls_with_token(&address, &connector, &token).unwrap();
+        let denied = Client::connect_tls_with_token(&address, &connector, &Token::new("guess").unwrap());
+        let plain = Client::connect_with_token(&address, &token);
+        while sinks.is_empty() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        sinks.publish(&a_record(7));
+        sinks.close();
+
+        assert_eq!(client.next_sweep().unwrap().unwrap().sequence, 7);
+        assert!(client.next_sweep().unwrap().is_none());
+        assert_eq!(denied.err().unwrap().downcast_ref(), Some(&AuthError::Denied));
+        assert!(plain.is_err());
+        assert_eq!(sinks.len(), 0);
+    }
+
+    #[test]
+    fn given_an_exclusive_stream_when_the_owner_leaves_then_stream_to_the_next_client() {
+        let sinks = SinkRegistry::new();
+        let mut session = Session::default();
+        let (mut clients, streams) = some_connections(2);
+
+        for stream in streams {
+            admit(Box::new(stream), Arbitration::Exclusive, &mut session, &sinks);
+        }
+        assert_eq!((sinks.len(), session.queue.len()), (1, 1));
+        sinks.publish(&a_record(0));
+
+        sinks.remove(session.owner.unwrap());
+        promote(&mut session, &sinks);
+        assert_eq!((sinks.len(), session.queue.len()), (1, 0));
+        sinks.publish(&a_record(1));
+        sinks.close();
+
+        let sequences: Vec<Vec<u64>> = clients
+            .iter_mut()
+            .map(|client| std::iter::from_fn(|| client.next_sweep().unwrap().map(|record| record.sequence)).collect())
+            .collect();
+        assert_eq!(sequences, vec![vec![0], vec![1]]);
+    }
+}
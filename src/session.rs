@@ -0,0 +1,381 @@
+//! # Session Module
+//!
+//! This module seals the recordings of a capture session with an integrity manifest, the SHA-256 digest of each
+//! recording, so truncated or corrupted recordings are detected after copying or uploading the session.
+//!
+//! The manifest is stored with the recordings as [`MANIFEST_NAME`], in the format of `sha256sum`, so it can also be
+//! checked with `sha256sum -c MANIFEST.sha256` on machines without the `sa430` utility.
+//!
+//! Sealing also stores the [`SessionInfo`] of the session as [`METADATA_NAME`]: the device, band and tags given by the
+//! user and the time range of the recordings, so sessions can be searched without reading their recordings.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use std::io::Write;
+//!
+//! use sa430::session::{seal, verify, SessionInfo, Status};
+//! use sa430::storage::{LocalStorage, Storage};
+//!
+//! let dir = std::env::temp_dir().join(format!("sa430-session-doc-{}", std::process::id()));
+//! let storage = LocalStorage::new(&dir);
+//! let mut recording = storage.create("eu868.csv").unwrap();
+//! recording.write_all(b"868.0,-90\n").unwrap();
+//! recording.flush().unwrap();
+//! drop(recording);
+//!
+//! let info = SessionInfo {
+//!     band: Some("eu868".to_string()),
+//!     tags: vec!["rooftop".to_string()],
+//!     ..Default::default()
+//! };
+//! seal(&storage, &info).unwrap();
+//! let checks = verify(&storage).unwrap();
+//!
+//! assert!(checks.iter().all(|check| check.status == Status::Ok));
+//! assert_eq!(SessionInfo::read(&storage).unwrap().tags, vec!["rooftop"]);
+//! # std::fs::remove_dir_all(&dir).unwrap();
+//! ```
+
+use std::{
+    error::Error,
+    fmt,
+    io::{Read, Write},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::sha256::{to_hex, Sha256};
+use crate::storage::Storage;
+
+/// Name of the manifest recording.
+pub const MANIFEST_NAME: &str = "MANIFEST.sha256";
+
+/// Name of the session metadata recording.
+pub const METADATA_NAME: &str = "SESSION.meta";
+
+/// Metadata of a session, stored as `key: value` lines, with the times in Unix seconds.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionInfo {
+    /// Serial number of the device that recorded the session.
+    pub device: Option<String>,
+
+    /// Band of the session, like a band preset name.
+    pub band: Option<String>,
+
+    /// When the first recording was written.
+    pub start: Option<SystemTime>,
+
+    /// When the last recording was written.
+    pub end: Option<SystemTime>,
+
+    /// Free form labels, like the route or site of a drive test, or `key=value` pairs like `site=roof`.
+    pub tags: Vec<String>,
+
+    /// Free form note, like a change of the setup.
+    pub note: Option<String>,
+}
+
+impl SessionInfo {
+    /// Reads the metadata of a sealed session.
+    pub fn read(storage: &dyn Storage) -> Result<Self, Box<dyn Error>> {
+        let mut text = String::new();
+        storage.open(METADATA_NAME)?.read_to_string(&mut text)?;
+        SessionInfo::parse(&text)
+    }
+
+    /// Parses the metadata, ignoring unknown keys.
+    pub fn parse(text: &str) -> Result<Self, Box<dyn Error>> {
+        let mut info = SessionInfo::default();
+        for (number, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let (key, value) = line.split_once(':').ok_or(SessionError::InvalidMetadata(number + 1))?;
+            let value = value.trim();
+            let time = || {
+                value
+                    .parse()
+                    .map(|seconds| UNIX_EPOCH + Duration::from_secs(seconds))
+                    .map_err(|_| SessionError::InvalidMetadata(number + 1))
+            };
+            match key.trim() {
+                "device" => info.device = Some(value.to_string()),
+                "band" => info.band = Some(value.to_string()),
+                "start" => info.start = Some(time()?),
+                "end" => info.end = Some(time()?),
+                "tags" => info.tags = value.split(',').map(|tag| tag.trim().to_string()).collect(),
+                "note" => info.note = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        info.tags.retain(|tag| !tag.is_empty());
+        Ok(info)
+    }
+
+    /// Returns how long the session took, from its first to its last recording.
+    pub fn duration(&self) -> Option<Duration> {
+        self.end?.duration_since(self.start?).ok()
+    }
+}
+
+impl fmt::Display for SessionInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let seconds = |time: SystemTime| time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        if let Some(device) = &self.device {
+            writeln!(f, "device: {}", device)?;
+        }
+        if let Some(band) = &self.band {
+            writeln!(f, "band: {}", band)?;
+        }
+        if let Some(start) = self.start {
+            writeln!(f, "start: {}", seconds(start))?;
+        }
+        if let Some(end) = self.end {
+            writeln!(f, "end: {}", seconds(end))?;
+        }
+        if !self.tags.is_empty() {
+            writeln!(f, "tags: {}", self.tags.join(", "))?;
+        }
+        if let Some(note) = &self.note {
+            writeln!(f, "note: {}", note.replace('\n', " "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Result of checking a recording against the manifest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// The recording matches the manifest.
+    Ok,
+
+    /// The recording changed, was truncated or corrupted.
+    Mismatch,
+
+    /// The recording is in the manifest, but not in the session.
+    Missing,
+
+    /// The recording is in the session, but not in the manifest, like one added after sealing.
+    Unlisted,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::Ok => write!(f, "ok"),
+            Status::Mismatch => write!(f, "mismatch"),
+            Status::Missing => write!(f, "missing"),
+            Status::Unlisted => write!(f, "unlisted"),
+        }
+    }
+}
+
+/// A recording checked against the manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+}
+
+/// Writes the metadata and the manifest of the recordings in `storage`, replacing the previous ones. Returns the number
+/// of recordings.
+///
+/// The time range of `info`, when not set, is the one of the recordings, if the storage keeps it. The tags and notes of
+/// the CSV recordings, written by `capture` as `# tag: <tag>` and `# note: <note>` comments, are added to the ones of
+/// `info`.
+pub fn seal(storage: &dyn Storage, info: &SessionInfo) -> Result<usize, Box<dyn Error>> {
+    let mut info = info.clone();
+    let mut notes: Vec<String> = info.note.take().into_iter().collect();
+    let mut times = Vec::new();
+    for name in recordings(storage)?.iter().filter(|name| *name != METADATA_NAME) {
+        times.extend(storage.modified(name)?);
+        if name.to_ascii_lowercase().ends_with(".csv") {
+            read_labels(storage, name, &mut info.tags, &mut notes)?;
+        }
+    }
+    info.note = (!notes.is_empty()).then(|| notes.join("; "));
+    info.start = info.start.or(times.iter().min().copied());
+    info.end = info.end.or(times.iter().max().copied());
+
+    let mut output = storage.create(METADATA_NAME)?;
+    output.write_all(info.to_string().as_bytes())?;
+    output.flush()?;
+    drop(output);
+
+    let names = recordings(storage)?;
+    let mut manifest = String::new();
+    for name in &names {
+        manifest.push_str(&format!("{}  {}\n", digest(storage, name)?, name));
+    }
+
+    let mut output = storage.create(MANIFEST_NAME)?;
+    output.write_all(manifest.as_bytes())?;
+    output.flush()?;
+    Ok(names.len() - 1)
+}
+
+/// Checks the recordings in `storage` against its manifest, in the order of the manifest, followed by the unlisted
+/// recordings.
+pub fn verify(storage: &dyn Storage) -> Result<Vec<Check>, Box<dyn Error>> {
+    let mut manifest = String::new();
+    storage
+        .open(MANIFEST_NAME)
+        .map_err(|error| format!("Unable to read the manifest of {}: {}", storage.location(), error))?
+        .read_to_string(&mut manifest)?;
+
+    let mut names = recordings(storage)?;
+    let mut checks = Vec::new();
+    for (number, line) in manifest.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+        let (expected, name) = line.split_once("  ").ok_or(SessionError::InvalidManifest(number + 1))?;
+        let status = match names.iter().position(|listed| listed == name) {
+            Some(index) => {
+                names.remove(index);
+                match digest(storage, name)? == expected.to_ascii_lowercase() {
+                    true => Status::Ok,
+                    false => Status::Mismatch,
+                }
+            }
+            None => Status::Missing,
+        };
+        checks.push(Check {
+            name: name.to_string(),
+            status,
+        });
+    }
+
+    checks.extend(names.into_iter().map(|name| Check {
+        name,
+        status: Status::Unlisted,
+    }));
+    Ok(checks)
+}
+
+/// Adds the tags and notes in the `#` comments of a CSV recording, skipping the repeated ones.
+fn read_labels(
+    storage: &dyn Storage,
+    name: &str,
+    tags: &mut Vec<String>,
+    notes: &mut Vec<String>,
+) -> Result<(), Box<dyn Error>> {
+    let mut text = String::new();
+    storage.open(name)?.read_to_string(&mut text)?;
+    for comment in text.lines().filter_map(|line| line.strip_prefix('#')) {
+        let (labels, value) = match comment.split_once(':') {
+            Some((key, value)) if key.trim() == "tag" => (&mut *tags, value.trim()),
+            Some((key, value)) if key.trim() == "note" => (&mut *notes, value.trim()),
+            _ => continue,
+        };
+        if !value.is_empty() && !labels.iter().any(|label| label == value) {
+            labels.push(value.to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Returns the names of the recordings of a session, without the manifest.
+fn recordings(storage: &dyn Storage) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut names = storage.list()?;
+    names.retain(|name| name != MANIFEST_NAME);
+    Ok(names)
+}
+
+/// Returns the SHA-256 digest of a recording, as hex.
+fn digest(storage: &dyn Storage, name: &str) -> Result<String, Box<dyn Error>> {
+    let mut input = storage.open(name)?;
+    let mut sha256 = Sha256::new();
+    let mut buffer = [0; 8192];
+    loop {
+        match input.read(&mut buffer)? {
+            0 => return Ok(to_hex(&sha256.finalize())),
+            count => sha256.update(&buffer[..count]),
+        }
+    }
+}
+
+/// Session errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionError {
+    /// A line of the manifest isn't a digest and a name, separated by two spaces (line number).
+    InvalidManifest(usize),
+
+    /// A line of the metadata isn't a `key: value` pair, or a time isn't in Unix seconds (line number).
+    InvalidMetadata(usize),
+}
+
+impl Error for SessionError {}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::InvalidManifest(line) => write!(f, "Invalid manifest line {}", line),
+            SessionError::InvalidMetadata(line) => write!(f, "Invalid session metadata line {}", line),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    use crate::storage::LocalStorage;
+
+    #[test]
+    fn given_a_sealed_session_when_recordings_change_then_report_them() {
+        let dir = std::env::temp_dir().join(format!("sa430-session-verify-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["a.csv", "b.csv", "c.csv"] {
+            fs::write(
+                dir.join(name),
+                "# tag: site=roof\n# note: new antenna\n868.0,-90\n868.1,-80\n",
+            )
+            .unwrap();
+        }
+        let storage = LocalStorage::new(&dir);
+
+        assert_eq!(seal(&storage, &SessionInfo::default()).unwrap(), 3);
+        let info = SessionInfo::read(&storage).unwrap();
+        assert_eq!(
+            (info.tags, info.note),
+            (vec!["site=roof".to_string()], Some("new antenna".to_string()))
+        );
+        fs::write(dir.join("a.csv"), "868.0,-90\n868.1").unwrap();
+        fs::remove_file(dir.join("b.csv")).unwrap();
+        fs::write(dir.join("d.csv"), "868.0,-90\n").unwrap();
+        let checks = verify(&storage).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let statuses: Vec<(&str, Status)> = checks.iter().map(|check| (check.name.as_str(), check.status)).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("SESSION.meta", Status::Ok),
+                ("a.csv", Status::Mismatch),
+                ("b.csv", Status::Missing),
+                ("c.csv", Status::Ok),
+                ("d.csv", Status::Unlisted)
+            ]
+        );
+    }
+
+    #[test]
+    fn given_metadata_when_parse_then_read_it_back_from_its_text() {
+        let info = SessionInfo {
+            device: Some("1234".to_string()),
+            band: Some("eu868".to_string()),
+            start: Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            end: Some(UNIX_EPOCH + Duration::from_secs(1_700_003_600)),
+            tags: vec!["north".to_string(), "site=roof".to_string()],
+            note: Some("new antenna".to_string()),
+        };
+
+        let text = info.to_string();
+
+        assert_eq!(
+            text,
+            "device: 1234\nband: eu868\nstart: 1700000000\nend: 1700003600\ntags: north, site=roof\n\
+             note: new antenna\n"
+        );
+        assert_eq!(SessionInfo::parse(&text).unwrap(), info);
+        assert_eq!(info.duration(), Some(Duration::from_secs(3600)));
+        assert!(SessionInfo::parse("start: yesterday").is_err());
+    }
+}
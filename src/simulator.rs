@@ -0,0 +1,1325 @@
+//! # Simulator Module
+//!
+//! This module provides a software model of a SA430 device that implements the `Channel` trait, so the whole
+//! protocol stack can be exercised without hardware. Frames written to the simulator are decoded and answered the
+//! same way the firmware does: an ACK followed by the response data, or an error frame if the command is not
+//! supported.
+//!
+//! The simulated device reports the same identification and calibration data as the fixtures in
+//! [frame::fixture](../frame/fixture/index.html).
+//!
+//! Faults can be injected into the replies with `set_fault_rate`, to check that the host recovers from a lossy or
+//! noisy link.
+//!
+//! The faults and the noise of the spectra are drawn from a generator seeded with `with_seed`, [`DEFAULT_SEED`] by
+//! default, so a failing run can be reproduced with the same seed.
+//!
+//! `measure` generates realistic spectra for the DSP and alerting features: a random noise floor following the
+//! [noise](../noise/index.html) model, from the resolution bandwidth and the gain of the reference level in the
+//! calibration, and the carriers of a `Scenario`, clipped at the reference level like a saturated receiver. The same
+//! spectra are returned to `GetSpecNoInit`, as the RSSI samples the firmware sends, for the sweep set up with the
+//! `SetFStart`, `SetFStop`, `SetFStep`, `SetRbw`, `SetIf` and `SetGain` requests and initialized with an empty
+//! `InitParameter`. A request with a payload of the wrong size is answered with `WrongCmdLength`, and a measurement
+//! without an initialized sweep, or with a register set after `InitParameter`, with `PllNotSettled`. Scenarios are
+//! loaded from a CSV file with one carrier per line:
+//!
+//! ```text
+//! # frequency_mhz,power_dbm,bandwidth_khz
+//! 868.1,-60,125
+//! ```
+//!
+//! Scenarios scripted over time are loaded from TOML files instead, with a `[[carrier]]` table per carrier and a
+//! `[[fault]]` table per period of faults, both active from `start_s` to `stop_s`, in seconds of simulated time,
+//! advanced with `Simulator::advance`. The faults are random reply faults at a `rate`, or an `unresponsive` device:
+//!
+//! ```toml
+//! [[carrier]]
+//! frequency_mhz = 868.1
+//! power_dbm = -60
+//! bandwidth_khz = 125
+//! start_s = 10
+//! stop_s = 20
+//!
+//! [[fault]]
+//! kind = "unresponsive"
+//! start_s = 30
+//! stop_s = 35
+//! ```
+//!
+//! Only this subset of TOML is read: tables of arrays, numbers and strings, and comments.
+//!
+//! This module is only available with the `simulator` feature.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use sa430::device::Sa430;
+//! use sa430::simulator::Simulator;
+//!
+//! let mut device = Sa430::new(Box::new(Simulator::new()));
+//! assert_eq!(device.serial_number().unwrap(), 0x0908);
+//! ```
+//!
+//! ```rust
+//! use sa430::frequency::Frequency;
+//! use sa430::simulator::{Scenario, Simulator};
+//!
+//! let mut simulator = Simulator::new();
+//! simulator.set_scenario(Scenario::read_csv("868.1,-60,125".as_bytes()).unwrap());
+//!
+//! let (fstart, fstop) = (Frequency::from_mhz(868.0), Frequency::from_mhz(868.2));
+//! let spectrum = simulator
+//!     .measure(fstart, fstop, Frequency::from_khz(10.0), -35, Frequency::from_khz(58.0))
+//!     .unwrap();
+//! let (frequency, power) = *spectrum.iter().max_by(|a, b| a.1.total_cmp(&b.1)).unwrap();
+//! assert!((frequency - 868.1e6).abs() < 100.0e3 && power > -75.0);
+//! ```
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fmt::{self, Display},
+    io::{self, BufRead},
+    time::Duration,
+};
+
+use super::channel::Channel;
+use super::correction::{CorrectionError, SweepCorrection};
+use super::device::{Calibration, FLASH_CALIBRATION_ADDR, FLASH_CALIBRATION_SIZE};
+use super::frame::*;
+use super::frequency::Frequency;
+use super::noise::noise_floor_at;
+use super::power::Dbm;
+use super::spectrum::Spectrum;
+use super::sweep::{SweepConfig, SweepRegisters, NOMINAL_XTAL_HZ};
+
+/// Seed of the random generator of a new simulator.
+pub const DEFAULT_SEED: u64 = 1;
+
+/// Start address of the simulated flash memory, matches the program header address.
+const FLASH_START_ADDR: u16 = 0xD400;
+
+/// Number of samples sent in each `GetSpecNoInit` data frame.
+const SAMPLES_PER_FRAME: usize = 64;
+
+/// Faults injected into the replies of the simulator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// The reply frame is lost.
+    DropReply,
+
+    /// The CRC of the reply frame is corrupted.
+    CorruptCrc,
+
+    /// Noise bytes are received before the reply frame.
+    Noise,
+}
+
+/// A signal received by the simulator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Carrier {
+    /// Center frequency.
+    pub frequency: Frequency,
+
+    /// Total power, in dBm.
+    pub power: f64,
+
+    /// Occupied bandwidth, 0 for a continuous wave.
+    pub bandwidth: Frequency,
+}
+
+/// Period of simulated time when a carrier or a fault is active.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Schedule {
+    /// Time it starts, from the start of the simulation.
+    pub start: Duration,
+
+    /// Time it stops, `None` to last until the end.
+    pub stop: Option<Duration>,
+}
+
+impl Schedule {
+    /// Returns true if active at `time`, from its start included to its stop excluded.
+    pub fn is_active(&self, time: Duration) -> bool {
+        time >= self.start && self.stop.is_none_or(|stop| time < stop)
+    }
+}
+
+/// Faults of the simulated device scheduled by a scenario.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScenarioFault {
+    /// Faults injected into the replies with a probability, from 0 to 1, like with `Simulator::set_fault_rate`.
+    Faults(f64),
+
+    /// The device ignores the requests, like a hung firmware.
+    Unresponsive,
+}
+
+/// Carriers received by the simulator and faults of the device, over time.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Scenario {
+    carriers: Vec<(Carrier, Schedule)>,
+    faults: Vec<(ScenarioFault, Schedule)>,
+}
+
+/// Errors when loading a scenario from a CSV file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScenarioError {
+    /// The line has fewer than the frequency and power columns.
+    MissingColumns(usize),
+
+    /// A column or key of the line is not a number, or is negative for the bandwidth, times and rate.
+    InvalidNumber(usize, &'static str),
+
+    /// The TOML line is not a table header, a key and value or a comment.
+    InvalidSyntax(usize),
+
+    /// The TOML table is not `carrier` or `fault`.
+    UnknownTable(usize, String),
+
+    /// The TOML table starting at the line lacks a key.
+    MissingKey(usize, &'static str),
+
+    /// The kind of the fault is not `faults` or `unresponsive`.
+    UnknownFault(usize, String),
+}
+
+impl Error for ScenarioError {}
+
+impl Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioError::MissingColumns(line) => {
+                write!(f, "Line {}: expected frequency_mhz and power_dbm columns", line)
+            }
+            ScenarioError::InvalidNumber(line, column) => write!(f, "Line {}: invalid {}", line, column),
+            ScenarioError::InvalidSyntax(line) => write!(f, "Line {}: expected a [[table]] or a key = value", line),
+            ScenarioError::UnknownTable(line, name) => {
+                write!(f, "Line {}: unknown table {}, expected carrier or fault", line, name)
+            }
+            ScenarioError::MissingKey(line, key) => write!(f, "Line {}: missing {}", line, key),
+            ScenarioError::UnknownFault(line, kind) => {
+                write!(
+                    f,
+                    "Line {}: unknown fault {}, expected faults or unresponsive",
+                    line, kind
+                )
+            }
+        }
+    }
+}
+
+impl Scenario {
+    /// Creates a scenario without carriers, where only noise is received.
+    pub fn new() -> Self {
+        Scenario::default()
+    }
+
+    /// Adds a carrier received all the time.
+    pub fn add(&mut self, carrier: Carrier) {
+        self.add_scheduled(carrier, Schedule::default());
+    }
+
+    /// Adds a carrier received during the schedule.
+    pub fn add_scheduled(&mut self, carrier: Carrier, schedule: Schedule) {
+        self.carriers.push((carrier, schedule));
+    }
+
+    /// Adds faults of the device during the schedule.
+    pub fn add_fault(&mut self, fault: ScenarioFault, schedule: Schedule) {
+        self.faults.push((fault, schedule));
+    }
+
+    /// Returns the carriers, with when they are received.
+    pub fn carriers(&self) -> &[(Carrier, Schedule)] {
+        &self.carriers
+    }
+
+    /// Returns the faults, with when they happen.
+    pub fn faults(&self) -> &[(ScenarioFault, Schedule)] {
+        &self.faults
+    }
+
+    /// Loads a scenario from a TOML file of `[[carrier]]` and `[[fault]]` tables.
+    pub fn read_toml<R: BufRead>(reader: R) -> Result<Self, Box<dyn Error>> {
+        let mut scenario = Scenario::new();
+        for table in read_toml_tables(reader)? {
+            let schedule = Schedule {
+                start: table.duration("start_s")?.unwrap_or_default(),
+                stop: table.duration("stop_s")?,
+            };
+            match table.name.as_str() {
+                "carrier" => {
+                    let bandwidth = table.number("bandwidth_khz")?.unwrap_or(0.0);
+                    if bandwidth < 0.0 {
+                        return Err(ScenarioError::InvalidNumber(table.line, "bandwidth_khz").into());
+                    }
+                    let carrier = Carrier {
+                        frequency: Frequency::from_mhz(table.required_number("frequency_mhz")?),
+                        power: table.required_number("power_dbm")?,
+                        bandwidth: Frequency::from_khz(bandwidth),
+                    };
+                    scenario.add_scheduled(carrier, schedule);
+                }
+                "fault" => {
+                    let fault = match table.string("kind").unwrap_or("faults") {
+                        "faults" => match table.required_number("rate")? {
+                            rate if (0.0..=1.0).contains(&rate) => ScenarioFault::Faults(rate),
+                            _ => return Err(ScenarioError::InvalidNumber(table.line, "rate").into()),
+                        },
+                        "unresponsive" => ScenarioFault::Unresponsive,
+                        kind => return Err(ScenarioError::UnknownFault(table.line, kind.to_string()).into()),
+                    };
+                    scenario.add_fault(fault, schedule);
+                }
+                name => return Err(ScenarioError::UnknownTable(table.line, name.to_string()).into()),
+            }
+        }
+        Ok(scenario)
+    }
+
+    /// Returns the highest rate of the reply faults scheduled at `time`, 0 if none.
+    fn fault_rate_at(&self, time: Duration) -> f64 {
+        self.faults
+            .iter()
+            .filter(|(_, schedule)| schedule.is_active(time))
+            .map(|(fault, _)| match fault {
+                ScenarioFault::Faults(rate) => *rate,
+                ScenarioFault::Unresponsive => 0.0,
+            })
+            .fold(0.0, f64::max)
+    }
+
+    /// Returns true if the device is scheduled to be unresponsive at `time`.
+    fn is_unresponsive_at(&self, time: Duration) -> bool {
+        self.faults
+            .iter()
+            .any(|(fault, schedule)| *fault == ScenarioFault::Unresponsive && schedule.is_active(time))
+    }
+
+    /// Loads a scenario from CSV lines of `frequency_mhz,power_dbm[,bandwidth_khz]`, skipping empty lines and `#`
+    /// comments.
+    pub fn read_csv<R: BufRead>(reader: R) -> Result<Self, Box<dyn Error>> {
+        let mut scenario = Scenario::new();
+        for (index, line) in reader.lines().enumerate() {
+            if let Some(carrier) = parse_carrier(index + 1, &line?)? {
+                scenario.add(carrier);
+            }
+        }
+        Ok(scenario)
+    }
+
+    /// Returns the power of the carriers received at `time` measured at `frequency` with a resolution bandwidth of
+    /// `rbw`, in mW.
+    fn power_at(&self, time: Duration, frequency: Frequency, rbw: Frequency) -> f64 {
+        self.carriers
+            .iter()
+            .filter(|(_, schedule)| schedule.is_active(time))
+            .map(|(carrier, _)| {
+                // The carrier power is spread over its bandwidth, and the resolution filter rolls off as a Gaussian,
+                // 3 dB down at half the resolution bandwidth from the edges of the carrier.
+                let spread = 10.0 * (carrier.bandwidth / rbw).max(1.0).log10();
+                let outside = ((frequency - carrier.frequency).hz().abs() - carrier.bandwidth.hz() / 2.0).max(0.0);
+                let rolloff = 12.0 * (outside / rbw.hz()).powi(2);
+                Dbm::new(carrier.power - spread - rolloff).mw()
+            })
+            .sum()
+    }
+}
+
+fn parse_carrier(number: usize, line: &str) -> Result<Option<Carrier>, ScenarioError> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+
+    let columns: Vec<&str> = line.split(',').map(str::trim).collect();
+    if columns.len() < 2 {
+        return Err(ScenarioError::MissingColumns(number));
+    }
+    let number_at = |index: usize, name: &'static str| -> Result<f64, ScenarioError> {
+        columns[index]
+            .parse::<f64>()
+            .ok()
+            .filter(|value| value.is_finite())
+            .ok_or(ScenarioError::InvalidNumber(number, name))
+    };
+    let bandwidth = match columns.get(2) {
+        Some(_) => number_at(2, "bandwidth_khz")?,
+        None => 0.0,
+    };
+    if bandwidth < 0.0 {
+        return Err(ScenarioError::InvalidNumber(number, "bandwidth_khz"));
+    }
+
+    Ok(Some(Carrier {
+        frequency: Frequency::from_mhz(number_at(0, "frequency_mhz")?),
+        power: number_at(1, "power_dbm")?,
+        bandwidth: Frequency::from_khz(bandwidth),
+    }))
+}
+
+/// A `[[name]]` table of a TOML file, with the line of its header and its keys, each with its value and line.
+struct TomlTable {
+    name: String,
+    line: usize,
+    values: Vec<(String, TomlValue, usize)>,
+}
+
+enum TomlValue {
+    Number(f64),
+    String(String),
+}
+
+impl TomlTable {
+    fn value(&self, key: &str) -> Option<(&TomlValue, usize)> {
+        self.values
+            .iter()
+            .find(|(name, _, _)| name == key)
+            .map(|(_, value, line)| (value, *line))
+    }
+
+    fn number(&self, key: &'static str) -> Result<Option<f64>, ScenarioError> {
+        match self.value(key) {
+            Some((TomlValue::Number(number), _)) => Ok(Some(*number)),
+            Some((TomlValue::String(_), line)) => Err(ScenarioError::InvalidNumber(line, key)),
+            None => Ok(None),
+        }
+    }
+
+    fn required_number(&self, key: &'static str) -> Result<f64, ScenarioError> {
+        self.number(key)?.ok_or(ScenarioError::MissingKey(self.line, key))
+    }
+
+    fn duration(&self, key: &'static str) -> Result<Option<Duration>, ScenarioError> {
+        match self.number(key)? {
+            Some(seconds) => Duration::try_from_secs_f64(seconds)
+                .map(Some)
+                .map_err(|_| ScenarioError::InvalidNumber(self.value(key).map_or(self.line, |(_, line)| line), key)),
+            None => Ok(None),
+        }
+    }
+
+    fn string(&self, key: &str) -> Option<&str> {
+        match self.value(key) {
+            Some((TomlValue::String(value), _)) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// Reads the `[[name]]` tables of a TOML file, with number and basic string values.
+fn read_toml_tables<R: BufRead>(reader: R) -> Result<Vec<TomlTable>, Box<dyn Error>> {
+    let mut tables: Vec<TomlTable> = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let number = index + 1;
+        let line = line?;
+        let line = strip_comment(&line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix("[[").and_then(|line| line.strip_suffix("]]")) {
+            tables.push(TomlTable {
+                name: name.trim().to_string(),
+                line: number,
+                values: Vec::new(),
+            });
+            continue;
+        }
+
+        let (key, value) = line.split_once('=').ok_or(ScenarioError::InvalidSyntax(number))?;
+        let value = value.trim();
+        let value = match value.strip_prefix('"').and_then(|value| value.strip_suffix('"')) {
+            Some(text) => TomlValue::String(text.to_string()),
+            None => TomlValue::Number(
+                value
+                    .replace('_', "")
+                    .parse()
+                    .map_err(|_| ScenarioError::InvalidSyntax(number))?,
+            ),
+        };
+        let table = tables.last_mut().ok_or(ScenarioError::InvalidSyntax(number))?;
+        table.values.push((key.trim().to_string(), value, number));
+    }
+    Ok(tables)
+}
+
+/// Removes the comment at the end of a TOML line, outside of strings.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (index, character) in line.char_indices() {
+        match character {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Simulated SA430 device.
+///
+/// Requests are processed as soon as a complete frame is written, and the responses are queued to be read back.
+/// Reading with no pending response fails with `TimedOut`, just like a serial port that gets no answer.
+#[derive(Debug)]
+pub struct Simulator {
+    idn: String,
+    serial_number: u32,
+    core_version: u16,
+    spectrum_version: u16,
+    boot_count: u32,
+    temperature: [u8; 6],
+    last_error: ErrorCode,
+    flash: Vec<u8>,
+    input: Vec<u8>,
+    output: VecDeque<u8>,
+    responsive: bool,
+    fault_rate: f64,
+    seed: u64,
+    rng: u64,
+    scenario: Scenario,
+    elapsed: Duration,
+    registers: RegisterFile,
+    sweep: Option<SweepConfig>,
+}
+
+/// Registers of the sweep set by the `Set*` requests, `None` until set.
+#[derive(Debug, Clone, Copy, Default)]
+struct RegisterFile {
+    fstart: Option<u32>,
+    fstop: Option<u32>,
+    fstep: Option<u16>,
+    rbw: Option<u8>,
+    intermediate_frequency: Option<u8>,
+    gain: Option<u8>,
+}
+
+impl RegisterFile {
+    /// Returns the registers of the sweep, if they were all set.
+    fn sweep(&self) -> Option<SweepRegisters> {
+        Some(SweepRegisters {
+            fstart: self.fstart?,
+            fstop: self.fstop?,
+            fstep: self.fstep?,
+            rbw: self.rbw?,
+            intermediate_frequency: self.intermediate_frequency?,
+            gain: self.gain?,
+        })
+    }
+}
+
+impl Default for Simulator {
+    fn default() -> Self {
+        Simulator::new()
+    }
+}
+
+impl Simulator {
+    /// Creates a new simulated device with the factory data from the fixtures.
+    pub fn new() -> Self {
+        let mut flash = fixture::PROG_HEADER_DATA.to_vec();
+        for data in [
+            fixture::CALIBRATION_DATA_1,
+            fixture::CALIBRATION_DATA_2,
+            fixture::CALIBRATION_DATA_3,
+            fixture::CALIBRATION_DATA_4,
+            fixture::CALIBRATION_DATA_5,
+            fixture::CALIBRATION_DATA_6,
+            fixture::CALIBRATION_DATA_7,
+        ] {
+            flash.extend_from_slice(data);
+        }
+
+        Simulator {
+            idn: String::from_utf8_lossy(fixture::a_get_idn_response().data()).to_string(),
+            serial_number: 0x0908,
+            core_version: 0x020A,
+            spectrum_version: 0x0205,
+            boot_count: 42,
+            temperature: [0x00, 0x00, 0x0A, 0x5C, 0x01, 0x9B],
+            last_error: ErrorCode::NoError,
+            flash,
+            input: Vec::new(),
+            output: VecDeque::new(),
+            responsive: true,
+            fault_rate: 0.0,
+            seed: DEFAULT_SEED,
+            rng: DEFAULT_SEED,
+            scenario: Scenario::new(),
+            elapsed: Duration::ZERO,
+            registers: RegisterFile::default(),
+            sweep: None,
+        }
+    }
+
+    /// Sets whether the device answers the requests, an unresponsive device ignores them, like a hung firmware.
+    pub fn set_responsive(&mut self, responsive: bool) {
+        self.responsive = responsive;
+    }
+
+    /// Seeds the random generator of the faults and noise, 0 is replaced by 1.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.reseed(seed);
+        self
+    }
+
+    /// Returns the seed of the random generator, to reproduce a run.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Sets the probability, from 0 to 1, of injecting a fault into each reply frame, drawing the faults from a
+    /// generator initialized with `seed`.
+    pub fn set_fault_rate(&mut self, rate: f64, seed: u64) {
+        self.fault_rate = rate;
+        self.reseed(seed);
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        // The xorshift generator is stuck at 0.
+        self.seed = seed.max(1);
+        self.rng = self.seed;
+    }
+
+    /// Sets the carriers received by the simulator and the faults of the device, restarting the simulated time.
+    pub fn set_scenario(&mut self, scenario: Scenario) {
+        self.scenario = scenario;
+        self.elapsed = Duration::ZERO;
+    }
+
+    /// Advances the simulated time of the scenario.
+    pub fn advance(&mut self, duration: Duration) {
+        self.elapsed += duration;
+    }
+
+    /// Returns the simulated time since the scenario was set.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Returns the calibration stored in the simulated flash memory.
+    pub fn calibration(&self) -> Result<Calibration, Box<dyn Error>> {
+        let offset = (FLASH_CALIBRATION_ADDR - FLASH_START_ADDR) as usize;
+        Calibration::try_from(&self.flash[offset..offset + FLASH_CALIBRATION_SIZE as usize])
+    }
+
+    /// Measures the spectrum from `fstart` to `fstop` with a step of `fstep` and a resolution bandwidth of `rbw`, at
+    /// the reference level `ref_level`, in dBm: the noise floor of the calibration, with random variations drawn from
+    /// the seeded generator, plus the carriers of the scenario, clipped at the reference level.
+    ///
+    /// Fails if the sweep is outside of the device ranges or the reference level is not in the calibration.
+    pub fn measure(
+        &mut self,
+        fstart: Frequency,
+        fstop: Frequency,
+        fstep: Frequency,
+        ref_level: i8,
+        rbw: Frequency,
+    ) -> Result<Spectrum, Box<dyn Error>> {
+        let calibration = self.calibration()?;
+        let mut points = Vec::new();
+        for segment in calibration.split_sweep(fstart, fstop, fstep)? {
+            let count = ((segment.fstop - segment.fstart) / fstep).round() as usize + 1;
+            for frequency in (0..count).map(|i| segment.fstart + i as f64 * fstep) {
+                let power = self.power_at(&calibration, segment.range, frequency, ref_level, rbw)?;
+                points.push((frequency.hz(), power));
+            }
+        }
+        Ok(Spectrum::new(points))
+    }
+
+    /// Returns the power measured at `frequency`, inside the frequency range `range`, in dBm: the noise floor with a
+    /// random variation plus the carriers, clipped at the reference level.
+    fn power_at(
+        &mut self,
+        calibration: &Calibration,
+        range: usize,
+        frequency: Frequency,
+        ref_level: i8,
+        rbw: Frequency,
+    ) -> Result<f64, Box<dyn Error>> {
+        let floor = noise_floor_at(calibration, range, frequency, ref_level, rbw)?;
+        let noise = Dbm::new(floor).mw() * self.next_exponential();
+        let power = Dbm::from_mw(noise + self.scenario.power_at(self.elapsed, frequency, rbw));
+        Ok(power.dbm().min(ref_level as f64))
+    }
+
+    /// Returns the samples of the sweep `config`, the RSSI of each bin as the firmware sends it, see
+    /// [`SweepCorrection::sample`].
+    fn samples(&mut self, config: &SweepConfig) -> Result<Vec<u8>, Box<dyn Error>> {
+        let calibration = self.calibration()?;
+        // The frequencies decoded from the registers are up to a register step below the requested ones, so the
+        // range is the one of the middle bin, or of a step above it at the start of a range.
+        let middle = config.frequency_at(config.bin_count() / 2);
+        let range = [middle, middle + config.xtal / 65536.0]
+            .into_iter()
+            .find_map(|frequency| calibration.range_of(frequency, frequency))
+            .ok_or(CorrectionError::OutsideRanges(config.fstart, config.fstop))?;
+        let correction = SweepCorrection::with_range(&calibration, config, range)?;
+
+        let mut samples = Vec::with_capacity(correction.len());
+        for (bin, (frequency, _)) in correction.bins().iter().enumerate() {
+            let power = self.power_at(
+                &calibration,
+                correction.range(),
+                *frequency,
+                correction.ref_level(),
+                config.rbw,
+            )?;
+            samples.push(correction.sample(bin, power));
+        }
+        Ok(samples)
+    }
+
+    /// Returns the next number of an exponential distribution with a mean of 1, the distribution of the power of
+    /// Gaussian noise.
+    fn next_exponential(&mut self) -> f64 {
+        let uniform = (self.next_random() >> 11) as f64 / (1u64 << 53) as f64;
+        -(1.0 - uniform).ln()
+    }
+
+    /// Returns the number of bytes written and not yet processed plus the bytes of the replies not read yet.
+    pub fn pending(&self) -> usize {
+        self.input.len() + self.output.len()
+    }
+
+    /// Returns the next number of the xorshift generator.
+    fn next_random(&mut self) -> u64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng
+    }
+
+    /// Draws the fault to inject into the next reply, if any.
+    fn next_fault(&mut self) -> Option<Fault> {
+        let rate = self.fault_rate.max(self.scenario.fault_rate_at(self.elapsed));
+        if rate <= 0.0 || (self.next_random() % 1_000_000) as f64 >= rate * 1e6 {
+            return None;
+        }
+
+        match self.next_random() % 3 {
+            0 => Some(Fault::DropReply),
+            1 => Some(Fault::CorruptCrc),
+            _ => Some(Fault::Noise),
+        }
+    }
+
+    /// Decodes every complete frame in the input buffer and queues the responses.
+    fn process(&mut self) {
+        loop {
+            let start = match self.input.iter().position(|b| *b == FRAME_MAGIC_VALUE) {
+                Some(start) => start,
+                None => {
+                    self.input.clear();
+                    return;
+                }
+            };
+            self.input.drain(..start);
+
+            if self.input.len() <= FRAME_DATA_LENGTH_INDEX {
+                return;
+            }
+
+            let size = FRAME_HEADER_SIZE + self.input[FRAME_DATA_LENGTH_INDEX] as usize + FRAME_CRC_SIZE;
+            if self.input.len() < size {
+                return;
+            }
+
+            let bytes: Vec<u8> = self.input.drain(..size).collect();
+            match Frame::from_bytes(&bytes) {
+                Ok(request) => self.execute(&request),
+                Err(FrameError::InvalidCrc(_, _)) => self.reply_error(ErrorCode::WrongCrcLowByte),
+                Err(_) => self.reply_error(ErrorCode::PacketError),
+            }
+        }
+    }
+
+    /// Executes a request, queueing the ACK and the response (if any).
+    fn execute(&mut self, request: &Frame) {
+        let cmd = request.cmd();
+        match cmd {
+            Command::GetIdn => self.reply_with_data(cmd, &self.idn.clone().into_bytes()),
+            Command::GetSerialNumber => self.reply_with_data(cmd, &self.serial_number.to_be_bytes()),
+            Command::GetCoreVersion => self.reply_with_data(cmd, &self.core_version.to_be_bytes()),
+            Command::GetSpectrumVersion => self.reply_with_data(cmd, &self.spectrum_version.to_be_bytes()),
+            Command::GetBootCnt => self.reply_with_data(cmd, &self.boot_count.to_be_bytes()),
+            Command::GetTemp => self.reply_with_data(cmd, &self.temperature.clone()),
+            Command::GetLastError => self.reply_with_data(cmd, &(self.last_error as u16).to_be_bytes()),
+            Command::BlinkLed | Command::HardwareReset | Command::Sync => self.reply(&Frame::new(cmd)),
+            Command::FlashRead => self.read_flash(request),
+            Command::SetFStart
+            | Command::SetFStop
+            | Command::SetFStep
+            | Command::SetRbw
+            | Command::SetIf
+            | Command::SetGain => self.set_register(request),
+            Command::InitParameter => self.init_sweep(request),
+            Command::GetSpecNoInit => self.measure_sweep(request),
+            _ => self.reply_error(ErrorCode::UnknownCmd),
+        }
+    }
+
+    fn read_flash(&mut self, request: &Frame) {
+        let data = request.data();
+        if data.len() != 4 {
+            self.reply_error(ErrorCode::WrongCmdLength);
+            return;
+        }
+
+        let addr = u16::from_be_bytes([data[0], data[1]]);
+        let size = u16::from_be_bytes([data[2], data[3]]) as usize;
+        let offset = addr.wrapping_sub(FLASH_START_ADDR) as usize;
+        if addr < FLASH_START_ADDR || size > 255 || offset + size > self.flash.len() {
+            self.reply_error(ErrorCode::BufferPosOutOfRange);
+            return;
+        }
+
+        let chunk = self.flash[offset..offset + size].to_vec();
+        self.reply_with_data(Command::FlashRead, &chunk);
+    }
+
+    /// Sets the register of a `Set*` request, checking the size of its payload. The sweep must be initialized again.
+    fn set_register(&mut self, request: &Frame) {
+        let registers = &mut self.registers;
+        match (request.cmd(), request.data()) {
+            (Command::SetFStart, [high, middle, low]) => {
+                registers.fstart = Some(u32::from_be_bytes([0, *high, *middle, *low]))
+            }
+            (Command::SetFStop, [high, middle, low]) => {
+                registers.fstop = Some(u32::from_be_bytes([0, *high, *middle, *low]))
+            }
+            (Command::SetFStep, [high, low]) => registers.fstep = Some(u16::from_be_bytes([*high, *low])),
+            (Command::SetRbw, [value]) => registers.rbw = Some(*value),
+            (Command::SetIf, [value]) => registers.intermediate_frequency = Some(*value),
+            (Command::SetGain, [value]) => registers.gain = Some(*value),
+            _ => {
+                self.reply_error(ErrorCode::WrongCmdLength);
+                return;
+            }
+        }
+        self.sweep = None;
+        self.reply(&Frame::new(request.cmd()));
+    }
+
+    /// Initializes the sweep set up in the registers, if they were all set with valid values. `InitParameter` has no
+    /// payload.
+    fn init_sweep(&mut self, request: &Frame) {
+        if !request.data().is_empty() {
+            self.reply_error(ErrorCode::WrongCmdLength);
+            return;
+        }
+
+        let xtal = self
+            .calibration()
+            .map_or(Frequency::from_hz(NOMINAL_XTAL_HZ), |calibration| calibration.xtal());
+        self.sweep = self
+            .registers
+            .sweep()
+            .and_then(|registers| SweepConfig::from_registers(&registers, xtal).ok());
+        self.reply(&Frame::new(Command::InitParameter));
+    }
+
+    /// Streams the samples of the sweep initialized by `InitParameter`, ending with a `NoError` status, or replies with
+    /// `PllNotSettled` without one.
+    fn measure_sweep(&mut self, request: &Frame) {
+        if !request.data().is_empty() {
+            self.reply_error(ErrorCode::WrongCmdLength);
+            return;
+        }
+
+        let samples = match self.sweep.map(|config| self.samples(&config)) {
+            Some(Ok(samples)) => samples,
+            Some(Err(_)) | None => {
+                self.reply_error(ErrorCode::PllNotSettled);
+                return;
+            }
+        };
+
+        self.reply(&Frame::new(Command::GetSpecNoInit));
+        for chunk in samples.chunks(SAMPLES_PER_FRAME) {
+            self.reply(&Frame::with_data(Command::GetSpecNoInit, chunk));
+        }
+        self.reply(&Frame::with_data(
+            Command::GetLastError,
+            &(ErrorCode::NoError as u16).to_be_bytes(),
+        ));
+    }
+
+    fn reply_with_data(&mut self, cmd: Command, data: &[u8]) {
+        self.reply(&Frame::new(cmd));
+        self.reply(&Frame::with_data(cmd, data));
+    }
+
+    fn reply_error(&mut self, code: ErrorCode) {
+        self.last_error = code;
+        self.reply(&Frame::with_data(Command::GetLastError, &(code as u16).to_be_bytes()));
+    }
+
+    fn reply(&mut self, frame: &Frame) {
+        if !self.responsive || self.scenario.is_unresponsive_at(self.elapsed) {
+            return;
+        }
+
+        let mut bytes = frame.to_bytes();
+        match self.next_fault() {
+            Some(Fault::DropReply) => return,
+            Some(Fault::CorruptCrc) => {
+                let last = bytes.len() - 1;
+                bytes[last] ^= 0xFF;
+            }
+            Some(Fault::Noise) => {
+                for _ in 0..1 + self.next_random() % 8 {
+                    let byte = self.next_random() as u8;
+                    self.output.push_back(if byte == FRAME_MAGIC_VALUE { 0 } else { byte });
+                }
+            }
+            None => {}
+        }
+        self.output.extend(bytes);
+    }
+}
+
+impl io::Write for Simulator {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.input.extend_from_slice(buf);
+        self.process();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl io::Read for Simulator {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.output.is_empty() && !buf.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "Operation timed out"));
+        }
+
+        let len = buf.len().min(self.output.len());
+        for (byte, value) in buf.iter_mut().zip(self.output.drain(..len)) {
+            *byte = value;
+        }
+        Ok(len)
+    }
+}
+
+impl Channel for Simulator {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::compat::Feature;
+    use crate::device::Sa430;
+
+    fn mhz(value: f64) -> Frequency {
+        Frequency::from_mhz(value)
+    }
+
+    fn khz(value: f64) -> Frequency {
+        Frequency::from_khz(value)
+    }
+
+    #[test]
+    fn given_a_simulator_when_reading_device_info_then_return_fixture_values() {
+        let mut device = Sa430::new(Box::new(Simulator::new()));
+
+        assert_eq!(
+            device.idn().unwrap(),
+            "Texas Instruments,MSP-SA430-SUB1GHZ: RF Dev Support Tool,HW2.0"
+        );
+        assert_eq!(device.serial_number().unwrap(), 0x0908);
+        assert_eq!(device.core_version().unwrap(), "2.10");
+        assert_eq!(device.spectrum_version().unwrap(), "2.5");
+    }
+
+    #[test]
+    fn given_a_simulator_when_reading_calibration_then_return_fixture_calibration() {
+        let mut device = Sa430::new(Box::new(Simulator::new()));
+
+        assert_eq!(device.calibration_version().unwrap(), "1.16");
+        assert_eq!(device.calibration_date().unwrap(), "Mo. Sep 19 2011\0");
+    }
+
+    #[test]
+    fn given_a_simulator_when_probing_capabilities_then_support_measurements() {
+        let mut device = Sa430::new(Box::new(Simulator::new()));
+
+        let capabilities = device.capabilities().unwrap();
+
+        assert!(capabilities.spectrum_supported());
+        assert!(capabilities.max_bins() > 0);
+        assert_eq!(capabilities.core_version(), 0x020A);
+        assert!(device.check_compat(Feature::Capture).is_ok());
+    }
+
+    #[test]
+    fn given_a_simulator_when_tune_flash_reads_then_keep_the_fastest_candidate() {
+        let mut device = Sa430::new(Box::new(Simulator::new()));
+        let candidates = [
+            crate::device::FlashReadPolicy::default(),
+            crate::device::FlashReadPolicy {
+                chunk_size: std::num::NonZeroU8::new(64).unwrap(),
+                window: std::num::NonZeroUsize::new(4).unwrap(),
+            },
+        ];
+
+        let policy = device.tune_flash_reads(&candidates).unwrap();
+
+        assert!(candidates.contains(&policy));
+        assert_eq!(device.flash_read_policy(), &policy);
+        assert_eq!(device.calibration_version().unwrap(), "1.16");
+    }
+
+    #[test]
+    fn given_a_simulator_when_abort_then_park_the_device() {
+        let mut device = Sa430::new(Box::new(Simulator::new()));
+
+        device.abort().unwrap();
+
+        assert!(!device.is_busy());
+        assert_eq!(device.serial_number().unwrap(), 0x0908);
+    }
+
+    /// Writes the requests setting up `config` with the built-in engine, returning the frames of the replies.
+    fn init_sweep(simulator: &mut Simulator, config: &SweepConfig) -> Vec<Frame> {
+        for request in config.registers().unwrap().to_frames() {
+            io::Write::write_all(simulator, &request.to_bytes()).unwrap();
+        }
+        io::Write::write_all(simulator, &Frame::new(Command::InitParameter).to_bytes()).unwrap();
+        let replies: Vec<u8> = simulator.output.drain(..).collect();
+        crate::frame::FrameStream::new(replies.as_slice())
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    fn a_sweep_config() -> SweepConfig {
+        SweepConfig {
+            fstart: mhz(868.0),
+            fstop: mhz(870.0),
+            fstep: khz(100.0),
+            rbw: khz(58.0),
+            ref_level_index: 7,
+            xtal: Simulator::new().calibration().unwrap().xtal(),
+        }
+    }
+
+    #[test]
+    fn given_the_register_requests_when_init_sweep_then_keep_the_sweep_for_the_measurement() {
+        let config = a_sweep_config();
+        let mut simulator = Simulator::new();
+
+        let replies = init_sweep(&mut simulator, &config);
+
+        assert_eq!(
+            replies.iter().map(Frame::cmd).collect::<Vec<_>>(),
+            [
+                Command::SetFStart,
+                Command::SetFStop,
+                Command::SetFStep,
+                Command::SetRbw,
+                Command::SetIf,
+                Command::SetGain,
+                Command::InitParameter
+            ]
+        );
+        assert_eq!(simulator.sweep.unwrap().registers(), config.registers());
+    }
+
+    #[test]
+    fn given_requests_with_a_wrong_payload_when_init_sweep_then_reply_with_error() {
+        let mut simulator = Simulator::new();
+
+        for request in [
+            Frame::with_data(Command::SetFStart, &[0x21, 0x62]),
+            Frame::with_data(Command::SetFStep, &[0x00, 0x00, 0xFC]),
+            Frame::with_data(Command::SetGain, &[]),
+            Frame::with_data(Command::InitParameter, &[0x00]),
+            Frame::with_data(Command::GetSpecNoInit, &[0x00]),
+        ] {
+            io::Write::write_all(&mut simulator, &request.to_bytes()).unwrap();
+
+            let response: Vec<u8> = simulator.output.drain(..).collect();
+            assert_eq!(
+                Frame::from_bytes(&response).unwrap().to_error_code(),
+                Some(ErrorCode::WrongCmdLength),
+                "{:?}",
+                request
+            );
+        }
+    }
+
+    #[test]
+    fn given_a_register_set_after_init_parameter_when_get_spec_no_init_then_reply_with_error() {
+        let mut simulator = Simulator::new();
+        init_sweep(&mut simulator, &a_sweep_config());
+
+        io::Write::write_all(&mut simulator, &Frame::with_data(Command::SetRbw, &[224]).to_bytes()).unwrap();
+        io::Write::write_all(&mut simulator, &Frame::new(Command::GetSpecNoInit).to_bytes()).unwrap();
+
+        let replies: Vec<u8> = simulator.output.drain(..).collect();
+        let mut frames = crate::frame::FrameStream::new(replies.as_slice());
+        assert_eq!(frames.next().unwrap().unwrap(), Frame::new(Command::SetRbw));
+        assert_eq!(
+            frames.next().unwrap().unwrap().to_error_code(),
+            Some(ErrorCode::PllNotSettled)
+        );
+    }
+
+    #[test]
+    fn given_a_simulator_when_command_is_not_supported_then_reply_with_error() {
+        let mut simulator = Simulator::new();
+
+        io::Write::write_all(&mut simulator, &Frame::new(Command::SweepEdc).to_bytes()).unwrap();
+
+        let response: Vec<u8> = simulator.output.drain(..).collect();
+        assert_eq!(
+            Frame::from_bytes(&response).unwrap().to_error_code(),
+            Some(ErrorCode::UnknownCmd)
+        );
+    }
+
+    #[test]
+    fn given_a_failed_command_when_health_then_report_its_error() {
+        let mut simulator = Simulator::new();
+        io::Write::write_all(&mut simulator, &Frame::new(Command::SweepEdc).to_bytes()).unwrap();
+        simulator.output.clear();
+        let mut device = Sa430::new(Box::new(simulator));
+
+        let health = device.health().unwrap();
+
+        assert_eq!(health.serial_number, 0x0908);
+        assert_eq!(health.boot_count, 42);
+        assert_eq!(health.temperature_raw, [0x00, 0x00, 0x0A, 0x5C, 0x01, 0x9B]);
+        assert_eq!(health.last_error, ErrorCode::UnknownCmd);
+        assert_eq!(health.link.commands, 4);
+        assert_eq!(health.link.receive_errors, 0);
+    }
+
+    #[test]
+    fn given_a_fault_rate_when_reading_device_info_then_fail_some_commands_and_recover() {
+        let mut simulator = Simulator::new();
+        simulator.set_fault_rate(0.5, 7);
+        let mut device = Sa430::new(Box::new(simulator));
+
+        let mut failures = 0;
+        for _ in 0..50 {
+            match device.serial_number() {
+                Ok(serial_number) => assert_eq!(serial_number, 0x0908),
+                Err(_) => {
+                    failures += 1;
+                    while device.recover().is_err() {}
+                }
+            }
+        }
+
+        assert!(failures > 0 && failures < 50);
+    }
+
+    #[test]
+    fn given_a_simulator_when_nothing_to_read_then_time_out() {
+        let mut simulator = Simulator::new();
+        let mut buf = [0; 1];
+
+        let result = io::Read::read(&mut simulator, &mut buf);
+
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn given_no_carriers_when_measure_then_return_the_noise_floor_of_the_ref_level() {
+        let mut simulator = Simulator::new();
+        let calibration = simulator.calibration().unwrap();
+        let range = calibration
+            .split_sweep(mhz(868.1), mhz(868.1), Frequency::from_hz(1.0))
+            .unwrap()[0]
+            .range;
+        let floor = noise_floor_at(&calibration, range, mhz(868.1), -35, khz(58.0)).unwrap();
+
+        let spectrum = simulator
+            .measure(mhz(868.0), mhz(868.2), khz(1.0), -35, khz(58.0))
+            .unwrap();
+
+        let mean = spectrum.mean_power().unwrap().dbm();
+        assert_eq!(spectrum.len(), 201);
+        assert!((mean - floor).abs() < 1.0, "mean {} dBm, floor {} dBm", mean, floor);
+    }
+
+    #[test]
+    fn given_a_carrier_above_the_ref_level_when_measure_then_clip_it() {
+        let mut simulator = Simulator::new();
+        simulator.set_scenario(Scenario::read_csv("# strong\n868.1,-10\n".as_bytes()).unwrap());
+
+        let spectrum = simulator
+            .measure(mhz(868.0), mhz(868.2), khz(10.0), -35, khz(58.0))
+            .unwrap();
+
+        assert_eq!(spectrum.powers().fold(f64::MIN, f64::max), -35.0);
+    }
+
+    #[test]
+    fn given_a_scenario_when_sweep_then_return_the_simulated_powers_within_the_rssi_resolution() {
+        let scenario = "868.1,-60,125\n";
+        let mut expected = Simulator::new().with_seed(3);
+        expected.set_scenario(Scenario::read_csv(scenario.as_bytes()).unwrap());
+        let mut simulator = Simulator::new().with_seed(3);
+        simulator.set_scenario(Scenario::read_csv(scenario.as_bytes()).unwrap());
+        let mut device = Sa430::new(Box::new(simulator));
+
+        let spectrum = device.capture(mhz(867.0), mhz(869.0), khz(10.0), -35).unwrap();
+
+        let calibration = Simulator::new().calibration().unwrap();
+        let gains = &calibration.frq_gains_tables[2][0];
+        let config = SweepConfig::with_auto_rbw(mhz(867.0), mhz(869.0), khz(10.0), 0, calibration.xtal());
+        assert_eq!(spectrum.len(), config.bin_count());
+        assert_eq!(spectrum.configs(), [config]);
+        assert_eq!(spectrum.rbw(), Some(khz(58.0)));
+        for (bin, (frequency, power)) in spectrum.iter().enumerate() {
+            let frequency = Frequency::from_hz(*frequency);
+            let expected_power = expected.power_at(&calibration, 2, frequency, -35, khz(58.0)).unwrap();
+            // The noise below the lowest RSSI is clamped to it, like the radio does
+            let lowest = i8::MIN as f64 / 2.0 - gains.gain_at(frequency);
+            assert_eq!(frequency, config.frequency_at(bin));
+            assert!(
+                (power - expected_power.max(lowest)).abs() <= 0.25,
+                "{} dBm at {} Hz",
+                power,
+                frequency
+            );
+        }
+    }
+
+    #[test]
+    fn given_no_sweep_config_when_get_spec_no_init_then_reply_with_error() {
+        let mut simulator = Simulator::new();
+
+        io::Write::write_all(&mut simulator, &Frame::new(Command::GetSpecNoInit).to_bytes()).unwrap();
+
+        let response: Vec<u8> = simulator.output.drain(..).collect();
+        assert_eq!(
+            Frame::from_bytes(&response).unwrap().to_error_code(),
+            Some(ErrorCode::PllNotSettled)
+        );
+    }
+
+    #[cfg(feature = "dsp")]
+    #[test]
+    fn given_a_scenario_when_measure_then_trip_a_trigger_on_its_carrier_only() {
+        use crate::dsp::TriggerCondition;
+
+        let mut simulator = Simulator::new();
+        simulator.set_scenario(Scenario::read_csv("868.3,-60,125".as_bytes()).unwrap());
+        let busy: TriggerCondition = "868.2-868.4 > -80dBm".parse().unwrap();
+        let idle: TriggerCondition = "867.8-868.0 > -80dBm".parse().unwrap();
+
+        let spectrum = simulator
+            .measure(mhz(867.8), mhz(868.4), khz(10.0), -35, khz(58.0))
+            .unwrap();
+
+        assert!(busy.matches(&spectrum));
+        assert!(!idle.matches(&spectrum));
+    }
+
+    #[test]
+    fn given_invalid_lines_when_read_csv_then_return_the_line_error() {
+        for (text, expected) in [
+            ("868.1\n", ScenarioError::MissingColumns(1)),
+            ("868.1,-60\nabc,-60\n", ScenarioError::InvalidNumber(2, "frequency_mhz")),
+            ("868.1,-60,-5\n", ScenarioError::InvalidNumber(1, "bandwidth_khz")),
+        ] {
+            let error = Scenario::read_csv(text.as_bytes()).unwrap_err();
+
+            assert_eq!(error.downcast_ref::<ScenarioError>(), Some(&expected));
+        }
+    }
+
+    const A_TOML_SCENARIO: &str = "\
+        # A burst, then a hung device\n\
+        [[carrier]]\n\
+        frequency_mhz = 868.3 # LoRa\n\
+        power_dbm = -60\n\
+        bandwidth_khz = 125\n\
+        start_s = 10\n\
+        stop_s = 20\n\
+        \n\
+        [[fault]]\n\
+        kind = \"unresponsive\"\n\
+        start_s = 30\n\
+        stop_s = 35\n";
+
+    #[cfg(feature = "dsp")]
+    #[test]
+    fn given_a_toml_scenario_when_advance_then_follow_the_schedule() {
+        use crate::dsp::TriggerCondition;
+
+        let mut simulator = Simulator::new();
+        simulator.set_scenario(Scenario::read_toml(A_TOML_SCENARIO.as_bytes()).unwrap());
+        let busy: TriggerCondition = "868.2-868.4 > -80dBm".parse().unwrap();
+        let mut tripped = Vec::new();
+
+        for _ in 0..4 {
+            let spectrum = simulator
+                .measure(mhz(868.2), mhz(868.4), khz(10.0), -35, khz(58.0))
+                .unwrap();
+            tripped.push(busy.matches(&spectrum));
+            simulator.advance(Duration::from_secs(5));
+        }
+
+        assert_eq!(tripped, vec![false, false, true, true]);
+    }
+
+    #[test]
+    fn given_an_unresponsive_fault_when_scheduled_then_ignore_requests_during_it() {
+        let mut simulator = Simulator::new();
+        simulator.set_scenario(Scenario::read_toml(A_TOML_SCENARIO.as_bytes()).unwrap());
+
+        for (time, answers) in [(29, true), (30, false), (34, false), (35, true)] {
+            simulator.advance(Duration::from_secs(time) - simulator.elapsed());
+            io::Write::write_all(&mut simulator, &Frame::new(Command::Sync).to_bytes()).unwrap();
+
+            assert_eq!(simulator.output.drain(..).count() > 0, answers, "at {} s", time);
+        }
+    }
+
+    #[test]
+    fn given_invalid_toml_when_read_toml_then_return_the_line_error() {
+        for (text, expected) in [
+            ("power_dbm = -60\n", ScenarioError::InvalidSyntax(1)),
+            (
+                "[[carrier]]\npower_dbm = -60\n",
+                ScenarioError::MissingKey(1, "frequency_mhz"),
+            ),
+            (
+                "[[carrier]]\nfrequency_mhz = \"868\"\n",
+                ScenarioError::InvalidNumber(2, "frequency_mhz"),
+            ),
+            ("[[signal]]\n", ScenarioError::UnknownTable(1, "signal".to_string())),
+            (
+                "[[fault]]\nkind = \"reboot\"\n",
+                ScenarioError::UnknownFault(1, "reboot".to_string()),
+            ),
+            ("[[fault]]\nrate = 2\n", ScenarioError::InvalidNumber(1, "rate")),
+        ] {
+            let error = Scenario::read_toml(text.as_bytes()).unwrap_err();
+
+            assert_eq!(error.downcast_ref::<ScenarioError>(), Some(&expected), "{}", text);
+        }
+    }
+
+    #[test]
+    fn given_the_same_seed_when_measure_then_return_the_same_noise() {
+        let measure = |seed: u64| {
+            Simulator::new()
+                .with_seed(seed)
+                .measure(mhz(868.0), mhz(868.2), khz(10.0), -70, khz(58.0))
+                .unwrap()
+        };
+
+        assert_eq!(measure(7), measure(7));
+        assert_ne!(measure(7), measure(8));
+        assert_eq!(Simulator::new().with_seed(0).seed(), 1);
+    }
+}
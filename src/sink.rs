@@ -0,0 +1,666 @@
+//! # Sink Module
+//!
+//! This module provides the `Sink` trait, implemented by the outputs of a capture, like the CSV, NDJSON, binary and
+//! HTML plot writers of the [`formats`](crate::formats) module, and the `SinkRegistry`, which broadcasts each sweep
+//! record to a set of sinks. The capture only produces `SweepRecord`s, so new formats are added as new sinks.
+//!
+//! The registry is shared, so sinks can be added and removed from another thread while the capture runs, without
+//! restarting the sweeps, for example when a dashboard connects or disconnects. A sink that fails is removed from the
+//! registry, since a closed connection would fail every following sweep.
+//!
+//! Sinks on slow links, like dashboards, can be wrapped in `Throttled`, to forward at most a number of sweeps per
+//! second, or in `Aggregated`, to combine a number of sweeps, or the sweeps of each period of time, like a minute or
+//! an hour, into one, shrinking the data of long surveys. Sinks that may block, like network clients, can be wrapped
+//! in `Queued`, to write from a thread of their own and drop the sweeps they can't keep up with.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use std::sync::{Arc, Mutex};
+//!
+//! use sa430::sink::{SinkRegistry, SweepRecord};
+//! use sa430::spectrum::Spectrum;
+//!
+//! let registry = SinkRegistry::new();
+//! let handle = registry.clone();
+//!
+//! let received = Arc::new(Mutex::new(Vec::new()));
+//! let sequences = received.clone();
+//! let id = handle.add(Box::new(move |record: &SweepRecord| -> Result<(), Box<dyn std::error::Error>> {
+//!     sequences.lock().unwrap().push(record.sequence);
+//!     Ok(())
+//! }));
+//!
+//! registry.publish(&SweepRecord::new(0, Spectrum::from_sweep(868.0e6, 0.1e6, &[-90.0])));
+//! handle.remove(id);
+//! registry.publish(&SweepRecord::new(1, Spectrum::from_sweep(868.0e6, 0.1e6, &[-90.0])));
+//!
+//! assert_eq!(*received.lock().unwrap(), vec![0]);
+//! ```
+
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    num::NonZeroUsize,
+    str::FromStr,
+    sync::{
+        mpsc::{self, SyncSender, TrySendError},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::spectrum::{BinWindow, Spectrum, SpectrumSet};
+
+/// A sweep of a capture, as written to the sinks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepRecord {
+    /// Number of the sweep in the capture, starting at 0.
+    pub sequence: u64,
+
+    /// When the sweep was measured.
+    pub timestamp: SystemTime,
+
+    /// Measured spectrum.
+    pub spectrum: Spectrum,
+
+    /// Frequency grid, when the spectrum was aligned to a power of two number of points.
+    pub window: Option<BinWindow>,
+}
+
+impl SweepRecord {
+    /// Creates a record of a sweep measured now, without alignment.
+    pub fn new(sequence: u64, spectrum: Spectrum) -> Self {
+        SweepRecord {
+            sequence,
+            timestamp: SystemTime::now(),
+            spectrum,
+            window: None,
+        }
+    }
+}
+
+/// An output receiving the sweeps of a capture, in one format.
+pub trait Sink {
+    /// Writes a sweep.
+    fn write(&mut self, record: &SweepRecord) -> Result<(), Box<dyn Error>>;
+
+    /// Completes the output when the capture ends, like formats written at once. Flushes nothing by default.
+    fn close(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+}
+
+/// A producer of sweep records, like a saved capture or the `client::Client` of a remote one.
+pub trait SweepSource {
+    /// Returns the next sweep, waiting for it to be measured, or `None` when the capture ended.
+    fn next_sweep(&mut self) -> Result<Option<SweepRecord>, Box<dyn Error>>;
+}
+
+impl<F> Sink for F
+where
+    F: FnMut(&SweepRecord) -> Result<(), Box<dyn Error>>,
+{
+    fn write(&mut self, record: &SweepRecord) -> Result<(), Box<dyn Error>> {
+        self(record)
+    }
+}
+
+impl Sink for Box<dyn Sink + Send + '_> {
+    fn write(&mut self, record: &SweepRecord) -> Result<(), Box<dyn Error>> {
+        self.as_mut().write(record)
+    }
+
+    fn close(&mut self) -> Result<(), Box<dyn Error>> {
+        self.as_mut().close()
+    }
+}
+
+/// Forwards at most a number of sweeps per second to a sink, dropping the others.
+///
+/// The rate is measured with the sweep timestamps, so it follows the capture, not the time the sweeps are written.
+pub struct Throttled<S: Sink> {
+    sink: S,
+    interval: Duration,
+    last: Option<SystemTime>,
+}
+
+impl<S: Sink> Throttled<S> {
+    /// Wraps `sink`, forwarding at most `max_rate` sweeps per second.
+    pub fn new(sink: S, max_rate: f64) -> Self {
+        Throttled {
+            sink,
+            interval: Duration::from_secs_f64(1.0 / max_rate),
+            last: None,
+        }
+    }
+}
+
+impl<S: Sink> Sink for Throttled<S> {
+    fn write(&mut self, record: &SweepRecord) -> Result<(), Box<dyn Error>> {
+        let elapsed = self.last.and_then(|last| record.timestamp.duration_since(last).ok());
+        if elapsed.is_some_and(|elapsed| elapsed < self.interval) {
+            return Ok(());
+        }
+        self.last = Some(record.timestamp);
+        self.sink.write(record)
+    }
+
+    fn close(&mut self) -> Result<(), Box<dyn Error>> {
+        self.sink.close()
+    }
+}
+
+/// How `Aggregated` combines the sweeps.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Aggregation {
+    /// Mean power of each bin, in linear scale.
+    #[default]
+    Mean,
+
+    /// Max power of each bin, so short bursts are not lost.
+    Max,
+}
+
+impl FromStr for Aggregation {
+    type Err = SinkError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "mean" | "avg" => Ok(Aggregation::Mean),
+            "max" => Ok(Aggregation::Max),
+            _ => Err(SinkError::InvalidAggregation),
+        }
+    }
+}
+
+impl Display for Aggregation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Aggregation::Mean => write!(f, "mean"),
+            Aggregation::Max => write!(f, "max"),
+        }
+    }
+}
+
+/// Sweeps combined into one by `Aggregated`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Window {
+    /// A number of consecutive sweeps.
+    Sweeps(NonZeroUsize),
+
+    /// The sweeps measured in the same period, aligned to the Unix epoch, so the windows of an hour start on the hour.
+    Period(Duration),
+}
+
+/// Parses a period of time, as a number of seconds with an optional `s`, `m`, `h` or `d` unit, like `90`, `15m` or
+/// `1h`.
+pub fn parse_period(value: &str) -> Result<Duration, SinkError> {
+    let value = value.trim();
+    let (number, unit) = match value.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(index) => value.split_at(index),
+        None => (value, "s"),
+    };
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        _ => return Err(SinkError::InvalidPeriod),
+    };
+    match number.trim().parse::<u64>() {
+        Ok(number) if number > 0 => Ok(Duration::from_secs(number * seconds)),
+        _ => Err(SinkError::InvalidPeriod),
+    }
+}
+
+/// Errors when parsing the settings of a sink.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SinkError {
+    /// The aggregation is not `mean` or `max`.
+    InvalidAggregation,
+
+    /// The period is not a positive number of seconds, minutes (`m`), hours (`h`) or days (`d`).
+    InvalidPeriod,
+}
+
+impl Error for SinkError {}
+
+impl Display for SinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SinkError::InvalidAggregation => write!(f, "Invalid aggregation, expected mean or max"),
+            SinkError::InvalidPeriod => write!(f, "Invalid period, expected a positive number like 90, 15m, 1h or 1d"),
+        }
+    }
+}
+
+/// Combines a number of sweeps, or the sweeps of each period of time, into one before forwarding it to a sink.
+///
+/// The combined sweep has the sequence, timestamp and grid of the last sweep. The sweeps left when the sink is closed
+/// are combined and forwarded, and a sweep with a different grid, like after a change of range, discards the sweeps
+/// collected before it.
+pub struct Aggregated<S: Sink> {
+    sink: S,
+    window: Window,
+    aggregation: Aggregation,
+    pending: SpectrumSet,
+    last: Option<SweepRecord>,
+}
+
+impl<S: Sink> Aggregated<S> {
+    /// Wraps `sink`, forwarding one sweep for each `count` sweeps, combined with `aggregation`.
+    pub fn new(sink: S, count: NonZeroUsize, aggregation: Aggregation) -> Self {
+        Self::with_window(sink, Window::Sweeps(count), aggregation)
+    }
+
+    /// Wraps `sink`, forwarding one sweep for each `period`, combined with `aggregation`. The periods are measured with
+    /// the sweep timestamps and aligned to the Unix epoch, so a period of a minute forwards a sweep per clock minute.
+    ///
+    /// # Panics
+    ///
+    /// If `period` is zero.
+    pub fn per_period(sink: S, period: Duration, aggregation: Aggregation) -> Self {
+        assert!(!period.is_zero(), "Invalid period: {:?}", period);
+        Self::with_window(sink, Window::Period(period), aggregation)
+    }
+
+    fn with_window(sink: S, window: Window, aggregation: Aggregation) -> Self {
+        Aggregated {
+            sink,
+            window,
+            aggregation,
+            pending: SpectrumSet::new(),
+            last: None,
+        }
+    }
+
+    /// Returns true if `record` belongs to a later period than the pending sweeps.
+    fn starts_period(&self, record: &SweepRecord) -> bool {
+        let (Window::Period(period), Some(last)) = (self.window, &self.last) else {
+            return false;
+        };
+        let index = |timestamp: SystemTime| {
+            let since_epoch = timestamp.duration_since(UNIX_EPOCH).unwrap_or_default();
+            since_epoch.as_nanos() / period.as_nanos()
+        };
+        index(record.timestamp) != index(last.timestamp)
+    }
+
+    /// Forwards the combination of the pending sweeps, if any.
+    fn flush(&mut self) -> Result<(), Box<dyn Error>> {
+        let (Some(mut record), false) = (self.last.take(), self.pending.is_empty()) else {
+            return Ok(());
+        };
+        record.spectrum = match self.aggregation {
+            Aggregation::Mean => self.pending.mean(),
+            Aggregation::Max => self.pending.max(),
+        };
+        self.pending = SpectrumSet::new();
+        self.sink.write(&record)
+    }
+}
+
+impl<S: Sink> Sink for Aggregated<S> {
+    fn write(&mut self, record: &SweepRecord) -> Result<(), Box<dyn Error>> {
+        if self.starts_period(record) {
+            self.flush()?;
+        }
+        if self.pending.push(record.spectrum.clone()).is_err() {
+            self.pending = SpectrumSet::new();
+            self.pending.push(record.spectrum.clone())?;
+        }
+        self.last = Some(record.clone());
+
+        if matches!(self.window, Window::Sweeps(count) if self.pending.len() >= count.get()) {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), Box<dyn Error>> {
+        self.flush()?;
+        self.sink.close()
+    }
+}
+
+/// Writes the sweeps to a sink from a thread of its own, through a queue of a number of sweeps, dropping the sweeps
+/// that don't fit, so a slow sink, like a client on a congested link, doesn't stall the capture or the other sinks.
+///
+/// A failure of the sink is returned by the following write, or by `close`.
+pub struct Queued {
+    sender: Option<SyncSender<SweepRecord>>,
+    writer: Option<JoinHandle<Result<(), String>>>,
+    dropped: u64,
+}
+
+impl Queued {
+    /// Wraps `sink`, queueing up to `capacity` sweeps.
+    pub fn new<S: Sink + Send + 'static>(mut sink: S, capacity: usize) -> Self {
+        let (sender, receiver) = mpsc::sync_channel::<SweepRecord>(capacity);
+        let writer = thread::spawn(move || {
+            let written = receiver.iter().try_for_each(|record| sink.write(&record));
+            let closed = sink.close();
+            written.and(closed).map_err(|error| error.to_string())
+        });
+        Queued {
+            sender: Some(sender),
+            writer: Some(writer),
+            dropped: 0,
+        }
+    }
+
+    /// Returns the number of sweeps dropped because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Stops the writer thread, once it writes the queued sweeps, and returns its result.
+    fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        self.sender = None;
+        match self.writer.take().map(JoinHandle::join) {
+            Some(Ok(result)) => Ok(result?),
+            Some(Err(_)) => Err("The queued sink panicked".into()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Sink for Queued {
+    fn write(&mut self, record: &SweepRecord) -> Result<(), Box<dyn Error>> {
+        let sender = self.sender.as_ref().ok_or("The queued sink is closed")?;
+        match sender.try_send(record.clone()) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                self.dropped += 1;
+                Ok(())
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                self.finish()?;
+                Err("The queued sink stopped writing".into())
+            }
+        }
+    }
+
+    fn close(&mut self) -> Result<(), Box<dyn Error>> {
+        self.finish()
+    }
+}
+
+/// Identifies a sink in its registry, to remove it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SinkId(u64);
+
+#[derive(Default)]
+struct Registry {
+    next_id: u64,
+    sinks: Vec<(SinkId, Box<dyn Sink + Send>)>,
+}
+
+/// A handle to the sinks of a capture.
+///
+/// Clones share the same sinks, so a sink added to any of them receives the sweeps published by all.
+#[derive(Clone, Default)]
+pub struct SinkRegistry {
+    registry: Arc<Mutex<Registry>>,
+}
+
+impl SinkRegistry {
+    /// Creates a new registry, without sinks.
+    pub fn new() -> Self {
+        SinkRegistry::default()
+    }
+
+    /// Adds a sink, receiving the sweeps published from now on.
+    pub fn add(&self, sink: Box<dyn Sink + Send>) -> SinkId {
+        let mut registry = self.lock();
+        let id = SinkId(registry.next_id);
+        registry.next_id += 1;
+        registry.sinks.push((id, sink));
+        id
+    }
+
+    /// Removes a sink, closing it. Returns false if it was already removed.
+    pub fn remove(&self, id: SinkId) -> bool {
+        let mut registry = self.lock();
+        match registry.sinks.iter().position(|(sink_id, _)| *sink_id == id) {
+            Some(index) => {
+                let _ = registry.sinks.remove(index).1.close();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns true if the sink was not removed, neither explicitly nor after failing.
+    pub fn contains(&self, id: SinkId) -> bool {
+        self.lock().sinks.iter().any(|(sink_id, _)| *sink_id == id)
+    }
+
+    /// Returns the number of sinks.
+    pub fn len(&self) -> usize {
+        self.lock().sinks.len()
+    }
+
+    /// Returns true if there are no sinks.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Writes a sweep to every sink, removing the ones that fail, which are returned with their error.
+    pub fn publish(&self, record: &SweepRecord) -> Vec<(SinkId, Box<dyn Error>)> {
+        let mut failed = Vec::new();
+        self.lock().sinks.retain_mut(|(id, sink)| match sink.write(record) {
+            Ok(()) => true,
+            Err(error) => {
+                failed.push((*id, error));
+                false
+            }
+        });
+        failed
+    }
+
+    /// Closes and removes every sink, when the capture ends. Returns the sinks that failed to close with their error.
+    pub fn close(&self) -> Vec<(SinkId, Box<dyn Error>)> {
+        let sinks = std::mem::take(&mut self.lock().sinks);
+        sinks
+            .into_iter()
+            .filter_map(|(id, mut sink)| sink.close().err().map(|error| (id, error)))
+            .collect()
+    }
+
+    /// Locks the sinks. A sink that panicked doesn't leave the registry unusable, since each write is independent.
+    fn lock(&self) -> MutexGuard<'_, Registry> {
+        self.registry.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io;
+
+    fn a_record() -> SweepRecord {
+        SweepRecord::new(0, Spectrum::from_sweep(868.0e6, 0.1e6, &[-90.0, -72.5]))
+    }
+
+    #[test]
+    fn given_a_sink_added_from_another_thread_when_publish_then_write_to_it() {
+        let registry = SinkRegistry::new();
+        let handle = registry.clone();
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let written = output.clone();
+
+        thread::spawn(move || {
+            handle.add(Box::new(move |record: &SweepRecord| -> Result<(), Box<dyn Error>> {
+                written.lock().unwrap().push(record.spectrum.len());
+                Ok(())
+            }));
+        })
+        .join()
+        .unwrap();
+        registry.publish(&a_record());
+
+        assert_eq!(*output.lock().unwrap(), vec![2]);
+    }
+
+    #[test]
+    fn given_a_failing_sink_when_publish_then_remove_it() {
+        let registry = SinkRegistry::new();
+        let id = registry.add(Box::new(|_: &SweepRecord| -> Result<(), Box<dyn Error>> {
+            Err(io::Error::from(io::ErrorKind::BrokenPipe).into())
+        }));
+        registry.add(Box::new(|_: &SweepRecord| -> Result<(), Box<dyn Error>> { Ok(()) }));
+
+        let failed = registry.publish(&a_record());
+
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0, id);
+        assert_eq!(registry.len(), 1);
+        assert!(!registry.remove(id));
+    }
+
+    /// Returns a sink collecting the records it receives.
+    fn a_collector() -> (Arc<Mutex<Vec<SweepRecord>>>, impl Sink) {
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let collected = records.clone();
+        let sink = move |record: &SweepRecord| -> Result<(), Box<dyn Error>> {
+            collected.lock().unwrap().push(record.clone());
+            Ok(())
+        };
+        (records, sink)
+    }
+
+    #[test]
+    fn given_a_blocked_sink_when_queued_write_then_drop_the_sweeps_that_do_not_fit() {
+        let (records, mut sink) = a_collector();
+        let (release, blocked) = mpsc::channel::<()>();
+        let blocking = move |record: &SweepRecord| -> Result<(), Box<dyn Error>> {
+            blocked.recv()?;
+            sink.write(record)
+        };
+        let mut queued = Queued::new(blocking, 2);
+
+        for _ in 0..10 {
+            queued.write(&a_record()).unwrap();
+        }
+        for _ in 0..10 {
+            let _ = release.send(());
+        }
+        queued.close().unwrap();
+
+        let written = records.lock().unwrap().len() as u64;
+        assert!(queued.dropped() >= 7);
+        assert_eq!(written + queued.dropped(), 10);
+    }
+
+    #[test]
+    fn given_a_failing_sink_when_queued_write_then_return_its_error() {
+        let failing = |_: &SweepRecord| -> Result<(), Box<dyn Error>> { Err(io::Error::other("closed").into()) };
+        let mut queued = Queued::new(failing, 2);
+
+        queued.write(&a_record()).unwrap();
+        let error = (0..100)
+            .find_map(|_| {
+                thread::sleep(Duration::from_millis(1));
+                queued.write(&a_record()).err()
+            })
+            .unwrap();
+
+        assert_eq!(error.to_string(), "closed");
+        assert!(queued.write(&a_record()).is_err());
+    }
+
+    #[test]
+    fn given_a_throttled_sink_when_sweeps_are_faster_than_the_rate_then_drop_them() {
+        let (records, sink) = a_collector();
+        let mut throttled = Throttled::new(sink, 2.0);
+        let start = SystemTime::now();
+
+        for (sequence, millis) in [(0, 0), (1, 200), (2, 500), (3, 900), (4, 1000)] {
+            let mut record = a_record();
+            record.sequence = sequence;
+            record.timestamp = start + Duration::from_millis(millis);
+            throttled.write(&record).unwrap();
+        }
+
+        let sequences: Vec<u64> = records.lock().unwrap().iter().map(|record| record.sequence).collect();
+        assert_eq!(sequences, vec![0, 2, 4]);
+    }
+
+    #[test]
+    fn given_an_aggregated_sink_when_write_then_forward_one_sweep_per_count() {
+        let (records, sink) = a_collector();
+        let mut aggregated = Aggregated::new(sink, NonZeroUsize::new(2).unwrap(), Aggregation::Max);
+
+        for (sequence, power) in [(0, -90.0), (1, -60.0), (2, -80.0)] {
+            let record = SweepRecord::new(sequence, Spectrum::from_sweep(868.0e6, 0.1e6, &[power]));
+            aggregated.write(&record).unwrap();
+        }
+        aggregated.close().unwrap();
+
+        let records = records.lock().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            (records[0].sequence, records[0].spectrum.points()),
+            (1, &[(868.0e6, -60.0)][..])
+        );
+        assert_eq!(
+            (records[1].sequence, records[1].spectrum.points()),
+            (2, &[(868.0e6, -80.0)][..])
+        );
+    }
+
+    #[test]
+    fn given_a_sink_aggregated_per_period_when_write_then_forward_one_sweep_per_period() {
+        let (records, sink) = a_collector();
+        let mut aggregated = Aggregated::per_period(sink, Duration::from_secs(60), Aggregation::Mean);
+        let start = UNIX_EPOCH + Duration::from_secs(1_700_000_080);
+
+        for (sequence, seconds, power) in [(0, 0, -90.0), (1, 10, -90.0), (2, 20, -80.0), (3, 30, -70.0)] {
+            let mut record = SweepRecord::new(sequence, Spectrum::from_sweep(868.0e6, 0.1e6, &[power]));
+            record.timestamp = start + Duration::from_secs(seconds);
+            aggregated.write(&record).unwrap();
+        }
+        aggregated.close().unwrap();
+
+        let records = records.lock().unwrap();
+        let sequences: Vec<u64> = records.iter().map(|record| record.sequence).collect();
+        assert_eq!(sequences, vec![1, 3]);
+        assert!((records[1].spectrum.points()[0].1 - -72.6).abs() < 0.1);
+    }
+
+    #[test]
+    fn given_period_strings_when_parse_period_then_return_durations() {
+        assert_eq!(parse_period("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_period("15m"), Ok(Duration::from_secs(900)));
+        assert_eq!(parse_period("1h"), Ok(Duration::from_secs(3600)));
+        assert_eq!(parse_period("0s"), Err(SinkError::InvalidPeriod));
+        assert_eq!(parse_period("1w"), Err(SinkError::InvalidPeriod));
+        assert_eq!("avg".parse(), Ok(Aggregation::Mean));
+    }
+
+    #[test]
+    fn given_sinks_when_close_then_close_and_remove_them() {
+        struct Closing(Arc<Mutex<bool>>);
+        impl Sink for Closing {
+            fn write(&mut self, _: &SweepRecord) -> Result<(), Box<dyn Error>> {
+                Ok(())
+            }
+            fn close(&mut self) -> Result<(), Box<dyn Error>> {
+                *self.0.lock().unwrap() = true;
+                Ok(())
+            }
+        }
+        let closed = Arc::new(Mutex::new(false));
+        let registry = SinkRegistry::new();
+        registry.add(Box::new(Closing(closed.clone())));
+
+        assert!(registry.close().is_empty());
+
+        assert!(*closed.lock().unwrap());
+        assert!(registry.is_empty());
+    }
+}
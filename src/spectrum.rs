@@ -0,0 +1,762 @@
+//! # Spectrum Module
+//!
+//! This module provides the `Spectrum` type, a measured spectrum represented as a list of points with the frequency,
+//! in Hz, and the power, in dBm, sorted by frequency. It is the result of `Sa430::capture` and `Sa430::measure`, with
+//! the grid of the sweep available from `fstart`, `fstop` and `fstep`, and the sweep parameters, like the resolution
+//! bandwidth and the reference level, from `configs`.
+//!
+//! Spectra from different sweep configurations can be brought to the same frequency grid with
+//! [Spectrum::resample](struct.Spectrum.html#method.resample) and
+//! [Spectrum::crop](struct.Spectrum.html#method.crop), so they can be compared bin to bin.
+//!
+//! FFT based processing usually expects a power of two number of points, which
+//! [Spectrum::align_to_power_of_two](struct.Spectrum.html#method.align_to_power_of_two) provides, either padding the
+//! grid or resampling the measured range, along with a `BinWindow` describing the resulting grid.
+//!
+//! Many sweeps over the same grid can be collected in a `SpectrumSet` to characterize the behavior of a band over
+//! time, with per bin statistics like the mean, the median, percentiles and variance.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use sa430::frequency::Frequency;
+//! use sa430::spectrum::{Interpolation, Spectrum, SpectrumSet};
+//!
+//! let spectrum = Spectrum::from_sweep(433.0e6, 1.0e6, &[-90.0, -70.0, -90.0]);
+//! let resampled = spectrum.resample(Frequency::from_khz(500.0), Interpolation::Linear);
+//!
+//! assert_eq!(resampled.powers().collect::<Vec<_>>(), vec![-90.0, -80.0, -70.0, -80.0, -90.0]);
+//!
+//! let mut set = SpectrumSet::new();
+//! set.push(Spectrum::from_sweep(433.0e6, 1.0e6, &[-90.0, -70.0, -80.0])).unwrap();
+//! set.push(Spectrum::from_sweep(433.0e6, 1.0e6, &[-92.0, -60.0, -80.0])).unwrap();
+//!
+//! assert_eq!(set.median().powers().collect::<Vec<_>>(), vec![-91.0, -65.0, -80.0]);
+//! ```
+
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use crate::frequency::Frequency;
+use crate::power::Dbm;
+use crate::sweep::SweepConfig;
+
+/// Tolerance used when computing the number of points of a frequency grid, in steps.
+const GRID_TOLERANCE: f64 = 1e-9;
+
+/// How to compute the power between two measured points.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Power of the closest point.
+    Nearest,
+
+    /// Linear interpolation of the power, in dB, between the surrounding points.
+    #[default]
+    Linear,
+}
+
+/// How to bring a spectrum to a power of two number of points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinAlignment {
+    /// Extends the grid past the last point, with the same step, filling the new points with the lowest power.
+    Pad,
+
+    /// Resamples the measured range with a smaller step.
+    Resample,
+}
+
+impl FromStr for BinAlignment {
+    type Err = SpectrumError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "pad" => Ok(BinAlignment::Pad),
+            "resample" => Ok(BinAlignment::Resample),
+            _ => Err(SpectrumError::InvalidAlignment),
+        }
+    }
+}
+
+impl Display for BinAlignment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinAlignment::Pad => write!(f, "pad"),
+            BinAlignment::Resample => write!(f, "resample"),
+        }
+    }
+}
+
+/// Frequency grid of a spectrum aligned to a power of two number of points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BinWindow {
+    /// Frequency of the first bin.
+    pub fstart: Frequency,
+
+    /// Distance between bins.
+    pub fstep: Frequency,
+
+    /// Number of bins, a power of two, or 0 for an empty spectrum.
+    pub bins: usize,
+
+    /// Number of points of the spectrum before the alignment.
+    pub measured: usize,
+
+    /// How the spectrum was aligned.
+    pub alignment: BinAlignment,
+}
+
+/// A measured spectrum.
+///
+/// Each point is a `(frequency_hz, power_dbm)` pair and the points are sorted by frequency. Spectra measured by the
+/// device also hold the `SweepConfig` of each measured segment, with the resolution bandwidth, reference level and
+/// crystal used for the points.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Spectrum {
+    points: Vec<(f64, f64)>,
+    configs: Vec<SweepConfig>,
+}
+
+impl Spectrum {
+    /// Creates a new spectrum with the given `(frequency_hz, power_dbm)` points, sorting them by frequency.
+    pub fn new(mut points: Vec<(f64, f64)>) -> Self {
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Spectrum {
+            points,
+            configs: Vec::new(),
+        }
+    }
+
+    /// Creates a new spectrum with the `(frequency_hz, power_dbm)` points measured by the sweep of `config`.
+    pub fn with_config(points: Vec<(f64, f64)>, config: SweepConfig) -> Self {
+        Spectrum {
+            configs: vec![config],
+            ..Spectrum::new(points)
+        }
+    }
+
+    /// Creates a new spectrum from the powers measured by a sweep starting at `fstart` with a step of `fstep`, in Hz.
+    pub fn from_sweep(fstart: f64, fstep: f64, powers: &[f64]) -> Self {
+        let points = powers
+            .iter()
+            .enumerate()
+            .map(|(i, power)| (fstart + i as f64 * fstep, *power))
+            .collect();
+        Spectrum::new(points)
+    }
+
+    /// Merges the spectra of the segments of a sweep into a single spectrum, keeping the sweep configurations of the
+    /// segments in order.
+    pub fn concat<I: IntoIterator<Item = Spectrum>>(segments: I) -> Self {
+        let mut points = Vec::new();
+        let mut configs = Vec::new();
+        for segment in segments {
+            points.extend(segment.points);
+            configs.extend(segment.configs);
+        }
+        Spectrum {
+            configs,
+            ..Spectrum::new(points)
+        }
+    }
+
+    /// Returns the sweep configurations of the measured segments, empty if the spectrum wasn't measured by the
+    /// device or its grid was changed, like by `resample`.
+    pub fn configs(&self) -> &[SweepConfig] {
+        &self.configs
+    }
+
+    /// Returns the resolution bandwidth of the sweep, or `None` if the spectrum has no sweep configuration.
+    ///
+    /// All segments of a sweep share the resolution bandwidth, see `SweepConfig::with_auto_rbw`.
+    pub fn rbw(&self) -> Option<Frequency> {
+        self.configs.first().map(|config| config.rbw)
+    }
+
+    /// Returns the index of the reference level of the sweep in `Calibration::ref_levels`, or `None` if the spectrum
+    /// has no sweep configuration.
+    pub fn ref_level_index(&self) -> Option<u8> {
+        self.configs.first().map(|config| config.ref_level_index)
+    }
+
+    /// Returns the number of points.
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Returns true if the spectrum has no points.
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Returns the `(frequency_hz, power_dbm)` points.
+    pub fn points(&self) -> &[(f64, f64)] {
+        &self.points
+    }
+
+    /// Returns an iterator over the `(frequency_hz, power_dbm)` points.
+    pub fn iter(&self) -> std::slice::Iter<'_, (f64, f64)> {
+        self.points.iter()
+    }
+
+    /// Returns an iterator over the frequencies, in Hz.
+    pub fn frequencies(&self) -> impl Iterator<Item = f64> + '_ {
+        self.points.iter().map(|(frequency, _)| *frequency)
+    }
+
+    /// Returns an iterator over the powers, in dBm.
+    pub fn powers(&self) -> impl Iterator<Item = f64> + '_ {
+        self.points.iter().map(|(_, power)| *power)
+    }
+
+    /// Returns the frequency of the first point.
+    pub fn fstart(&self) -> Option<Frequency> {
+        self.points.first().map(|(frequency, _)| Frequency::from_hz(*frequency))
+    }
+
+    /// Returns the frequency of the last point.
+    pub fn fstop(&self) -> Option<Frequency> {
+        self.points.last().map(|(frequency, _)| Frequency::from_hz(*frequency))
+    }
+
+    /// Returns the distance between the first two points, the step of a spectrum measured by a sweep, or `None` if
+    /// the spectrum has less than two points.
+    pub fn fstep(&self) -> Option<Frequency> {
+        match self.points.as_slice() {
+            [(first, _), (second, _), ..] => Some(Frequency::from_hz(second - first)),
+            _ => None,
+        }
+    }
+
+    /// Returns the mean power of the points, averaged in linear scale, or `None` if the spectrum is empty.
+    pub fn mean_power(&self) -> Option<Dbm> {
+        Dbm::mean(self.powers().map(Dbm::new))
+    }
+
+    /// Returns the power at `frequency`, in dBm, interpolated from the surrounding points.
+    ///
+    /// Returns `None` if the frequency is outside of the spectrum.
+    pub fn power_at(&self, frequency: Frequency, interpolation: Interpolation) -> Option<f64> {
+        let (fstart, fstop) = (self.fstart()?, self.fstop()?);
+        if frequency < fstart || frequency > fstop {
+            return None;
+        }
+
+        let frequency = frequency.hz();
+        let index = self.points.partition_point(|(f, _)| *f < frequency);
+        let (f1, p1) = self.points[index];
+        if f1 == frequency || index == 0 {
+            return Some(p1);
+        }
+
+        let (f0, p0) = self.points[index - 1];
+        let power = match interpolation {
+            Interpolation::Nearest if frequency - f0 <= f1 - frequency => p0,
+            Interpolation::Nearest => p1,
+            Interpolation::Linear => p0 + (p1 - p0) * (frequency - f0) / (f1 - f0),
+        };
+        Some(power)
+    }
+
+    /// Resamples the spectrum to a grid starting at the first point with a step of `fstep`.
+    ///
+    /// # Panics
+    ///
+    /// If `fstep` is not positive.
+    pub fn resample(&self, fstep: Frequency, interpolation: Interpolation) -> Spectrum {
+        assert!(fstep > Frequency::ZERO, "Invalid frequency step: {}", fstep);
+
+        let (fstart, fstop) = match (self.fstart(), self.fstop()) {
+            (Some(fstart), Some(fstop)) => (fstart, fstop),
+            _ => return Spectrum::default(),
+        };
+
+        let count = ((fstop - fstart) / fstep + GRID_TOLERANCE).floor() as usize + 1;
+        let points = (0..count)
+            .map(|i| fstart + i as f64 * fstep)
+            .map(|frequency| {
+                (
+                    frequency.hz(),
+                    self.power_at(frequency.min(fstop), interpolation).unwrap(),
+                )
+            })
+            .collect();
+        Spectrum::new(points)
+    }
+
+    /// Returns the points between `f_lo` and `f_hi`, inclusive.
+    pub fn crop(&self, f_lo: Frequency, f_hi: Frequency) -> Spectrum {
+        let points = self
+            .points
+            .iter()
+            .filter(|(frequency, _)| *frequency >= f_lo.hz() && *frequency <= f_hi.hz())
+            .copied()
+            .collect();
+        Spectrum {
+            points,
+            configs: self.configs.clone(),
+        }
+    }
+
+    /// Returns the spectrum with a power of two number of points and its grid.
+    ///
+    /// The spectrum must have a uniform grid, like the ones created by a sweep, as padding continues it with the step
+    /// between the first two points. Spectra that already have a power of two number of points are kept unchanged.
+    pub fn align_to_power_of_two(
+        &self,
+        alignment: BinAlignment,
+        interpolation: Interpolation,
+    ) -> (Spectrum, BinWindow) {
+        let measured = self.len();
+        let bins = match measured {
+            0 => 0,
+            count => count.next_power_of_two(),
+        };
+        let fstart = self.fstart().unwrap_or_default();
+        let fstep = self.fstep().unwrap_or_default();
+        let window = BinWindow {
+            fstart,
+            fstep,
+            bins,
+            measured,
+            alignment,
+        };
+
+        if bins == measured {
+            return (self.clone(), window);
+        }
+
+        let spectrum = match alignment {
+            BinAlignment::Pad => {
+                let floor = self.powers().fold(f64::INFINITY, f64::min);
+                let padding = (measured..bins).map(|i| ((fstart + i as f64 * fstep).hz(), floor));
+                Spectrum::new(self.points.iter().copied().chain(padding).collect())
+            }
+            BinAlignment::Resample => {
+                let fstop = self.fstop().unwrap_or_default();
+                let fstep = (fstop - fstart) / (bins - 1) as f64;
+                let mut resampled = self.resample(fstep, interpolation);
+                resampled.points.truncate(bins);
+                return (resampled, BinWindow { fstep, ..window });
+            }
+        };
+        (spectrum, window)
+    }
+}
+
+impl<'a> IntoIterator for &'a Spectrum {
+    type Item = &'a (f64, f64);
+    type IntoIter = std::slice::Iter<'a, (f64, f64)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Errors when collecting spectra in a `SpectrumSet`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpectrumError {
+    /// The spectrum frequencies don't match the set grid.
+    GridMismatch,
+
+    /// The bin alignment is not `pad` or `resample`.
+    InvalidAlignment,
+}
+
+impl Error for SpectrumError {}
+
+impl Display for SpectrumError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpectrumError::GridMismatch => write!(f, "Spectrum frequencies don't match the set grid"),
+            SpectrumError::InvalidAlignment => write!(f, "Invalid bin alignment, expected pad or resample"),
+        }
+    }
+}
+
+/// A collection of spectra measured over the same frequency grid.
+///
+/// The aggregations are computed per bin, across all spectra, and return a spectrum with the set grid, which is empty
+/// if the set has no spectra.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpectrumSet {
+    spectra: Vec<Spectrum>,
+}
+
+impl SpectrumSet {
+    /// Creates a new empty set.
+    pub fn new() -> Self {
+        SpectrumSet::default()
+    }
+
+    /// Adds a spectrum to the set.
+    ///
+    /// Returns `SpectrumError::GridMismatch` if its frequencies differ from the spectra already in the set, resample
+    /// and crop it first to bring it to the set grid.
+    pub fn push(&mut self, spectrum: Spectrum) -> Result<(), SpectrumError> {
+        if let Some(first) = self.spectra.first() {
+            if !first.frequencies().eq(spectrum.frequencies()) {
+                return Err(SpectrumError::GridMismatch);
+            }
+        }
+
+        self.spectra.push(spectrum);
+        Ok(())
+    }
+
+    /// Returns the number of spectra in the set.
+    pub fn len(&self) -> usize {
+        self.spectra.len()
+    }
+
+    /// Returns true if the set has no spectra.
+    pub fn is_empty(&self) -> bool {
+        self.spectra.is_empty()
+    }
+
+    /// Returns the spectra in the set.
+    pub fn spectra(&self) -> &[Spectrum] {
+        &self.spectra
+    }
+
+    /// Returns the mean power of each bin.
+    ///
+    /// The powers are averaged in linear scale (mW), so a few strong sweeps weigh more than in a plain dB average.
+    pub fn mean(&self) -> Spectrum {
+        // Bins are only aggregated for a set with spectra, so there's always a mean.
+        self.aggregate(|powers| {
+            Dbm::mean(powers.iter().copied().map(Dbm::new))
+                .unwrap_or_default()
+                .dbm()
+        })
+    }
+
+    /// Returns the median power of each bin.
+    pub fn median(&self) -> Spectrum {
+        self.percentile(50.0)
+    }
+
+    /// Returns the `p` percentile of the power of each bin, interpolating between the closest ranks.
+    ///
+    /// Low percentiles, like the 10th, estimate the noise floor of the band.
+    ///
+    /// # Panics
+    ///
+    /// If `p` is not between 0 and 100.
+    pub fn percentile(&self, p: f64) -> Spectrum {
+        assert!((0.0..=100.0).contains(&p), "Invalid percentile: {}", p);
+
+        self.aggregate(|powers| {
+            powers.sort_by(f64::total_cmp);
+            let rank = p / 100.0 * (powers.len() - 1) as f64;
+            let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+            powers[lower] + (powers[upper] - powers[lower]) * (rank - lower as f64)
+        })
+    }
+
+    /// Returns the population variance of the power of each bin, in dB².
+    pub fn variance(&self) -> Spectrum {
+        self.aggregate(|powers| {
+            let count = powers.len() as f64;
+            let mean = powers.iter().sum::<f64>() / count;
+            powers.iter().map(|power| (power - mean).powi(2)).sum::<f64>() / count
+        })
+    }
+
+    /// Returns the minimum power of each bin.
+    pub fn min(&self) -> Spectrum {
+        self.aggregate(|powers| powers.iter().copied().fold(f64::INFINITY, f64::min))
+    }
+
+    /// Returns the maximum power of each bin, like a peak hold trace.
+    pub fn max(&self) -> Spectrum {
+        self.aggregate(|powers| powers.iter().copied().fold(f64::NEG_INFINITY, f64::max))
+    }
+
+    /// Applies `function` to the powers of each bin across all spectra.
+    fn aggregate<F: Fn(&mut [f64]) -> f64>(&self, function: F) -> Spectrum {
+        let first = match self.spectra.first() {
+            Some(first) => first,
+            None => return Spectrum::default(),
+        };
+
+        let mut powers = Vec::with_capacity(self.spectra.len());
+        let points = first
+            .frequencies()
+            .enumerate()
+            .map(|(bin, frequency)| {
+                powers.clear();
+                powers.extend(self.spectra.iter().map(|spectrum| spectrum.points[bin].1));
+                (frequency, function(&mut powers))
+            })
+            .collect();
+        Spectrum {
+            points,
+            configs: first.configs.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MHZ: Frequency = Frequency::from_mhz(1.0);
+
+    fn a_spectrum() -> Spectrum {
+        Spectrum::from_sweep(100.0, 10.0, &[-90.0, -70.0, -80.0, -100.0])
+    }
+
+    #[test]
+    fn given_unsorted_points_when_new_then_sort_by_frequency() {
+        let spectrum = Spectrum::new(vec![(20.0, -1.0), (10.0, -2.0)]);
+        assert_eq!(spectrum.points(), &[(10.0, -2.0), (20.0, -1.0)]);
+    }
+
+    #[test]
+    fn given_segments_when_concat_then_merge_the_points_by_frequency() {
+        let high = Spectrum::from_sweep(389.0, 1.0, &[-70.0, -71.0]);
+        let low = Spectrum::from_sweep(347.0, 1.0, &[-90.0, -91.0]);
+
+        let spectrum = Spectrum::concat([high, low]);
+
+        assert_eq!(
+            spectrum.points(),
+            &[(347.0, -90.0), (348.0, -91.0), (389.0, -70.0), (390.0, -71.0)]
+        );
+    }
+
+    #[test]
+    fn given_measured_segments_when_concat_then_keep_their_configs() {
+        let xtal = Frequency::from_mhz(26.0);
+        let low = SweepConfig::with_auto_rbw(Frequency::from_mhz(347.0), Frequency::from_mhz(348.0), MHZ, 0, xtal);
+        let high = SweepConfig::with_auto_rbw(Frequency::from_mhz(389.0), Frequency::from_mhz(390.0), MHZ, 0, xtal);
+
+        let spectrum = Spectrum::concat([
+            Spectrum::with_config(vec![(389.0e6, -70.0), (390.0e6, -71.0)], high),
+            Spectrum::with_config(vec![(347.0e6, -90.0), (348.0e6, -91.0)], low),
+        ]);
+
+        assert_eq!(spectrum.configs(), [high, low]);
+        assert_eq!(spectrum.rbw(), Some(high.rbw));
+        assert_eq!(spectrum.ref_level_index(), Some(0));
+        assert_eq!(
+            spectrum
+                .crop(Frequency::from_mhz(300.0), Frequency::from_mhz(350.0))
+                .configs(),
+            [high, low]
+        );
+        assert!(spectrum.resample(MHZ, Interpolation::Linear).configs().is_empty());
+    }
+
+    #[test]
+    fn given_points_when_new_then_have_no_config() {
+        let spectrum = a_spectrum();
+
+        assert!(spectrum.configs().is_empty());
+        assert_eq!(spectrum.rbw(), None);
+        assert_eq!(spectrum.ref_level_index(), None);
+    }
+
+    #[test]
+    fn given_a_sweep_when_fstep_then_return_the_distance_between_the_first_points() {
+        assert_eq!(a_spectrum().fstep(), Some(Frequency::from_hz(10.0)));
+        assert_eq!(Spectrum::from_sweep(433.0e6, 1.0e6, &[-90.0]).fstep(), None);
+    }
+
+    #[test]
+    fn given_sweep_powers_when_from_sweep_then_compute_frequencies() {
+        let spectrum = a_spectrum();
+
+        assert_eq!(spectrum.len(), 4);
+        assert_eq!(
+            spectrum.frequencies().collect::<Vec<_>>(),
+            vec![100.0, 110.0, 120.0, 130.0]
+        );
+        assert_eq!(spectrum.fstart(), Some(Frequency::from_hz(100.0)));
+        assert_eq!(spectrum.fstop(), Some(Frequency::from_hz(130.0)));
+    }
+
+    #[test]
+    fn given_a_frequency_between_points_when_power_at_then_interpolate() {
+        let spectrum = a_spectrum();
+
+        assert_eq!(
+            spectrum.power_at(Frequency::from_hz(105.0), Interpolation::Linear),
+            Some(-80.0)
+        );
+        assert_eq!(
+            spectrum.power_at(Frequency::from_hz(104.0), Interpolation::Nearest),
+            Some(-90.0)
+        );
+        assert_eq!(
+            spectrum.power_at(Frequency::from_hz(106.0), Interpolation::Nearest),
+            Some(-70.0)
+        );
+        assert_eq!(
+            spectrum.power_at(Frequency::from_hz(130.0), Interpolation::Linear),
+            Some(-100.0)
+        );
+    }
+
+    #[test]
+    fn given_a_frequency_outside_the_spectrum_when_power_at_then_return_none() {
+        let spectrum = a_spectrum();
+
+        assert_eq!(spectrum.power_at(Frequency::from_hz(99.0), Interpolation::Linear), None);
+        assert_eq!(
+            spectrum.power_at(Frequency::from_hz(131.0), Interpolation::Linear),
+            None
+        );
+    }
+
+    #[test]
+    fn given_a_smaller_step_when_resample_then_interpolate_new_points() {
+        let resampled = a_spectrum().resample(Frequency::from_hz(5.0), Interpolation::Linear);
+
+        assert_eq!(
+            resampled.points(),
+            &[
+                (100.0, -90.0),
+                (105.0, -80.0),
+                (110.0, -70.0),
+                (115.0, -75.0),
+                (120.0, -80.0),
+                (125.0, -90.0),
+                (130.0, -100.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn given_a_larger_step_when_resample_then_stop_before_the_last_point() {
+        let resampled = a_spectrum().resample(Frequency::from_hz(20.0), Interpolation::Nearest);
+
+        assert_eq!(resampled.points(), &[(100.0, -90.0), (120.0, -80.0)]);
+    }
+
+    #[test]
+    fn given_an_empty_spectrum_when_resample_then_return_an_empty_spectrum() {
+        assert!(Spectrum::default()
+            .resample(Frequency::from_hz(1.0), Interpolation::Linear)
+            .is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn given_a_zero_step_when_resample_then_panic() {
+        a_spectrum().resample(Frequency::from_hz(0.0), Interpolation::Linear);
+    }
+
+    #[test]
+    fn given_a_spectrum_when_align_with_pad_then_extend_the_grid_with_the_lowest_power() {
+        let spectrum = Spectrum::from_sweep(868.0e6, 0.5e6, &[-90.0, -70.0, -95.0, -80.0, -85.0]);
+
+        let (aligned, window) = spectrum.align_to_power_of_two(BinAlignment::Pad, Interpolation::Linear);
+
+        assert_eq!(aligned.len(), 8);
+        assert_eq!(&aligned.points()[..5], spectrum.points());
+        assert_eq!(aligned.points()[7], (871.5e6, -95.0));
+        assert_eq!(
+            (window.fstart, window.fstep, window.bins, window.measured),
+            (Frequency::from_mhz(868.0), Frequency::from_khz(500.0), 8, 5)
+        );
+    }
+
+    #[test]
+    fn given_a_spectrum_when_align_with_resample_then_keep_the_measured_range() {
+        let spectrum = Spectrum::from_sweep(868.0e6, 1.0e6, &[-90.0, -80.0, -70.0]);
+
+        let (aligned, window) = spectrum.align_to_power_of_two(BinAlignment::Resample, Interpolation::Linear);
+
+        assert_eq!(aligned.len(), 4);
+        assert_eq!(aligned.fstart(), Some(Frequency::from_mhz(868.0)));
+        assert!((aligned.fstop().unwrap().hz() - 870.0e6).abs() < 1e-3);
+        assert!((aligned.points()[1].1 + 83.333).abs() < 1e-3);
+        assert!((window.fstep.hz() - 2.0e6 / 3.0).abs() < 1e-6);
+        assert_eq!((window.bins, window.measured), (4, 3));
+    }
+
+    #[test]
+    fn given_a_power_of_two_spectrum_when_align_then_keep_it_unchanged() {
+        let spectrum = Spectrum::from_sweep(868.0e6, 1.0e6, &[-90.0, -80.0, -70.0, -60.0]);
+
+        let (aligned, window) = spectrum.align_to_power_of_two(BinAlignment::Resample, Interpolation::Linear);
+
+        assert_eq!(aligned, spectrum);
+        assert_eq!((window.fstep, window.bins), (Frequency::from_mhz(1.0), 4));
+    }
+
+    #[test]
+    fn given_a_range_when_crop_then_keep_the_points_inside_the_range() {
+        let cropped = a_spectrum().crop(Frequency::from_hz(105.0), Frequency::from_hz(120.0));
+
+        assert_eq!(cropped.points(), &[(110.0, -70.0), (120.0, -80.0)]);
+    }
+
+    fn a_spectrum_set() -> SpectrumSet {
+        let mut set = SpectrumSet::new();
+        set.push(Spectrum::from_sweep(100.0, 10.0, &[-90.0, -60.0])).unwrap();
+        set.push(Spectrum::from_sweep(100.0, 10.0, &[-80.0, -60.0])).unwrap();
+        set.push(Spectrum::from_sweep(100.0, 10.0, &[-100.0, -60.0])).unwrap();
+        set
+    }
+
+    #[test]
+    fn given_a_spectrum_with_another_grid_when_push_then_return_grid_mismatch() {
+        let mut set = a_spectrum_set();
+
+        let result = set.push(Spectrum::from_sweep(100.0, 5.0, &[-90.0, -60.0]));
+
+        assert_eq!(result, Err(SpectrumError::GridMismatch));
+        assert_eq!(set.len(), 3);
+    }
+
+    #[test]
+    fn given_a_spectrum_set_when_median_then_return_the_middle_power_of_each_bin() {
+        let median = a_spectrum_set().median();
+        assert_eq!(median.points(), &[(100.0, -90.0), (110.0, -60.0)]);
+    }
+
+    #[test]
+    fn given_a_spectrum_set_when_percentile_then_interpolate_between_ranks() {
+        let set = a_spectrum_set();
+
+        assert_eq!(set.percentile(0.0).powers().collect::<Vec<_>>(), vec![-100.0, -60.0]);
+        assert_eq!(set.percentile(25.0).powers().collect::<Vec<_>>(), vec![-95.0, -60.0]);
+        assert_eq!(set.percentile(100.0).powers().collect::<Vec<_>>(), vec![-80.0, -60.0]);
+    }
+
+    #[test]
+    fn given_a_spectrum_set_when_mean_then_average_in_linear_scale() {
+        let mean = a_spectrum_set().mean();
+        let powers: Vec<f64> = mean.powers().collect();
+
+        assert!((powers[0] - -84.32).abs() < 0.01);
+        assert!((powers[1] - -60.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn given_a_spectrum_set_when_variance_then_return_the_variance_of_each_bin() {
+        let variance = a_spectrum_set().variance();
+        let powers: Vec<f64> = variance.powers().collect();
+
+        assert!((powers[0] - 200.0 / 3.0).abs() < 1e-9);
+        assert_eq!(powers[1], 0.0);
+    }
+
+    #[test]
+    fn given_a_spectrum_set_when_min_and_max_then_return_the_extremes_of_each_bin() {
+        let set = a_spectrum_set();
+
+        assert_eq!(set.min().powers().collect::<Vec<_>>(), vec![-100.0, -60.0]);
+        assert_eq!(set.max().powers().collect::<Vec<_>>(), vec![-80.0, -60.0]);
+    }
+
+    #[test]
+    fn given_an_empty_spectrum_set_when_aggregate_then_return_an_empty_spectrum() {
+        assert!(SpectrumSet::new().mean().is_empty());
+    }
+}
@@ -0,0 +1,213 @@
+//! # Storage Module
+//!
+//! This module provides the `Storage` trait, where recordings of capture sessions, like CSV traces or binary captures,
+//! are written and read by name, and `LocalStorage`, which stores them as the files of a directory.
+//!
+//! Tools reading or writing recordings through the trait work unchanged with other backends, like object storage for
+//! drive-test rigs uploading their sessions directly. Backends that store whole objects upload a recording when its
+//! writer is flushed, as the [`sink`](crate::sink) formats do when the capture ends.
+//!
+//! Recorders check write errors with `is_storage_full`, to stop cleanly when the filesystem fills instead of failing on
+//! every sweep, and write through `SyncedFile`, which flushes the data to the disk periodically, so a power loss only
+//! loses the last seconds of a recording.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use std::io::{Read, Write};
+//!
+//! use sa430::storage::{LocalStorage, Storage};
+//!
+//! let dir = std::env::temp_dir().join(format!("sa430-storage-doc-{}", std::process::id()));
+//! let storage = LocalStorage::new(&dir);
+//!
+//! let mut recording = storage.create("eu868.csv").unwrap();
+//! recording.write_all(b"868.0,-90\n").unwrap();
+//! recording.flush().unwrap();
+//! drop(recording);
+//!
+//! let mut data = String::new();
+//! storage.open("eu868.csv").unwrap().read_to_string(&mut data).unwrap();
+//! assert_eq!(storage.list().unwrap(), vec!["eu868.csv"]);
+//! assert_eq!(data, "868.0,-90\n");
+//! # std::fs::remove_dir_all(&dir).unwrap();
+//! ```
+
+use std::{
+    error::Error,
+    fmt, fs,
+    io::{self, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Default period between the syncs of a [`SyncedFile`].
+pub const DEFAULT_SYNC_INTERVAL: Duration = Duration::from_secs(10);
+
+/// A place where recordings are stored by name.
+///
+/// Names are single path components, like `eu868-0001.csv`, so they map to files and to object keys alike.
+pub trait Storage: Send + Sync {
+    /// Returns the names of the recordings, sorted.
+    fn list(&self) -> Result<Vec<String>, Box<dyn Error>>;
+
+    /// Opens a recording for reading.
+    fn open(&self, name: &str) -> Result<Box<dyn Read + Send>, Box<dyn Error>>;
+
+    /// Creates a recording, replacing the one with the same name. It is stored, at the latest, when the writer is
+    /// flushed.
+    fn create(&self, name: &str) -> Result<Box<dyn Write + Send>, Box<dyn Error>>;
+
+    /// Returns when a recording was last written, if the backend keeps it.
+    fn modified(&self, _name: &str) -> Result<Option<SystemTime>, Box<dyn Error>> {
+        Ok(None)
+    }
+
+    /// Returns a description of where the recordings are stored, for messages.
+    fn location(&self) -> String;
+}
+
+/// Stores the recordings as the files of a directory, created when the first recording is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalStorage {
+    root: PathBuf,
+}
+
+impl LocalStorage {
+    /// Creates a storage in the directory `root`.
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        LocalStorage {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    fn path(&self, name: &str) -> Result<PathBuf, StorageError> {
+        validate_name(name)?;
+        Ok(self.root.join(name))
+    }
+}
+
+impl Storage for LocalStorage {
+    fn list(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut names = Vec::new();
+        for entry in fs::read_dir(&self.root)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                names.push(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    fn open(&self, name: &str) -> Result<Box<dyn Read + Send>, Box<dyn Error>> {
+        Ok(Box::new(fs::File::open(self.path(name)?)?))
+    }
+
+    fn create(&self, name: &str) -> Result<Box<dyn Write + Send>, Box<dyn Error>> {
+        let path = self.path(name)?;
+        fs::create_dir_all(&self.root)?;
+        Ok(Box::new(BufWriter::new(fs::File::create(path)?)))
+    }
+
+    fn modified(&self, name: &str) -> Result<Option<SystemTime>, Box<dyn Error>> {
+        Ok(Some(fs::metadata(self.path(name)?)?.modified()?))
+    }
+
+    fn location(&self) -> String {
+        self.root.display().to_string()
+    }
+}
+
+/// A file whose data is synced to the disk at most every interval, when written, and when flushed.
+pub struct SyncedFile {
+    file: fs::File,
+    interval: Duration,
+    last_sync: Instant,
+}
+
+impl SyncedFile {
+    /// Wraps `file`, syncing it every `interval`.
+    pub fn new(file: fs::File, interval: Duration) -> Self {
+        SyncedFile {
+            file,
+            interval,
+            last_sync: Instant::now(),
+        }
+    }
+}
+
+impl Write for SyncedFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.file.write(buf)?;
+        if self.last_sync.elapsed() >= self.interval {
+            self.file.sync_data()?;
+            self.last_sync = Instant::now();
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        self.file.sync_data()?;
+        self.last_sync = Instant::now();
+        Ok(())
+    }
+}
+
+/// Returns true if `error` is a write error caused by the storage having no space left, or the quota being exceeded.
+pub fn is_storage_full(error: &(dyn Error + 'static)) -> bool {
+    error
+        .downcast_ref::<io::Error>()
+        .is_some_and(|error| matches!(error.kind(), io::ErrorKind::StorageFull | io::ErrorKind::QuotaExceeded))
+}
+
+/// Fails if `name` is not a single path component.
+fn validate_name(name: &str) -> Result<(), StorageError> {
+    match name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+        true => Err(StorageError::InvalidName(name.to_string())),
+        false => Ok(()),
+    }
+}
+
+/// Storage errors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StorageError {
+    /// The name of a recording is empty or has path separators.
+    InvalidName(String),
+}
+
+impl Error for StorageError {}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::InvalidName(name) => write!(f, "Invalid recording name: \"{}\"", name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_a_name_with_path_separators_when_create_then_return_invalid_name() {
+        let storage = LocalStorage::new(std::env::temp_dir().join("sa430-storage-invalid"));
+
+        for name in ["", "..", "../escape.csv", "session/eu868.csv"] {
+            let error = storage.create(name).err().unwrap();
+            assert_eq!(error.downcast_ref(), Some(&StorageError::InvalidName(name.to_string())));
+        }
+    }
+
+    #[test]
+    fn given_write_errors_when_is_storage_full_then_detect_a_full_filesystem() {
+        let full: Box<dyn Error> = Box::new(io::Error::from(io::ErrorKind::StorageFull));
+        let other: Box<dyn Error> = Box::new(io::Error::from(io::ErrorKind::BrokenPipe));
+
+        assert!(is_storage_full(&*full));
+        assert!(!is_storage_full(&*other));
+        assert!(!is_storage_full(&StorageError::InvalidName("a/b".to_string())));
+    }
+}
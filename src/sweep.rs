@@ -0,0 +1,544 @@
+//! # Sweep Module
+//!
+//! This module provides `SweepConfig`, the parameters of a spectrum measurement, and `SweepRegisters`, their encoding
+//! in the registers of the device, set with one command each before measuring the spectrum with `GetSpecNoInit`:
+//!
+//! | Command     | Payload | Register                                                                 |
+//! |-------------|---------|--------------------------------------------------------------------------|
+//! | `SetFStart` | u8[3]   | Start frequency, compensated for the crystal                            |
+//! | `SetFStop`  | u8[3]   | Stop frequency, compensated for the crystal                             |
+//! | `SetFStep`  | u8[2]   | Step frequency, compensated for the crystal                             |
+//! | `SetRbw`    | u8      | RegValue of the resolution bandwidth in Table 8 of the protocol         |
+//! | `SetIf`     | u8      | RegValueIf of the resolution bandwidth in Table 8 of the protocol       |
+//! | `SetGain`   | u8      | RegValue of the reference level in Table 9 of the protocol              |
+//!
+//! The registers are big endian, like the rest of the protocol. The frequencies are compensated for the deviation of
+//! the crystal oscillator, with its frequency in the calibration, as the TI software does:
+//!
+//! ```text
+//! register = f[MHz] · 65536 / xtal[MHz], truncated
+//! ```
+//!
+//! The device measures from the start register to the stop register, in steps of the step register, so the frequency
+//! axis of a measurement, `bin_count` and `frequency_at`, is computed from the registers, not from the requested
+//! frequencies: the bins are up to a register step, about 400 Hz, below them, and the step is rounded down too.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use sa430::frequency::Frequency;
+//! use sa430::sweep::{SweepConfig, NOMINAL_XTAL_HZ};
+//!
+//! let config = SweepConfig {
+//!     fstart: Frequency::from_mhz(868.0),
+//!     fstop: Frequency::from_mhz(870.0),
+//!     fstep: Frequency::from_khz(100.0),
+//!     rbw: Frequency::from_khz(58.0),
+//!     ref_level_index: 0,
+//!     xtal: Frequency::from_hz(NOMINAL_XTAL_HZ),
+//! };
+//!
+//! let registers = config.registers().unwrap();
+//!
+//! assert_eq!(registers.fstart, 0x216276);
+//! assert_eq!(registers.rbw, 240);
+//! assert_eq!(registers.gain, 128);
+//! ```
+
+use std::{
+    error::Error,
+    fmt::{self, Display},
+};
+
+use super::frame::{Command, Frame};
+use super::frequency::Frequency;
+
+/// Nominal frequency of the crystal of the device, in Hz.
+pub const NOMINAL_XTAL_HZ: f64 = 26e6;
+
+/// Resolution bandwidths of the Rx filter, in kHz, with the values of the `SetRbw` and `SetIf` registers of each
+/// (Table 8 of the protocol).
+pub const RBW_TABLE: [(f64, u8, u8); 16] = [
+    (58.0, 240, 8),
+    (67.7, 224, 7),
+    (81.3, 208, 7),
+    (101.6, 192, 8),
+    (116.1, 176, 7),
+    (135.4, 160, 7),
+    (162.5, 144, 8),
+    (203.1, 128, 8),
+    (232.1, 112, 8),
+    (270.8, 96, 10),
+    (325.0, 80, 11),
+    (406.3, 64, 10),
+    (464.3, 48, 12),
+    (541.7, 32, 13),
+    (650.0, 16, 16),
+    (812.5, 0, 18),
+];
+
+/// Reference levels, in dBm, with the value of the `SetGain` register of each, in the order of the reference levels
+/// of the calibration (Table 9 of the protocol).
+pub const REF_LEVEL_TABLE: [(i8, u8); 8] = [
+    (-35, 128),
+    (-40, 144),
+    (-45, 145),
+    (-50, 74),
+    (-55, 12),
+    (-60, 179),
+    (-65, 44),
+    (-70, 61),
+];
+
+/// Largest value of the start and stop frequency registers, 3 bytes.
+const MAX_FREQUENCY_REGISTER: u32 = 0x00FF_FFFF;
+
+/// Parameters of a spectrum measurement.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SweepConfig {
+    /// Start frequency.
+    pub fstart: Frequency,
+
+    /// Stop frequency.
+    pub fstop: Frequency,
+
+    /// Step frequency.
+    pub fstep: Frequency,
+
+    /// Resolution bandwidth of the Rx filter, one of `RBW_TABLE`.
+    pub rbw: Frequency,
+
+    /// Index of the reference level in `REF_LEVEL_TABLE` and `Calibration::ref_levels`.
+    pub ref_level_index: u8,
+
+    /// Frequency of the crystal of the device, see `Calibration::xtal`.
+    pub xtal: Frequency,
+}
+
+/// Registers of the device set up for a spectrum measurement.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SweepRegisters {
+    /// Start frequency, 3 bytes.
+    pub fstart: u32,
+
+    /// Stop frequency, 3 bytes.
+    pub fstop: u32,
+
+    /// Step frequency.
+    pub fstep: u16,
+
+    /// Resolution bandwidth of the Rx filter.
+    pub rbw: u8,
+
+    /// Intermediate frequency.
+    pub intermediate_frequency: u8,
+
+    /// Gain of the Rx path.
+    pub gain: u8,
+}
+
+/// Errors when encoding or decoding a sweep configuration.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum SweepConfigError {
+    /// The step isn't positive or the start frequency is above the stop frequency.
+    InvalidSweep(Frequency, Frequency, Frequency),
+
+    /// The frequency of the named field doesn't fit in its register.
+    OutOfRange(&'static str, Frequency),
+
+    /// The resolution bandwidth isn't in `RBW_TABLE`.
+    UnknownRbw(Frequency),
+
+    /// The reference level index isn't in `REF_LEVEL_TABLE`.
+    UnknownRefLevel(u8),
+
+    /// The value of the named register isn't in the tables of the protocol.
+    UnknownRegister(&'static str, u8),
+}
+
+impl Error for SweepConfigError {}
+
+impl Display for SweepConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SweepConfigError::InvalidSweep(fstart, fstop, fstep) => {
+                write!(f, "Invalid sweep: {} to {} with step {}", fstart, fstop, fstep)
+            }
+            SweepConfigError::OutOfRange(field, frequency) => {
+                write!(f, "Invalid {}: {} is out of range", field, frequency)
+            }
+            SweepConfigError::UnknownRbw(rbw) => write!(f, "Unknown resolution bandwidth: {}", rbw),
+            SweepConfigError::UnknownRefLevel(index) => write!(f, "Unknown reference level index: {}", index),
+            SweepConfigError::UnknownRegister(register, value) => {
+                write!(f, "Unknown value of the {} register: {}", register, value)
+            }
+        }
+    }
+}
+
+impl SweepConfig {
+    /// Creates the configuration of a sweep with the narrowest resolution bandwidth that is at least twice the step,
+    /// or the widest one for larger steps, for a crystal of `xtal`.
+    pub fn with_auto_rbw(
+        fstart: Frequency,
+        fstop: Frequency,
+        fstep: Frequency,
+        ref_level_index: u8,
+        xtal: Frequency,
+    ) -> Self {
+        let (rbw, _, _) = RBW_TABLE
+            .iter()
+            .copied()
+            .find(|(rbw, _, _)| Frequency::from_khz(*rbw) >= 2.0 * fstep)
+            .unwrap_or(RBW_TABLE[RBW_TABLE.len() - 1]);
+        SweepConfig {
+            fstart,
+            fstop,
+            fstep,
+            rbw: Frequency::from_khz(rbw),
+            ref_level_index,
+            xtal,
+        }
+    }
+
+    /// Creates the configuration of the sweep set up in `registers`, for a crystal of `xtal`.
+    ///
+    /// The frequencies are in the middle of the step of their registers, so they encode back to the same registers.
+    /// Fails if the resolution bandwidth or the gain aren't in the tables of the protocol.
+    pub fn from_registers(registers: &SweepRegisters, xtal: Frequency) -> Result<Self, SweepConfigError> {
+        let (rbw, _, _) = RBW_TABLE
+            .iter()
+            .find(|(_, rbw, intermediate_frequency)| {
+                (*rbw, *intermediate_frequency) == (registers.rbw, registers.intermediate_frequency)
+            })
+            .ok_or(SweepConfigError::UnknownRegister("rbw", registers.rbw))?;
+        let ref_level_index = REF_LEVEL_TABLE
+            .iter()
+            .position(|(_, gain)| *gain == registers.gain)
+            .ok_or(SweepConfigError::UnknownRegister("gain", registers.gain))?;
+
+        let frequency = |register: u32| Frequency::from_hz(to_hz(register as f64 + 0.5, xtal));
+        Ok(SweepConfig {
+            fstart: frequency(registers.fstart),
+            fstop: frequency(registers.fstop),
+            fstep: frequency(registers.fstep as u32),
+            rbw: Frequency::from_khz(*rbw),
+            ref_level_index: ref_level_index as u8,
+            xtal,
+        })
+    }
+
+    /// Encodes the configuration in the registers of the device.
+    ///
+    /// Fails if the step isn't positive, the start is above the stop, a frequency doesn't fit in its register, or the
+    /// resolution bandwidth or the reference level aren't in the tables of the protocol.
+    pub fn registers(&self) -> Result<SweepRegisters, SweepConfigError> {
+        let fstep = compensate(self.fstep, self.xtal);
+        if fstep == 0 || self.fstart > self.fstop {
+            return Err(SweepConfigError::InvalidSweep(self.fstart, self.fstop, self.fstep));
+        }
+
+        let (_, rbw, intermediate_frequency) = RBW_TABLE
+            .iter()
+            .copied()
+            .find(|(rbw, _, _)| Frequency::from_khz(*rbw) == self.rbw)
+            .ok_or(SweepConfigError::UnknownRbw(self.rbw))?;
+        let (_, gain) = REF_LEVEL_TABLE
+            .get(self.ref_level_index as usize)
+            .ok_or(SweepConfigError::UnknownRefLevel(self.ref_level_index))?;
+
+        let register = |field: &'static str, frequency: Frequency, max: u32| match compensate(frequency, self.xtal) {
+            register if frequency >= Frequency::ZERO && register <= max => Ok(register),
+            _ => Err(SweepConfigError::OutOfRange(field, frequency)),
+        };
+        Ok(SweepRegisters {
+            fstart: register("fstart", self.fstart, MAX_FREQUENCY_REGISTER)?,
+            fstop: register("fstop", self.fstop, MAX_FREQUENCY_REGISTER)?,
+            fstep: register("fstep", self.fstep, u16::MAX as u32)? as u16,
+            rbw,
+            intermediate_frequency,
+            gain: *gain,
+        })
+    }
+
+    /// Returns the number of bins the device measures, 0 if the configuration can't be encoded, see
+    /// [`SweepRegisters::bin_count`].
+    pub fn bin_count(&self) -> usize {
+        self.registers().map_or(0, |registers| registers.bin_count())
+    }
+
+    /// Returns the frequency the device tunes to for the bin `index`, see [`SweepRegisters::frequency_at`].
+    ///
+    /// The index isn't checked against `bin_count`, and the frequency is 0 Hz if the configuration can't be encoded.
+    pub fn frequency_at(&self, index: usize) -> Frequency {
+        self.registers()
+            .map_or(Frequency::ZERO, |registers| registers.frequency_at(index, self.xtal))
+    }
+}
+
+impl SweepRegisters {
+    /// Returns the requests that set the registers, in the order of the protocol.
+    pub fn to_frames(&self) -> Vec<Frame> {
+        vec![
+            Frame::with_data(Command::SetFStart, &self.fstart.to_be_bytes()[1..]),
+            Frame::with_data(Command::SetFStop, &self.fstop.to_be_bytes()[1..]),
+            Frame::with_data(Command::SetFStep, &self.fstep.to_be_bytes()),
+            Frame::with_data(Command::SetRbw, &[self.rbw]),
+            Frame::with_data(Command::SetIf, &[self.intermediate_frequency]),
+            Frame::with_data(Command::SetGain, &[self.gain]),
+        ]
+    }
+
+    /// Returns the number of bins the device measures, the steps from the start to the stop register, with an
+    /// integer division, plus the start: the last bin is the last step at or below the stop register.
+    pub fn bin_count(&self) -> usize {
+        match (self.fstep, self.fstart <= self.fstop) {
+            (0, _) | (_, false) => 0,
+            (fstep, true) => ((self.fstop - self.fstart) / fstep as u32) as usize + 1,
+        }
+    }
+
+    /// Returns the frequency the device tunes to for the bin `index`, the start register plus `index` step registers,
+    /// converted back to Hz for a crystal of `xtal`.
+    ///
+    /// The index isn't checked against `bin_count`.
+    pub fn frequency_at(&self, index: usize, xtal: Frequency) -> Frequency {
+        let register = self.fstart as u64 + index as u64 * self.fstep as u64;
+        Frequency::from_hz(to_hz(register as f64, xtal))
+    }
+}
+
+/// Returns the register of `frequency` compensated for a crystal of `xtal`, `f · 65536 / xtal`, in MHz, truncated and
+/// saturated at `u32::MAX`.
+///
+/// Without a crystal frequency, 0 Hz, the TI software uses `f · 0xFFFF / 26`, which is kept for compatibility.
+pub fn compensate(frequency: Frequency, xtal: Frequency) -> u32 {
+    match xtal > Frequency::ZERO {
+        true => (frequency.mhz() * 65536.0 / xtal.mhz()) as u32,
+        false => (frequency.mhz() * 0xFFFF as f64 / 26.0) as u32,
+    }
+}
+
+/// Returns the frequency of `register`, in Hz, the inverse of `compensate`.
+fn to_hz(register: f64, xtal: Frequency) -> f64 {
+    match xtal > Frequency::ZERO {
+        true => register * xtal.mhz() / 65536.0 * 1e6,
+        false => register * 26.0 / 0xFFFF as f64 * 1e6,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn xtal() -> Frequency {
+        Frequency::from_hz(NOMINAL_XTAL_HZ)
+    }
+
+    fn a_config() -> SweepConfig {
+        SweepConfig {
+            fstart: Frequency::from_mhz(868.0),
+            fstop: Frequency::from_mhz(870.0),
+            fstep: Frequency::from_khz(100.0),
+            rbw: Frequency::from_khz(58.0),
+            ref_level_index: 0,
+            xtal: xtal(),
+        }
+    }
+
+    #[test]
+    fn given_a_frequency_when_compensate_then_follow_the_formula_of_the_protocol() {
+        // 868 · 65536 / 26 = 2187894.2
+        assert_eq!(compensate(Frequency::from_mhz(868.0), xtal()), 2_187_894);
+        // 100 kHz · 65536 / 26 MHz = 252.06
+        assert_eq!(compensate(Frequency::from_khz(100.0), xtal()), 252);
+        // 868 · 65536 / 26.000520 = 2187850.4, a crystal 20 ppm fast
+        assert_eq!(
+            compensate(Frequency::from_mhz(868.0), Frequency::from_hz(26_000_520.0)),
+            2_187_850
+        );
+        // 868 · 0xFFFF / 26 = 2187860.8, without a crystal frequency
+        assert_eq!(compensate(Frequency::from_mhz(868.0), Frequency::ZERO), 2_187_860);
+    }
+
+    #[test]
+    fn given_a_config_when_to_frames_then_set_each_register_with_its_command() {
+        let frames = a_config().registers().unwrap().to_frames();
+
+        assert_eq!(
+            frames,
+            vec![
+                Frame::with_data(Command::SetFStart, &[0x21, 0x62, 0x76]),
+                Frame::with_data(Command::SetFStop, &[0x21, 0x76, 0x27]),
+                Frame::with_data(Command::SetFStep, &[0x00, 0xFC]),
+                Frame::with_data(Command::SetRbw, &[240]),
+                Frame::with_data(Command::SetIf, &[8]),
+                Frame::with_data(Command::SetGain, &[128]),
+            ]
+        );
+    }
+
+    #[test]
+    fn given_each_rbw_of_table_8_when_registers_then_set_its_reg_values() {
+        for (rbw, register, intermediate_frequency) in RBW_TABLE {
+            let config = SweepConfig {
+                rbw: Frequency::from_khz(rbw),
+                ..a_config()
+            };
+
+            let registers = config.registers().unwrap();
+
+            assert_eq!(
+                (registers.rbw, registers.intermediate_frequency),
+                (register, intermediate_frequency)
+            );
+            assert_eq!(SweepConfig::from_registers(&registers, xtal()).unwrap().rbw, config.rbw);
+        }
+    }
+
+    #[test]
+    fn given_each_ref_level_of_table_9_when_registers_then_set_its_gain() {
+        let gains: Vec<u8> = (0..8)
+            .map(|index| {
+                let config = SweepConfig {
+                    ref_level_index: index,
+                    ..a_config()
+                };
+                config.registers().unwrap().gain
+            })
+            .collect();
+
+        assert_eq!(gains, [128, 144, 145, 74, 12, 179, 44, 61]);
+        assert_eq!(
+            REF_LEVEL_TABLE.map(|(dbm, _)| dbm),
+            [-35, -40, -45, -50, -55, -60, -65, -70]
+        );
+    }
+
+    #[test]
+    fn given_registers_when_from_registers_then_encode_back_to_the_same_registers() {
+        let registers = a_config().registers().unwrap();
+
+        let config = SweepConfig::from_registers(&registers, xtal()).unwrap();
+
+        assert_eq!(config.registers(), Ok(registers));
+        assert_eq!(config.bin_count(), a_config().bin_count());
+        assert_eq!(
+            SweepConfig::from_registers(&SweepRegisters { gain: 0, ..registers }, xtal()),
+            Err(SweepConfigError::UnknownRegister("gain", 0))
+        );
+        assert_eq!(
+            SweepConfig::from_registers(
+                &SweepRegisters {
+                    intermediate_frequency: 9,
+                    ..registers
+                },
+                xtal()
+            ),
+            Err(SweepConfigError::UnknownRegister("rbw", 240))
+        );
+    }
+
+    #[test]
+    fn given_a_step_when_with_auto_rbw_then_select_the_narrowest_rbw_of_twice_the_step() {
+        let (fstart, fstop) = (Frequency::from_mhz(868.0), Frequency::from_mhz(870.0));
+
+        let narrow = SweepConfig::with_auto_rbw(fstart, fstop, Frequency::from_khz(10.0), 0, xtal());
+        let wide = SweepConfig::with_auto_rbw(fstart, fstop, Frequency::from_khz(60.0), 0, xtal());
+        let widest = SweepConfig::with_auto_rbw(fstart, fstop, Frequency::from_mhz(1.0), 0, xtal());
+
+        assert_eq!(
+            narrow,
+            SweepConfig {
+                fstep: Frequency::from_khz(10.0),
+                ..a_config()
+            }
+        );
+        assert_eq!(wide.rbw, Frequency::from_khz(135.4));
+        assert_eq!(widest.rbw, Frequency::from_khz(812.5));
+    }
+
+    #[test]
+    fn given_a_config_when_bin_count_then_count_the_steps_of_the_registers() {
+        // (0x217627 - 0x216276) / 252 + 1 = 5041 / 252 + 1
+        assert_eq!(a_config().bin_count(), 21);
+        assert_eq!(a_config().frequency_at(0).hz(), 2_187_894.0 * 26e6 / 65536.0);
+        assert_eq!(
+            a_config().frequency_at(20).hz(),
+            (2_187_894.0 + 20.0 * 252.0) * 26e6 / 65536.0
+        );
+    }
+
+    #[test]
+    fn given_a_crystal_off_nominal_when_frequency_at_then_follow_f_n_of_the_protocol_in_registers() {
+        // A crystal 20 ppm fast and a stop off the grid of the step: 10 kHz · 65536 / 26.00052 MHz = 25.2
+        let config = SweepConfig {
+            fstop: Frequency::from_mhz(868.1),
+            fstep: Frequency::from_khz(10.0),
+            xtal: Frequency::from_hz(26_000_520.0),
+            ..a_config()
+        };
+        let registers = config.registers().unwrap();
+        let unit = 26_000_520.0 / 65536.0;
+
+        assert_eq!(
+            (registers.fstart, registers.fstop, registers.fstep),
+            (2_187_850, 2_188_102, 25)
+        );
+        // (2188102 - 2187850) / 25 + 1 = 10.08 + 1
+        assert_eq!(config.bin_count(), 11);
+        for bin in 0..config.bin_count() {
+            let expected = (2_187_850.0 + bin as f64 * 25.0) * unit;
+            assert!((config.frequency_at(bin).hz() - expected).abs() < 1e-6, "bin {}", bin);
+        }
+        assert!(config.frequency_at(10) <= config.fstop);
+        assert!(config.fstop - config.frequency_at(10) < config.fstep);
+    }
+
+    #[test]
+    fn given_invalid_configs_when_registers_then_return_an_error() {
+        let reversed = SweepConfig {
+            fstart: Frequency::from_mhz(870.0),
+            fstop: Frequency::from_mhz(868.0),
+            ..a_config()
+        };
+        let no_step = SweepConfig {
+            fstep: Frequency::from_hz(100.0),
+            ..a_config()
+        };
+        let too_high = SweepConfig {
+            fstop: Frequency::from_mhz(7000.0),
+            ..a_config()
+        };
+        let wide_step = SweepConfig {
+            fstep: Frequency::from_mhz(30.0),
+            ..a_config()
+        };
+        let rbw = SweepConfig {
+            rbw: Frequency::from_khz(100.0),
+            ..a_config()
+        };
+        let ref_level = SweepConfig {
+            ref_level_index: 8,
+            ..a_config()
+        };
+
+        assert!(matches!(reversed.registers(), Err(SweepConfigError::InvalidSweep(..))));
+        assert!(matches!(no_step.registers(), Err(SweepConfigError::InvalidSweep(..))));
+        assert!(matches!(
+            too_high.registers(),
+            Err(SweepConfigError::OutOfRange("fstop", _))
+        ));
+        assert!(matches!(
+            wide_step.registers(),
+            Err(SweepConfigError::OutOfRange("fstep", _))
+        ));
+        assert_eq!(
+            rbw.registers(),
+            Err(SweepConfigError::UnknownRbw(Frequency::from_khz(100.0)))
+        );
+        assert_eq!(ref_level.registers(), Err(SweepConfigError::UnknownRefLevel(8)));
+        assert_eq!(no_step.bin_count(), 0);
+    }
+}
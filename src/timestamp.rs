@@ -0,0 +1,249 @@
+//! # Timestamp Module
+//!
+//! This module formats times as ISO 8601 timestamps with millisecond precision, like `2024-02-29T12:30:00.250Z`, in
+//! UTC or in the local time zone with its offset, like `2024-02-29T13:30:00.250+01:00`, to correlate the sweeps with
+//! the logs of other equipment.
+//!
+//! The local offset is read with `localtime_r` of the C library, so it follows the `TZ` variable and the daylight
+//! saving time of the system, and is cached for each hour. On systems without it, times are written in UTC.
+//!
+//! ## Usage Example
+//!
+//! ```rust
+//! use std::time::{Duration, UNIX_EPOCH};
+//!
+//! use sa430::timestamp::{format_iso8601, TimeZone};
+//!
+//! let time = UNIX_EPOCH + Duration::from_millis(1_709_209_800_250);
+//!
+//! assert_eq!(format_iso8601(time, TimeZone::Utc), "2024-02-29T12:30:00.250Z");
+//! ```
+
+#[cfg(unix)]
+use std::ffi::{c_char, c_int, c_long};
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    str::FromStr,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Number of seconds in a day.
+pub const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Number of seconds in an hour, the period the local offset is cached for.
+const SECONDS_PER_HOUR: u64 = 60 * 60;
+
+/// `time_t` of the C library, a `long` but on 32-bit musl, which moved to 64 bits.
+#[cfg(all(unix, not(all(target_env = "musl", target_pointer_width = "32"))))]
+type TimeT = c_long;
+#[cfg(all(unix, target_env = "musl", target_pointer_width = "32"))]
+type TimeT = i64;
+
+/// Broken-down time, `struct tm`, with the `tm_gmtoff` and `tm_zone` extensions of glibc, musl, Bionic and the BSDs.
+#[cfg(unix)]
+#[repr(C)]
+struct Tm {
+    tm_sec: c_int,
+    tm_min: c_int,
+    tm_hour: c_int,
+    tm_mday: c_int,
+    tm_mon: c_int,
+    tm_year: c_int,
+    tm_wday: c_int,
+    tm_yday: c_int,
+    tm_isdst: c_int,
+    tm_gmtoff: c_long,
+    tm_zone: *const c_char,
+}
+
+#[cfg(unix)]
+extern "C" {
+    fn tzset();
+    fn localtime_r(time: *const TimeT, result: *mut Tm) -> *mut Tm;
+}
+
+/// Local offset of the last hour looked up, as the hour since the epoch and the offset in seconds east of UTC.
+static LOCAL_OFFSET: Mutex<Option<(u64, i32)>> = Mutex::new(None);
+
+/// Time zone of the formatted timestamps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeZone {
+    /// Coordinated Universal Time, with the `Z` suffix.
+    #[default]
+    Utc,
+
+    /// The local time zone of the system, with its offset.
+    Local,
+}
+
+/// Error parsing a time zone name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidTimeZone;
+
+impl Error for InvalidTimeZone {}
+
+impl Display for InvalidTimeZone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid time zone, expected utc or local")
+    }
+}
+
+impl FromStr for TimeZone {
+    type Err = InvalidTimeZone;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "utc" => Ok(TimeZone::Utc),
+            "local" => Ok(TimeZone::Local),
+            _ => Err(InvalidTimeZone),
+        }
+    }
+}
+
+impl Display for TimeZone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeZone::Utc => write!(f, "utc"),
+            TimeZone::Local => write!(f, "local"),
+        }
+    }
+}
+
+/// Formats `time` as an ISO 8601 timestamp with milliseconds, in the time zone `zone`.
+pub fn format_iso8601(time: SystemTime, zone: TimeZone) -> String {
+    let offset = match zone {
+        TimeZone::Utc => 0,
+        TimeZone::Local => local_offset(time),
+    };
+    format_with_offset(time, offset)
+}
+
+/// Formats `time` with the offset, in seconds east of UTC, using `Z` for UTC.
+fn format_with_offset(time: SystemTime, offset: i32) -> String {
+    let millis = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as i64 + offset as i64 * 1000;
+    let seconds = millis.div_euclid(1000);
+    let (year, month, day) = civil_from_days(seconds.div_euclid(SECONDS_PER_DAY as i64));
+    let seconds = seconds.rem_euclid(SECONDS_PER_DAY as i64);
+    let zone = match offset {
+        0 => "Z".to_string(),
+        _ => format!(
+            "{}{:02}:{:02}",
+            if offset < 0 { '-' } else { '+' },
+            offset.unsigned_abs() / 3600,
+            offset.unsigned_abs() / 60 % 60
+        ),
+    };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}{}",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        millis.rem_euclid(1000),
+        zone
+    )
+}
+
+/// Returns the offset of the local time zone at `time`, in seconds east of UTC, 0 if unknown.
+fn local_offset(time: SystemTime) -> i32 {
+    let seconds = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let hour = seconds / SECONDS_PER_HOUR;
+    let mut cache = LOCAL_OFFSET.lock().unwrap_or_else(|error| error.into_inner());
+    match *cache {
+        Some((cached, offset)) if cached == hour => offset,
+        _ => {
+            let offset = read_local_offset(seconds).unwrap_or(0);
+            *cache = Some((hour, offset));
+            offset
+        }
+    }
+}
+
+/// Reads the local offset at `seconds` since the epoch with `localtime_r`, reloading the time zone first.
+#[cfg(unix)]
+fn read_local_offset(seconds: u64) -> Option<i32> {
+    let time = TimeT::try_from(seconds).ok()?;
+    let mut tm = Tm {
+        tm_sec: 0,
+        tm_min: 0,
+        tm_hour: 0,
+        tm_mday: 0,
+        tm_mon: 0,
+        tm_year: 0,
+        tm_wday: 0,
+        tm_yday: 0,
+        tm_isdst: 0,
+        tm_gmtoff: 0,
+        tm_zone: std::ptr::null(),
+    };
+    // SAFETY: tzset only reads the TZ variable, which the crate never changes, and the time zone files.
+    unsafe { tzset() };
+    // SAFETY: time and tm are valid for the duration of the call, and localtime_r writes only to tm.
+    match unsafe { localtime_r(&time, &mut tm) }.is_null() {
+        true => None,
+        false => i32::try_from(tm.tm_gmtoff).ok(),
+    }
+}
+
+/// The local offset is unknown without the C library, times are written in UTC.
+#[cfg(not(unix))]
+fn read_local_offset(_seconds: u64) -> Option<i32> {
+    None
+}
+
+/// Returns the number of days since 1970-01-01 of a date of the proleptic Gregorian calendar.
+pub fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month as i64 + 9) % 12) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Returns the date, as year, month and day, of a number of days since 1970-01-01.
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    #[test]
+    fn given_offsets_when_format_with_offset_then_write_the_local_time_and_offset() {
+        let time = UNIX_EPOCH + Duration::from_millis(1_709_251_199_999);
+
+        assert_eq!(format_with_offset(time, 0), "2024-02-29T23:59:59.999Z");
+        assert_eq!(format_with_offset(time, 5400), "2024-03-01T01:29:59.999+01:30");
+        assert_eq!(format_with_offset(time, -10800), "2024-02-29T20:59:59.999-03:00");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn given_a_time_when_read_local_offset_then_return_an_offset_of_at_most_a_day() {
+        let offset = read_local_offset(1_709_251_199).unwrap();
+
+        assert!(offset.unsigned_abs() < SECONDS_PER_DAY as u32);
+        assert_eq!(offset % 60, 0);
+    }
+}
@@ -0,0 +1,243 @@
+//! # TLS Module
+//!
+//! This module encrypts the network connections of the crate with TLS, through `rustls`, so the sweeps and the tokens
+//! of the [`auth`](crate::auth) module don't travel in clear text on untrusted networks.
+//!
+//! A [`TlsAcceptor`] holds the certificate chain and private key of a server, like the one of
+//! [`listen_tls`](crate::server::listen_tls), and a [`TlsConnector`] the certificate authorities a client trusts, like
+//! the [`Client`](crate::client::Client) of [`connect_tls`](crate::client::Client::connect_tls). Both are read from PEM
+//! files. Clients verify the certificate of the server, but not the other way around: client certificates aren't
+//! supported, so clients still authenticate with a token.
+//!
+//! ## Usage Example
+//!
+//! ```rust,no_run
+//! use std::path::Path;
+//!
+//! use sa430::client::Client;
+//! use sa430::tls::TlsConnector;
+//!
+//! let connector = TlsConnector::from_pem_file(Path::new("ca.pem")).unwrap();
+//! let client = Client::connect_tls("sa430-host:5430", &connector).unwrap();
+//! ```
+
+use std::{
+    error::Error,
+    fmt, fs,
+    io::{self, Read, Write},
+    net::TcpStream,
+    path::Path,
+    sync::Arc,
+};
+
+use rustls::{
+    crypto::ring,
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer, ServerName},
+    ClientConfig, ClientConnection, RootCertStore, ServerConfig, ServerConnection, StreamOwned,
+};
+
+/// A TLS connection accepted by a server, which notifies the client when dropped, so it tells the end of the stream
+/// from a truncated one.
+pub struct ServerStream(StreamOwned<ServerConnection, TcpStream>);
+
+impl Read for ServerStream {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buffer)
+    }
+}
+
+impl Write for ServerStream {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        self.0.write(buffer)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl Drop for ServerStream {
+    fn drop(&mut self) {
+        self.0.conn.send_close_notify();
+        let _ = self.0.flush();
+    }
+}
+
+/// A TLS connection to a server.
+pub type ClientStream = StreamOwned<ClientConnection, TcpStream>;
+
+/// Accepts the TLS connections of a server, with its certificate chain and private key.
+#[derive(Clone)]
+pub struct TlsAcceptor(Arc<ServerConfig>);
+
+impl TlsAcceptor {
+    /// Creates an acceptor from a PEM certificate chain, starting with the certificate of the server, and the PEM
+    /// private key of that certificate.
+    pub fn from_pem(certificates: &[u8], key: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let chain = CertificateDer::pem_slice_iter(certificates).collect::<Result<Vec<_>, _>>()?;
+        if chain.is_empty() {
+            return Err(TlsError::NoCertificate.into());
+        }
+        let key = PrivateKeyDer::from_pem_slice(key)?;
+        let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_single_cert(chain, key)?;
+        Ok(TlsAcceptor(Arc::new(config)))
+    }
+
+    /// Creates an acceptor from the files of a PEM certificate chain and its private key, see [`TlsAcceptor::from_pem`].
+    pub fn from_pem_files(certificates: &Path, key: &Path) -> Result<Self, Box<dyn Error>> {
+        TlsAcceptor::from_pem(&fs::read(certificates)?, &fs::read(key)?)
+    }
+
+    /// Completes the TLS handshake of a client, within the timeouts of `stream`.
+    pub fn accept(&self, mut stream: TcpStream) -> Result<ServerStream, Box<dyn Error>> {
+        let mut connection = ServerConnection::new(self.0.clone())?;
+        while connection.is_handshaking() {
+            connection.complete_io(&mut stream)?;
+        }
+        Ok(ServerStream(StreamOwned::new(connection, stream)))
+    }
+}
+
+/// Opens TLS connections to servers signed by the certificate authorities it trusts.
+#[derive(Clone)]
+pub struct TlsConnector(Arc<ClientConfig>);
+
+impl TlsConnector {
+    /// Creates a connector trusting the PEM certificates of `authorities`.
+    pub fn from_pem(authorities: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let mut roots = RootCertStore::empty();
+        for certificate in CertificateDer::pem_slice_iter(authorities) {
+            roots.add(certificate?)?;
+        }
+        if roots.is_empty() {
+            return Err(TlsError::NoCertificate.into());
+        }
+        let config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        Ok(TlsConnector(Arc::new(config)))
+    }
+
+    /// Creates a connector trusting the certificates of a PEM file, see [`TlsConnector::from_pem`].
+    pub fn from_pem_file(authorities: &Path) -> Result<Self, Box<dyn Error>> {
+        TlsConnector::from_pem(&fs::read(authorities)?)
+    }
+
+    /// Completes the TLS handshake with the server of `stream`, verifying its certificate is valid for `host`, a DNS
+    /// name or an IP address.
+    pub fn connect(&self, host: &str, mut stream: TcpStream) -> Result<ClientStream, Box<dyn Error>> {
+        let name = ServerName::try_from(host.to_string())?;
+        let mut connection = ClientConnection::new(self.0.clone(), name)?;
+        while connection.is_handshaking() {
+            connection.complete_io(&mut stream)?;
+        }
+        Ok(StreamOwned::new(connection, stream))
+    }
+}
+
+/// TLS errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TlsError {
+    /// The PEM input has no certificate.
+    NoCertificate,
+}
+
+impl Error for TlsError {}
+
+impl fmt::Display for TlsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TlsError::NoCertificate => write!(f, "No certificate found in the PEM input"),
+        }
+    }
+}
+
+/// Test certificate authority, and a server certificate it signed for `localhost` and `127.0.0.1`.
+#[cfg(test)]
+pub(crate) mod fixtures {
+    pub(crate) const CA: &[u8] = include_bytes!("../tests/fixtures/tls/ca.pem");
+    pub(crate) const SERVER_CERTIFICATE: &[u8] = include_bytes!("../tests/fixtures/tls/server.pem");
+    pub(crate) const SERVER_KEY: &[u8] = include_bytes!("../tests/fixtures/tls/server-key.pem");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use fixtures::{CA, SERVER_CERTIFICATE, SERVER_KEY};
+
+    /// Accepts a connection on a local port with `acceptor`, echoing one line, and returns the port.
+    fn an_echo_server(acceptor: TlsAcceptor) -> (u16, thread::JoinHandle<Result<(), String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut stream = BufReader::new(acceptor.accept(stream).map_err(|error| error.to_string())?);
+            let mut line = String::new();
+            stream.read_line(&mut line).map_err(|error| error.to_string())?;
+            stream
+                .get_mut()
+                .write_all(line.as_bytes())
+                .map_err(|error| error.to_string())
+        });
+        (port, server)
+    }
+
+    #[test]
+    fn given_a_trusted_server_when_connect_then_exchange_data_encrypted() {
+        let (port, server) = an_echo_server(TlsAcceptor::from_pem(SERVER_CERTIFICATE, SERVER_KEY).unwrap());
+        let connector = TlsConnector::from_pem(CA).unwrap();
+
+        let stream = connector.connect("localhost", TcpStream::connect(("127.0.0.1", port)).unwrap());
+        let mut stream = BufReader::new(stream.unwrap());
+        stream.get_mut().write_all(b"sweep\n").unwrap();
+        let mut line = String::new();
+        stream.read_line(&mut line).unwrap();
+
+        assert_eq!(line, "sweep\n");
+        assert_eq!(server.join().unwrap(), Ok(()));
+    }
+
+    #[test]
+    fn given_a_certificate_for_another_host_when_connect_then_return_error() {
+        let (port, server) = an_echo_server(TlsAcceptor::from_pem(SERVER_CERTIFICATE, SERVER_KEY).unwrap());
+        let connector = TlsConnector::from_pem(CA).unwrap();
+
+        let result = connector.connect("sa430-host", TcpStream::connect(("127.0.0.1", port)).unwrap());
+
+        assert!(result.err().unwrap().to_string().contains("certificate"));
+        assert!(server.join().unwrap().is_err());
+    }
+
+    #[test]
+    fn given_an_untrusted_server_when_connect_then_return_error() {
+        let (port, server) = an_echo_server(TlsAcceptor::from_pem(SERVER_CERTIFICATE, SERVER_KEY).unwrap());
+        // The server certificate isn't a certificate authority, so it can't sign itself.
+        let connector = TlsConnector::from_pem(SERVER_CERTIFICATE).unwrap();
+
+        let result = connector.connect("localhost", TcpStream::connect(("127.0.0.1", port)).unwrap());
+
+        assert!(result.is_err());
+        assert!(server.join().unwrap().is_err());
+    }
+
+    #[test]
+    fn given_pem_without_certificates_when_from_pem_then_return_error() {
+        let acceptor = TlsAcceptor::from_pem(SERVER_KEY, SERVER_KEY);
+        let connector = TlsConnector::from_pem(b"");
+
+        assert_eq!(acceptor.err().unwrap().downcast_ref(), Some(&TlsError::NoCertificate));
+        assert_eq!(connector.err().unwrap().downcast_ref(), Some(&TlsError::NoCertificate));
+    }
+}
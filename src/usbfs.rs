@@ -0,0 +1,407 @@
+//! # Usbfs Module
+//!
+//! This module provides `UsbfsChannel`, a `Channel` that talks to the SA430 through a usbfs file descriptor of the
+//! device, with bulk transfers to its CDC data interface instead of a serial port driver.
+//!
+//! Android doesn't expose the device as a serial port, but grants apps a file descriptor of the USB device through
+//! its USB host API. Termux passes it to a command with `termux-usb`, as its first argument:
+//!
+//! ```bash
+//! termux-usb -r -e ./survey.sh /dev/bus/usb/001/002
+//! ```
+//!
+//! When opened, the channel reads the descriptors of the device to find the CDC interfaces, claims them, detaching
+//! the kernel driver if there is one, and sets the line coding of the serial port channel, 926100 bps 8N1, with DTR
+//! and RTS set. The interfaces are released when the channel is dropped, and the file descriptor is closed.
+//!
+//! ## Usage Example
+//!
+//! ```ignore
+//! use std::os::fd::{FromRawFd, OwnedFd};
+//!
+//! use sa430::device::Sa430;
+//! use sa430::usbfs::UsbfsChannel;
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let fd: i32 = std::env::args().nth(1).ok_or("Missing file descriptor")?.parse()?;
+//!     // SAFETY: termux-usb passes a file descriptor of the device that nothing else in the process owns.
+//!     let channel = UsbfsChannel::new(unsafe { OwnedFd::from_raw_fd(fd) })?;
+//!     let mut device = Sa430::new(Box::new(channel));
+//!     println!("IDN: {}", device.idn()?);
+//!     Ok(())
+//! }
+//! ```
+
+use std::{
+    collections::VecDeque,
+    ffi::{c_int, c_uint, c_ulong, c_void},
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    mem::size_of,
+    os::fd::{AsRawFd, OwnedFd},
+    ptr,
+    time::Duration,
+};
+
+use super::channel::Channel;
+
+extern "C" {
+    // Bionic declares the request as an int, which is passed in the same register and has the same low bits.
+    fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+}
+
+/// Argument of `USBDEVFS_BULK`, `struct usbdevfs_bulktransfer`.
+#[repr(C)]
+struct BulkTransfer {
+    endpoint: c_uint,
+    length: c_uint,
+    timeout: c_uint,
+    data: *mut c_void,
+}
+
+/// Argument of `USBDEVFS_CONTROL`, `struct usbdevfs_ctrltransfer`.
+#[repr(C)]
+struct ControlTransfer {
+    request_type: u8,
+    request: u8,
+    value: u16,
+    index: u16,
+    length: u16,
+    timeout: u32,
+    data: *mut c_void,
+}
+
+/// Argument of `USBDEVFS_IOCTL`, `struct usbdevfs_ioctl`.
+#[repr(C)]
+struct InterfaceIoctl {
+    interface: c_int,
+    code: c_int,
+    data: *mut c_void,
+}
+
+/// Returns the number of a usbfs ioctl, like the `_IOC` macro of Linux.
+const fn usbfs_ioctl(direction: c_ulong, number: c_ulong, size: usize) -> c_ulong {
+    (direction << 30) | ((size as c_ulong) << 16) | ((b'U' as c_ulong) << 8) | number
+}
+
+/// Direction of the ioctls that write to the kernel.
+const IOC_WRITE: c_ulong = 1;
+
+/// Direction of the ioctls that read from the kernel.
+const IOC_READ: c_ulong = 2;
+
+const USBDEVFS_CONTROL: c_ulong = usbfs_ioctl(IOC_READ | IOC_WRITE, 0, size_of::<ControlTransfer>());
+const USBDEVFS_BULK: c_ulong = usbfs_ioctl(IOC_READ | IOC_WRITE, 2, size_of::<BulkTransfer>());
+const USBDEVFS_CLAIMINTERFACE: c_ulong = usbfs_ioctl(IOC_READ, 15, size_of::<c_uint>());
+const USBDEVFS_RELEASEINTERFACE: c_ulong = usbfs_ioctl(IOC_READ, 16, size_of::<c_uint>());
+const USBDEVFS_IOCTL: c_ulong = usbfs_ioctl(IOC_READ | IOC_WRITE, 18, size_of::<InterfaceIoctl>());
+const USBDEVFS_DISCONNECT: c_ulong = usbfs_ioctl(0, 22, 0);
+
+/// Error of a claim when another driver has the interface.
+const EBUSY: i32 = 16;
+
+/// Descriptor types.
+const INTERFACE_DESCRIPTOR: u8 = 4;
+const ENDPOINT_DESCRIPTOR: u8 = 5;
+
+/// Interface classes.
+const CDC_COMMUNICATION_CLASS: u8 = 0x02;
+const CDC_DATA_CLASS: u8 = 0x0A;
+
+/// Transfer type of the bulk endpoints, in the attributes of the endpoint descriptor.
+const BULK_TRANSFER: u8 = 0x02;
+
+/// Direction bit of the IN endpoint addresses.
+const ENDPOINT_IN: u8 = 0x80;
+
+/// Request type of the CDC class requests to an interface.
+const CDC_REQUEST_TYPE: u8 = 0x21;
+const SET_LINE_CODING: u8 = 0x20;
+const SET_CONTROL_LINE_STATE: u8 = 0x22;
+
+/// Control line state with DTR and RTS set.
+const DTR_RTS: u16 = 0x03;
+
+/// Line coding of the serial port: 926100 bps, 1 stop bit, no parity, 8 data bits.
+const LINE_CODING: [u8; 7] = [0x94, 0x21, 0x0E, 0x00, 0, 0, 8];
+
+/// Timeout of the control transfers and the initial timeout of the bulk transfers, in ms.
+const DEFAULT_TIMEOUT_MS: u32 = 5000;
+
+/// Size of the bulk IN transfers, a multiple of the packet size, so a packet never overflows the buffer.
+const BULK_READ_SIZE: usize = 4096;
+
+/// Largest bulk OUT transfer accepted by usbfs.
+const BULK_WRITE_SIZE: usize = 16384;
+
+/// Interfaces and bulk endpoints of a CDC ACM device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CdcEndpoints {
+    control: u8,
+    data: u8,
+    bulk_in: u8,
+    bulk_out: u8,
+}
+
+/// ### `UsbfsChannel`
+///
+/// A channel to the device through a usbfs file descriptor, like the ones granted by Android to apps.
+#[derive(Debug)]
+pub struct UsbfsChannel {
+    file: File,
+    endpoints: CdcEndpoints,
+    timeout_ms: u32,
+    pending: VecDeque<u8>,
+}
+
+impl UsbfsChannel {
+    /// Opens the channel on the usbfs file descriptor `fd` of the device, claiming its CDC interfaces.
+    ///
+    /// Fails if the descriptors of the device don't have a CDC data interface with bulk endpoints, or the interfaces
+    /// can't be claimed or configured.
+    pub fn new(fd: OwnedFd) -> io::Result<Self> {
+        let mut file = File::from(fd);
+        let mut descriptors = Vec::new();
+        file.seek(SeekFrom::Start(0))?;
+        file.read_to_end(&mut descriptors)?;
+
+        let mut channel = UsbfsChannel {
+            file,
+            endpoints: find_cdc_endpoints(&descriptors)?,
+            timeout_ms: DEFAULT_TIMEOUT_MS,
+            pending: VecDeque::new(),
+        };
+        channel.claim(channel.endpoints.control)?;
+        if channel.endpoints.data != channel.endpoints.control {
+            channel.claim(channel.endpoints.data)?;
+        }
+        channel.control_out(SET_LINE_CODING, 0, &LINE_CODING)?;
+        channel.control_out(SET_CONTROL_LINE_STATE, DTR_RTS, &[])?;
+        Ok(channel)
+    }
+
+    /// Claims `interface`, detaching its kernel driver if it has one.
+    fn claim(&mut self, interface: u8) -> io::Result<()> {
+        let mut number = interface as c_uint;
+        // SAFETY: number is a valid unsigned int for the duration of the call.
+        match unsafe { ioctl(self.file.as_raw_fd(), USBDEVFS_CLAIMINTERFACE, &mut number) } {
+            -1 if io::Error::last_os_error().raw_os_error() == Some(EBUSY) => {
+                let mut command = InterfaceIoctl {
+                    interface: interface as c_int,
+                    code: USBDEVFS_DISCONNECT as c_int,
+                    data: ptr::null_mut(),
+                };
+                // SAFETY: command is a valid usbdevfs_ioctl for the duration of the call, and DISCONNECT has no data.
+                unsafe { ioctl(self.file.as_raw_fd(), USBDEVFS_IOCTL, &mut command) };
+                // SAFETY: number is a valid unsigned int for the duration of the call.
+                match unsafe { ioctl(self.file.as_raw_fd(), USBDEVFS_CLAIMINTERFACE, &mut number) } {
+                    -1 => Err(io::Error::last_os_error()),
+                    _ => Ok(()),
+                }
+            }
+            -1 => Err(io::Error::last_os_error()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Sends a CDC class request with `data` to the communication interface.
+    fn control_out(&mut self, request: u8, value: u16, data: &[u8]) -> io::Result<()> {
+        let mut transfer = ControlTransfer {
+            request_type: CDC_REQUEST_TYPE,
+            request,
+            value,
+            index: self.endpoints.control as u16,
+            length: data.len() as u16,
+            timeout: DEFAULT_TIMEOUT_MS,
+            data: data.as_ptr() as *mut c_void,
+        };
+        // SAFETY: transfer points to data, which outlives the call, and the kernel only reads it for an OUT request.
+        match unsafe { ioctl(self.file.as_raw_fd(), USBDEVFS_CONTROL, &mut transfer) } {
+            -1 => Err(io::Error::last_os_error()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Runs a bulk transfer of `length` bytes at `data` on `endpoint`, returning the number of bytes transferred.
+    fn bulk(&mut self, endpoint: u8, data: *mut c_void, length: usize) -> io::Result<usize> {
+        let mut transfer = BulkTransfer {
+            endpoint: endpoint as c_uint,
+            length: length as c_uint,
+            timeout: self.timeout_ms,
+            data,
+        };
+        // SAFETY: the callers pass a buffer of length bytes that outlives the call, writable for the IN endpoint.
+        match unsafe { ioctl(self.file.as_raw_fd(), USBDEVFS_BULK, &mut transfer) } {
+            -1 => Err(io::Error::last_os_error()),
+            count => Ok(count as usize),
+        }
+    }
+}
+
+impl io::Read for UsbfsChannel {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pending.is_empty() {
+            let mut packet = [0u8; BULK_READ_SIZE];
+            let count = self.bulk(self.endpoints.bulk_in, packet.as_mut_ptr().cast(), packet.len())?;
+            self.pending.extend(&packet[..count]);
+        }
+
+        let count = buf.len().min(self.pending.len());
+        for (byte, pending) in buf.iter_mut().zip(self.pending.drain(..count)) {
+            *byte = pending;
+        }
+        Ok(count)
+    }
+}
+
+impl io::Write for UsbfsChannel {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let length = buf.len().min(BULK_WRITE_SIZE);
+        self.bulk(self.endpoints.bulk_out, buf.as_ptr() as *mut c_void, length)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Channel for UsbfsChannel {
+    fn set_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        // A timeout of 0 waits forever, so shorter timeouts are rounded up to 1 ms.
+        self.timeout_ms = timeout.as_millis().clamp(1, u32::MAX as u128) as u32;
+        Ok(())
+    }
+}
+
+/// Releases the claimed interfaces, so the kernel driver can be bound again.
+impl Drop for UsbfsChannel {
+    fn drop(&mut self) {
+        for interface in [self.endpoints.control, self.endpoints.data] {
+            let mut number = interface as c_uint;
+            // SAFETY: number is a valid unsigned int for the duration of the call, errors are ignored.
+            unsafe { ioctl(self.file.as_raw_fd(), USBDEVFS_RELEASEINTERFACE, &mut number) };
+        }
+    }
+}
+
+/// Finds the CDC interfaces and the bulk endpoints of the data interface in the descriptors of the device.
+///
+/// The communication interface defaults to the data interface when the device doesn't have one.
+fn find_cdc_endpoints(descriptors: &[u8]) -> io::Result<CdcEndpoints> {
+    let mut control = None;
+    let mut data = None;
+    let mut bulk_in = None;
+    let mut bulk_out = None;
+    let mut class = None;
+
+    let mut offset = 0;
+    while offset + 2 <= descriptors.len() {
+        let length = descriptors[offset] as usize;
+        if length < 2 || offset + length > descriptors.len() {
+            break;
+        }
+        let descriptor = &descriptors[offset..offset + length];
+        match descriptor[1] {
+            INTERFACE_DESCRIPTOR if length >= 9 => {
+                class = Some(descriptor[5]);
+                match descriptor[5] {
+                    CDC_COMMUNICATION_CLASS => control = control.or(Some(descriptor[2])),
+                    CDC_DATA_CLASS if data.is_none() => data = Some(descriptor[2]),
+                    _ => {}
+                }
+            }
+            ENDPOINT_DESCRIPTOR if length >= 7 && class == Some(CDC_DATA_CLASS) => {
+                let address = descriptor[2];
+                if descriptor[3] & 0x03 == BULK_TRANSFER {
+                    match address & ENDPOINT_IN {
+                        0 => bulk_out = bulk_out.or(Some(address)),
+                        _ => bulk_in = bulk_in.or(Some(address)),
+                    }
+                }
+            }
+            _ => {}
+        }
+        offset += length;
+    }
+
+    match (data, bulk_in, bulk_out) {
+        (Some(data), Some(bulk_in), Some(bulk_out)) => Ok(CdcEndpoints {
+            control: control.unwrap_or(data),
+            data,
+            bulk_in,
+            bulk_out,
+        }),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "The USB device has no CDC data interface with bulk endpoints",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Write;
+
+    /// Descriptors of the SA430: the device, a configuration, the CDC communication interface with its interrupt
+    /// endpoint, and the CDC data interface with its bulk endpoints.
+    const SA430_DESCRIPTORS: [u8; 85] = [
+        0x12, 0x01, 0x00, 0x02, 0x02, 0x00, 0x00, 0x08, 0x51, 0x20, 0x30, 0xF4, 0x00, 0x02, 0x01, 0x02, 0x03, 0x01,
+        0x09, 0x02, 0x43, 0x00, 0x02, 0x01, 0x00, 0x80, 0x32, // configuration
+        0x09, 0x04, 0x00, 0x00, 0x01, 0x02, 0x02, 0x01, 0x00, // communication interface
+        0x05, 0x24, 0x00, 0x10, 0x01, // header
+        0x05, 0x24, 0x01, 0x00, 0x01, // call management
+        0x04, 0x24, 0x02, 0x02, // abstract control management
+        0x05, 0x24, 0x06, 0x00, 0x01, // union
+        0x07, 0x05, 0x82, 0x03, 0x40, 0x00, 0xFF, // interrupt IN
+        0x09, 0x04, 0x01, 0x00, 0x02, 0x0A, 0x00, 0x00, 0x00, // data interface
+        0x07, 0x05, 0x03, 0x02, 0x40, 0x00, 0xFF, // bulk OUT
+        0x07, 0x05, 0x83, 0x02, 0x40, 0x00, 0xFF, // bulk IN
+    ];
+
+    #[test]
+    fn given_the_sa430_descriptors_when_find_cdc_endpoints_then_return_the_data_interface_endpoints() {
+        let endpoints = find_cdc_endpoints(&SA430_DESCRIPTORS).unwrap();
+
+        assert_eq!(
+            endpoints,
+            CdcEndpoints {
+                control: 0,
+                data: 1,
+                bulk_in: 0x83,
+                bulk_out: 0x03,
+            }
+        );
+    }
+
+    #[test]
+    fn given_descriptors_without_a_data_interface_when_find_cdc_endpoints_then_fail() {
+        let result = find_cdc_endpoints(&SA430_DESCRIPTORS[..63]);
+
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    #[cfg(all(target_os = "linux", target_pointer_width = "64"))]
+    fn given_the_usbfs_ioctls_when_computed_then_match_the_linux_headers() {
+        assert_eq!(USBDEVFS_CONTROL, 0xC018_5500);
+        assert_eq!(USBDEVFS_BULK, 0xC018_5502);
+        assert_eq!(USBDEVFS_CLAIMINTERFACE, 0x8004_550F);
+        assert_eq!(USBDEVFS_RELEASEINTERFACE, 0x8004_5510);
+        assert_eq!(USBDEVFS_IOCTL, 0xC010_5512);
+        assert_eq!(USBDEVFS_DISCONNECT, 0x5516);
+    }
+
+    #[test]
+    fn given_a_file_that_is_not_a_usb_device_when_new_then_fail_to_claim_it() {
+        let path = std::env::temp_dir().join(format!("sa430-usbfs-{}", std::process::id()));
+        File::create(&path).unwrap().write_all(&SA430_DESCRIPTORS).unwrap();
+        let file = File::open(&path).unwrap();
+
+        let result = UsbfsChannel::new(OwnedFd::from(file));
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,119 @@
+//! Golden file tests of the exporters, fed with the spectra of a seeded simulator, so any change of the formats shows
+//! up in the diff of `tests/golden` and is reviewed.
+//!
+//! Run with `UPDATE_GOLDEN=1` to write the files again after a deliberate change.
+
+use std::{
+    env, fs,
+    path::PathBuf,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use sa430::formats::{
+    binary::BinarySink,
+    csv::CsvSink,
+    gains::{write_gains_csv, write_gains_json},
+    html::HtmlSink,
+    ndjson::NdjsonSink,
+};
+use sa430::frequency::Frequency;
+use sa430::simulator::{Carrier, Scenario, Simulator};
+use sa430::sink::{Sink, SweepRecord};
+use sa430::timestamp::TimeZone;
+
+/// Compares `actual` with the golden file `name`, or writes it with `UPDATE_GOLDEN` set.
+fn assert_golden(name: &str, actual: &[u8]) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(name);
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read(&path).unwrap_or_else(|_| panic!("Missing {}, run with UPDATE_GOLDEN=1", path.display()));
+    match (String::from_utf8(expected.clone()), String::from_utf8(actual.to_vec())) {
+        (Ok(expected), Ok(actual)) => assert_eq!(actual, expected, "{} changed", name),
+        _ => assert!(actual == expected, "{} changed", name),
+    }
+}
+
+/// Three sweeps of a simulated carrier at 868.1 MHz, one second apart.
+fn some_records() -> Vec<SweepRecord> {
+    let mut scenario = Scenario::new();
+    scenario.add(Carrier {
+        frequency: Frequency::from_mhz(868.1),
+        power: -60.0,
+        bandwidth: Frequency::from_khz(125.0),
+    });
+    let mut simulator = Simulator::new().with_seed(7);
+    simulator.set_scenario(scenario);
+
+    let (fstart, fstop) = (Frequency::from_mhz(868.0), Frequency::from_mhz(868.3));
+    (0..3)
+        .map(|sequence| {
+            let spectrum = simulator
+                .measure(fstart, fstop, Frequency::from_khz(50.0), -35, Frequency::from_khz(58.0))
+                .unwrap();
+            let mut record = SweepRecord::new(sequence, spectrum);
+            record.timestamp = UNIX_EPOCH + Duration::from_millis(1_709_209_800_250 + sequence * 1000);
+            record
+        })
+        .collect()
+}
+
+fn export(mut sink: impl Sink) {
+    for record in some_records() {
+        sink.write(&record).unwrap();
+    }
+    sink.close().unwrap();
+}
+
+#[test]
+fn given_simulated_sweeps_when_exported_as_csv_then_match_the_golden_file() {
+    let mut output = Vec::new();
+
+    export(CsvSink::new(&mut output, ',').unwrap().with_timestamps(TimeZone::Utc));
+
+    assert_golden("sweeps.csv", &output);
+}
+
+#[test]
+fn given_simulated_sweeps_when_exported_as_ndjson_then_match_the_golden_file() {
+    let mut output = Vec::new();
+
+    export(NdjsonSink::new(&mut output).with_timestamps(TimeZone::Utc));
+
+    assert_golden("sweeps.ndjson", &output);
+}
+
+#[test]
+fn given_simulated_sweeps_when_exported_as_binary_then_match_the_golden_file() {
+    let mut output = Vec::new();
+
+    export(BinarySink::new(&mut output));
+
+    assert_golden("sweeps.bin", &output);
+}
+
+#[test]
+fn given_simulated_sweeps_when_plotted_then_match_the_golden_file() {
+    let mut output = Vec::new();
+
+    export(HtmlSink::new(&mut output, "Golden capture"));
+
+    assert_golden("sweeps.html", &output);
+}
+
+#[test]
+fn given_the_simulator_calibration_when_exporting_gains_then_match_the_golden_files() {
+    let calibration = Simulator::new().calibration().unwrap();
+    let mut csv = Vec::new();
+    let mut json = Vec::new();
+
+    write_gains_csv(&calibration, ',', &mut csv).unwrap();
+    write_gains_json(&calibration, &mut json).unwrap();
+
+    assert_golden("gains.csv", &csv);
+    assert_golden("gains.json", &json);
+}
@@ -34,3 +34,68 @@ fn when_scan_then_return_error() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+#[test]
+fn when_bands_list_then_print_builtin_bands() -> Result<(), Box<dyn std::error::Error>> {
+    Command::cargo_bin(BIN_NAME)?
+        .args(["bands", "list"])
+        .assert()
+        .success()
+        .stdout(contains("eu868"))
+        .stdout(contains("us915"));
+
+    Ok(())
+}
+
+#[test]
+fn given_narrow_layout_when_bands_list_then_print_a_value_per_line() -> Result<(), Box<dyn std::error::Error>> {
+    Command::cargo_bin(BIN_NAME)?
+        .args(["bands", "list", "--layout", "narrow"])
+        .assert()
+        .success()
+        .stdout(contains("name: eu868\nfstart (MHz): 863.000\n"));
+
+    Ok(())
+}
+
+#[test]
+fn given_a_config_dir_with_bands_when_bands_list_then_print_its_bands() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join(format!("sa430-config-dir-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join("bands.csv"), "site-a,868.0,868.6,Gateway uplink at site A\n")?;
+
+    let assert = Command::cargo_bin(BIN_NAME)?
+        .args(["bands", "list"])
+        .env("SA430_CONFIG_DIR", &dir)
+        .assert();
+    std::fs::remove_dir_all(&dir)?;
+
+    assert.success().stdout(contains("Gateway uplink at site A"));
+    Ok(())
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn given_an_invalid_usb_file_descriptor_when_info_then_return_an_error() -> Result<(), Box<dyn std::error::Error>> {
+    Command::cargo_bin(BIN_NAME)?
+        .args(["info", "usbfd:-1"])
+        .assert()
+        .failure()
+        .stderr(contains("Invalid file descriptor: -1"));
+
+    Ok(())
+}
+
+#[test]
+#[ignore = "long running, run with --ignored"]
+#[cfg(feature = "simulator")]
+fn given_the_simulator_when_soak_then_complete_all_cycles() -> Result<(), Box<dyn std::error::Error>> {
+    Command::cargo_bin(BIN_NAME)?
+        .args(["soak", "--cycles", "20000", "--fault-rate", "0.02"])
+        .assert()
+        .success()
+        .stdout(contains("20000 cycles"))
+        .stdout(contains("2857 sweeps"));
+
+    Ok(())
+}
@@ -0,0 +1,233 @@
+//! Lists the public items of the library by path, one module per block, under the same features as in the library,
+//! so removing, renaming or moving an item, or gating it behind another feature, fails to build this test. Additions
+//! and removals show up in the diff of this file, which is reviewed for semver.
+//!
+//! Run with `--all-features` to check the items of every feature. Signatures are checked by the code that uses them,
+//! like the tests, examples and the CLI.
+#![allow(unused_imports)]
+
+#[cfg(feature = "net")]
+mod auth {
+    pub use sa430::auth::{accept_token, send_token, AuthError, Token, MAX_LINE};
+}
+
+mod bands {
+    pub use sa430::bands::{Band, BandError, Bands};
+}
+
+mod cancel {
+    pub use sa430::cancel::CancellationToken;
+}
+
+mod capabilities {
+    pub use sa430::capabilities::{Capabilities, RBWS_KHZ};
+}
+
+mod channel {
+    pub use sa430::channel::{is_reopened, Channel, ChannelError, ChannelReader, ChannelWriter};
+    #[cfg(feature = "serial")]
+    pub use sa430::channel::{Opener, RecoveryPolicy, SerialHandle, SerialPortChannel, WarningHandler};
+}
+
+#[cfg(feature = "net")]
+mod client {
+    pub use sa430::client::{Client, DEFAULT_PORT};
+}
+
+mod compat {
+    pub use sa430::compat::{
+        check, format_version, CompatError, Feature, Firmware, MIN_CORE_VERSION, MIN_SPECTRUM_VERSION,
+    };
+}
+
+mod correction {
+    pub use sa430::correction::{CorrectionError, SweepCorrection};
+}
+
+mod device {
+    pub use sa430::device::{Calibration, FrequencyGain, FrequencyRange, Health, RefLevel, Sa430, SweepSegment};
+    pub use sa430::device::{
+        CommandClass, CommandTiming, FlashReadPolicy, FlushPolicy, FrameDirection, FrameHandler, FrameRecord,
+        LinkStats, ProtocolError, Timeouts, WritePolicy, DEFAULT_TRANSCRIPT_SIZE,
+    };
+}
+
+#[cfg(feature = "dsp")]
+mod dsp {
+    pub use sa430::dsp::{
+        active_regions, channelize, classify, detect_bursts, merge_sweeps, Burst, BurstTiming, ChannelPlan,
+        ChannelPower, ChannelSpec, ClassifierConfig, Comparison, Detection, DwellSample, DwellStats, Region,
+        SignalClass, SurveyConfig, TimeStats, Trigger, TriggerCondition, TriggerError,
+    };
+}
+
+mod engine {
+    pub use sa430::engine::{builtin_engines, ParameterCommandsEngine, SpectrumEngine};
+}
+
+mod formats {
+    pub use sa430::formats::repair;
+}
+
+mod formats_binary {
+    pub use sa430::formats::binary::{complete_length, read_record, BinaryError, BinarySink, RECORD_MAGIC};
+}
+
+mod formats_csv {
+    pub use sa430::formats::csv::{
+        parse_delimiter, parse_spectrum, read_spectrum, CsvError, CsvSink, CsvWriter, DEFAULT_DELIMITER, DELIMITERS,
+    };
+}
+
+mod formats_gains {
+    pub use sa430::formats::gains::{check_gains_csv, read_gains_csv, write_gains_csv, write_gains_json, GainsError};
+}
+
+mod formats_html {
+    pub use sa430::formats::html::{write_html_report, HtmlSink, Trace};
+}
+
+mod formats_ndjson {
+    pub use sa430::formats::ndjson::{escape, NdjsonSink};
+}
+
+mod formats_zip {
+    pub use sa430::formats::zip::ZipWriter;
+}
+
+mod frame {
+    pub use sa430::frame::{
+        hexdump, split_stream, Command, ErrorCode, Frame, FrameError, FrameStream, ParseMode, StreamItem,
+        FRAME_COMMAND_INDEX, FRAME_CRC_SIZE, FRAME_DATA_INDEX, FRAME_DATA_LENGTH_INDEX, FRAME_HEADER_SIZE,
+        FRAME_MAGIC_INDEX, FRAME_MAGIC_VALUE,
+    };
+}
+
+mod frequency {
+    pub use sa430::frequency::{Frequency, InvalidFrequency};
+}
+
+#[cfg(feature = "http")]
+mod http {
+    #[cfg(feature = "tls")]
+    pub use sa430::http::serve_tls;
+    pub use sa430::http::{
+        read_request, serve, write_response, Handler, HttpError, Request, Response, WarningHandler, MAX_BODY_SIZE,
+        MAX_CONNECTIONS, MAX_LINE,
+    };
+}
+
+mod location {
+    pub use sa430::location::{Location, LocationError};
+}
+
+#[cfg(feature = "gpsd")]
+mod location_gpsd {
+    pub use sa430::location::gpsd::{parse_tpv, read_location, DEFAULT_ADDRESS};
+}
+
+mod log {
+    pub use sa430::log::{rotated_path, Level, Logger, Rotation};
+}
+
+mod metrics {
+    pub use sa430::metrics::{write_metrics, Metric, MetricKind, CONTENT_TYPE};
+}
+
+#[cfg(feature = "monitor")]
+mod monitor {
+    pub use sa430::monitor::{Debouncer, Event, EventHandler, Metadata, Monitor, DEFAULT_DEBOUNCE};
+}
+
+mod noise {
+    pub use sa430::noise::{estimate_noise_floor, estimate_noise_floors, noise_floor_at, NoiseFloor};
+}
+
+mod port {
+    pub use sa430::port::{
+        Port, BY_ID_DIR, DEVLINKS_PROPERTY_KEY, FIRMWARE_VERSION_PROPERTY_KEY, PRODUCT_ID_PROPERTY_KEY,
+        SERIAL_NUMBER_PROPERTY_KEY, USB_PRODUCT_ID, USB_VENDOR_ID, VENDOR_ID_PROPERTY_KEY,
+    };
+}
+
+mod power {
+    pub use sa430::power::Dbm;
+}
+
+mod profile {
+    pub use sa430::profile::{LatencyHistogram, ProfileReport};
+}
+
+mod reference {
+    pub use sa430::reference::{Correction, CorrectionProfile, InvalidReferencePoint, ReferencePoint};
+}
+
+#[cfg(feature = "scanner")]
+mod scanner {
+    pub use sa430::scanner::Scanner;
+}
+
+#[cfg(feature = "net")]
+mod server {
+    #[cfg(feature = "tls")]
+    pub use sa430::server::listen_tls;
+    pub use sa430::server::{listen, Arbitration, CLIENT_QUEUE};
+}
+
+mod session {
+    pub use sa430::session::{seal, verify, Check, SessionError, SessionInfo, Status, MANIFEST_NAME, METADATA_NAME};
+}
+
+#[cfg(feature = "simulator")]
+mod simulator {
+    pub use sa430::simulator::{
+        Carrier, Fault, Scenario, ScenarioError, ScenarioFault, Schedule, Simulator, DEFAULT_SEED,
+    };
+}
+
+mod sink {
+    pub use sa430::sink::{
+        parse_period, Aggregated, Aggregation, Queued, Sink, SinkError, SinkId, SinkRegistry, SweepRecord, SweepSource,
+        Throttled,
+    };
+}
+
+mod spectrum {
+    pub use sa430::spectrum::{BinAlignment, BinWindow, Interpolation, Spectrum, SpectrumError, SpectrumSet};
+}
+
+mod storage {
+    pub use sa430::storage::{is_storage_full, LocalStorage, Storage, StorageError, SyncedFile, DEFAULT_SYNC_INTERVAL};
+}
+
+mod sweep {
+    pub use sa430::sweep::{
+        compensate, SweepConfig, SweepConfigError, SweepRegisters, NOMINAL_XTAL_HZ, RBW_TABLE, REF_LEVEL_TABLE,
+    };
+}
+
+mod timestamp {
+    pub use sa430::timestamp::{
+        civil_from_days, days_from_civil, format_iso8601, InvalidTimeZone, TimeZone, SECONDS_PER_DAY,
+    };
+}
+
+#[cfg(feature = "tls")]
+mod tls {
+    pub use sa430::tls::{ClientStream, ServerStream, TlsAcceptor, TlsConnector, TlsError};
+}
+
+#[cfg(all(feature = "usbfs", any(target_os = "linux", target_os = "android")))]
+mod usbfs {
+    pub use sa430::usbfs::UsbfsChannel;
+}
+
+/// Functions of the crate root.
+mod root {
+    #[cfg(all(target_os = "linux", feature = "monitor"))]
+    pub use sa430::create_inotify_monitor;
+    #[cfg(feature = "monitor")]
+    pub use sa430::create_monitor;
+    #[cfg(feature = "scanner")]
+    pub use sa430::create_scanner;
+}